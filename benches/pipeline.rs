@@ -0,0 +1,107 @@
+// Benchmarks of the work done for every message
+// Everything here runs on the gateway's event loop for each message the bot sees, so it
+// getting slower costs throughput on big servers. Compare against a saved baseline with
+// `cargo bench -- --save-baseline main` and `cargo bench -- --baseline main`.
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use discordbot_but_rust::bench::{
+    caps_percent, match_triggers, CompiledTrigger, Level, RateLimiter, TriggerKind,
+};
+use poise::serenity_prelude as serenity;
+
+// As many triggers as a guild can have, the worst case every message is checked against
+const TRIGGERS: usize = 50;
+
+const SHORT_MESSAGE: &str = "anyone up for a game tonight?";
+const LONG_MESSAGE: &str = "So I finally got around to setting up the server again after the \
+    update broke everything, and it turns out the config moved to a different folder. If \
+    anyone else runs into it, check the release notes, there's a migration script in there \
+    that does most of the work for you. Still can't get the mods to load though, ANY IDEAS?";
+
+fn triggers(kind: TriggerKind) -> Vec<CompiledTrigger> {
+    (0..TRIGGERS)
+        .map(|i| {
+            let pattern = match kind {
+                TriggerKind::Regex => format!(r"\b(server|srv)\s*{}\b", i),
+                _ => format!("keyword number {}", i),
+            };
+            CompiledTrigger::new(kind, &pattern, Some("reply".to_string()), None, None).unwrap()
+        })
+        .collect()
+}
+
+fn bench_triggers(c: &mut Criterion) {
+    let channel = serenity::ChannelId(1);
+    let mut group = c.benchmark_group("triggers");
+
+    for kind in [
+        TriggerKind::Exact,
+        TriggerKind::Contains,
+        TriggerKind::Regex,
+    ] {
+        let triggers = triggers(kind);
+        for (length, message) in [("short", SHORT_MESSAGE), ("long", LONG_MESSAGE)] {
+            group.bench_function(format!("{}/{}", kind.name(), length), |b| {
+                b.iter(|| match_triggers(&triggers, channel, black_box(message)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_automod(c: &mut Criterion) {
+    let mut group = c.benchmark_group("automod");
+    group.bench_function("caps/short", |b| {
+        b.iter(|| caps_percent(black_box(SHORT_MESSAGE)))
+    });
+    group.bench_function("caps/long", |b| {
+        b.iter(|| caps_percent(black_box(LONG_MESSAGE)))
+    });
+    group.finish();
+}
+
+fn bench_ratelimit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ratelimit");
+
+    group.bench_function("same_user", |b| {
+        let limiter = RateLimiter::new(3, Duration::from_secs(2));
+        b.iter(|| limiter.try_acquire(black_box(serenity::UserId(1))))
+    });
+
+    // Every message from someone new adds a bucket, like a busy server after a restart
+    group.bench_function("new_users", |b| {
+        b.iter_batched(
+            || RateLimiter::new(3, Duration::from_secs(2)),
+            |limiter| {
+                for user in 1..=1000 {
+                    limiter.try_acquire(serenity::UserId(user));
+                }
+                limiter
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn bench_levels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("levels");
+    for xp in [40, 100_000, 2_500_000] {
+        group.bench_function(format!("from_xp/{}", xp), |b| {
+            b.iter(|| Level::from_xp(black_box(xp)).level)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_triggers,
+    bench_automod,
+    bench_ratelimit,
+    bench_levels
+);
+criterion_main!(benches);
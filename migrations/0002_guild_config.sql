@@ -0,0 +1,14 @@
+-- Per-guild settings, a missing row means every setting is at its default
+CREATE TABLE IF NOT EXISTS guild_config (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    prefix TEXT,
+    log_channel_id INTEGER
+);
+
+-- Features a guild has explicitly turned on or off
+CREATE TABLE IF NOT EXISTS guild_features (
+    guild_id INTEGER NOT NULL,
+    feature TEXT NOT NULL,
+    enabled INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, feature)
+);
@@ -0,0 +1,16 @@
+-- Warnings handed out by moderators, case IDs count up per guild
+CREATE TABLE IF NOT EXISTS warnings (
+    guild_id INTEGER NOT NULL,
+    case_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    moderator_id INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, case_id)
+);
+
+CREATE INDEX IF NOT EXISTS warnings_by_user ON warnings (guild_id, user_id);
+
+-- Automatic timeout after every N warnings, NULL threshold disables it
+ALTER TABLE guild_config ADD COLUMN warn_threshold INTEGER;
+ALTER TABLE guild_config ADD COLUMN warn_timeout_secs INTEGER;
@@ -0,0 +1,16 @@
+-- Channels with special rules, like emoji-only channels
+CREATE TABLE IF NOT EXISTS guild_channel_modes (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER PRIMARY KEY NOT NULL,
+    mode TEXT NOT NULL
+);
+
+-- Reactions in reaction wall channels, counted per week until they are reported
+CREATE TABLE IF NOT EXISTS reaction_wall_stats (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    week INTEGER NOT NULL,
+    emoji TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (channel_id, week, emoji)
+);
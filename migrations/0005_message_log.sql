@@ -0,0 +1,2 @@
+-- Channel where edited and deleted messages are logged
+ALTER TABLE guild_config ADD COLUMN message_log_channel_id INTEGER;
@@ -0,0 +1,21 @@
+-- Invite each member joined through, so bans can be linked to an invite
+CREATE TABLE IF NOT EXISTS member_invites (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    invite_code TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- Bans used to recognise the same person joining again on another account
+CREATE TABLE IF NOT EXISTS recent_bans (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    invite_code TEXT,
+    banned_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- What happens to members that look like an alt, NULL threshold only alerts
+ALTER TABLE guild_config ADD COLUMN alt_threshold INTEGER;
+ALTER TABLE guild_config ADD COLUMN alt_action TEXT;
@@ -0,0 +1,11 @@
+-- Perceptual hashes of images moderators have blocked
+CREATE TABLE IF NOT EXISTS blocked_images (
+    guild_id INTEGER NOT NULL,
+    hash INTEGER NOT NULL,
+    added_by INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, hash)
+);
+
+-- How many bits an image hash may differ from a blocked one and still match
+ALTER TABLE guild_config ADD COLUMN image_hash_tolerance INTEGER;
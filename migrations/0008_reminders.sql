@@ -0,0 +1,10 @@
+-- Reminders set with /remind, due_at is a unix timestamp
+CREATE TABLE IF NOT EXISTS reminders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    due_at INTEGER NOT NULL,
+    text TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS reminders_by_due_at ON reminders (due_at);
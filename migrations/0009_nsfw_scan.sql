@@ -0,0 +1,12 @@
+-- Roles grouped into lists with a special meaning, e.g. roles exempt from the NSFW scan
+CREATE TABLE IF NOT EXISTS guild_role_lists (
+    guild_id INTEGER NOT NULL,
+    list TEXT NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, list, role_id)
+);
+
+-- Channel where flagged content waits for a moderator
+ALTER TABLE guild_config ADD COLUMN review_channel_id INTEGER;
+-- NSFW score in percent from which images are flagged
+ALTER TABLE guild_config ADD COLUMN nsfw_threshold INTEGER;
@@ -0,0 +1,12 @@
+-- Roles handed out for reacting to a message with an emoji
+-- `emoji` is the ID of custom emoji and the emoji itself for unicode ones
+CREATE TABLE IF NOT EXISTS reaction_roles (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    emoji TEXT NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (message_id, emoji)
+);
+
+CREATE INDEX IF NOT EXISTS reaction_roles_by_guild ON reaction_roles (guild_id);
@@ -0,0 +1,12 @@
+-- XP members earned by chatting, the level is derived from it
+CREATE TABLE IF NOT EXISTS member_xp (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    xp INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS member_xp_by_xp ON member_xp (guild_id, xp DESC);
+
+-- Where level ups are announced, the channel of the message if unset
+ALTER TABLE guild_config ADD COLUMN level_channel_id INTEGER;
@@ -0,0 +1,17 @@
+-- Topics that have to be spoilered until `expires_at`, keywords are separated by newlines
+CREATE TABLE IF NOT EXISTS spoiler_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    keywords TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS spoiler_rules_by_guild ON spoiler_rules (guild_id);
+
+-- Channels a spoiler rule is limited to, rules without any apply everywhere
+CREATE TABLE IF NOT EXISTS spoiler_rule_channels (
+    rule_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (rule_id, channel_id)
+);
@@ -0,0 +1,6 @@
+-- Domains whose links are never expanded or cleaned
+CREATE TABLE IF NOT EXISTS guild_link_allowlist (
+    guild_id INTEGER NOT NULL,
+    domain TEXT NOT NULL,
+    PRIMARY KEY (guild_id, domain)
+);
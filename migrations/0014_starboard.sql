@@ -0,0 +1,10 @@
+-- Messages that were posted to the starboard, so later stars edit the post
+CREATE TABLE IF NOT EXISTS starboard_posts (
+    message_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    post_id INTEGER NOT NULL
+);
+
+ALTER TABLE guild_config ADD COLUMN starboard_channel_id INTEGER;
+-- Stars a message needs to be posted
+ALTER TABLE guild_config ADD COLUMN starboard_threshold INTEGER;
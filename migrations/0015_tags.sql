@@ -0,0 +1,10 @@
+-- Canned responses created by staff, shown with /tag show or the prefix and the name
+CREATE TABLE IF NOT EXISTS tags (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    author_id INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
@@ -0,0 +1,26 @@
+-- Work the bot has to do at a later time, like removing a temporary role
+-- `target_id` depends on the kind, e.g. the role to remove
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    run_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS jobs_by_run_at ON jobs (run_at);
+
+-- Roles given to every member with `members_role` during a weekly window, times are minutes
+-- since Monday 00:00 UTC
+CREATE TABLE IF NOT EXISTS role_schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    members_role_id INTEGER NOT NULL,
+    grant_at INTEGER NOT NULL,
+    remove_at INTEGER NOT NULL,
+    -- Whether the role was last granted or removed, compared on every check so windows that
+    -- started or ended while the bot was offline are caught up on
+    active BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,9 @@
+-- Roles members get automatically once they reach a level and have been in the server long
+-- enough, one rule per role
+CREATE TABLE IF NOT EXISTS promotion_rules (
+    guild_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    min_level INTEGER NOT NULL DEFAULT 0,
+    min_days INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, role_id)
+);
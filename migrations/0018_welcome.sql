@@ -0,0 +1,5 @@
+-- Where members are welcomed and said goodbye to, off if unset
+ALTER TABLE guild_config ADD COLUMN welcome_channel_id INTEGER;
+-- Templates with placeholders like {mention}, the built-in ones are used if unset
+ALTER TABLE guild_config ADD COLUMN welcome_message TEXT;
+ALTER TABLE guild_config ADD COLUMN goodbye_message TEXT;
@@ -0,0 +1,11 @@
+-- Permission overwrites saved from a channel with /permtemplate save, a template is all rows
+-- with the same name. `kind` is `role` or `member`, allow and deny are permission bits
+CREATE TABLE IF NOT EXISTS perm_template_overwrites (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    allow INTEGER NOT NULL,
+    deny INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, name, target_id)
+);
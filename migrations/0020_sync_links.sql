@@ -0,0 +1,8 @@
+-- Which object in a synced guild is the copy of which object in the source guild, so renamed
+-- roles and channels are still recognized. Snowflakes are unique across guilds
+CREATE TABLE IF NOT EXISTS sync_links (
+    source_id INTEGER NOT NULL,
+    target_guild_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    PRIMARY KEY (source_id, target_guild_id)
+);
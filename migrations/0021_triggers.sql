@@ -0,0 +1,12 @@
+-- Auto-responses, `kind` is how `pattern` is matched: exact, contains or regex
+-- At least one of `response` and `reaction` is set
+CREATE TABLE IF NOT EXISTS triggers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    response TEXT,
+    reaction TEXT
+);
+
+CREATE INDEX IF NOT EXISTS triggers_by_guild ON triggers (guild_id);
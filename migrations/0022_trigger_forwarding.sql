@@ -0,0 +1,3 @@
+-- Triggers can also copy matching messages to a channel, e.g. everything about refunds to a
+-- support triage channel
+ALTER TABLE triggers ADD COLUMN forward_channel_id INTEGER;
@@ -0,0 +1,15 @@
+-- Keywords users get DMed about with /notify, stored lowercase
+CREATE TABLE IF NOT EXISTS notify_keywords (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    keyword TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, keyword)
+);
+
+-- Channels a user doesn't want keyword notifications from
+CREATE TABLE IF NOT EXISTS notify_mutes (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id, channel_id)
+);
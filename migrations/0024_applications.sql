@@ -0,0 +1,25 @@
+-- Forms members fill in with /apply, accepting an application gives `role_id`
+-- `questions` has one question per line
+CREATE TABLE IF NOT EXISTS application_forms (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    role_id INTEGER NOT NULL,
+    review_channel_id INTEGER NOT NULL,
+    questions TEXT NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
+
+-- `answers` is a JSON array in the order of the questions, `status` is pending, accepted or
+-- rejected
+CREATE TABLE IF NOT EXISTS applications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    form_name TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    answers TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewer_id INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS applications_by_user ON applications (guild_id, user_id);
@@ -0,0 +1,25 @@
+-- Where onboarding steps go for members with closed DMs, and where introductions are posted
+ALTER TABLE guild_config ADD COLUMN onboarding_channel_id INTEGER;
+
+-- Messages new members get one after another, `delay_secs` is the wait since the previous
+-- step, or since joining for the first one. `kind` is message, roles or intro
+CREATE TABLE IF NOT EXISTS onboarding_steps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    content TEXT NOT NULL,
+    delay_secs INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS onboarding_steps_by_guild ON onboarding_steps (guild_id);
+
+-- How far a member got, `via_channel` is set once a step had to go to the onboarding channel
+CREATE TABLE IF NOT EXISTS onboarding_progress (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    steps_sent INTEGER NOT NULL DEFAULT 0,
+    via_channel BOOLEAN NOT NULL DEFAULT FALSE,
+    started_at INTEGER NOT NULL,
+    completed_at INTEGER,
+    PRIMARY KEY (guild_id, user_id)
+);
@@ -0,0 +1,25 @@
+-- Channels without messages for `inactive_days` get `action` taken: warn, move or lock.
+-- `move` puts them in `category_id`, `report_channel_id` gets a weekly list of candidates
+CREATE TABLE IF NOT EXISTS archive_policies (
+    guild_id INTEGER PRIMARY KEY,
+    inactive_days INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    category_id INTEGER,
+    report_channel_id INTEGER,
+    last_report_at INTEGER
+);
+
+-- Channels, or whole categories, the policy leaves alone
+CREATE TABLE IF NOT EXISTS archive_exemptions (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+-- Channels the action was taken on, until they see a message again
+CREATE TABLE IF NOT EXISTS archived_channels (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    archived_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
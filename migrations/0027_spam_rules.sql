@@ -0,0 +1,12 @@
+-- Anti-spam rules, one per kind and guild. What `threshold` counts depends on `rule`: messages
+-- within a few seconds for flood, identical messages within a minute for repeat, mentions in a
+-- message for mentions and the percentage of capital letters for caps. `action` is delete,
+-- warn or timeout, `timeout_secs` is only used by timeout
+CREATE TABLE IF NOT EXISTS spam_rules (
+    guild_id INTEGER NOT NULL,
+    rule TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    timeout_secs INTEGER,
+    PRIMARY KEY (guild_id, rule)
+);
@@ -0,0 +1,21 @@
+-- Polls posted with /poll, `options` has one option per line. `closes_at` is unset for polls
+-- that stay open until they're closed by hand
+CREATE TABLE IF NOT EXISTS polls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER,
+    author_id INTEGER NOT NULL,
+    question TEXT NOT NULL,
+    options TEXT NOT NULL,
+    closes_at INTEGER,
+    closed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- One vote per member, `option` is the index into the poll's options
+CREATE TABLE IF NOT EXISTS poll_votes (
+    poll_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    option INTEGER NOT NULL,
+    PRIMARY KEY (poll_id, user_id)
+);
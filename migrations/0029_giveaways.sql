@@ -0,0 +1,19 @@
+-- Giveaways started with /giveaway, `winner_id` is unset until one is drawn and stays unset
+-- if nobody entered
+CREATE TABLE IF NOT EXISTS giveaways (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER,
+    host_id INTEGER NOT NULL,
+    prize TEXT NOT NULL,
+    ends_at INTEGER NOT NULL,
+    ended BOOLEAN NOT NULL DEFAULT FALSE,
+    winner_id INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS giveaway_entries (
+    giveaway_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (giveaway_id, user_id)
+);
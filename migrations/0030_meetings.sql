@@ -0,0 +1,25 @@
+-- Meetings started with /meeting in a voice channel, `ended_at` is unset while it's running
+CREATE TABLE IF NOT EXISTS meetings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    voice_channel_id INTEGER NOT NULL,
+    text_channel_id INTEGER NOT NULL,
+    host_id INTEGER NOT NULL,
+    title TEXT,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER
+);
+
+-- A voice channel can only have one meeting at a time
+CREATE UNIQUE INDEX IF NOT EXISTS meetings_by_running_channel
+    ON meetings (voice_channel_id) WHERE ended_at IS NULL;
+
+-- Every stretch a member spent in the meeting, `left_at` is unset while they're still there
+CREATE TABLE IF NOT EXISTS meeting_attendance (
+    meeting_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    joined_at INTEGER NOT NULL,
+    left_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS meeting_attendance_by_meeting ON meeting_attendance (meeting_id);
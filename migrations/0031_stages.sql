@@ -0,0 +1,26 @@
+-- Stages started with /stage, `message_id` is the control message with the raise hand buttons
+CREATE TABLE IF NOT EXISTS stages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    text_channel_id INTEGER NOT NULL,
+    message_id INTEGER,
+    host_id INTEGER NOT NULL,
+    topic TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER
+);
+
+-- A stage channel can only have one stage at a time
+CREATE UNIQUE INDEX IF NOT EXISTS stages_by_running_channel
+    ON stages (channel_id) WHERE ended_at IS NULL;
+
+-- Raised hands, `status` is waiting, approved or denied
+CREATE TABLE IF NOT EXISTS stage_requests (
+    stage_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    requested_at INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'waiting',
+    decided_at INTEGER,
+    PRIMARY KEY (stage_id, user_id)
+);
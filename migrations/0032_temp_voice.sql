@@ -0,0 +1,10 @@
+-- Joining this voice channel creates a temporary one for the member
+ALTER TABLE guild_config ADD COLUMN voice_hub_channel_id INTEGER;
+
+-- Voice channels created by joining the hub, deleted once they're empty
+CREATE TABLE IF NOT EXISTS temp_voice_channels (
+    channel_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    owner_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Last case ID handed out per guild, so IDs are never reused after a warning is cleared
+CREATE TABLE IF NOT EXISTS case_counters (
+    guild_id INTEGER PRIMARY KEY,
+    last_case_id INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO case_counters (guild_id, last_case_id)
+SELECT guild_id, MAX(case_id) FROM warnings GROUP BY guild_id;
@@ -0,0 +1,2 @@
+-- Times a job was tried, jobs failing with transient errors are pushed back further every try
+ALTER TABLE jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Times delivering a reminder failed, it's tried again later until this reaches a limit
+ALTER TABLE reminders ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
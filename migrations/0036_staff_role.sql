@@ -0,0 +1,2 @@
+-- Role pinged by /staff
+ALTER TABLE guild_config ADD COLUMN staff_role_id INTEGER;
@@ -0,0 +1,9 @@
+-- Messages marked as the answer of a forum or help thread, one per thread
+CREATE TABLE IF NOT EXISTS forum_answers (
+    thread_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    answerer_id INTEGER NOT NULL,
+    marked_by INTEGER NOT NULL,
+    marked_at INTEGER NOT NULL
+);
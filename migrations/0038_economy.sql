@@ -0,0 +1,22 @@
+-- Coins of each member, a balance can never go below zero
+CREATE TABLE IF NOT EXISTS balances (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    balance INTEGER NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- Every change to a balance. A NULL sender means the coins were created (e.g. by /daily), a
+-- NULL receiver that they were spent. The key makes a retried command apply only once
+CREATE TABLE IF NOT EXISTS economy_ledger (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    from_id INTEGER,
+    to_id INTEGER,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    reason TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS economy_ledger_members ON economy_ledger (guild_id, from_id, to_id);
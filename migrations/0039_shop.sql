@@ -0,0 +1,22 @@
+-- Items of each guild's shop. Role items give `role_id`, for `rental_secs` if it's set
+CREATE TABLE IF NOT EXISTS shop_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,
+    kind TEXT NOT NULL,
+    price INTEGER NOT NULL CHECK (price > 0),
+    description TEXT,
+    role_id INTEGER,
+    rental_secs INTEGER,
+    UNIQUE (guild_id, name)
+);
+
+-- Items members bought, rentals are kept after they expire until they're bought again
+CREATE TABLE IF NOT EXISTS inventory (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    item_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    expires_at INTEGER,
+    PRIMARY KEY (guild_id, user_id, item_id)
+);
@@ -0,0 +1,7 @@
+-- Share of every bet the house keeps on average, in percent
+ALTER TABLE guild_config ADD COLUMN house_edge INTEGER;
+-- Smallest and biggest bets in coins
+ALTER TABLE guild_config ADD COLUMN min_bet INTEGER;
+ALTER TABLE guild_config ADD COLUMN max_bet INTEGER;
+-- Coins a member may lose gambling in a day, no limit if NULL
+ALTER TABLE guild_config ADD COLUMN gambling_loss_limit INTEGER;
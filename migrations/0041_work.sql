@@ -0,0 +1,11 @@
+-- When members can next use an economy action like /work, kept across restarts
+CREATE TABLE IF NOT EXISTS economy_cooldowns (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    ready_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id, action)
+);
+
+-- Chance in percent that /rob succeeds, 0 turns it off
+ALTER TABLE guild_config ADD COLUMN rob_chance INTEGER;
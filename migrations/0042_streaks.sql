@@ -0,0 +1,19 @@
+-- UTC offset of each user in minutes, their days start at midnight of it
+CREATE TABLE IF NOT EXISTS user_timezones (
+    user_id INTEGER PRIMARY KEY,
+    offset_minutes INTEGER NOT NULL,
+    changed_at INTEGER NOT NULL
+);
+
+-- Days in a row members did an activity like /daily, counted in their own timezone
+CREATE TABLE IF NOT EXISTS streaks (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    activity TEXT NOT NULL,
+    current INTEGER NOT NULL,
+    best INTEGER NOT NULL,
+    last_day INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id, activity)
+);
+
+CREATE INDEX IF NOT EXISTS streaks_leaderboard ON streaks (guild_id, activity, current);
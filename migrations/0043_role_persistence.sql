@@ -0,0 +1,11 @@
+-- Roles members had when they left, to give back when they rejoin
+CREATE TABLE IF NOT EXISTS saved_roles (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    saved_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id, role_id)
+);
+
+-- How long saved roles are kept, in seconds
+ALTER TABLE guild_config ADD COLUMN role_retention_secs INTEGER;
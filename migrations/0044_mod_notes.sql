@@ -0,0 +1,22 @@
+-- Free-form notes moderators keep on users, separate from warnings
+CREATE TABLE IF NOT EXISTS mod_notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    author_id INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS mod_notes_by_user ON mod_notes (guild_id, user_id);
+
+-- What a note said before each edit, and who changed it
+CREATE TABLE IF NOT EXISTS mod_note_edits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id INTEGER NOT NULL,
+    editor_id INTEGER NOT NULL,
+    previous_content TEXT NOT NULL,
+    edited_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS mod_note_edits_by_note ON mod_note_edits (note_id);
@@ -0,0 +1,12 @@
+-- Members moderators keep an eye on, their activity is summarized to the watch channel
+CREATE TABLE IF NOT EXISTS watchlist (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    reason TEXT,
+    added_by INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+-- Where watchlist summaries go, the log channel if unset
+ALTER TABLE guild_config ADD COLUMN watch_channel_id INTEGER;
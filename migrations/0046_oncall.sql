@@ -0,0 +1,31 @@
+-- Weekly windows in which a moderator is on call, in minutes since Monday 00:00 UTC
+CREATE TABLE IF NOT EXISTS oncall_shifts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    starts_at INTEGER NOT NULL,
+    ends_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS oncall_shifts_by_guild ON oncall_shifts (guild_id);
+
+-- Moderators taking over the shifts of another one for a while
+CREATE TABLE IF NOT EXISTS oncall_covers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    covered_id INTEGER NOT NULL,
+    starts_at INTEGER NOT NULL,
+    ends_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS oncall_covers_by_guild ON oncall_covers (guild_id, ends_at);
+
+-- The last week the rotation was posted for, counted in weeks since the unix epoch
+CREATE TABLE IF NOT EXISTS oncall_posts (
+    guild_id INTEGER PRIMARY KEY,
+    week INTEGER NOT NULL
+);
+
+-- Where the weekly rotation is posted, the log channel if unset
+ALTER TABLE guild_config ADD COLUMN oncall_channel_id INTEGER;
@@ -0,0 +1,23 @@
+-- Incidents opened by staff, at most one open per guild
+CREATE TABLE IF NOT EXISTS incidents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    opened_by INTEGER NOT NULL,
+    opened_at INTEGER NOT NULL,
+    closed_by INTEGER,
+    closed_at INTEGER
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS incidents_open ON incidents (guild_id) WHERE closed_at IS NULL;
+
+-- What happened during an incident, in order
+CREATE TABLE IF NOT EXISTS incident_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    incident_id INTEGER NOT NULL,
+    at INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    summary TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS incident_events_by_incident ON incident_events (incident_id);
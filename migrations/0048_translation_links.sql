@@ -0,0 +1,10 @@
+-- Messages in channel_id are translated into language and mirrored into target_id
+CREATE TABLE IF NOT EXISTS guild_translation_links (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    target_id INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    PRIMARY KEY (channel_id, target_id)
+);
+
+CREATE INDEX IF NOT EXISTS guild_translation_links_by_guild ON guild_translation_links (guild_id);
@@ -0,0 +1,6 @@
+-- Channels where members are reminded to describe the images they post
+CREATE TABLE IF NOT EXISTS guild_alt_text_channels (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
@@ -0,0 +1,19 @@
+-- Weekly windows in which a channel is locked or slowed down, in minutes since Monday 00:00 UTC
+CREATE TABLE IF NOT EXISTS quiet_hours (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    starts_at INTEGER NOT NULL,
+    ends_at INTEGER NOT NULL,
+    -- Slowmode in seconds during the window, NULL locks the channel instead
+    slowmode INTEGER,
+    -- Like role schedules, compared on every check so missed windows are caught up on
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    -- What the window changed, restored when it ends. The @everyone overwrite is NULL if the
+    -- channel had none.
+    saved_allow INTEGER,
+    saved_deny INTEGER,
+    saved_slowmode INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS quiet_hours_by_guild ON quiet_hours (guild_id);
@@ -0,0 +1,20 @@
+-- Yearly themes like holidays, dates are month * 100 + day and both days are included
+CREATE TABLE IF NOT EXISTS themes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    starts_on INTEGER NOT NULL,
+    ends_on INTEGER NOT NULL,
+    colour INTEGER,
+    -- A data URI, attachment links expire
+    icon TEXT,
+    -- Unicode emojis separated by spaces
+    reactions TEXT,
+    -- Set by the bot owner only, it's shown on every server
+    status TEXT,
+    -- Like role schedules, compared on every check so missed starts and ends are caught up on
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Server icon from before the theme as a data URI, NULL if there was none
+    saved_icon TEXT,
+    UNIQUE (guild_id, name)
+);
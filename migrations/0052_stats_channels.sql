@@ -0,0 +1,11 @@
+-- Voice channels whose name shows a statistic of their guild
+CREATE TABLE IF NOT EXISTS stats_channels (
+    channel_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    -- members, online or boosts
+    stat TEXT NOT NULL,
+    -- Channel name with {count} where the number goes
+    template TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS stats_channels_by_guild ON stats_channels (guild_id);
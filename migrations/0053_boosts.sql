@@ -0,0 +1,10 @@
+-- Where boosters are thanked
+ALTER TABLE guild_config ADD COLUMN boost_channel_id INTEGER;
+
+-- Personal colour roles of boosters, deleted when they stop boosting
+CREATE TABLE IF NOT EXISTS booster_roles (
+    guild_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
@@ -0,0 +1,24 @@
+-- Where partner ads are posted, and how often
+ALTER TABLE guild_config ADD COLUMN partner_channel_id INTEGER;
+ALTER TABLE guild_config ADD COLUMN partner_interval_secs INTEGER;
+
+-- Partner servers whose ads are posted in turn
+CREATE TABLE IF NOT EXISTS partners (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    blurb TEXT NOT NULL,
+    invite TEXT NOT NULL,
+    added_by INTEGER NOT NULL,
+    -- Unix timestamp, NULL if the partnership doesn't end
+    expires_at INTEGER,
+    impressions INTEGER NOT NULL DEFAULT 0,
+    last_posted_at INTEGER,
+    UNIQUE (guild_id, name)
+);
+
+-- When an ad was last posted in each guild, so restarts don't post early
+CREATE TABLE IF NOT EXISTS partner_posts (
+    guild_id INTEGER PRIMARY KEY,
+    posted_at INTEGER NOT NULL
+);
@@ -0,0 +1,25 @@
+-- Community moderation, NULL threshold means it's off
+ALTER TABLE guild_config ADD COLUMN vote_delete_threshold INTEGER;
+ALTER TABLE guild_config ADD COLUMN vote_delete_action TEXT;
+ALTER TABLE guild_config ADD COLUMN vote_delete_emoji TEXT;
+
+-- Messages members voted to delete, deleted right away or waiting for staff
+CREATE TABLE IF NOT EXISTS vote_deletions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL UNIQUE,
+    author_id INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    -- pending, deleted or dismissed
+    status TEXT NOT NULL,
+    reviewed_by INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+-- Who voted, so members whose votes keep getting dismissed stand out
+CREATE TABLE IF NOT EXISTS vote_deletion_voters (
+    deletion_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (deletion_id, user_id)
+);
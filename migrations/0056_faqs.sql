@@ -0,0 +1,18 @@
+-- Answers to frequently asked questions in help channels, matched by keywords, a regex or both
+CREATE TABLE IF NOT EXISTS faqs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    question TEXT NOT NULL,
+    answer TEXT NOT NULL,
+    -- Separated by spaces
+    keywords TEXT,
+    regex TEXT,
+    -- Share of the keywords in percent a message needs to be answered
+    min_confidence INTEGER NOT NULL,
+    -- How often it was answered, and what askers said about it
+    matches INTEGER NOT NULL DEFAULT 0,
+    helpful INTEGER NOT NULL DEFAULT 0,
+    unhelpful INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS faqs_by_guild ON faqs (guild_id);
@@ -0,0 +1,18 @@
+-- Articles of a guild's knowledge base
+CREATE TABLE IF NOT EXISTS kb_articles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    title TEXT NOT NULL COLLATE NOCASE,
+    body TEXT NOT NULL,
+    author_id INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    UNIQUE (guild_id, title)
+);
+
+-- Full-text index of the articles, kept in sync by the queries that change them
+CREATE VIRTUAL TABLE IF NOT EXISTS kb_search USING fts5 (
+    title,
+    body,
+    content = 'kb_articles',
+    content_rowid = 'id'
+);
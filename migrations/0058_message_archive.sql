@@ -0,0 +1,11 @@
+-- Messages of guilds with the message_archive feature on, searched with /search. The rowid is
+-- the message ID, and permissions are checked against `parent_id`, the channel itself or the
+-- channel a thread is in
+CREATE VIRTUAL TABLE IF NOT EXISTS archived_messages USING fts5 (
+    content,
+    guild_id UNINDEXED,
+    channel_id UNINDEXED,
+    parent_id UNINDEXED,
+    author_id UNINDEXED,
+    created_at UNINDEXED
+);
@@ -0,0 +1,15 @@
+-- Daily digests, posted in `channel_id` at `hour` UTC about the channels in digest_channels.
+-- `summarize` adds an LLM summary, `last_posted_at` is the slot the last digest was for
+CREATE TABLE IF NOT EXISTS digests (
+    guild_id INTEGER PRIMARY KEY,
+    channel_id INTEGER NOT NULL,
+    hour INTEGER NOT NULL,
+    summarize INTEGER NOT NULL DEFAULT 0,
+    last_posted_at INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS digest_channels (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
@@ -0,0 +1,10 @@
+-- Thread summaries a guild can have a day, the default if not set
+ALTER TABLE guild_config ADD COLUMN summary_limit INTEGER;
+
+-- Thread summaries each guild had per day, `day` counts days since the unix epoch
+CREATE TABLE IF NOT EXISTS summary_usage (
+    guild_id INTEGER NOT NULL,
+    day INTEGER NOT NULL,
+    used INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, day)
+);
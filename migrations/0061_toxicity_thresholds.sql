@@ -0,0 +1,7 @@
+-- Toxicity categories a guild scans messages for, with the score in percent they're flagged at
+CREATE TABLE IF NOT EXISTS guild_toxicity_thresholds (
+    guild_id INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, category)
+);
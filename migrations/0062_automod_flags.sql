@@ -0,0 +1,21 @@
+-- Messages automod flagged for staff to review, kept to see which rules flag too much
+CREATE TABLE IF NOT EXISTS automod_flags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    author_id INTEGER NOT NULL,
+    -- What flagged it, like toxicity or spam, and the rule or category of it
+    source TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    -- pending, approved, deleted, warned or timed_out
+    status TEXT NOT NULL DEFAULT 'pending',
+    -- The message in the review channel
+    review_channel_id INTEGER,
+    review_message_id INTEGER,
+    reviewed_by INTEGER,
+    created_at INTEGER NOT NULL,
+    reviewed_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS automod_flags_guild ON automod_flags (guild_id, created_at);
@@ -0,0 +1,11 @@
+-- Bans and unbans, numbered together with warnings by case_counters
+CREATE TABLE IF NOT EXISTS mod_cases (
+    guild_id INTEGER NOT NULL,
+    case_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    moderator_id INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, case_id)
+);
@@ -0,0 +1,14 @@
+-- Tiers the bot owner gave guilds, guilds without one are on the default tier
+CREATE TABLE IF NOT EXISTS guild_tiers (
+    guild_id INTEGER PRIMARY KEY NOT NULL,
+    tier TEXT NOT NULL
+);
+
+-- Uses of each quota per guild and day, `day` counts days since the unix epoch
+CREATE TABLE IF NOT EXISTS quota_usage (
+    guild_id INTEGER NOT NULL,
+    quota TEXT NOT NULL,
+    day INTEGER NOT NULL,
+    used INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, quota, day)
+);
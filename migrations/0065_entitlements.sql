@@ -0,0 +1,13 @@
+-- Entitlements users and guilds have to the app's SKUs, mirrored from Discord
+-- A subscription has exactly one of `user_id` and `guild_id`, `ends_at` is NULL for test
+-- entitlements, which don't expire
+CREATE TABLE IF NOT EXISTS entitlements (
+    id INTEGER PRIMARY KEY NOT NULL,
+    sku_id INTEGER NOT NULL,
+    user_id INTEGER,
+    guild_id INTEGER,
+    ends_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS entitlements_guild ON entitlements (guild_id);
+CREATE INDEX IF NOT EXISTS entitlements_user ON entitlements (user_id);
@@ -0,0 +1,14 @@
+-- Votes for the bot on top.gg, one row per vote
+CREATE TABLE IF NOT EXISTS votes (
+    user_id INTEGER NOT NULL,
+    voted_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS votes_user ON votes (user_id, voted_at);
+CREATE INDEX IF NOT EXISTS votes_voted_at ON votes (voted_at);
+
+-- Months a vote leaderboard was posted for, by the unix timestamp the month starts at
+CREATE TABLE IF NOT EXISTS vote_leaderboards (
+    month INTEGER PRIMARY KEY NOT NULL,
+    posted_at INTEGER NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Whether the winner of a giveaway was announced. Ending a giveaway is retried until it was, with
+-- the winner drawn on the first try
+ALTER TABLE giveaways ADD COLUMN announced BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE giveaways SET announced = ended;
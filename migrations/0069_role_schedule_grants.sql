@@ -0,0 +1,7 @@
+-- Members a role schedule gave its role to. Only they lose it again when the window ends, not
+-- members who had it already
+CREATE TABLE IF NOT EXISTS role_schedule_grants (
+    schedule_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (schedule_id, user_id)
+);
@@ -0,0 +1,849 @@
+-- The schema of SQLite migrations 0001 to 0066 in one go, PostgreSQL support started there.
+-- Later migrations get the same number in both directories. Columns are BIGINT where SQLite has
+-- INTEGER, since Discord IDs and timestamps don't fit in 32 bits.
+
+-- Compares like SQLite's NOCASE, also for text the queries compare these columns with
+CREATE COLLATION nocase (provider = icu, locale = 'und-u-ks-level2', deterministic = false);
+
+-- Metadata about the database itself
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+
+INSERT INTO meta (key, value)
+VALUES ('created_at', CAST(CAST(EXTRACT(EPOCH FROM now()) AS BIGINT) AS TEXT));
+
+CREATE TABLE guild_config (
+    guild_id BIGINT PRIMARY KEY NOT NULL,
+    prefix TEXT,
+    log_channel_id BIGINT,
+    warn_threshold BIGINT,
+    warn_timeout_secs BIGINT,
+    message_log_channel_id BIGINT,
+    alt_threshold BIGINT,
+    alt_action TEXT,
+    image_hash_tolerance BIGINT,
+    review_channel_id BIGINT,
+    nsfw_threshold BIGINT,
+    level_channel_id BIGINT,
+    starboard_channel_id BIGINT,
+    starboard_threshold BIGINT,
+    welcome_channel_id BIGINT,
+    welcome_message TEXT,
+    goodbye_message TEXT,
+    onboarding_channel_id BIGINT,
+    voice_hub_channel_id BIGINT,
+    staff_role_id BIGINT,
+    house_edge BIGINT,
+    min_bet BIGINT,
+    max_bet BIGINT,
+    gambling_loss_limit BIGINT,
+    rob_chance BIGINT,
+    role_retention_secs BIGINT,
+    watch_channel_id BIGINT,
+    oncall_channel_id BIGINT,
+    boost_channel_id BIGINT,
+    partner_channel_id BIGINT,
+    partner_interval_secs BIGINT,
+    vote_delete_threshold BIGINT,
+    vote_delete_action TEXT,
+    vote_delete_emoji TEXT,
+    summary_limit BIGINT
+);
+
+CREATE TABLE guild_features (
+    guild_id BIGINT NOT NULL,
+    feature TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, feature)
+);
+
+CREATE TABLE warnings (
+    guild_id BIGINT NOT NULL,
+    case_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    moderator_id BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, case_id)
+);
+
+CREATE INDEX warnings_by_user ON warnings (guild_id, user_id);
+
+CREATE TABLE guild_channel_modes (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT PRIMARY KEY NOT NULL,
+    mode TEXT NOT NULL
+);
+
+CREATE TABLE reaction_wall_stats (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    week BIGINT NOT NULL,
+    emoji TEXT NOT NULL,
+    count BIGINT NOT NULL,
+    PRIMARY KEY (channel_id, week, emoji)
+);
+
+CREATE TABLE member_invites (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    invite_code TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE recent_bans (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    invite_code TEXT,
+    banned_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE blocked_images (
+    guild_id BIGINT NOT NULL,
+    hash BIGINT NOT NULL,
+    added_by BIGINT NOT NULL,
+    added_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, hash)
+);
+
+CREATE TABLE reminders (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    due_at BIGINT NOT NULL,
+    text TEXT NOT NULL,
+    attempts BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX reminders_by_due_at ON reminders (due_at);
+
+CREATE TABLE guild_role_lists (
+    guild_id BIGINT NOT NULL,
+    list TEXT NOT NULL,
+    role_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, list, role_id)
+);
+
+CREATE TABLE reaction_roles (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    emoji TEXT NOT NULL,
+    role_id BIGINT NOT NULL,
+    PRIMARY KEY (message_id, emoji)
+);
+
+CREATE INDEX reaction_roles_by_guild ON reaction_roles (guild_id);
+
+CREATE TABLE member_xp (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    xp BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE INDEX member_xp_by_xp ON member_xp (guild_id, xp DESC);
+
+CREATE TABLE spoiler_rules (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    keywords TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX spoiler_rules_by_guild ON spoiler_rules (guild_id);
+
+CREATE TABLE spoiler_rule_channels (
+    rule_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (rule_id, channel_id)
+);
+
+CREATE TABLE guild_link_allowlist (
+    guild_id BIGINT NOT NULL,
+    domain TEXT NOT NULL,
+    PRIMARY KEY (guild_id, domain)
+);
+
+CREATE TABLE starboard_posts (
+    message_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    post_id BIGINT NOT NULL
+);
+
+CREATE TABLE tags (
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    author_id BIGINT NOT NULL,
+    uses BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
+
+CREATE TABLE jobs (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    kind TEXT NOT NULL,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    target_id BIGINT NOT NULL,
+    run_at BIGINT NOT NULL,
+    attempts BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX jobs_by_run_at ON jobs (run_at);
+
+CREATE TABLE role_schedules (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    members_role_id BIGINT NOT NULL,
+    grant_at BIGINT NOT NULL,
+    remove_at BIGINT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE promotion_rules (
+    guild_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    min_level BIGINT NOT NULL DEFAULT 0,
+    min_days BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, role_id)
+);
+
+CREATE TABLE perm_template_overwrites (
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    target_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    allow BIGINT NOT NULL,
+    deny BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, name, target_id)
+);
+
+CREATE TABLE sync_links (
+    source_id BIGINT NOT NULL,
+    target_guild_id BIGINT NOT NULL,
+    target_id BIGINT NOT NULL,
+    PRIMARY KEY (source_id, target_guild_id)
+);
+
+CREATE TABLE triggers (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    response TEXT,
+    reaction TEXT,
+    forward_channel_id BIGINT
+);
+
+CREATE INDEX triggers_by_guild ON triggers (guild_id);
+
+CREATE TABLE notify_keywords (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    keyword TEXT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, keyword)
+);
+
+CREATE TABLE notify_mutes (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, channel_id)
+);
+
+CREATE TABLE application_forms (
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    role_id BIGINT NOT NULL,
+    review_channel_id BIGINT NOT NULL,
+    questions TEXT NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
+
+CREATE TABLE applications (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    form_name TEXT NOT NULL,
+    user_id BIGINT NOT NULL,
+    answers TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewer_id BIGINT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX applications_by_user ON applications (guild_id, user_id);
+
+CREATE TABLE onboarding_steps (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    content TEXT NOT NULL,
+    delay_secs BIGINT NOT NULL
+);
+
+CREATE INDEX onboarding_steps_by_guild ON onboarding_steps (guild_id);
+
+CREATE TABLE onboarding_progress (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    steps_sent BIGINT NOT NULL DEFAULT 0,
+    via_channel BOOLEAN NOT NULL DEFAULT FALSE,
+    started_at BIGINT NOT NULL,
+    completed_at BIGINT,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE archive_policies (
+    guild_id BIGINT PRIMARY KEY,
+    inactive_days BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    category_id BIGINT,
+    report_channel_id BIGINT,
+    last_report_at BIGINT
+);
+
+CREATE TABLE archive_exemptions (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE archived_channels (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    archived_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE spam_rules (
+    guild_id BIGINT NOT NULL,
+    rule TEXT NOT NULL,
+    threshold BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    timeout_secs BIGINT,
+    PRIMARY KEY (guild_id, rule)
+);
+
+CREATE TABLE polls (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT,
+    author_id BIGINT NOT NULL,
+    question TEXT NOT NULL,
+    options TEXT NOT NULL,
+    closes_at BIGINT,
+    closed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE poll_votes (
+    poll_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    option BIGINT NOT NULL,
+    PRIMARY KEY (poll_id, user_id)
+);
+
+CREATE TABLE giveaways (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT,
+    host_id BIGINT NOT NULL,
+    prize TEXT NOT NULL,
+    ends_at BIGINT NOT NULL,
+    ended BOOLEAN NOT NULL DEFAULT FALSE,
+    winner_id BIGINT
+);
+
+CREATE TABLE giveaway_entries (
+    giveaway_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    PRIMARY KEY (giveaway_id, user_id)
+);
+
+CREATE TABLE meetings (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    voice_channel_id BIGINT NOT NULL,
+    text_channel_id BIGINT NOT NULL,
+    host_id BIGINT NOT NULL,
+    title TEXT,
+    started_at BIGINT NOT NULL,
+    ended_at BIGINT
+);
+
+CREATE UNIQUE INDEX meetings_by_running_channel
+    ON meetings (voice_channel_id) WHERE ended_at IS NULL;
+
+CREATE TABLE meeting_attendance (
+    meeting_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    joined_at BIGINT NOT NULL,
+    left_at BIGINT
+);
+
+CREATE INDEX meeting_attendance_by_meeting ON meeting_attendance (meeting_id);
+
+CREATE TABLE stages (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    text_channel_id BIGINT NOT NULL,
+    message_id BIGINT,
+    host_id BIGINT NOT NULL,
+    topic TEXT NOT NULL,
+    started_at BIGINT NOT NULL,
+    ended_at BIGINT
+);
+
+CREATE UNIQUE INDEX stages_by_running_channel
+    ON stages (channel_id) WHERE ended_at IS NULL;
+
+CREATE TABLE stage_requests (
+    stage_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    requested_at BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'waiting',
+    decided_at BIGINT,
+    PRIMARY KEY (stage_id, user_id)
+);
+
+CREATE TABLE temp_voice_channels (
+    channel_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    owner_id BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE case_counters (
+    guild_id BIGINT PRIMARY KEY,
+    last_case_id BIGINT NOT NULL
+);
+
+CREATE TABLE forum_answers (
+    thread_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    answerer_id BIGINT NOT NULL,
+    marked_by BIGINT NOT NULL,
+    marked_at BIGINT NOT NULL
+);
+
+CREATE TABLE balances (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    balance BIGINT NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE economy_ledger (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    from_id BIGINT,
+    to_id BIGINT,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    reason TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL UNIQUE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX economy_ledger_members ON economy_ledger (guild_id, from_id, to_id);
+
+CREATE TABLE shop_items (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL COLLATE nocase,
+    kind TEXT NOT NULL,
+    price BIGINT NOT NULL CHECK (price > 0),
+    description TEXT,
+    role_id BIGINT,
+    rental_secs BIGINT,
+    UNIQUE (guild_id, name)
+);
+
+CREATE TABLE inventory (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    item_id BIGINT NOT NULL,
+    quantity BIGINT NOT NULL,
+    expires_at BIGINT,
+    PRIMARY KEY (guild_id, user_id, item_id)
+);
+
+CREATE TABLE economy_cooldowns (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    ready_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, action)
+);
+
+CREATE TABLE user_timezones (
+    user_id BIGINT PRIMARY KEY,
+    offset_minutes BIGINT NOT NULL,
+    changed_at BIGINT NOT NULL
+);
+
+CREATE TABLE streaks (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    activity TEXT NOT NULL,
+    current BIGINT NOT NULL,
+    best BIGINT NOT NULL,
+    last_day BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, activity)
+);
+
+CREATE INDEX streaks_leaderboard ON streaks (guild_id, activity, current);
+
+CREATE TABLE saved_roles (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    saved_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id, role_id)
+);
+
+CREATE TABLE mod_notes (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    author_id BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX mod_notes_by_user ON mod_notes (guild_id, user_id);
+
+CREATE TABLE mod_note_edits (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    note_id BIGINT NOT NULL,
+    editor_id BIGINT NOT NULL,
+    previous_content TEXT NOT NULL,
+    edited_at BIGINT NOT NULL
+);
+
+CREATE INDEX mod_note_edits_by_note ON mod_note_edits (note_id);
+
+CREATE TABLE watchlist (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    reason TEXT,
+    added_by BIGINT NOT NULL,
+    added_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE oncall_shifts (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    starts_at BIGINT NOT NULL,
+    ends_at BIGINT NOT NULL
+);
+
+CREATE INDEX oncall_shifts_by_guild ON oncall_shifts (guild_id);
+
+CREATE TABLE oncall_covers (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    covered_id BIGINT NOT NULL,
+    starts_at BIGINT NOT NULL,
+    ends_at BIGINT NOT NULL
+);
+
+CREATE INDEX oncall_covers_by_guild ON oncall_covers (guild_id, ends_at);
+
+CREATE TABLE oncall_posts (
+    guild_id BIGINT PRIMARY KEY,
+    week BIGINT NOT NULL
+);
+
+CREATE TABLE incidents (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    opened_by BIGINT NOT NULL,
+    opened_at BIGINT NOT NULL,
+    closed_by BIGINT,
+    closed_at BIGINT
+);
+
+CREATE UNIQUE INDEX incidents_open ON incidents (guild_id) WHERE closed_at IS NULL;
+
+CREATE TABLE incident_events (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    incident_id BIGINT NOT NULL,
+    at BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    summary TEXT NOT NULL
+);
+
+CREATE INDEX incident_events_by_incident ON incident_events (incident_id);
+
+CREATE TABLE guild_translation_links (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    target_id BIGINT NOT NULL,
+    language TEXT NOT NULL,
+    PRIMARY KEY (channel_id, target_id)
+);
+
+CREATE INDEX guild_translation_links_by_guild ON guild_translation_links (guild_id);
+
+CREATE TABLE guild_alt_text_channels (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE quiet_hours (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    starts_at BIGINT NOT NULL,
+    ends_at BIGINT NOT NULL,
+    slowmode BIGINT,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    saved_allow BIGINT,
+    saved_deny BIGINT,
+    saved_slowmode BIGINT
+);
+
+CREATE INDEX quiet_hours_by_guild ON quiet_hours (guild_id);
+
+CREATE TABLE themes (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    starts_on BIGINT NOT NULL,
+    ends_on BIGINT NOT NULL,
+    colour BIGINT,
+    icon TEXT,
+    reactions TEXT,
+    status TEXT,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    saved_icon TEXT,
+    UNIQUE (guild_id, name)
+);
+
+CREATE TABLE stats_channels (
+    channel_id BIGINT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    stat TEXT NOT NULL,
+    template TEXT NOT NULL
+);
+
+CREATE INDEX stats_channels_by_guild ON stats_channels (guild_id);
+
+CREATE TABLE booster_roles (
+    guild_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    role_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE TABLE partners (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    blurb TEXT NOT NULL,
+    invite TEXT NOT NULL,
+    added_by BIGINT NOT NULL,
+    expires_at BIGINT,
+    impressions BIGINT NOT NULL DEFAULT 0,
+    last_posted_at BIGINT,
+    UNIQUE (guild_id, name)
+);
+
+CREATE TABLE partner_posts (
+    guild_id BIGINT PRIMARY KEY,
+    posted_at BIGINT NOT NULL
+);
+
+CREATE TABLE vote_deletions (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL UNIQUE,
+    author_id BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reviewed_by BIGINT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE vote_deletion_voters (
+    deletion_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    PRIMARY KEY (deletion_id, user_id)
+);
+
+CREATE TABLE faqs (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    question TEXT NOT NULL,
+    answer TEXT NOT NULL,
+    keywords TEXT,
+    regex TEXT,
+    min_confidence BIGINT NOT NULL,
+    matches BIGINT NOT NULL DEFAULT 0,
+    helpful BIGINT NOT NULL DEFAULT 0,
+    unhelpful BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX faqs_by_guild ON faqs (guild_id);
+
+CREATE TABLE kb_articles (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    title TEXT NOT NULL COLLATE nocase,
+    body TEXT NOT NULL,
+    author_id BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    UNIQUE (guild_id, title)
+);
+
+-- What SQLite keeps in FTS5 tables are plain tables with a text search vector here. kb_search
+-- takes the same inserts as the FTS5 table, including its 'delete' command
+CREATE TABLE kb_search (
+    rowid BIGINT PRIMARY KEY,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    kb_search TEXT,
+    search TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', title), 'A') || to_tsvector('simple', body)
+    ) STORED
+);
+
+CREATE INDEX kb_search_search ON kb_search USING GIN (search);
+
+CREATE FUNCTION kb_search_command() RETURNS trigger AS $$
+BEGIN
+    IF NEW.kb_search = 'delete' THEN
+        DELETE FROM kb_search WHERE rowid = NEW.rowid;
+        RETURN NULL;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER kb_search_command BEFORE INSERT ON kb_search
+    FOR EACH ROW EXECUTE FUNCTION kb_search_command();
+
+CREATE TABLE archived_messages (
+    rowid BIGINT PRIMARY KEY,
+    content TEXT NOT NULL,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    parent_id BIGINT NOT NULL,
+    author_id BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    search TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', content)) STORED
+);
+
+CREATE INDEX archived_messages_search ON archived_messages USING GIN (search);
+CREATE INDEX archived_messages_guild ON archived_messages (guild_id, parent_id);
+
+CREATE TABLE digests (
+    guild_id BIGINT PRIMARY KEY,
+    channel_id BIGINT NOT NULL,
+    hour BIGINT NOT NULL,
+    summarize BOOLEAN NOT NULL DEFAULT FALSE,
+    last_posted_at BIGINT
+);
+
+CREATE TABLE digest_channels (
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE summary_usage (
+    guild_id BIGINT NOT NULL,
+    day BIGINT NOT NULL,
+    used BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, day)
+);
+
+CREATE TABLE guild_toxicity_thresholds (
+    guild_id BIGINT NOT NULL,
+    category TEXT NOT NULL,
+    threshold BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, category)
+);
+
+CREATE TABLE automod_flags (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    author_id BIGINT NOT NULL,
+    source TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    review_channel_id BIGINT,
+    review_message_id BIGINT,
+    reviewed_by BIGINT,
+    created_at BIGINT NOT NULL,
+    reviewed_at BIGINT
+);
+
+CREATE INDEX automod_flags_guild ON automod_flags (guild_id, created_at);
+
+CREATE TABLE mod_cases (
+    guild_id BIGINT NOT NULL,
+    case_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    user_id BIGINT NOT NULL,
+    moderator_id BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, case_id)
+);
+
+CREATE TABLE guild_tiers (
+    guild_id BIGINT PRIMARY KEY NOT NULL,
+    tier TEXT NOT NULL
+);
+
+CREATE TABLE quota_usage (
+    guild_id BIGINT NOT NULL,
+    quota TEXT NOT NULL,
+    day BIGINT NOT NULL,
+    used BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, quota, day)
+);
+
+CREATE TABLE entitlements (
+    id BIGINT PRIMARY KEY NOT NULL,
+    sku_id BIGINT NOT NULL,
+    user_id BIGINT,
+    guild_id BIGINT,
+    ends_at BIGINT
+);
+
+CREATE INDEX entitlements_guild ON entitlements (guild_id);
+CREATE INDEX entitlements_user ON entitlements (user_id);
+
+CREATE TABLE votes (
+    user_id BIGINT NOT NULL,
+    voted_at BIGINT NOT NULL
+);
+
+CREATE INDEX votes_user ON votes (user_id, voted_at);
+CREATE INDEX votes_voted_at ON votes (voted_at);
+
+CREATE TABLE vote_leaderboards (
+    month BIGINT PRIMARY KEY NOT NULL,
+    posted_at BIGINT NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Whether the results of a closed poll were posted on its message. Closing a poll is retried
+-- until they were
+ALTER TABLE polls ADD COLUMN results_posted BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE polls SET results_posted = closed;
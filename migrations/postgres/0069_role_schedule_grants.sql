@@ -0,0 +1,7 @@
+-- Members a role schedule gave its role to. Only they lose it again when the window ends, not
+-- members who had it already
+CREATE TABLE role_schedule_grants (
+    schedule_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    PRIMARY KEY (schedule_id, user_id)
+);
@@ -0,0 +1,356 @@
+// Small client for the Source engine server query protocol (A2S)
+// See https://developer.valvesoftware.com/wiki/Server_queries
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::{net::UdpSocket, time::timeout};
+
+use crate::Error;
+
+// Default query port for Source servers
+const DEFAULT_PORT: u16 = 27015;
+// How long we wait for a single response packet before giving up
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+// Servers never send more than this in a single (non split) packet
+const MAX_PACKET_SIZE: usize = 1400;
+
+const SIMPLE_HEADER: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+const A2S_INFO: u8 = 0x54;
+const A2S_PLAYER: u8 = 0x55;
+const S2C_CHALLENGE: u8 = 0x41;
+const S2A_INFO: u8 = 0x49;
+const S2A_PLAYER: u8 = 0x44;
+
+/// Basic server information returned by A2S_INFO
+pub struct ServerInfo {
+    pub name: String,
+    pub map: String,
+    pub game: String,
+    pub players: u8,
+    pub max_players: u8,
+    pub bots: u8,
+    pub vac: bool,
+}
+
+/// A single player entry returned by A2S_PLAYER
+pub struct Player {
+    pub name: String,
+    pub score: i32,
+    pub duration: Duration,
+}
+
+/// Resolves `host`, `host:port`, an IP or `[ipv6]:port` into a socket address, defaulting to
+/// port 27015. Only public addresses are allowed, the query must not reach into our own network
+pub async fn resolve(address: &str) -> Result<SocketAddr, Error> {
+    let addr = if let Ok(addr) = address.parse::<SocketAddr>() {
+        addr
+    } else if let Ok(ip) = address.parse::<IpAddr>() {
+        // A bare IPv6 address is full of colons but has no port
+        SocketAddr::new(ip, DEFAULT_PORT)
+    } else {
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{}:{}", address, DEFAULT_PORT)
+        };
+
+        tokio::net::lookup_host(address)
+            .await?
+            .next()
+            .ok_or("Could not resolve server address")?
+    };
+
+    if !is_public(addr.ip()) {
+        return Err("Only public server addresses can be queried".into());
+    }
+
+    Ok(addr)
+}
+
+// Loopback, private, link-local and other special ranges aren't game servers on the internet
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    // 100.64.0.0/10 is carrier-grade NAT
+    let shared = a == 100 && (64..128).contains(&b);
+
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || shared)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    // fc00::/7 is unique local, fe80::/10 link-local
+    let unique_local = first & 0xfe00 == 0xfc00;
+    let link_local = first & 0xffc0 == 0xfe80;
+
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local)
+}
+
+/// Queries the server for its basic information (name, map, player count)
+pub async fn query_info(addr: SocketAddr) -> Result<ServerInfo, Error> {
+    let mut request = SIMPLE_HEADER.to_vec();
+    request.push(A2S_INFO);
+    request.extend_from_slice(b"Source Engine Query\0");
+
+    let socket = connect(addr).await?;
+    let mut response = exchange(&socket, &request).await?;
+
+    // Newer servers answer with a challenge which has to be appended to the request
+    if response.first() == Some(&S2C_CHALLENGE) {
+        request.extend_from_slice(&challenge(&response)?);
+        response = exchange(&socket, &request).await?;
+    }
+
+    let mut reader = Reader::new(&response);
+    if reader.u8()? != S2A_INFO {
+        return Err("Unexpected response to A2S_INFO".into());
+    }
+
+    let _protocol = reader.u8()?;
+    let name = reader.string()?;
+    let map = reader.string()?;
+    let _folder = reader.string()?;
+    let game = reader.string()?;
+    let _app_id = reader.u16()?;
+    let players = reader.u8()?;
+    let max_players = reader.u8()?;
+    let bots = reader.u8()?;
+    let _server_type = reader.u8()?;
+    let _environment = reader.u8()?;
+    let _visibility = reader.u8()?;
+    let vac = reader.u8()? == 1;
+
+    Ok(ServerInfo {
+        name,
+        map,
+        game,
+        players,
+        max_players,
+        bots,
+        vac,
+    })
+}
+
+/// Queries the server for the list of connected players
+pub async fn query_players(addr: SocketAddr) -> Result<Vec<Player>, Error> {
+    let socket = connect(addr).await?;
+
+    // A2S_PLAYER always requires a challenge, which we get by sending -1
+    let mut request = SIMPLE_HEADER.to_vec();
+    request.push(A2S_PLAYER);
+    request.extend_from_slice(&SIMPLE_HEADER);
+
+    let mut response = exchange(&socket, &request).await?;
+    if response.first() == Some(&S2C_CHALLENGE) {
+        request.truncate(5);
+        request.extend_from_slice(&challenge(&response)?);
+        response = exchange(&socket, &request).await?;
+    }
+
+    parse_players(&response)
+}
+
+fn parse_players(response: &[u8]) -> Result<Vec<Player>, Error> {
+    let mut reader = Reader::new(response);
+    if reader.u8()? != S2A_PLAYER {
+        return Err("Unexpected response to A2S_PLAYER".into());
+    }
+
+    let count = reader.u8()?;
+    let mut players = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let _index = reader.u8()?;
+        let name = reader.string()?;
+        let score = reader.i32()?;
+        let duration = reader.f32()?;
+
+        players.push(Player {
+            name,
+            score,
+            // Negative, NaN or infinite from broken or hostile servers
+            duration: Duration::try_from_secs_f32(duration).unwrap_or_default(),
+        });
+    }
+
+    Ok(players)
+}
+
+async fn connect(addr: SocketAddr) -> Result<UdpSocket, Error> {
+    let bind_addr = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+// Sends a request and waits for a single packet, stripping the simple header
+async fn exchange(socket: &UdpSocket, request: &[u8]) -> Result<Vec<u8>, Error> {
+    socket.send(request).await?;
+
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    let len = timeout(QUERY_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| "Server did not respond in time")??;
+
+    match buf[..len].strip_prefix(&SIMPLE_HEADER) {
+        Some(payload) => Ok(payload.to_vec()),
+        // 0xFFFFFFFE means the response was split over multiple packets
+        None => Err("Split responses are not supported".into()),
+    }
+}
+
+fn challenge(response: &[u8]) -> Result<[u8; 4], Error> {
+    response
+        .get(1..5)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Malformed challenge response".into())
+}
+
+// Cursor over a response payload, all numbers are little endian
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.data.len() < N {
+            return Err("Response ended unexpectedly".into());
+        }
+
+        let (head, rest) = self.data.split_at(N);
+        self.data = rest;
+        Ok(head.try_into()?)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let end = self
+            .data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("Unterminated string in response")?;
+
+        let value = String::from_utf8_lossy(&self.data[..end]).into_owned();
+        self.data = &self.data[end + 1..];
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_ip_literals() {
+        assert_eq!(
+            resolve("8.8.8.8").await.unwrap(),
+            "8.8.8.8:27015".parse().unwrap()
+        );
+        assert_eq!(
+            resolve("2001:4860::8888").await.unwrap(),
+            "[2001:4860::8888]:27015".parse().unwrap()
+        );
+        assert_eq!(
+            resolve("[2001:4860::8888]:27016").await.unwrap(),
+            "[2001:4860::8888]:27016".parse().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_internal_addresses() {
+        for address in [
+            "127.0.0.1",
+            "10.0.0.1:27015",
+            "192.168.1.10",
+            "169.254.169.254",
+            "::1",
+            "[fe80::1]:27015",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(resolve(address).await.is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn survives_invalid_player_durations() {
+        let mut response = vec![S2A_PLAYER, 3];
+        for duration in [f32::INFINITY, f32::NAN, 60.0] {
+            response.extend_from_slice(&[0, b'a', 0, 1, 0, 0, 0]);
+            response.extend_from_slice(&duration.to_le_bytes());
+        }
+
+        let durations: Vec<_> = parse_players(&response)
+            .unwrap()
+            .iter()
+            .map(|p| p.duration)
+            .collect();
+        assert_eq!(
+            durations,
+            [Duration::ZERO, Duration::ZERO, Duration::from_secs(60)]
+        );
+    }
+
+    #[test]
+    fn reads_little_endian_fields() {
+        let data = [
+            0x49, 0x34, 0x12, 0xFE, 0xFF, 0xFF, 0xFF, b'd', b'e', 0, 0, 0, 0x80, 0x3F,
+        ];
+        let mut reader = Reader::new(&data);
+
+        assert_eq!(reader.u8().unwrap(), 0x49);
+        assert_eq!(reader.u16().unwrap(), 0x1234);
+        assert_eq!(reader.i32().unwrap(), -2);
+        assert_eq!(reader.string().unwrap(), "de");
+        assert_eq!(reader.f32().unwrap(), 1.0);
+        assert!(reader.u8().is_err());
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        let mut reader = Reader::new(b"de");
+        assert!(reader.string().is_err());
+    }
+}
@@ -0,0 +1,267 @@
+// Alt account detection
+// Every member that joins gets a score from a few heuristics, anything that looks suspicious
+// is posted to the log channel. Servers can set an alt_threshold and alt_action to act on
+// high scores automatically. Invite tracking needs the Manage Server permission.
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::AltAction,
+    duration,
+    mentions::{self, Mentions},
+    modlog::{self, Action},
+    Data, Error,
+};
+
+// Weights of the heuristics, they add up to 100
+const DEFAULT_AVATAR_SCORE: u32 = 20;
+const NEW_ACCOUNT_SCORE: u32 = 30;
+const SIMILAR_NAME_SCORE: u32 = 30;
+const SHARED_INVITE_SCORE: u32 = 20;
+
+// Accounts younger than this count as new
+const NEW_ACCOUNT_AGE: i64 = 7 * 24 * 60 * 60;
+// Bans older than this are no longer compared against
+const BAN_MEMORY: i64 = 30 * 24 * 60 * 60;
+// Names at least this similar (0 to 1) count as a match
+const NAME_SIMILARITY: f64 = 0.8;
+// How long members flagged with the timeout action are timed out
+const ALT_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Remembers how often each invite was used, so we can tell which one a member joined with
+#[derive(Default)]
+pub struct InviteTracker {
+    uses: Mutex<HashMap<serenity::GuildId, HashMap<String, u64>>>,
+}
+
+impl InviteTracker {
+    /// Refreshes the invite uses of a guild and returns the invite whose count went up
+    pub async fn refresh(
+        &self,
+        ctx: &serenity::Context,
+        guild: serenity::GuildId,
+    ) -> Option<String> {
+        let invites = match guild.invites(&ctx.http).await {
+            Ok(invites) => invites,
+            Err(e) => {
+                tracing::debug!(guild = guild.0, "Error fetching invites: {}", e);
+                return None;
+            }
+        };
+
+        let current: HashMap<String, u64> = invites.into_iter().map(|i| (i.code, i.uses)).collect();
+        let previous = self.uses.lock().unwrap().insert(guild, current.clone())?;
+
+        current
+            .into_iter()
+            .find(|(code, uses)| *uses > previous.get(code).copied().unwrap_or(0))
+            .map(|(code, _)| code)
+    }
+}
+
+struct Score {
+    total: u32,
+    reasons: Vec<String>,
+}
+
+/// Scores a new member and alerts or acts depending on the guild's settings
+pub async fn handle_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let guild = member.guild_id;
+    let invite = data.invites.refresh(ctx, guild).await;
+    if let Some(invite) = &invite {
+        data.db
+            .record_member_invite(guild, member.user.id, invite)
+            .await?;
+    }
+
+    let score = score(data, member, invite.as_deref()).await?;
+    if score.total == 0 {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let action = match (config.alt_threshold, config.alt_action) {
+        (Some(threshold), Some(action)) if score.total >= threshold => Some(action),
+        _ => None,
+    };
+
+    if let Some(channel) = config.log_channel {
+        let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+            m.embed(|e| {
+                e.title("Possible alt account joined")
+                    .field(
+                        "User",
+                        format!("{} (<@{}>)", member.user.tag(), member.user.id.0),
+                        true,
+                    )
+                    .field("Alt score", format!("{}%", score.total), true)
+                    .field("Reasons", score.reasons.join("\n"), false)
+                    .field("Invite", invite.as_deref().unwrap_or("Unknown"), true);
+
+                if let Some(action) = action {
+                    e.field("Action taken", action.name(), true);
+                }
+
+                e.timestamp(serenity::Timestamp::now())
+            })
+        })
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Error posting alt alert: {}", e);
+        }
+    }
+
+    if let Some(action) = action {
+        take_action(ctx, data, member, action, score.total).await?;
+    }
+
+    Ok(())
+}
+
+async fn score(
+    data: &Data,
+    member: &serenity::Member,
+    invite: Option<&str>,
+) -> Result<Score, Error> {
+    let user = &member.user;
+    let mut score = Score {
+        total: 0,
+        reasons: Vec::new(),
+    };
+
+    if user.avatar.is_none() {
+        score.total += DEFAULT_AVATAR_SCORE;
+        score.reasons.push("Default avatar".to_string());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let age = now - user.id.created_at().unix_timestamp();
+    if age < NEW_ACCOUNT_AGE {
+        score.total += NEW_ACCOUNT_SCORE;
+        score.reasons.push(format!(
+            "Account created {} ago",
+            duration::format(Duration::from_secs(age.max(0) as u64))
+        ));
+    }
+
+    let bans = data
+        .db
+        .recent_bans(member.guild_id, now - BAN_MEMORY)
+        .await?;
+
+    if let Some(ban) = bans
+        .iter()
+        .find(|ban| similarity(&ban.name, &user.name) >= NAME_SIMILARITY)
+    {
+        score.total += SIMILAR_NAME_SCORE;
+        score
+            .reasons
+            .push(format!("Name is similar to banned user `{}`", ban.name));
+    }
+
+    if let Some(invite) = invite {
+        if bans
+            .iter()
+            .any(|ban| ban.invite_code.as_deref() == Some(invite))
+        {
+            score.total += SHARED_INVITE_SCORE;
+            score
+                .reasons
+                .push(format!("Joined through `{}`, like a banned user", invite));
+        }
+    }
+
+    Ok(score)
+}
+
+async fn take_action(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+    action: AltAction,
+    score: u32,
+) -> Result<(), Error> {
+    let guild = member.guild_id;
+    let reason = format!("Likely alt account ({}% alt score)", score);
+
+    let name = match action {
+        AltAction::Kick => {
+            guild
+                .kick_with_reason(&ctx.http, member.user.id, &reason)
+                .await?;
+            "kicked"
+        }
+        AltAction::Ban => {
+            guild
+                .ban_with_reason(&ctx.http, member.user.id, 0, &reason)
+                .await?;
+            "banned"
+        }
+        AltAction::Timeout => {
+            let until = serenity::Timestamp::from_unix_timestamp(
+                serenity::Timestamp::now().unix_timestamp() + ALT_TIMEOUT.as_secs() as i64,
+            )?;
+            guild
+                .edit_member(&ctx.http, member.user.id, |m| {
+                    m.disable_communication_until_datetime(until)
+                })
+                .await?;
+            "timed out"
+        }
+    };
+
+    let bot = ctx.cache.current_user();
+    modlog::post_action(
+        ctx,
+        data,
+        guild,
+        &bot.into(),
+        Action {
+            name,
+            target: &member.user,
+            reason: &reason,
+            details: None,
+        },
+    )
+    .await
+}
+
+// How similar two names are, from 0 (nothing in common) to 1 (the same)
+// Case, spaces and symbols are ignored so `Cool.Guy` and `coolguy_` match
+fn similarity(a: &str, b: &str) -> f64 {
+    let normalize = |s: &str| -> Vec<char> {
+        s.to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect()
+    };
+    let (a, b) = (normalize(a), normalize(b));
+
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+
+    // Levenshtein distance, keeping only the previous row
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
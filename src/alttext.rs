@@ -0,0 +1,296 @@
+// Alt text reminders
+// In channels added with /config alttext, members who post images without a description get a
+// DM with an "Add alt text" button. If their DMs are closed, it's a reply that doesn't ping them
+// instead, removed again after REMINDER_LIFETIME. The button opens a modal, and what they write
+// is posted as a reply to their message so screen reader users get it too. serenity doesn't
+// know attachment descriptions yet, so messages with images in these channels are fetched once
+// more as plain JSON to read them.
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+
+use crate::{
+    imagehash,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+// Long enough to notice it, short enough not to clutter the channel
+const REMINDER_LIFETIME: Duration = Duration::from_secs(5 * 60);
+/// Most characters of a description
+const MAX_DESCRIPTION_LENGTH: u64 = 1000;
+const BUTTON_PREFIX: &str = "alttext-button-";
+const MODAL_PREFIX: &str = "alttext-modal-";
+
+/// How many of the `images` have no description in the raw JSON of their message
+pub fn missing_descriptions(raw: &Value, images: &[serenity::AttachmentId]) -> usize {
+    let attachments = match raw["attachments"].as_array() {
+        Some(attachments) => attachments,
+        None => return images.len(),
+    };
+
+    images
+        .iter()
+        .filter(|id| {
+            let id = id.0.to_string();
+            !attachments.iter().any(|a| {
+                a["id"].as_str() == Some(id.as_str())
+                    && a["description"]
+                        .as_str()
+                        .is_some_and(|d| !d.trim().is_empty())
+            })
+        })
+        .count()
+}
+
+// The message a button or modal is about, from its custom ID
+fn parse_message(
+    custom_id: &str,
+    prefix: &str,
+) -> Option<(serenity::ChannelId, serenity::MessageId)> {
+    let (channel, message) = custom_id.strip_prefix(prefix)?.split_once('-')?;
+    Some((
+        serenity::ChannelId(channel.parse().ok()?),
+        serenity::MessageId(message.parse().ok()?),
+    ))
+}
+
+/// Reminds the author of a message in an alt text channel to describe their images
+pub async fn check(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let images: Vec<_> = message
+        .attachments
+        .iter()
+        .filter(|a| imagehash::is_image(a))
+        .map(|a| a.id)
+        .collect();
+    if images.is_empty() {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    if !config.alt_text_channels.contains(&message.channel_id) {
+        return Ok(());
+    }
+
+    let request = ::serenity::http::request::RequestBuilder::new(
+        ::serenity::http::routing::RouteInfo::GetMessage {
+            channel_id: message.channel_id.0,
+            message_id: message.id.0,
+        },
+    )
+    .build();
+    let raw: Value = ctx.http.fire(request).await?;
+    let missing = missing_descriptions(&raw, &images);
+    if missing == 0 {
+        return Ok(());
+    }
+
+    let images = if missing == 1 {
+        "an image".to_string()
+    } else {
+        format!("{} images", missing)
+    };
+    let content = format!(
+        "You posted {} without a description in {}, adding one helps members using a screen \
+        reader.",
+        images,
+        message.link()
+    );
+
+    let dm = message
+        .author
+        .dm(&ctx.http, |m| {
+            m.content(&content);
+            add_button(m, message)
+        })
+        .await;
+    let error = match dm {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    // Their DMs are closed, so the reminder goes below the images without pinging them
+    tracing::debug!(
+        user = message.author.id.0,
+        "Can't DM alt text reminder: {}",
+        error
+    );
+    let reminder = mentions::send_message(&ctx.http, message.channel_id, Mentions::Nothing, |m| {
+        m.reference_message(message).content(&content);
+        add_button(m, message)
+    })
+    .await?;
+
+    let http = Arc::clone(&ctx.http);
+    tokio::spawn(async move {
+        tokio::time::sleep(REMINDER_LIFETIME).await;
+        let _ = reminder.delete(&http).await;
+    });
+
+    Ok(())
+}
+
+fn add_button<'a, 'b>(
+    m: &'b mut serenity::CreateMessage<'a>,
+    message: &serenity::Message,
+) -> &'b mut serenity::CreateMessage<'a> {
+    m.components(|c| {
+        c.create_action_row(|r| {
+            r.create_button(|b| {
+                b.custom_id(format!(
+                    "{}{}-{}",
+                    BUTTON_PREFIX, message.channel_id.0, message.id.0
+                ))
+                .label("Add alt text")
+                .style(serenity::ButtonStyle::Primary)
+            })
+        })
+    })
+}
+
+/// Opens the description modal for the author of the message
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    _data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (channel, message) = match parse_message(&interaction.data.custom_id, BUTTON_PREFIX) {
+        Some(ids) => ids,
+        None => return Ok(()),
+    };
+
+    let author = channel
+        .message(ctx, message)
+        .await
+        .ok()
+        .map(|m| m.author.id);
+    if author != Some(interaction.user.id) {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| {
+                    d.content(":x: Only the author of the images can describe them.")
+                        .ephemeral(true)
+                })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(serenity::InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("{}{}-{}", MODAL_PREFIX, channel.0, message.0))
+                        .title("Add alt text")
+                        .components(|c| {
+                            c.create_action_row(|r| {
+                                r.create_input_text(|t| {
+                                    t.custom_id("description")
+                                        .label("Describe what your images show")
+                                        .style(serenity::InputTextStyle::Paragraph)
+                                        .max_length(MAX_DESCRIPTION_LENGTH)
+                                        .required(true)
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Posts descriptions submitted with the modal as a reply to the images
+pub async fn handle_modal(
+    ctx: &serenity::Context,
+    _data: &Data,
+    interaction: &serenity::ModalSubmitInteraction,
+) -> Result<(), Error> {
+    let (channel, message) = match parse_message(&interaction.data.custom_id, MODAL_PREFIX) {
+        Some(ids) => ids,
+        None => return Ok(()),
+    };
+
+    let description = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|c| match c {
+            serenity::ActionRowComponent::InputText(input) => Some(input.value.trim().to_string()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let reply = match channel.message(ctx, message).await {
+        _ if description.is_empty() => ":x: The description is empty.",
+        Ok(images) if images.author.id == interaction.user.id => {
+            mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+                m.reference_message(&images).embed(|e| {
+                    e.author(|a| a.name(images.author.tag()).icon_url(images.author.face()))
+                        .title("Image description")
+                        .description(&description)
+                })
+            })
+            .await?;
+
+            // The reminder did its job
+            if let Some(reminder) = &interaction.message {
+                let _ = reminder.delete(ctx).await;
+            }
+            ":white_check_mark: Thanks, your description was added"
+        }
+        Ok(_) => ":x: Only the author of the images can describe them.",
+        Err(_) => ":x: The message with the images is gone.",
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn images_without_descriptions_are_counted() {
+        let raw = json!({
+            "attachments": [
+                { "id": "1", "description": "A cat on a sofa" },
+                { "id": "2" },
+                { "id": "3", "description": "  " },
+            ]
+        });
+        let ids = |ids: &[u64]| {
+            ids.iter()
+                .map(|id| serenity::AttachmentId(*id))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(missing_descriptions(&raw, &ids(&[1])), 0);
+        assert_eq!(missing_descriptions(&raw, &ids(&[1, 2, 3])), 2);
+        assert_eq!(missing_descriptions(&json!({}), &ids(&[1])), 1);
+
+        assert_eq!(
+            parse_message("alttext-button-12-34", BUTTON_PREFIX),
+            Some((serenity::ChannelId(12), serenity::MessageId(34)))
+        );
+        assert_eq!(parse_message("alttext-modal-12-34", BUTTON_PREFIX), None);
+    }
+}
@@ -0,0 +1,278 @@
+// Applications
+// Admins set up forms of up to ten questions for a role, like a staff application. Members fill
+// them in with /apply, five questions per modal, and the answers are posted in the form's review
+// channel with Accept and Reject buttons. Accepting gives the role, either way the applicant is
+// told in a DM. The buttons are handled as events instead of with a collector so they keep
+// working after a restart.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::ApplicationForm,
+    dm,
+    mentions::{self, Mentions},
+    permissions, Data, Error,
+};
+
+pub const MAX_QUESTIONS: usize = 10;
+/// Questions are modal labels, which Discord limits to 45 characters
+pub const MAX_QUESTION_LENGTH: usize = 45;
+/// Keeps every answer of a form within the size limit of one embed
+pub const MAX_ANSWER_LENGTH: u64 = 500;
+/// Most text inputs Discord allows in one modal
+pub const QUESTIONS_PER_PAGE: usize = 5;
+const BUTTON_PREFIX: &str = "application";
+
+/// Posts a finished application in the form's review channel and returns its ID
+pub async fn submit(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    form: &ApplicationForm,
+    user: &serenity::User,
+    answers: &[String],
+) -> Result<i64, Error> {
+    let id = data
+        .db
+        .add_application(guild, &form.name, user.id, answers)
+        .await?;
+
+    mentions::send_message(&ctx.http, form.review_channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(format!("Application #{} for {}", id, form.name))
+                .author(|a| a.name(user.tag()).icon_url(user.face()))
+                .description(format!("<@{}> applied for <@&{}>", user.id.0, form.role.0));
+            for (question, answer) in form.questions.iter().zip(answers) {
+                e.field(question, answer, false);
+            }
+            e.footer(|f| f.text(format!("User ID: {}", user.id.0)))
+                .timestamp(serenity::Timestamp::now())
+        })
+        .components(|c| {
+            c.create_action_row(|r| {
+                r.create_button(|b| {
+                    b.custom_id(format!("{}-accept-{}", BUTTON_PREFIX, id))
+                        .label("Accept")
+                        .style(serenity::ButtonStyle::Success)
+                })
+                .create_button(|b| {
+                    b.custom_id(format!("{}-reject-{}", BUTTON_PREFIX, id))
+                        .label("Reject")
+                        .style(serenity::ButtonStyle::Danger)
+                })
+            })
+        })
+    })
+    .await?;
+
+    Ok(id)
+}
+
+// Returns whether the button accepts and the application ID
+fn parse_button(custom_id: &str) -> Option<(bool, i64)> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    let (action, id) = rest.split_once('-')?;
+    let accept = match action {
+        "accept" => true,
+        "reject" => false,
+        _ => return None,
+    };
+
+    Some((accept, id.parse().ok()?))
+}
+
+/// Accepts or rejects an application when a reviewer clicks one of its buttons
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (accept, id) = match parse_button(&interaction.data.custom_id) {
+        Some(button) => button,
+        None => return Ok(()),
+    };
+
+    match review(ctx, data, interaction, accept, id).await? {
+        Ok(outcome) => {
+            let mut embed = interaction
+                .message
+                .embeds
+                .first()
+                .cloned()
+                .map(serenity::CreateEmbed::from)
+                .unwrap_or_default();
+            embed.field("Outcome", outcome, false).colour(if accept {
+                serenity::Colour::DARK_GREEN
+            } else {
+                serenity::Colour::RED
+            });
+
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+                })
+                .await?;
+        }
+        Err(refusal) => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.interaction_response_data(|d| d.content(refusal).ephemeral(true))
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the outcome to show on the review message, or why the reviewer can't do this
+async fn review(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+    accept: bool,
+    id: i64,
+) -> Result<Result<String, String>, Error> {
+    let (guild, reviewer) = match (interaction.guild_id, &interaction.member) {
+        (Some(guild), Some(member)) => (guild, member),
+        _ => {
+            return Ok(Err(
+                ":x: Applications can only be reviewed in a server.".to_string()
+            ))
+        }
+    };
+
+    let application = match data.db.application(id).await? {
+        Some(application) if application.guild_id as u64 == guild.0 => application,
+        _ => {
+            return Ok(Err(
+                ":x: That application doesn't exist anymore.".to_string()
+            ))
+        }
+    };
+    if application.status != "pending" {
+        return Ok(Err(format!(
+            ":x: That application was already {}.",
+            application.status
+        )));
+    }
+    let form = match data
+        .db
+        .application_form(guild, &application.form_name)
+        .await?
+    {
+        Some(form) => form,
+        None => {
+            return Ok(Err(format!(
+                ":x: The `{}` form was deleted.",
+                application.form_name
+            )))
+        }
+    };
+
+    // Same rules as handing out the role by hand
+    if !reviewer.permissions.is_some_and(|p| p.manage_roles()) {
+        return Ok(Err(
+            ":x: You need the Manage Roles permission to review applications.".to_string(),
+        ));
+    }
+    if accept && !is_above(ctx, guild, reviewer, form.role) {
+        return Ok(Err(format!(
+            ":x: Your highest role must be above <@&{}>.",
+            form.role.0
+        )));
+    }
+
+    let applicant = serenity::UserId(application.user_id as u64);
+    if accept {
+        let result = ctx
+            .http
+            .add_member_role(
+                guild.0,
+                applicant.0,
+                form.role.0,
+                Some(&format!(
+                    "Application #{} accepted by {}",
+                    id,
+                    reviewer.user.tag()
+                )),
+            )
+            .await;
+
+        if let Err(e) = result {
+            let error: Error = e.into();
+            return Ok(Err(if permissions::is_missing_permissions(&error) {
+                format!(":x: My highest role must be above <@&{}>.", form.role.0)
+            } else {
+                format!(":x: I couldn't give <@&{}>: {}", form.role.0, error)
+            }));
+        }
+    }
+
+    // Another reviewer may have clicked at the same time
+    if !data
+        .db
+        .review_application(id, accept, reviewer.user.id)
+        .await?
+    {
+        return Ok(Err(":x: That application was already reviewed.".to_string()));
+    }
+
+    let server = ctx
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_default();
+    let content = if accept {
+        format!(
+            ":white_check_mark: Your {} application in {} was accepted!",
+            form.name, server
+        )
+    } else {
+        format!(
+            "Your {} application in {} was not accepted this time.",
+            form.name, server
+        )
+    };
+    match applicant.to_user(ctx).await {
+        Ok(user) => {
+            dm::send(
+                ctx,
+                &data.dm_stats,
+                &dm::APPLICATION_RESULT,
+                &user,
+                interaction.channel_id,
+                &content,
+            )
+            .await;
+        }
+        Err(e) => tracing::warn!(user = applicant.0, "Error fetching applicant: {}", e),
+    }
+
+    let verdict = if accept { "Accepted" } else { "Rejected" };
+    Ok(Ok(format!("{} by <@{}>", verdict, reviewer.user.id.0)))
+}
+
+// Whether a member's highest role is above `role`, the owner is above everything
+fn is_above(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    member: &serenity::Member,
+    role: serenity::RoleId,
+) -> bool {
+    ctx.cache
+        .guild_field(guild, |g| {
+            if g.owner_id == member.user.id {
+                return true;
+            }
+
+            let top = member
+                .roles
+                .iter()
+                .filter_map(|r| g.roles.get(r))
+                .map(|r| r.position)
+                .max()
+                .unwrap_or(0);
+            g.roles.get(&role).is_some_and(|r| top > r.position)
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,292 @@
+// Auto-archiving of inactive channels
+// Guilds can set a policy with /archive: text channels without messages for a number of days
+// get a warning posted in them, are moved to an archive category, or are made read-only for
+// everyone. A channel's last activity comes from the ID of its last message, so nothing has to
+// be tracked per message. The action is taken once per inactive stretch, a channel that sees a
+// message again can be archived again later. Channels and categories can be exempted, and the
+// policy can post a weekly list of candidates for admins.
+use std::{collections::HashSet, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{ArchivePolicy, Db},
+    mentions::{self, Mentions},
+    watchdog, Error,
+};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const REPORT_INTERVAL_SECS: i64 = 7 * 24 * 60 * 60;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Longest inactivity a policy can wait for
+pub const MAX_INACTIVE_DAYS: i64 = 365;
+/// What a locked channel doesn't allow anymore
+pub const LOCKED: serenity::Permissions = serenity::Permissions::SEND_MESSAGES
+    .union(serenity::Permissions::SEND_MESSAGES_IN_THREADS)
+    .union(serenity::Permissions::CREATE_PUBLIC_THREADS)
+    .union(serenity::Permissions::CREATE_PRIVATE_THREADS);
+
+/// What happens to inactive channels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ArchiveAction {
+    /// Post a warning in the channel
+    #[name = "warn"]
+    Warn,
+    /// Move the channel to the archive category
+    #[name = "move"]
+    Move,
+    /// Make the channel read-only for everyone
+    #[name = "lock"]
+    Lock,
+}
+
+/// A channel that has been inactive long enough
+pub struct Candidate {
+    pub channel: serenity::GuildChannel,
+    /// Unix timestamp of the last message, or of the channel's creation if it has none
+    pub last_activity: i64,
+}
+
+/// Unix timestamp of the last message in a channel, or of its creation if it has none
+fn last_activity(channel: &serenity::GuildChannel) -> i64 {
+    channel
+        .last_message_id
+        .map(|m| m.created_at())
+        .unwrap_or_else(|| channel.id.created_at())
+        .unix_timestamp()
+}
+
+/// Text channels a policy applies to that had no messages for long enough, least recently
+/// active first
+pub async fn candidates(
+    http: &serenity::Http,
+    db: &Db,
+    policy: &ArchivePolicy,
+) -> Result<Vec<Candidate>, Error> {
+    let guild = serenity::GuildId(policy.guild_id as u64);
+    let channels = guild.channels(http).await?;
+    let exempt = db.archive_exemptions(guild).await?;
+
+    Ok(inactive(
+        policy,
+        channels.into_values(),
+        &exempt,
+        serenity::Timestamp::now().unix_timestamp(),
+    ))
+}
+
+fn inactive(
+    policy: &ArchivePolicy,
+    channels: impl Iterator<Item = serenity::GuildChannel>,
+    exempt: &HashSet<serenity::ChannelId>,
+    now: i64,
+) -> Vec<Candidate> {
+    let category = policy.category_id.map(|c| serenity::ChannelId(c as u64));
+
+    let mut candidates: Vec<_> = channels
+        .filter(|c| c.kind == serenity::ChannelType::Text)
+        .filter(|c| !exempt.contains(&c.id))
+        // Channels in the archive category are archived already
+        .filter(|c| {
+            c.parent_id
+                .is_none_or(|p| !exempt.contains(&p) && Some(p) != category)
+        })
+        .map(|channel| Candidate {
+            last_activity: last_activity(&channel),
+            channel,
+        })
+        .filter(|c| now - c.last_activity >= policy.inactive_days * SECONDS_PER_DAY)
+        .collect();
+    candidates.sort_by_key(|c| c.last_activity);
+
+    candidates
+}
+
+/// Starts the task that applies the archive policies and posts the weekly reports
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("archive", async move {
+        loop {
+            if let Err(e) = sweep(&ctx, &db).await {
+                tracing::warn!("Error applying archive policies: {}", e);
+            }
+
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+async fn sweep(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    for policy in db.archive_policies(None).await? {
+        if let Err(e) = sweep_guild(ctx, db, &policy).await {
+            tracing::warn!(
+                guild = policy.guild_id,
+                "Error applying archive policy: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn sweep_guild(
+    ctx: &serenity::Context,
+    db: &Db,
+    policy: &ArchivePolicy,
+) -> Result<(), Error> {
+    let guild = serenity::GuildId(policy.guild_id as u64);
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let channels = guild.channels(&ctx.http).await?;
+    let exempt = db.archive_exemptions(guild).await?;
+    let mut archived = db.archived_channels(guild).await?;
+
+    // Channels that saw a message since can be archived again once they go quiet
+    for (channel, at) in archived.clone() {
+        let active = channels.get(&channel).is_none_or(|c| last_activity(c) > at);
+        if active {
+            db.unset_archived(guild, channel).await?;
+            archived.remove(&channel);
+        }
+    }
+
+    let candidates = inactive(policy, channels.into_values(), &exempt, now);
+    let action = policy.action.parse().unwrap_or(ArchiveAction::Warn);
+
+    for candidate in &candidates {
+        let channel = &candidate.channel;
+        if archived.contains_key(&channel.id) {
+            continue;
+        }
+
+        match archive(ctx, policy, action, candidate).await {
+            // The notice is the last message now, it mustn't count as the channel being active
+            Ok(at) => db.set_archived(guild, channel.id, at).await?,
+            Err(e) => tracing::warn!(channel = channel.id.0, "Error archiving channel: {}", e),
+        }
+    }
+
+    if let Some(report) = policy.report_channel_id {
+        let due = policy
+            .last_report_at
+            .is_none_or(|at| now - at >= REPORT_INTERVAL_SECS);
+        if due {
+            db.set_archive_report_time(guild, now).await?;
+            post_report(ctx, serenity::ChannelId(report as u64), policy, &candidates).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Takes the action and posts a notice in the channel, returns when the notice was sent
+async fn archive(
+    ctx: &serenity::Context,
+    policy: &ArchivePolicy,
+    action: ArchiveAction,
+    candidate: &Candidate,
+) -> Result<i64, Error> {
+    let channel = &candidate.channel;
+
+    let notice = match action {
+        ArchiveAction::Warn => format!(
+            ":warning: This channel had no messages for {} days and may get archived.",
+            policy.inactive_days
+        ),
+        ArchiveAction::Move => {
+            let category = policy.category_id.ok_or("Archive policy has no category")?;
+            channel
+                .id
+                .edit(&ctx.http, |c| {
+                    c.category(serenity::ChannelId(category as u64))
+                })
+                .await?;
+            format!(
+                ":file_cabinet: This channel was archived after {} days without messages.",
+                policy.inactive_days
+            )
+        }
+        ArchiveAction::Lock => {
+            // Keeps whatever else the @everyone overwrite allows or denies
+            let everyone =
+                serenity::PermissionOverwriteType::Role(serenity::RoleId(channel.guild_id.0));
+            let (allow, deny) = channel
+                .permission_overwrites
+                .iter()
+                .find(|o| o.kind == everyone)
+                .map_or(
+                    (
+                        serenity::Permissions::empty(),
+                        serenity::Permissions::empty(),
+                    ),
+                    |o| (o.allow, o.deny),
+                );
+
+            channel
+                .create_permission(
+                    &ctx.http,
+                    &serenity::PermissionOverwrite {
+                        allow: allow - LOCKED,
+                        deny: deny | LOCKED,
+                        kind: everyone,
+                    },
+                )
+                .await?;
+            format!(
+                ":lock: This channel was made read-only after {} days without messages.",
+                policy.inactive_days
+            )
+        }
+    };
+
+    let notice = mentions::send_message(&ctx.http, channel.id, Mentions::Nothing, |m| {
+        m.content(notice)
+    })
+    .await?;
+
+    Ok(notice.timestamp.unix_timestamp())
+}
+
+/// Lists the current candidates in an embed, for the report and /archive candidates
+pub fn describe<'a>(
+    e: &'a mut serenity::CreateEmbed,
+    policy: &ArchivePolicy,
+    candidates: &[Candidate],
+) -> &'a mut serenity::CreateEmbed {
+    let mut list = String::new();
+    for candidate in candidates {
+        let line = format!(
+            "<#{}> last active <t:{}:R>\n",
+            candidate.channel.id.0, candidate.last_activity
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+    if list.is_empty() {
+        list = "Every channel is active.".to_string();
+    }
+
+    e.title("Archive candidates").description(list).footer(|f| {
+        f.text(format!(
+            "{} channels without messages for {} days",
+            candidates.len(),
+            policy.inactive_days
+        ))
+    })
+}
+
+async fn post_report(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    policy: &ArchivePolicy,
+    candidates: &[Candidate],
+) -> Result<(), Error> {
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| describe(e, policy, candidates))
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// Roles for new members
+// Members get the guild's `autorole` roles when they join. In servers with membership
+// screening they only get them once they accepted the rules, giving roles earlier would let
+// them skip the screening.
+use poise::serenity_prelude as serenity;
+
+use crate::{config::RoleList, Data, Error};
+
+/// Gives a member that just joined the autoroles, unless they still have to pass screening
+pub async fn handle_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    if member.pending {
+        return Ok(());
+    }
+
+    assign(ctx, data, member).await
+}
+
+/// Gives the autoroles to members that just passed membership screening
+pub async fn handle_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    old: Option<&serenity::Member>,
+    new: &serenity::Member,
+) -> Result<(), Error> {
+    // Without the old member we can't tell whether screening was passed just now
+    if !old.is_some_and(|old| old.pending) || new.pending {
+        return Ok(());
+    }
+
+    assign(ctx, data, new).await
+}
+
+async fn assign(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(member.guild_id).await?;
+    let roles = match config.role_lists.get(&RoleList::AutoRole) {
+        Some(roles) => roles,
+        None => return Ok(()),
+    };
+
+    for role in roles.iter().filter(|r| !member.roles.contains(r)) {
+        let result = ctx
+            .http
+            .add_member_role(
+                member.guild_id.0,
+                member.user.id.0,
+                role.0,
+                Some("Autorole"),
+            )
+            .await;
+
+        // Usually the role was moved above ours or we lost Manage Roles, the other roles may
+        // still work
+        if let Err(e) = result {
+            tracing::warn!(
+                guild = member.guild_id.0,
+                role = role.0,
+                member = member.user.id.0,
+                "Error giving autorole: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+// Ban list and tempbans
+// /ban with a duration schedules an `unban` job, so tempbans survive restarts like every other
+// job. A ban lifted early, by /unban after an appeal or by hand in Discord, cancels the job, and
+// at startup jobs are checked against the guild's actual ban list so bans lifted while the bot
+// was offline aren't lifted again later. Bans and unbans are numbered as cases together with
+// warnings.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Job},
+    jobs::{self, JobKind},
+    mentions::{self, Mentions},
+    watchdog, Data, Error,
+};
+
+/// Bans shown on one page of /bans list
+pub const PAGE_SIZE: usize = 10;
+
+/// Bans whose user name, ID or reason contain `query`, ignoring case, all of them without one
+pub fn search<'a>(bans: &'a [serenity::Ban], query: Option<&str>) -> Vec<&'a serenity::Ban> {
+    let query = match query.map(|q| q.trim().to_lowercase()) {
+        Some(query) if !query.is_empty() => query,
+        _ => return bans.iter().collect(),
+    };
+
+    bans.iter()
+        .filter(|ban| {
+            ban.user.tag().to_lowercase().contains(&query)
+                || ban.user.id.0.to_string() == query
+                || ban
+                    .reason
+                    .as_ref()
+                    .is_some_and(|r| r.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+/// Lifts a tempban, for `JobKind::Unban`
+pub async fn expire(ctx: &serenity::Context, db: &Db, job: &Job) -> Result<(), Error> {
+    let guild = serenity::GuildId(job.guild_id as u64);
+    let user = serenity::UserId(job.user_id as u64);
+    let reason = "Tempban expired";
+
+    let result = ctx.http.remove_ban(guild.0, user.0, Some(reason)).await;
+    match result {
+        Ok(()) => {}
+        // Unbanned already, the ban removal event should have cancelled the job
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+        {
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let bot = ctx.cache.current_user_id();
+    let case = db.add_case(guild, "unban", user, bot, reason).await?;
+    tracing::info!(guild = guild.0, user = user.0, case, "Tempban expired");
+
+    // Jobs run without the guild config cache, so this reads the log channel itself
+    let channel = match db.load_guild_config(guild).await?.log_channel {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title("Member unbanned")
+                .field("User", format!("<@{}> ({})", user.0, user.0), true)
+                .field("Banned by", format!("<@{}>", job.target_id), true)
+                .field("Reason", reason, false)
+                .field("Details", format!("Case #{}", case), false)
+                .timestamp(serenity::Timestamp::now())
+        })
+    })
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Error posting to log channel: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Cancels the tempban of a user whose ban was lifted
+pub async fn handle_unban(
+    data: &Data,
+    guild: serenity::GuildId,
+    user: &serenity::User,
+) -> Result<(), Error> {
+    jobs::take(&data.db, JobKind::Unban, guild, user.id, None).await?;
+    Ok(())
+}
+
+/// Starts the task that cancels tempbans lifted while the bot was offline
+pub fn spawn_reconcile(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("tempban reconcile", async move {
+        if let Err(e) = reconcile(&ctx, &db).await {
+            tracing::warn!("Error reconciling tempbans: {}", e);
+        }
+    });
+}
+
+async fn reconcile(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let mut tempbans = db.jobs_of_kind(JobKind::Unban.name()).await?;
+    tempbans.sort_unstable_by_key(|job| job.guild_id);
+
+    for guild_tempbans in tempbans.chunk_by(|a, b| a.guild_id == b.guild_id) {
+        let guild = serenity::GuildId(guild_tempbans[0].guild_id as u64);
+        // Up to 1000 bans come in one request, enough for all but the biggest servers
+        let banned = match guild.bans(&ctx.http).await {
+            Ok(bans) => bans,
+            // Not in the guild anymore or missing permissions, the jobs fail on their own
+            Err(e) => {
+                tracing::warn!(guild = guild.0, "Error fetching bans: {}", e);
+                continue;
+            }
+        };
+
+        for job in guild_tempbans {
+            if !banned.iter().any(|b| b.user.id.0 == job.user_id as u64) {
+                tracing::info!(
+                    guild = guild.0,
+                    user = job.user_id,
+                    "Cancelling tempban lifted while offline"
+                );
+                db.delete_job(job.id).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ban(id: u64, name: &str, reason: Option<&str>) -> serenity::Ban {
+        serde_json::from_value(serde_json::json!({
+            "reason": reason,
+            "user": {
+                "id": id.to_string(),
+                "username": name,
+                "discriminator": "0001",
+                "avatar": null
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn bans_are_searched_by_name_id_and_reason() {
+        let bans = [
+            ban(1, "Raider", Some("Raid on 2024-01-01")),
+            ban(22, "spammer", Some("Scam links")),
+            ban(3, "quiet", None),
+        ];
+        let ids = |query| {
+            search(&bans, query)
+                .iter()
+                .map(|b| b.user.id.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(None), [1, 22, 3]);
+        assert_eq!(ids(Some("  ")), [1, 22, 3]);
+        assert_eq!(ids(Some("RAID")), [1]);
+        assert_eq!(ids(Some("scam")), [22]);
+        assert_eq!(ids(Some("22")), [22]);
+        // IDs match whole, not every ID with a 2 in it
+        assert_eq!(ids(Some("2")), [1]);
+        assert!(ids(Some("nobody")).is_empty());
+    }
+}
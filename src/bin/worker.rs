@@ -0,0 +1,4 @@
+#[tokio::main]
+async fn main() {
+    discordbot_but_rust::run_worker().await;
+}
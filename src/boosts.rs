@@ -0,0 +1,101 @@
+// Server boosts
+// Members who start boosting are thanked in the boost channel. While they boost they get a
+// bigger /daily and can give themselves a colour role with /boostcolour, which is deleted
+// again when they stop. Boosts are noticed from member updates: Discord sets when a member
+// started boosting and clears it when they stop.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Extra coins boosters get from /daily
+pub const DAILY_BONUS: i64 = 100;
+// Without the member from before an update, a boost this recent is taken as new
+const NEW_BOOST: Duration = Duration::from_secs(60);
+
+/// Whether a member update is someone starting to boost, given when they started before and
+/// after it. `before` is None if we didn't have the member cached.
+pub fn started_boosting(before: Option<Option<i64>>, after: Option<i64>, now: i64) -> bool {
+    match (before, after) {
+        (Some(before), Some(after)) => before != Some(after),
+        (None, Some(after)) => now - after < NEW_BOOST.as_secs() as i64,
+        (_, None) => false,
+    }
+}
+
+/// Thanks new boosters and takes the perks of members who stopped boosting
+pub async fn handle_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    old: Option<&serenity::Member>,
+    new: &serenity::Member,
+) -> Result<(), Error> {
+    let after = new.premium_since.map(|t| t.unix_timestamp());
+
+    if after.is_none() {
+        if let Some(role) = data
+            .db
+            .delete_booster_role(new.guild_id, new.user.id)
+            .await?
+        {
+            match new.guild_id.delete_role(&ctx.http, role).await {
+                Ok(()) => {}
+                // Deleted by hand already, which is fine
+                Err(serenity::Error::Http(e))
+                    if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        return Ok(());
+    }
+
+    let before = old.map(|m| m.premium_since.map(|t| t.unix_timestamp()));
+    let now = serenity::Timestamp::now().unix_timestamp();
+    if !started_boosting(before, after, now) {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(new.guild_id).await?;
+    let channel = match config.boost_channel {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    mentions::send_message(&ctx.http, channel, Mentions::User(new.user.id), |m| {
+        m.content(format!("<@{}>", new.user.id.0)).embed(|e| {
+            e.title("Thanks for boosting!")
+                .description(format!(
+                    "<@{}> just boosted the server :sparkles:\n\
+                    Boosters get {} more coins from /daily, and their own colour with \
+                    /boostcolour.",
+                    new.user.id.0, DAILY_BONUS
+                ))
+                .thumbnail(new.face())
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_new_boosts_are_thanked() {
+        // Cached before and after
+        assert!(started_boosting(Some(None), Some(100), 1000));
+        assert!(!started_boosting(Some(Some(100)), Some(100), 1000));
+        // Boosting again after stopping
+        assert!(started_boosting(Some(Some(100)), Some(500), 1000));
+        assert!(!started_boosting(Some(Some(100)), None, 1000));
+
+        // Not cached, so only recent boosts count
+        assert!(started_boosting(None, Some(990), 1000));
+        assert!(!started_boosting(None, Some(100), 1000));
+    }
+}
@@ -0,0 +1,125 @@
+// Posts our server and shard counts to bot list sites
+// Each list is enabled by setting its API token in the environment:
+// TOPGG_TOKEN for top.gg and DBOTS_TOKEN for discord.bots.gg.
+// Set BOTLISTS_DRY_RUN to log the requests instead of sending them.
+use std::{env, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use serde_json::{json, Value};
+
+use crate::{
+    circuit::CircuitBreaker,
+    retry::{report_failure, retry, RetryPolicy},
+    watchdog,
+};
+
+// How often the counts are posted
+const POST_INTERVAL: Duration = Duration::from_secs(30 * 60);
+// A hanging list would stop the counts from being posted to every list
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct BotList {
+    name: &'static str,
+    url: String,
+    token: String,
+    body: fn(usize, u64) -> Value,
+    breaker: Arc<CircuitBreaker>,
+}
+
+/// Starts the background task if at least one bot list is configured
+/// Returns the circuit breakers of the configured lists so their health can be reported
+pub fn spawn(ctx: serenity::Context, bot_id: serenity::UserId) -> Vec<Arc<CircuitBreaker>> {
+    let lists = configured_lists(bot_id);
+    let breakers = lists.iter().map(|l| Arc::clone(&l.breaker)).collect();
+    if lists.is_empty() {
+        return breakers;
+    }
+
+    let dry_run = env::var("BOTLISTS_DRY_RUN").is_ok();
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build bot list HTTP client");
+
+    watchdog::spawn("bot lists", async move {
+        loop {
+            let guilds = ctx.cache.guild_count();
+            let shards = ctx.cache.shard_count();
+
+            for list in &lists {
+                let body = (list.body)(guilds, shards);
+
+                if dry_run {
+                    tracing::info!(
+                        list = list.name,
+                        "Dry run, would POST {} to {}",
+                        body,
+                        list.url
+                    );
+                    continue;
+                }
+
+                // Skip lists that keep failing until their cooldown is over
+                if !list.breaker.allow() {
+                    continue;
+                }
+
+                let result = retry(&RetryPolicy::DEFAULT, || post(&client, list, &body)).await;
+                match result {
+                    Ok(_) => list.breaker.record_success(),
+                    Err(e) => {
+                        list.breaker.record_failure();
+                        let job = format!("Posting stats to {}", list.name);
+                        report_failure(&ctx.http, &job, e).await;
+                    }
+                }
+            }
+
+            tokio::time::sleep(POST_INTERVAL).await;
+        }
+    });
+
+    breakers
+}
+
+fn configured_lists(bot_id: serenity::UserId) -> Vec<BotList> {
+    let mut lists = Vec::new();
+
+    if let Ok(token) = env::var("TOPGG_TOKEN") {
+        lists.push(BotList {
+            name: "top.gg",
+            url: format!("https://top.gg/api/bots/{}/stats", bot_id.0),
+            token,
+            body: |guilds, shards| json!({ "server_count": guilds, "shard_count": shards }),
+            breaker: Arc::new(CircuitBreaker::new("top.gg")),
+        });
+    }
+
+    if let Ok(token) = env::var("DBOTS_TOKEN") {
+        lists.push(BotList {
+            name: "discord.bots.gg",
+            url: format!("https://discord.bots.gg/api/v1/bots/{}/stats", bot_id.0),
+            token,
+            body: |guilds, shards| json!({ "guildCount": guilds, "shardCount": shards }),
+            breaker: Arc::new(CircuitBreaker::new("discord.bots.gg")),
+        });
+    }
+
+    lists
+}
+
+async fn post(
+    client: &reqwest::Client,
+    list: &BotList,
+    body: &Value,
+) -> Result<(), reqwest::Error> {
+    client
+        .post(&list.url)
+        .header("Authorization", &list.token)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+// Circuit breaker for external API integrations
+// After a number of consecutive failures the integration is marked degraded and calls
+// are refused straight away. Once the cooldown has passed a single probe call is let
+// through, if it succeeds the integration is healthy again. A probe that never reports back,
+// e.g. because its caller gave up on it, is replaced by a new one after PROBE_TIMEOUT.
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Consecutive failures before the circuit opens
+const FAILURE_THRESHOLD: u32 = 5;
+// How long the circuit stays open before a probe is allowed
+const COOLDOWN: Duration = Duration::from_secs(5 * 60);
+// How long a probe may take before another one is let through, longer than any request timeout
+const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+enum State {
+    Closed { failures: u32 },
+    Open { since: Instant },
+    HalfOpen { since: Instant },
+}
+
+pub struct CircuitBreaker {
+    name: &'static str,
+    cooldown: Duration,
+    probe_timeout: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            cooldown: COOLDOWN,
+            probe_timeout: PROBE_TIMEOUT,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Whether a call should be attempted right now
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { since } if since.elapsed() >= self.cooldown => {
+                // Let exactly one probe through
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            State::HalfOpen { since } if since.elapsed() >= self.probe_timeout => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if let State::HalfOpen { .. } = *state {
+            tracing::info!(integration = self.name, "Integration recovered");
+        }
+        *state = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            // A failed probe opens the circuit again right away
+            State::HalfOpen { .. } => FAILURE_THRESHOLD,
+            State::Open { .. } => return,
+        };
+
+        if failures >= FAILURE_THRESHOLD {
+            tracing::warn!(
+                integration = self.name,
+                "Integration is degraded, pausing calls for {}s",
+                self.cooldown.as_secs()
+            );
+            *state = State::Open {
+                since: Instant::now(),
+            };
+        } else {
+            *state = State::Closed { failures };
+        }
+    }
+}
+
+impl fmt::Display for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+
+        match *state {
+            State::Closed { failures: 0 } => write!(f, "{}: healthy", self.name),
+            State::Closed { failures } => {
+                write!(f, "{}: healthy ({} recent failures)", self.name, failures)
+            }
+            State::Open { since } => write!(
+                f,
+                "{}: degraded for {}s",
+                self.name,
+                since.elapsed().as_secs()
+            ),
+            State::HalfOpen { .. } => write!(f, "{}: probing", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(cooldown: Duration, probe_timeout: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            cooldown,
+            probe_timeout,
+            ..CircuitBreaker::new("test")
+        }
+    }
+
+    fn open(breaker: &CircuitBreaker) {
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(breaker.allow());
+            breaker.record_failure();
+        }
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker(COOLDOWN, PROBE_TIMEOUT);
+
+        for _ in 1..FAILURE_THRESHOLD {
+            breaker.record_failure();
+        }
+        // A success in between starts the count again
+        breaker.record_success();
+        for _ in 1..FAILURE_THRESHOLD {
+            breaker.record_failure();
+        }
+        assert!(breaker.allow());
+
+        breaker.record_failure();
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn successful_probe_closes_the_circuit() {
+        let breaker = breaker(Duration::ZERO, PROBE_TIMEOUT);
+        open(&breaker);
+
+        assert!(breaker.allow());
+        // Only one probe at a time
+        assert!(!breaker.allow());
+
+        breaker.record_success();
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn failed_probe_opens_the_circuit_again() {
+        let breaker = breaker(Duration::from_secs(60), PROBE_TIMEOUT);
+        open(&breaker);
+        assert!(!breaker.allow());
+
+        *breaker.state.lock().unwrap() = State::HalfOpen {
+            since: Instant::now(),
+        };
+        breaker.record_failure();
+        assert!(matches!(*breaker.state.lock().unwrap(), State::Open { .. }));
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn abandoned_probes_are_replaced() {
+        let breaker = breaker(Duration::ZERO, Duration::ZERO);
+        open(&breaker);
+
+        // Neither probe reports back
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+
+        breaker.record_success();
+        assert!(matches!(
+            *breaker.state.lock().unwrap(),
+            State::Closed { failures: 0 }
+        ));
+    }
+}
@@ -0,0 +1,432 @@
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use super::roles::check_role;
+use crate::{
+    applications::{
+        self, MAX_ANSWER_LENGTH, MAX_QUESTIONS, MAX_QUESTION_LENGTH, QUESTIONS_PER_PAGE,
+    },
+    db::ApplicationForm,
+    mentions::Mentions,
+    Context, Error,
+};
+
+const MAX_FORM_NAME_LENGTH: usize = 32;
+// How long a page of questions or the button to the next page stays open
+const FORM_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Set up application forms members fill in to get a role
+///
+/// Usage: `/application create <name> <role> <review_channel> <questions>`, `/application delete <name>` or `/application list`
+/// Example: `/application create staff @Staff #applications How old are you? | Why do you want to help?`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("create", "delete", "list"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn application(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Creates a form, or replaces the one with the same name
+///
+/// Usage: `/application create <name> <role> <review_channel> <questions>`
+/// Example: `/application create staff @Staff #applications How old are you? | Why do you want to help?`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_ROLES")]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "Name members apply with"] name: String,
+    #[description = "Role accepted applicants get"] role: serenity::Role,
+    #[description = "Channel applications are posted in"]
+    #[channel_types("Text")]
+    review_channel: serenity::GuildChannel,
+    #[description = "Questions separated by |"] questions: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let name = match normalize_name(&name) {
+        Some(name) => name,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Form names can be up to {} characters without spaces.",
+                    MAX_FORM_NAME_LENGTH
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let questions: Vec<String> = questions
+        .split('|')
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .collect();
+    if questions.is_empty()
+        || questions.len() > MAX_QUESTIONS
+        || questions
+            .iter()
+            .any(|q| q.chars().count() > MAX_QUESTION_LENGTH)
+    {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Forms can have up to {} questions of up to {} characters, separated by `|`.",
+                MAX_QUESTIONS, MAX_QUESTION_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Whoever can review applications could otherwise hand out roles above their own
+    if let Some(refusal) = check_role(ctx, &role).await {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let form = ApplicationForm {
+        name,
+        role: role.id,
+        review_channel: review_channel.id,
+        questions,
+    };
+    ctx.data().db.save_application_form(guild, &form).await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Members can now apply for <@&{}> with `/apply {}`, applications are posted in <#{}>",
+            form.role.0, form.name, form.review_channel.0
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes a form, applications that weren't reviewed yet can't be anymore
+///
+/// Usage: `/application delete <name>`
+/// Example: `/application delete staff`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_ROLES")]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "Name of the form"]
+    #[autocomplete = "autocomplete_form"]
+    name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let deleted = match normalize_name(&name) {
+        Some(name) => ctx.data().db.delete_application_form(guild, &name).await?,
+        None => false,
+    };
+
+    let response = if deleted {
+        format!(":white_check_mark: Deleted form `{}`", name)
+    } else {
+        format!(":x: There is no form called `{}`.", name)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the application forms of this server
+///
+/// Usage: `/application list`
+/// Example: `/application list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_ROLES")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let forms = ctx.data().db.application_forms(guild).await?;
+
+    if forms.is_empty() {
+        ctx.send(|m| m.content("There are no application forms.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let list = forms
+        .iter()
+        .map(|f| {
+            format!(
+                "`{}`: <@&{}>, {} questions, reviewed in <#{}>",
+                f.name,
+                f.role.0,
+                f.questions.len(),
+                f.review_channel.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Application forms").description(list))
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Applies for a role by filling in a form
+///
+/// Usage: `/apply <form>`
+/// Example: `/apply staff`
+#[poise::command(slash_command, guild_only)]
+async fn apply(
+    ctx: Context<'_>,
+    #[description = "Form to fill in"]
+    #[autocomplete = "autocomplete_form"]
+    form: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let app_ctx = match ctx {
+        poise::Context::Application(app_ctx) => app_ctx,
+        poise::Context::Prefix(_) => return Err("Forms need a slash command".into()),
+    };
+
+    let form = match normalize_name(&form) {
+        Some(name) => ctx.data().db.application_form(guild, &name).await?,
+        None => None,
+    };
+    let form = match form {
+        Some(form) => form,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: There is no form with that name, see the suggestions.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let has_role = ctx
+        .author_member()
+        .await
+        .is_some_and(|m| m.roles.contains(&form.role));
+    if has_role {
+        ctx.send(|m| {
+            m.content(format!(":x: You already have <@&{}>.", form.role.0))
+                .allowed_mentions(|a| Mentions::Nothing.apply(a))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+    if ctx
+        .data()
+        .db
+        .has_pending_application(guild, &form.name, ctx.author().id)
+        .await?
+    {
+        ctx.send(|m| {
+            m.content(":x: You already applied, please wait until your application is reviewed.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let discord = ctx.discord();
+    let pages: Vec<_> = form.questions.chunks(QUESTIONS_PER_PAGE).collect();
+    let mut answers = Vec::new();
+    // The button that opens the next page, Discord doesn't allow a modal right after another
+    let mut next_button: Option<std::sync::Arc<serenity::MessageComponentInteraction>> = None;
+
+    for (page, questions) in pages.iter().enumerate() {
+        let modal_id = format!("{}-page-{}", ctx.id(), page);
+        let title = if pages.len() > 1 {
+            format!("{} application ({}/{})", form.name, page + 1, pages.len())
+        } else {
+            format!("{} application", form.name)
+        };
+        let response = modal(&modal_id, &title, questions);
+
+        match &next_button {
+            None => {
+                app_ctx
+                    .interaction
+                    .unwrap()
+                    .create_interaction_response(discord, |r| {
+                        *r = response;
+                        r
+                    })
+                    .await?;
+                app_ctx
+                    .has_sent_initial_response
+                    .store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+            Some(button) => {
+                button
+                    .create_interaction_response(discord, |r| {
+                        *r = response;
+                        r
+                    })
+                    .await?;
+            }
+        }
+
+        let submission = serenity::CollectModalInteraction::new(&discord.shard)
+            .author_id(ctx.author().id)
+            .filter(move |m| m.data.custom_id == modal_id)
+            .timeout(FORM_TIMEOUT)
+            .await;
+        // Closing the modal can't be noticed, so timing out is the only way to give up
+        let submission = match submission {
+            Some(submission) => submission,
+            None => return Ok(()),
+        };
+
+        for row in &submission.data.components {
+            if let Some(serenity::ActionRowComponent::InputText(input)) = row.components.first() {
+                answers.push(input.value.trim().to_string());
+            }
+        }
+
+        // The first page was opened by the command, later ones by the button on our message
+        let kind = if page == 0 {
+            serenity::InteractionResponseType::ChannelMessageWithSource
+        } else {
+            serenity::InteractionResponseType::UpdateMessage
+        };
+
+        if page + 1 < pages.len() {
+            let button_id = format!("{}-next-{}", ctx.id(), page);
+            submission
+                .create_interaction_response(discord, |r| {
+                    r.kind(kind).interaction_response_data(|d| {
+                        d.content(format!(
+                            "Page {} of {} done, your answers are kept until you finish.",
+                            page + 1,
+                            pages.len()
+                        ))
+                        .components(|c| {
+                            c.create_action_row(|r| {
+                                r.create_button(|b| {
+                                    b.custom_id(&button_id)
+                                        .label("Continue")
+                                        .style(serenity::ButtonStyle::Primary)
+                                })
+                            })
+                        })
+                        .ephemeral(true)
+                    })
+                })
+                .await?;
+
+            next_button = serenity::CollectComponentInteraction::new(discord)
+                .author_id(ctx.author().id)
+                .filter(move |i| i.data.custom_id == button_id)
+                .timeout(FORM_TIMEOUT)
+                .await;
+            if next_button.is_none() {
+                submission
+                    .edit_original_interaction_response(discord, |m| {
+                        m.content(":x: Timed out, run /apply again to start over.")
+                            .components(|c| c)
+                    })
+                    .await?;
+                return Ok(());
+            }
+            continue;
+        }
+
+        let result =
+            applications::submit(discord, ctx.data(), guild, &form, ctx.author(), &answers).await;
+        let content = match result {
+            Ok(_) => {
+                ":white_check_mark: Your application was sent, you'll get a DM once it's reviewed."
+            }
+            Err(e) => {
+                tracing::warn!(
+                    guild = guild.0,
+                    form = %form.name,
+                    "Error submitting application: {}",
+                    e
+                );
+                ":x: Your application couldn't be sent, please tell a moderator."
+            }
+        };
+        submission
+            .create_interaction_response(discord, |r| {
+                r.kind(kind).interaction_response_data(|d| {
+                    d.content(content).components(|c| c).ephemeral(true)
+                })
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn modal(
+    custom_id: &str,
+    title: &str,
+    questions: &[String],
+) -> serenity::CreateInteractionResponse<'static> {
+    let mut response = serenity::CreateInteractionResponse::default();
+    response
+        .kind(serenity::InteractionResponseType::Modal)
+        .interaction_response_data(|d| {
+            d.custom_id(custom_id).title(title).components(|c| {
+                for (i, question) in questions.iter().enumerate() {
+                    c.create_action_row(|r| {
+                        r.create_input_text(|t| {
+                            t.custom_id(i)
+                                .label(question)
+                                .style(serenity::InputTextStyle::Paragraph)
+                                .max_length(MAX_ANSWER_LENGTH)
+                                .required(true)
+                        })
+                    });
+                }
+                c
+            })
+        });
+
+    response
+}
+
+async fn autocomplete_form<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let forms = match ctx.guild_id() {
+        Some(guild) => ctx
+            .data()
+            .db
+            .application_forms(guild)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    forms
+        .into_iter()
+        .map(|f| f.name)
+        .filter(move |name| name.starts_with(&partial))
+        // Discord shows at most 25 choices
+        .take(25)
+}
+
+fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_FORM_NAME_LENGTH
+        && !name.contains(char::is_whitespace);
+
+    valid.then_some(name)
+}
+
+command_list!["Applications": application, apply];
@@ -0,0 +1,813 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    duration,
+    modlog::{self, Action},
+    reviewqueue,
+    spam::{Rule, SpamAction, SpamRule},
+    toxicity::Category,
+    Context, Error,
+};
+
+// Flags listed by /review pending, enough to fit an embed
+const MAX_PENDING: u32 = 15;
+// Spoiler rules can't last longer than this
+const MAX_SPOILER_RULE: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Blocks the images in this message and deletes it
+///
+/// Usage: right click a message, then Apps > Block image
+/// Example: `Block image` on a message with a scam screenshot
+#[poise::command(
+    context_menu_command = "Block image",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn block_image(ctx: Context<'_>, message: serenity::Message) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    ctx.defer_ephemeral().await?;
+
+    let blocklist = &ctx.data().image_blocklist;
+    let hashes = blocklist.hash_attachments(&message).await;
+    if hashes.is_empty() {
+        ctx.say(":x: That message has no images I can read.")
+            .await?;
+        return Ok(());
+    }
+
+    let mut added = 0;
+    for hash in &hashes {
+        if blocklist.block(guild, *hash, ctx.author().id).await? {
+            added += 1;
+        }
+    }
+
+    message.delete(ctx.discord()).await?;
+
+    let hashes = hashes
+        .iter()
+        .map(|h| format!("`{:016x}`", h))
+        .collect::<Vec<_>>()
+        .join(", ");
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "had an image blocked",
+            target: &message.author,
+            reason: "Image added to the blocklist",
+            details: Some(format!("Hashes: {}", hashes)),
+        },
+    )
+    .await?;
+
+    ctx.say(format!(
+        ":white_check_mark: Blocked {} new image(s) and deleted the message: {}",
+        added, hashes
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Manage the blocked images of this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("list", "remove"),
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn imageblock(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Lists the hashes of all blocked images
+///
+/// Usage: `/imageblock list`
+/// Example: `/imageblock list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_MESSAGES")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let hashes = ctx.data().image_blocklist.get(guild).await?;
+
+    if hashes.is_empty() {
+        ctx.send(|m| m.content("No images are blocked.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for hash in hashes.iter() {
+        let line = format!("`{:016x}`\n", hash);
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Blocked images")
+                .description(list)
+                .footer(|f| f.text(format!("{} in total", hashes.len())))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Unblocks an image by its hash
+///
+/// Usage: `/imageblock remove <hash>`
+/// Example: `/imageblock remove 8f3a1c0e9b2d4471`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_MESSAGES")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Hash from /imageblock list"] hash: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = match u64::from_str_radix(hash.trim().trim_matches('`'), 16) {
+        Ok(parsed) => {
+            if ctx.data().image_blocklist.unblock(guild, parsed).await? {
+                format!(":white_check_mark: Unblocked `{:016x}`", parsed)
+            } else {
+                format!(":x: `{:016x}` isn't blocked.", parsed)
+            }
+        }
+        Err(_) => ":x: That doesn't look like an image hash.".to_string(),
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Manage the spoiler rules of this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("spoiler_add", "spoiler_remove", "spoiler_list"),
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn spoiler(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Makes messages mentioning a keyword use spoiler tags for a while
+///
+/// Usage: `/spoiler add <name> <keywords> <duration> [channels]`
+/// Example: `/spoiler add "Show finale" finale, ending 1w #anime #general`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES | MANAGE_WEBHOOKS"
+)]
+async fn spoiler_add(
+    ctx: Context<'_>,
+    #[description = "Name of the rule, e.g. the show"] name: String,
+    #[description = "Keywords separated by commas"] keywords: String,
+    #[description = "How long the rule lasts, e.g. 3d or 1w"] duration: String,
+    #[description = "Channels the rule applies in, all of them if empty"] channels: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let keywords: Vec<String> = keywords
+        .split(',')
+        .map(|k| k.trim().to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect();
+    if keywords.is_empty() {
+        ctx.send(|m| {
+            m.content(":x: Please give at least one keyword.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let length = match duration::parse(&duration) {
+        Some(length) if length <= MAX_SPOILER_RULE => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `12h`, `3d` or `1w`, up to a year.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut channel_ids = Vec::new();
+    for mention in channels.as_deref().unwrap_or_default().split_whitespace() {
+        match mention
+            .strip_prefix("<#")
+            .and_then(|m| m.strip_suffix('>'))
+            .unwrap_or(mention)
+            .parse()
+        {
+            Ok(id) => channel_ids.push(serenity::ChannelId(id)),
+            Err(_) => {
+                ctx.send(|m| {
+                    m.content(format!(":x: `{}` doesn't look like a channel.", mention))
+                        .ephemeral(true)
+                })
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let expires_at = serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64;
+    let id = ctx
+        .data()
+        .spoiler_rules
+        .add(guild, name.trim(), &keywords, &channel_ids, expires_at)
+        .await?;
+
+    let scope = if channel_ids.is_empty() {
+        "every channel".to_string()
+    } else {
+        channel_ids
+            .iter()
+            .map(|c| format!("<#{}>", c.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Rule #{} spoilers `{}` in {} until <t:{}:f>",
+            id,
+            keywords.join("`, `"),
+            scope,
+            expires_at
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a spoiler rule
+///
+/// Usage: `/spoiler remove <id>`
+/// Example: `/spoiler remove 3`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn spoiler_remove(
+    ctx: Context<'_>,
+    #[description = "ID from /spoiler list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().spoiler_rules.remove(guild, id).await? {
+        format!(":white_check_mark: Removed spoiler rule #{}", id)
+    } else {
+        format!(":x: There is no spoiler rule #{}.", id)
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Lists the active spoiler rules
+///
+/// Usage: `/spoiler list`
+/// Example: `/spoiler list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn spoiler_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let rules = ctx.data().spoiler_rules.get(guild).await?;
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let mut list = String::new();
+    for rule in rules.iter().filter(|r| r.expires_at > now) {
+        let scope = if rule.channels.is_empty() {
+            "everywhere".to_string()
+        } else {
+            rule.channels
+                .iter()
+                .map(|c| format!("<#{}>", c.0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let line = format!(
+            "**#{}** {}: `{}` in {}, ends <t:{}:R>\n",
+            rule.id,
+            rule.name,
+            rule.keywords.join("`, `"),
+            scope,
+            rule.expires_at
+        );
+
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    if list.is_empty() {
+        ctx.send(|m| m.content("There are no spoiler rules.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Spoiler rules").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Delete spam and warn or time out whoever sent it
+///
+/// Usage: `/antispam set <rule> <threshold> <action> [timeout]`, `/antispam remove <rule>` or `/antispam list`
+/// Example: `/antispam set flood 6 timeout 30m`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("antispam_set", "antispam_remove", "antispam_list"),
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn antispam(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets an anti-spam rule, replacing the current rule of the same kind
+///
+/// Usage: `/antispam set <rule> <threshold> <action> [timeout]`
+/// Example: `/antispam set mentions 5 warn` or `/antispam set caps 80 delete`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "set",
+    required_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES | MODERATE_MEMBERS"
+)]
+async fn antispam_set(
+    ctx: Context<'_>,
+    #[description = "What to look for"] rule: SpamRule,
+    #[description = "Messages, repeats, mentions, links or percent of caps that count as spam"]
+    threshold: u32,
+    #[description = "What to do with spam"] action: SpamAction,
+    #[description = "How long timeouts last, like 10m or 1h"] timeout: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let (min, max) = rule.limits();
+    if !(min..=max).contains(&threshold) {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: The threshold for {} has to be between {} and {}.",
+                rule.name(),
+                min,
+                max
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let timeout = match timeout.as_deref().map(duration::parse) {
+        None => None,
+        Some(Some(timeout)) if timeout <= duration::MAX_TIMEOUT => Some(timeout),
+        Some(_) => {
+            ctx.send(|m| {
+                m.content(":x: Please give a timeout like `10m` or `1h`, up to 28 days.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let rule = Rule {
+        kind: rule,
+        threshold,
+        action,
+        timeout,
+    };
+    ctx.data().spam_filter.set(guild, &rule).await?;
+
+    let action = match action {
+        SpamAction::Delete => "deleted".to_string(),
+        SpamAction::Warn => "deleted and warned".to_string(),
+        SpamAction::Timeout => format!(
+            "deleted and timed out for {}",
+            duration::format(rule.timeout())
+        ),
+        SpamAction::Review => "flagged for review".to_string(),
+    };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Messages with {} are now {}",
+            rule.kind.describe(threshold),
+            action
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes an anti-spam rule
+///
+/// Usage: `/antispam remove <rule>`
+/// Example: `/antispam remove caps`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn antispam_remove(
+    ctx: Context<'_>,
+    #[description = "Rule to remove"] rule: SpamRule,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().spam_filter.remove(guild, rule).await? {
+        format!(":white_check_mark: Removed the {} rule", rule.name())
+    } else {
+        format!(":x: There is no {} rule.", rule.name())
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Lists the anti-spam rules in the order they're checked
+///
+/// Usage: `/antispam list`
+/// Example: `/antispam list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn antispam_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let rules = ctx.data().spam_filter.get(guild).await?;
+
+    if rules.is_empty() {
+        ctx.send(|m| m.content("There are no anti-spam rules.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let list = rules
+        .iter()
+        .map(|rule| {
+            let action = match rule.action {
+                SpamAction::Timeout => format!("timeout for {}", duration::format(rule.timeout())),
+                action => action.name().to_string(),
+            };
+            format!(
+                "**{}**: {} → {}",
+                rule.kind.name(),
+                rule.kind.describe(rule.threshold),
+                action
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Anti-spam rules")
+                .description(list)
+                .footer(|f| f.text("Roles in the automod_exempt list are never checked"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Flag messages that score as toxic for moderators to review
+///
+/// Usage: `/toxicity set <category> <threshold>`, `/toxicity remove <category>` or `/toxicity list`
+/// Example: `/toxicity set insult 90`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("toxicity_set", "toxicity_remove", "toxicity_list"),
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn toxicity(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Scores messages for a category and flags them from a threshold on
+///
+/// Usage: `/toxicity set <category> <threshold>`
+/// Example: `/toxicity set threat 80`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "set",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn toxicity_set(
+    ctx: Context<'_>,
+    #[description = "What to score messages for"] category: Category,
+    #[description = "Score in percent from which messages are flagged"]
+    #[min = 1]
+    #[max = 100]
+    threshold: u32,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if ctx.data().toxicity.is_none() {
+        ctx.send(|m| {
+            m.content(":x: Toxicity scoring isn't set up by the bot owner.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let threshold = threshold.clamp(1, 100);
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            c.toxicity_thresholds.insert(category, threshold);
+        })
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Messages scoring {}% or more for {} are flagged in the review channel",
+            threshold,
+            category.name()
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops scoring messages for a category
+///
+/// Usage: `/toxicity remove <category>`
+/// Example: `/toxicity remove profanity`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn toxicity_remove(
+    ctx: Context<'_>,
+    #[description = "Category to stop scoring"] category: Category,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let config = ctx.data().guild_configs.get(guild).await?;
+    if !config.toxicity_thresholds.contains_key(&category) {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Messages aren't scored for {}.",
+                category.name()
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            c.toxicity_thresholds.remove(&category);
+        })
+        .await?;
+
+    let response = format!(
+        ":white_check_mark: Messages aren't scored for {} anymore",
+        category.name()
+    );
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the categories messages are scored for
+///
+/// Usage: `/toxicity list`
+/// Example: `/toxicity list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn toxicity_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+
+    let list = Category::ALL
+        .iter()
+        .filter_map(|category| {
+            let threshold = config.toxicity_thresholds.get(category)?;
+            Some(format!("**{}**: from {}%", category.name(), threshold))
+        })
+        .collect::<Vec<_>>();
+    if list.is_empty() {
+        ctx.send(|m| {
+            m.content("Messages aren't scored for toxicity.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Toxicity scoring")
+                .description(list.join("\n"))
+                .footer(|f| f.text("Roles in the automod_exempt list are never scored"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// See what automod flagged and how often its flags were wrong
+///
+/// Usage: `/review stats [days]` or `/review pending`
+/// Example: `/review stats 7`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("review_stats", "review_pending"),
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn review(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows per rule how many messages were flagged and how many of them were false positives
+///
+/// Usage: `/review stats [days]`
+/// Example: `/review stats 7`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "stats",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn review_stats(
+    ctx: Context<'_>,
+    #[description = "How many days back to look, 30 if not given"]
+    #[min = 1]
+    #[max = 365]
+    days: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let days = days.unwrap_or(30).clamp(1, 365);
+    let since = serenity::Timestamp::now().unix_timestamp() - days as i64 * 24 * 60 * 60;
+    let db = &ctx.data().db;
+
+    let counts = db.automod_flag_counts(guild, since).await?;
+    if counts.is_empty() {
+        ctx.send(|m| {
+            m.content(format!("Nothing was flagged in the last {} days.", days))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let rules = reviewqueue::rule_stats(&counts)
+        .iter()
+        .map(|((source, rule), stats)| {
+            let false_positives = match stats.false_positive_rate() {
+                Some(rate) => format!("{}% false positives", rate),
+                None => "none reviewed".to_string(),
+            };
+            format!(
+                "**{}** {}: {} flagged, {} pending, {}",
+                source, rule, stats.flagged, stats.pending, false_positives
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let reviewers = db
+        .automod_flag_reviewers(guild, since)
+        .await?
+        .iter()
+        .take(10)
+        .map(|(user, count)| format!("<@{}>: {}", user.0, count))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Automod flags of the last {} days", days))
+                .description(rules.chars().take(4000).collect::<String>())
+                .field(
+                    "Reviewers",
+                    if reviewers.is_empty() {
+                        "Nobody yet"
+                    } else {
+                        &reviewers
+                    },
+                    false,
+                )
+                .footer(|f| f.text("Approved flags count as false positives"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the oldest flags nobody reviewed yet
+///
+/// Usage: `/review pending`
+/// Example: `/review pending`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "pending",
+    required_permissions = "MANAGE_MESSAGES"
+)]
+async fn review_pending(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let flags = ctx
+        .data()
+        .db
+        .pending_automod_flags(guild, MAX_PENDING)
+        .await?;
+
+    if flags.is_empty() {
+        ctx.send(|m| {
+            m.content(":white_check_mark: Every flag has been reviewed")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let list = flags
+        .iter()
+        .map(|flag| {
+            let link = match (flag.review_channel_id, flag.review_message_id) {
+                (Some(channel), Some(message)) => format!(
+                    "https://discord.com/channels/{}/{}/{}",
+                    guild.0, channel, message
+                ),
+                _ => format!(
+                    "https://discord.com/channels/{}/{}/{}",
+                    guild.0, flag.channel_id, flag.message_id
+                ),
+            };
+            format!(
+                "[#{}]({}) {} {} by <@{}>, <t:{}:R>",
+                flag.id, link, flag.source, flag.rule, flag.author_id, flag.created_at
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Pending flags").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Automod": block_image, imageblock, spoiler, antispam, toxicity, review];
@@ -0,0 +1,1309 @@
+use std::{collections::HashSet, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    archive::{self, ArchiveAction, MAX_INACTIVE_DAYS},
+    config::Feature,
+    duration, forums,
+    jobs::{self, JobKind},
+    mentions::Mentions,
+    passes, permissions, quiet,
+    statschannels::{self, Stat},
+    summaries, temproles, Context, Error,
+};
+
+const MAX_TEMPLATE_NAME_LENGTH: usize = 32;
+// How long the Apply button of /permtemplate apply works
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+// Passes longer than this are most likely typos, give a role instead
+const MAX_PASS: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Save a channel's permissions and copy them to other channels
+///
+/// Usage: `/permtemplate save <name> <channel>`, `/permtemplate apply <name> <channel> [channels...]`, `/permtemplate list` or `/permtemplate delete <name>`
+/// Example: `/permtemplate apply staff #mod-chat #mod-log`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("save", "apply", "list", "delete"),
+    required_permissions = "MANAGE_ROLES | MANAGE_CHANNELS",
+    default_member_permissions = "MANAGE_ROLES | MANAGE_CHANNELS"
+)]
+async fn permtemplate(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Saves the permission overwrites of a channel as a template
+///
+/// Usage: `/permtemplate save <name> <channel>`
+/// Example: `/permtemplate save staff #mod-chat`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES | MANAGE_CHANNELS"
+)]
+async fn save(
+    ctx: Context<'_>,
+    #[description = "Name of the template"] name: String,
+    #[description = "Channel to copy the permissions of"] channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let name = match normalize_name(&name) {
+        Some(name) => name,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Template names can be up to {} characters without spaces.",
+                    MAX_TEMPLATE_NAME_LENGTH
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Templates without rows don't exist, and would only reset channels anyway
+    if channel.permission_overwrites.is_empty() {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: <#{}> has no permission overwrites to save.",
+                channel.id.0
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data()
+        .db
+        .save_perm_template(guild, &name, &channel.permission_overwrites)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Saved the {} overwrites of <#{}> as `{}`",
+            channel.permission_overwrites.len(),
+            channel.id.0,
+            name
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Replaces the permission overwrites of channels with a template, after showing what changes
+///
+/// Usage: `/permtemplate apply <name> <channel> [channels...]`
+/// Example: `/permtemplate apply staff #mod-chat #mod-log`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES | MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_ROLES | MANAGE_CHANNELS"
+)]
+async fn apply(
+    ctx: Context<'_>,
+    #[description = "Name of the template"]
+    #[autocomplete = "autocomplete_template"]
+    name: String,
+    #[description = "Channel to apply it to"] channel: serenity::GuildChannel,
+    #[description = "Another channel"] channel2: Option<serenity::GuildChannel>,
+    #[description = "Another channel"] channel3: Option<serenity::GuildChannel>,
+    #[description = "Another channel"] channel4: Option<serenity::GuildChannel>,
+    #[description = "Another channel"] channel5: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let template = match normalize_name(&name) {
+        Some(name) => ctx.data().db.perm_template(guild, &name).await?,
+        None => None,
+    };
+    let template = match template {
+        Some(template) => template,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no template called `{}`.", name))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut channels: Vec<_> = [Some(channel), channel2, channel3, channel4, channel5]
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut seen = HashSet::new();
+    channels.retain(|c| seen.insert(c.id));
+
+    let preview = channels
+        .iter()
+        .map(|channel| {
+            format!(
+                "<#{}>: {}",
+                channel.id.0,
+                describe_diff(guild, &channel.permission_overwrites, &template)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let confirm_id = format!("{}-apply", ctx.id());
+    let cancel_id = format!("{}-cancel", ctx.id());
+    let reply = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.title(format!("Apply `{}`?", name))
+                    .description(&preview)
+                    .footer(|f| f.text("+ added, ~ changed, - removed"))
+            })
+            .components(|c| {
+                c.create_action_row(|r| {
+                    r.create_button(|b| {
+                        b.custom_id(&confirm_id)
+                            .label("Apply")
+                            .style(serenity::ButtonStyle::Danger)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(&cancel_id)
+                            .label("Cancel")
+                            .style(serenity::ButtonStyle::Secondary)
+                    })
+                })
+            })
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+        })
+        .await?;
+
+    let interaction = serenity::CollectComponentInteraction::new(ctx.discord())
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .timeout(CONFIRM_TIMEOUT)
+        .filter({
+            let confirm_id = confirm_id.clone();
+            move |i| i.data.custom_id == confirm_id || i.data.custom_id == cancel_id
+        })
+        .await;
+
+    let interaction = match interaction {
+        Some(interaction) => interaction,
+        None => {
+            reply
+                .edit(ctx, |m| {
+                    m.content(":x: Timed out, nothing was changed.")
+                        .components(|c| c)
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    interaction
+        .create_interaction_response(ctx.discord(), |r| {
+            r.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+        })
+        .await?;
+
+    if interaction.data.custom_id != confirm_id {
+        reply
+            .edit(ctx, |m| {
+                m.content("Cancelled, nothing was changed.")
+                    .components(|c| c)
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    for channel in &channels {
+        let result = channel
+            .id
+            .edit(ctx.discord(), |c| c.permissions(template.iter().cloned()))
+            .await;
+
+        results.push(match result {
+            Ok(_) => format!(":white_check_mark: <#{}>", channel.id.0),
+            Err(e) => {
+                tracing::warn!(
+                    channel = channel.id.0,
+                    "Error applying permission template: {}",
+                    e
+                );
+                let error: Error = e.into();
+                if permissions::is_missing_permissions(&error) {
+                    format!(
+                        ":x: <#{}>: I can only give out permissions I have myself",
+                        channel.id.0
+                    )
+                } else {
+                    format!(":x: <#{}>: {}", channel.id.0, error)
+                }
+            }
+        });
+    }
+
+    reply
+        .edit(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("Applied `{}`", name))
+                    .description(results.join("\n"))
+            })
+            .components(|c| c)
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Lists the permission templates of this server
+///
+/// Usage: `/permtemplate list`
+/// Example: `/permtemplate list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES | MANAGE_CHANNELS"
+)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let templates = ctx.data().db.perm_templates(guild).await?;
+
+    if templates.is_empty() {
+        ctx.send(|m| {
+            m.content("There are no permission templates.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let list = templates
+        .iter()
+        .map(|(name, overwrites)| format!("`{}`: {} overwrites", name, overwrites))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Permission templates").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes a permission template, channels it was applied to keep their permissions
+///
+/// Usage: `/permtemplate delete <name>`
+/// Example: `/permtemplate delete staff`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES | MANAGE_CHANNELS"
+)]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "Name of the template"]
+    #[autocomplete = "autocomplete_template"]
+    name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let deleted = match normalize_name(&name) {
+        Some(name) => ctx.data().db.delete_perm_template(guild, &name).await?,
+        None => false,
+    };
+
+    let response = if deleted {
+        format!(":white_check_mark: Deleted template `{}`", name)
+    } else {
+        format!(":x: There is no template called `{}`.", name)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Give members temporary access to a private channel
+///
+/// Usage: `/pass grant <user> <channel> <duration>`, `/pass revoke <user> <channel>` or `/pass list`
+/// Example: `/pass grant @Sticks #staff-meeting 2h`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("pass_grant", "pass_revoke", "pass_list"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn pass(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Lets a member see and talk in a channel for a limited time
+///
+/// Usage: `/pass grant <user> <channel> <duration>`
+/// Example: `/pass grant @Sticks #staff-meeting 2h`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "grant",
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn pass_grant(
+    ctx: Context<'_>,
+    #[description = "Member to give access"] user: serenity::Member,
+    #[description = "Channel to give access to"]
+    #[channel_types("Text", "News", "Voice", "Stage")]
+    channel: serenity::GuildChannel,
+    #[description = "How long the pass lasts, like 1h or 3d"] duration: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let length = match duration::parse(&duration) {
+        Some(length) if length <= MAX_PASS => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `1h`, `3d` or `2w`, up to a year.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Revoking the pass deletes the member's overwrite, which would also drop permissions they
+    // were given by hand
+    let member_kind = serenity::PermissionOverwriteType::Member(user.user.id);
+    let has_pass = jobs::pending(&ctx.data().db, JobKind::RevokePass, guild)
+        .await?
+        .iter()
+        .any(|j| j.user_id as u64 == user.user.id.0 && j.target_id as u64 == channel.id.0);
+    if !has_pass
+        && channel
+            .permission_overwrites
+            .iter()
+            .any(|o| o.kind == member_kind)
+    {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: <@{}> already has their own permissions in <#{}>.",
+                user.user.id.0, channel.id.0
+            ))
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    channel
+        .create_permission(
+            ctx.discord(),
+            &serenity::PermissionOverwrite {
+                allow: passes::permissions(channel.kind),
+                deny: serenity::Permissions::empty(),
+                kind: member_kind,
+            },
+        )
+        .await?;
+
+    // Extends the current pass if the member already has one for this channel
+    let expires_at = serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64;
+    jobs::schedule(
+        &ctx.data().db,
+        JobKind::RevokePass,
+        guild,
+        user.user.id,
+        channel.id.0,
+        expires_at,
+    )
+    .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: <@{}> can access <#{}> until <t:{}:f>",
+            user.user.id.0, channel.id.0, expires_at
+        ))
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Takes a pass away before it expires
+///
+/// Usage: `/pass revoke <user> <channel>`
+/// Example: `/pass revoke @Sticks #staff-meeting`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "revoke",
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn pass_revoke(
+    ctx: Context<'_>,
+    #[description = "Member with the pass"] user: serenity::User,
+    #[description = "Channel of the pass"] channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let jobs = jobs::take(
+        &ctx.data().db,
+        JobKind::RevokePass,
+        guild,
+        user.id,
+        Some(channel.id.0),
+    )
+    .await?;
+    for job in &jobs {
+        passes::revoke(ctx.discord(), job).await?;
+    }
+
+    let response = if jobs.is_empty() {
+        format!(":x: <@{}> has no pass for <#{}>.", user.id.0, channel.id.0)
+    } else {
+        format!(
+            ":white_check_mark: Revoked the pass of <@{}> for <#{}>",
+            user.id.0, channel.id.0
+        )
+    };
+    ctx.send(|m| {
+        m.content(response)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the passes that haven't expired yet
+///
+/// Usage: `/pass list`
+/// Example: `/pass list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn pass_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let passes = jobs::pending(&ctx.data().db, JobKind::RevokePass, guild).await?;
+
+    if passes.is_empty() {
+        ctx.send(|m| m.content("There are no active passes.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for pass in &passes {
+        let line = format!(
+            "<@{}> in <#{}>, expires <t:{}:R>\n",
+            pass.user_id, pass.target_id, pass.run_at
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Channel passes")
+                .description(list)
+                .footer(|f| f.text(format!("{} active", passes.len())))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Archive, lock or warn about channels nobody wrote in for a while
+///
+/// Usage: `/archive policy <days> <action> [category] [report]`, `/archive off`, `/archive exempt <channel> <exempt>` or `/archive candidates`
+/// Example: `/archive policy 30 move #Archive #admin-log`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "archive_policy",
+        "archive_off",
+        "archive_exempt",
+        "archive_candidates"
+    ),
+    required_permissions = "MANAGE_CHANNELS",
+    default_member_permissions = "MANAGE_CHANNELS"
+)]
+async fn archive(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets what happens to text channels without messages for a number of days
+///
+/// Usage: `/archive policy <days> <action> [category] [report]`
+/// Example: `/archive policy 60 lock` or `/archive policy 30 warn report:#admin-log`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "policy",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | MANAGE_ROLES"
+)]
+async fn archive_policy(
+    ctx: Context<'_>,
+    #[description = "Days without messages"]
+    #[min = 1]
+    #[max = 365]
+    days: i64,
+    #[description = "What to do with inactive channels"] action: ArchiveAction,
+    #[description = "Category to move channels to, needed for move"]
+    #[channel_types("Category")]
+    category: Option<serenity::GuildChannel>,
+    #[description = "Channel for a weekly list of inactive channels"]
+    #[channel_types("Text")]
+    report: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if !(1..=MAX_INACTIVE_DAYS).contains(&days) {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Please give between 1 and {} days.",
+                MAX_INACTIVE_DAYS
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+    if action == ArchiveAction::Move && category.is_none() {
+        ctx.send(|m| {
+            m.content(":x: Please give a category to move inactive channels to.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data()
+        .db
+        .set_archive_policy(
+            guild,
+            days,
+            action.name(),
+            category.as_ref().map(|c| c.id),
+            report.as_ref().map(|c| c.id),
+        )
+        .await?;
+
+    let what = match action {
+        ArchiveAction::Warn => "get a warning".to_string(),
+        ArchiveAction::Move => format!(
+            "are moved to {}",
+            category.map(|c| c.name).unwrap_or_default()
+        ),
+        ArchiveAction::Lock => "are made read-only".to_string(),
+    };
+    let report = match report {
+        Some(report) => format!(", candidates are listed in <#{}> weekly", report.id.0),
+        None => String::new(),
+    };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Channels without messages for {} days now {}{}",
+            days, what, report
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops archiving inactive channels, archived channels stay as they are
+///
+/// Usage: `/archive off`
+/// Example: `/archive off`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "off",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+async fn archive_off(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().db.delete_archive_policy(guild).await? {
+        ":white_check_mark: Inactive channels aren't archived anymore"
+    } else {
+        ":x: This server doesn't archive inactive channels."
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Keeps a channel, or every channel in a category, from being archived
+///
+/// Usage: `/archive exempt <channel> <exempt>`
+/// Example: `/archive exempt #announcements True`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "exempt",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+async fn archive_exempt(
+    ctx: Context<'_>,
+    #[description = "Channel or category"]
+    #[channel_types("Text", "Category")]
+    channel: serenity::GuildChannel,
+    #[description = "Whether it's exempt"] exempt: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .db
+        .set_archive_exemption(guild, channel.id, exempt)
+        .await?;
+
+    let response = if exempt {
+        format!(
+            ":white_check_mark: <#{}> is never archived now",
+            channel.id.0
+        )
+    } else {
+        format!(
+            ":white_check_mark: <#{}> is archived when inactive again",
+            channel.id.0
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the channels that are inactive long enough to be archived
+///
+/// Usage: `/archive candidates`
+/// Example: `/archive candidates`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "candidates",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+async fn archive_candidates(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let policy = match db.archive_policies(Some(guild)).await?.pop() {
+        Some(policy) => policy,
+        None => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: This server doesn't archive inactive channels, see `/archive policy`.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    ctx.defer_ephemeral().await?;
+    let candidates = archive::candidates(&ctx.discord().http, db, &policy).await?;
+    ctx.send(|m| {
+        m.embed(|e| archive::describe(e, &policy, &candidates))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lock channels or slow them down during a weekly window
+///
+/// Usage: `/quiethours add <channel> <start> <end> [slowmode]`, `/quiethours remove <id>` or `/quiethours list`
+/// Example: `/quiethours add #general mon 01:00 mon 07:00`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("quiet_add", "quiet_remove", "quiet_list"),
+    required_permissions = "MANAGE_CHANNELS",
+    default_member_permissions = "MANAGE_CHANNELS"
+)]
+async fn quiethours(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Locks a channel every week, or raises its slowmode, times are in UTC
+///
+/// Usage: `/quiethours add <channel> <start> <end> [slowmode]`
+/// Example: `/quiethours add #general mon 01:00 mon 07:00 30`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | MANAGE_ROLES"
+)]
+async fn quiet_add(
+    ctx: Context<'_>,
+    #[description = "Channel to quiet down"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+    #[description = "When quiet hours start, like mon 01:00"] start: String,
+    #[description = "When they end, like mon 07:00"] end: String,
+    #[description = "Slowmode in seconds instead of locking the channel, up to 6 hours"]
+    #[min = 1]
+    #[max = 21600]
+    slowmode: Option<u64>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let (starts_at, ends_at) = match (
+        temproles::parse_weekly(&start),
+        temproles::parse_weekly(&end),
+    ) {
+        (Some(starts_at), Some(ends_at)) if starts_at != ends_at => (starts_at, ends_at),
+        _ => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: Please give two different times like `mon 01:00` and `mon 07:00`, in UTC.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // They'd save and restore each other's changes
+    let overlapping = db.quiet_hours(Some(guild)).await?.into_iter().find(|w| {
+        w.channel_id == channel.id.0 as i64
+            && quiet::overlaps((w.starts_at, w.ends_at), (starts_at, ends_at))
+    });
+    if let Some(overlapping) = overlapping {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: That overlaps with quiet hours #{} of <#{}>.",
+                overlapping.id, channel.id.0
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Picked up by the next check, also when the window already started
+    let id = db
+        .add_quiet_hours(guild, channel.id, starts_at, ends_at, slowmode)
+        .await?;
+
+    let what = match slowmode {
+        Some(slowmode) => format!("gets a {} second slowmode", slowmode),
+        None => "is locked".to_string(),
+    };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Quiet hours #{}: <#{}> {} from {} until {} UTC",
+            id,
+            channel.id.0,
+            what,
+            temproles::format_weekly(starts_at),
+            temproles::format_weekly(ends_at)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes quiet hours, restoring their channel if they're going on
+///
+/// Usage: `/quiethours remove <id>`
+/// Example: `/quiethours remove 3`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+async fn quiet_remove(
+    ctx: Context<'_>,
+    #[description = "ID from /quiethours list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let window = match ctx.data().db.delete_quiet_hours(guild, id).await? {
+        Some(window) => window,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There are no quiet hours #{}.", id))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    quiet::end(ctx.discord(), &window).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Deleted quiet hours #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the quiet hours of this server
+///
+/// Usage: `/quiethours list`
+/// Example: `/quiethours list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_CHANNELS"
+)]
+async fn quiet_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let windows = ctx.data().db.quiet_hours(Some(guild)).await?;
+
+    if windows.is_empty() {
+        ctx.send(|m| m.content("There are no quiet hours.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for window in &windows {
+        let what = match window.slowmode {
+            Some(slowmode) => format!("{}s slowmode", slowmode),
+            None => "locked".to_string(),
+        };
+        let line = format!(
+            "#{}: <#{}> {}, {} until {}{}\n",
+            window.id,
+            window.channel_id,
+            what,
+            temproles::format_weekly(window.starts_at),
+            temproles::format_weekly(window.ends_at),
+            if window.active { " (active)" } else { "" }
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Quiet hours")
+                .description(list)
+                .footer(|f| f.text("Times are in UTC"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Show member, online and boost counts in the names of voice channels
+///
+/// Usage: `/statschannels setup [category]` or `/statschannels remove`
+/// Example: `/statschannels setup`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("stats_setup", "stats_remove"),
+    required_permissions = "MANAGE_CHANNELS",
+    default_member_permissions = "MANAGE_CHANNELS"
+)]
+async fn statschannels(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Creates locked voice channels whose names show the member, online and boost counts
+///
+/// Usage: `/statschannels setup [category]`
+/// Example: `/statschannels setup`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "setup",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS | MANAGE_ROLES"
+)]
+async fn stats_setup(
+    ctx: Context<'_>,
+    #[description = "Category for the channels, a new one by default"]
+    #[channel_types("Category")]
+    category: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    // Ones deleted by hand are only forgotten on the next refresh
+    let existing = db.stats_channels(Some(guild)).await?.into_iter().any(|c| {
+        ctx.discord()
+            .cache
+            .guild_channel(c.channel_id as u64)
+            .is_some()
+    });
+    if existing {
+        ctx.send(|m| {
+            m.content(":x: This server has stats channels already, see `/statschannels remove`.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+    let category = match category {
+        Some(category) => category.id,
+        None => {
+            guild
+                .create_channel(ctx.discord(), |c| {
+                    c.name("Server stats").kind(serenity::ChannelType::Category)
+                })
+                .await?
+                .id
+        }
+    };
+
+    // Visible for everyone, but nobody can join
+    let locked = serenity::PermissionOverwrite {
+        allow: serenity::Permissions::empty(),
+        deny: serenity::Permissions::CONNECT,
+        kind: serenity::PermissionOverwriteType::Role(serenity::RoleId(guild.0)),
+    };
+    for stat in Stat::ALL {
+        let channel = guild
+            .create_channel(ctx.discord(), |c| {
+                c.name(statschannels::render(stat.default_template(), 0))
+                    .kind(serenity::ChannelType::Voice)
+                    .category(category)
+                    .permissions([locked.clone()])
+            })
+            .await?;
+        db.add_stats_channel(guild, channel.id, stat.name(), stat.default_template())
+            .await?;
+    }
+    statschannels::refresh(ctx.discord(), db, Some(guild)).await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Added stats channels to <#{}>, they're updated every 10 minutes",
+            category.0
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the stats channels of this server
+///
+/// Usage: `/statschannels remove`
+/// Example: `/statschannels remove`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_CHANNELS",
+    required_bot_permissions = "MANAGE_CHANNELS"
+)]
+async fn stats_remove(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let channels = db.stats_channels(Some(guild)).await?;
+    if channels.is_empty() {
+        ctx.send(|m| {
+            m.content(":x: This server has no stats channels.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+    for channel in &channels {
+        let id = serenity::ChannelId(channel.channel_id as u64);
+        // Already deleted by hand is fine
+        let _ = id.delete(ctx.discord()).await;
+        db.delete_stats_channel(id).await?;
+    }
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Deleted {} stats channels",
+            channels.len()
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Marks this message as the answer of its thread
+///
+/// Usage: right click a message in a forum or help thread, then Apps > Mark as answer
+/// Example: `Mark as answer` on the reply that fixed your problem
+#[poise::command(context_menu_command = "Mark as answer", guild_only)]
+async fn mark_answer(ctx: Context<'_>, message: serenity::Message) -> Result<(), Error> {
+    let thread = match message.channel_id.to_channel(ctx.discord()).await?.guild() {
+        Some(thread) if thread.thread_metadata.is_some() => thread,
+        _ => {
+            ctx.send(|m| m.content(":x: This only works in threads.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if thread.owner_id != Some(ctx.author().id) && !can_manage_threads(ctx, &thread).await {
+        ctx.send(|m| {
+            m.content(":x: Only the author of this thread or moderators can mark its answer.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+    let content = if forums::mark(
+        ctx.discord(),
+        ctx.data(),
+        &thread,
+        &message,
+        ctx.author().id,
+    )
+    .await?
+    {
+        ":white_check_mark: Marked the answer, thanks for closing the loop".to_string()
+    } else {
+        ":x: This thread has an answer already.".to_string()
+    };
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// Posts a summary of this message's thread
+///
+/// Usage: right click a message in a thread, then Apps > Summarize thread
+/// Example: `Summarize thread` on any message of a long support thread
+#[poise::command(
+    context_menu_command = "Summarize thread",
+    guild_only,
+    user_cooldown = 60
+)]
+async fn summarize_thread(ctx: Context<'_>, message: serenity::Message) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+    let thread = match message.channel_id.to_channel(ctx.discord()).await?.guild() {
+        Some(thread) if thread.thread_metadata.is_some() => Some(thread),
+        _ => None,
+    };
+
+    let refusal = if !config.is_enabled(Feature::Summaries) {
+        Some(
+            ":x: This server doesn't summarize threads, an admin can turn it on with \
+            `/config feature summaries True`."
+                .to_string(),
+        )
+    } else if ctx.data().llm.is_none() {
+        Some(":x: Summaries need an LLM, which the bot owner hasn't set up.".to_string())
+    } else if thread.is_none() {
+        Some(":x: This only works in threads.".to_string())
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+    // Both were checked above
+    let (thread, llm) = match (thread, &ctx.data().llm) {
+        (Some(thread), Some(llm)) => (thread, llm),
+        _ => return Ok(()),
+    };
+
+    let day = serenity::Timestamp::now().unix_timestamp() / (24 * 60 * 60);
+    if !ctx
+        .data()
+        .db
+        .claim_summary(guild, day, config.summary_limit())
+        .await?
+    {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: This server had all {} of its summaries for today, try again tomorrow.",
+                config.summary_limit()
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let messages = summaries::fetch(&ctx.discord().http, thread.id).await?;
+    let lines = summaries::transcript(&messages);
+    if lines.is_empty() {
+        ctx.say(":x: Nobody wrote anything in this thread yet.")
+            .await?;
+        return Ok(());
+    }
+
+    let summary = match summaries::summarize(llm, &lines).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            tracing::warn!(thread = thread.id.0, "Error summarizing thread: {}", e);
+            ctx.say(":x: The summary couldn't be written, please try again later.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Summary of {}", thread.name))
+                .description(summary.chars().take(4000).collect::<String>())
+                .footer(|f| {
+                    f.text(format!(
+                        "From the last {} messages, written by an LLM so it can be wrong",
+                        lines.len()
+                    ))
+                })
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+// Threads have no permissions of their own, they follow their parent channel
+async fn can_manage_threads(ctx: Context<'_>, thread: &serenity::GuildChannel) -> bool {
+    let parent = thread
+        .parent_id
+        .and_then(|p| ctx.discord().cache.guild_channel(p));
+    let (guild, parent) = match (ctx.guild(), parent) {
+        (Some(guild), Some(parent)) => (guild, parent),
+        _ => return false,
+    };
+    let member = match ctx.author_member().await {
+        Some(member) => member,
+        None => return false,
+    };
+
+    guild
+        .user_permissions_in(&parent, &member)
+        .is_ok_and(|p| p.manage_threads())
+}
+
+async fn autocomplete_template<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let templates = match ctx.guild_id() {
+        Some(guild) => ctx
+            .data()
+            .db
+            .perm_templates(guild)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    templates
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(move |name| name.starts_with(&partial))
+        // Discord shows at most 25 choices
+        .take(25)
+}
+
+fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_TEMPLATE_NAME_LENGTH
+        && !name.contains(char::is_whitespace);
+
+    valid.then_some(name)
+}
+
+// Lists whose overwrites applying the template adds, changes and removes
+fn describe_diff(
+    guild: serenity::GuildId,
+    current: &[serenity::PermissionOverwrite],
+    template: &[serenity::PermissionOverwrite],
+) -> String {
+    let mut changes = Vec::new();
+
+    for overwrite in template {
+        match current.iter().find(|o| o.kind == overwrite.kind) {
+            None => changes.push(format!("+{}", target(guild, overwrite.kind))),
+            Some(o) if o.allow != overwrite.allow || o.deny != overwrite.deny => {
+                changes.push(format!("~{}", target(guild, overwrite.kind)))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for overwrite in current {
+        if !template.iter().any(|o| o.kind == overwrite.kind) {
+            changes.push(format!("-{}", target(guild, overwrite.kind)));
+        }
+    }
+
+    if changes.is_empty() {
+        "No changes".to_string()
+    } else {
+        changes.join(" ")
+    }
+}
+
+fn target(guild: serenity::GuildId, kind: serenity::PermissionOverwriteType) -> String {
+    match kind {
+        // The @everyone role has the guild's ID and can't be mentioned like other roles
+        serenity::PermissionOverwriteType::Role(role) if role.0 == guild.0 => {
+            "@everyone".to_string()
+        }
+        serenity::PermissionOverwriteType::Role(role) => format!("<@&{}>", role.0),
+        serenity::PermissionOverwriteType::Member(user) => format!("<@{}>", user.0),
+        _ => "unknown".to_string(),
+    }
+}
+
+command_list!["Channels": permtemplate, pass, archive, quiethours, statschannels, mark_answer, summarize_thread];
@@ -0,0 +1,1368 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{
+        AltAction, ChannelMode, Feature, RoleList, VoteAction, DEFAULT_MAX_BET, DEFAULT_MIN_BET,
+    },
+    duration, economy, gambling, privacy, setup, translate, votedelete, Context, Error,
+};
+
+// Above this, unrelated images start matching each other
+const MAX_IMAGE_HASH_TOLERANCE: u32 = 20;
+// Roles kept longer than this would mostly be of members that never come back
+const MAX_ROLE_RETENTION: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
+// Partner ads more often than this would drown out the channel
+const MIN_PARTNER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+// Every summary can take a few LLM requests, the owner pays for all of them
+const MAX_SUMMARY_LIMIT: u32 = 200;
+// Embed field values can't be longer than this
+const FIELD_LIMIT: usize = 1024;
+
+/// Settings that can be changed with `/config set`
+#[derive(poise::ChoiceParameter)]
+enum Setting {
+    #[name = "prefix"]
+    Prefix,
+    #[name = "log_channel"]
+    LogChannel,
+    #[name = "message_log_channel"]
+    MessageLogChannel,
+    #[name = "review_channel"]
+    ReviewChannel,
+    #[name = "level_channel"]
+    LevelChannel,
+    #[name = "starboard_channel"]
+    StarboardChannel,
+    #[name = "voice_hub_channel"]
+    VoiceHubChannel,
+    #[name = "watch_channel"]
+    WatchChannel,
+    #[name = "oncall_channel"]
+    OncallChannel,
+    #[name = "boost_channel"]
+    BoostChannel,
+    #[name = "partner_channel"]
+    PartnerChannel,
+    #[name = "staff_role"]
+    StaffRole,
+    #[name = "starboard_threshold"]
+    StarboardThreshold,
+    #[name = "warn_threshold"]
+    WarnThreshold,
+    #[name = "warn_timeout"]
+    WarnTimeout,
+    #[name = "alt_threshold"]
+    AltThreshold,
+    #[name = "alt_action"]
+    AltAction,
+    #[name = "image_hash_tolerance"]
+    ImageHashTolerance,
+    #[name = "nsfw_threshold"]
+    NsfwThreshold,
+    #[name = "house_edge"]
+    HouseEdge,
+    #[name = "min_bet"]
+    MinBet,
+    #[name = "max_bet"]
+    MaxBet,
+    #[name = "gambling_loss_limit"]
+    GamblingLossLimit,
+    #[name = "rob_chance"]
+    RobChance,
+    #[name = "role_retention"]
+    RoleRetention,
+    #[name = "partner_interval"]
+    PartnerInterval,
+    #[name = "vote_delete_threshold"]
+    VoteDeleteThreshold,
+    #[name = "vote_delete_action"]
+    VoteDeleteAction,
+    #[name = "vote_delete_emoji"]
+    VoteDeleteEmoji,
+    #[name = "summary_limit"]
+    SummaryLimit,
+}
+
+/// View or change this server's bot settings
+///
+/// Usage: `/config get`, `/config set <setting> <value>`, `/config feature <feature> <on/off>` or `/config channel <channel> [mode]` `/config roles <list> <role> <added>`, `/config links <domain> <allowed>`, `/config translation <channel> <linked> [language] [linked_language]` or `/config alttext <channel> <required>`
+/// Example: `/config set prefix !`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "get",
+        "set",
+        "feature",
+        "channel",
+        "roles",
+        "links",
+        "translation",
+        "alttext"
+    ),
+    required_permissions = "ADMINISTRATOR",
+    default_member_permissions = "ADMINISTRATOR"
+)]
+async fn config(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows the current settings
+///
+/// Usage: `/config get`
+/// Example: `/config get`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn get(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+
+    let channel = |channel: Option<serenity::ChannelId>| match channel {
+        Some(channel) => format!("<#{}>", channel.0),
+        None => "Not set".to_string(),
+    };
+
+    // Merged into one field, embeds have at most 25
+    let staff_channels = [
+        ("Log", config.log_channel),
+        ("Message log", config.message_log_channel),
+        ("Review", config.review_channel),
+        ("Watchlist", config.watch_channel),
+        ("On-call", config.oncall_channel),
+    ]
+    .iter()
+    .map(|(name, c)| format!("{}: {}", name, channel(*c)))
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    let warn_threshold = match config.warn_threshold {
+        Some(threshold) => format!(
+            "Timeout for {} every {} warnings",
+            duration::format(config.warn_timeout()),
+            threshold
+        ),
+        None => "Off".to_string(),
+    };
+
+    let alt_action = match (config.alt_action, config.alt_threshold) {
+        (Some(action), Some(threshold)) => format!("`{}` at {}%", action.name(), threshold),
+        _ => "Alerts only".to_string(),
+    };
+
+    let mut gambling = format!(
+        "{}% house edge, bets from {} to {}",
+        config.house_edge(),
+        config.min_bet(),
+        config.max_bet()
+    );
+    if let Some(limit) = config.gambling_loss_limit {
+        gambling.push_str(&format!(", up to {} lost a day", limit));
+    }
+
+    let features = Feature::ALL
+        .iter()
+        .map(|f| {
+            let state = if config.is_enabled(*f) { "on" } else { "off" };
+            format!("`{}`: {}", f.name(), state)
+        })
+        .collect::<Vec<_>>();
+    let features = fit(features, "\n", FIELD_LIMIT);
+
+    let role_lists = RoleList::ALL
+        .iter()
+        .map(|list| {
+            let roles = match config.role_lists.get(list) {
+                Some(roles) if !roles.is_empty() => fit(
+                    roles.iter().map(|r| format!("<@&{}>", r.0)).collect(),
+                    ", ",
+                    FIELD_LIMIT / RoleList::ALL.len(),
+                ),
+                _ => "None".to_string(),
+            };
+            format!("`{}`: {}", list.name(), roles)
+        })
+        .collect::<Vec<_>>();
+    let role_lists = fit(role_lists, "\n", FIELD_LIMIT);
+
+    let link_allowlist = if config.link_allowlist.is_empty() {
+        "None".to_string()
+    } else {
+        let mut domains: Vec<_> = config
+            .link_allowlist
+            .iter()
+            .map(|d| format!("`{}`", d))
+            .collect();
+        domains.sort_unstable();
+        fit(domains, ", ", FIELD_LIMIT)
+    };
+
+    let mut translation_links: Vec<_> = config
+        .translation_links
+        .iter()
+        .flat_map(|(channel, targets)| {
+            targets.iter().map(move |(target, language)| {
+                format!("<#{}> to <#{}> in `{}`", channel.0, target.0, language)
+            })
+        })
+        .collect();
+    translation_links.sort_unstable();
+    let translation_links = if translation_links.is_empty() {
+        "None".to_string()
+    } else {
+        fit(translation_links, "\n", FIELD_LIMIT)
+    };
+
+    let mut alt_text_channels: Vec<_> = config
+        .alt_text_channels
+        .iter()
+        .map(|c| format!("<#{}>", c.0))
+        .collect();
+    alt_text_channels.sort_unstable();
+    let alt_text_channels = if alt_text_channels.is_empty() {
+        "None".to_string()
+    } else {
+        fit(alt_text_channels, ", ", FIELD_LIMIT)
+    };
+
+    let channels = if config.channel_modes.is_empty() {
+        "None".to_string()
+    } else {
+        let channels = config
+            .channel_modes
+            .iter()
+            .map(|(channel, mode)| format!("<#{}>: `{}`", channel.0, mode.name()))
+            .collect();
+        fit(channels, "\n", FIELD_LIMIT)
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Server settings")
+                .field("Prefix", format!("`{}`", config.prefix()), true)
+                .field("Staff channels", staff_channels, false)
+                .field("Level up channel", channel(config.level_channel), true)
+                .field("Boost channel", channel(config.boost_channel), true)
+                .field(
+                    "Vote to delete",
+                    match config.vote_delete_threshold {
+                        Some(threshold) => format!(
+                            "{} {}, `{}`",
+                            threshold,
+                            config.vote_delete_emoji(),
+                            config.vote_delete_action().name()
+                        ),
+                        None => "Off".to_string(),
+                    },
+                    true,
+                )
+                .field(
+                    "Partner ads",
+                    match config.partner_channel {
+                        Some(partners) => format!(
+                            "<#{}> every {}",
+                            partners.0,
+                            duration::format(config.partner_interval())
+                        ),
+                        None => "Off".to_string(),
+                    },
+                    true,
+                )
+                .field(
+                    "Starboard",
+                    match config.starboard_channel {
+                        Some(starboard) => format!(
+                            "<#{}> from {} stars",
+                            starboard.0,
+                            config.starboard_threshold()
+                        ),
+                        None => "Off".to_string(),
+                    },
+                    true,
+                )
+                .field("Welcome channel", channel(config.welcome_channel), true)
+                .field(
+                    "Onboarding channel",
+                    channel(config.onboarding_channel),
+                    true,
+                )
+                .field("Join to create", channel(config.voice_hub_channel), true)
+                .field(
+                    "Staff role",
+                    match config.staff_role {
+                        Some(role) => format!("<@&{}>", role.0),
+                        None => "Not set".to_string(),
+                    },
+                    true,
+                )
+                .field("Warning escalation", warn_threshold, false)
+                .field("Alt accounts", alt_action, false)
+                .field(
+                    "Image hash tolerance",
+                    format!("{} bits", config.image_hash_tolerance()),
+                    true,
+                )
+                .field(
+                    "NSFW threshold",
+                    format!("{}%", config.nsfw_threshold()),
+                    true,
+                )
+                .field(
+                    "Role retention",
+                    duration::format(config.role_retention()),
+                    true,
+                )
+                .field("Gambling", gambling, false)
+                .field(
+                    "Robbing",
+                    match config.rob_chance() {
+                        0 => "Off".to_string(),
+                        chance => format!("{}% success chance", chance),
+                    },
+                    true,
+                )
+                .field(
+                    "Thread summaries",
+                    if config.is_enabled(Feature::Summaries) {
+                        format!("{} a day", config.summary_limit())
+                    } else {
+                        "Off".to_string()
+                    },
+                    true,
+                )
+                .field("Features", features, false)
+                .field("Channels", channels, false)
+                .field("Role lists", role_lists, false)
+                .field("Link allowlist", link_allowlist, false)
+                .field("Translation links", translation_links, false)
+                .field("Alt text channels", alt_text_channels, false)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Changes a setting, use `none` to reset it
+///
+/// Usage: `/config set <setting> <value>`
+/// Example: `/config set log_channel #mod-log`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Setting to change"] setting: Setting,
+    #[description = "New value, or `none` to reset"] value: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let value = value.trim();
+    let reset = value.eq_ignore_ascii_case("none");
+
+    let response = match setting {
+        Setting::Prefix => {
+            if !reset && (value.is_empty() || value.len() > 5 || value.contains(' ')) {
+                ":x: The prefix must be 1 to 5 characters without spaces.".to_string()
+            } else {
+                let prefix = (!reset).then(|| value.to_string());
+                let config = ctx
+                    .data()
+                    .guild_configs
+                    .update(guild, |c| c.prefix = prefix)
+                    .await?;
+                format!(":white_check_mark: Prefix set to `{}`", config.prefix())
+            }
+        }
+        Setting::LogChannel
+        | Setting::MessageLogChannel
+        | Setting::ReviewChannel
+        | Setting::LevelChannel
+        | Setting::StarboardChannel
+        | Setting::VoiceHubChannel
+        | Setting::WatchChannel
+        | Setting::OncallChannel
+        | Setting::BoostChannel
+        | Setting::PartnerChannel => {
+            let channel = if reset {
+                None
+            } else {
+                match parse_channel(value) {
+                    Some(channel) => Some(channel),
+                    None => {
+                        ctx.send(|m| {
+                            m.content(":x: That doesn't look like a channel.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| match setting {
+                    Setting::MessageLogChannel => c.message_log_channel = channel,
+                    Setting::ReviewChannel => c.review_channel = channel,
+                    Setting::LevelChannel => c.level_channel = channel,
+                    Setting::StarboardChannel => c.starboard_channel = channel,
+                    Setting::VoiceHubChannel => c.voice_hub_channel = channel,
+                    Setting::WatchChannel => c.watch_channel = channel,
+                    Setting::OncallChannel => c.oncall_channel = channel,
+                    Setting::BoostChannel => c.boost_channel = channel,
+                    Setting::PartnerChannel => c.partner_channel = channel,
+                    _ => c.log_channel = channel,
+                })
+                .await?;
+
+            match channel {
+                Some(channel) => format!(
+                    ":white_check_mark: `{}` set to <#{}>",
+                    setting.name(),
+                    channel.0
+                ),
+                None => format!(":white_check_mark: `{}` cleared", setting.name()),
+            }
+        }
+        Setting::StaffRole => {
+            let role = if reset {
+                None
+            } else {
+                match parse_role(value) {
+                    Some(role) => Some(role),
+                    None => {
+                        ctx.send(|m| {
+                            m.content(":x: That doesn't look like a role.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| c.staff_role = role)
+                .await?;
+
+            match role {
+                Some(role) => format!(":white_check_mark: /staff now pings <@&{}>", role.0),
+                None => ":white_check_mark: `staff_role` cleared".to_string(),
+            }
+        }
+        Setting::WarnThreshold => {
+            let threshold = if reset {
+                None
+            } else {
+                match value.parse::<u32>() {
+                    Ok(threshold) if threshold > 0 => Some(threshold),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The threshold must be a whole number above 0.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| c.warn_threshold = threshold)
+                .await?;
+
+            match threshold {
+                Some(threshold) => format!(
+                    ":white_check_mark: Members will be timed out every {} warnings",
+                    threshold
+                ),
+                None => ":white_check_mark: Warning escalation turned off".to_string(),
+            }
+        }
+        Setting::WarnTimeout => {
+            let timeout = if reset {
+                None
+            } else {
+                match duration::parse(value) {
+                    Some(timeout) if timeout <= duration::MAX_TIMEOUT => Some(timeout),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(
+                                ":x: Please give a duration like `10m`, `1h` or `2d`, up to 28 days.",
+                            )
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.warn_timeout = timeout)
+                .await?;
+            format!(
+                ":white_check_mark: Warning timeouts now last {}",
+                duration::format(config.warn_timeout())
+            )
+        }
+        Setting::AltThreshold => {
+            let threshold = if reset {
+                None
+            } else {
+                match value.trim_end_matches('%').parse::<u32>() {
+                    Ok(threshold) if (1..=100).contains(&threshold) => Some(threshold),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The threshold must be a percentage from 1 to 100.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| c.alt_threshold = threshold)
+                .await?;
+
+            match threshold {
+                Some(threshold) => format!(
+                    ":white_check_mark: Alt action will be taken from a {}% score",
+                    threshold
+                ),
+                None => ":white_check_mark: Alt accounts will only be reported".to_string(),
+            }
+        }
+        Setting::AltAction => {
+            let action = if reset {
+                None
+            } else {
+                match value.to_lowercase().parse::<AltAction>() {
+                    Ok(action) => Some(action),
+                    Err(_) => {
+                        ctx.send(|m| {
+                            m.content(":x: The action must be `kick`, `ban` or `timeout`.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| c.alt_action = action)
+                .await?;
+
+            match action {
+                Some(action) => format!(
+                    ":white_check_mark: Likely alts will be handled with `{}`",
+                    action.name()
+                ),
+                None => ":white_check_mark: Alt accounts will only be reported".to_string(),
+            }
+        }
+        Setting::ImageHashTolerance => {
+            let tolerance = if reset {
+                None
+            } else {
+                match value.parse::<u32>() {
+                    Ok(tolerance) if tolerance <= MAX_IMAGE_HASH_TOLERANCE => Some(tolerance),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(format!(
+                                ":x: The tolerance must be a number of bits from 0 to {}.",
+                                MAX_IMAGE_HASH_TOLERANCE
+                            ))
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.image_hash_tolerance = tolerance)
+                .await?;
+            format!(
+                ":white_check_mark: Images now match blocked ones with up to {} differing bits",
+                config.image_hash_tolerance()
+            )
+        }
+        Setting::StarboardThreshold => {
+            let threshold = if reset {
+                None
+            } else {
+                match value.parse::<u32>() {
+                    Ok(threshold) if threshold > 0 => Some(threshold),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The threshold must be a whole number above 0.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.starboard_threshold = threshold)
+                .await?;
+            format!(
+                ":white_check_mark: Messages need {} stars for the starboard",
+                config.starboard_threshold()
+            )
+        }
+        Setting::NsfwThreshold => {
+            let threshold = if reset {
+                None
+            } else {
+                match value.trim_end_matches('%').parse::<u32>() {
+                    Ok(threshold) if (1..=100).contains(&threshold) => Some(threshold),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The threshold must be a percentage from 1 to 100.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.nsfw_threshold = threshold)
+                .await?;
+            format!(
+                ":white_check_mark: Images scoring {}% or more will be flagged for review",
+                config.nsfw_threshold()
+            )
+        }
+        Setting::HouseEdge => {
+            let edge = if reset {
+                None
+            } else {
+                match value.trim_end_matches('%').parse::<u32>() {
+                    Ok(edge) if edge <= gambling::MAX_HOUSE_EDGE => Some(edge),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(format!(
+                                ":x: The house edge must be a percentage from 0 to {}.",
+                                gambling::MAX_HOUSE_EDGE
+                            ))
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.house_edge = edge)
+                .await?;
+            format!(
+                ":white_check_mark: The house now keeps {}% of bets on average",
+                config.house_edge()
+            )
+        }
+        Setting::RoleRetention => {
+            let retention = if reset {
+                None
+            } else {
+                match duration::parse(value) {
+                    Some(retention) if retention <= MAX_ROLE_RETENTION => Some(retention),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: Please give a duration like `7d` or `2w`, up to a year.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.role_retention = retention)
+                .await?;
+            format!(
+                ":white_check_mark: Roles of members that left are now kept for {}",
+                duration::format(config.role_retention())
+            )
+        }
+        Setting::PartnerInterval => {
+            let interval = if reset {
+                None
+            } else {
+                match duration::parse(value) {
+                    Some(interval) if interval >= MIN_PARTNER_INTERVAL => Some(interval),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(
+                                ":x: Please give a duration like `12h` or `2d`, from an hour.",
+                            )
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.partner_interval = interval)
+                .await?;
+            format!(
+                ":white_check_mark: A partner ad is now posted every {}",
+                duration::format(config.partner_interval())
+            )
+        }
+        Setting::SummaryLimit => {
+            let limit = if reset {
+                None
+            } else {
+                match value.parse::<u32>() {
+                    Ok(limit) if (1..=MAX_SUMMARY_LIMIT).contains(&limit) => Some(limit),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(format!(
+                                ":x: The limit must be a whole number from 1 to {}, turn \
+                                summaries off with `/config feature summaries False`.",
+                                MAX_SUMMARY_LIMIT
+                            ))
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.summary_limit = limit)
+                .await?;
+            format!(
+                ":white_check_mark: Threads can now be summarized {} times a day",
+                config.summary_limit()
+            )
+        }
+        Setting::VoteDeleteThreshold => {
+            let threshold = if reset {
+                None
+            } else {
+                match value.parse::<u32>() {
+                    Ok(threshold) if (2..=votedelete::MAX_THRESHOLD).contains(&threshold) => {
+                        Some(threshold)
+                    }
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(format!(
+                                ":x: The threshold must be a number of votes from 2 to {}.",
+                                votedelete::MAX_THRESHOLD
+                            ))
+                            .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.vote_delete_threshold = threshold)
+                .await?;
+
+            match threshold {
+                Some(threshold) => format!(
+                    ":white_check_mark: Messages with {} {} reactions are now voted to be deleted",
+                    threshold,
+                    config.vote_delete_emoji()
+                ),
+                None => ":white_check_mark: Voting to delete messages turned off".to_string(),
+            }
+        }
+        Setting::VoteDeleteAction => {
+            let action = if reset {
+                None
+            } else {
+                match value.to_lowercase().parse::<VoteAction>() {
+                    Ok(action) => Some(action),
+                    Err(_) => {
+                        ctx.send(|m| {
+                            m.content(":x: The action must be `review` or `delete`.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.vote_delete_action = action)
+                .await?;
+
+            match config.vote_delete_action() {
+                VoteAction::Review => {
+                    ":white_check_mark: Messages voted to be deleted now go to the review channel"
+                        .to_string()
+                }
+                VoteAction::Delete => {
+                    ":white_check_mark: Messages voted to be deleted are now deleted right away"
+                        .to_string()
+                }
+            }
+        }
+        Setting::VoteDeleteEmoji => {
+            let emoji = if reset {
+                None
+            } else {
+                match votedelete::parse_emoji(value) {
+                    Some(_) => Some(value.to_string()),
+                    None => {
+                        ctx.send(|m| {
+                            m.content(":x: That doesn't look like an emoji.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.vote_delete_emoji = emoji)
+                .await?;
+            format!(
+                ":white_check_mark: Members now vote to delete messages with {}",
+                config.vote_delete_emoji()
+            )
+        }
+        Setting::RobChance => {
+            let chance = if reset {
+                None
+            } else {
+                match value.trim_end_matches('%').parse::<u32>() {
+                    Ok(chance) if chance <= 100 => Some(chance),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The chance must be a percentage from 0 to 100.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| c.rob_chance = chance)
+                .await?;
+            match config.rob_chance() {
+                0 => ":white_check_mark: Robbing turned off".to_string(),
+                chance => format!(
+                    ":white_check_mark: Robberies now succeed {}% of the time",
+                    chance
+                ),
+            }
+        }
+        Setting::MinBet | Setting::MaxBet | Setting::GamblingLossLimit => {
+            let amount = if reset {
+                None
+            } else {
+                match value.parse::<i64>() {
+                    Ok(amount) if amount > 0 => Some(amount),
+                    _ => {
+                        ctx.send(|m| {
+                            m.content(":x: The amount must be a whole number of coins above 0.")
+                                .ephemeral(true)
+                        })
+                        .await?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            // Bets outside the range are refused, so it can't be empty
+            let current = ctx.data().guild_configs.get(guild).await?;
+            let (min, max) = match setting {
+                Setting::MinBet => (amount.unwrap_or(DEFAULT_MIN_BET), current.max_bet()),
+                Setting::MaxBet => (current.min_bet(), amount.unwrap_or(DEFAULT_MAX_BET)),
+                _ => (current.min_bet(), current.max_bet()),
+            };
+            if min > max {
+                ctx.send(|m| {
+                    m.content(":x: The smallest bet can't be bigger than the biggest one.")
+                        .ephemeral(true)
+                })
+                .await?;
+                return Ok(());
+            }
+
+            let config = ctx
+                .data()
+                .guild_configs
+                .update(guild, |c| match setting {
+                    Setting::MinBet => c.min_bet = amount,
+                    Setting::MaxBet => c.max_bet = amount,
+                    _ => c.gambling_loss_limit = amount,
+                })
+                .await?;
+
+            match (setting, config.gambling_loss_limit) {
+                (Setting::GamblingLossLimit, None) => {
+                    ":white_check_mark: Members can lose any amount gambling".to_string()
+                }
+                (Setting::GamblingLossLimit, Some(limit)) => format!(
+                    ":white_check_mark: Members can lose up to {} a day gambling",
+                    economy::format(limit)
+                ),
+                _ => format!(
+                    ":white_check_mark: Bets are now from {} to {}",
+                    economy::format(config.min_bet()),
+                    economy::format(config.max_bet())
+                ),
+            }
+        }
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Turns a feature on or off
+///
+/// Usage: `/config feature <feature> <enabled>`
+/// Example: `/config feature translate True`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn feature(
+    ctx: Context<'_>,
+    #[description = "Feature to toggle"] feature: Feature,
+    #[description = "Whether the feature is on"] enabled: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            c.features.insert(feature, enabled);
+        })
+        .await?;
+    // Nothing stays stored once a server stops wanting it
+    if feature == Feature::MessageArchive && !enabled {
+        ctx.data().db.clear_archive(guild).await?;
+    }
+    // Content hashed once can't be restored by turning privacy mode off again
+    if feature == Feature::PrivacyMode && enabled {
+        ctx.data().db.redact_content(guild).await?;
+    }
+
+    let state = if enabled { "on" } else { "off" };
+    let mut content = format!(":white_check_mark: `{}` is now {}", feature.name(), state);
+    if feature == Feature::PrivacyMode && enabled && !privacy::has_key() {
+        content.push_str(
+            "\n:warning: The bot's owner hasn't set PRIVACY_KEY, so no messages are archived.",
+        );
+    }
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Gives a channel special rules, leave the mode empty to remove them
+///
+/// Usage: `/config channel <channel> [mode]`
+/// Example: `/config channel #reactions reaction_wall` or `/config channel #bot-games ignored`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn channel(
+    ctx: Context<'_>,
+    #[description = "Channel to change"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+    #[description = "Rules for the channel"] mode: Option<ChannelMode>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| match mode {
+            Some(mode) => {
+                c.channel_modes.insert(channel.id, mode);
+            }
+            None => {
+                c.channel_modes.remove(&channel.id);
+            }
+        })
+        .await?;
+
+    let response = match mode {
+        Some(mode) => format!(
+            ":white_check_mark: <#{}> is now `{}`",
+            channel.id.0,
+            mode.name()
+        ),
+        None => format!(
+            ":white_check_mark: <#{}> is a normal channel again",
+            channel.id.0
+        ),
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Adds a role to a role list or removes it from one
+///
+/// Usage: `/config roles <list> <role> <added>`
+/// Example: `/config roles nsfw_exempt @Artists True`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn roles(
+    ctx: Context<'_>,
+    #[description = "List to change"] list: RoleList,
+    #[description = "Role to add or remove"] role: serenity::Role,
+    #[description = "Whether the role is in the list"] added: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            let roles = c.role_lists.entry(list).or_default();
+            if added {
+                roles.insert(role.id);
+            } else {
+                roles.remove(&role.id);
+            }
+        })
+        .await?;
+
+    let response = if added {
+        format!(
+            ":white_check_mark: Added <@&{}> to `{}`",
+            role.id.0,
+            list.name()
+        )
+    } else {
+        format!(
+            ":white_check_mark: Removed <@&{}> from `{}`",
+            role.id.0,
+            list.name()
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Adds a domain to the link allowlist or removes it, allowed links are never cleaned
+///
+/// Usage: `/config links <domain> <allowed>`
+/// Example: `/config links youtube.com True`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn links(
+    ctx: Context<'_>,
+    #[description = "Domain, subdomains are included"] domain: String,
+    #[description = "Whether links to the domain are left alone"] allowed: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    // Accept full links too, only the host matters
+    let domain = domain.trim().to_lowercase();
+    let domain = domain
+        .split("://")
+        .last()
+        .and_then(|d| d.split('/').next())
+        .unwrap_or_default()
+        .trim_start_matches("www.")
+        .to_string();
+    if domain.is_empty() || !domain.contains('.') || domain.contains(char::is_whitespace) {
+        ctx.send(|m| {
+            m.content(":x: That doesn't look like a domain.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            if allowed {
+                c.link_allowlist.insert(domain.clone());
+            } else {
+                c.link_allowlist.remove(&domain);
+            }
+        })
+        .await?;
+
+    let response = if allowed {
+        format!(":white_check_mark: Links to `{}` are left alone", domain)
+    } else {
+        format!(":white_check_mark: Links to `{}` are cleaned again", domain)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Mirrors messages of two channels into each other translated, without languages it unlinks them
+///
+/// Usage: `/config translation <channel> <linked> [language] [linked_language]`
+/// Example: `/config translation #general #general-fr en fr`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn translation(
+    ctx: Context<'_>,
+    #[description = "Channel to link"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+    #[description = "Channel to link it with"]
+    #[channel_types("Text")]
+    linked: serenity::GuildChannel,
+    #[description = "Language of the first channel, like en"] language: Option<String>,
+    #[description = "Language of the linked channel, like fr"] linked_language: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let (a, b) = (channel.id, linked.id);
+    let unlink = |c: &mut crate::config::GuildConfig| {
+        for (from, to) in [(a, b), (b, a)] {
+            if let Some(targets) = c.translation_links.get_mut(&from) {
+                targets.retain(|(target, _)| *target != to);
+                if targets.is_empty() {
+                    c.translation_links.remove(&from);
+                }
+            }
+        }
+    };
+
+    let languages = language
+        .zip(linked_language)
+        .map(|(a, b)| (a.trim().to_lowercase(), b.trim().to_lowercase()));
+    let response = match languages {
+        None => {
+            ctx.data().guild_configs.update(guild, unlink).await?;
+            format!(
+                ":white_check_mark: <#{}> and <#{}> aren't linked anymore",
+                a.0, b.0
+            )
+        }
+        Some(_) if a == b => ":x: A channel can't be linked with itself.".to_string(),
+        Some((language, linked_language))
+            if !translate::LANGUAGES.contains(&language.as_str())
+                || !translate::LANGUAGES.contains(&linked_language.as_str()) =>
+        {
+            format!(
+                ":x: Languages are given by their code, one of {}.",
+                translate::LANGUAGES.join(", ")
+            )
+        }
+        Some((language, linked_language)) => {
+            if ctx.data().translator.is_none() {
+                ctx.send(|m| {
+                    m.content(":x: Translation isn't set up for this bot.")
+                        .ephemeral(true)
+                })
+                .await?;
+                return Ok(());
+            }
+
+            ctx.data()
+                .guild_configs
+                .update(guild, |c| {
+                    unlink(c);
+                    c.translation_links
+                        .entry(a)
+                        .or_default()
+                        .push((b, linked_language.clone()));
+                    c.translation_links
+                        .entry(b)
+                        .or_default()
+                        .push((a, language.clone()));
+                })
+                .await?;
+            format!(
+                ":white_check_mark: Messages in <#{}> are mirrored into <#{}> in `{}`, and the other way around in `{}`",
+                a.0, b.0, linked_language, language
+            )
+        }
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Reminds members to describe the images they post in a channel, with a button to add one
+///
+/// Usage: `/config alttext <channel> <required>`
+/// Example: `/config alttext #art True`
+#[poise::command(slash_command, guild_only, required_permissions = "ADMINISTRATOR")]
+async fn alttext(
+    ctx: Context<'_>,
+    #[description = "Channel for the reminders"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+    #[description = "Whether images there should have a description"] required: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            if required {
+                c.alt_text_channels.insert(channel.id);
+            } else {
+                c.alt_text_channels.remove(&channel.id);
+            }
+        })
+        .await?;
+
+    let response = if required {
+        format!(
+            ":white_check_mark: Images without a description in <#{}> get a reminder",
+            channel.id.0
+        )
+    } else {
+        format!(
+            ":white_check_mark: Images in <#{}> don't need a description anymore",
+            channel.id.0
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+// Accepts a channel mention (<#123>) or a raw ID
+fn parse_channel(value: &str) -> Option<serenity::ChannelId> {
+    let id = value
+        .strip_prefix("<#")
+        .and_then(|v| v.strip_suffix('>'))
+        .unwrap_or(value);
+
+    id.parse().ok().map(serenity::ChannelId)
+}
+
+// Accepts a role mention (<@&123>) or a raw ID
+fn parse_role(value: &str) -> Option<serenity::RoleId> {
+    let id = value
+        .strip_prefix("<@&")
+        .and_then(|v| v.strip_suffix('>'))
+        .unwrap_or(value);
+
+    id.parse().ok().map(serenity::RoleId)
+}
+
+// Joins as many items as fit in `limit` characters, counting the ones left out
+fn fit(items: Vec<String>, separator: &str, limit: usize) -> String {
+    let mut value = String::new();
+    for (i, item) in items.iter().enumerate() {
+        let left = items.len() - i - 1;
+        let more = if left == 0 {
+            0
+        } else {
+            separator.len() + format!("...and {} more", left).len()
+        };
+        let separator = if value.is_empty() { "" } else { separator };
+        if value.len() + separator.len() + item.len() + more > limit {
+            value.push_str(separator);
+            value.push_str(&format!("...and {} more", left + 1));
+            break;
+        }
+        value.push_str(separator);
+        value.push_str(item);
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_keeps_short_lists() {
+        let items = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(fit(items, ", ", 100), "a, b");
+    }
+
+    #[test]
+    fn fit_counts_what_is_left_out() {
+        let items: Vec<_> = (0..500).map(|i| format!("<#{}>", i)).collect();
+        let value = fit(items, "\n", FIELD_LIMIT);
+        assert!(value.len() <= FIELD_LIMIT);
+        assert!(value.ends_with("more"));
+    }
+}
+
+/// Checks what's set up in this server
+///
+/// Usage: `/setup status`
+/// Example: `/setup status`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("setup_status"),
+    required_permissions = "ADMINISTRATOR",
+    default_member_permissions = "ADMINISTRATOR"
+)]
+async fn setup(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows a checklist of the bot's setup, with buttons to fix what's missing
+///
+/// Usage: `/setup status`
+/// Example: `/setup status`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "status",
+    required_permissions = "ADMINISTRATOR"
+)]
+async fn setup_status(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let items = setup::audit(ctx.discord(), ctx.data(), guild).await?;
+    let name = guild
+        .name(ctx.discord())
+        .unwrap_or_else(|| "this server".to_string());
+
+    ctx.send(|m| {
+        m.embed(|e| setup::embed(e, &name, &items))
+            .components(|c| setup::buttons(c, &items))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Settings": config, setup];
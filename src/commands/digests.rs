@@ -0,0 +1,249 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    digests::{self, MAX_CHANNELS},
+    Context, Error,
+};
+
+/// Post a daily digest of what happened in busy channels
+///
+/// Usage: `/digest setup <channel> [hour] [summarize]`, `/digest off`, `/digest add <channel>`, `/digest remove <channel>` or `/digest show`
+/// Example: `/digest setup #daily-digest 18`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "digest_setup",
+        "digest_off",
+        "digest_add",
+        "digest_remove",
+        "digest_show"
+    ),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn digest(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets where and when the digest is posted
+///
+/// Usage: `/digest setup <channel> [hour] [summarize]`
+/// Example: `/digest setup #daily-digest 18 True`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "setup",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn digest_setup(
+    ctx: Context<'_>,
+    #[description = "Channel to post the digest in"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+    #[description = "Hour of the day in UTC to post it at, 0 if not given"]
+    #[min = 0]
+    #[max = 23]
+    hour: Option<u32>,
+    #[description = "Whether to add a summary written by an LLM"] summarize: Option<bool>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let hour = hour.unwrap_or(0).min(23) as i64;
+    let summarize = summarize.unwrap_or(false);
+
+    if summarize && ctx.data().llm.is_none() {
+        ctx.send(|m| {
+            m.content(":x: Summaries need an LLM, which the bot owner hasn't set up.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // The first digest is the next one, not one for the day that's already half over
+    let now = serenity::Timestamp::now().unix_timestamp();
+    ctx.data()
+        .db
+        .set_digest(
+            guild,
+            channel.id,
+            hour,
+            summarize,
+            digests::latest_slot(now, hour),
+        )
+        .await?;
+
+    let summary = if summarize { " with a summary" } else { "" };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: The digest is posted in <#{}> at {:02}:00 UTC{}, add channels \
+            with `/digest add`",
+            channel.id.0, hour, summary
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops posting the digest
+///
+/// Usage: `/digest off`
+/// Example: `/digest off`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "off",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn digest_off(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().db.delete_digest(guild).await? {
+        ":white_check_mark: The digest isn't posted anymore"
+    } else {
+        ":x: This server has no digest."
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Adds a channel to the digest
+///
+/// Usage: `/digest add <channel>`
+/// Example: `/digest add #general`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn digest_add(
+    ctx: Context<'_>,
+    #[description = "Channel to add"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+    let channels = db.digest_channels(guild).await?;
+
+    let refusal = if db.digests(Some(guild)).await?.is_empty() {
+        Some(":x: Please set up the digest with `/digest setup` first.".to_string())
+    } else if channels.contains(&channel.id) {
+        Some(format!(":x: <#{}> is in the digest already.", channel.id.0))
+    } else if channels.len() >= MAX_CHANNELS {
+        Some(format!(
+            ":x: Digests can cover up to {} channels, remove one first.",
+            MAX_CHANNELS
+        ))
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    db.set_digest_channel(guild, channel.id, true).await?;
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: <#{}> is in the digest now",
+            channel.id.0
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Takes a channel out of the digest
+///
+/// Usage: `/digest remove <channel>`
+/// Example: `/digest remove #general`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn digest_remove(
+    ctx: Context<'_>,
+    #[description = "Channel to remove"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .db
+        .set_digest_channel(guild, channel.id, false)
+        .await?;
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: <#{}> isn't in the digest anymore",
+            channel.id.0
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows when and where the digest is posted and which channels it covers
+///
+/// Usage: `/digest show`
+/// Example: `/digest show`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "show",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn digest_show(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let digest = match db.digests(Some(guild)).await?.pop() {
+        Some(digest) => digest,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: This server has no digest, see `/digest setup`.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let channels = db.digest_channels(guild).await?;
+    let channels = if channels.is_empty() {
+        "None yet, add some with `/digest add`".to_string()
+    } else {
+        channels
+            .iter()
+            .map(|c| format!("<#{}>", c.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Daily digest")
+                .field("Posted in", format!("<#{}>", digest.channel_id), true)
+                .field("At", format!("{:02}:00 UTC", digest.hour), true)
+                .field("Summary", if digest.summarize { "On" } else { "Off" }, true)
+                .field("Channels", channels, false)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Channels": digest];
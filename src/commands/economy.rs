@@ -0,0 +1,660 @@
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use super::roles::check_role;
+use crate::{
+    boosts,
+    db::NewShopItem,
+    duration,
+    economy::{self, Transfer},
+    gambling::{self, Space},
+    shop::{self, ItemKind},
+    streaks::{self, Activity},
+    votes, work, Context, Error,
+};
+
+// Coins /daily gives
+const DAILY_AMOUNT: i64 = 100;
+// Rentals longer than this are most likely typos
+const MAX_RENTAL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Shows your or another member's coins
+///
+/// Usage: `/balance [member]`
+/// Example: `/balance @user`
+#[poise::command(slash_command, prefix_command, guild_only, user_cooldown = 5)]
+async fn balance(
+    ctx: Context<'_>,
+    #[description = "Member to show"] member: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let user = member.as_ref().unwrap_or_else(|| ctx.author());
+
+    let balance = ctx.data().db.balance(guild, user.id).await?;
+    ctx.say(format!("{} has {}", user.name, economy::format(balance)))
+        .await?;
+
+    Ok(())
+}
+
+/// Sends some of your coins to another member
+///
+/// Usage: `/pay <member> <amount>`
+/// Example: `/pay @user 50`
+#[poise::command(slash_command, guild_only)]
+async fn pay(
+    ctx: Context<'_>,
+    #[description = "Member to send the coins to"] member: serenity::User,
+    #[description = "How many coins to send"]
+    #[min = 1]
+    amount: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+
+    if member.id == author || member.bot || amount < 1 {
+        ctx.send(|m| {
+            m.content(":x: You can only pay another member at least 1 coin.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let transfer = ctx
+        .data()
+        .db
+        .transfer(
+            guild,
+            Some(author),
+            Some(member.id),
+            amount,
+            "pay",
+            &economy::invocation_key(ctx, "pay"),
+        )
+        .await?;
+    let content = match transfer {
+        Transfer::Done | Transfer::Duplicate => format!(
+            ":white_check_mark: Sent {} to <@{}>",
+            economy::format(amount),
+            member.id.0
+        ),
+        Transfer::Insufficient => format!(
+            ":x: You only have {}.",
+            economy::format(ctx.data().db.balance(guild, author).await?)
+        ),
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Claims your daily coins
+///
+/// Usage: `/daily`
+/// Example: `/daily`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn daily(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+
+    let (today, offset) = streaks::today(db, author).await?;
+    let key = economy::daily_key("daily", guild, author, today);
+
+    let transfer = db
+        .transfer(guild, None, Some(author), DAILY_AMOUNT, "daily", &key)
+        .await?;
+    if transfer == Transfer::Duplicate {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: You claimed your coins today already, come back <t:{}:R>.",
+                streaks::day_end(today, offset)
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Only the claim that went through counts today, so the streak can't grow twice
+    let streak = db
+        .record_streak(guild, author, Activity::Daily, today)
+        .await?
+        .map_or(1, |advanced| advanced.streak.current);
+    let bonus = streaks::daily_bonus(DAILY_AMOUNT, streak);
+    if bonus > 0 {
+        let key = format!("{}:bonus", key);
+        db.transfer(guild, None, Some(author), bonus, "daily", &key)
+            .await?;
+    }
+
+    let boosting = match ctx.author_member().await {
+        Some(member) => member.premium_since.is_some(),
+        None => false,
+    };
+    let booster_bonus = if boosting { boosts::DAILY_BONUS } else { 0 };
+    if booster_bonus > 0 {
+        let key = format!("{}:booster", key);
+        db.transfer(guild, None, Some(author), booster_bonus, "daily", &key)
+            .await?;
+    }
+
+    let voter_bonus = if votes::perks_until(db, author).await?.is_some() {
+        votes::DAILY_BONUS
+    } else {
+        0
+    };
+    if voter_bonus > 0 {
+        let key = format!("{}:vote", key);
+        db.transfer(guild, None, Some(author), voter_bonus, "daily", &key)
+            .await?;
+    }
+
+    let mut content = format!(
+        ":white_check_mark: You got {}",
+        economy::format(DAILY_AMOUNT + bonus + booster_bonus + voter_bonus)
+    );
+    if streak > 1 {
+        content.push_str(&format!(
+            ", {} of them for your {} day streak",
+            bonus, streak
+        ));
+    }
+    if booster_bonus > 0 {
+        content.push_str(&format!(", {} for boosting", booster_bonus));
+    }
+    if voter_bonus > 0 {
+        content.push_str(&format!(", {} for voting", voter_bonus));
+    }
+    content.push_str(&format!(
+        ", you have {} now",
+        economy::format(db.balance(guild, author).await?)
+    ));
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Works a job for coins, pays more the higher your level is
+///
+/// Usage: `/work`
+/// Example: `/work`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn work(ctx: Context<'_>) -> Result<(), Error> {
+    let content = work::work(ctx).await?;
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Commits a crime, pays more than working but you may get fined
+///
+/// Usage: `/crime`
+/// Example: `/crime`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn crime(ctx: Context<'_>) -> Result<(), Error> {
+    let content = work::crime(ctx).await?;
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// Tries to steal some of another member's coins, if you're caught you pay them
+///
+/// Usage: `/rob <member>`
+/// Example: `/rob @user`
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn rob(
+    ctx: Context<'_>,
+    #[description = "Member to rob"] member: serenity::User,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let chance = ctx.data().guild_configs.get(guild).await?.rob_chance();
+
+    let content = work::rob(ctx, &member, chance).await?;
+    ctx.say(content).await?;
+
+    Ok(())
+}
+
+/// Side of a coin
+#[derive(Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+enum Side {
+    #[name = "heads"]
+    Heads,
+    #[name = "tails"]
+    Tails,
+}
+
+/// Bets coins on three reels of fruit
+///
+/// Usage: `/slots <bet>`
+/// Example: `/slots 50`
+#[poise::command(slash_command, guild_only, user_cooldown = 3)]
+async fn slots(
+    ctx: Context<'_>,
+    #[description = "How many coins to bet"]
+    #[min = 1]
+    bet: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+    if let Some(refusal) = gambling::check_bet(ctx, &config, bet).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let reels = gambling::spin_reels();
+    let payout = gambling::slots_payout(reels, bet, config.house_edge());
+    let outcome = match payout {
+        0 => "no luck this time".to_string(),
+        payout => format!("you won {}", economy::format(payout)),
+    };
+    settle(
+        ctx,
+        "slots",
+        bet,
+        payout,
+        format!("{} | {}", gambling::show_reels(reels), outcome),
+    )
+    .await
+}
+
+/// Bets coins on where the roulette ball lands
+///
+/// Usage: `/roulette <bet> <space>`
+/// Example: `/roulette 50 red`
+#[poise::command(slash_command, guild_only, user_cooldown = 3)]
+async fn roulette(
+    ctx: Context<'_>,
+    #[description = "How many coins to bet"]
+    #[min = 1]
+    bet: i64,
+    #[description = "red, black, even, odd or a number from 0 to 36"] space: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+
+    let space = match Space::parse(&space) {
+        Some(space) => space,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: Bet on `red`, `black`, `even`, `odd` or a number from 0 to 36.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+    if let Some(refusal) = gambling::check_bet(ctx, &config, bet).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let pocket = gambling::spin_wheel();
+    let (payout, outcome) = if space.wins(pocket) {
+        let payout = space.payout(bet, config.house_edge());
+        (payout, format!("you won {}", economy::format(payout)))
+    } else {
+        (0, "no luck this time".to_string())
+    };
+    let summary = format!(
+        "The ball landed on **{}** ({}), {}",
+        pocket,
+        gambling::color(pocket),
+        outcome
+    );
+    settle(ctx, "roulette", bet, payout, summary).await
+}
+
+/// Bets coins on a coin flip
+///
+/// Usage: `/coinbet <bet> <side>`
+/// Example: `/coinbet 50 heads`
+#[poise::command(slash_command, guild_only, user_cooldown = 3)]
+async fn coinbet(
+    ctx: Context<'_>,
+    #[description = "How many coins to bet"]
+    #[min = 1]
+    bet: i64,
+    #[description = "Side you bet on"] side: Side,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+    if let Some(refusal) = gambling::check_bet(ctx, &config, bet).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let flipped = if rand::random() {
+        Side::Heads
+    } else {
+        Side::Tails
+    };
+    let (payout, outcome) = if flipped == side {
+        let payout = gambling::coin_payout(bet, config.house_edge());
+        (payout, format!("you won {}", economy::format(payout)))
+    } else {
+        (0, "no luck this time".to_string())
+    };
+    let summary = format!("The coin landed on **{}**, {}", flipped.name(), outcome);
+    settle(ctx, "coinbet", bet, payout, summary).await
+}
+
+// Takes the bet, pays out and replies with `summary` and the new balance
+async fn settle(
+    ctx: Context<'_>,
+    game: &str,
+    bet: i64,
+    payout: i64,
+    summary: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    match gambling::settle(ctx, game, bet, payout).await? {
+        Some(balance) => {
+            ctx.say(format!(
+                "{}\n{} bet {}, and has {} now",
+                summary,
+                ctx.author().name,
+                economy::format(bet),
+                economy::format(balance)
+            ))
+            .await?;
+        }
+        None => {
+            let balance = ctx.data().db.balance(guild, ctx.author().id).await?;
+            ctx.send(|m| {
+                m.content(format!(":x: You only have {}.", economy::format(balance)))
+                    .ephemeral(true)
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Buy roles, titles and perks with your coins
+///
+/// Usage: `/shop list`, `/shop buy <item>` or `/shop admin add|remove`
+/// Example: `/shop buy VIP`
+#[poise::command(slash_command, guild_only, subcommands("shop_list", "buy", "admin"))]
+async fn shop(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows what this server's shop sells
+///
+/// Usage: `/shop list`
+/// Example: `/shop list`
+#[poise::command(slash_command, guild_only, rename = "list")]
+async fn shop_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let items = ctx.data().db.shop_items(guild).await?;
+
+    if items.is_empty() {
+        ctx.send(|m| m.content("This server's shop is empty.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let balance = ctx.data().db.balance(guild, ctx.author().id).await?;
+    ctx.send(|m| {
+        m.embed(|e| {
+            for item in &items {
+                let mut value = format!("{}, {}", economy::format(item.price), item.kind);
+                if let Some(role) = item.role_id {
+                    value.push_str(&format!(" <@&{}>", role));
+                }
+                if let Some(rental) = item.rental_secs {
+                    value.push_str(&format!(
+                        " for {}",
+                        duration::format(Duration::from_secs(rental as u64))
+                    ));
+                }
+                if let Some(description) = &item.description {
+                    value.push_str(&format!("\n{}", description));
+                }
+                e.field(&item.name, value, false);
+            }
+            e.title("Shop").footer(|f| {
+                f.text(format!(
+                    "You have {} {}, buy with /shop buy",
+                    balance,
+                    economy::CURRENCY
+                ))
+            })
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Buys an item from the shop
+///
+/// Usage: `/shop buy <item>`
+/// Example: `/shop buy VIP`
+#[poise::command(slash_command, guild_only)]
+async fn buy(
+    ctx: Context<'_>,
+    #[description = "Item to buy"]
+    #[autocomplete = "autocomplete_item"]
+    item: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = match ctx.data().db.shop_item(guild, &item).await? {
+        Some(item) => {
+            ctx.defer_ephemeral().await?;
+            shop::buy(ctx, &item).await?
+        }
+        None => format!(":x: The shop doesn't sell `{}`.", item),
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Stock the shop of this server
+///
+/// Usage: `/shop admin add <name> <price> <kind> [role] [rental] [description]` or `/shop admin remove <name>`
+/// Example: `/shop admin add VIP 500 role @VIP 30d`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("admin_add", "admin_remove"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds an item to the shop
+///
+/// Usage: `/shop admin add <name> <price> <kind> [role] [rental] [description]`
+/// Example: `/shop admin add VIP 500 role @VIP 30d`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn admin_add(
+    ctx: Context<'_>,
+    #[description = "Name members buy it by"] name: String,
+    #[description = "Price in coins"]
+    #[min = 1]
+    price: i64,
+    #[description = "What the item is"] kind: ItemKind,
+    #[description = "Role it gives, for role items"] role: Option<serenity::Role>,
+    #[description = "How long the role is rented for, e.g. 30d, forever if empty"] rental: Option<
+        String,
+    >,
+    #[description = "What members get"] description: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+    let name = name.trim();
+
+    let rental = match rental.as_deref().map(duration::parse) {
+        None => None,
+        Some(Some(rental)) if rental <= MAX_RENTAL => Some(rental),
+        Some(_) => {
+            ctx.send(|m| {
+                m.content(":x: Rentals are written like `7d` or `12h`, and up to a year.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let refusal = if name.is_empty() || name.chars().count() > shop::MAX_NAME_LENGTH {
+        Some(format!(
+            ":x: Item names can be up to {} characters.",
+            shop::MAX_NAME_LENGTH
+        ))
+    } else if (kind == ItemKind::Role) != role.is_some() {
+        Some(":x: Role items need a role, other items can't have one.".to_string())
+    } else if rental.is_some() && kind != ItemKind::Role {
+        Some(":x: Only role items can be rented.".to_string())
+    } else if db.shop_items(guild).await?.len() >= shop::MAX_ITEMS {
+        Some(format!(
+            ":x: Shops can have up to {} items.",
+            shop::MAX_ITEMS
+        ))
+    } else {
+        match &role {
+            Some(role) => check_role(ctx, role).await.map(str::to_string),
+            None => None,
+        }
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let item = NewShopItem {
+        name,
+        kind: kind.name(),
+        price,
+        description: description.as_deref(),
+        role: role.map(|r| r.id),
+        rental,
+    };
+    let content = if db.create_shop_item(guild, &item).await? {
+        format!(
+            ":white_check_mark: Added **{}** to the shop for {}",
+            name,
+            economy::format(price)
+        )
+    } else {
+        format!(":x: The shop sells **{}** already.", name)
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Removes an item from the shop and the inventories of everyone who bought it
+///
+/// Usage: `/shop admin remove <name>`
+/// Example: `/shop admin remove VIP`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn admin_remove(
+    ctx: Context<'_>,
+    #[description = "Item to remove"]
+    #[autocomplete = "autocomplete_item"]
+    name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    // Rented roles are still taken away when their rental ends
+    let content = if ctx.data().db.delete_shop_item(guild, name.trim()).await? {
+        format!(
+            ":white_check_mark: Removed **{}** from the shop",
+            name.trim()
+        )
+    } else {
+        format!(":x: The shop doesn't sell `{}`.", name)
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Shows what you or another member bought
+///
+/// Usage: `/inventory [member]`
+/// Example: `/inventory`
+#[poise::command(slash_command, guild_only)]
+async fn inventory(
+    ctx: Context<'_>,
+    #[description = "Member to show"] member: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let user = member.as_ref().unwrap_or_else(|| ctx.author());
+
+    let items = ctx.data().db.inventory(guild, user.id).await?;
+    let mut list = String::new();
+    for item in &items {
+        list.push_str(&format!("**{}**", item.name));
+        match item.role_id {
+            Some(role) => list.push_str(&format!(" <@&{}>", role)),
+            None => list.push_str(&format!(" ({})", item.kind)),
+        }
+        if item.quantity > 1 {
+            list.push_str(&format!(" x{}", item.quantity));
+        }
+        if let Some(expires_at) = item.expires_at {
+            list.push_str(&format!(", until <t:{}:f>", expires_at));
+        }
+        list.push('\n');
+    }
+    if list.is_empty() {
+        list = format!("{} hasn't bought anything yet.", user.name);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Inventory of {}", user.name))
+                .description(list)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn autocomplete_item<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let items = match ctx.guild_id() {
+        Some(guild) => ctx.data().db.shop_items(guild).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    items
+        .into_iter()
+        .map(|item| item.name)
+        .filter(move |name| name.to_lowercase().starts_with(&partial))
+        // Discord shows at most 25 choices
+        .take(25)
+}
+
+command_list!["Economy": balance, pay, daily, shop, inventory, work, crime, rob, slots, roulette,
+    coinbet];
@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    emojipack::{self, Collision, MAX_EXPORT_FILES, MAX_IMPORT_BYTES},
+    Context, Error,
+};
+
+// Skipped files listed in the import reply, the rest are only counted
+const MAX_LISTED_SKIPS: usize = 15;
+
+/// Copy the custom emojis of one server to another as a zip
+///
+/// Usage: `/emojipack export` or `/emojipack import <zip> [on_collision]`
+/// Example: `/emojipack import pack.zip on_collision:rename`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("export", "import"),
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS",
+    default_member_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+async fn emojipack(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Downloads every custom emoji of this server into a zip
+///
+/// Usage: `/emojipack export`
+/// Example: `/emojipack export`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+async fn export(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.defer_ephemeral().await?;
+    let export =
+        emojipack::export(&ctx.discord().http, guild, ctx.data().worker.as_deref()).await?;
+    if export.archives.is_empty() {
+        let content = if export.failed.is_empty() {
+            ":x: This server has no custom emojis.".to_string()
+        } else {
+            format!(
+                ":x: None of the {} emojis could be downloaded, try again later.",
+                export.failed.len()
+            )
+        };
+        ctx.send(|m| m.content(content).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let mut content = format!(
+        ":white_check_mark: Exported {} emojis in {} zips",
+        export.exported,
+        export.archives.len()
+    );
+    if !export.failed.is_empty() {
+        content.push_str(&format!(", {} couldn't be downloaded", export.failed.len()));
+    }
+
+    let count = export.archives.len();
+    let mut archives = export.archives.into_iter().enumerate().peekable();
+    let mut first = true;
+    while archives.peek().is_some() {
+        let batch: Vec<_> = archives.by_ref().take(MAX_EXPORT_FILES).collect();
+        ctx.send(|m| {
+            if first {
+                m.content(&content);
+            }
+            for (i, archive) in batch {
+                let filename = if count == 1 {
+                    "emojis.zip".to_string()
+                } else {
+                    format!("emojis-{}.zip", i + 1)
+                };
+                m.attachment(serenity::AttachmentType::Bytes {
+                    data: Cow::Owned(archive),
+                    filename,
+                });
+            }
+            m.ephemeral(true)
+        })
+        .await?;
+        first = false;
+    }
+
+    Ok(())
+}
+
+/// Uploads the images in a zip as emojis of this server
+///
+/// Usage: `/emojipack import <zip> [on_collision]`
+/// Example: `/emojipack import pack.zip on_collision:replace`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_EMOJIS_AND_STICKERS",
+    required_bot_permissions = "MANAGE_EMOJIS_AND_STICKERS"
+)]
+async fn import(
+    ctx: Context<'_>,
+    #[description = "Zip with PNG, GIF, JPEG or WebP images named after the emojis"]
+    zip: serenity::Attachment,
+    #[description = "What to do with emojis named like existing ones, skip by default"]
+    on_collision: Option<Collision>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if zip.size > MAX_IMPORT_BYTES {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Zips can be up to {} MB.",
+                MAX_IMPORT_BYTES / 1024 / 1024
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+    let archive = emojipack::download(&zip.url).await?;
+    let report = match emojipack::import(
+        &ctx.discord().http,
+        guild,
+        &archive,
+        on_collision.unwrap_or(Collision::Skip),
+    )
+    .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            ctx.send(|m| {
+                m.content(format!(":x: Couldn't import the zip: {}", e))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut content = if report.imported.is_empty() {
+        ":x: No emojis were imported.".to_string()
+    } else {
+        format!(
+            ":white_check_mark: Imported {} emojis",
+            report.imported.len()
+        )
+    };
+    if !report.skipped.is_empty() {
+        content.push_str(&format!("\nSkipped {} files:", report.skipped.len()));
+        for (file, reason) in report.skipped.iter().take(MAX_LISTED_SKIPS) {
+            content.push_str(&format!("\n`{}`: {}", file, reason));
+        }
+        if report.skipped.len() > MAX_LISTED_SKIPS {
+            content.push_str(&format!(
+                "\n...and {} more",
+                report.skipped.len() - MAX_LISTED_SKIPS
+            ));
+        }
+    }
+
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+command_list!["Emojis": emojipack];
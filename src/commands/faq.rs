@@ -0,0 +1,188 @@
+use crate::{
+    db::Faq,
+    faq::{self, MAX_FAQS},
+    mentions::Mentions,
+    Context, Error,
+};
+
+// Titles of embeds can't be longer than this
+const MAX_QUESTION_LENGTH: usize = 256;
+const MAX_ANSWER_LENGTH: usize = 2000;
+const DEFAULT_CONFIDENCE: u32 = 60;
+
+/// Answers to frequently asked questions in help channels
+///
+/// Usage: `/faq add <question> <answer> [keywords] [regex] [confidence]`, `/faq remove <id>` or `/faq list`
+/// Example: `/faq add "How do I reset my password?" "Use Forgot password on the login page." keywords:reset password`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn faq(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Answers questions in help channels that have enough of the keywords or match the regex
+///
+/// Usage: `/faq add <question> <answer> [keywords] [regex] [confidence]`
+/// Example: `/faq add "Where do I report bugs?" "In #bug-reports!" keywords:report bug confidence:100`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The question, shown as the answer's title"] question: String,
+    #[description = "What to answer with"] answer: String,
+    #[description = "Words questions have, separated by spaces"] keywords: Option<String>,
+    #[description = "Regex questions match, ignoring case"] regex: Option<String>,
+    #[description = "Share of the keywords in percent a question needs, 60 if not given"]
+    #[min = 1]
+    #[max = 100]
+    confidence: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let question = question.trim().to_string();
+    let answer = answer.trim().to_string();
+    let keywords = keywords
+        .map(|k| {
+            let mut words: Vec<_> = faq::words(&k).into_iter().collect();
+            words.sort_unstable();
+            words.join(" ")
+        })
+        .filter(|k| !k.is_empty());
+    let regex = regex.filter(|r| !r.trim().is_empty());
+
+    let refusal = if question.is_empty() || question.chars().count() > MAX_QUESTION_LENGTH {
+        Some(format!(
+            ":x: The question can be up to {} characters.",
+            MAX_QUESTION_LENGTH
+        ))
+    } else if answer.is_empty() || answer.chars().count() > MAX_ANSWER_LENGTH {
+        Some(format!(
+            ":x: The answer can be up to {} characters.",
+            MAX_ANSWER_LENGTH
+        ))
+    } else if keywords.is_none() && regex.is_none() {
+        Some(":x: Please give keywords, a regex or both to match questions with.".to_string())
+    } else if let Some(Err(e)) = regex.as_deref().map(faq::compile_regex) {
+        Some(format!(":x: That regex doesn't work: ```{}```", e))
+    } else if ctx.data().db.faqs(guild).await?.len() >= MAX_FAQS {
+        Some(format!(
+            ":x: Servers can have up to {} FAQs, remove one first.",
+            MAX_FAQS
+        ))
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let faq = Faq {
+        guild_id: guild.0 as i64,
+        question,
+        answer,
+        keywords,
+        regex,
+        min_confidence: confidence.unwrap_or(DEFAULT_CONFIDENCE) as i64,
+        ..Faq::default()
+    };
+    let id = ctx.data().faqs.add(&faq).await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Added FAQ #{}, it's answered in channels set to `help` \
+            with `/config channel`",
+            id
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a FAQ
+///
+/// Usage: `/faq remove <id>`
+/// Example: `/faq remove 3`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "ID from /faq list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().faqs.remove(guild, id).await? {
+        format!(":white_check_mark: Removed FAQ #{}", id)
+    } else {
+        format!(":x: There is no FAQ #{}.", id)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the FAQs of this server, how often they were answered and whether they helped
+///
+/// Usage: `/faq list`
+/// Example: `/faq list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let faqs = ctx.data().db.faqs(guild).await?;
+
+    if faqs.is_empty() {
+        ctx.send(|m| m.content("This server has no FAQs.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for faq in &faqs {
+        let mut matcher = Vec::new();
+        if let Some(keywords) = &faq.keywords {
+            matcher.push(format!("`{}` from {}%", keywords, faq.min_confidence));
+        }
+        if let Some(regex) = &faq.regex {
+            matcher.push(format!("regex `{}`", regex));
+        }
+        let rated = faq.helpful + faq.unhelpful;
+        let helped = if rated == 0 {
+            "not rated yet".to_string()
+        } else {
+            format!("helped {}% of {} rated", faq.helpful * 100 / rated, rated)
+        };
+
+        let line = format!(
+            "#{} **{}** {}\nAnswered {} times, {}\n",
+            faq.id,
+            faq.question,
+            matcher.join(" or "),
+            faq.matches,
+            helped
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("FAQs")
+                .description(list)
+                .footer(|f| f.text(format!("{} of {}", faqs.len(), MAX_FAQS)))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Triggers": faq];
@@ -0,0 +1,13 @@
+use crate::{Context, Error};
+
+/// h
+///
+/// Usage: `/h`
+/// Example: `~h`
+#[poise::command(prefix_command, slash_command, channel_cooldown = 5)]
+async fn h(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("h").await?;
+    Ok(())
+}
+
+command_list!["Fun": h];
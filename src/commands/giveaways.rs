@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{duration, giveaways, mentions::Mentions, Context, Error};
+
+// Longer giveaways are more likely forgotten than won
+const MAX_GIVEAWAY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const MIN_GIVEAWAY: Duration = Duration::from_secs(60);
+// Embed titles can't be longer than 256 characters, minus the emoji in front
+const MAX_PRIZE_LENGTH: usize = 200;
+
+/// Give something away to a random member who pressed a button
+///
+/// Usage: `/giveaway start <duration> <prize>` or `/giveaway reroll <id>`
+/// Example: `/giveaway start 2d "Nitro Classic"`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("start", "reroll"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn giveaway(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Posts a giveaway in this channel, a winner is drawn when it ends
+///
+/// Usage: `/giveaway start <duration> <prize>`
+/// Example: `/giveaway start 1w "Steam key for Portal 2"`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    required_bot_permissions = "SEND_MESSAGES"
+)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "How long members can enter, like 1h or 3d"] duration: String,
+    #[description = "What the winner gets"] prize: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let length = match duration::parse(&duration) {
+        Some(length) if (MIN_GIVEAWAY..=MAX_GIVEAWAY).contains(&length) => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Please give a duration like `1h` or `3d`, from {} up to {}.",
+                    duration::format(MIN_GIVEAWAY),
+                    duration::format(MAX_GIVEAWAY)
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let prize = prize.trim();
+    if prize.is_empty() || prize.chars().count() > MAX_PRIZE_LENGTH {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Prizes can be up to {} characters.",
+                MAX_PRIZE_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let ends_at = serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64;
+    let id = db
+        .create_giveaway(guild, ctx.channel_id(), ctx.author().id, prize, ends_at)
+        .await?;
+    let giveaway = db.giveaway(id, None).await?.ok_or("Giveaway disappeared")?;
+    giveaways::post(ctx.discord(), db, &giveaway).await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Started giveaway #{}, it ends <t:{}:R>",
+            id, ends_at
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Draws another winner for a giveaway that has ended
+///
+/// Usage: `/giveaway reroll <id>`
+/// Example: `/giveaway reroll 12`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn reroll(
+    ctx: Context<'_>,
+    #[description = "ID from the giveaway's footer"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let content = match db.giveaway(id, Some(guild)).await? {
+        None => format!(":x: There is no giveaway #{}.", id),
+        Some(giveaway) if !giveaway.ended => format!(
+            ":x: Giveaway #{} is still running, it ends <t:{}:R>.",
+            id, giveaway.ends_at
+        ),
+        Some(giveaway) => match giveaways::reroll(ctx.discord(), db, &giveaway).await? {
+            Some(winner) => format!(":white_check_mark: <@{}> won the reroll", winner.0),
+            None => ":x: There is nobody else to pick.".to_string(),
+        },
+    };
+
+    ctx.send(|m| {
+        m.content(content)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Utility": giveaway];
@@ -0,0 +1,213 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    kb::{self, MAX_RESULTS, MAX_TITLE_LENGTH},
+    mentions::Mentions,
+    Context, Error,
+};
+
+// Leaves room in the embed description
+const MAX_ARTICLE_LENGTH: usize = 4000;
+
+/// This server's knowledge base
+///
+/// Usage: `/kb search <query>`, `/kb add <title> <text>`, `/kb edit <title> <text>` or `/kb remove <title>`
+/// Example: `/kb search reset password`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("search", "add", "edit", "remove")
+)]
+async fn kb(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows the article with a title, or searches for the best matching ones
+///
+/// Usage: `/kb search <query>`
+/// Example: `/kb search how do I get a refund`
+#[poise::command(slash_command, guild_only, channel_cooldown = 3)]
+async fn search(
+    ctx: Context<'_>,
+    #[description = "Title of an article or words to search for"]
+    #[autocomplete = "autocomplete_title"]
+    query: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    if let Some(article) = db.kb_article(guild, query.trim()).await? {
+        ctx.send(|m| {
+            m.embed(|e| {
+                e.title(&article.title)
+                    .description(&article.body)
+                    .field("Last edited by", format!("<@{}>", article.author_id), true)
+                    .timestamp(
+                        serenity::Timestamp::from_unix_timestamp(article.updated_at)
+                            .unwrap_or_else(|_| serenity::Timestamp::now()),
+                    )
+            })
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let results = match kb::fts_query(&query) {
+        Some(fts) => db.search_kb(guild, &fts, MAX_RESULTS).await?,
+        None => Vec::new(),
+    };
+    if results.is_empty() {
+        ctx.send(|m| {
+            m.content(":x: No articles match that, try other words.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Articles for \"{}\"", query.trim()));
+            for (title, snippet) in &results {
+                e.field(title, snippet, false);
+            }
+            e.footer(|f| f.text("Show one with /kb search and its title"))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Writes a new article
+///
+/// Usage: `/kb add <title> <text>`
+/// Example: `/kb add Refunds Ask in #billing with your order number.`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_MESSAGES")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "Title of the article"] title: String,
+    #[description = "Text of the article"] text: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let title = title.trim();
+
+    if let Some(refusal) = refuse(title, &text) {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let response = if ctx
+        .data()
+        .db
+        .add_kb_article(guild, title, text.trim(), ctx.author().id)
+        .await?
+    {
+        format!(":white_check_mark: Added the article `{}`", title)
+    } else {
+        format!(
+            ":x: There is an article called `{}` already, change it with /kb edit.",
+            title
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Replaces the text of an article
+///
+/// Usage: `/kb edit <title> <text>`
+/// Example: `/kb edit Refunds Open a ticket with /ticket and your order number.`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_MESSAGES")]
+async fn edit(
+    ctx: Context<'_>,
+    #[description = "Title of the article"]
+    #[autocomplete = "autocomplete_title"]
+    title: String,
+    #[description = "New text of the article"] text: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let title = title.trim();
+
+    if let Some(refusal) = refuse(title, &text) {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let response = if ctx
+        .data()
+        .db
+        .edit_kb_article(guild, title, text.trim(), ctx.author().id)
+        .await?
+    {
+        format!(":white_check_mark: Changed the article `{}`", title)
+    } else {
+        format!(":x: There is no article called `{}`.", title)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Deletes an article
+///
+/// Usage: `/kb remove <title>`
+/// Example: `/kb remove Refunds`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_MESSAGES")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Title of the article"]
+    #[autocomplete = "autocomplete_title"]
+    title: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let title = title.trim();
+
+    let response = if ctx.data().db.delete_kb_article(guild, title).await? {
+        format!(":white_check_mark: Deleted the article `{}`", title)
+    } else {
+        format!(":x: There is no article called `{}`.", title)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+// Why an article can't be saved, if it can't
+fn refuse(title: &str, text: &str) -> Option<String> {
+    if title.is_empty() || title.chars().count() > MAX_TITLE_LENGTH {
+        Some(format!(
+            ":x: Titles can be up to {} characters.",
+            MAX_TITLE_LENGTH
+        ))
+    } else if text.trim().is_empty() || text.chars().count() > MAX_ARTICLE_LENGTH {
+        Some(format!(
+            ":x: Articles can be up to {} characters.",
+            MAX_ARTICLE_LENGTH
+        ))
+    } else {
+        None
+    }
+}
+
+async fn autocomplete_title<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let titles = match ctx.guild_id() {
+        Some(guild) => ctx.data().db.kb_titles(guild).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    titles
+        .into_iter()
+        .filter(move |title| title.to_lowercase().contains(&partial))
+        // Discord shows at most 25 choices
+        .take(25)
+}
+
+command_list!["Tags": kb];
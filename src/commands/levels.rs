@@ -0,0 +1,152 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{levels::Level, promotions, Context, Error};
+
+// Members shown on one leaderboard page
+const LEADERBOARD_PAGE_SIZE: i64 = 10;
+
+/// Shows your or another member's level
+///
+/// Usage: `/rank [user]`
+/// Example: `/rank @user`
+#[poise::command(slash_command, prefix_command, guild_only, user_cooldown = 5)]
+async fn rank(
+    ctx: Context<'_>,
+    #[description = "Member to show"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+
+    let (xp, rank) = match ctx.data().db.member_rank(guild, user.id).await? {
+        Some(rank) => rank,
+        None => {
+            ctx.say(format!("{} hasn't earned any XP yet.", user.name))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let level = Level::from_xp(xp);
+
+    // Members that left still have a rank, but can't be promoted anymore
+    let rules = ctx.data().db.promotion_rules(Some(guild)).await?;
+    let member = if rules.is_empty() {
+        None
+    } else {
+        guild.member(ctx.discord(), user.id).await.ok()
+    };
+    let promotions = member.map(|member| {
+        let days = promotions::days_since(
+            member.joined_at,
+            serenity::Timestamp::now().unix_timestamp(),
+        );
+        rules
+            .iter()
+            .map(|rule| {
+                let role = serenity::RoleId(rule.role_id as u64);
+                let done =
+                    member.roles.contains(&role) || promotions::qualifies(rule, level.level, days);
+                format!(
+                    "{} <@&{}> at {}",
+                    if done { ":white_check_mark:" } else { ":lock:" },
+                    role.0,
+                    promotions::describe(rule)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.author(|a| a.name(user.tag()).icon_url(user.face()))
+                .field("Level", level.level, true)
+                .field("Rank", format!("#{}", rank), true)
+                .field("Total XP", xp, true)
+                .field(
+                    "Next level",
+                    format!(
+                        "{} {}/{} XP",
+                        progress_bar(level.progress, level.needed),
+                        level.progress,
+                        level.needed
+                    ),
+                    false,
+                );
+
+            if let Some(promotions) = &promotions {
+                e.field("Promotions", promotions, false);
+            }
+
+            e
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows the members with the most XP
+///
+/// Usage: `/leaderboard [page]`
+/// Example: `/leaderboard 2`
+#[poise::command(slash_command, prefix_command, guild_only, guild_cooldown = 10)]
+async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "Page to show"]
+    #[min = 1]
+    page: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let page = page.unwrap_or(1).max(1) as i64;
+
+    let offset = (page - 1) * LEADERBOARD_PAGE_SIZE;
+    let members = ctx
+        .data()
+        .db
+        .leaderboard(guild, LEADERBOARD_PAGE_SIZE, offset)
+        .await?;
+
+    if members.is_empty() {
+        ctx.say("Nobody is on that page yet.").await?;
+        return Ok(());
+    }
+
+    let list = members
+        .iter()
+        .enumerate()
+        .map(|(i, member)| {
+            format!(
+                "**{}.** <@{}> level {} ({} XP)",
+                offset + i as i64 + 1,
+                member.user_id,
+                Level::from_xp(member.xp).level,
+                member.xp
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Leaderboard")
+                .description(list)
+                .footer(|f| f.text(format!("Page {}", page)))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn progress_bar(progress: i64, needed: i64) -> String {
+    const WIDTH: i64 = 10;
+    let filled = (progress * WIDTH / needed.max(1)).clamp(0, WIDTH);
+    format!(
+        "{}{}",
+        "▰".repeat(filled as usize),
+        "▱".repeat((WIDTH - filled) as usize)
+    )
+}
+
+command_list!["Levels": rank, leaderboard];
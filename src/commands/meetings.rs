@@ -0,0 +1,174 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{meetings, mentions::Mentions, Context, Error};
+
+// Meeting titles go in embed titles, which can't be longer than 256 characters
+const MAX_TITLE_LENGTH: usize = 256;
+
+/// Record who attends a meeting in a voice channel and for how long
+///
+/// Usage: `/meeting start [channel] [title]` or `/meeting end [channel]`
+/// Example: `/meeting start channel:#standup title:"Weekly sync"`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("start", "end"),
+    required_permissions = "MANAGE_EVENTS",
+    default_member_permissions = "MANAGE_EVENTS"
+)]
+async fn meeting(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Starts a meeting in a voice channel, yours if none is given
+///
+/// Usage: `/meeting start [channel] [title]`
+/// Example: `/meeting start title:"Sprint planning"`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_EVENTS",
+    required_bot_permissions = "SEND_MESSAGES"
+)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "Voice channel of the meeting, the one you're in by default"]
+    #[channel_types("Voice", "Stage")]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "What the meeting is about"] title: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let voice = match channel.map(|c| c.id).or_else(|| voice_channel(ctx)) {
+        Some(voice) => voice,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: Join a voice channel or give one for the meeting.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let title = title.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    if title.is_some_and(|t| t.chars().count() > MAX_TITLE_LENGTH) {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Titles can be up to {} characters.",
+                MAX_TITLE_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let id = match db
+        .start_meeting(guild, voice, ctx.channel_id(), ctx.author().id, title, now)
+        .await?
+    {
+        Some(id) => id,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: <#{}> has a meeting running already, end it with `/meeting end`.",
+                    voice.0
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Whoever is in the channel already attends from the start
+    let present: Vec<serenity::UserId> = ctx
+        .guild()
+        .map(|g| {
+            g.voice_states
+                .values()
+                .filter(|v| v.channel_id == Some(voice))
+                .filter(|v| !v.member.as_ref().is_some_and(|m| m.user.bot))
+                .map(|v| v.user_id)
+                .collect()
+        })
+        .unwrap_or_default();
+    for user in present {
+        db.join_meeting(id, user, now).await?;
+    }
+
+    let meeting = db
+        .running_meeting(guild, voice)
+        .await?
+        .ok_or("Meeting disappeared")?;
+    meetings::announce(ctx.discord(), &meeting).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Started meeting #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Ends the meeting in a voice channel and posts who attended
+///
+/// Usage: `/meeting end [channel]`
+/// Example: `/meeting end channel:#standup`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_EVENTS")]
+async fn end(
+    ctx: Context<'_>,
+    #[description = "Voice channel of the meeting, the one you're in by default"]
+    #[channel_types("Voice", "Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let meeting = match channel.map(|c| c.id).or_else(|| voice_channel(ctx)) {
+        Some(voice) => db.running_meeting(guild, voice).await?,
+        None => None,
+    };
+    let mut meeting = match meeting {
+        Some(meeting) => meeting,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: There is no meeting running in that channel.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    if !db.end_meeting(meeting.id, now).await? {
+        ctx.send(|m| {
+            m.content(":x: This meeting has ended already.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+    meeting.ended_at = Some(now);
+
+    let attendance = db.meeting_attendance(meeting.id).await?;
+    ctx.send(|m| {
+        m.embed(|e| meetings::summary(e, &meeting, &attendance))
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+// The voice channel the author is in, from the cache
+fn voice_channel(ctx: Context<'_>) -> Option<serenity::ChannelId> {
+    ctx.guild()?.voice_states.get(&ctx.author().id)?.channel_id
+}
+
+command_list!["Utility": meeting];
@@ -0,0 +1,91 @@
+// Commands are grouped into modules by topic.
+// Each module lists its commands with `command_list!`, and `all()` collects them for the
+// framework, so adding a command only means touching its own module.
+// The category given to `command_list!` is what /help groups the commands by.
+use crate::{i18n, Data, Error};
+
+/// Generates a `commands()` function returning the given commands of a module in a category
+macro_rules! command_list {
+    ($category:literal: $($command:ident),* $(,)?) => {
+        pub fn commands() -> Vec<poise::Command<crate::Data, crate::Error>> {
+            let mut commands = vec![$($command()),*];
+            for command in &mut commands {
+                command.category = Some($category);
+            }
+            commands
+        }
+    };
+}
+
+mod applications;
+mod automod;
+mod channels;
+mod config;
+mod digests;
+mod economy;
+mod emojis;
+mod faq;
+mod fun;
+mod giveaways;
+mod kb;
+mod levels;
+mod meetings;
+mod moderation;
+mod notify;
+mod onboarding;
+mod oncall;
+mod owner;
+mod partners;
+mod polls;
+mod roles;
+mod search;
+mod stages;
+mod streaks;
+mod tags;
+mod themes;
+mod triggers;
+mod util;
+mod voice;
+mod welcome;
+
+/// Every command the bot registers, passed into `FrameworkOptions`
+pub fn all() -> Vec<poise::Command<Data, Error>> {
+    let mut commands: Vec<_> = vec![
+        applications::commands(),
+        automod::commands(),
+        channels::commands(),
+        config::commands(),
+        digests::commands(),
+        economy::commands(),
+        emojis::commands(),
+        faq::commands(),
+        fun::commands(),
+        giveaways::commands(),
+        kb::commands(),
+        levels::commands(),
+        meetings::commands(),
+        moderation::commands(),
+        notify::commands(),
+        oncall::commands(),
+        onboarding::commands(),
+        owner::commands(),
+        partners::commands(),
+        polls::commands(),
+        roles::commands(),
+        search::commands(),
+        stages::commands(),
+        streaks::commands(),
+        tags::commands(),
+        themes::commands(),
+        triggers::commands(),
+        util::commands(),
+        voice::commands(),
+        welcome::commands(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    i18n::localize(&mut commands);
+    commands
+}
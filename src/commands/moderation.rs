@@ -0,0 +1,1422 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    bans, dm, duration,
+    incidents::{self, EventKind},
+    jobs::{self, JobKind},
+    mentions::{self, Mentions},
+    modlog::{self, Action},
+    notes, oncall, watchlist, Context, Error,
+};
+
+// How many recent messages get copied into an incident thread by /staff
+const STAFF_SNAPSHOT_SIZE: u64 = 25;
+// Discord refuses to bulk delete messages older than this
+const BULK_DELETE_MAX_AGE: i64 = 14 * 24 * 60 * 60;
+
+/// Calls the on-duty staff to this channel
+///
+/// Usage: `/staff [reason]`
+/// Example: `/staff Someone is posting scam links`
+#[poise::command(
+    slash_command,
+    guild_only,
+    user_cooldown = 300,
+    required_bot_permissions = "CREATE_PUBLIC_THREADS | READ_MESSAGE_HISTORY"
+)]
+async fn staff(
+    ctx: Context<'_>,
+    #[description = "What is going on?"] reason: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let data = ctx.data();
+
+    // Only whoever is on call, so the rest of the staff isn't woken up
+    let on_call = oncall::on_call_now(&data.db, guild).await?;
+    let (ping, allowed) = match data.guild_configs.get(guild).await?.staff_role {
+        _ if !on_call.is_empty() => (
+            on_call
+                .iter()
+                .map(|u| format!("<@{}>", u.0))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Mentions::Users,
+        ),
+        Some(role) => (format!("<@&{}>", role.0), Mentions::Roles(vec![role])),
+        None => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: No staff role has been set up and nobody is on call, see \
+                    `/config set staff_role` and `/oncall add`.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let http = &ctx.discord().http;
+    let channel = ctx.channel_id();
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    // Grab the messages before posting the alert so it doesn't end up in the snapshot
+    let mut snapshot = channel
+        .messages(http, |m| m.limit(STAFF_SNAPSHOT_SIZE))
+        .await?;
+    snapshot.reverse();
+
+    let alert = ctx
+        .send(|m| {
+            m.content(format!(
+                ":rotating_light: {} staff requested by {}: {}",
+                ping,
+                ctx.author().tag(),
+                reason
+            ))
+            .allowed_mentions(|a| allowed.apply(a))
+        })
+        .await?
+        .into_message()
+        .await?;
+
+    let thread = channel
+        .create_public_thread(http, alert.id, |t| {
+            t.name(format!("incident-{}", ctx.author().name))
+        })
+        .await?;
+
+    // Calling staff opens an incident, unless one is going on already
+    let title: String = reason.chars().take(incidents::MAX_TITLE_LENGTH).collect();
+    data.db
+        .open_incident(guild, &title, ctx.author().id)
+        .await?;
+    let channel_name = channel
+        .name(ctx.discord())
+        .await
+        .unwrap_or_else(|| channel.0.to_string());
+    incidents::record(
+        data,
+        guild,
+        EventKind::StaffCall,
+        &format!(
+            "{} called staff in #{}: {} ({})",
+            ctx.author().tag(),
+            channel_name,
+            reason,
+            alert.link()
+        ),
+    )
+    .await;
+
+    // Post the snapshot in chunks so we stay under the message length limit
+    let mut chunk = String::new();
+    for message in snapshot {
+        let line = format!(
+            "`{}` **{}**: {}\n",
+            message.timestamp.format("%H:%M:%S"),
+            message.author.tag(),
+            message.content
+        );
+
+        if !chunk.is_empty() && chunk.len() + line.len() > 1900 {
+            mentions::send_message(http, thread.id, Mentions::Nothing, |m| m.content(&chunk))
+                .await?;
+            chunk.clear();
+        }
+        chunk.push_str(&line.chars().take(1900).collect::<String>());
+    }
+
+    if !chunk.is_empty() {
+        mentions::send_message(http, thread.id, Mentions::Nothing, |m| m.content(&chunk)).await?;
+    }
+
+    Ok(())
+}
+
+/// Keep a timeline of an incident and export a postmortem
+///
+/// Usage: `/incident start <title>`, `/incident note <text>` or `/incident close [summary]`
+/// Example: `/incident start Raid in #general`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("incident_start", "incident_note", "incident_close"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn incident(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Opens an incident, moderation actions and flagged messages are collected until it's closed
+///
+/// Usage: `/incident start <title>`
+/// Example: `/incident start Raid in #general`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "start",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn incident_start(
+    ctx: Context<'_>,
+    #[description = "What the incident is about"] title: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let title = title.trim();
+
+    let content = if title.is_empty() || title.chars().count() > incidents::MAX_TITLE_LENGTH {
+        format!(
+            ":x: Titles can be 1 to {} characters.",
+            incidents::MAX_TITLE_LENGTH
+        )
+    } else {
+        let db = &ctx.data().db;
+        match db.open_incident(guild, title, ctx.author().id).await? {
+            Some(id) => format!(
+                ":white_check_mark: Opened incident #{}, close it with `/incident close`",
+                id
+            ),
+            None => {
+                let open = db.open_incident_of(guild).await?;
+                format!(
+                    ":x: Incident **{}** is still open, close it with `/incident close` first.",
+                    open.map(|i| i.title).unwrap_or_default()
+                )
+            }
+        }
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Adds a note to the timeline of the open incident
+///
+/// Usage: `/incident note <text>`
+/// Example: `/incident note Raid accounts all joined through the same invite`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "note",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn incident_note(
+    ctx: Context<'_>,
+    #[description = "What happened"] text: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let data = ctx.data();
+
+    let content = if data.db.open_incident_of(guild).await?.is_none() {
+        ":x: There is no open incident, start one with `/incident start`.".to_string()
+    } else {
+        incidents::record(
+            data,
+            guild,
+            EventKind::Note,
+            &format!("{}: {}", ctx.author().tag(), text.trim()),
+        )
+        .await;
+        ":white_check_mark: Added to the timeline".to_string()
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Closes the open incident and exports its postmortem
+///
+/// Usage: `/incident close [summary]`
+/// Example: `/incident close Raid stopped by enabling verification, 14 accounts banned`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "close",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn incident_close(
+    ctx: Context<'_>,
+    #[description = "What happened and what was done about it"] summary: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let data = ctx.data();
+
+    let (incident, events) = match data.db.close_incident(guild, ctx.author().id).await? {
+        Some(closed) => closed,
+        None => {
+            ctx.send(|m| m.content(":x: There is no open incident.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let opened_by = match serenity::UserId(incident.opened_by as u64)
+        .to_user(ctx.discord())
+        .await
+    {
+        Ok(user) => user.tag(),
+        Err(_) => incident.opened_by.to_string(),
+    };
+    let doc = incidents::postmortem(
+        &incident,
+        &events,
+        &opened_by,
+        &ctx.author().tag(),
+        summary.as_deref().map(str::trim),
+    );
+    let filename = format!("incident-{}.md", incident.id);
+
+    let config = data.guild_configs.get(guild).await?;
+    if let Some(channel) = config.log_channel {
+        let result = mentions::send_message(ctx.discord(), channel, Mentions::Nothing, |m| {
+            m.content(format!(
+                "Postmortem of incident #{}: **{}**",
+                incident.id, incident.title
+            ))
+            .add_file(serenity::AttachmentType::Bytes {
+                data: doc.clone().into_bytes().into(),
+                filename: filename.clone(),
+            })
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Error posting postmortem to log channel: {}", e);
+        }
+    }
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Closed incident #{} with {} events",
+            incident.id,
+            events.len()
+        ))
+        .attachment(serenity::AttachmentType::Bytes {
+            data: doc.into_bytes().into(),
+            filename,
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Kicks a member from the server
+///
+/// Usage: `/kick <user> [reason]`
+/// Example: `/kick @user Advertising`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "KICK_MEMBERS",
+    required_bot_permissions = "KICK_MEMBERS",
+    default_member_permissions = "KICK_MEMBERS"
+)]
+async fn kick(
+    ctx: Context<'_>,
+    #[description = "Member to kick"] user: serenity::User,
+    #[description = "Why they are being kicked"] reason: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    if let Some(refusal) = check_hierarchy(ctx, &user).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    // DM first, we can't reach them once they share no server with us
+    notify(
+        ctx,
+        &user,
+        &format!(
+            "You have been kicked from **{}**.\nReason: {}",
+            guild_name(ctx),
+            reason
+        ),
+    )
+    .await;
+    guild
+        .kick_with_reason(ctx.discord(), user.id, &reason)
+        .await?;
+
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "kicked",
+            target: &user,
+            reason: &reason,
+            details: None,
+        },
+    )
+    .await?;
+
+    ctx.say(format!(":white_check_mark: Kicked **{}**", user.tag()))
+        .await?;
+    Ok(())
+}
+
+/// Bans a user from the server, for good or for a while
+///
+/// Usage: `/ban <user> [reason] [delete_days] [duration]`
+/// Example: `/ban @user Raiding 1` or `/ban @user Spamming 0 7d`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "BAN_MEMBERS",
+    required_bot_permissions = "BAN_MEMBERS",
+    default_member_permissions = "BAN_MEMBERS"
+)]
+async fn ban(
+    ctx: Context<'_>,
+    #[description = "User to ban"] user: serenity::User,
+    #[description = "Why they are being banned"] reason: Option<String>,
+    #[description = "Days of their messages to delete (0-7)"]
+    #[min = 0]
+    #[max = 7]
+    delete_days: Option<u8>,
+    #[description = "How long, e.g. 7d or 4w, for good if not given"] duration: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    let length = match duration.as_deref().map(duration::parse) {
+        None => None,
+        Some(Some(length)) => Some(length),
+        Some(None) => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `7d` or `4w`.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(refusal) = check_hierarchy(ctx, &user).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let until = length.map(|l| serenity::Timestamp::now().unix_timestamp() + l.as_secs() as i64);
+    let notice = match until {
+        Some(until) => format!(
+            "You have been banned from **{}** until <t:{}:f>.\nReason: {}",
+            guild_name(ctx),
+            until,
+            reason
+        ),
+        None => format!(
+            "You have been banned from **{}**.\nReason: {}",
+            guild_name(ctx),
+            reason
+        ),
+    };
+    notify(ctx, &user, &notice).await;
+    guild
+        .ban_with_reason(ctx.discord(), user.id, delete_days.unwrap_or(0), &reason)
+        .await?;
+
+    let db = &ctx.data().db;
+    let case = db
+        .add_case(guild, "ban", user.id, ctx.author().id, &reason)
+        .await?;
+    // A permanent ban replaces an earlier tempban
+    jobs::take(db, JobKind::Unban, guild, user.id, None).await?;
+    if let Some(until) = until {
+        jobs::schedule(db, JobKind::Unban, guild, user.id, ctx.author().id.0, until).await?;
+    }
+
+    let details = match until {
+        Some(until) => format!("Case #{}, until <t:{}:f>", case, until),
+        None => format!("Case #{}", case),
+    };
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "banned",
+            target: &user,
+            reason: &reason,
+            details: Some(details),
+        },
+    )
+    .await?;
+
+    let response = match length {
+        Some(length) => format!(
+            ":white_check_mark: Banned **{}** for {} (case #{})",
+            user.tag(),
+            duration::format(length),
+            case
+        ),
+        None => format!(
+            ":white_check_mark: Banned **{}** (case #{})",
+            user.tag(),
+            case
+        ),
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Lifts a user's ban, tempbans included
+///
+/// Usage: `/unban <user> [reason]`
+/// Example: `/unban 123456789012345678 Appeal accepted`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "BAN_MEMBERS",
+    required_bot_permissions = "BAN_MEMBERS",
+    default_member_permissions = "BAN_MEMBERS"
+)]
+async fn unban(
+    ctx: Context<'_>,
+    #[description = "User to unban"] user: serenity::User,
+    #[description = "Why they are being unbanned"] reason: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    // Taken first, the ban removal event cancels it too once the ban is lifted
+    let db = &ctx.data().db;
+    let tempban = !jobs::take(db, JobKind::Unban, guild, user.id, None)
+        .await?
+        .is_empty();
+
+    match guild.unban(ctx.discord(), user.id).await {
+        Ok(()) => {}
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+        {
+            ctx.send(|m| {
+                m.content(format!(":x: **{}** isn't banned.", user.tag()))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let case = db
+        .add_case(guild, "unban", user.id, ctx.author().id, &reason)
+        .await?;
+
+    let details = if tempban {
+        format!("Case #{}, the tempban was lifted early", case)
+    } else {
+        format!("Case #{}", case)
+    };
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "unbanned",
+            target: &user,
+            reason: &reason,
+            details: Some(details),
+        },
+    )
+    .await?;
+
+    ctx.say(format!(
+        ":white_check_mark: Unbanned **{}** (case #{})",
+        user.tag(),
+        case
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Browse the server's bans
+///
+/// Usage: `/bans list [search] [page]`
+/// Example: `/bans list raid`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("bans_list"),
+    required_permissions = "BAN_MEMBERS",
+    default_member_permissions = "BAN_MEMBERS"
+)]
+async fn bans(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Lists the bans, searching user names, IDs and reasons
+///
+/// Usage: `/bans list [search] [page]`
+/// Example: `/bans list spam 2`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "BAN_MEMBERS",
+    required_bot_permissions = "BAN_MEMBERS"
+)]
+async fn bans_list(
+    ctx: Context<'_>,
+    #[description = "Text the user name, ID or reason contains"] search: Option<String>,
+    #[description = "Page to show"]
+    #[min = 1]
+    page: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let page = page.unwrap_or(1).max(1) as usize;
+
+    let all = guild.bans(ctx.discord()).await?;
+    let found = bans::search(&all, search.as_deref());
+    let pages = found.len().div_ceil(bans::PAGE_SIZE).max(1);
+    let shown: Vec<_> = found
+        .iter()
+        .skip((page - 1) * bans::PAGE_SIZE)
+        .take(bans::PAGE_SIZE)
+        .collect();
+
+    if shown.is_empty() {
+        let response = if found.is_empty() {
+            "No bans found."
+        } else {
+            "There are no bans on that page."
+        };
+        ctx.send(|m| m.content(response).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let tempbans = jobs::pending(&ctx.data().db, JobKind::Unban, guild).await?;
+    let list = shown
+        .iter()
+        .map(|ban| {
+            let mut reason: String = ban
+                .reason
+                .as_deref()
+                .unwrap_or("No reason given")
+                .chars()
+                .take(200)
+                .collect();
+            if let Some(job) = tempbans.iter().find(|j| j.user_id as u64 == ban.user.id.0) {
+                reason.push_str(&format!(" (until <t:{}:f>)", job.run_at));
+            }
+            format!("**{}** (`{}`): {}", ban.user.tag(), ban.user.id.0, reason)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Bans ({})", found.len()))
+                .description(list)
+                .footer(|f| f.text(format!("Page {} of {}", page, pages)))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Times a member out so they can't talk for a while
+///
+/// Usage: `/timeout <user> <duration> [reason]`
+/// Example: `/timeout @user 30m Spamming`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    required_bot_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn timeout(
+    ctx: Context<'_>,
+    #[description = "Member to time out"] user: serenity::User,
+    #[description = "How long, e.g. 10m, 1h or 2d (max 28d)"] duration: String,
+    #[description = "Why they are being timed out"] reason: Option<String>,
+) -> Result<(), Error> {
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    let length = match duration::parse(&duration) {
+        Some(length) if length <= duration::MAX_TIMEOUT => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `10m`, `1h` or `2d`, up to 28 days.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(refusal) = check_hierarchy(ctx, &user).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let length = apply_timeout(ctx, &user, length, &reason).await?;
+
+    ctx.say(format!(
+        ":white_check_mark: Timed out **{}** for {}",
+        user.tag(),
+        length
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Warns a member, repeated warnings lead to an automatic timeout
+///
+/// Usage: `/warn <user> <reason>`
+/// Example: `/warn @user Please keep it civil`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn warn(
+    ctx: Context<'_>,
+    #[description = "Member to warn"] user: serenity::User,
+    #[description = "Why they are being warned"] reason: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if let Some(refusal) = check_hierarchy(ctx, &user).await? {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let warning = db
+        .add_warning(guild, user.id, ctx.author().id, &reason)
+        .await?;
+    let count = db.warnings_for(guild, user.id).await?.len();
+
+    notify(
+        ctx,
+        &user,
+        &format!(
+            "You have been warned in **{}**.\nReason: {}",
+            guild_name(ctx),
+            reason
+        ),
+    )
+    .await;
+
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "warned",
+            target: &user,
+            reason: &reason,
+            details: Some(format!(
+                "Case #{}, warning {} in total",
+                warning.case_id, count
+            )),
+        },
+    )
+    .await?;
+
+    let mut response = format!(
+        ":white_check_mark: Warned **{}** (case #{}, {} in total)",
+        user.tag(),
+        warning.case_id,
+        count
+    );
+
+    // Escalate every time they reach another multiple of the threshold
+    let config = ctx.data().guild_configs.get(guild).await?;
+    if let Some(threshold) = config.warn_threshold.filter(|t| *t > 0) {
+        if count % threshold as usize == 0 {
+            let reason = format!("Reached {} warnings", count);
+            match apply_timeout(ctx, &user, config.warn_timeout(), &reason).await {
+                Ok(length) => {
+                    response.push_str(&format!("\n:mute: They have been timed out for {}", length))
+                }
+                Err(e) => {
+                    tracing::warn!("Error applying warning timeout: {}", e);
+                    response.push_str(
+                        "\n:warning: They reached the warning limit, but I couldn't time them out.",
+                    );
+                }
+            }
+        }
+    }
+
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Lists a member's warnings
+///
+/// Usage: `/warnings <user>`
+/// Example: `/warnings @user`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn warnings(
+    ctx: Context<'_>,
+    #[description = "Member to look up"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let warnings = ctx.data().db.warnings_for(guild, user.id).await?;
+    let notes = ctx.data().db.notes_for(guild, user.id).await?;
+
+    if warnings.is_empty() && notes.is_empty() {
+        ctx.send(|m| {
+            m.content(format!("**{}** has no warnings or notes.", user.tag()))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Newest first, and only as many as fit in an embed
+    let mut list = String::new();
+    for warning in warnings.iter().rev() {
+        let line = format!(
+            "**#{}** <t:{}:d> by <@{}>: {}\n",
+            warning.case_id, warning.created_at, warning.moderator_id, warning.reason
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+    if list.is_empty() {
+        list = "No warnings".to_string();
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            // Notes come first, they're usually what the next moderator needs to know
+            if !notes.is_empty() {
+                e.field("Notes", notes::summary(&notes, 1024), false);
+            }
+            e.title(format!("Warnings for {}", user.tag()))
+                .description(list)
+                .footer(|f| {
+                    f.text(format!(
+                        "{} warnings and {} notes in total",
+                        warnings.len(),
+                        notes.len()
+                    ))
+                })
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Keep notes on users for the other moderators
+///
+/// Usage: `/note add <user> <text>`, `/note list <user>`, `/note edit <id> <text>` or `/note history <id>`
+/// Example: `/note add @user Was warned in DMs about self-promotion`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("note_add", "note_list", "note_edit", "note_history"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn note(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a note on a user
+///
+/// Usage: `/note add <user> <text>`
+/// Example: `/note add @user Was warned in DMs about self-promotion`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn note_add(
+    ctx: Context<'_>,
+    #[description = "User the note is about"] user: serenity::User,
+    #[description = "What the other moderators should know"] text: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = match check_note(&text) {
+        Some(refusal) => refusal,
+        None => {
+            let id = ctx
+                .data()
+                .db
+                .add_note(guild, user.id, ctx.author().id, text.trim())
+                .await?;
+            format!(
+                ":white_check_mark: Added note #{} on **{}**",
+                id,
+                user.tag()
+            )
+        }
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the notes on a user
+///
+/// Usage: `/note list <user>`
+/// Example: `/note list @user`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn note_list(
+    ctx: Context<'_>,
+    #[description = "User to look up"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let notes = ctx.data().db.notes_for(guild, user.id).await?;
+
+    if notes.is_empty() {
+        ctx.send(|m| {
+            m.content(format!("There are no notes on **{}**.", user.tag()))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Notes on {}", user.tag()))
+                .description(notes::summary(&notes, 4000))
+                .footer(|f| f.text(format!("{} in total", notes.len())))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Changes what a note says, the earlier version is kept
+///
+/// Usage: `/note edit <id> <text>`
+/// Example: `/note edit 4 Was warned twice in DMs about self-promotion`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "edit",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn note_edit(
+    ctx: Context<'_>,
+    #[description = "Number of the note"] id: i64,
+    #[description = "What the note should say"] text: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = match check_note(&text) {
+        Some(refusal) => refusal,
+        None => {
+            if ctx
+                .data()
+                .db
+                .edit_note(guild, id, ctx.author().id, text.trim())
+                .await?
+            {
+                format!(":white_check_mark: Edited note #{}", id)
+            } else {
+                format!(":x: There's no note #{} in this server.", id)
+            }
+        }
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Shows a note and what it said before each edit
+///
+/// Usage: `/note history <id>`
+/// Example: `/note history 4`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "history",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn note_history(
+    ctx: Context<'_>,
+    #[description = "Number of the note"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let (note, edits) = match ctx.data().db.note_history(guild, id).await? {
+        Some(history) => history,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There's no note #{} in this server.", id))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Each version was written by whoever made the edit after the one before
+    let mut writer = note.author_id;
+    let mut versions = Vec::new();
+    for edit in &edits {
+        versions.push((writer, &edit.previous_content));
+        writer = edit.editor_id;
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Note #{}", note.id)).description(format!(
+                "On <@{}>, written by <@{}> <t:{}:f>\n\n{}",
+                note.user_id, note.author_id, note.created_at, note.content
+            ));
+            for (edit, (writer, content)) in edits.iter().zip(versions).rev().take(10) {
+                e.field(
+                    format!("Before <t:{}:f>", edit.edited_at),
+                    format!("<@{}>: {}", writer, content),
+                    false,
+                );
+            }
+            e.footer(|f| f.text(format!("Edited {} times", note.edits)))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+// Returns why a note can't be saved, if it can't
+fn check_note(text: &str) -> Option<String> {
+    let length = text.trim().chars().count();
+    (length == 0 || length > notes::MAX_NOTE_LENGTH).then(|| {
+        format!(
+            ":x: Notes can be up to {} characters.",
+            notes::MAX_NOTE_LENGTH
+        )
+    })
+}
+
+/// Keep an eye on members, their activity is summarized to the watch channel
+///
+/// Usage: `/watchlist add <user> [reason]`, `/watchlist remove <user>` or `/watchlist list`
+/// Example: `/watchlist add @user Keeps skirting the self-promotion rule`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("watch_add", "watch_remove", "watch_list"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn watchlist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Puts a user on the watchlist
+///
+/// Usage: `/watchlist add <user> [reason]`
+/// Example: `/watchlist add @user Keeps skirting the self-promotion rule`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn watch_add(
+    ctx: Context<'_>,
+    #[description = "User to watch"] user: serenity::User,
+    #[description = "Why they're watched"] reason: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let reason = reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+
+    let content = if reason.is_some_and(|r| r.chars().count() > watchlist::MAX_REASON_LENGTH) {
+        format!(
+            ":x: Reasons can be up to {} characters.",
+            watchlist::MAX_REASON_LENGTH
+        )
+    } else if user.bot {
+        ":x: Bots can't be watched.".to_string()
+    } else {
+        let data = ctx.data();
+        let added = data
+            .watchlist
+            .add(guild, user.id, reason, ctx.author().id)
+            .await?;
+        let config = data.guild_configs.get(guild).await?;
+
+        let mut content = if added {
+            format!(":white_check_mark: Watching **{}**", user.tag())
+        } else {
+            format!(
+                ":white_check_mark: Updated the reason **{}** is watched for",
+                user.tag()
+            )
+        };
+        if config.watch_channel().is_none() {
+            content.push_str(
+                ", set a `watch_channel` or `log_channel` with `/config set` to get summaries",
+            );
+        }
+        content
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Takes a user off the watchlist
+///
+/// Usage: `/watchlist remove <user>`
+/// Example: `/watchlist remove @user`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn watch_remove(
+    ctx: Context<'_>,
+    #[description = "User to stop watching"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = if ctx.data().watchlist.remove(guild, user.id).await? {
+        format!(":white_check_mark: Stopped watching **{}**", user.tag())
+    } else {
+        format!(":x: **{}** isn't on the watchlist.", user.tag())
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the watched users
+///
+/// Usage: `/watchlist list`
+/// Example: `/watchlist list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn watch_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let watched = ctx.data().db.watchlist(guild).await?;
+
+    if watched.is_empty() {
+        ctx.send(|m| m.content("Nobody is on the watchlist.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for w in &watched {
+        let line = format!(
+            "<@{}> since <t:{}:d> by <@{}>: {}\n",
+            w.user_id,
+            w.added_at,
+            w.added_by,
+            w.reason.as_deref().unwrap_or("No reason")
+        );
+        // Embed descriptions can't be longer than 4096 characters
+        if list.len() + line.len() + "...".len() > 4096 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Watchlist ({})", watched.len()))
+                .description(list)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a warning by its case number
+///
+/// Usage: `/clearwarn <case> [reason]`
+/// Example: `/clearwarn 12 Given by mistake`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn clearwarn(
+    ctx: Context<'_>,
+    #[description = "Case number of the warning"] case: i64,
+    #[description = "Why it is being removed"] reason: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let reason = reason.unwrap_or_else(|| "No reason given".to_string());
+
+    let warning = match ctx.data().db.delete_warning(guild, case).await? {
+        Some(warning) => warning,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no case #{}.", case))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let user = serenity::UserId(warning.user_id as u64)
+        .to_user(ctx.discord())
+        .await?;
+
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "warning removed",
+            target: &user,
+            reason: &reason,
+            details: Some(format!("Case #{}: {}", warning.case_id, warning.reason)),
+        },
+    )
+    .await?;
+
+    ctx.say(format!(
+        ":white_check_mark: Removed case #{} from **{}**",
+        warning.case_id,
+        user.tag()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Deletes recent messages in this channel, optionally only those of a user or with some text
+///
+/// Usage: `/purge <count> [user] [contains]`
+/// Example: `/purge 50 @user discord.gg`
+#[poise::command(
+    slash_command,
+    guild_only,
+    channel_cooldown = 5,
+    required_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES | READ_MESSAGE_HISTORY",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn purge(
+    ctx: Context<'_>,
+    #[description = "How many messages to delete"]
+    #[min = 1]
+    #[max = 100]
+    count: u8,
+    #[description = "Only delete messages from this user"] user: Option<serenity::User>,
+    #[description = "Only delete messages containing this text"] contains: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let contains = contains.map(|c| c.to_lowercase());
+    let oldest = serenity::Timestamp::now().unix_timestamp() - BULK_DELETE_MAX_AGE;
+
+    // Filters look at the last 100 messages, not until `count` matching ones are found
+    let messages = ctx
+        .channel_id()
+        .messages(ctx.discord(), |r| r.limit(100))
+        .await?;
+
+    let mut too_old = 0;
+    let mut ids = Vec::new();
+    for message in &messages {
+        if ids.len() >= count as usize {
+            break;
+        }
+        if user.as_ref().is_some_and(|u| u.id != message.author.id)
+            || contains
+                .as_deref()
+                .is_some_and(|c| !message.content.to_lowercase().contains(c))
+        {
+            continue;
+        }
+        if message.timestamp.unix_timestamp() <= oldest {
+            too_old += 1;
+            continue;
+        }
+        ids.push(message.id);
+    }
+
+    // Bulk deletes need at least two messages
+    match ids.as_slice() {
+        [] => {}
+        [id] => ctx.channel_id().delete_message(ctx.discord(), id).await?,
+        _ => {
+            ctx.channel_id()
+                .delete_messages(ctx.discord(), &ids)
+                .await?
+        }
+    }
+
+    tracing::info!(
+        moderator = %ctx.author().tag(),
+        channel = ctx.channel_id().0,
+        count = ids.len(),
+        "Purged messages"
+    );
+
+    let mut response = format!(":white_check_mark: Deleted {} message(s)", ids.len());
+    if too_old > 0 {
+        response.push_str(&format!(
+            ", {} matching message(s) were skipped because they are older than 14 days",
+            too_old
+        ));
+    }
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+// Times `user` out for `length`, DMs and logs it, returns the formatted length
+async fn apply_timeout(
+    ctx: Context<'_>,
+    user: &serenity::User,
+    length: std::time::Duration,
+    reason: &str,
+) -> Result<String, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let until = serenity::Timestamp::from_unix_timestamp(
+        serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64,
+    )?;
+    guild
+        .edit_member(ctx.discord(), user.id, |m| {
+            m.disable_communication_until_datetime(until)
+        })
+        .await?;
+
+    let length = duration::format(length);
+    notify(
+        ctx,
+        user,
+        &format!(
+            "You have been timed out in **{}** for {}.\nReason: {}",
+            guild_name(ctx),
+            length,
+            reason
+        ),
+    )
+    .await;
+
+    modlog::log_action(
+        ctx,
+        Action {
+            name: "timed out",
+            target: user,
+            reason,
+            details: Some(format!(
+                "For {} (until <t:{}:f>)",
+                length,
+                until.unix_timestamp()
+            )),
+        },
+    )
+    .await?;
+
+    Ok(length)
+}
+
+// Returns why the invoker may not act on `target`, based on role hierarchy
+async fn check_hierarchy(
+    ctx: Context<'_>,
+    target: &serenity::User,
+) -> Result<Option<&'static str>, Error> {
+    let author = ctx.author();
+    let bot_id = ctx.framework().bot_id;
+
+    if target.id == author.id {
+        return Ok(Some(":x: You can't do that to yourself."));
+    }
+    if target.id == bot_id {
+        return Ok(Some(":x: I can't do that to myself."));
+    }
+
+    let guild = match ctx.guild() {
+        Some(guild) => guild,
+        None => return Ok(None),
+    };
+
+    if target.id == guild.owner_id {
+        return Ok(Some(":x: The server owner can't be moderated."));
+    }
+
+    // Users that aren't members (e.g. banning someone who already left) have no roles
+    let target_position = match top_role_position(ctx, &guild, target.id).await {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+
+    if author.id != guild.owner_id
+        && top_role_position(ctx, &guild, author.id).await.unwrap_or(0) <= target_position
+    {
+        return Ok(Some(":x: Your highest role must be above theirs."));
+    }
+
+    if top_role_position(ctx, &guild, bot_id).await.unwrap_or(0) <= target_position {
+        return Ok(Some(":x: My highest role must be above theirs."));
+    }
+
+    Ok(None)
+}
+
+// Position of the member's highest role, None if they aren't a member
+async fn top_role_position(
+    ctx: Context<'_>,
+    guild: &serenity::Guild,
+    user: serenity::UserId,
+) -> Option<i64> {
+    let member = guild.id.member(ctx.discord(), user).await.ok()?;
+
+    let position = member
+        .roles
+        .iter()
+        .filter_map(|role| guild.roles.get(role))
+        .map(|role| role.position)
+        .max()
+        .unwrap_or(0);
+
+    Some(position)
+}
+
+fn guild_name(ctx: Context<'_>) -> String {
+    ctx.guild()
+        .map(|g| g.name)
+        .unwrap_or_else(|| "the server".to_string())
+}
+
+async fn notify(ctx: Context<'_>, user: &serenity::User, content: &str) {
+    dm::send(
+        ctx.discord(),
+        &ctx.data().dm_stats,
+        &dm::MODERATION_NOTICE,
+        user,
+        ctx.channel_id(),
+        content,
+    )
+    .await;
+}
+
+command_list!["Moderation": staff, incident, kick, ban, unban, bans, timeout, warn, warnings, note, watchlist, clearwarn, purge];
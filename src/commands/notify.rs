@@ -0,0 +1,227 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    notify::{self, MAX_KEYWORDS, MAX_KEYWORD_LENGTH},
+    Context, Error,
+};
+
+/// Get a DM when someone uses a keyword in a channel you can read
+///
+/// Usage: `/notify add <keyword>`, `/notify remove <keyword>`, `/notify list`, `/notify mute <channel>` or `/notify unmute <channel>`
+/// Example: `/notify add rust`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list", "mute", "unmute")
+)]
+async fn notify(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// DMs you when a message in this server uses a word, ignoring case
+///
+/// Usage: `/notify add <keyword>`
+/// Example: `/notify add rust`
+#[poise::command(slash_command, guild_only)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "A single word"] keyword: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let keyword = match notify::normalize_keyword(&keyword) {
+        Some(keyword) => keyword,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Keywords are a single word of up to {} letters or numbers.",
+                    MAX_KEYWORD_LENGTH
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let notifications = &ctx.data().notifications;
+    if notifications.keywords(guild, ctx.author().id).await?.len() >= MAX_KEYWORDS {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: You can have up to {} keywords, remove one first.",
+                MAX_KEYWORDS
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let response = if notifications.add(guild, ctx.author().id, &keyword).await? {
+        format!(
+            ":white_check_mark: You'll get a DM when someone says `{}` in a channel you can read",
+            keyword
+        )
+    } else {
+        format!(":x: You already follow `{}`.", keyword)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Stops notifying you about a keyword
+///
+/// Usage: `/notify remove <keyword>`
+/// Example: `/notify remove rust`
+#[poise::command(slash_command, guild_only)]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Keyword from /notify list"]
+    #[autocomplete = "autocomplete_keyword"]
+    keyword: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let removed = match notify::normalize_keyword(&keyword) {
+        Some(keyword) => {
+            ctx.data()
+                .notifications
+                .remove(guild, ctx.author().id, &keyword)
+                .await?
+        }
+        None => false,
+    };
+
+    let response = if removed {
+        format!(":white_check_mark: Stopped following `{}`", keyword)
+    } else {
+        format!(":x: You don't follow `{}`.", keyword)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists your keywords and muted channels
+///
+/// Usage: `/notify list`
+/// Example: `/notify list`
+#[poise::command(slash_command, guild_only)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let notifications = &ctx.data().notifications;
+    let keywords = notifications.keywords(guild, ctx.author().id).await?;
+    let muted = notifications.muted(guild, ctx.author().id).await?;
+
+    if keywords.is_empty() {
+        ctx.send(|m| {
+            m.content("You don't follow any keywords, add one with /notify add.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let keyword_list = keywords
+        .iter()
+        .map(|k| format!("`{}`", k))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let muted_list = if muted.is_empty() {
+        "None".to_string()
+    } else {
+        muted
+            .iter()
+            .map(|c| format!("<#{}>", c.0))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Your keyword notifications")
+                .field("Keywords", keyword_list, false)
+                .field("Muted channels", muted_list, false)
+                .footer(|f| f.text(format!("{} of {}", keywords.len(), MAX_KEYWORDS)))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops keyword notifications from a channel
+///
+/// Usage: `/notify mute <channel>`
+/// Example: `/notify mute #off-topic`
+#[poise::command(slash_command, guild_only)]
+async fn mute(
+    ctx: Context<'_>,
+    #[description = "Channel to mute"] channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    set_muted(ctx, channel.id, true).await
+}
+
+/// Turns keyword notifications from a channel back on
+///
+/// Usage: `/notify unmute <channel>`
+/// Example: `/notify unmute #off-topic`
+#[poise::command(slash_command, guild_only)]
+async fn unmute(
+    ctx: Context<'_>,
+    #[description = "Channel to unmute"] channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    set_muted(ctx, channel.id, false).await
+}
+
+async fn set_muted(
+    ctx: Context<'_>,
+    channel: serenity::ChannelId,
+    muted: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .notifications
+        .set_muted(guild, ctx.author().id, channel, muted)
+        .await?;
+
+    let response = if muted {
+        format!(
+            ":white_check_mark: You won't get keyword notifications from <#{}> anymore",
+            channel.0
+        )
+    } else {
+        format!(
+            ":white_check_mark: You'll get keyword notifications from <#{}> again",
+            channel.0
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+async fn autocomplete_keyword<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let keywords = match ctx.guild_id() {
+        Some(guild) => ctx
+            .data()
+            .notifications
+            .keywords(guild, ctx.author().id)
+            .await
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    keywords
+        .into_iter()
+        .filter(move |keyword| keyword.starts_with(&partial))
+}
+
+command_list!["Utility": notify];
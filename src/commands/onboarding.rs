@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    duration,
+    mentions::Mentions,
+    onboarding::{StepKind, MAX_DELAY, MAX_STEPS},
+    welcome, Context, Error,
+};
+
+// Leaves room for the ping when a step has to be posted in the onboarding channel
+const MAX_CONTENT_LENGTH: usize = 1500;
+
+/// A sequence of DMs new members get over time, like the rules, role picking and an intro
+///
+/// Usage: `/onboarding add <kind> <content> [delay]`, `/onboarding remove <id>`, `/onboarding list`, `/onboarding channel [channel]` or `/onboarding progress <member>`
+/// Example: `/onboarding add roles "Pick what you're here for" 10m`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list", "channel", "progress"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn onboarding(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a step to the end, `roles` offers the `onboarding` list of /config roles
+///
+/// Usage: `/onboarding add <kind> <content> [delay]`
+/// Example: `/onboarding add message "Welcome to {server}! Please read #rules"` or `/onboarding add intro "Tell us about yourself!" 1d`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "What the step shows besides the message"] kind: StepKind,
+    #[description = "Message, can use placeholders like {mention}"] content: String,
+    #[description = "Wait since the previous step, like 10m or 1d"] delay: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let delay = match delay.as_deref().map(duration::parse) {
+        None => Duration::ZERO,
+        Some(Some(delay)) if delay <= MAX_DELAY => delay,
+        Some(_) => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Please give a delay like `10m` or `1d`, up to {}.",
+                    duration::format(MAX_DELAY)
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if content.trim().is_empty() || content.chars().count() > MAX_CONTENT_LENGTH {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Steps need a message of up to {} characters.",
+                MAX_CONTENT_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if ctx.data().db.onboarding_steps(guild).await?.len() >= MAX_STEPS {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Onboarding can have up to {} steps, remove one first.",
+                MAX_STEPS
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let id = ctx
+        .data()
+        .db
+        .add_onboarding_step(guild, kind.name(), &content, delay)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Added step #{} ({}), placeholders: {}",
+            id,
+            kind.name(),
+            welcome::PLACEHOLDERS
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a step, members that joined already don't get it either
+///
+/// Usage: `/onboarding remove <id>`
+/// Example: `/onboarding remove 3`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "ID from /onboarding list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().db.delete_onboarding_step(guild, id).await? {
+        format!(":white_check_mark: Removed step #{}", id)
+    } else {
+        format!(":x: There is no step #{}.", id)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the onboarding steps in the order they're sent
+///
+/// Usage: `/onboarding list`
+/// Example: `/onboarding list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let steps = ctx.data().db.onboarding_steps(guild).await?;
+
+    if steps.is_empty() {
+        ctx.send(|m| {
+            m.content("This server has no onboarding steps.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    let mut total = Duration::ZERO;
+    for step in &steps {
+        total += Duration::from_secs(step.delay_secs as u64);
+        let preview: String = step.content.chars().take(80).collect();
+        let after = if total.is_zero() {
+            "on join".to_string()
+        } else {
+            format!("{} after joining", duration::format(total))
+        };
+
+        list.push_str(&format!(
+            "#{} {} {}: \"{}\"\n",
+            step.id, step.kind, after, preview
+        ));
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Onboarding")
+                .description(list)
+                .footer(|f| f.text(format!("{} of {}", steps.len(), MAX_STEPS)))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Sets the channel for members with closed DMs and for introductions, unsets it if empty
+///
+/// Usage: `/onboarding channel [channel]`
+/// Example: `/onboarding channel #introductions`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn channel(
+    ctx: Context<'_>,
+    #[description = "Channel for onboarding and introductions"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let channel = channel.map(|c| c.id);
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| c.onboarding_channel = channel)
+        .await?;
+
+    let response = match channel {
+        Some(channel) => format!(
+            ":white_check_mark: Onboarding now uses <#{}> when DMs are closed",
+            channel.0
+        ),
+        None => ":white_check_mark: Onboarding is DM only now, introductions are off".to_string(),
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Shows how far a member got with onboarding
+///
+/// Usage: `/onboarding progress <member>`
+/// Example: `/onboarding progress @Sticks`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn progress(
+    ctx: Context<'_>,
+    #[description = "Member to look up"] member: serenity::User,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = match ctx.data().db.onboarding_progress(guild, member.id).await? {
+        None => format!("<@{}> never went through onboarding.", member.id.0),
+        Some(progress) => {
+            let state = match progress.completed_at {
+                Some(completed) => format!("completed it <t:{}:R>", completed),
+                None => format!("got {} steps so far", progress.steps_sent),
+            };
+            let via = if progress.via_channel {
+                ", some in the onboarding channel since their DMs are closed"
+            } else {
+                ""
+            };
+
+            format!(
+                "<@{}> started onboarding <t:{}:R> and {}{}",
+                member.id.0, progress.started_at, state, via
+            )
+        }
+    };
+
+    ctx.send(|m| {
+        m.content(response)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Settings": onboarding];
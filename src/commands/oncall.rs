@@ -0,0 +1,207 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{duration, oncall, temproles, Context, Error};
+
+// Longer covers are really a change of the rotation
+const MAX_COVER: std::time::Duration = std::time::Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Manage who gets pinged by /staff
+///
+/// Usage: `/oncall add <start> <end>`, `/oncall remove <id>`, `/oncall cover <user> <for>` or `/oncall schedule`
+/// Example: `/oncall add fri 18:00 sat 02:00`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("shift_add", "shift_remove", "cover", "schedule"),
+    required_permissions = "MODERATE_MEMBERS",
+    default_member_permissions = "MODERATE_MEMBERS"
+)]
+async fn oncall(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a weekly shift in which you're on call, times are in UTC
+///
+/// Usage: `/oncall add <start> <end>`
+/// Example: `/oncall add fri 18:00 sat 02:00`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn shift_add(
+    ctx: Context<'_>,
+    #[description = "When the shift starts, like fri 18:00"] start: String,
+    #[description = "When it ends, like sat 02:00"] end: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let (starts_at, ends_at) = match (
+        temproles::parse_weekly(&start),
+        temproles::parse_weekly(&end),
+    ) {
+        (Some(starts_at), Some(ends_at)) if starts_at != ends_at => (starts_at, ends_at),
+        _ => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: Please give two different times like `fri 18:00` and `sat 02:00`, in UTC.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let id = ctx
+        .data()
+        .db
+        .add_oncall_shift(guild, ctx.author().id, starts_at, ends_at)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Shift #{}: you're on call from {} until {} UTC",
+            id,
+            temproles::format_weekly(starts_at),
+            temproles::format_weekly(ends_at)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes an on-call shift
+///
+/// Usage: `/oncall remove <id>`
+/// Example: `/oncall remove 3`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MODERATE_MEMBERS"
+)]
+async fn shift_remove(
+    ctx: Context<'_>,
+    #[description = "ID from /oncall schedule"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = match ctx.data().db.delete_oncall_shift(guild, id).await? {
+        Some(shift) => format!(
+            ":white_check_mark: Deleted shift #{} of <@{}>",
+            id, shift.user_id
+        ),
+        None => format!(":x: There is no shift #{}.", id),
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Takes over the shifts of another moderator for a while
+///
+/// Usage: `/oncall cover <user> <for>`
+/// Example: `/oncall cover @moderator 3d`
+#[poise::command(slash_command, guild_only, required_permissions = "MODERATE_MEMBERS")]
+async fn cover(
+    ctx: Context<'_>,
+    #[description = "Moderator whose shifts you take over"] user: serenity::User,
+    #[description = "For how long, like 8h or 3d"]
+    #[rename = "for"]
+    duration: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let duration = match duration::parse(&duration) {
+        Some(duration) if duration <= MAX_COVER => duration,
+        _ => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Please give a duration like `8h` or `3d`, up to {}.",
+                    duration::format(MAX_COVER)
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+    if user.id == ctx.author().id {
+        ctx.send(|m| {
+            m.content(":x: You can't cover your own shifts.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let ends_at = now + duration.as_secs() as i64;
+    ctx.data()
+        .db
+        .add_oncall_cover(guild, ctx.author().id, user.id, now, ends_at)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: You cover the shifts of <@{}> until <t:{}:f>",
+            user.id.0, ends_at
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows the on-call rotation and who's on call now
+///
+/// Usage: `/oncall schedule`
+/// Example: `/oncall schedule`
+#[poise::command(slash_command, guild_only, required_permissions = "MODERATE_MEMBERS")]
+async fn schedule(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let shifts = db.oncall_shifts(guild).await?;
+    if shifts.is_empty() {
+        ctx.send(|m| {
+            m.content("There are no on-call shifts, /staff pings the staff role.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let covers = db.oncall_covers(guild, now).await?;
+    let on_call = oncall::on_call(&shifts, &covers, temproles::current_minute_of_week(), now);
+    let on_call = if on_call.is_empty() {
+        "Nobody, /staff pings the staff role".to_string()
+    } else {
+        on_call
+            .iter()
+            .map(|u| format!("<@{}>", u.0))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("On-call rotation")
+                .description(oncall::rotation(&shifts, &covers))
+                .field("On call now", on_call, false)
+                .footer(|f| f.text("Times are in UTC"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Moderation": oncall];
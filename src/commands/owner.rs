@@ -0,0 +1,584 @@
+use std::time::Instant;
+
+use poise::serenity_prelude as serenity;
+use tracing::{level_filters::LevelFilter, Level};
+
+use crate::{
+    guard::dangerous_action,
+    logs::{self, Filter, TAIL_DURATION},
+    mentions::Mentions,
+    quotas::Tier,
+    serversync::{self, SyncOptions},
+    Context, Error,
+};
+
+// Changes listed per guild in the /sync preview
+const SYNC_PREVIEW_STEPS: usize = 15;
+// Entries listed per section of /admin state
+const STATE_ENTRIES: usize = 10;
+
+#[derive(poise::ChoiceParameter)]
+enum ActivityKind {
+    #[name = "playing"]
+    Playing,
+    #[name = "listening"]
+    Listening,
+    #[name = "watching"]
+    Watching,
+    #[name = "competing"]
+    Competing,
+}
+
+#[derive(poise::ChoiceParameter)]
+enum Status {
+    #[name = "online"]
+    Online,
+    #[name = "idle"]
+    Idle,
+    #[name = "dnd"]
+    DoNotDisturb,
+    #[name = "invisible"]
+    Invisible,
+}
+
+#[derive(Clone, Copy, poise::ChoiceParameter)]
+enum LogLevel {
+    #[name = "error"]
+    Error,
+    #[name = "warn"]
+    Warn,
+    #[name = "info"]
+    Info,
+    #[name = "debug"]
+    Debug,
+    #[name = "trace"]
+    Trace,
+}
+
+impl LogLevel {
+    fn level(self) -> Level {
+        match self {
+            LogLevel::Error => Level::ERROR,
+            LogLevel::Warn => Level::WARN,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Trace => Level::TRACE,
+        }
+    }
+}
+
+/// Shows a health snapshot of the bot (latency, REST round trip, event loop lag)
+///
+/// Usage: `/diagnostics`
+/// Example: `/diagnostics`
+#[poise::command(slash_command, owners_only)]
+async fn diagnostics(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    // Heartbeat latency as reported by each shard runner
+    let shard_manager = ctx.framework().shard_manager();
+    let mut shards = Vec::new();
+    {
+        let manager = shard_manager.lock().await;
+        let runners = manager.runners.lock().await;
+
+        for (id, runner) in runners.iter() {
+            let latency = match runner.latency {
+                Some(latency) => format!("{}ms", latency.as_millis()),
+                None => "n/a".to_string(),
+            };
+            shards.push(format!("Shard {}: {} ({})", id.0, latency, runner.stage));
+        }
+    }
+    shards.sort();
+    if shards.is_empty() {
+        shards.push("No shards running".to_string());
+    }
+
+    // Time a cheap API call to measure the REST round trip
+    let rest_start = Instant::now();
+    let rest = match ctx.discord().http.get_current_user().await {
+        Ok(_) => format!("{}ms", rest_start.elapsed().as_millis()),
+        Err(e) => format!("failed ({})", e),
+    };
+
+    let watchdog = &ctx.data().watchdog;
+    let lag = format!(
+        "{}ms (max {}ms, {} stalls)",
+        watchdog.last_lag().as_millis(),
+        watchdog.max_lag().as_millis(),
+        watchdog.stalls()
+    );
+
+    let database = match ctx.data().db.ping().await {
+        Ok(latency) => match ctx.data().db.created_at().await {
+            Ok(created_at) => format!("{}ms (created <t:{}:R>)", latency.as_millis(), created_at),
+            Err(_) => format!("{}ms", latency.as_millis()),
+        },
+        Err(e) => format!("failed ({})", e),
+    };
+
+    let apis = match ctx.data().integrations.as_slice() {
+        [] => "None configured".to_string(),
+        breakers => breakers
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Diagnostics")
+                .field("Gateway heartbeat", shards.join("\n"), false)
+                .field("REST round trip", rest, true)
+                .field("Event loop lag", lag, true)
+                .field("Database", database, true)
+                .field("External APIs", apis, true)
+                .field("DMs", ctx.data().dm_stats.to_string(), true)
+                .field(
+                    "Rate limiter",
+                    format!("{} users tracked", ctx.data().rate_limiter.tracked_users()),
+                    true,
+                )
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Copies the roles, categories and channels of a template server to other servers
+///
+/// Usage: `/sync <source> <targets> [apply] [rename] [reorder] [exclude]`
+/// Example: `/sync 123 456,789 True exclude:staff*,logs`
+#[poise::command(slash_command, owners_only)]
+async fn sync(
+    ctx: Context<'_>,
+    #[description = "ID of the template server"] source: String,
+    #[description = "IDs of the servers to sync, separated by commas"] targets: String,
+    #[description = "Make the changes instead of only listing them"] apply: Option<bool>,
+    #[description = "Rename copies that were renamed in the template"] rename: Option<bool>,
+    #[description = "Move copies to the template's positions"] reorder: Option<bool>,
+    #[description = "Names to skip, separated by commas, `name*` matches by prefix"]
+    exclude: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let parse = |id: &str| id.trim().parse().ok().map(serenity::GuildId);
+    let source = parse(&source);
+    let targets: Option<Vec<_>> = targets.split(',').map(parse).collect();
+    let (source, targets) = match (source, targets) {
+        (Some(source), Some(targets)) if !targets.contains(&source) => (source, targets),
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give server IDs, and don't sync a server to itself.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let options = SyncOptions {
+        rename: rename.unwrap_or(false),
+        reorder: reorder.unwrap_or(false),
+        exclude: exclude
+            .unwrap_or_default()
+            .split(',')
+            .map(|e| e.trim().to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect(),
+    };
+
+    let cache = &ctx.discord().cache;
+    let name = |guild: serenity::GuildId| {
+        cache
+            .guild_field(guild, |g| g.name.clone())
+            .unwrap_or_else(|| guild.0.to_string())
+    };
+
+    let http = &ctx.discord().http;
+    let db = &ctx.data().db;
+    let mut plans = Vec::new();
+    for target in targets {
+        plans.push(serversync::plan(http, db, source, target, &options).await?);
+    }
+
+    let mut preview = String::new();
+    for plan in &plans {
+        preview.push_str(&format!(
+            "**{}**: {} change(s)\n",
+            name(plan.target),
+            plan.steps.len()
+        ));
+        for step in plan.steps.iter().take(SYNC_PREVIEW_STEPS) {
+            preview.push_str(&format!("{}\n", step));
+        }
+        if plan.steps.len() > SYNC_PREVIEW_STEPS {
+            preview.push_str(&format!(
+                "...and {} more\n",
+                plan.steps.len() - SYNC_PREVIEW_STEPS
+            ));
+        }
+    }
+    if preview.len() > 4000 {
+        preview.truncate(preview.floor_char_boundary(4000));
+        preview.push_str("...");
+    }
+
+    let total: usize = plans.iter().map(|p| p.steps.len()).sum();
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Sync from {}", name(source)))
+                .description(&preview)
+                .footer(|f| {
+                    f.text(if apply == Some(true) {
+                        "Confirm in your DMs to make these changes"
+                    } else {
+                        "Dry run, nothing was changed"
+                    })
+                })
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    if apply != Some(true) || total == 0 || !dangerous_action(ctx, "sync").await? {
+        return Ok(());
+    }
+
+    let mut report = Vec::new();
+    for plan in plans {
+        let target = plan.target;
+        let steps = plan.steps.len();
+        let errors = plan.run(http, db).await?;
+        report.push(format!(
+            "**{}**: {} of {} change(s) made",
+            name(target),
+            steps - errors.len(),
+            steps
+        ));
+        report.extend(errors.into_iter().take(5).map(|e| format!(":x: {}", e)));
+    }
+
+    tracing::info!(user = %ctx.author().tag(), source = source.0, "Synced server structure");
+    ctx.send(|m| {
+        m.embed(|e| e.title("Sync finished").description(report.join("\n")))
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shuts the bot down (requires DM confirmation)
+///
+/// Usage: `/shutdown`
+/// Example: `~shutdown`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn shutdown(ctx: Context<'_>) -> Result<(), Error> {
+    if !dangerous_action(ctx, "shutdown").await? {
+        return Ok(());
+    }
+
+    tracing::info!(user = %ctx.author().tag(), "Shutdown requested");
+    ctx.data()
+        .shutdown
+        .run(
+            &ctx.framework().shard_manager(),
+            &ctx.data().db,
+            &ctx.data().counters,
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Bot administration
+///
+/// Usage: `/admin reload`, `/admin status <kind> <text> [status]`, `/admin leave <guild>`, `/admin state` or `/admin tier <guild> <tier>`
+/// Example: `/admin status watching the logs`
+#[poise::command(
+    slash_command,
+    prefix_command,
+    owners_only,
+    subcommands("reload", "status", "leave", "state", "tier")
+)]
+async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Reloads cached settings, rules, keywords, watchlists and blocked images from the DB
+///
+/// Usage: `/admin reload`
+/// Example: `~admin reload`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn reload(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    let configs = data.guild_configs.clear_cache();
+    data.triggers.clear_cache();
+    data.faqs.clear_cache();
+    data.spoiler_rules.clear_cache();
+    data.notifications.clear_cache();
+    data.spam_filter.clear_cache();
+    data.watchlist.clear_cache();
+    data.image_blocklist.clear_cache();
+
+    tracing::info!(user = %ctx.author().tag(), "Reloaded cached settings");
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Cleared the cached settings of {} servers, they are loaded again on next use",
+            configs
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Changes what the bot is doing on every shard, until the next reconnect
+///
+/// Usage: `/admin status <kind> <text> [status]`
+/// Example: `/admin status watching the logs`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn status(
+    ctx: Context<'_>,
+    #[description = "What the bot is doing"] kind: ActivityKind,
+    #[description = "Text of the activity"] text: String,
+    #[description = "Online status, online by default"] status: Option<Status>,
+) -> Result<(), Error> {
+    let activity = match kind {
+        ActivityKind::Playing => serenity::Activity::playing(&text),
+        ActivityKind::Listening => serenity::Activity::listening(&text),
+        ActivityKind::Watching => serenity::Activity::watching(&text),
+        ActivityKind::Competing => serenity::Activity::competing(&text),
+    };
+    let status = match status.unwrap_or(Status::Online) {
+        Status::Online => serenity::OnlineStatus::Online,
+        Status::Idle => serenity::OnlineStatus::Idle,
+        Status::DoNotDisturb => serenity::OnlineStatus::DoNotDisturb,
+        Status::Invisible => serenity::OnlineStatus::Invisible,
+    };
+
+    {
+        let shard_manager = ctx.framework().shard_manager();
+        let manager = shard_manager.lock().await;
+        for runner in manager.runners.lock().await.values() {
+            runner
+                .runner_tx
+                .set_presence(Some(activity.clone()), status);
+        }
+    }
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Now {} {}", kind.name(), text))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Makes the bot leave a server (requires DM confirmation)
+///
+/// Usage: `/admin leave <guild>`
+/// Example: `~admin leave 123456789012345678`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn leave(
+    ctx: Context<'_>,
+    #[description = "ID of the server"] guild: String,
+) -> Result<(), Error> {
+    let guild = match guild.trim().parse() {
+        Ok(id) => serenity::GuildId(id),
+        Err(_) => {
+            ctx.send(|m| m.content(":x: Please give a server ID.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+    let name = ctx
+        .discord()
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_else(|| guild.0.to_string());
+
+    if !dangerous_action(ctx, "leave").await? {
+        return Ok(());
+    }
+
+    guild.leave(ctx.discord()).await?;
+    tracing::info!(user = %ctx.author().tag(), guild = guild.0, "Left server");
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Left {}", name))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows what the bot keeps in memory
+///
+/// Usage: `/admin state`
+/// Example: `~admin state`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn state(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+
+    // Users closest to being rate limited first
+    let mut buckets = data.rate_limiter.snapshot();
+    buckets.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let mut rate_limiter = buckets
+        .iter()
+        .take(STATE_ENTRIES)
+        .map(|(user, tokens)| format!("<@{}>: {:.1} tokens", user.0, tokens))
+        .collect::<Vec<_>>();
+    rate_limiter.push(format!("{} users tracked", buckets.len()));
+
+    let cache = &ctx.discord().cache;
+    let guilds = data.guild_configs.cached_guilds();
+    let mut configs = guilds
+        .iter()
+        .take(STATE_ENTRIES)
+        .map(
+            |guild| match cache.guild_field(*guild, |g| g.name.clone()) {
+                Some(name) => format!("{} ({})", name, guild.0),
+                None => guild.0.to_string(),
+            },
+        )
+        .collect::<Vec<_>>();
+    configs.push(format!("{} servers cached", guilds.len()));
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Internal state")
+                .field("Rate limiter", rate_limiter.join("\n"), false)
+                .field(
+                    "XP limiter",
+                    format!("{} users tracked", data.xp_limiter.tracked_users()),
+                    false,
+                )
+                .field("Server configs", configs.join("\n"), false)
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Sets how big a server's daily quotas are
+///
+/// Usage: `/admin tier <guild> <tier>`
+/// Example: `~admin tier 123456789012345678 premium`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn tier(
+    ctx: Context<'_>,
+    #[description = "ID of the server"] guild: String,
+    #[description = "Tier to put the server on"] tier: Tier,
+) -> Result<(), Error> {
+    let guild = match guild.trim().parse() {
+        Ok(id) => serenity::GuildId(id),
+        Err(_) => {
+            ctx.send(|m| m.content(":x: Please give a server ID.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+    let name = ctx
+        .discord()
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_else(|| guild.0.to_string());
+
+    ctx.data().db.set_guild_tier(guild, tier).await?;
+    tracing::info!(user = %ctx.author().tag(), guild = guild.0, tier = tier.name(), "Set server tier");
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: {} is on the {} tier now",
+            name,
+            tier.name()
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The bot's logs
+///
+/// Usage: `/logs tail [level] [module]`
+/// Example: `~logs tail debug spam`
+#[poise::command(slash_command, prefix_command, owners_only, subcommands("tail"))]
+async fn logs(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Streams the bot's logs into a private thread for ten minutes
+///
+/// Usage: `/logs tail [level] [module]`
+/// Example: `~logs tail debug spam`
+#[poise::command(slash_command, prefix_command, owners_only)]
+async fn tail(
+    ctx: Context<'_>,
+    #[description = "Least severe level shown, info by default"] level: Option<LogLevel>,
+    #[description = "Only logs of this module, like spam or serenity"] module: Option<String>,
+) -> Result<(), Error> {
+    let level = level.map_or(Level::INFO, LogLevel::level);
+    // Lines RUST_LOG filters out never reach the tail
+    let logged = LevelFilter::current();
+    let note = if LevelFilter::from_level(level) > logged {
+        format!(" RUST_LOG only logs {} and above though.", logged)
+    } else {
+        String::new()
+    };
+
+    // Where the bot can't make a thread, like in DMs or threads, the logs go to the channel
+    let mut channel = ctx.channel_id();
+    if ctx.guild_id().is_some() {
+        match channel
+            .create_private_thread(ctx.discord(), |t| t.name("Logs"))
+            .await
+        {
+            Ok(thread) => {
+                thread
+                    .id
+                    .add_thread_member(ctx.discord(), ctx.author().id)
+                    .await?;
+                channel = thread.id;
+            }
+            Err(e) => tracing::debug!("Couldn't create a thread for the logs: {}", e),
+        }
+    }
+
+    logs::spawn(
+        ctx.discord().http.clone(),
+        &ctx.data().log_tail,
+        channel,
+        Filter {
+            level,
+            module: module.map(|m| m.trim().to_string()),
+        },
+    );
+    tracing::info!(user = %ctx.author().tag(), channel = channel.0, "Started tailing logs");
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Streaming the logs into <#{}> for {} minutes.{}",
+            channel.0,
+            TAIL_DURATION.as_secs() / 60,
+            note
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Owner": diagnostics, sync, shutdown, admin, logs];
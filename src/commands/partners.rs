@@ -0,0 +1,187 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Partner, duration, partners, Context, Error};
+
+const MAX_PARTNER_NAME_LENGTH: usize = 32;
+// Leaves room in the embed description
+const MAX_BLURB_LENGTH: usize = 2000;
+// Partnerships longer than this are better added without an end
+const MAX_PARTNERSHIP: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Manage partner servers whose ads are posted in the partner channel
+///
+/// Usage: `/partner add <name> <invite> <blurb> [for]`, `/partner remove <name>` or `/partner list`
+/// Example: `/partner add rust discord.gg/rust-lang A friendly place to talk about Rust for:90d`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn partner(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a partner server, its ad is posted in turn with the others
+///
+/// Usage: `/partner add <name> <invite> <blurb> [for]`
+/// Example: `/partner add rust discord.gg/rust-lang A friendly place to talk about Rust for:90d`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the partner server"] name: String,
+    #[description = "Invite to the partner server"] invite: String,
+    #[description = "What the ad says about the server"] blurb: String,
+    #[description = "How long the partnership lasts, like 90d, forever if not given"]
+    #[rename = "for"]
+    duration: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let name = name.trim().to_string();
+    let blurb = blurb.trim().to_string();
+    let invite = partners::parse_invite(&invite);
+    let duration = duration.as_deref().map(duration::parse);
+
+    let refusal = if name.is_empty() || name.chars().count() > MAX_PARTNER_NAME_LENGTH {
+        Some(format!(
+            ":x: Partner names can be up to {} characters.",
+            MAX_PARTNER_NAME_LENGTH
+        ))
+    } else if invite.is_none() {
+        Some(":x: That doesn't look like an invite.".to_string())
+    } else if blurb.is_empty() || blurb.chars().count() > MAX_BLURB_LENGTH {
+        Some(format!(
+            ":x: The blurb can be up to {} characters.",
+            MAX_BLURB_LENGTH
+        ))
+    } else if duration == Some(None) || duration.flatten().is_some_and(|d| d > MAX_PARTNERSHIP) {
+        Some(format!(
+            ":x: Please give a duration like `30d` or `12w`, up to {}.",
+            duration::format(MAX_PARTNERSHIP)
+        ))
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let expires_at = duration.flatten().map(|d| now + d.as_secs() as i64);
+    let partner = Partner {
+        guild_id: guild.0 as i64,
+        name: name.clone(),
+        blurb,
+        invite: invite.unwrap_or_default(),
+        added_by: ctx.author().id.0 as i64,
+        expires_at,
+        ..Partner::default()
+    };
+    if !ctx.data().db.add_partner(&partner).await? {
+        ctx.send(|m| {
+            m.content(format!(":x: There is a partner called `{}` already.", name))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let config = ctx.data().guild_configs.get(guild).await?;
+    let mut content = match expires_at {
+        Some(expires_at) => format!(
+            ":white_check_mark: Added `{}` as a partner until <t:{}:f>",
+            name, expires_at
+        ),
+        None => format!(":white_check_mark: Added `{}` as a partner", name),
+    };
+    if config.partner_channel.is_none() {
+        content.push_str(
+            "\nAds aren't posted until a channel is set with `/config set partner_channel`.",
+        );
+    }
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Removes a partner server, its ad isn't posted anymore
+///
+/// Usage: `/partner remove <name>`
+/// Example: `/partner remove rust`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the partner server"] name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let name = name.trim();
+
+    let content = if ctx.data().db.delete_partner(guild, name).await? {
+        format!(":white_check_mark: Removed the `{}` partner", name)
+    } else {
+        format!(":x: There is no partner called `{}`.", name)
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the partner servers, how often their ad was posted and when they expire
+///
+/// Usage: `/partner list`
+/// Example: `/partner list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let partners = ctx.data().db.partners(guild).await?;
+
+    if partners.is_empty() {
+        ctx.send(|m| m.content("There are no partners.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let next = partners::next_partner(&partners, now).map(|p| p.id);
+    let mut list = String::new();
+    for partner in &partners {
+        let expiry = match partner.expires_at {
+            Some(expires_at) if partners::expired(partner, now) => {
+                format!("expired <t:{}:R>", expires_at)
+            }
+            Some(expires_at) => format!("expires <t:{}:R>", expires_at),
+            None => "doesn't expire".to_string(),
+        };
+        let line = format!(
+            "**{}**{}: {} ({} impression{}), {}\n",
+            partner.name,
+            if next == Some(partner.id) {
+                " (next)"
+            } else {
+                ""
+            },
+            partner.invite,
+            partner.impressions,
+            if partner.impressions == 1 { "" } else { "s" },
+            expiry
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Partners").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Settings": partner];
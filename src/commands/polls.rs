@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    duration,
+    polls::{self, MAX_OPTIONS, MAX_OPTION_LENGTH},
+    Context, Error,
+};
+
+// Polls longer than this are better off as a pinned message
+const MAX_POLL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+// Embed titles can't be longer than this
+const MAX_QUESTION_LENGTH: usize = 256;
+
+/// Ask a question and let members vote with buttons
+///
+/// Usage: `/poll create <question> <option1> <option2> [option3] [option4] [option5] [duration]`
+/// Example: `/poll create "Movie night?" Friday Saturday duration:2d`
+#[poise::command(slash_command, guild_only, subcommands("create"))]
+async fn poll(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Posts a poll in this channel, open until closed or for the given duration
+///
+/// Usage: `/poll create <question> <option1> <option2> [option3] [option4] [option5] [duration]`
+/// Example: `/poll create "Best pizza topping?" Pineapple Mushrooms Salami duration:1d`
+#[poise::command(slash_command, guild_only, required_bot_permissions = "SEND_MESSAGES")]
+#[allow(clippy::too_many_arguments)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "What to ask"] question: String,
+    #[description = "First option"] option1: String,
+    #[description = "Second option"] option2: String,
+    #[description = "Another option"] option3: Option<String>,
+    #[description = "Another option"] option4: Option<String>,
+    #[description = "Another option"] option5: Option<String>,
+    #[description = "How long voting lasts, like 1h or 3d"] duration: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let options: Vec<String> = [Some(option1), Some(option2), option3, option4, option5]
+        .into_iter()
+        .flatten()
+        .map(|o| o.trim().to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+    if options.len() < 2
+        || options.len() > MAX_OPTIONS
+        || options
+            .iter()
+            .any(|o| o.chars().count() > MAX_OPTION_LENGTH)
+    {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Polls need 2 to {} options of up to {} characters.",
+                MAX_OPTIONS, MAX_OPTION_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let question = question.trim();
+    if question.is_empty() || question.chars().count() > MAX_QUESTION_LENGTH {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Questions can be up to {} characters.",
+                MAX_QUESTION_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let closes_at = match duration.as_deref().map(duration::parse) {
+        None => None,
+        Some(Some(length)) if length <= MAX_POLL => {
+            Some(serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64)
+        }
+        Some(_) => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: Please give a duration like `1h` or `3d`, up to {}.",
+                    duration::format(MAX_POLL)
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let db = &ctx.data().db;
+    let id = db
+        .create_poll(
+            guild,
+            ctx.channel_id(),
+            ctx.author().id,
+            question,
+            &options,
+            closes_at,
+        )
+        .await?;
+    let poll = db.poll(id).await?.ok_or("Poll disappeared")?;
+    polls::post(ctx.discord(), db, &poll).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Posted poll #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Utility": poll];
@@ -0,0 +1,895 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::RoleList,
+    db, duration,
+    jobs::{self, JobKind},
+    promotions, reactionroles, rolepersist, temproles, themes, Context, Error,
+};
+
+const MAX_TEMPROLE: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Manage the reaction roles of this server
+///
+/// Usage: `/reactionrole add <message> <emoji> <role>`, `/reactionrole remove <message> [emoji]` or `/reactionrole list`
+/// Example: `/reactionrole add https://discord.com/channels/1/2/3 🎮 @Gamers`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn reactionrole(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Gives members a role when they react to a message with an emoji
+///
+/// Usage: `/reactionrole add <message> <emoji> <role>`
+/// Example: `/reactionrole add https://discord.com/channels/1/2/3 🎮 @Gamers`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES | ADD_REACTIONS"
+)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message"] message: serenity::Message,
+    #[description = "Emoji members react with"] emoji: String,
+    #[description = "Role to give"] role: serenity::Role,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if message.guild_id.is_some_and(|g| g != guild) {
+        ctx.send(|m| {
+            m.content(":x: That message is in another server.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(refusal) = check_role(ctx, &role).await {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let emoji = match serenity::ReactionType::try_from(emoji.trim()) {
+        Ok(emoji) => emoji,
+        Err(_) => {
+            ctx.send(|m| m.content(":x: That isn't an emoji.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Reacting first also checks that the emoji exists and that we can use it
+    if message.react(ctx.discord(), emoji.clone()).await.is_err() {
+        ctx.send(|m| {
+            m.content(":x: I can't react with that emoji, custom emoji must be from this server.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.data()
+        .db
+        .add_reaction_role(
+            guild,
+            message.channel_id,
+            message.id,
+            &reactionroles::emoji_key(&emoji),
+            role.id,
+        )
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Reacting with {} on {} now gives <@&{}>",
+            emoji,
+            message.link(),
+            role.id.0
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops handing out roles for a message, or only for one of its emoji
+///
+/// Usage: `/reactionrole remove <message> [emoji]`
+/// Example: `/reactionrole remove https://discord.com/channels/1/2/3 🎮`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_ROLES")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the message"] message: String,
+    #[description = "Emoji to remove, all of them if empty"] emoji: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    // The message may be gone already, so it's only parsed and not fetched
+    let message = match parse_message_id(&message) {
+        Some(message) => message,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: That doesn't look like a message link or ID.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let emoji = match emoji
+        .as_deref()
+        .map(|e| serenity::ReactionType::try_from(e.trim()))
+    {
+        Some(Ok(emoji)) => Some(reactionroles::emoji_key(&emoji)),
+        Some(Err(_)) => {
+            ctx.send(|m| m.content(":x: That isn't an emoji.").ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+        None => None,
+    };
+
+    let removed = ctx
+        .data()
+        .db
+        .remove_reaction_roles(guild, message, emoji.as_deref())
+        .await?;
+
+    let response = if removed == 0 {
+        ":x: That message has no matching reaction roles.".to_string()
+    } else {
+        format!(":white_check_mark: Removed {} reaction role(s)", removed)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the reaction roles of this server
+///
+/// Usage: `/reactionrole list`
+/// Example: `/reactionrole list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_ROLES")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let roles = ctx.data().db.reaction_roles(guild).await?;
+
+    if roles.is_empty() {
+        ctx.send(|m| m.content("There are no reaction roles.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    let mut last_message = None;
+    for role in &roles {
+        let mut line = String::new();
+        if last_message != Some(role.message_id) {
+            last_message = Some(role.message_id);
+            line.push_str(&format!(
+                "https://discord.com/channels/{}/{}/{}\n",
+                guild.0, role.channel_id, role.message_id
+            ));
+        }
+
+        // Custom emoji are stored by ID, their name isn't needed to show them
+        let emoji = match role.emoji.parse::<u64>() {
+            Ok(id) => format!("<:emoji:{}>", id),
+            Err(_) => role.emoji.clone(),
+        };
+        line.push_str(&format!("{} → <@&{}>\n", emoji, role.role_id));
+
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Reaction roles")
+                .description(list)
+                .footer(|f| f.text(format!("{} in total", roles.len())))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Gives a member a role for a limited time
+///
+/// Usage: `/temprole <user> <role> <duration>`
+/// Example: `/temprole @Sticks @Event 3d`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn temprole(
+    ctx: Context<'_>,
+    #[description = "Member to give the role"] user: serenity::Member,
+    #[description = "Role to give"] role: serenity::Role,
+    #[description = "How long they keep it, like 1h or 3d"] duration: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let length = match duration::parse(&duration) {
+        Some(length) if length <= MAX_TEMPROLE => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `1h`, `3d` or `2w`, up to a year.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(refusal) = check_role(ctx, &role).await {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    ctx.discord()
+        .http
+        .add_member_role(
+            guild.0,
+            user.user.id.0,
+            role.id.0,
+            Some(&format!("Temporary role from {}", ctx.author().tag())),
+        )
+        .await?;
+
+    // Extends the current one if the member already has this role temporarily
+    let expires_at = serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64;
+    jobs::schedule(
+        &ctx.data().db,
+        JobKind::RemoveRole,
+        guild,
+        user.user.id,
+        role.id.0,
+        expires_at,
+    )
+    .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Gave <@&{}> to <@{}> until <t:{}:f>",
+            role.id.0, user.user.id.0, expires_at
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Gives a member that rejoined the roles they had when they left
+///
+/// Usage: `/restoreroles <user>`
+/// Example: `/restoreroles @Sticks`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn restoreroles(
+    ctx: Context<'_>,
+    #[description = "Member to give their roles back"] user: serenity::Member,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let reason = format!("Roles restored by {}", ctx.author().tag());
+    let restored = rolepersist::restore(ctx.discord(), ctx.data(), &user, &reason).await?;
+    let content = if restored.is_empty() {
+        format!(
+            ":x: There are no saved roles to give back to <@{}>.",
+            user.user.id.0
+        )
+    } else {
+        let roles: Vec<_> = restored.iter().map(|r| format!("<@&{}>", r.0)).collect();
+        format!(
+            ":white_check_mark: Gave <@{}> back {}",
+            user.user.id.0,
+            roles.join(", ")
+        )
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Manage roles given out during a weekly window
+///
+/// Usage: `/roleschedule add <role> <members> <grant> <remove>`, `/roleschedule remove <id>` or `/roleschedule list`
+/// Example: `/roleschedule add @Weekend Gamer @Gamers fri 18:00 mon 00:00`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("schedule_add", "schedule_remove", "schedule_list"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn roleschedule(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Gives a role to everyone with another role every week, times are in UTC
+///
+/// Usage: `/roleschedule add <role> <members> <grant> <remove>`
+/// Example: `/roleschedule add @Weekend Gamer @Gamers fri 18:00 mon 00:00`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn schedule_add(
+    ctx: Context<'_>,
+    #[description = "Role to give"] role: serenity::Role,
+    #[description = "Members with this role get it"] members: serenity::Role,
+    #[description = "When to give it, like fri 18:00"] grant: String,
+    #[description = "When to take it away, like mon 00:00"] remove: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let (grant_at, remove_at) = match (
+        temproles::parse_weekly(&grant),
+        temproles::parse_weekly(&remove),
+    ) {
+        (Some(grant_at), Some(remove_at)) if grant_at != remove_at => (grant_at, remove_at),
+        _ => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: Please give two different times like `fri 18:00` and `mon 00:00`, in UTC.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if role.id == members.id {
+        ctx.send(|m| {
+            m.content(":x: The role to give and the members role must be different.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(refusal) = check_role(ctx, &role).await {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    // Picked up by the next check, also when the window already started
+    let id = ctx
+        .data()
+        .db
+        .add_role_schedule(guild, role.id, members.id, grant_at, remove_at)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Schedule #{}: members with <@&{}> get <@&{}> from {} until {} UTC",
+            id,
+            members.id.0,
+            role.id.0,
+            temproles::format_weekly(grant_at),
+            temproles::format_weekly(remove_at)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes a role schedule, taking its role away if it's currently given out
+///
+/// Usage: `/roleschedule remove <id>`
+/// Example: `/roleschedule remove 3`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn schedule_remove(
+    ctx: Context<'_>,
+    #[description = "ID from /roleschedule list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let schedule = match ctx.data().db.delete_role_schedule(guild, id).await? {
+        Some(schedule) => schedule,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no role schedule #{}.", id))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    // Also takes it from members a failed removal missed, even if the window is over
+    ctx.defer_ephemeral().await?;
+    temproles::apply(ctx.discord(), &ctx.data().db, &schedule, false).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Deleted role schedule #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the role schedules of this server
+///
+/// Usage: `/roleschedule list`
+/// Example: `/roleschedule list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn schedule_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let schedules = ctx.data().db.role_schedules(Some(guild)).await?;
+
+    if schedules.is_empty() {
+        ctx.send(|m| m.content("There are no role schedules.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for schedule in &schedules {
+        let line = format!(
+            "#{}: <@&{}> for <@&{}>, {} until {}{}\n",
+            schedule.id,
+            schedule.role_id,
+            schedule.members_role_id,
+            temproles::format_weekly(schedule.grant_at),
+            temproles::format_weekly(schedule.remove_at),
+            if schedule.active { " (active)" } else { "" }
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Role schedules")
+                .description(list)
+                .footer(|f| f.text("Times are in UTC"))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Gives you a role with your own colour while you boost, without a colour it's removed
+///
+/// Usage: `/boostcolour [colour]`
+/// Example: `/boostcolour #ff66aa`
+#[poise::command(slash_command, guild_only, required_bot_permissions = "MANAGE_ROLES")]
+async fn boostcolour(
+    ctx: Context<'_>,
+    #[description = "Colour in hex, like #ff66aa"] colour: Option<String>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author();
+    let db = &ctx.data().db;
+
+    let colour = match colour.as_deref().map(themes::parse_colour) {
+        Some(Some(colour)) => colour,
+        Some(None) => {
+            ctx.send(|m| {
+                m.content(":x: Please give the colour in hex, like `#ff66aa`.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+        None => {
+            let content = match db.delete_booster_role(guild, author.id).await? {
+                Some(role) => {
+                    // Deleted by hand already is fine
+                    let _ = guild.delete_role(ctx.discord(), role).await;
+                    ":white_check_mark: Removed your colour"
+                }
+                None => ":x: You don't have a colour.",
+            };
+            ctx.send(|m| m.content(content).ephemeral(true)).await?;
+            return Ok(());
+        }
+    };
+
+    let boosting = match ctx.author_member().await {
+        Some(member) => member.premium_since.is_some(),
+        None => false,
+    };
+    if !boosting {
+        ctx.send(|m| {
+            m.content(":x: Colours are a perk of boosting this server.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let existing = match db.booster_role(guild, author.id).await? {
+        Some(role) => guild
+            .edit_role(ctx.discord(), role, |r| r.colour(colour as u64))
+            .await
+            .ok(),
+        None => None,
+    };
+    if existing.is_none() {
+        let role = guild
+            .create_role(ctx.discord(), |r| {
+                r.name(format!("{}'s colour", author.name))
+                    .colour(colour as u64)
+            })
+            .await?;
+
+        // Colours only show when the role is above the member's other coloured roles
+        let top = ctx.guild().and_then(|g| {
+            let bot = g.members.get(&ctx.framework().bot_id)?;
+            bot.roles
+                .iter()
+                .filter_map(|r| g.roles.get(r))
+                .map(|r| r.position)
+                .max()
+        });
+        if let Some(top) = top.filter(|t| *t > 1) {
+            let _ = guild
+                .edit_role_position(ctx.discord(), role.id, (top - 1) as u64)
+                .await;
+        }
+
+        db.set_booster_role(guild, author.id, role.id).await?;
+        guild
+            .member(ctx.discord(), author.id)
+            .await?
+            .add_role(ctx.discord(), role.id)
+            .await?;
+    }
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Your colour is now `#{:06x}`, it stays while you boost",
+            colour
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the roles members get automatically for their level and membership age
+///
+/// Usage: `/autopromote add <role> [level] [days]`, `/autopromote remove <role>` or `/autopromote list`
+/// Example: `/autopromote add @Member 5 7`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("promote_add", "promote_remove", "promote_list"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn autopromote(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Gives a role to members once they reach a level and/or have been here for some days
+///
+/// Usage: `/autopromote add <role> [level] [days]`
+/// Example: `/autopromote add @Member 5 7`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "add",
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn promote_add(
+    ctx: Context<'_>,
+    #[description = "Role to give"] role: serenity::Role,
+    #[description = "Level needed"]
+    #[min = 0]
+    level: Option<u32>,
+    #[description = "Days in the server needed"]
+    #[min = 0]
+    days: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let level = level.unwrap_or(0) as i64;
+    let days = days.unwrap_or(0) as i64;
+
+    if level == 0 && days == 0 {
+        ctx.send(|m| {
+            m.content(":x: Please give a level, a number of days or both.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(refusal) = check_role(ctx, &role).await {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    db.set_promotion_rule(guild, role.id, level, days).await?;
+
+    // Members that already qualify shouldn't have to wait for the daily check
+    let rules = db.promotion_rules(Some(guild)).await?;
+    let discord = ctx.discord().clone();
+    let db = db.clone();
+    tokio::spawn(async move {
+        if let Err(e) = promotions::sweep_guild(&discord, &db, guild, &rules).await {
+            tracing::warn!(guild = guild.0, "Error checking promotion rules: {}", e);
+        }
+    });
+
+    let rule = db::PromotionRule {
+        guild_id: guild.0 as i64,
+        role_id: role.id.0 as i64,
+        min_level: level,
+        min_days: days,
+    };
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Members now get <@&{}> at {}",
+            role.id.0,
+            promotions::describe(&rule)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops giving out a role automatically, members keep it
+///
+/// Usage: `/autopromote remove <role>`
+/// Example: `/autopromote remove @Member`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "remove",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn promote_remove(
+    ctx: Context<'_>,
+    #[description = "Role to stop giving"] role: serenity::Role,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().db.delete_promotion_rule(guild, role.id).await? {
+        format!(
+            ":white_check_mark: <@&{}> isn't given out automatically anymore",
+            role.id.0
+        )
+    } else {
+        format!(":x: <@&{}> has no promotion rule.", role.id.0)
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Lists the promotion rules of this server
+///
+/// Usage: `/autopromote list`
+/// Example: `/autopromote list`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "list",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn promote_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let rules = ctx.data().db.promotion_rules(Some(guild)).await?;
+
+    if rules.is_empty() {
+        ctx.send(|m| m.content("There are no promotion rules.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let list = rules
+        .iter()
+        .map(|rule| format!("<@&{}> at {}", rule.role_id, promotions::describe(rule)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Promotion rules").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the roles new members get when they join
+///
+/// Usage: `/autorole set <role> [role2] [role3]` or `/autorole clear`
+/// Example: `/autorole set @Member`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("autorole_set", "autorole_clear"),
+    required_permissions = "MANAGE_ROLES",
+    default_member_permissions = "MANAGE_ROLES"
+)]
+async fn autorole(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets the roles new members get, replacing the current ones
+///
+/// Usage: `/autorole set <role> [role2] [role3]`
+/// Example: `/autorole set @Member @Unverified`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "set",
+    required_permissions = "MANAGE_ROLES",
+    required_bot_permissions = "MANAGE_ROLES"
+)]
+async fn autorole_set(
+    ctx: Context<'_>,
+    #[description = "Role to give"] role: serenity::Role,
+    #[description = "Another role to give"] role2: Option<serenity::Role>,
+    #[description = "Another role to give"] role3: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let roles: Vec<_> = [Some(role), role2, role3].into_iter().flatten().collect();
+
+    for role in &roles {
+        if let Some(refusal) = check_role(ctx, role).await {
+            ctx.send(|m| {
+                m.content(format!("{} (<@&{}>)", refusal, role.id.0))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    }
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            c.role_lists
+                .insert(RoleList::AutoRole, roles.iter().map(|r| r.id).collect());
+        })
+        .await?;
+
+    let list = roles
+        .iter()
+        .map(|r| format!("<@&{}>", r.id.0))
+        .collect::<Vec<_>>()
+        .join(", ");
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: New members now get {}", list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Stops giving roles to new members
+///
+/// Usage: `/autorole clear`
+/// Example: `/autorole clear`
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "clear",
+    required_permissions = "MANAGE_ROLES"
+)]
+async fn autorole_clear(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            c.role_lists.remove(&RoleList::AutoRole);
+        })
+        .await?;
+
+    ctx.send(|m| {
+        m.content(":white_check_mark: New members don't get any roles anymore")
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Returns why this role can't be handed out by the author
+pub async fn check_role(ctx: Context<'_>, role: &serenity::Role) -> Option<&'static str> {
+    let guild = ctx.guild()?;
+
+    if role.id.0 == guild.id.0 {
+        return Some(":x: Everyone already has that role.");
+    }
+    if role.managed {
+        return Some(":x: That role is managed by an integration and can't be given out.");
+    }
+
+    let top_position = |member: Option<serenity::Member>| {
+        member
+            .map(|m| {
+                m.roles
+                    .iter()
+                    .filter_map(|r| guild.roles.get(r))
+                    .map(|r| r.position)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0)
+    };
+
+    let author = ctx.author().id;
+    if author != guild.owner_id {
+        let member = guild.member(ctx.discord(), author).await.ok();
+        if top_position(member) <= role.position {
+            return Some(":x: Your highest role must be above that role.");
+        }
+    }
+
+    let member = guild
+        .member(ctx.discord(), ctx.framework().bot_id)
+        .await
+        .ok();
+    if top_position(member) <= role.position {
+        return Some(":x: My highest role must be above that role.");
+    }
+
+    None
+}
+
+// Accepts a message link or a raw ID
+fn parse_message_id(value: &str) -> Option<serenity::MessageId> {
+    let id = value.trim().rsplit('/').next()?;
+    id.parse().ok().map(serenity::MessageId)
+}
+
+command_list!["Roles": reactionrole, temprole, restoreroles, roleschedule, autopromote, autorole, boostcolour];
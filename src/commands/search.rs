@@ -0,0 +1,134 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::Feature,
+    mentions::Mentions,
+    messagearchive::{self, PAGE_SIZE},
+    privacy, Context, Error,
+};
+
+/// Searches this server's archived messages in the channels you can read
+///
+/// Usage: `/search <query> [channel] [author] [page]`
+/// Example: `/search release date channel:#announcements`
+#[poise::command(slash_command, guild_only, user_cooldown = 5)]
+async fn search(
+    ctx: Context<'_>,
+    #[description = "Words the messages have"] query: String,
+    #[description = "Only search this channel and its threads"] channel: Option<
+        serenity::GuildChannel,
+    >,
+    #[description = "Only search messages by this member"] author: Option<serenity::User>,
+    #[description = "Page to show"]
+    #[min = 1]
+    page: Option<u32>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let page = page.unwrap_or(1).max(1) as i64;
+
+    let config = ctx.data().guild_configs.get(guild).await?;
+    let stores_content = ctx.data().db.stores_content(guild).await?;
+    let fts = messagearchive::fts_query(&query, (!stores_content).then_some(guild));
+    let member = ctx.author_member().await.ok_or("Must be used in a guild")?;
+    let readable = messagearchive::readable_channels(ctx.discord(), guild, &member);
+    // Threads go by their parent like in the archive
+    let parent = channel.as_ref().map(|c| match c.kind {
+        serenity::ChannelType::PublicThread | serenity::ChannelType::NewsThread => {
+            c.parent_id.unwrap_or(c.id)
+        }
+        _ => c.id,
+    });
+
+    let refusal = if !config.is_enabled(Feature::MessageArchive) {
+        Some(
+            ":x: This server doesn't archive messages, an admin can turn it on with \
+            `/config feature message_archive True`."
+                .to_string(),
+        )
+    } else if !stores_content && !privacy::has_key() {
+        Some(
+            ":x: This server is in privacy mode, which needs the bot's owner to set PRIVACY_KEY \
+            before messages can be archived."
+                .to_string(),
+        )
+    } else if fts.is_none() {
+        Some(":x: Please search for at least one word.".to_string())
+    } else if parent.is_some_and(|p| !readable.contains(&p)) {
+        Some(":x: You can't read that channel.".to_string())
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let offset = (page - 1) * PAGE_SIZE;
+    let results = ctx
+        .data()
+        .db
+        .search_archive(
+            guild,
+            &fts.unwrap_or_default(),
+            &readable,
+            channel.as_ref().map(|c| c.id),
+            author.as_ref().map(|a| a.id),
+            // One more than shown tells whether there's a next page
+            PAGE_SIZE + 1,
+            offset,
+        )
+        .await?;
+
+    if results.is_empty() {
+        let response = if page == 1 {
+            ":x: No messages match that, try other words."
+        } else {
+            ":x: There are no more results."
+        };
+        ctx.send(|m| m.content(response).ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let more = results.len() as i64 > PAGE_SIZE;
+    let list = results
+        .iter()
+        .take(PAGE_SIZE as usize)
+        .map(|message| {
+            format!(
+                "<@{}> in <#{}> <t:{}:R> [Jump]({})\n{}",
+                message.author_id,
+                message.channel_id,
+                message.created_at,
+                serenity::MessageId(message.message_id as u64)
+                    .link(serenity::ChannelId(message.channel_id as u64), Some(guild)),
+                if stores_content {
+                    message.snippet.as_str()
+                } else {
+                    "*Content isn't stored in privacy mode*"
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let footer = if more {
+        format!("Page {}, see more with page:{}", page, page + 1)
+    } else {
+        format!("Page {}", page)
+    };
+
+    // Results can come from channels others here can't read, so only the searcher sees them
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Messages matching \"{}\"", query.trim()))
+                .description(list)
+                .footer(|f| f.text(footer))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Utility": search];
@@ -0,0 +1,321 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Stage, mentions::Mentions, stages, Context, Error};
+
+// Discord limits stage topics to this many characters
+const MAX_TOPIC_LENGTH: usize = 120;
+
+/// Run a stage with a raise hand queue for speakers
+///
+/// Usage: `/stage start <topic> [channel]`, `/stage approve [user]`, `/stage deny <user>`, `/stage queue` or `/stage end`
+/// Example: `/stage start "Community Q&A"`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("start", "approve", "deny", "queue", "end"),
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS",
+    default_member_permissions = "MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn stage(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Opens a stage with a topic and posts the raise hand buttons in this channel
+///
+/// Usage: `/stage start <topic> [channel]`
+/// Example: `/stage start "Release party" channel:#main-stage`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS",
+    required_bot_permissions = "SEND_MESSAGES | MANAGE_CHANNELS | MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "What the stage is about"] topic: String,
+    #[description = "Stage channel to use, the one you're in by default"]
+    #[channel_types("Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let channel = match channel.map(|c| c.id).or_else(|| stage_channel(ctx)) {
+        Some(channel) => channel,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: Join a stage channel or give one.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let topic = topic.trim();
+    if topic.is_empty() || topic.chars().count() > MAX_TOPIC_LENGTH {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Topics can be up to {} characters.",
+                MAX_TOPIC_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let db = &ctx.data().db;
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let id = match db
+        .start_stage(
+            guild,
+            channel,
+            ctx.channel_id(),
+            ctx.author().id,
+            topic,
+            now,
+        )
+        .await?
+    {
+        Some(id) => id,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(
+                    ":x: <#{}> has a stage running already, end it with `/stage end`.",
+                    channel.0
+                ))
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(e) = channel
+        .create_stage_instance(ctx.discord(), |i| i.channel_id(channel.0).topic(topic))
+        .await
+    {
+        db.end_stage(id, now).await?;
+        ctx.send(|m| {
+            m.content(format!(":x: Couldn't open the stage: {}", e))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let stage = db.stage(id).await?.ok_or("Stage disappeared")?;
+    stages::post(ctx.discord(), db, &stage).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Started stage #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lets a member speak, the longest waiting raised hand by default
+///
+/// Usage: `/stage approve [user] [channel]`
+/// Example: `/stage approve @Sticks`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn approve(
+    ctx: Context<'_>,
+    #[description = "Member to let speak, next in line by default"] user: Option<serenity::User>,
+    #[description = "Stage channel, the one you're in by default"]
+    #[channel_types("Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let stage = match running_stage(ctx, channel).await? {
+        Some(stage) => stage,
+        None => return Ok(()),
+    };
+    let db = &ctx.data().db;
+
+    let user = match user.map(|u| u.id) {
+        Some(user) => user,
+        None => match db.stage_queue(stage.id).await?.first() {
+            Some(request) => serenity::UserId(request.user_id as u64),
+            None => {
+                ctx.send(|m| m.content(":x: Nobody is waiting to speak.").ephemeral(true))
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    db.approve_speaker(stage.id, user, serenity::Timestamp::now().unix_timestamp())
+        .await?;
+    let content = if stages::invite(ctx.discord(), &stage, user).await? {
+        format!(":white_check_mark: Invited <@{}> to speak", user.0)
+    } else {
+        format!(
+            ":white_check_mark: Approved <@{}>, they'll be invited to speak once they join <#{}>",
+            user.0, stage.channel_id
+        )
+    };
+    stages::refresh(ctx.discord(), db, &stage).await?;
+
+    ctx.send(|m| {
+        m.content(content)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Turns down a raised hand
+///
+/// Usage: `/stage deny <user> [channel]`
+/// Example: `/stage deny @Sticks`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn deny(
+    ctx: Context<'_>,
+    #[description = "Member whose hand to lower"] user: serenity::User,
+    #[description = "Stage channel, the one you're in by default"]
+    #[channel_types("Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let stage = match running_stage(ctx, channel).await? {
+        Some(stage) => stage,
+        None => return Ok(()),
+    };
+    let db = &ctx.data().db;
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let content = if db.deny_speaker(stage.id, user.id, now).await? {
+        stages::refresh(ctx.discord(), db, &stage).await?;
+        format!(":white_check_mark: Lowered the hand of <@{}>", user.id.0)
+    } else {
+        format!(":x: <@{}> isn't waiting to speak.", user.id.0)
+    };
+
+    ctx.send(|m| {
+        m.content(content)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows who is waiting to speak
+///
+/// Usage: `/stage queue [channel]`
+/// Example: `/stage queue`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn queue(
+    ctx: Context<'_>,
+    #[description = "Stage channel, the one you're in by default"]
+    #[channel_types("Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let stage = match running_stage(ctx, channel).await? {
+        Some(stage) => stage,
+        None => return Ok(()),
+    };
+
+    let queue = ctx.data().db.stage_queue(stage.id).await?;
+    let content = if queue.is_empty() {
+        "Nobody is waiting to speak.".to_string()
+    } else {
+        // Embed descriptions fit this many lines
+        queue
+            .iter()
+            .take(50)
+            .enumerate()
+            .map(|(i, r)| format!("{}. <@{}> <t:{}:R>", i + 1, r.user_id, r.requested_at))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Queue for {}", stage.topic))
+                .description(content)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Closes the stage and posts who spoke
+///
+/// Usage: `/stage end [channel]`
+/// Example: `/stage end`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MUTE_MEMBERS | MOVE_MEMBERS",
+    required_bot_permissions = "MANAGE_CHANNELS | MUTE_MEMBERS | MOVE_MEMBERS"
+)]
+async fn end(
+    ctx: Context<'_>,
+    #[description = "Stage channel, the one you're in by default"]
+    #[channel_types("Stage")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let stage = match running_stage(ctx, channel).await? {
+        Some(stage) => stage,
+        None => return Ok(()),
+    };
+
+    let id = stage.id;
+    stages::end(ctx.discord(), &ctx.data().db, stage, true).await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Ended stage #{}", id))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+// The stage in the given channel or the author's, replies itself if there is none
+async fn running_stage(
+    ctx: Context<'_>,
+    channel: Option<serenity::GuildChannel>,
+) -> Result<Option<Stage>, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let stage = match channel.map(|c| c.id).or_else(|| stage_channel(ctx)) {
+        Some(channel) => ctx.data().db.running_stage(guild, channel).await?,
+        None => None,
+    };
+    if stage.is_none() {
+        ctx.send(|m| {
+            m.content(":x: There is no stage running there, join it or give its channel.")
+                .ephemeral(true)
+        })
+        .await?;
+    }
+
+    Ok(stage)
+}
+
+// The voice or stage channel the author is in, from the cache
+fn stage_channel(ctx: Context<'_>) -> Option<serenity::ChannelId> {
+    ctx.guild()?.voice_states.get(&ctx.author().id)?.channel_id
+}
+
+command_list!["Channels": stage];
@@ -0,0 +1,207 @@
+use crate::{
+    economy::{self, Transfer},
+    streaks::{self, Activity},
+    wordgame, Context, Error,
+};
+
+// Members shown on a streak leaderboard
+const LEADERBOARD_SIZE: i64 = 10;
+
+/// Sets your timezone, your days and streaks start at its midnight
+///
+/// Usage: `/timezone <offset>`
+/// Example: `/timezone UTC+02:00`
+#[poise::command(slash_command, prefix_command)]
+async fn timezone(
+    ctx: Context<'_>,
+    #[description = "Offset from UTC, like +2 or -05:30"] offset: String,
+) -> Result<(), Error> {
+    let offset = match streaks::parse_offset(&offset) {
+        Some(offset) => offset,
+        None => {
+            ctx.send(|m| {
+                m.content(":x: Give your offset from UTC, like `+2`, `-05:30` or `UTC+9`.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let db = &ctx.data().db;
+    let author = ctx.author().id;
+    let content = if db.timezone(author).await? == offset {
+        format!(
+            ":white_check_mark: Your timezone is {} already",
+            streaks::format_offset(offset)
+        )
+    } else {
+        match db
+            .set_timezone(author, offset, streaks::TIMEZONE_COOLDOWN)
+            .await?
+        {
+            None => format!(
+                ":white_check_mark: Your timezone is now {}",
+                streaks::format_offset(offset)
+            ),
+            Some(ready_at) => format!(
+                ":x: You changed your timezone recently, you can change it again <t:{}:R>.",
+                ready_at
+            ),
+        }
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Shows the longest running streaks and your own
+///
+/// Usage: `/streaks [activity]`
+/// Example: `/streaks word`
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    guild_cooldown = 10,
+    rename = "streaks"
+)]
+async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "Activity to show, daily if empty"] activity: Option<Activity>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let activity = activity.unwrap_or(Activity::Daily);
+    let db = &ctx.data().db;
+
+    // Timezones are at most a day apart, anything older is broken for everyone
+    let utc_today = economy::today();
+    let top = db
+        .top_streaks(guild, activity, utc_today - 2, LEADERBOARD_SIZE)
+        .await?;
+    let list = top
+        .iter()
+        .enumerate()
+        .map(|(i, (user, streak))| {
+            format!(
+                "**{}.** <@{}> {} days (best {})",
+                i + 1,
+                user,
+                streak.current,
+                streak.best
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let (today, _) = streaks::today(db, ctx.author().id).await?;
+    let own = match db.streak(guild, ctx.author().id, activity).await? {
+        Some(streak) if streaks::is_alive(streak.last_day, today) => {
+            format!("{} days, your best is {}", streak.current, streak.best)
+        }
+        Some(streak) => format!("Broken, your best is {}", streak.best),
+        None => "Not started yet".to_string(),
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Longest `{}` streaks", activity.name()))
+                .description(if list.is_empty() {
+                    "Nobody has a streak going yet.".to_string()
+                } else {
+                    list
+                })
+                .field("Your streak", own, false)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Unscramble the word of the day
+///
+/// Usage: `/word today` or `/word guess <answer>`
+/// Example: `/word guess garden`
+#[poise::command(slash_command, guild_only, subcommands("word_today", "guess"))]
+async fn word(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows today's scrambled word
+///
+/// Usage: `/word today`
+/// Example: `/word today`
+#[poise::command(slash_command, guild_only, rename = "today")]
+async fn word_today(ctx: Context<'_>) -> Result<(), Error> {
+    let (today, offset) = streaks::today(&ctx.data().db, ctx.author().id).await?;
+    let (_, scrambled) = wordgame::word_of(today);
+
+    ctx.send(|m| {
+        m.content(format!(
+            "Today's word is **{}**, unscramble it with `/word guess` for {}. A new one comes \
+            <t:{}:R>.",
+            scrambled,
+            economy::format(wordgame::WORD_REWARD),
+            streaks::day_end(today, offset)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Guesses today's word
+///
+/// Usage: `/word guess <answer>`
+/// Example: `/word guess garden`
+#[poise::command(slash_command, guild_only, user_cooldown = 3)]
+async fn guess(
+    ctx: Context<'_>,
+    #[description = "The unscrambled word"] answer: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+    let (today, offset) = streaks::today(db, author).await?;
+
+    if !wordgame::is_right(today, &answer) {
+        ctx.send(|m| m.content(":x: That's not it, try again.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let key = economy::daily_key("word", guild, author, today);
+    let transfer = db
+        .transfer(
+            guild,
+            None,
+            Some(author),
+            wordgame::WORD_REWARD,
+            "word",
+            &key,
+        )
+        .await?;
+    let content = if transfer == Transfer::Duplicate {
+        format!(
+            ":x: You solved today's word already, a new one comes <t:{}:R>.",
+            streaks::day_end(today, offset)
+        )
+    } else {
+        let streak = db
+            .record_streak(guild, author, Activity::Word, today)
+            .await?
+            .map_or(1, |advanced| advanced.streak.current);
+        format!(
+            ":white_check_mark: That's it! You got {}, your word streak is {} days",
+            economy::format(wordgame::WORD_REWARD),
+            streak
+        )
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+command_list!["Streaks": timezone, leaderboard, word];
@@ -0,0 +1,227 @@
+use crate::{mentions::Mentions, tags, Context, Error};
+
+/// Canned responses for this server
+///
+/// Usage: `/tag show <name>`, `/tag list`, `/tag create <name> <content>` or `/tag delete <name>`
+/// Example: `/tag show rules` or `~rules`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("show", "list", "create", "delete")
+)]
+async fn tag(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows a tag
+///
+/// Usage: `/tag show <name>`
+/// Example: `/tag show rules`
+#[poise::command(slash_command, guild_only, channel_cooldown = 3)]
+async fn show(
+    ctx: Context<'_>,
+    #[description = "Name of the tag"]
+    #[autocomplete = "autocomplete_tag"]
+    name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let content = match tags::normalize_name(&name) {
+        Some(name) => ctx.data().db.use_tag(guild, &name).await?,
+        None => None,
+    };
+
+    match content {
+        Some(content) => {
+            ctx.send(|m| {
+                m.content(content)
+                    .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            })
+            .await?;
+        }
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no tag called `{}`.", name))
+                    .ephemeral(true)
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the tags of this server
+///
+/// Usage: `/tag list`
+/// Example: `/tag list`
+#[poise::command(slash_command, guild_only)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let tags = ctx.data().db.tags(guild).await?;
+
+    if tags.is_empty() {
+        ctx.send(|m| m.content("This server has no tags yet.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for tag in &tags {
+        let line = format!(
+            "`{}` by <@{}> on <t:{}:d>, {} uses\n",
+            tag.name, tag.author_id, tag.created_at, tag.uses
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    let prefix = ctx
+        .data()
+        .guild_configs
+        .get(guild)
+        .await?
+        .prefix()
+        .to_string();
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Tags").description(list).footer(|f| {
+                f.text(format!(
+                    "{} in total, show one with /tag show or {}name",
+                    tags.len(),
+                    prefix
+                ))
+            })
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Creates a tag
+///
+/// Usage: `/tag create <name> <content>`
+/// Example: `/tag create rules Please read #rules before posting!`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn create(
+    ctx: Context<'_>,
+    #[description = "Name, letters, numbers, - and _ only"] name: String,
+    #[description = "What the tag says"] content: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let name = match tags::normalize_name(&name) {
+        Some(name) => name,
+        None => {
+            ctx.send(|m| {
+                m.content(
+                    ":x: Tag names can only have letters, numbers, - and _, up to 32 characters.",
+                )
+                .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if content.chars().count() > 2000 {
+        ctx.send(|m| {
+            m.content(":x: Tags can be up to 2000 characters long.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // A tag named like a command could never be used with the prefix
+    if ctx
+        .framework()
+        .options()
+        .commands
+        .iter()
+        .any(|c| c.name == name || c.aliases.iter().any(|a| *a == name))
+    {
+        ctx.send(|m| {
+            m.content(format!(":x: `{}` is already a command.", name))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let response = if ctx
+        .data()
+        .db
+        .create_tag(guild, &name, &content, ctx.author().id)
+        .await?
+    {
+        format!(":white_check_mark: Created tag `{}`", name)
+    } else {
+        format!(":x: There already is a tag called `{}`.", name)
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Deletes a tag
+///
+/// Usage: `/tag delete <name>`
+/// Example: `/tag delete rules`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES",
+    default_member_permissions = "MANAGE_MESSAGES"
+)]
+async fn delete(
+    ctx: Context<'_>,
+    #[description = "Name of the tag"]
+    #[autocomplete = "autocomplete_tag"]
+    name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let deleted = match tags::normalize_name(&name) {
+        Some(name) => ctx.data().db.delete_tag(guild, &name).await?,
+        None => false,
+    };
+
+    let response = if deleted {
+        format!(":white_check_mark: Deleted tag `{}`", name)
+    } else {
+        format!(":x: There is no tag called `{}`.", name)
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+async fn autocomplete_tag<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let tags = match ctx.guild_id() {
+        Some(guild) => ctx.data().db.tags(guild).await.unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let partial = partial.to_lowercase();
+    tags.into_iter()
+        .map(|tag| tag.name)
+        .filter(move |name| name.starts_with(&partial))
+        // Discord shows at most 25 choices
+        .take(25)
+}
+
+command_list!["Tags": tag];
@@ -0,0 +1,264 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Theme, themes, Context, Error};
+
+const MAX_THEME_NAME_LENGTH: usize = 32;
+// Discord's limits are higher, but icons are kept in the database until the theme is deleted
+const MAX_ICON_SIZE: u64 = 1024 * 1024;
+const MAX_REACTIONS: usize = 10;
+
+/// Set up seasonal themes for holidays and events
+///
+/// Usage: `/theme add <name> <start> <end> [colour] [icon] [reactions] [status]`, `/theme remove <name>` or `/theme list`
+/// Example: `/theme add halloween 10-24 10-31 #ff7518 reactions:🎃 👻`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn theme(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Adds a theme that's applied every year between two dates
+///
+/// Usage: `/theme add <name> <start> <end> [colour] [icon] [reactions] [status]`
+/// Example: `/theme add winter 12-20 01-02 #7fb2f0 reactions:❄️ ⛄`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+#[allow(clippy::too_many_arguments)]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "Name of the theme"] name: String,
+    #[description = "First day, like 12-20"] start: String,
+    #[description = "Last day, like 01-02"] end: String,
+    #[description = "Embed colour, like #7fb2f0"] colour: Option<String>,
+    #[description = "Server icon during the theme"] icon: Option<serenity::Attachment>,
+    #[description = "Emojis the bot sometimes reacts with, separated by spaces"] reactions: Option<
+        String,
+    >,
+    #[description = "Bot status like watching the snow fall, bot owner only"] status: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+
+    let name = name.trim().to_lowercase();
+    let dates = themes::parse_date(&start).zip(themes::parse_date(&end));
+    let colour = colour.as_deref().map(themes::parse_colour);
+    let reactions = reactions.map(|r| themes::parse_reactions(&r));
+    let owner = ctx.framework().options().owners.contains(&ctx.author().id);
+    let bad_icon = icon.as_ref().is_some_and(|i| {
+        i.size > MAX_ICON_SIZE
+            || !i
+                .content_type
+                .as_deref()
+                .is_some_and(|t| t.starts_with("image/"))
+    });
+
+    let refusal = if name.is_empty()
+        || name.chars().count() > MAX_THEME_NAME_LENGTH
+        || name.contains(char::is_whitespace)
+    {
+        Some(format!(
+            ":x: Theme names can be up to {} characters without spaces.",
+            MAX_THEME_NAME_LENGTH
+        ))
+    } else if dates.is_none() {
+        Some(":x: Please give the first and last day like `12-20` and `01-02`.".to_string())
+    } else if colour == Some(None) {
+        Some(":x: Please give the colour in hex, like `#7fb2f0`.".to_string())
+    } else if reactions
+        .as_ref()
+        .is_some_and(|r| r.is_empty() || r.len() > MAX_REACTIONS)
+    {
+        Some(format!(
+            ":x: Please give up to {} emojis separated by spaces.",
+            MAX_REACTIONS
+        ))
+    } else if status.is_some() && !owner {
+        Some(":x: Only the bot owner can theme the bot's status.".to_string())
+    } else if bad_icon {
+        Some(format!(
+            ":x: The icon must be an image of up to {} KB.",
+            MAX_ICON_SIZE / 1024
+        ))
+    } else {
+        None
+    };
+    if let Some(refusal) = refusal {
+        ctx.send(|m| m.content(refusal).ephemeral(true)).await?;
+        return Ok(());
+    }
+    let (starts_on, ends_on) = dates.unwrap_or_default();
+
+    // They'd save each other's server icons
+    let overlapping = db
+        .themes(Some(guild))
+        .await?
+        .into_iter()
+        .find(|t| themes::overlaps((t.starts_on, t.ends_on), (starts_on, ends_on)));
+    if let Some(overlapping) = overlapping {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: That overlaps with the `{}` theme.",
+                overlapping.name
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+    let icon = match &icon {
+        Some(icon) => Some(ctx.data().themes.download_icon(&icon.url).await?),
+        None => None,
+    };
+
+    let theme = Theme {
+        guild_id: guild.0 as i64,
+        name: name.clone(),
+        starts_on,
+        ends_on,
+        colour: colour.flatten().map(i64::from),
+        icon,
+        reactions: reactions.map(|r| {
+            r.iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }),
+        status,
+        ..Theme::default()
+    };
+    if !db.add_theme(&theme).await? {
+        ctx.send(|m| {
+            m.content(format!(":x: There is a `{}` theme already.", name))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    // Applied right away if it's already going
+    ctx.data()
+        .themes
+        .reconcile(ctx.discord(), &ctx.framework().shard_manager())
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: The `{}` theme is applied every year from {} until {}",
+            name,
+            themes::format_date(starts_on),
+            themes::format_date(ends_on)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes a theme, putting the server icon back if it's going
+///
+/// Usage: `/theme remove <name>`
+/// Example: `/theme remove halloween`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the theme"] name: String,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let name = name.trim().to_lowercase();
+
+    let theme = match ctx.data().db.delete_theme(guild, &name).await? {
+        Some(theme) => theme,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no `{}` theme.", name))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    ctx.defer_ephemeral().await?;
+    let themes = &ctx.data().themes;
+    themes.end(ctx.discord(), &theme).await?;
+    themes
+        .reconcile(ctx.discord(), &ctx.framework().shard_manager())
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(":white_check_mark: Deleted the `{}` theme", name))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the themes of this server
+///
+/// Usage: `/theme list`
+/// Example: `/theme list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let themes = ctx.data().db.themes(Some(guild)).await?;
+
+    if themes.is_empty() {
+        ctx.send(|m| m.content("There are no themes.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for theme in &themes {
+        let mut changes = Vec::new();
+        if let Some(colour) = theme.colour {
+            changes.push(format!("colour `#{:06x}`", colour));
+        }
+        if theme.icon.is_some() {
+            changes.push("server icon".to_string());
+        }
+        if let Some(reactions) = &theme.reactions {
+            changes.push(format!("reactions {}", reactions));
+        }
+        if let Some(status) = &theme.status {
+            changes.push(format!("status `{}`", status));
+        }
+        if changes.is_empty() {
+            changes.push("nothing".to_string());
+        }
+
+        let line = format!(
+            "**{}**: {} until {}{}, {}\n",
+            theme.name,
+            themes::format_date(theme.starts_on),
+            themes::format_date(theme.ends_on),
+            if theme.active { " (going)" } else { "" },
+            changes.join(", ")
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| e.title("Themes").description(list))
+            .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Settings": theme];
@@ -0,0 +1,192 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::Mentions,
+    triggers::{self, TriggerKind, MAX_TRIGGERS},
+    Context, Error,
+};
+
+/// Automatic replies to messages matching a pattern, or copies of them in another channel
+///
+/// Usage: `/trigger add <kind> <pattern> [response] [reaction] [forward]`, `/trigger remove <id>` or `/trigger list`
+/// Example: `/trigger add contains "good bot" Thank you! 💙`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn trigger(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Replies, reacts or forwards to a channel when messages match a pattern, ignoring case
+///
+/// Usage: `/trigger add <kind> <pattern> [response] [reaction] [forward]`
+/// Example: `/trigger add regex "^(hi|hello)$" Hey there! 👋` or `/trigger add contains refund forward:#billing`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "How the pattern is matched"] kind: TriggerKind,
+    #[description = "Text or regex to look for"] pattern: String,
+    #[description = "What to reply with"] response: Option<String>,
+    #[description = "Emoji to react with"] reaction: Option<String>,
+    #[description = "Channel to copy matching messages to"]
+    #[channel_types("Text")]
+    forward: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let response = response.filter(|r| !r.trim().is_empty());
+    let reaction = reaction.map(|r| r.trim().to_string());
+    let forward = forward.map(|c| c.id);
+
+    if response.is_none() && reaction.is_none() && forward.is_none() {
+        ctx.send(|m| {
+            m.content(":x: Please give a response, a reaction or a channel to forward to.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if let Err(e) = triggers::validate(kind, &pattern) {
+        ctx.send(|m| {
+            m.content(format!(":x: That pattern doesn't work: ```{}```", e))
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    if reaction
+        .as_deref()
+        .is_some_and(|r| serenity::ReactionType::try_from(r).is_err())
+    {
+        ctx.send(|m| m.content(":x: That isn't an emoji.").ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    if ctx.data().db.triggers(guild).await?.len() >= MAX_TRIGGERS {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Servers can have up to {} triggers, remove one first.",
+                MAX_TRIGGERS
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let id = ctx
+        .data()
+        .triggers
+        .add(
+            guild,
+            kind,
+            &pattern,
+            response.as_deref(),
+            reaction.as_deref(),
+            forward,
+        )
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Added trigger #{} for messages matching `{}` ({})",
+            id,
+            pattern,
+            kind.name()
+        ))
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a trigger
+///
+/// Usage: `/trigger remove <id>`
+/// Example: `/trigger remove 3`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "ID from /trigger list"] id: i64,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    let response = if ctx.data().triggers.remove(guild, id).await? {
+        format!(":white_check_mark: Removed trigger #{}", id)
+    } else {
+        format!(":x: There is no trigger #{}.", id)
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Lists the triggers of this server
+///
+/// Usage: `/trigger list`
+/// Example: `/trigger list`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let triggers = ctx.data().db.triggers(guild).await?;
+
+    if triggers.is_empty() {
+        ctx.send(|m| {
+            m.content("This server has no triggers, only the built-in `h` one.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let mut list = String::new();
+    for trigger in &triggers {
+        let mut answer = Vec::new();
+        if let Some(response) = &trigger.response {
+            let preview: String = response.chars().take(50).collect();
+            answer.push(format!("\"{}\"", preview));
+        }
+        if let Some(reaction) = &trigger.reaction {
+            answer.push(reaction.clone());
+        }
+        if let Some(channel) = trigger.forward_channel_id {
+            answer.push(format!("forward to <#{}>", channel));
+        }
+
+        let line = format!(
+            "#{} {} `{}` → {}\n",
+            trigger.id,
+            trigger.kind,
+            trigger.pattern,
+            answer.join(" and ")
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Triggers")
+                .description(list)
+                .footer(|f| f.text(format!("{} of {}", triggers.len(), MAX_TRIGGERS)))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+command_list!["Triggers": trigger];
@@ -0,0 +1,788 @@
+use std::time::Instant;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    a2s,
+    db::Entitlement,
+    duration,
+    mentions::Mentions,
+    quotas::{self, Quota, Tier},
+    votes, Context, Error,
+};
+
+// Reminders further out than this are most likely typos
+const MAX_REMINDER: std::time::Duration = std::time::Duration::from_secs(365 * 24 * 60 * 60);
+// Pending reminders a single user can have
+const MAX_REMINDERS_PER_USER: usize = 25;
+// Discord's limits for embed titles and field values
+const EMBED_TITLE_LIMIT: usize = 256;
+const EMBED_FIELD_LIMIT: usize = 1024;
+
+/// Displays your or another user's account creation date
+///
+/// Usage: `/age [user]`
+/// Example: `/age @user`
+#[poise::command(slash_command)]
+async fn age(
+    ctx: Context<'_>,
+    #[description = "Selected user"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let u = user.as_ref().unwrap_or_else(|| ctx.author());
+    let response = format!("{}'s account was created at {}", u.name, u.created_at());
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Lists all commands, or shows how to use one
+///
+/// Usage: `/help [command]`
+/// Example: `/help timeout` or `~help config set`
+#[poise::command(prefix_command, slash_command, track_edits)]
+async fn help(
+    ctx: Context<'_>,
+    #[description = "Command to show help for"]
+    #[autocomplete = "autocomplete_command"]
+    #[rest]
+    command: Option<String>,
+) -> Result<(), Error> {
+    let name = match command {
+        Some(name) => name,
+        None => {
+            let config = poise::builtins::HelpConfiguration {
+                extra_text_at_bottom: "Use /help <command> to see how to use a command.",
+                ..Default::default()
+            };
+            poise::builtins::help(ctx, None, config).await?;
+            return Ok(());
+        }
+    };
+
+    // Walk down the subcommands so `config set` finds the `set` subcommand of `config`
+    let mut commands = &ctx.framework().options().commands;
+    let mut found = None;
+    for part in name.split_whitespace() {
+        match commands
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(part) && !c.hide_in_help)
+        {
+            Some(command) => {
+                commands = &command.subcommands;
+                found = Some(command);
+            }
+            None => {
+                found = None;
+                break;
+            }
+        }
+    }
+
+    let command = match found {
+        Some(command) => command,
+        None => {
+            ctx.send(|m| {
+                m.content(format!(":x: There is no command called `{}`.", name))
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let subcommands = command
+        .subcommands
+        .iter()
+        .map(|c| {
+            format!(
+                "`{}`: {}",
+                c.name,
+                c.description.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(&command.qualified_name)
+                .description(command.description.as_deref().unwrap_or("No description"));
+
+            if let Some(help_text) = command.help_text {
+                e.field("How to use", help_text(), false);
+            }
+            if !subcommands.is_empty() {
+                e.field("Subcommands", subcommands, false);
+            }
+            if let Some(category) = command.category {
+                e.footer(|f| f.text(category));
+            }
+
+            e
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn autocomplete_command<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    ctx.framework()
+        .options()
+        .commands
+        .iter()
+        .filter(|c| !c.hide_in_help && c.name.starts_with(partial))
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Set, list or cancel reminders
+#[poise::command(slash_command, prefix_command, subcommands("me", "list", "cancel"))]
+async fn remind(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Reminds you of something later, by DM or in this channel
+///
+/// Usage: `/remind me <duration> <text>`
+/// Example: `/remind me 2h30m Take the pizza out` or `~remind me 1d Renew the domain`
+#[poise::command(slash_command, prefix_command)]
+async fn me(
+    ctx: Context<'_>,
+    #[description = "In how long, e.g. 10m, 2h or 1w"] duration: String,
+    #[description = "What to remind you of"]
+    #[rest]
+    text: String,
+) -> Result<(), Error> {
+    let length = match duration::parse(&duration) {
+        Some(length) if length <= MAX_REMINDER => length,
+        _ => {
+            ctx.send(|m| {
+                m.content(":x: Please give a duration like `10m`, `2h` or `1w`, up to a year.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let db = &ctx.data().db;
+    if db.user_reminders(ctx.author().id).await?.len() >= MAX_REMINDERS_PER_USER {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: You can't have more than {} reminders at once.",
+                MAX_REMINDERS_PER_USER
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let due_at = serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64;
+    let id = db
+        .add_reminder(ctx.author().id, ctx.channel_id(), due_at, &text)
+        .await?;
+
+    ctx.send(|m| {
+        m.content(format!(
+            ":alarm_clock: I'll remind you <t:{}:R> (reminder #{})",
+            due_at, id
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Lists your pending reminders
+///
+/// Usage: `/remind list`
+/// Example: `/remind list`
+#[poise::command(slash_command, prefix_command)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let reminders = ctx.data().db.user_reminders(ctx.author().id).await?;
+
+    let response = if reminders.is_empty() {
+        "You have no reminders.".to_string()
+    } else {
+        reminders
+            .iter()
+            .map(|r| {
+                let text: String = r.text.chars().take(100).collect();
+                format!("**#{}** <t:{}:R>: {}", r.id, r.due_at, text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    ctx.send(|m| {
+        m.content(response)
+            .allowed_mentions(|a| Mentions::Nothing.apply(a))
+            .ephemeral(true)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Cancels one of your reminders
+///
+/// Usage: `/remind cancel <id>`
+/// Example: `/remind cancel 42`
+#[poise::command(slash_command, prefix_command)]
+async fn cancel(
+    ctx: Context<'_>,
+    #[description = "Reminder number from /remind list"] id: i64,
+) -> Result<(), Error> {
+    let response = if ctx
+        .data()
+        .db
+        .delete_reminder(id, Some(ctx.author().id))
+        .await?
+    {
+        format!(":white_check_mark: Cancelled reminder #{}", id)
+    } else {
+        format!(":x: You have no reminder #{}.", id)
+    };
+
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Registers or removes the slash commands
+#[poise::command(prefix_command, hide_in_help)]
+async fn register(ctx: Context<'_>) -> Result<(), Error> {
+    poise::builtins::register_application_commands_buttons(ctx).await?;
+    Ok(())
+}
+
+/// Shows the map, player count and player list of a Source engine server
+///
+/// Usage: `/gameserver <address>`
+/// Example: `/gameserver play.example.com:27015`
+#[poise::command(slash_command, user_cooldown = 10)]
+async fn gameserver(
+    ctx: Context<'_>,
+    #[description = "Server address (host or host:port)"] address: String,
+) -> Result<(), Error> {
+    // Queries can take a few seconds if the server is slow or offline
+    ctx.defer().await?;
+
+    let addr = match a2s::resolve(&address).await {
+        Ok(addr) => addr,
+        Err(e) => {
+            ctx.say(format!(":x: Could not resolve `{}`: {}", address, e))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let info = match a2s::query_info(addr).await {
+        Ok(info) => info,
+        Err(e) => {
+            ctx.say(format!(":x: Could not query `{}`: {}", addr, e))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Some servers disable player queries, so don't fail the whole command over it
+    let players = match a2s::query_players(addr).await {
+        Ok(players) if players.is_empty() => "Nobody is online".to_string(),
+        Ok(players) => {
+            let mut list = String::new();
+
+            // Embed fields are capped at 1024 characters
+            for p in &players {
+                let minutes = p.duration.as_secs() / 60;
+                let line = format!("{} ({} pts, {}m)\n", p.name, p.score, minutes);
+                if list.len() + line.len() > 1000 {
+                    list.push_str("...");
+                    break;
+                }
+                list.push_str(&line);
+            }
+
+            list
+        }
+        Err(_) => "Player list unavailable".to_string(),
+    };
+
+    let name = match embed_text(&info.name, EMBED_TITLE_LIMIT) {
+        Some(name) => name,
+        None => addr.to_string(),
+    };
+    let unknown = || "Unknown".to_string();
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(name)
+                .description(format!("`{}`", addr))
+                .field(
+                    "Game",
+                    embed_text(&info.game, EMBED_FIELD_LIMIT).unwrap_or_else(unknown),
+                    true,
+                )
+                .field(
+                    "Map",
+                    embed_text(&info.map, EMBED_FIELD_LIMIT).unwrap_or_else(unknown),
+                    true,
+                )
+                .field(
+                    "Players",
+                    format!("{}/{} ({} bots)", info.players, info.max_players, info.bots),
+                    true,
+                )
+                .field("VAC", if info.vac { "Secured" } else { "Off" }, true)
+                .field("Online", players, false)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+// Text from game servers for an embed, which Discord rejects when it's blank or too long
+fn embed_text(text: &str, limit: usize) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= limit {
+        return Some(text.to_string());
+    }
+
+    let mut text: String = text.chars().take(limit - 3).collect();
+    text.push_str("...");
+    Some(text)
+}
+
+/// Shows a user's account, membership, roles and boost status
+///
+/// Usage: `/userinfo [user]`
+/// Example: `/userinfo @user`
+#[poise::command(slash_command)]
+async fn userinfo(
+    ctx: Context<'_>,
+    #[description = "User to show, yourself by default"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let guild = ctx.guild();
+    let member = match ctx.guild_id() {
+        Some(guild) => guild.member(ctx.discord(), user.id).await.ok(),
+        None => None,
+    };
+
+    // Highest role first, @everyone left out
+    let mut roles: Vec<&serenity::Role> = match (&guild, &member) {
+        (Some(guild), Some(member)) => member
+            .roles
+            .iter()
+            .filter_map(|r| guild.roles.get(r))
+            .collect(),
+        _ => Vec::new(),
+    };
+    roles.sort_by_key(|r| std::cmp::Reverse(r.position));
+    let colour = roles.iter().find(|r| r.colour.0 != 0).map(|r| r.colour);
+
+    // Embed fields are capped at 1024 characters
+    let mut role_list = String::new();
+    for role in &roles {
+        let mention = format!("<@&{}> ", role.id.0);
+        if role_list.len() + mention.len() > 1000 {
+            role_list.push_str("...");
+            break;
+        }
+        role_list.push_str(&mention);
+    }
+    if role_list.is_empty() {
+        role_list = "None".to_string();
+    }
+
+    let created = user.created_at().unix_timestamp();
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.author(|a| a.name(user.tag()).icon_url(user.face()))
+                .thumbnail(
+                    member
+                        .as_ref()
+                        .map(|m| m.face())
+                        .unwrap_or_else(|| user.face()),
+                )
+                .field("ID", user.id.0, true)
+                .field("Bot", if user.bot { "Yes" } else { "No" }, true)
+                .field(
+                    "Account created",
+                    format!("<t:{}:D> (<t:{}:R>)", created, created),
+                    false,
+                );
+            if let Some(colour) = colour {
+                e.colour(colour);
+            }
+            if let Some(member) = &member {
+                if let Some(nick) = &member.nick {
+                    e.field("Nickname", nick, true);
+                }
+                if let Some(joined) = member.joined_at {
+                    let joined = joined.unix_timestamp();
+                    e.field(
+                        "Joined server",
+                        format!("<t:{}:D> (<t:{}:R>)", joined, joined),
+                        false,
+                    );
+                }
+                let boosting = match member.premium_since {
+                    Some(since) => format!("Since <t:{}:D>", since.unix_timestamp()),
+                    None => "Not boosting".to_string(),
+                };
+                e.field("Boost", boosting, true).field(
+                    format!("Roles ({})", roles.len()),
+                    role_list,
+                    false,
+                );
+            }
+            e
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows this server's members, channels, roles and boosts
+///
+/// Usage: `/serverinfo`
+/// Example: `/serverinfo`
+#[poise::command(slash_command, guild_only)]
+async fn serverinfo(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild().ok_or("Server isn't cached")?;
+
+    let (mut text, mut voice, mut categories) = (0, 0, 0);
+    for channel in guild.channels.values() {
+        match channel {
+            serenity::Channel::Guild(c) if c.kind == serenity::ChannelType::Voice => voice += 1,
+            serenity::Channel::Guild(c) if c.kind == serenity::ChannelType::Stage => voice += 1,
+            serenity::Channel::Guild(_) => text += 1,
+            serenity::Channel::Category(_) => categories += 1,
+            _ => {}
+        }
+    }
+
+    // Only shown when every member is cached, otherwise the counts would be off
+    let bots = (guild.members.len() as u64 == guild.member_count)
+        .then(|| guild.members.values().filter(|m| m.user.bot).count());
+    let members = match bots {
+        Some(bots) => format!(
+            "{} ({} humans, {} bots)",
+            guild.member_count,
+            guild.member_count - bots as u64,
+            bots
+        ),
+        None => guild.member_count.to_string(),
+    };
+
+    let tier = match guild.premium_tier {
+        serenity::PremiumTier::Tier1 => "Level 1",
+        serenity::PremiumTier::Tier2 => "Level 2",
+        serenity::PremiumTier::Tier3 => "Level 3",
+        _ => "No level",
+    };
+    let animated = guild.emojis.values().filter(|e| e.animated).count();
+    let created = guild.id.created_at().unix_timestamp();
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(&guild.name)
+                .field("Owner", format!("<@{}>", guild.owner_id.0), true)
+                .field("ID", guild.id.0, true)
+                .field(
+                    "Created",
+                    format!("<t:{}:D> (<t:{}:R>)", created, created),
+                    false,
+                )
+                .field("Members", members, true)
+                .field(
+                    "Channels",
+                    format!("{} text, {} voice, {} categories", text, voice, categories),
+                    true,
+                )
+                .field("Roles", guild.roles.len().saturating_sub(1), true)
+                .field(
+                    "Emojis",
+                    format!(
+                        "{} static, {} animated",
+                        guild.emojis.len() - animated,
+                        animated
+                    ),
+                    true,
+                )
+                .field(
+                    "Boosts",
+                    format!("{}, {} boosts", tier, guild.premium_subscription_count),
+                    true,
+                );
+            if let Some(description) = &guild.description {
+                e.description(description);
+            }
+            if let Some(icon) = guild.icon_url() {
+                e.thumbnail(icon);
+            }
+            if let Some(banner) = guild.banner_url() {
+                e.image(banner);
+            }
+            e
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Lists the members boosting this server, longest first
+///
+/// Usage: `/boosters`
+/// Example: `/boosters`
+#[poise::command(slash_command, guild_only)]
+async fn boosters(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild().ok_or("Server isn't cached")?;
+
+    let mut boosters: Vec<_> = guild
+        .members
+        .values()
+        .filter_map(|m| Some((m.premium_since?.unix_timestamp(), m.user.id)))
+        .collect();
+    if boosters.is_empty() {
+        ctx.send(|m| {
+            m.content("Nobody is boosting this server right now.")
+                .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+    boosters.sort_unstable();
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let mut list = String::new();
+    for (since, user) in &boosters {
+        let boosting = std::time::Duration::from_secs((now - since).max(0) as u64);
+        let line = format!(
+            "<@{}> for {}, since <t:{}:D>\n",
+            user.0,
+            duration::format(boosting),
+            since
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Boosters of {}", guild.name))
+                .description(list)
+                .footer(|f| {
+                    f.text(format!(
+                        "{} boosters, {} boosts",
+                        boosters.len(),
+                        guild.premium_subscription_count
+                    ))
+                })
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows this server's tier and how much of its daily quotas it used
+///
+/// Usage: `/stats`
+/// Example: `/stats`
+#[poise::command(slash_command, guild_only)]
+async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let tier = quotas::tier(ctx.data(), guild, ctx.author().id).await?;
+    let day = quotas::day(serenity::Timestamp::now().unix_timestamp());
+    let usage = ctx.data().db.quota_usage(guild, day).await?;
+
+    let quotas = Quota::ALL
+        .iter()
+        .map(|&quota| {
+            let used = usage
+                .iter()
+                .find(|(name, _)| name == quota.name())
+                .map_or(0, |(_, used)| *used);
+            format!("{}: {}/{}", quota.uses(), used, tier.limit(quota))
+        })
+        .collect::<Vec<_>>();
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Usage today")
+                .field("Tier", tier.name(), false)
+                .field("Quotas", quotas.join("\n"), false)
+                .field("Resets", format!("<t:{}:R>", quotas::resets_at(day)), false)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Premium subscriptions of the bot
+///
+/// Usage: `/premium status`
+/// Example: `/premium status`
+#[poise::command(slash_command, guild_only, subcommands("premium_status"))]
+async fn premium(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows what this server and you have unlocked
+///
+/// Usage: `/premium status`
+/// Example: `/premium status`
+#[poise::command(slash_command, guild_only, rename = "status")]
+async fn premium_status(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let data = ctx.data();
+    let user = ctx.author().id;
+
+    let entitlements = match &data.premium {
+        Some(premium) => premium.active(&data.db, Some(guild), Some(user)).await?,
+        None => Vec::new(),
+    };
+    let subscription = |entitlement: Option<&Entitlement>| match entitlement {
+        Some(Entitlement {
+            ends_at: Some(ends_at),
+            ..
+        }) => format!("Subscribed until <t:{}:D>", ends_at),
+        Some(_) => "Subscribed".to_string(),
+        None => "Not subscribed".to_string(),
+    };
+    let server = entitlements
+        .iter()
+        .find(|e| e.guild_id == Some(guild.0 as i64));
+    let own = entitlements
+        .iter()
+        .find(|e| e.user_id == Some(user.0 as i64));
+
+    let server = match server {
+        None if data.db.guild_tier(guild).await? == Tier::Premium => {
+            "Premium, given by the bot owner".to_string()
+        }
+        server => subscription(server),
+    };
+    let voted = match votes::perks_until(&data.db, user).await? {
+        Some(until) => format!("Vote perks until <t:{}:t>", until),
+        None => "No vote perks, `/vote` to get them".to_string(),
+    };
+    let tier = quotas::tier(data, guild, user).await?;
+    let quotas = Quota::ALL
+        .iter()
+        .map(|&quota| format!("{}: {} a day", quota.uses(), tier.limit(quota)))
+        .collect::<Vec<_>>();
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("Premium: {}", tier.name()))
+                .field("This server", server, true)
+                .field("You", format!("{}\n{}", subscription(own), voted), true)
+                .field("Quotas", quotas.join("\n"), false);
+            if data.premium.is_none() {
+                e.footer(|f| f.text("Subscriptions aren't set up for this bot"));
+            }
+            e
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows where to vote for the bot and what voting gets you
+///
+/// Usage: `/vote`
+/// Example: `/vote`
+#[poise::command(slash_command, prefix_command)]
+async fn vote(ctx: Context<'_>) -> Result<(), Error> {
+    let bot = ctx.framework().bot_id;
+    let perks = match votes::perks_until(&ctx.data().db, ctx.author().id).await? {
+        Some(until) => format!("You voted already, your perks last until <t:{}:t>.", until),
+        None => "You haven't voted recently.".to_string(),
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Vote for the bot")
+                .url(format!("https://top.gg/bot/{}/vote", bot.0))
+                .description(format!(
+                    "Voting on top.gg gets you {} more coins from /daily and premium quotas for \
+                    {} hours.\n\n{}",
+                    votes::DAILY_BONUS,
+                    votes::PERK_DURATION.as_secs() / 3600,
+                    perks
+                ))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Shows how fast the bot reaches Discord
+///
+/// Usage: `/ping`
+/// Example: `/ping`
+#[poise::command(slash_command, prefix_command)]
+async fn ping(ctx: Context<'_>) -> Result<(), Error> {
+    let reply = ctx.say(":ping_pong: Pinging...").await?;
+
+    // Editing the reply is a REST round trip, same as what every command does
+    let start = Instant::now();
+    reply
+        .edit(ctx, |m| m.content(":ping_pong: Measuring..."))
+        .await?;
+    let rest = start.elapsed();
+
+    // Heartbeat latency of the shard this guild or DM is on
+    let shard_manager = ctx.framework().shard_manager();
+    let heartbeat = {
+        let manager = shard_manager.lock().await;
+        let runners = manager.runners.lock().await;
+        runners
+            .get(&serenity::ShardId(ctx.discord().shard_id))
+            .and_then(|runner| runner.latency)
+    };
+    let heartbeat = match heartbeat {
+        Some(latency) => format!("{}ms", latency.as_millis()),
+        // Right after connecting no heartbeat has been acknowledged yet
+        None => "Not measured yet".to_string(),
+    };
+
+    reply
+        .edit(ctx, |m| {
+            m.content("").embed(|e| {
+                e.title(":ping_pong: Pong!")
+                    .field("Gateway heartbeat", heartbeat, true)
+                    .field("REST round trip", format!("{}ms", rest.as_millis()), true)
+                    .footer(|f| f.text(format!("Shard {}", ctx.discord().shard_id)))
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+command_list!["Utility": help, ping, stats, premium, vote, age, userinfo, serverinfo, boosters, remind, register, gameserver];
@@ -0,0 +1,216 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    tempvoice::{self, VoiceRegion},
+    Context, Error,
+};
+
+// Discord allows user limits up to this, 0 means no limit
+const MAX_USER_LIMIT: u64 = 99;
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Change the temporary voice channel you own
+///
+/// Usage: `/voice limit <n>`, `/voice rename <name>`, `/voice region <region>` or `/voice claim`
+/// Example: `/voice limit 5`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("limit", "rename", "region", "claim")
+)]
+async fn voice(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets how many members can join your channel, 0 for no limit
+///
+/// Usage: `/voice limit <n>`
+/// Example: `/voice limit 4`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS"
+)]
+async fn limit(
+    ctx: Context<'_>,
+    #[description = "Most members in the channel, 0 for no limit"]
+    #[max = 99]
+    limit: u64,
+) -> Result<(), Error> {
+    let channel = match owned_channel(ctx).await? {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+
+    let limit = limit.min(MAX_USER_LIMIT);
+    channel.edit(ctx.discord(), |c| c.user_limit(limit)).await?;
+
+    let content = if limit == 0 {
+        ":white_check_mark: Removed the user limit".to_string()
+    } else {
+        format!(
+            ":white_check_mark: Limited the channel to {} members",
+            limit
+        )
+    };
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Renames your channel
+///
+/// Usage: `/voice rename <name>`
+/// Example: `/voice rename Movie night`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS"
+)]
+async fn rename(
+    ctx: Context<'_>,
+    #[description = "New name of the channel"] name: String,
+) -> Result<(), Error> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Channel names can be up to {} characters.",
+                MAX_NAME_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let channel = match owned_channel(ctx).await? {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+
+    channel.edit(ctx.discord(), |c| c.name(name)).await?;
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Renamed the channel to **{}**",
+            name
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Picks the voice server region of your channel
+///
+/// Usage: `/voice region <region>`
+/// Example: `/voice region rotterdam`
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_bot_permissions = "MANAGE_CHANNELS"
+)]
+async fn region(
+    ctx: Context<'_>,
+    #[description = "Region to use, automatic lets Discord pick"] region: VoiceRegion,
+) -> Result<(), Error> {
+    let channel = match owned_channel(ctx).await? {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+
+    channel
+        .edit(ctx.discord(), |c| c.voice_region(region.id()))
+        .await?;
+    ctx.send(|m| {
+        m.content(format!(
+            ":white_check_mark: Set the region to `{}`",
+            region.name()
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Takes over the channel you're in after its owner left
+///
+/// Usage: `/voice claim`
+/// Example: `/voice claim`
+#[poise::command(slash_command, guild_only)]
+async fn claim(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let db = &ctx.data().db;
+    let author = ctx.author().id;
+
+    let channel = current_channel(ctx);
+    let owner = match channel {
+        Some(channel) => db.temp_voice_owner(channel).await?,
+        None => None,
+    };
+    let content = match (channel, owner) {
+        (Some(channel), Some(owner)) if owner == author => {
+            format!(":x: You own <#{}> already.", channel.0)
+        }
+        (Some(channel), Some(owner)) if tempvoice::is_in(ctx.discord(), guild, channel, owner) => {
+            ":x: The owner is still in the channel.".to_string()
+        }
+        (Some(channel), Some(_)) => {
+            db.set_temp_voice_owner(channel, author).await?;
+            format!(":white_check_mark: You own <#{}> now", channel.0)
+        }
+        _ => ":x: Join a temporary voice channel first.".to_string(),
+    };
+
+    ctx.send(|m| m.content(content).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+// The voice channel the author is in, from the cache
+fn current_channel(ctx: Context<'_>) -> Option<serenity::ChannelId> {
+    ctx.guild()?.voice_states.get(&ctx.author().id)?.channel_id
+}
+
+// The temporary channel the author is in if they own it or may manage it anyway, replies
+// itself otherwise
+async fn owned_channel(ctx: Context<'_>) -> Result<Option<serenity::ChannelId>, Error> {
+    let channel = current_channel(ctx);
+    let owner = match channel {
+        Some(channel) => ctx.data().db.temp_voice_owner(channel).await?,
+        None => None,
+    };
+
+    let error = match (channel, owner) {
+        (Some(channel), Some(owner)) => {
+            if owner == ctx.author().id || can_manage(ctx, channel).await {
+                return Ok(Some(channel));
+            }
+            ":x: Only the owner of this channel can change it, use `/voice claim` once they left."
+        }
+        _ => ":x: Join a temporary voice channel first.",
+    };
+    ctx.send(|m| m.content(error).ephemeral(true)).await?;
+
+    Ok(None)
+}
+
+// Members who can manage the channel anyway don't need to own it
+async fn can_manage(ctx: Context<'_>, channel: serenity::ChannelId) -> bool {
+    let (guild, channel) = match (ctx.guild(), ctx.discord().cache.guild_channel(channel)) {
+        (Some(guild), Some(channel)) => (guild, channel),
+        _ => return false,
+    };
+    let member = match ctx.author_member().await {
+        Some(member) => member,
+        None => return false,
+    };
+
+    guild
+        .user_permissions_in(&channel, &member)
+        .is_ok_and(|p| p.manage_channels())
+}
+
+command_list!["Channels": voice];
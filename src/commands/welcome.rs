@@ -0,0 +1,161 @@
+use poise::serenity_prelude as serenity;
+
+use crate::{mentions::Mentions, welcome, Context, Error};
+
+// Leaves room for long usernames and server names once placeholders are filled in
+const MAX_TEMPLATE_LENGTH: usize = 1500;
+
+/// Welcome joining members and say goodbye to leaving ones
+///
+/// Usage: `/welcome channel [channel]`, `/welcome message [template]`, `/welcome goodbye [template]` or `/welcome test`
+/// Example: `/welcome message Hi {mention}, welcome to {server}!`
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("channel", "message", "goodbye", "test"),
+    required_permissions = "MANAGE_GUILD",
+    default_member_permissions = "MANAGE_GUILD"
+)]
+async fn welcome(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets the channel members are welcomed in, turns welcome messages off if empty
+///
+/// Usage: `/welcome channel [channel]`
+/// Example: `/welcome channel #welcome`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn channel(
+    ctx: Context<'_>,
+    #[description = "Channel for welcome and goodbye messages"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let channel = channel.map(|c| c.id);
+
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| c.welcome_channel = channel)
+        .await?;
+
+    let response = match channel {
+        Some(channel) => format!(
+            ":white_check_mark: Members are now welcomed in <#{}>",
+            channel.0
+        ),
+        None => ":white_check_mark: Welcome and goodbye messages are off".to_string(),
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+/// Changes the welcome message, resets it if empty
+///
+/// Usage: `/welcome message [template]`
+/// Example: `/welcome message Hi {mention}, welcome to {server}!`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn message(
+    ctx: Context<'_>,
+    #[description = "Can use {user}, {mention}, {server} and {count}"] template: Option<String>,
+) -> Result<(), Error> {
+    set_template(ctx, template, false).await
+}
+
+/// Changes the goodbye message, resets it if empty
+///
+/// Usage: `/welcome goodbye [template]`
+/// Example: `/welcome goodbye {user} left us, we're down to {count}.`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn goodbye(
+    ctx: Context<'_>,
+    #[description = "Can use {user}, {mention}, {server} and {count}"] template: Option<String>,
+) -> Result<(), Error> {
+    set_template(ctx, template, true).await
+}
+
+/// Shows what the welcome and goodbye messages look like for you
+///
+/// Usage: `/welcome test`
+/// Example: `/welcome test`
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn test(ctx: Context<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let config = ctx.data().guild_configs.get(guild).await?;
+
+    let welcome = welcome::render(config.welcome_message(), ctx.discord(), guild, ctx.author());
+    let goodbye = welcome::render(config.goodbye_message(), ctx.discord(), guild, ctx.author());
+    let channel = match config.welcome_channel {
+        Some(channel) => format!("Posted in <#{}>", channel.0),
+        None => "Off, set a channel with /welcome channel".to_string(),
+    };
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Welcome messages")
+                .field("Welcome", welcome, false)
+                .field("Goodbye", goodbye, false)
+                .footer(|f| f.text(channel))
+        })
+        .allowed_mentions(|a| Mentions::Nothing.apply(a))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn set_template(
+    ctx: Context<'_>,
+    template: Option<String>,
+    goodbye: bool,
+) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let template = template
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    if template
+        .as_ref()
+        .is_some_and(|t| t.chars().count() > MAX_TEMPLATE_LENGTH)
+    {
+        ctx.send(|m| {
+            m.content(format!(
+                ":x: Messages can be up to {} characters long.",
+                MAX_TEMPLATE_LENGTH
+            ))
+            .ephemeral(true)
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let reset = template.is_none();
+    ctx.data()
+        .guild_configs
+        .update(guild, |c| {
+            if goodbye {
+                c.goodbye_message = template;
+            } else {
+                c.welcome_message = template;
+            }
+        })
+        .await?;
+
+    let kind = if goodbye { "goodbye" } else { "welcome" };
+    let response = if reset {
+        format!(":white_check_mark: Reset the {} message", kind)
+    } else {
+        format!(
+            ":white_check_mark: Changed the {} message, see how it looks with /welcome test. Placeholders are {}.",
+            kind,
+            welcome::PLACEHOLDERS
+        )
+    };
+    ctx.send(|m| m.content(response).ephemeral(true)).await?;
+
+    Ok(())
+}
+
+command_list!["Settings": welcome];
@@ -0,0 +1,575 @@
+// Per-guild configuration
+// Settings are persisted in the database and cached in memory, since some of them
+// (like the prefix) are looked up for every single message.
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Db, toxicity::Category, Data, Error};
+
+/// Prefix used when a guild hasn't set its own
+pub const DEFAULT_PREFIX: &str = "~";
+
+/// Length of the automatic timeout when a member reaches the warning threshold
+pub const DEFAULT_WARN_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Default number of differing bits at which two image hashes still count as the same image
+pub const DEFAULT_IMAGE_HASH_TOLERANCE: u32 = 6;
+
+/// Stars a message needs for the starboard when a guild hasn't set its own threshold
+pub const DEFAULT_STARBOARD_THRESHOLD: u32 = 3;
+
+/// Default NSFW score in percent from which images are flagged
+pub const DEFAULT_NSFW_THRESHOLD: u32 = 80;
+
+/// Share of bets the house keeps in percent when a guild hasn't set its own
+pub const DEFAULT_HOUSE_EDGE: u32 = 5;
+
+/// Smallest bet in coins when a guild hasn't set its own
+pub const DEFAULT_MIN_BET: i64 = 10;
+
+/// Biggest bet in coins when a guild hasn't set its own
+pub const DEFAULT_MAX_BET: i64 = 1000;
+
+/// How long the roles of members that left are kept when a guild hasn't set its own
+pub const DEFAULT_ROLE_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How often a partner ad is posted when a guild hasn't set its own
+pub const DEFAULT_PARTNER_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Thread summaries a guild can have a day when it hasn't set its own
+pub const DEFAULT_SUMMARY_LIMIT: u32 = 20;
+
+/// Reaction members vote to delete messages with when a guild hasn't set its own
+pub const DEFAULT_VOTE_DELETE_EMOJI: &str = "🗑️";
+
+/// Chance in percent that /rob succeeds when a guild hasn't set its own
+pub const DEFAULT_ROB_CHANCE: u32 = 40;
+
+/// Welcome message used when a guild hasn't written its own
+pub const DEFAULT_WELCOME_MESSAGE: &str =
+    "Welcome to **{server}**, {mention}! You're member #{count}.";
+
+/// Goodbye message used when a guild hasn't written its own
+pub const DEFAULT_GOODBYE_MESSAGE: &str = "**{user}** left the server.";
+
+/// Features that can be turned on or off per guild
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Feature {
+    #[name = "h"]
+    HReply,
+    #[name = "translate"]
+    Translate,
+    #[name = "nsfw_scan"]
+    NsfwScan,
+    #[name = "levels"]
+    Levels,
+    #[name = "clean_links"]
+    CleanLinks,
+    #[name = "repost_links"]
+    RepostLinks,
+    #[name = "gambling"]
+    Gambling,
+    #[name = "restore_roles"]
+    RestoreRoles,
+    #[name = "message_archive"]
+    MessageArchive,
+    #[name = "summaries"]
+    Summaries,
+    #[name = "privacy_mode"]
+    PrivacyMode,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::HReply,
+        Feature::Translate,
+        Feature::NsfwScan,
+        Feature::Levels,
+        Feature::CleanLinks,
+        Feature::RepostLinks,
+        Feature::Gambling,
+        Feature::RestoreRoles,
+        Feature::MessageArchive,
+        Feature::Summaries,
+        Feature::PrivacyMode,
+    ];
+
+    fn enabled_by_default(self) -> bool {
+        match self {
+            Feature::HReply => true,
+            // Posts other people's messages back, so servers have to opt in
+            Feature::Translate => false,
+            // Sends every image to an external API
+            Feature::NsfwScan => false,
+            // Level up announcements are noisy
+            Feature::Levels => false,
+            Feature::CleanLinks => false,
+            // Deletes messages, replying with the cleaned links is the gentler default
+            Feature::RepostLinks => false,
+            // Servers that don't want it opt out
+            Feature::Gambling => true,
+            // Members may have left to get rid of a role, servers decide whether it sticks
+            Feature::RestoreRoles => false,
+            // Stores everything members say, servers have to opt in
+            Feature::MessageArchive => false,
+            // Sends whole threads to an external API
+            Feature::Summaries => false,
+            // Search only finds whole words and digests lose their summary
+            Feature::PrivacyMode => false,
+        }
+    }
+}
+
+/// Special rules a channel can have
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ChannelMode {
+    /// Only emoji, stickers and GIFs, everything else gets deleted
+    #[name = "emoji_only"]
+    EmojiOnly,
+    /// Emoji only, plus a weekly post with the most used reactions
+    #[name = "reaction_wall"]
+    ReactionWall,
+    /// The bot doesn't act on messages at all, e.g. for channels other bots run
+    #[name = "ignored"]
+    Ignored,
+    /// Questions are answered with the guild's FAQs, in the channel and its threads
+    #[name = "help"]
+    Help,
+}
+
+/// Lists of roles with a special meaning
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum RoleList {
+    /// Members with these roles (e.g. artists) skip the NSFW scan
+    #[name = "nsfw_exempt"]
+    NsfwExempt,
+    /// Roles every new member gets
+    #[name = "autorole"]
+    AutoRole,
+    /// Roles new members can pick during onboarding
+    #[name = "onboarding"]
+    Onboarding,
+    /// Members with these roles (e.g. moderators) skip the anti-spam rules
+    #[name = "automod_exempt"]
+    AutomodExempt,
+    /// Roles (e.g. staff roles) never given back to members that rejoin
+    #[name = "not_restored"]
+    NotRestored,
+    /// Members with these roles can vote to delete messages, everyone can if it's empty
+    #[name = "vote_delete"]
+    VoteDelete,
+}
+
+impl RoleList {
+    pub const ALL: &'static [RoleList] = &[
+        RoleList::NsfwExempt,
+        RoleList::AutoRole,
+        RoleList::Onboarding,
+        RoleList::AutomodExempt,
+        RoleList::NotRestored,
+        RoleList::VoteDelete,
+    ];
+}
+
+/// What happens to members that look like an alt account
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum AltAction {
+    #[name = "kick"]
+    Kick,
+    #[name = "ban"]
+    Ban,
+    #[name = "timeout"]
+    Timeout,
+}
+
+/// What happens to messages members voted to delete
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum VoteAction {
+    /// Staff decide in the review channel
+    #[name = "review"]
+    Review,
+    #[name = "delete"]
+    Delete,
+}
+
+#[derive(Clone, Default)]
+pub struct GuildConfig {
+    pub prefix: Option<String>,
+    pub log_channel: Option<serenity::ChannelId>,
+    /// Where flagged content is posted for moderators to look at
+    pub review_channel: Option<serenity::ChannelId>,
+    /// Where edited and deleted messages are logged
+    pub message_log_channel: Option<serenity::ChannelId>,
+    /// Where level ups are announced, the channel of the message if unset
+    pub level_channel: Option<serenity::ChannelId>,
+    /// Where messages with enough stars are reposted
+    pub starboard_channel: Option<serenity::ChannelId>,
+    pub starboard_threshold: Option<u32>,
+    /// Where joining and leaving members are announced
+    pub welcome_channel: Option<serenity::ChannelId>,
+    pub welcome_message: Option<String>,
+    pub goodbye_message: Option<String>,
+    /// Where onboarding falls back to when DMs are closed, and where introductions go
+    pub onboarding_channel: Option<serenity::ChannelId>,
+    /// Joining this voice channel creates a temporary one owned by the member
+    pub voice_hub_channel: Option<serenity::ChannelId>,
+    /// Role pinged by /staff
+    pub staff_role: Option<serenity::RoleId>,
+    /// Members get timed out after every this many warnings
+    pub warn_threshold: Option<u32>,
+    /// How long the automatic warning timeout lasts
+    pub warn_timeout: Option<Duration>,
+    /// Alt score in percent at which `alt_action` is taken
+    pub alt_threshold: Option<u32>,
+    pub alt_action: Option<AltAction>,
+    /// Bits an image hash may differ from a blocked one and still match
+    pub image_hash_tolerance: Option<u32>,
+    /// NSFW score in percent from which images are flagged
+    pub nsfw_threshold: Option<u32>,
+    /// Share of bets the house keeps on average, in percent
+    pub house_edge: Option<u32>,
+    pub min_bet: Option<i64>,
+    pub max_bet: Option<i64>,
+    /// Coins a member may lose gambling in a day
+    pub gambling_loss_limit: Option<i64>,
+    /// Chance in percent that /rob succeeds, 0 turns it off
+    pub rob_chance: Option<u32>,
+    /// How long the roles of members that left are kept
+    pub role_retention: Option<Duration>,
+    /// Where activity of watched members is summarized
+    pub watch_channel: Option<serenity::ChannelId>,
+    /// Where the on-call rotation is posted every week
+    pub oncall_channel: Option<serenity::ChannelId>,
+    /// Where members are thanked for boosting
+    pub boost_channel: Option<serenity::ChannelId>,
+    /// Where partner ads are posted
+    pub partner_channel: Option<serenity::ChannelId>,
+    /// How often a partner ad is posted
+    pub partner_interval: Option<Duration>,
+    /// Votes it takes to delete a message, voting is off without one
+    pub vote_delete_threshold: Option<u32>,
+    pub vote_delete_action: Option<VoteAction>,
+    /// Reaction members vote with
+    pub vote_delete_emoji: Option<String>,
+    /// Thread summaries the guild can have a day
+    pub summary_limit: Option<u32>,
+    /// Features that differ from their default
+    pub features: HashMap<Feature, bool>,
+    pub channel_modes: HashMap<serenity::ChannelId, ChannelMode>,
+    pub role_lists: HashMap<RoleList, HashSet<serenity::RoleId>>,
+    /// Domains whose links are left alone by `clean_links`, subdomains included
+    pub link_allowlist: HashSet<String>,
+    /// Channels messages are mirrored into, translated into the language given with them
+    pub translation_links: HashMap<serenity::ChannelId, Vec<(serenity::ChannelId, String)>>,
+    /// Channels where images without a description get a reminder to add one
+    pub alt_text_channels: HashSet<serenity::ChannelId>,
+    /// Toxicity categories messages are scored for, with the score in percent they're flagged at
+    pub toxicity_thresholds: HashMap<Category, u32>,
+}
+
+impl GuildConfig {
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or(DEFAULT_PREFIX)
+    }
+
+    pub fn warn_timeout(&self) -> Duration {
+        self.warn_timeout.unwrap_or(DEFAULT_WARN_TIMEOUT)
+    }
+
+    pub fn image_hash_tolerance(&self) -> u32 {
+        self.image_hash_tolerance
+            .unwrap_or(DEFAULT_IMAGE_HASH_TOLERANCE)
+    }
+
+    /// Falls back to the log channel so flagged content isn't lost
+    pub fn review_channel(&self) -> Option<serenity::ChannelId> {
+        self.review_channel.or(self.log_channel)
+    }
+
+    pub fn watch_channel(&self) -> Option<serenity::ChannelId> {
+        self.watch_channel.or(self.log_channel)
+    }
+
+    pub fn oncall_channel(&self) -> Option<serenity::ChannelId> {
+        self.oncall_channel.or(self.log_channel)
+    }
+
+    pub fn starboard_threshold(&self) -> u32 {
+        self.starboard_threshold
+            .unwrap_or(DEFAULT_STARBOARD_THRESHOLD)
+    }
+
+    pub fn welcome_message(&self) -> &str {
+        self.welcome_message
+            .as_deref()
+            .unwrap_or(DEFAULT_WELCOME_MESSAGE)
+    }
+
+    pub fn goodbye_message(&self) -> &str {
+        self.goodbye_message
+            .as_deref()
+            .unwrap_or(DEFAULT_GOODBYE_MESSAGE)
+    }
+
+    pub fn nsfw_threshold(&self) -> u32 {
+        self.nsfw_threshold.unwrap_or(DEFAULT_NSFW_THRESHOLD)
+    }
+
+    pub fn house_edge(&self) -> u32 {
+        self.house_edge.unwrap_or(DEFAULT_HOUSE_EDGE)
+    }
+
+    pub fn min_bet(&self) -> i64 {
+        self.min_bet.unwrap_or(DEFAULT_MIN_BET)
+    }
+
+    pub fn max_bet(&self) -> i64 {
+        self.max_bet.unwrap_or(DEFAULT_MAX_BET)
+    }
+
+    pub fn rob_chance(&self) -> u32 {
+        self.rob_chance.unwrap_or(DEFAULT_ROB_CHANCE)
+    }
+
+    pub fn role_retention(&self) -> Duration {
+        self.role_retention.unwrap_or(DEFAULT_ROLE_RETENTION)
+    }
+
+    pub fn partner_interval(&self) -> Duration {
+        self.partner_interval.unwrap_or(DEFAULT_PARTNER_INTERVAL)
+    }
+
+    pub fn vote_delete_action(&self) -> VoteAction {
+        self.vote_delete_action.unwrap_or(VoteAction::Review)
+    }
+
+    pub fn vote_delete_emoji(&self) -> &str {
+        self.vote_delete_emoji
+            .as_deref()
+            .unwrap_or(DEFAULT_VOTE_DELETE_EMOJI)
+    }
+
+    pub fn summary_limit(&self) -> u32 {
+        self.summary_limit.unwrap_or(DEFAULT_SUMMARY_LIMIT)
+    }
+
+    /// Whether any of `roles` is in `list`
+    pub fn has_role_in(&self, list: RoleList, roles: &[serenity::RoleId]) -> bool {
+        self.role_lists
+            .get(&list)
+            .is_some_and(|list| roles.iter().any(|r| list.contains(r)))
+    }
+
+    /// Whether `host` is an allowlisted domain or one of its subdomains
+    pub fn is_link_allowed(&self, host: &str) -> bool {
+        self.link_allowlist.iter().any(|domain| {
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.features
+            .get(&feature)
+            .copied()
+            .unwrap_or_else(|| feature.enabled_by_default())
+    }
+}
+
+/// Values loaded from the database per guild and kept in memory until they change
+/// A load that overlaps with a change is returned but not cached, so a value read before the
+/// change can't replace the one it made
+pub struct GuildCache<T> {
+    state: RwLock<CacheState<T>>,
+}
+
+struct CacheState<T> {
+    values: HashMap<serenity::GuildId, Arc<T>>,
+    // Bumped by every change, loads only cache their value if it's still the same afterwards
+    generation: u64,
+}
+
+impl<T> Default for GuildCache<T> {
+    fn default() -> Self {
+        Self {
+            state: RwLock::new(CacheState {
+                values: HashMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+}
+
+impl<T> GuildCache<T> {
+    /// Returns the cached value of a guild, or runs `load` and caches what it returns
+    pub async fn get_or_load(
+        &self,
+        guild: serenity::GuildId,
+        load: impl Future<Output = Result<T, Error>>,
+    ) -> Result<Arc<T>, Error> {
+        let generation = {
+            let state = self.state.read().unwrap();
+            if let Some(value) = state.values.get(&guild) {
+                return Ok(Arc::clone(value));
+            }
+            state.generation
+        };
+
+        let value = Arc::new(load.await?);
+        let mut state = self.state.write().unwrap();
+        if state.generation == generation {
+            state.values.insert(guild, Arc::clone(&value));
+        }
+
+        Ok(value)
+    }
+
+    /// Caches the value a guild was just changed to
+    pub fn set(&self, guild: serenity::GuildId, value: Arc<T>) {
+        let mut state = self.state.write().unwrap();
+        state.generation += 1;
+        state.values.insert(guild, value);
+    }
+
+    /// Drops the value of a guild after it changed in the database, so it's loaded again
+    pub fn invalidate(&self, guild: serenity::GuildId) {
+        let mut state = self.state.write().unwrap();
+        state.generation += 1;
+        state.values.remove(&guild);
+    }
+
+    /// Guilds with a cached value
+    pub fn guilds(&self) -> Vec<serenity::GuildId> {
+        self.state.read().unwrap().values.keys().copied().collect()
+    }
+
+    /// Drops everything cached so it's loaded from the database again, returns how many
+    /// guilds were cached
+    pub fn clear(&self) -> usize {
+        let mut state = self.state.write().unwrap();
+        state.generation += 1;
+        let guilds = state.values.len();
+        state.values.clear();
+        guilds
+    }
+}
+
+pub struct GuildConfigs {
+    db: Db,
+    cache: GuildCache<GuildConfig>,
+    // Held for the whole read, modify, write of an update so concurrent updates don't
+    // overwrite each other's changes
+    updates: tokio::sync::Mutex<()>,
+}
+
+impl GuildConfigs {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            cache: GuildCache::default(),
+            updates: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Returns the config of a guild, loading it from the database on first use
+    pub async fn get(&self, guild: serenity::GuildId) -> Result<Arc<GuildConfig>, Error> {
+        self.cache
+            .get_or_load(guild, self.db.load_guild_config(guild))
+            .await
+    }
+
+    /// Changes the config of a guild and persists it
+    pub async fn update(
+        &self,
+        guild: serenity::GuildId,
+        f: impl FnOnce(&mut GuildConfig),
+    ) -> Result<Arc<GuildConfig>, Error> {
+        let _guard = self.updates.lock().await;
+        let mut config = (*self.get(guild).await?).clone();
+        f(&mut config);
+
+        self.db.save_guild_config(guild, &config).await?;
+
+        let config = Arc::new(config);
+        self.cache.set(guild, Arc::clone(&config));
+
+        Ok(config)
+    }
+
+    /// Guilds whose config is cached
+    pub fn cached_guilds(&self) -> Vec<serenity::GuildId> {
+        self.cache.guilds()
+    }
+
+    /// Drops every cached config, returns how many guilds had one
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+}
+
+/// Resolves the prefix of the guild a message was sent in, for `PrefixFrameworkOptions`
+pub fn dynamic_prefix(
+    ctx: poise::PartialContext<'_, Data, Error>,
+) -> poise::BoxFuture<'_, Result<Option<String>, Error>> {
+    Box::pin(async move {
+        let prefix = match ctx.guild_id {
+            Some(guild) => ctx
+                .data
+                .guild_configs
+                .get(guild)
+                .await?
+                .prefix()
+                .to_string(),
+            None => DEFAULT_PREFIX.to_string(),
+        };
+
+        Ok(Some(prefix))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: serenity::GuildId = serenity::GuildId(1);
+
+    #[tokio::test]
+    async fn loads_are_cached_until_cleared() {
+        let cache = GuildCache::default();
+
+        assert_eq!(*cache.get_or_load(GUILD, async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(*cache.get_or_load(GUILD, async { Ok(2) }).await.unwrap(), 1);
+
+        assert_eq!(cache.clear(), 1);
+        assert_eq!(*cache.get_or_load(GUILD, async { Ok(3) }).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn loads_overlapping_a_change_are_not_cached() {
+        let cache = GuildCache::default();
+
+        // Read before the change was written, returned but not cached
+        let stale = cache.get_or_load(GUILD, async {
+            cache.set(GUILD, Arc::new(2));
+            Ok(1)
+        });
+        assert_eq!(*stale.await.unwrap(), 1);
+        assert_eq!(*cache.get_or_load(GUILD, async { Ok(3) }).await.unwrap(), 2);
+
+        cache.invalidate(GUILD);
+        let stale = cache.get_or_load(GUILD, async {
+            cache.invalidate(GUILD);
+            Ok(4)
+        });
+        assert_eq!(*stale.await.unwrap(), 4);
+        assert_eq!(*cache.get_or_load(GUILD, async { Ok(5) }).await.unwrap(), 5);
+    }
+}
@@ -0,0 +1,235 @@
+// Write-behind counters
+// Counters bumped for every message or reaction, like XP and reaction wall stats, are added up in
+// memory and written to the database in one transaction every FLUSH_INTERVAL, and once more
+// when the bot shuts down. Losses are bounded: a crash loses at most the counts of the last
+// FLUSH_INTERVAL, and a failed flush keeps its counts to try again with the next one, so nothing
+// is written twice. Counts keep coming in while a flush writes, only the counts it wrote are
+// taken out of the pending ones afterwards. XP totals read through `add_xp` include pending
+// counts, the stored part is read once per member and then kept up to date by the flushes, since
+// nothing else writes XP. Leaderboards read straight from the database and can lag behind by up
+// to FLUSH_INTERVAL.
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Db, watchdog, Error};
+
+// How often pending counts are written, also the most that's lost if the bot crashes
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A counter that's only ever added to
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// XP of a member
+    Xp {
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    },
+    /// Uses of an emoji on the messages of a reaction wall in one week
+    WallReaction {
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        week: i64,
+        emoji: String,
+    },
+}
+
+#[derive(Default)]
+struct Buffer {
+    pending: HashMap<Counter, i64>,
+    // XP in the database of members that gained XP since the start
+    stored_xp: HashMap<(serenity::GuildId, serenity::UserId), i64>,
+}
+
+#[derive(Clone)]
+pub struct Counters {
+    db: Db,
+    // Only locked for map updates, never while waiting for the database
+    buffer: Arc<Mutex<Buffer>>,
+    // Held while flushing and while loading stored XP, so loads see every flush whole or not at
+    // all
+    flushing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Counters {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            buffer: Arc::default(),
+            flushing: Arc::default(),
+        }
+    }
+
+    /// Gives a member XP and returns their new total, pending XP included
+    pub async fn add_xp(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        amount: i64,
+    ) -> Result<i64, Error> {
+        let member = (guild, user);
+        if !self.buffer.lock().unwrap().stored_xp.contains_key(&member) {
+            let _flushing = self.flushing.lock().await;
+            let stored = self.db.xp(guild, user).await?;
+            self.buffer
+                .lock()
+                .unwrap()
+                .stored_xp
+                .entry(member)
+                .or_insert(stored);
+        }
+
+        let mut buffer = self.buffer.lock().unwrap();
+        let pending = buffer
+            .pending
+            .entry(Counter::Xp { guild, user })
+            .or_default();
+        *pending += amount;
+        let pending = *pending;
+
+        Ok(buffer.stored_xp[&member] + pending)
+    }
+
+    /// Counts a reaction in a reaction wall channel for the given week
+    pub async fn record_wall_reaction(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        week: i64,
+        emoji: &str,
+    ) {
+        let counter = Counter::WallReaction {
+            guild,
+            channel,
+            week,
+            emoji: emoji.to_string(),
+        };
+        *self
+            .buffer
+            .lock()
+            .unwrap()
+            .pending
+            .entry(counter)
+            .or_default() += 1;
+    }
+
+    /// Writes every pending count, returns how many counters changed
+    pub async fn flush(&self) -> Result<usize, Error> {
+        let _flushing = self.flushing.lock().await;
+        let counts = self.buffer.lock().unwrap().pending.clone();
+        if counts.is_empty() {
+            return Ok(0);
+        }
+
+        self.db.add_counts(&counts).await?;
+
+        let mut buffer = self.buffer.lock().unwrap();
+        for (counter, amount) in &counts {
+            if let Entry::Occupied(mut pending) = buffer.pending.entry(counter.clone()) {
+                *pending.get_mut() -= amount;
+                if *pending.get() == 0 {
+                    pending.remove();
+                }
+            }
+            if let Counter::Xp { guild, user } = counter {
+                if let Some(stored) = buffer.stored_xp.get_mut(&(*guild, *user)) {
+                    *stored += amount;
+                }
+            }
+        }
+
+        Ok(counts.len())
+    }
+}
+
+/// Starts the task that writes pending counts every FLUSH_INTERVAL
+pub fn spawn(counters: Counters) {
+    watchdog::spawn("counters", async move {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+
+            if let Err(e) = counters.flush().await {
+                tracing::warn!("Error writing counters, trying again later: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn counters() -> Counters {
+        Counters::new(Db::memory().await)
+    }
+
+    #[tokio::test]
+    async fn xp_totals_include_pending_xp() {
+        let counters = counters().await;
+        let (guild, user) = (serenity::GuildId(1), serenity::UserId(2));
+
+        assert_eq!(counters.add_xp(guild, user, 20).await.unwrap(), 20);
+        assert_eq!(counters.add_xp(guild, user, 15).await.unwrap(), 35);
+        assert_eq!(counters.db.xp(guild, user).await.unwrap(), 0);
+
+        assert_eq!(counters.flush().await.unwrap(), 1);
+        assert_eq!(counters.db.xp(guild, user).await.unwrap(), 35);
+        assert_eq!(counters.add_xp(guild, user, 5).await.unwrap(), 40);
+    }
+
+    #[tokio::test]
+    async fn stored_xp_is_read_once_per_member() {
+        let counters = counters().await;
+        let (guild, user) = (serenity::GuildId(1), serenity::UserId(2));
+        let counter = Counter::Xp { guild, user };
+
+        counters
+            .db
+            .add_counts(&HashMap::from([(counter.clone(), 100)]))
+            .await
+            .unwrap();
+        assert_eq!(counters.add_xp(guild, user, 10).await.unwrap(), 110);
+
+        // Written behind the buffer's back, which the bot never does, so it isn't seen
+        counters
+            .db
+            .add_counts(&HashMap::from([(counter, 1000)]))
+            .await
+            .unwrap();
+        assert_eq!(counters.add_xp(guild, user, 10).await.unwrap(), 120);
+        counters.flush().await.unwrap();
+        assert_eq!(counters.add_xp(guild, user, 10).await.unwrap(), 130);
+        assert_eq!(counters.db.xp(guild, user).await.unwrap(), 1120);
+    }
+
+    #[tokio::test]
+    async fn flushes_are_written_once() {
+        let counters = counters().await;
+        let (guild, channel) = (serenity::GuildId(1), serenity::ChannelId(3));
+
+        for emoji in ["🔥", "🔥", "👍"] {
+            counters
+                .record_wall_reaction(guild, channel, 7, emoji)
+                .await;
+        }
+        assert_eq!(counters.flush().await.unwrap(), 2);
+        assert_eq!(counters.flush().await.unwrap(), 0);
+        counters.record_wall_reaction(guild, channel, 7, "🔥").await;
+        counters.flush().await.unwrap();
+
+        let mut stats: Vec<_> = counters
+            .db
+            .take_wall_stats(8)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.emoji, s.count))
+            .collect();
+        stats.sort();
+        assert_eq!(stats, [("👍".to_string(), 1), ("🔥".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,6535 @@
+// Storage layer
+// The database lives at DATABASE_URL (default `sqlite://bot.db`), a SQLite file created on first
+// start or a PostgreSQL database, see `storage`. Schema changes go in `migrations/` and are applied
+// automatically on startup.
+// Features add their own typed helpers to `Db` instead of writing SQL in command handlers.
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{ChannelMode, Feature, GuildConfig, RoleList},
+    counters::Counter,
+    economy::Transfer,
+    privacy,
+    quotas::Tier,
+    storage::{to_tsquery, with_pool, Pool, PoolExt, Storage},
+    streaks::{self, Activity, Advanced},
+    toxicity::Category,
+    Error,
+};
+
+const DEFAULT_URL: &str = "sqlite://bot.db";
+
+#[derive(Clone)]
+pub struct Db {
+    pool: Pool,
+}
+
+impl Db {
+    /// Opens (or creates) the database and applies pending migrations
+    pub async fn connect(url: Option<&str>) -> Result<Self, Error> {
+        let pool = Pool::connect(url.unwrap_or(DEFAULT_URL)).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// A new in-memory database, for tests
+    #[cfg(test)]
+    pub async fn memory() -> Self {
+        Self::connect(Some(crate::storage::MEMORY_URL))
+            .await
+            .unwrap()
+    }
+
+    /// Waits for running queries and closes every connection, queries after this fail
+    pub async fn close(&self) {
+        with_pool!(&self.pool, |pool| {
+            pool.close().await;
+        })
+    }
+
+    /// Runs a trivial query and returns how long it took
+    pub async fn ping(&self) -> Result<Duration, Error> {
+        with_pool!(&self.pool, |pool| {
+            let start = Instant::now();
+            sqlx::query(pool.sql("SELECT 1")).execute(pool).await?;
+            Ok(start.elapsed())
+        })
+    }
+
+    /// Unix timestamp of when the database was first created
+    pub async fn created_at(&self) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (value,): (String,) =
+                sqlx::query_as(pool.sql("SELECT value FROM meta WHERE key = 'created_at'"))
+                    .fetch_one(pool)
+                    .await?;
+
+            Ok(value.parse()?)
+        })
+    }
+
+    pub async fn load_guild_config(&self, guild: serenity::GuildId) -> Result<GuildConfig, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<GuildConfigRow> =
+                sqlx::query_as(pool.sql("SELECT * FROM guild_config WHERE guild_id = ?"))
+                    .bind(guild.0 as i64)
+                    .fetch_optional(pool)
+                    .await?;
+
+            let features: Vec<(String, bool)> = sqlx::query_as(
+                pool.sql("SELECT feature, enabled FROM guild_features WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let channel_modes: Vec<(i64, String)> = sqlx::query_as(
+                pool.sql("SELECT channel_id, mode FROM guild_channel_modes WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let role_lists: Vec<(String, i64)> = sqlx::query_as(
+                pool.sql("SELECT list, role_id FROM guild_role_lists WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let link_allowlist: Vec<(String,)> = sqlx::query_as(
+                pool.sql("SELECT domain FROM guild_link_allowlist WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let translation_links: Vec<(i64, i64, String)> = sqlx::query_as(pool.sql(
+                "SELECT channel_id, target_id, language FROM guild_translation_links
+                WHERE guild_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let alt_text_channels: Vec<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT channel_id FROM guild_alt_text_channels WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let toxicity_thresholds: Vec<(String, i64)> = sqlx::query_as(pool.sql(
+                "SELECT category, threshold FROM guild_toxicity_thresholds WHERE guild_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            let mut config = row.map(GuildConfig::from).unwrap_or_default();
+
+            // Features that were removed from the bot are skipped
+            config.features = features
+                .into_iter()
+                .filter_map(|(name, enabled)| Some((name.parse::<Feature>().ok()?, enabled)))
+                .collect();
+            config.channel_modes = channel_modes
+                .into_iter()
+                .filter_map(|(channel, mode)| {
+                    Some((
+                        serenity::ChannelId(channel as u64),
+                        mode.parse::<ChannelMode>().ok()?,
+                    ))
+                })
+                .collect();
+            for (list, role) in role_lists {
+                if let Ok(list) = list.parse::<RoleList>() {
+                    config
+                        .role_lists
+                        .entry(list)
+                        .or_default()
+                        .insert(serenity::RoleId(role as u64));
+                }
+            }
+
+            config.link_allowlist = link_allowlist.into_iter().map(|(domain,)| domain).collect();
+            for (channel, target, language) in translation_links {
+                config
+                    .translation_links
+                    .entry(serenity::ChannelId(channel as u64))
+                    .or_default()
+                    .push((serenity::ChannelId(target as u64), language));
+            }
+            config.alt_text_channels = alt_text_channels
+                .into_iter()
+                .map(|(channel,)| serenity::ChannelId(channel as u64))
+                .collect();
+            config.toxicity_thresholds = toxicity_thresholds
+                .into_iter()
+                .filter_map(|(category, threshold)| {
+                    Some((category.parse::<Category>().ok()?, threshold as u32))
+                })
+                .collect();
+
+            Ok(config)
+        })
+    }
+
+    pub async fn save_guild_config(
+        &self,
+        guild: serenity::GuildId,
+        config: &GuildConfig,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            sqlx::query(pool.sql("DELETE FROM guild_config WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(
+                pool.sql("INSERT INTO guild_config
+                (guild_id, prefix, log_channel_id, message_log_channel_id, warn_threshold,
+                warn_timeout_secs, alt_threshold, alt_action, image_hash_tolerance, review_channel_id,
+                nsfw_threshold, level_channel_id, starboard_channel_id, starboard_threshold,
+                welcome_channel_id, welcome_message, goodbye_message, onboarding_channel_id,
+                voice_hub_channel_id, staff_role_id, house_edge, min_bet, max_bet,
+                gambling_loss_limit, rob_chance, role_retention_secs, watch_channel_id,
+                oncall_channel_id, boost_channel_id, partner_channel_id, partner_interval_secs,
+                vote_delete_threshold, vote_delete_action, vote_delete_emoji, summary_limit)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                ?, ?, ?, ?, ?, ?)"),
+            )
+            .bind(guild.0 as i64)
+            .bind(&config.prefix)
+            .bind(config.log_channel.map(|c| c.0 as i64))
+            .bind(config.message_log_channel.map(|c| c.0 as i64))
+            .bind(config.warn_threshold.map(|t| t as i64))
+            .bind(config.warn_timeout.map(|t| t.as_secs() as i64))
+            .bind(config.alt_threshold.map(|t| t as i64))
+            .bind(config.alt_action.map(|a| a.name()))
+            .bind(config.image_hash_tolerance.map(|t| t as i64))
+            .bind(config.review_channel.map(|c| c.0 as i64))
+            .bind(config.nsfw_threshold.map(|t| t as i64))
+            .bind(config.level_channel.map(|c| c.0 as i64))
+            .bind(config.starboard_channel.map(|c| c.0 as i64))
+            .bind(config.starboard_threshold.map(|t| t as i64))
+            .bind(config.welcome_channel.map(|c| c.0 as i64))
+            .bind(&config.welcome_message)
+            .bind(&config.goodbye_message)
+            .bind(config.onboarding_channel.map(|c| c.0 as i64))
+            .bind(config.voice_hub_channel.map(|c| c.0 as i64))
+            .bind(config.staff_role.map(|r| r.0 as i64))
+            .bind(config.house_edge.map(|e| e as i64))
+            .bind(config.min_bet)
+            .bind(config.max_bet)
+            .bind(config.gambling_loss_limit)
+            .bind(config.rob_chance.map(|c| c as i64))
+            .bind(config.role_retention.map(|r| r.as_secs() as i64))
+            .bind(config.watch_channel.map(|c| c.0 as i64))
+            .bind(config.oncall_channel.map(|c| c.0 as i64))
+            .bind(config.boost_channel.map(|c| c.0 as i64))
+            .bind(config.partner_channel.map(|c| c.0 as i64))
+            .bind(config.partner_interval.map(|i| i.as_secs() as i64))
+            .bind(config.vote_delete_threshold.map(|t| t as i64))
+            .bind(config.vote_delete_action.map(|a| a.name()))
+            .bind(&config.vote_delete_emoji)
+            .bind(config.summary_limit.map(|l| l as i64))
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(pool.sql("DELETE FROM guild_features WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for (feature, enabled) in &config.features {
+                sqlx::query(pool.sql(
+                    "INSERT INTO guild_features (guild_id, feature, enabled) VALUES (?, ?, ?)",
+                ))
+                .bind(guild.0 as i64)
+                .bind(feature.name())
+                .bind(enabled)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_channel_modes WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for (channel, mode) in &config.channel_modes {
+                sqlx::query(pool.sql(
+                    "INSERT INTO guild_channel_modes (guild_id, channel_id, mode) VALUES (?, ?, ?)",
+                ))
+                .bind(guild.0 as i64)
+                .bind(channel.0 as i64)
+                .bind(mode.name())
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_role_lists WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for (list, roles) in &config.role_lists {
+                for role in roles {
+                    sqlx::query(pool.sql(
+                        "INSERT INTO guild_role_lists (guild_id, list, role_id) VALUES (?, ?, ?)",
+                    ))
+                    .bind(guild.0 as i64)
+                    .bind(list.name())
+                    .bind(role.0 as i64)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_link_allowlist WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for domain in &config.link_allowlist {
+                sqlx::query(
+                    pool.sql("INSERT INTO guild_link_allowlist (guild_id, domain) VALUES (?, ?)"),
+                )
+                .bind(guild.0 as i64)
+                .bind(domain)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_translation_links WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for (channel, targets) in &config.translation_links {
+                for (target, language) in targets {
+                    sqlx::query(
+                        pool.sql("INSERT INTO guild_translation_links (guild_id, channel_id, target_id, language)
+                        VALUES (?, ?, ?, ?)"),
+                    )
+                    .bind(guild.0 as i64)
+                    .bind(channel.0 as i64)
+                    .bind(target.0 as i64)
+                    .bind(language)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_alt_text_channels WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for channel in &config.alt_text_channels {
+                sqlx::query(pool.sql(
+                    "INSERT INTO guild_alt_text_channels (guild_id, channel_id) VALUES (?, ?)",
+                ))
+                .bind(guild.0 as i64)
+                .bind(channel.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(pool.sql("DELETE FROM guild_toxicity_thresholds WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            for (category, threshold) in &config.toxicity_thresholds {
+                sqlx::query(pool.sql(
+                    "INSERT INTO guild_toxicity_thresholds (guild_id, category, threshold)
+                    VALUES (?, ?, ?)",
+                ))
+                .bind(guild.0 as i64)
+                .bind(category.name())
+                .bind(*threshold as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the reaction counts of all weeks before `week`
+    pub async fn take_wall_stats(&self, week: i64) -> Result<Vec<WallStat>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let stats = sqlx::query_as(pool.sql(
+                "DELETE FROM reaction_wall_stats WHERE week < ?
+                RETURNING guild_id, channel_id, week, emoji, count",
+            ))
+            .bind(week)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(stats)
+        })
+    }
+
+    /// Remembers which invite a member joined through
+    pub async fn record_member_invite(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        invite: &str,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO member_invites (guild_id, user_id, invite_code) VALUES (?, ?, ?)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET invite_code = excluded.invite_code",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(invite)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Stores a ban together with the invite the user joined through, if known
+    pub async fn record_ban(
+        &self,
+        guild: serenity::GuildId,
+        user: &serenity::User,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO recent_bans (guild_id, user_id, name, invite_code, banned_at)
+                VALUES (?, ?, ?,
+                    (SELECT invite_code FROM member_invites WHERE guild_id = ? AND user_id = ?),
+                    CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET name = excluded.name,
+                invite_code = excluded.invite_code, banned_at = excluded.banned_at",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.id.0 as i64)
+            .bind(&user.name)
+            .bind(guild.0 as i64)
+            .bind(user.id.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Bans since the given unix timestamp, older ones are deleted
+    pub async fn recent_bans(
+        &self,
+        guild: serenity::GuildId,
+        since: i64,
+    ) -> Result<Vec<RecentBan>, Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("DELETE FROM recent_bans WHERE banned_at < ?"))
+                .bind(since)
+                .execute(pool)
+                .await?;
+
+            let bans = sqlx::query_as(
+                pool.sql("SELECT name, invite_code FROM recent_bans WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(bans)
+        })
+    }
+
+    /// Hashes of the images blocked in a guild
+    pub async fn blocked_images(&self, guild: serenity::GuildId) -> Result<Vec<u64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let hashes: Vec<(i64,)> =
+                sqlx::query_as(pool.sql("SELECT hash FROM blocked_images WHERE guild_id = ?"))
+                    .bind(guild.0 as i64)
+                    .fetch_all(pool)
+                    .await?;
+
+            // SQLite only has signed integers, the bits are stored as is
+            Ok(hashes.into_iter().map(|(hash,)| hash as u64).collect())
+        })
+    }
+
+    /// Blocks an image hash, returns false if it already was blocked
+    pub async fn block_image(
+        &self,
+        guild: serenity::GuildId,
+        hash: u64,
+        moderator: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO blocked_images (guild_id, hash, added_by, added_at)
+                VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+            ))
+            .bind(guild.0 as i64)
+            .bind(hash as i64)
+            .bind(moderator.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Unblocks an image hash, returns false if it wasn't blocked
+    pub async fn unblock_image(&self, guild: serenity::GuildId, hash: u64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM blocked_images WHERE guild_id = ? AND hash = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(hash as i64)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Stores a reminder and returns its ID
+    pub async fn add_reminder(
+        &self,
+        user: serenity::UserId,
+        channel: serenity::ChannelId,
+        due_at: i64,
+        text: &str,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO reminders (user_id, channel_id, due_at, text) VALUES (?, ?, ?, ?)
+                RETURNING id",
+            ))
+            .bind(user.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(due_at)
+            .bind(text)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Pending reminders of a user, soonest first
+    pub async fn user_reminders(&self, user: serenity::UserId) -> Result<Vec<Reminder>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let reminders = sqlx::query_as(pool.sql(
+                "SELECT id, user_id, channel_id, due_at, text FROM reminders
+                WHERE user_id = ? ORDER BY due_at",
+            ))
+            .bind(user.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(reminders)
+        })
+    }
+
+    /// Reminders that are due at `now`
+    pub async fn due_reminders(&self, now: i64) -> Result<Vec<Reminder>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let reminders = sqlx::query_as(pool.sql(
+                "SELECT id, user_id, channel_id, due_at, text FROM reminders WHERE due_at <= ?",
+            ))
+            .bind(now)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(reminders)
+        })
+    }
+
+    /// Pushes a reminder that couldn't be delivered back, a minute after the first try and
+    /// doubling from there. Returns false once it was tried `max_attempts` times
+    pub async fn postpone_reminder(
+        &self,
+        id: i64,
+        now: i64,
+        max_attempts: u32,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            // PostgreSQL only shifts by an INT
+            let result = sqlx::query(pool.sql(
+                "UPDATE reminders SET due_at = ? + (60 << CAST(MIN(attempts, 6) AS INT)),
+                attempts = attempts + 1
+                WHERE id = ? AND attempts + 1 < ?",
+            ))
+            .bind(now)
+            .bind(id)
+            .bind(max_attempts as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Deletes a reminder, only if it belongs to `user` when one is given
+    pub async fn delete_reminder(
+        &self,
+        id: i64,
+        user: Option<serenity::UserId>,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("DELETE FROM reminders WHERE id = ? AND (? IS NULL OR user_id = ?)"),
+            )
+            .bind(id)
+            .bind(user.map(|u| u.0 as i64))
+            .bind(user.map(|u| u.0 as i64))
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Maps an emoji on a message to a role, replacing the role it was mapped to before
+    pub async fn add_reaction_role(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        message: serenity::MessageId,
+        emoji: &str,
+        role: serenity::RoleId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO reaction_roles (guild_id, channel_id, message_id, emoji, role_id)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (message_id, emoji) DO UPDATE SET role_id = excluded.role_id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(message.0 as i64)
+            .bind(emoji)
+            .bind(role.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Role handed out for reacting to a message with an emoji
+    pub async fn reaction_role(
+        &self,
+        message: serenity::MessageId,
+        emoji: &str,
+    ) -> Result<Option<serenity::RoleId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let role: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT role_id FROM reaction_roles WHERE message_id = ? AND emoji = ?"),
+            )
+            .bind(message.0 as i64)
+            .bind(emoji)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(role.map(|(id,)| serenity::RoleId(id as u64)))
+        })
+    }
+
+    /// Every reaction role of a guild, grouped by message
+    pub async fn reaction_roles(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<ReactionRole>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let roles = sqlx::query_as(pool.sql(
+                "SELECT channel_id, message_id, emoji, role_id FROM reaction_roles
+                WHERE guild_id = ? ORDER BY message_id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(roles)
+        })
+    }
+
+    /// Removes one emoji of a message, or all of them when no emoji is given
+    /// Returns how many mappings were removed
+    pub async fn remove_reaction_roles(
+        &self,
+        guild: serenity::GuildId,
+        message: serenity::MessageId,
+        emoji: Option<&str>,
+    ) -> Result<u64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "DELETE FROM reaction_roles
+                WHERE guild_id = ? AND message_id = ? AND (? IS NULL OR emoji = ?)",
+            ))
+            .bind(guild.0 as i64)
+            .bind(message.0 as i64)
+            .bind(emoji)
+            .bind(emoji)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected())
+        })
+    }
+
+    /// Stores a spoiler rule and returns its ID
+    pub async fn add_spoiler_rule(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+        keywords: &[String],
+        channels: &[serenity::ChannelId],
+        expires_at: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let (id,): (i64,) = sqlx::query_as(
+                pool.sql("INSERT INTO spoiler_rules (guild_id, name, keywords, expires_at) VALUES (?, ?, ?, ?)
+                RETURNING id"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name)
+            .bind(keywords.join("\n"))
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            for channel in channels {
+                sqlx::query(
+                    pool.sql("INSERT OR IGNORE INTO spoiler_rule_channels (rule_id, channel_id) VALUES (?, ?)"),
+                )
+                .bind(id)
+                .bind(channel.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(id)
+        })
+    }
+
+    /// Spoiler rules of a guild that haven't expired at `now`
+    pub async fn spoiler_rules(
+        &self,
+        guild: serenity::GuildId,
+        now: i64,
+    ) -> Result<Vec<SpoilerRule>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, String, String, i64, Option<String>)> = sqlx::query_as(pool.sql(
+                "SELECT r.id, r.name, r.keywords, r.expires_at, GROUP_CONCAT(c.channel_id)
+                FROM spoiler_rules AS r
+                LEFT JOIN spoiler_rule_channels AS c ON c.rule_id = r.id
+                WHERE r.guild_id = ? AND r.expires_at > ?
+                GROUP BY r.id ORDER BY r.id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(now)
+            .fetch_all(pool)
+            .await?;
+
+            let rules = rows
+                .into_iter()
+                .map(|(id, name, keywords, expires_at, channels)| SpoilerRule {
+                    id,
+                    name,
+                    keywords: keywords.lines().map(str::to_string).collect(),
+                    channels: channels
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter_map(|c| c.parse().ok().map(serenity::ChannelId))
+                        .collect(),
+                    expires_at,
+                })
+                .collect();
+
+            Ok(rules)
+        })
+    }
+
+    /// Deletes a spoiler rule, returns false if it didn't exist
+    pub async fn delete_spoiler_rule(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let result =
+                sqlx::query(pool.sql("DELETE FROM spoiler_rules WHERE guild_id = ? AND id = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+
+            if result.rows_affected() > 0 {
+                sqlx::query(pool.sql("DELETE FROM spoiler_rule_channels WHERE rule_id = ?"))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// The starboard post of a message, if it was posted
+    pub async fn starboard_post(
+        &self,
+        message: serenity::MessageId,
+    ) -> Result<Option<serenity::MessageId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let post: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT post_id FROM starboard_posts WHERE message_id = ?"),
+            )
+            .bind(message.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(post.map(|(id,)| serenity::MessageId(id as u64)))
+        })
+    }
+
+    /// Remembers the starboard post of a message, replacing an older one
+    pub async fn set_starboard_post(
+        &self,
+        guild: serenity::GuildId,
+        message: serenity::MessageId,
+        post: serenity::MessageId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO starboard_posts (message_id, guild_id, post_id) VALUES (?, ?, ?)
+                ON CONFLICT (message_id) DO UPDATE SET guild_id = excluded.guild_id,
+                post_id = excluded.post_id",
+            ))
+            .bind(message.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(post.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Schedules a job, replacing a pending one of the same kind for the same user and target
+    pub async fn schedule_job(
+        &self,
+        kind: &str,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        target: u64,
+        run_at: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            sqlx::query(
+                pool.sql("DELETE FROM jobs WHERE kind = ? AND guild_id = ? AND user_id = ? AND target_id = ?"),
+            )
+            .bind(kind)
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(target as i64)
+            .execute(&mut *tx)
+            .await?;
+
+            let (id,): (i64,) = sqlx::query_as(
+                pool.sql("INSERT INTO jobs (kind, guild_id, user_id, target_id, run_at) VALUES (?, ?, ?, ?, ?)
+                RETURNING id"),
+            )
+            .bind(kind)
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(target as i64)
+            .bind(run_at)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(id)
+        })
+    }
+
+    /// Jobs that are due at `now`, oldest first
+    pub async fn due_jobs(&self, now: i64) -> Result<Vec<Job>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let jobs = sqlx::query_as(pool.sql(
+                "SELECT id, kind, guild_id, user_id, target_id, run_at FROM jobs
+                WHERE run_at <= ? ORDER BY run_at",
+            ))
+            .bind(now)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(jobs)
+        })
+    }
+
+    /// Pending jobs of one kind in a guild, soonest first
+    pub async fn pending_jobs(
+        &self,
+        kind: &str,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<Job>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let jobs = sqlx::query_as(pool.sql(
+                "SELECT id, kind, guild_id, user_id, target_id, run_at FROM jobs
+                WHERE kind = ? AND guild_id = ? ORDER BY run_at",
+            ))
+            .bind(kind)
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(jobs)
+        })
+    }
+
+    /// Pending jobs of one kind in every guild
+    pub async fn jobs_of_kind(&self, kind: &str) -> Result<Vec<Job>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let jobs = sqlx::query_as(pool.sql(
+                "SELECT id, kind, guild_id, user_id, target_id, run_at FROM jobs WHERE kind = ?",
+            ))
+            .bind(kind)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(jobs)
+        })
+    }
+
+    /// Deletes the pending jobs of one kind for a user, for any target if `target` is None,
+    /// and returns them
+    pub async fn take_jobs(
+        &self,
+        kind: &str,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        target: Option<u64>,
+    ) -> Result<Vec<Job>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let jobs = sqlx::query_as(pool.sql(
+                "DELETE FROM jobs WHERE kind = ? AND guild_id = ? AND user_id = ?
+                AND (? IS NULL OR target_id = ?)
+                RETURNING id, kind, guild_id, user_id, target_id, run_at",
+            ))
+            .bind(kind)
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(target.map(|t| t as i64))
+            .bind(target.map(|t| t as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(jobs)
+        })
+    }
+
+    /// Claims a due job by pushing it back, a minute after the first try and doubling up to an
+    /// hour, so it runs again if it fails. Returns false if it was run or cancelled meanwhile
+    pub async fn claim_job(&self, id: i64, now: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            // PostgreSQL only shifts by an INT
+            let result = sqlx::query(pool.sql(
+                "UPDATE jobs SET run_at = ? + MIN(60 << CAST(MIN(attempts, 6) AS INT), 3600),
+                attempts = attempts + 1
+                WHERE id = ? AND run_at <= ?",
+            ))
+            .bind(now)
+            .bind(id)
+            .bind(now)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Deletes a job, returns false if it was already gone
+    pub async fn delete_job(&self, id: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql("DELETE FROM jobs WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Stores a weekly role schedule and returns its ID
+    pub async fn add_role_schedule(
+        &self,
+        guild: serenity::GuildId,
+        role: serenity::RoleId,
+        members_role: serenity::RoleId,
+        grant_at: i64,
+        remove_at: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(
+                pool.sql("INSERT INTO role_schedules (guild_id, role_id, members_role_id, grant_at, remove_at)
+                VALUES (?, ?, ?, ?, ?) RETURNING id"),
+            )
+            .bind(guild.0 as i64)
+            .bind(role.0 as i64)
+            .bind(members_role.0 as i64)
+            .bind(grant_at)
+            .bind(remove_at)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Role schedules of a guild, or of every guild if none is given
+    pub async fn role_schedules(
+        &self,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Vec<RoleSchedule>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let schedules = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, role_id, members_role_id, grant_at, remove_at, active
+                FROM role_schedules WHERE ? IS NULL OR guild_id = ? ORDER BY id",
+            ))
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(schedules)
+        })
+    }
+
+    /// Remembers whether a schedule's role was last granted or removed
+    pub async fn set_role_schedule_active(&self, id: i64, active: bool) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE role_schedules SET active = ? WHERE id = ?"))
+                .bind(active)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Remembers that a schedule gave its role to a member
+    pub async fn add_role_schedule_grant(
+        &self,
+        schedule: i64,
+        user: serenity::UserId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO role_schedule_grants (schedule_id, user_id) VALUES (?, ?)",
+            ))
+            .bind(schedule)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Members a schedule gave its role to and didn't take it from yet
+    pub async fn role_schedule_grants(
+        &self,
+        schedule: i64,
+    ) -> Result<Vec<serenity::UserId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let users: Vec<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT user_id FROM role_schedule_grants WHERE schedule_id = ?"),
+            )
+            .bind(schedule)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(users
+                .into_iter()
+                .map(|(user,)| serenity::UserId(user as u64))
+                .collect())
+        })
+    }
+
+    /// Forgets that a schedule gave its role to a member, once it's taken away again
+    pub async fn delete_role_schedule_grant(
+        &self,
+        schedule: i64,
+        user: serenity::UserId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("DELETE FROM role_schedule_grants WHERE schedule_id = ? AND user_id = ?"),
+            )
+            .bind(schedule)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Deletes a role schedule, returns it if it existed
+    pub async fn delete_role_schedule(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<Option<RoleSchedule>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let schedule = sqlx::query_as(pool.sql(
+                "DELETE FROM role_schedules WHERE guild_id = ? AND id = ?
+                RETURNING id, guild_id, role_id, members_role_id, grant_at, remove_at, active",
+            ))
+            .bind(guild.0 as i64)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(schedule)
+        })
+    }
+
+    /// Stores a weekly quiet hours window and returns its ID
+    pub async fn add_quiet_hours(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        starts_at: i64,
+        ends_at: i64,
+        slowmode: Option<u64>,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO quiet_hours (guild_id, channel_id, starts_at, ends_at, slowmode)
+                VALUES (?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(starts_at)
+            .bind(ends_at)
+            .bind(slowmode.map(|s| s as i64))
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Quiet hours of a guild, or of every guild if none is given
+    pub async fn quiet_hours(
+        &self,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Vec<QuietHours>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let windows = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, channel_id, starts_at, ends_at, slowmode, active,
+                    saved_allow, saved_deny, saved_slowmode
+                FROM quiet_hours WHERE ? IS NULL OR guild_id = ? ORDER BY id",
+            ))
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(windows)
+        })
+    }
+
+    /// Marks quiet hours as started with what they're about to change, or as ended
+    pub async fn set_quiet_hours_active(
+        &self,
+        id: i64,
+        saved: Option<&QuietSnapshot>,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("UPDATE quiet_hours SET active = ?, saved_allow = ?, saved_deny = ?, saved_slowmode = ?
+                WHERE id = ?"),
+            )
+            .bind(saved.is_some())
+            .bind(
+                saved
+                    .and_then(|s| s.overwrite)
+                    .map(|(allow, _)| allow.bits() as i64),
+            )
+            .bind(
+                saved
+                    .and_then(|s| s.overwrite)
+                    .map(|(_, deny)| deny.bits() as i64),
+            )
+            .bind(saved.map(|s| s.slowmode as i64))
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Deletes quiet hours, returns them if they existed
+    pub async fn delete_quiet_hours(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<Option<QuietHours>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let window = sqlx::query_as(pool.sql(
+                "DELETE FROM quiet_hours WHERE guild_id = ? AND id = ?
+                RETURNING id, guild_id, channel_id, starts_at, ends_at, slowmode, active,
+                    saved_allow, saved_deny, saved_slowmode",
+            ))
+            .bind(guild.0 as i64)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(window)
+        })
+    }
+
+    /// Stores a theme, returns false if the guild has one with that name already
+    pub async fn add_theme(&self, theme: &Theme) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("INSERT INTO themes (guild_id, name, starts_on, ends_on, colour, icon, reactions, status)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT DO NOTHING"),
+            )
+            .bind(theme.guild_id)
+            .bind(&theme.name)
+            .bind(theme.starts_on)
+            .bind(theme.ends_on)
+            .bind(theme.colour)
+            .bind(&theme.icon)
+            .bind(&theme.reactions)
+            .bind(&theme.status)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Themes of a guild, or of every guild if none is given
+    pub async fn themes(&self, guild: Option<serenity::GuildId>) -> Result<Vec<Theme>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let themes = sqlx::query_as(
+                pool.sql("SELECT id, guild_id, name, starts_on, ends_on, colour, icon, reactions, status, active,
+                    saved_icon
+                FROM themes WHERE ? IS NULL OR guild_id = ? ORDER BY id"),
+            )
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(themes)
+        })
+    }
+
+    /// Remembers whether a theme is applied, and the server icon it replaced
+    pub async fn set_theme_active(
+        &self,
+        id: i64,
+        active: bool,
+        saved_icon: Option<&str>,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE themes SET active = ?, saved_icon = ? WHERE id = ?"))
+                .bind(active)
+                .bind(saved_icon)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Deletes a theme, returns it if it existed
+    pub async fn delete_theme(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<Option<Theme>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let theme = sqlx::query_as(pool.sql(
+                "DELETE FROM themes WHERE guild_id = ? AND name = ?
+                RETURNING id, guild_id, name, starts_on, ends_on, colour, icon, reactions, status,
+                    active, saved_icon",
+            ))
+            .bind(guild.0 as i64)
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(theme)
+        })
+    }
+
+    /// Remembers a channel that shows a statistic in its name
+    pub async fn add_stats_channel(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        stat: &str,
+        template: &str,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO stats_channels (channel_id, guild_id, stat, template)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT (channel_id) DO UPDATE SET guild_id = excluded.guild_id,
+                stat = excluded.stat, template = excluded.template",
+            ))
+            .bind(channel.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(stat)
+            .bind(template)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Stats channels of a guild, or of every guild if none is given
+    pub async fn stats_channels(
+        &self,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Vec<StatsChannel>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let channels = sqlx::query_as(pool.sql(
+                "SELECT channel_id, guild_id, stat, template FROM stats_channels
+                WHERE ? IS NULL OR guild_id = ? ORDER BY guild_id, channel_id",
+            ))
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(channels)
+        })
+    }
+
+    /// Forgets a stats channel, e.g. after it was deleted
+    pub async fn delete_stats_channel(&self, channel: serenity::ChannelId) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("DELETE FROM stats_channels WHERE channel_id = ?"))
+                .bind(channel.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// The colour role of a booster, if they made one
+    pub async fn booster_role(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Option<serenity::RoleId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let role: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT role_id FROM booster_roles WHERE guild_id = ? AND user_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(role.map(|(id,)| serenity::RoleId(id as u64)))
+        })
+    }
+
+    pub async fn set_booster_role(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        role: serenity::RoleId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO booster_roles (guild_id, user_id, role_id) VALUES (?, ?, ?)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET role_id = excluded.role_id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(role.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Forgets the colour role of a booster, returns it if they had one
+    pub async fn delete_booster_role(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Option<serenity::RoleId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let role: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "DELETE FROM booster_roles WHERE guild_id = ? AND user_id = ? RETURNING role_id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(role.map(|(id,)| serenity::RoleId(id as u64)))
+        })
+    }
+
+    /// Stores a partner, returns false if the guild has one with that name already
+    pub async fn add_partner(&self, partner: &Partner) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO partners (guild_id, name, blurb, invite, added_by, expires_at)
+                VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+            ))
+            .bind(partner.guild_id)
+            .bind(&partner.name)
+            .bind(&partner.blurb)
+            .bind(&partner.invite)
+            .bind(partner.added_by)
+            .bind(partner.expires_at)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn partners(&self, guild: serenity::GuildId) -> Result<Vec<Partner>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let partners = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, name, blurb, invite, added_by, expires_at, impressions,
+                    last_posted_at
+                FROM partners WHERE guild_id = ? ORDER BY name",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(partners)
+        })
+    }
+
+    /// Guilds with at least one partner
+    pub async fn partner_guilds(&self) -> Result<Vec<serenity::GuildId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let guilds: Vec<(i64,)> =
+                sqlx::query_as(pool.sql("SELECT DISTINCT guild_id FROM partners"))
+                    .fetch_all(pool)
+                    .await?;
+
+            Ok(guilds
+                .into_iter()
+                .map(|(guild,)| serenity::GuildId(guild as u64))
+                .collect())
+        })
+    }
+
+    /// Deletes a partner, returns false if there was none with that name
+    pub async fn delete_partner(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM partners WHERE guild_id = ? AND name = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(name)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Marks a guild's partner ad as posted at `now`, returns false if one was posted less
+    /// than `interval` seconds ago
+    pub async fn claim_partner_post(
+        &self,
+        guild: serenity::GuildId,
+        now: i64,
+        interval: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO partner_posts (guild_id, posted_at) VALUES (?, ?)
+                ON CONFLICT (guild_id) DO UPDATE SET posted_at = excluded.posted_at
+                WHERE partner_posts.posted_at <= excluded.posted_at - ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(now)
+            .bind(interval)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Counts a posted ad of a partner
+    pub async fn add_partner_impression(&self, id: i64, now: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("UPDATE partners SET impressions = impressions + 1, last_posted_at = ? WHERE id = ?"),
+            )
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Adds a weekly on-call shift, returns its ID
+    pub async fn add_oncall_shift(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO oncall_shifts (guild_id, user_id, starts_at, ends_at)
+                VALUES (?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(starts_at)
+            .bind(ends_at)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// On-call shifts of a guild in the order they come up in a week
+    pub async fn oncall_shifts(&self, guild: serenity::GuildId) -> Result<Vec<OncallShift>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let shifts = sqlx::query_as(pool.sql(
+                "SELECT id, user_id, starts_at, ends_at FROM oncall_shifts
+                WHERE guild_id = ? ORDER BY starts_at, id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(shifts)
+        })
+    }
+
+    /// Deletes an on-call shift, returns it if it existed
+    pub async fn delete_oncall_shift(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<Option<OncallShift>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let shift = sqlx::query_as(pool.sql(
+                "DELETE FROM oncall_shifts WHERE guild_id = ? AND id = ?
+                RETURNING id, user_id, starts_at, ends_at",
+            ))
+            .bind(guild.0 as i64)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(shift)
+        })
+    }
+
+    /// Guilds with at least one on-call shift
+    pub async fn oncall_guilds(&self) -> Result<Vec<serenity::GuildId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let guilds: Vec<(i64,)> =
+                sqlx::query_as(pool.sql("SELECT DISTINCT guild_id FROM oncall_shifts"))
+                    .fetch_all(pool)
+                    .await?;
+
+            Ok(guilds
+                .into_iter()
+                .map(|(guild,)| serenity::GuildId(guild as u64))
+                .collect())
+        })
+    }
+
+    /// Has `user` take over the shifts of `covered` between two unix timestamps, and forgets
+    /// covers that ended
+    pub async fn add_oncall_cover(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        covered: serenity::UserId,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            sqlx::query(pool.sql("DELETE FROM oncall_covers WHERE guild_id = ? AND ends_at <= ?"))
+                .bind(guild.0 as i64)
+                .bind(starts_at)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(pool.sql(
+                "INSERT INTO oncall_covers (guild_id, user_id, covered_id, starts_at, ends_at)
+                VALUES (?, ?, ?, ?, ?)",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(covered.0 as i64)
+            .bind(starts_at)
+            .bind(ends_at)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Covers of a guild that haven't ended by `now`, oldest first
+    pub async fn oncall_covers(
+        &self,
+        guild: serenity::GuildId,
+        now: i64,
+    ) -> Result<Vec<OncallCover>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let covers = sqlx::query_as(pool.sql(
+                "SELECT user_id, covered_id, starts_at, ends_at FROM oncall_covers
+                WHERE guild_id = ? AND ends_at > ? ORDER BY id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(now)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(covers)
+        })
+    }
+
+    /// Claims posting the rotation of `week`, returns false if it was posted already
+    pub async fn claim_oncall_post(
+        &self,
+        guild: serenity::GuildId,
+        week: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO oncall_posts (guild_id, week) VALUES (?, ?)
+                ON CONFLICT (guild_id) DO UPDATE SET week = excluded.week
+                WHERE oncall_posts.week < excluded.week",
+            ))
+            .bind(guild.0 as i64)
+            .bind(week)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Opens an incident, returns None if one is open already
+    pub async fn open_incident(
+        &self,
+        guild: serenity::GuildId,
+        title: &str,
+        opened_by: serenity::UserId,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let id: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "INSERT INTO incidents (guild_id, title, opened_by, opened_at) VALUES (?, ?, ?, ?)
+                ON CONFLICT DO NOTHING RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(title)
+            .bind(opened_by.0 as i64)
+            .bind(serenity::Timestamp::now().unix_timestamp())
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(id.map(|(id,)| id))
+        })
+    }
+
+    /// The open incident of a guild
+    pub async fn open_incident_of(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Option<Incident>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let incident = sqlx::query_as(pool.sql(
+                "SELECT id, title, opened_by, opened_at, closed_at FROM incidents
+                WHERE guild_id = ? AND closed_at IS NULL",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(incident)
+        })
+    }
+
+    /// Adds an event to the open incident of a guild, if there is one
+    pub async fn record_incident_event(
+        &self,
+        guild: serenity::GuildId,
+        kind: &str,
+        summary: &str,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO incident_events (incident_id, at, kind, summary)
+                SELECT id, ?, ?, ? FROM incidents WHERE guild_id = ? AND closed_at IS NULL",
+            ))
+            .bind(serenity::Timestamp::now().unix_timestamp())
+            .bind(kind)
+            .bind(summary)
+            .bind(guild.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Closes the open incident of a guild, returns it with its timeline
+    pub async fn close_incident(
+        &self,
+        guild: serenity::GuildId,
+        closed_by: serenity::UserId,
+    ) -> Result<Option<(Incident, Vec<IncidentEvent>)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let incident: Option<Incident> = sqlx::query_as(pool.sql(
+                "UPDATE incidents SET closed_by = ?, closed_at = ?
+                WHERE guild_id = ? AND closed_at IS NULL
+                RETURNING id, title, opened_by, opened_at, closed_at",
+            ))
+            .bind(closed_by.0 as i64)
+            .bind(serenity::Timestamp::now().unix_timestamp())
+            .bind(guild.0 as i64)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let incident = match incident {
+                Some(incident) => incident,
+                None => return Ok(None),
+            };
+
+            let events = sqlx::query_as(pool.sql(
+                "SELECT at, kind, summary FROM incident_events WHERE incident_id = ? ORDER BY id",
+            ))
+            .bind(incident.id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(Some((incident, events)))
+        })
+    }
+
+    /// Saves a permission template, replacing one with the same name
+    pub async fn save_perm_template(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+        overwrites: &[serenity::PermissionOverwrite],
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            sqlx::query(
+                pool.sql("DELETE FROM perm_template_overwrites WHERE guild_id = ? AND name = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+
+            for overwrite in overwrites {
+                let (kind, target) = match overwrite.kind {
+                    serenity::PermissionOverwriteType::Role(role) => ("role", role.0),
+                    serenity::PermissionOverwriteType::Member(user) => ("member", user.0),
+                    _ => continue,
+                };
+
+                sqlx::query(
+                    pool.sql("INSERT INTO perm_template_overwrites (guild_id, name, target_id, kind, allow, deny)
+                    VALUES (?, ?, ?, ?, ?, ?)"),
+                )
+                .bind(guild.0 as i64)
+                .bind(name)
+                .bind(target as i64)
+                .bind(kind)
+                .bind(overwrite.allow.bits() as i64)
+                .bind(overwrite.deny.bits() as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Overwrites of a permission template, None if there is no template with that name
+    pub async fn perm_template(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<Option<Vec<serenity::PermissionOverwrite>>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, String, i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT target_id, kind, allow, deny FROM perm_template_overwrites
+                WHERE guild_id = ? AND name = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(name)
+            .fetch_all(pool)
+            .await?;
+
+            if rows.is_empty() {
+                return Ok(None);
+            }
+
+            let overwrites = rows
+                .into_iter()
+                .map(
+                    |(target, kind, allow, deny)| serenity::PermissionOverwrite {
+                        allow: serenity::Permissions::from_bits_truncate(allow as u64),
+                        deny: serenity::Permissions::from_bits_truncate(deny as u64),
+                        kind: match kind.as_str() {
+                            "member" => serenity::PermissionOverwriteType::Member(
+                                serenity::UserId(target as u64),
+                            ),
+                            _ => serenity::PermissionOverwriteType::Role(serenity::RoleId(
+                                target as u64,
+                            )),
+                        },
+                    },
+                )
+                .collect();
+
+            Ok(Some(overwrites))
+        })
+    }
+
+    /// Names of the permission templates of a guild and how many overwrites each has
+    pub async fn perm_templates(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<(String, i64)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let templates = sqlx::query_as(pool.sql(
+                "SELECT name, COUNT(*) FROM perm_template_overwrites WHERE guild_id = ?
+                GROUP BY name ORDER BY name",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(templates)
+        })
+    }
+
+    /// Deletes a permission template, returns false if there was none
+    pub async fn delete_perm_template(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("DELETE FROM perm_template_overwrites WHERE guild_id = ? AND name = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Source object IDs synced to a guild and the IDs of their copies there
+    pub async fn sync_links(&self, target: serenity::GuildId) -> Result<HashMap<u64, u64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let links: Vec<(i64, i64)> = sqlx::query_as(
+                pool.sql("SELECT source_id, target_id FROM sync_links WHERE target_guild_id = ?"),
+            )
+            .bind(target.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(links
+                .into_iter()
+                .map(|(source, target)| (source as u64, target as u64))
+                .collect())
+        })
+    }
+
+    /// Remembers which object in a synced guild is the copy of a source object
+    pub async fn set_sync_link(
+        &self,
+        source: u64,
+        target_guild: serenity::GuildId,
+        target: u64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO sync_links (source_id, target_guild_id, target_id)
+                VALUES (?, ?, ?)
+                ON CONFLICT (source_id, target_guild_id) DO UPDATE SET
+                target_id = excluded.target_id",
+            ))
+            .bind(source as i64)
+            .bind(target_guild.0 as i64)
+            .bind(target as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Stores a FAQ and returns its ID
+    pub async fn add_faq(&self, faq: &Faq) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO faqs (guild_id, question, answer, keywords, regex, min_confidence)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(faq.guild_id)
+            .bind(&faq.question)
+            .bind(&faq.answer)
+            .bind(&faq.keywords)
+            .bind(&faq.regex)
+            .bind(faq.min_confidence)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// FAQs of a guild, oldest first
+    pub async fn faqs(&self, guild: serenity::GuildId) -> Result<Vec<Faq>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let faqs = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, question, answer, keywords, regex, min_confidence, matches,
+                    helpful, unhelpful
+                FROM faqs WHERE guild_id = ? ORDER BY id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(faqs)
+        })
+    }
+
+    /// Deletes a FAQ, returns false if it didn't exist
+    pub async fn delete_faq(&self, guild: serenity::GuildId, id: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql("DELETE FROM faqs WHERE guild_id = ? AND id = ?"))
+                .bind(guild.0 as i64)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Counts a FAQ being answered
+    pub async fn add_faq_match(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE faqs SET matches = matches + 1 WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Counts whether a FAQ's answer helped the member that asked
+    pub async fn add_faq_feedback(&self, id: i64, helpful: bool) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let query = if helpful {
+                "UPDATE faqs SET helpful = helpful + 1 WHERE id = ?"
+            } else {
+                "UPDATE faqs SET unhelpful = unhelpful + 1 WHERE id = ?"
+            };
+            sqlx::query(pool.sql(query)).bind(id).execute(pool).await?;
+
+            Ok(())
+        })
+    }
+
+    /// Stores a knowledge base article, returns false if the guild has one with that title
+    pub async fn add_kb_article(
+        &self,
+        guild: serenity::GuildId,
+        title: &str,
+        body: &str,
+        author: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let id: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "INSERT INTO kb_articles (guild_id, title, body, author_id, updated_at)
+                VALUES (?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT DO NOTHING RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(title)
+            .bind(body)
+            .bind(author.0 as i64)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let id = match id {
+                Some((id,)) => id,
+                None => return Ok(false),
+            };
+            sqlx::query(pool.sql("INSERT INTO kb_search (rowid, title, body) VALUES (?, ?, ?)"))
+                .bind(id)
+                .bind(title)
+                .bind(body)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(true)
+        })
+    }
+
+    /// Replaces the text of an article, returns false if there is none with that title
+    pub async fn edit_kb_article(
+        &self,
+        guild: serenity::GuildId,
+        title: &str,
+        body: &str,
+        author: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            let old = match delete_kb_index(&mut tx, guild, title).await? {
+                Some(old) => old,
+                None => return Ok(false),
+            };
+            sqlx::query(pool.sql(
+                "UPDATE kb_articles
+                SET body = ?, author_id = ?, updated_at = CAST(strftime('%s', 'now') AS INTEGER)
+                WHERE id = ?",
+            ))
+            .bind(body)
+            .bind(author.0 as i64)
+            .bind(old.id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(pool.sql("INSERT INTO kb_search (rowid, title, body) VALUES (?, ?, ?)"))
+                .bind(old.id)
+                .bind(&old.title)
+                .bind(body)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(true)
+        })
+    }
+
+    /// Deletes an article, returns false if there is none with that title
+    pub async fn delete_kb_article(
+        &self,
+        guild: serenity::GuildId,
+        title: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            let old = match delete_kb_index(&mut tx, guild, title).await? {
+                Some(old) => old,
+                None => return Ok(false),
+            };
+            sqlx::query(pool.sql("DELETE FROM kb_articles WHERE id = ?"))
+                .bind(old.id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(true)
+        })
+    }
+
+    /// The article with a title, ignoring case
+    pub async fn kb_article(
+        &self,
+        guild: serenity::GuildId,
+        title: &str,
+    ) -> Result<Option<KbArticle>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let article = sqlx::query_as(pool.sql(
+                "SELECT id, title, body, author_id, updated_at FROM kb_articles
+                WHERE guild_id = ? AND title = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(title)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(article)
+        })
+    }
+
+    /// Titles of a guild's articles, alphabetically
+    pub async fn kb_titles(&self, guild: serenity::GuildId) -> Result<Vec<String>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let titles: Vec<(String,)> = sqlx::query_as(
+                pool.sql("SELECT title FROM kb_articles WHERE guild_id = ? ORDER BY title"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(titles.into_iter().map(|(title,)| title).collect())
+        })
+    }
+
+    /// Best matching articles for a full-text query, with the matching part of their text
+    /// Titles count five times as much as the text
+    pub async fn search_kb(
+        &self,
+        guild: serenity::GuildId,
+        query: &str,
+        limit: u32,
+    ) -> Result<Vec<(String, String)>, Error> {
+        // Full-text search differs too much between the backends to rewrite
+        let (sql, query) = match &self.pool {
+            Pool::Sqlite(_) => (
+                "SELECT a.title, snippet(kb_search, 1, '**', '**', '...', 24)
+                FROM kb_search JOIN kb_articles a ON a.id = kb_search.rowid
+                WHERE kb_search MATCH ? AND a.guild_id = ?
+                ORDER BY bm25(kb_search, 5.0, 1.0) LIMIT ?",
+                query.to_string(),
+            ),
+            Pool::Postgres(_) => (
+                "SELECT a.title,
+                    ts_headline('simple', kb_search.body, q, 'StartSel=**, StopSel=**, MaxWords=24')
+                FROM kb_search JOIN kb_articles a ON a.id = kb_search.rowid,
+                    to_tsquery('simple', ?) q
+                WHERE kb_search.search @@ q AND a.guild_id = ?
+                ORDER BY ts_rank(kb_search.search, q) DESC LIMIT ?",
+                to_tsquery(query),
+            ),
+        };
+
+        with_pool!(&self.pool, |pool| {
+            let results = sqlx::query_as(pool.sql(sql))
+                .bind(&query)
+                .bind(guild.0 as i64)
+                .bind(limit as i64)
+                .fetch_all(pool)
+                .await?;
+
+            Ok(results)
+        })
+    }
+
+    /// Whether message content of the guild may be stored, false in privacy mode
+    pub async fn stores_content(&self, guild: serenity::GuildId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<(bool,)> = sqlx::query_as(
+                pool.sql("SELECT enabled FROM guild_features WHERE guild_id = ? AND feature = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(Feature::PrivacyMode.name())
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(!row.is_some_and(|(enabled,)| enabled))
+        })
+    }
+
+    // Message content as the guild's privacy mode lets it be stored, every query storing
+    // content goes through this
+    async fn stored_content(
+        &self,
+        guild: serenity::GuildId,
+        content: &str,
+    ) -> Result<String, Error> {
+        if self.stores_content(guild).await? {
+            Ok(content.to_string())
+        } else {
+            Ok(privacy::hash_words(guild, content))
+        }
+    }
+
+    /// Hashes the message content stored for a guild before it turned privacy mode on, returns
+    /// how many messages had content
+    pub async fn redact_content(&self, guild: serenity::GuildId) -> Result<u64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+            let archived: Vec<(i64, String)> = sqlx::query_as(
+                pool.sql("SELECT rowid, content FROM archived_messages WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(&mut *tx)
+            .await?;
+            for (id, content) in &archived {
+                sqlx::query(pool.sql("UPDATE archived_messages SET content = ? WHERE rowid = ?"))
+                    .bind(privacy::hash_words(guild, content))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            let voted: Vec<(i64, String)> = sqlx::query_as(
+                pool.sql("SELECT id, content FROM vote_deletions WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(&mut *tx)
+            .await?;
+            for (id, content) in &voted {
+                sqlx::query(pool.sql("UPDATE vote_deletions SET content = ? WHERE id = ?"))
+                    .bind(privacy::hash_words(guild, content))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await?;
+            Ok((archived.len() + voted.len()) as u64)
+        })
+    }
+
+    /// Stores a message of a guild with the message archive on, `parent` is the channel whose
+    /// permissions decide who can find it
+    pub async fn archive_message(
+        &self,
+        guild: serenity::GuildId,
+        parent: serenity::ChannelId,
+        message: &serenity::Message,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO archived_messages
+                (rowid, content, guild_id, channel_id, parent_id, author_id, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)",
+            ))
+            .bind(message.id.0 as i64)
+            .bind(self.stored_content(guild, &message.content).await?)
+            .bind(guild.0 as i64)
+            .bind(message.channel_id.0 as i64)
+            .bind(parent.0 as i64)
+            .bind(message.author.id.0 as i64)
+            .bind(message.timestamp.unix_timestamp())
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Changes the content of an archived message, does nothing if it isn't archived
+    pub async fn edit_archived_message(
+        &self,
+        guild: serenity::GuildId,
+        message: serenity::MessageId,
+        content: &str,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE archived_messages SET content = ? WHERE rowid = ?"))
+                .bind(self.stored_content(guild, content).await?)
+                .bind(message.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Removes deleted messages from the archive
+    pub async fn delete_archived_messages(
+        &self,
+        messages: &[serenity::MessageId],
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let ids = messages
+                .iter()
+                .map(|m| m.0.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            sqlx::query(pool.sql(
+                "DELETE FROM archived_messages WHERE rowid IN (SELECT value FROM json_each(?))",
+            ))
+            .bind(format!("[{}]", ids))
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Removes every archived message of a guild, returns how many there were
+    pub async fn clear_archive(&self, guild: serenity::GuildId) -> Result<u64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql("DELETE FROM archived_messages WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(result.rows_affected())
+        })
+    }
+
+    /// Archived messages matching an FTS5 query, best matches first. Only messages whose parent
+    /// channel is one of `readable` are searched, optionally only those in or under `channel`
+    /// and by `author`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_archive(
+        &self,
+        guild: serenity::GuildId,
+        query: &str,
+        readable: &[serenity::ChannelId],
+        channel: Option<serenity::ChannelId>,
+        author: Option<serenity::UserId>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ArchivedMessage>, Error> {
+        // Like in search_kb, each backend has its own full-text search
+        let (sql, query) = match &self.pool {
+            Pool::Sqlite(_) => (
+                "SELECT rowid AS message_id, channel_id, author_id, created_at,
+                    snippet(archived_messages, 0, '**', '**', '...', 24) AS snippet
+                FROM archived_messages
+                WHERE archived_messages MATCH ? AND guild_id = ?
+                    AND parent_id IN (SELECT value FROM json_each(?))
+                    AND (? IS NULL OR channel_id = ? OR parent_id = ?)
+                    AND (? IS NULL OR author_id = ?)
+                ORDER BY rank LIMIT ? OFFSET ?",
+                query.to_string(),
+            ),
+            Pool::Postgres(_) => (
+                "SELECT rowid AS message_id, channel_id, author_id, created_at,
+                    ts_headline('simple', content, q, 'StartSel=**, StopSel=**, MaxWords=24')
+                        AS snippet
+                FROM archived_messages, to_tsquery('simple', ?) q
+                WHERE search @@ q AND guild_id = ?
+                    AND parent_id IN (SELECT value FROM json_each(?))
+                    AND (? IS NULL OR channel_id = ? OR parent_id = ?)
+                    AND (? IS NULL OR author_id = ?)
+                ORDER BY ts_rank(search, q) DESC, rowid DESC LIMIT ? OFFSET ?",
+                to_tsquery(query),
+            ),
+        };
+
+        with_pool!(&self.pool, |pool| {
+            let readable = readable
+                .iter()
+                .map(|c| c.0.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            let channel = channel.map(|c| c.0 as i64);
+            let author = author.map(|u| u.0 as i64);
+
+            let messages = sqlx::query_as(pool.sql(sql))
+                .bind(&query)
+                .bind(guild.0 as i64)
+                .bind(format!("[{}]", readable))
+                .bind(channel)
+                .bind(channel)
+                .bind(channel)
+                .bind(author)
+                .bind(author)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?;
+
+            Ok(messages)
+        })
+    }
+
+    /// Stores a trigger and returns its ID
+    pub async fn add_trigger(
+        &self,
+        guild: serenity::GuildId,
+        kind: &str,
+        pattern: &str,
+        response: Option<&str>,
+        reaction: Option<&str>,
+        forward: Option<serenity::ChannelId>,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(
+                pool.sql("INSERT INTO triggers (guild_id, kind, pattern, response, reaction, forward_channel_id)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING id"),
+            )
+            .bind(guild.0 as i64)
+            .bind(kind)
+            .bind(pattern)
+            .bind(response)
+            .bind(reaction)
+            .bind(forward.map(|c| c.0 as i64))
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Triggers of a guild, oldest first
+    pub async fn triggers(&self, guild: serenity::GuildId) -> Result<Vec<Trigger>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let triggers = sqlx::query_as(pool.sql(
+                "SELECT id, kind, pattern, response, reaction, forward_channel_id FROM triggers
+                WHERE guild_id = ? ORDER BY id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(triggers)
+        })
+    }
+
+    /// Deletes a trigger, returns false if it didn't exist
+    pub async fn delete_trigger(&self, guild: serenity::GuildId, id: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM triggers WHERE guild_id = ? AND id = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Subscribes a user to a keyword, returns false if they already were
+    pub async fn add_notify_keyword(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        keyword: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("INSERT OR IGNORE INTO notify_keywords (guild_id, user_id, keyword) VALUES (?, ?, ?)"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(keyword)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Unsubscribes a user from a keyword, returns false if they weren't subscribed
+    pub async fn delete_notify_keyword(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        keyword: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "DELETE FROM notify_keywords WHERE guild_id = ? AND user_id = ? AND keyword = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(keyword)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Every keyword subscription of a guild as (user, keyword)
+    pub async fn notify_keywords(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<(serenity::UserId, String)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, String)> = sqlx::query_as(pool.sql(
+                "SELECT user_id, keyword FROM notify_keywords WHERE guild_id = ? ORDER BY keyword",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(user, keyword)| (serenity::UserId(user as u64), keyword))
+                .collect())
+        })
+    }
+
+    /// Mutes or unmutes keyword notifications from a channel for a user
+    pub async fn set_notify_mute(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        channel: serenity::ChannelId,
+        muted: bool,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let query = if muted {
+                "INSERT OR IGNORE INTO notify_mutes (guild_id, user_id, channel_id) VALUES (?, ?, ?)"
+            } else {
+                "DELETE FROM notify_mutes WHERE guild_id = ? AND user_id = ? AND channel_id = ?"
+            };
+            sqlx::query(pool.sql(query))
+                .bind(guild.0 as i64)
+                .bind(user.0 as i64)
+                .bind(channel.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Every muted channel of a guild as (user, channel)
+    pub async fn notify_mutes(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<(serenity::UserId, serenity::ChannelId)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, i64)> = sqlx::query_as(
+                pool.sql("SELECT user_id, channel_id FROM notify_mutes WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(user, channel)| {
+                    (
+                        serenity::UserId(user as u64),
+                        serenity::ChannelId(channel as u64),
+                    )
+                })
+                .collect())
+        })
+    }
+
+    /// Creates or replaces an application form
+    pub async fn save_application_form(
+        &self,
+        guild: serenity::GuildId,
+        form: &ApplicationForm,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("INSERT INTO application_forms (guild_id, name, role_id, review_channel_id, questions)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id, name) DO UPDATE SET
+                    role_id = excluded.role_id,
+                    review_channel_id = excluded.review_channel_id,
+                    questions = excluded.questions"),
+            )
+            .bind(guild.0 as i64)
+            .bind(&form.name)
+            .bind(form.role.0 as i64)
+            .bind(form.review_channel.0 as i64)
+            .bind(form.questions.join("\n"))
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Application forms of a guild, by name
+    pub async fn application_forms(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<ApplicationForm>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(String, i64, i64, String)> = sqlx::query_as(pool.sql(
+                "SELECT name, role_id, review_channel_id, questions FROM application_forms
+                WHERE guild_id = ? ORDER BY name",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(name, role, channel, questions)| ApplicationForm {
+                    name,
+                    role: serenity::RoleId(role as u64),
+                    review_channel: serenity::ChannelId(channel as u64),
+                    questions: questions.lines().map(str::to_string).collect(),
+                })
+                .collect())
+        })
+    }
+
+    /// An application form by name
+    pub async fn application_form(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<Option<ApplicationForm>, Error> {
+        Ok(self
+            .application_forms(guild)
+            .await?
+            .into_iter()
+            .find(|f| f.name == name))
+    }
+
+    /// Deletes an application form, returns false if it didn't exist
+    pub async fn delete_application_form(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("DELETE FROM application_forms WHERE guild_id = ? AND name = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Stores a submitted application and returns its ID
+    pub async fn add_application(
+        &self,
+        guild: serenity::GuildId,
+        form: &str,
+        user: serenity::UserId,
+        answers: &[String],
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO applications (guild_id, form_name, user_id, answers, created_at)
+                VALUES (?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER)) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(form)
+            .bind(user.0 as i64)
+            .bind(serde_json::to_string(answers)?)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Whether a user has an application for a form that wasn't reviewed yet
+    pub async fn has_pending_application(
+        &self,
+        guild: serenity::GuildId,
+        form: &str,
+        user: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "SELECT id FROM applications
+                WHERE guild_id = ? AND form_name = ? AND user_id = ? AND status = 'pending'",
+            ))
+            .bind(guild.0 as i64)
+            .bind(form)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(row.is_some())
+        })
+    }
+
+    /// An application by ID
+    pub async fn application(&self, id: i64) -> Result<Option<Application>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let application =
+                sqlx::query_as(pool.sql(
+                    "SELECT guild_id, form_name, user_id, status FROM applications WHERE id = ?",
+                ))
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+            Ok(application)
+        })
+    }
+
+    /// Marks a pending application as accepted or rejected, returns false if it was already
+    /// reviewed
+    pub async fn review_application(
+        &self,
+        id: i64,
+        accepted: bool,
+        reviewer: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("UPDATE applications SET status = ?, reviewer_id = ? WHERE id = ? AND status = 'pending'"),
+            )
+            .bind(if accepted { "accepted" } else { "rejected" })
+            .bind(reviewer.0 as i64)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Records a message members voted to delete and who voted, returns its ID or None if it
+    /// was voted on already
+    pub async fn add_vote_deletion(
+        &self,
+        message: &serenity::Message,
+        guild: serenity::GuildId,
+        voters: &[serenity::UserId],
+        status: &str,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let content = self.stored_content(guild, &message.content).await?;
+            let mut tx = pool.transaction().await?;
+
+            let id: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "INSERT INTO vote_deletions
+                (guild_id, channel_id, message_id, author_id, content, status, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT DO NOTHING RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(message.channel_id.0 as i64)
+            .bind(message.id.0 as i64)
+            .bind(message.author.id.0 as i64)
+            .bind(&content)
+            .bind(status)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let id = match id {
+                Some((id,)) => id,
+                None => return Ok(None),
+            };
+
+            for voter in voters {
+                sqlx::query(
+                    pool.sql(
+                        "INSERT INTO vote_deletion_voters (deletion_id, user_id) VALUES (?, ?)",
+                    ),
+                )
+                .bind(id)
+                .bind(voter.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(Some(id))
+        })
+    }
+
+    pub async fn vote_deletion(&self, id: i64) -> Result<Option<VoteDeletion>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let deletion = sqlx::query_as(pool.sql(
+                "SELECT guild_id, channel_id, message_id, author_id, status
+                FROM vote_deletions WHERE id = ?",
+            ))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(deletion)
+        })
+    }
+
+    /// Marks a pending vote deletion as deleted or dismissed, returns false if it was already
+    /// reviewed
+    pub async fn review_vote_deletion(
+        &self,
+        id: i64,
+        deleted: bool,
+        reviewer: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("UPDATE vote_deletions SET status = ?, reviewed_by = ? WHERE id = ? AND status = 'pending'"),
+            )
+            .bind(if deleted { "deleted" } else { "dismissed" })
+            .bind(reviewer.0 as i64)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// How many votes of each member of a guild staff dismissed
+    pub async fn dismissed_votes(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<HashMap<serenity::UserId, i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let votes: Vec<(i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT v.user_id, COUNT(*) FROM vote_deletion_voters v
+                JOIN vote_deletions d ON d.id = v.deletion_id
+                WHERE d.guild_id = ? AND d.status = 'dismissed' GROUP BY v.user_id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(votes
+                .into_iter()
+                .map(|(user, count)| (serenity::UserId(user as u64), count))
+                .collect())
+        })
+    }
+
+    /// Stores a message automod flagged for review and returns the flag's ID
+    pub async fn add_automod_flag(
+        &self,
+        guild: serenity::GuildId,
+        message: &serenity::Message,
+        source: &str,
+        rule: &str,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO automod_flags
+                (guild_id, channel_id, message_id, author_id, source, rule, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER)) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(message.channel_id.0 as i64)
+            .bind(message.id.0 as i64)
+            .bind(message.author.id.0 as i64)
+            .bind(source)
+            .bind(rule)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Remembers where a flag was posted for review
+    pub async fn set_automod_flag_message(
+        &self,
+        id: i64,
+        message: &serenity::Message,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("UPDATE automod_flags SET review_channel_id = ?, review_message_id = ? WHERE id = ?"),
+            )
+            .bind(message.channel_id.0 as i64)
+            .bind(message.id.0 as i64)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn automod_flag(&self, id: i64) -> Result<Option<AutomodFlag>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let flag = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, channel_id, message_id, author_id, source, rule, status,
+                review_channel_id, review_message_id, created_at
+                FROM automod_flags WHERE id = ?",
+            ))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(flag)
+        })
+    }
+
+    /// The oldest flags of a guild nobody reviewed yet
+    pub async fn pending_automod_flags(
+        &self,
+        guild: serenity::GuildId,
+        limit: u32,
+    ) -> Result<Vec<AutomodFlag>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let flags = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, channel_id, message_id, author_id, source, rule, status,
+                review_channel_id, review_message_id, created_at
+                FROM automod_flags WHERE guild_id = ? AND status = 'pending'
+                ORDER BY id LIMIT ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(flags)
+        })
+    }
+
+    /// Marks a pending flag as reviewed, returns false if it was already
+    pub async fn review_automod_flag(
+        &self,
+        id: i64,
+        status: &str,
+        reviewer: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "UPDATE automod_flags SET status = ?, reviewed_by = ?,
+                reviewed_at = CAST(strftime('%s', 'now') AS INTEGER)
+                WHERE id = ? AND status = 'pending'",
+            ))
+            .bind(status)
+            .bind(reviewer.0 as i64)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// How many flags of each rule of a guild since `since` ended up with each status
+    pub async fn automod_flag_counts(
+        &self,
+        guild: serenity::GuildId,
+        since: i64,
+    ) -> Result<Vec<FlagCount>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let counts = sqlx::query_as(pool.sql(
+                "SELECT source, rule, status, COUNT(*) AS count FROM automod_flags
+                WHERE guild_id = ? AND created_at >= ? GROUP BY source, rule, status",
+            ))
+            .bind(guild.0 as i64)
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(counts)
+        })
+    }
+
+    /// Who reviewed how many flags of a guild since `since`, the most first
+    pub async fn automod_flag_reviewers(
+        &self,
+        guild: serenity::GuildId,
+        since: i64,
+    ) -> Result<Vec<(serenity::UserId, i64)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let reviewers: Vec<(i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT reviewed_by, COUNT(*) AS count FROM automod_flags
+                WHERE guild_id = ? AND created_at >= ? AND reviewed_by IS NOT NULL
+                GROUP BY reviewed_by ORDER BY count DESC",
+            ))
+            .bind(guild.0 as i64)
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(reviewers
+                .into_iter()
+                .map(|(user, count)| (serenity::UserId(user as u64), count))
+                .collect())
+        })
+    }
+
+    /// Adds a step to the end of a guild's onboarding and returns its ID
+    pub async fn add_onboarding_step(
+        &self,
+        guild: serenity::GuildId,
+        kind: &str,
+        content: &str,
+        delay: Duration,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO onboarding_steps (guild_id, kind, content, delay_secs)
+                VALUES (?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(kind)
+            .bind(content)
+            .bind(delay.as_secs() as i64)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// A guild's onboarding steps in the order they're sent
+    pub async fn onboarding_steps(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<OnboardingStep>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let steps = sqlx::query_as(pool.sql(
+                "SELECT id, kind, content, delay_secs FROM onboarding_steps
+                WHERE guild_id = ? ORDER BY id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(steps)
+        })
+    }
+
+    /// Removes an onboarding step, returns false if it didn't exist
+    pub async fn delete_onboarding_step(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM onboarding_steps WHERE guild_id = ? AND id = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Starts tracking a member's onboarding, from scratch if they went through it before
+    pub async fn start_onboarding(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO onboarding_progress (guild_id, user_id, started_at)
+                VALUES (?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET started_at = excluded.started_at,
+                steps_sent = 0, via_channel = FALSE, completed_at = NULL",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Counts a step as sent to a member, completing their onboarding if it was the last one
+    pub async fn record_onboarding_step(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        via_channel: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "UPDATE onboarding_progress SET steps_sent = steps_sent + 1,
+                via_channel = via_channel OR ?,
+                completed_at = CASE WHEN ? THEN CAST(strftime('%s', 'now') AS INTEGER) END
+                WHERE guild_id = ? AND user_id = ?",
+            ))
+            .bind(via_channel)
+            .bind(last)
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// How far a member got with onboarding, None if they never started it
+    pub async fn onboarding_progress(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Option<OnboardingProgress>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let progress = sqlx::query_as(pool.sql(
+                "SELECT steps_sent, via_channel, started_at, completed_at FROM onboarding_progress
+                WHERE guild_id = ? AND user_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(progress)
+        })
+    }
+
+    /// Sets the archive policy of a guild, keeping when the last report was posted
+    pub async fn set_archive_policy(
+        &self,
+        guild: serenity::GuildId,
+        inactive_days: i64,
+        action: &str,
+        category: Option<serenity::ChannelId>,
+        report_channel: Option<serenity::ChannelId>,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO archive_policies
+                (guild_id, inactive_days, action, category_id, report_channel_id)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id) DO UPDATE
+                SET inactive_days = excluded.inactive_days, action = excluded.action,
+                category_id = excluded.category_id, report_channel_id = excluded.report_channel_id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(inactive_days)
+            .bind(action)
+            .bind(category.map(|c| c.0 as i64))
+            .bind(report_channel.map(|c| c.0 as i64))
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Turns a guild's archive policy off, returns false if it had none
+    pub async fn delete_archive_policy(&self, guild: serenity::GuildId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let result = sqlx::query(pool.sql("DELETE FROM archive_policies WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(pool.sql("DELETE FROM archived_channels WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// The archive policy of a guild, or of every guild if none is given
+    pub async fn archive_policies(
+        &self,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Vec<ArchivePolicy>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let policies = sqlx::query_as(
+                pool.sql("SELECT guild_id, inactive_days, action, category_id, report_channel_id, last_report_at
+                FROM archive_policies WHERE ? IS NULL OR guild_id = ?"),
+            )
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(policies)
+        })
+    }
+
+    pub async fn set_archive_report_time(
+        &self,
+        guild: serenity::GuildId,
+        at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("UPDATE archive_policies SET last_report_at = ? WHERE guild_id = ?"),
+            )
+            .bind(at)
+            .bind(guild.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Sets up or changes a guild's daily digest, the first one is for the slot after `slot`
+    pub async fn set_digest(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        hour: i64,
+        summarize: bool,
+        slot: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO digests (guild_id, channel_id, hour, summarize, last_posted_at)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id) DO UPDATE
+                SET channel_id = excluded.channel_id, hour = excluded.hour,
+                summarize = excluded.summarize, last_posted_at = excluded.last_posted_at",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(hour)
+            .bind(summarize)
+            .bind(slot)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Turns a guild's digest off, returns false if it had none
+    pub async fn delete_digest(&self, guild: serenity::GuildId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let result = sqlx::query(pool.sql("DELETE FROM digests WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(pool.sql("DELETE FROM digest_channels WHERE guild_id = ?"))
+                .bind(guild.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// The digest of a guild, or of every guild if none is given
+    pub async fn digests(&self, guild: Option<serenity::GuildId>) -> Result<Vec<Digest>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let digests = sqlx::query_as(pool.sql(
+                "SELECT guild_id, channel_id, hour, summarize, last_posted_at
+                FROM digests WHERE ? IS NULL OR guild_id = ?",
+            ))
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(digests)
+        })
+    }
+
+    /// Claims the digest for a slot, returns false if it was posted already
+    pub async fn claim_digest(&self, guild: serenity::GuildId, slot: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "UPDATE digests SET last_posted_at = ?
+                WHERE guild_id = ? AND (last_posted_at IS NULL OR last_posted_at < ?)",
+            ))
+            .bind(slot)
+            .bind(guild.0 as i64)
+            .bind(slot)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Counts a thread summary for a guild on a day, returns false if it had `limit` already
+    pub async fn claim_summary(
+        &self,
+        guild: serenity::GuildId,
+        day: i64,
+        limit: u32,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO summary_usage (guild_id, day, used) VALUES (?, ?, 1)
+                ON CONFLICT (guild_id, day) DO UPDATE SET used = summary_usage.used + 1
+                WHERE summary_usage.used < ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(day)
+            .bind(limit as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn set_guild_tier(&self, guild: serenity::GuildId, tier: Tier) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO guild_tiers (guild_id, tier) VALUES (?, ?)
+                ON CONFLICT (guild_id) DO UPDATE SET tier = excluded.tier",
+            ))
+            .bind(guild.0 as i64)
+            .bind(tier.name())
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn guild_tier(&self, guild: serenity::GuildId) -> Result<Tier, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<(String,)> =
+                sqlx::query_as(pool.sql("SELECT tier FROM guild_tiers WHERE guild_id = ?"))
+                    .bind(guild.0 as i64)
+                    .fetch_optional(pool)
+                    .await?;
+
+            Ok(row
+                .and_then(|(tier,)| tier.parse().ok())
+                .unwrap_or_default())
+        })
+    }
+
+    /// Counts a use of a quota if the guild has any left that day, returns whether it had
+    pub async fn claim_quota(
+        &self,
+        guild: serenity::GuildId,
+        quota: &str,
+        day: i64,
+        limit: u32,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            if limit == 0 {
+                return Ok(false);
+            }
+
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO quota_usage (guild_id, quota, day, used) VALUES (?, ?, ?, 1)
+                ON CONFLICT (guild_id, quota, day) DO UPDATE SET used = quota_usage.used + 1
+                WHERE quota_usage.used < ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(quota)
+            .bind(day)
+            .bind(limit as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// How often each quota was used by the guild on a day
+    pub async fn quota_usage(
+        &self,
+        guild: serenity::GuildId,
+        day: i64,
+    ) -> Result<Vec<(String, i64)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let usage = sqlx::query_as(
+                pool.sql("SELECT quota, used FROM quota_usage WHERE guild_id = ? AND day = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(day)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(usage)
+        })
+    }
+
+    /// Stores an entitlement or updates it, e.g. when its subscription was renewed or cancelled
+    pub async fn save_entitlement(&self, entitlement: &Entitlement) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("INSERT INTO entitlements (id, sku_id, user_id, guild_id, ends_at) VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (id) DO UPDATE SET ends_at = excluded.ends_at"),
+            )
+            .bind(entitlement.id)
+            .bind(entitlement.sku_id)
+            .bind(entitlement.user_id)
+            .bind(entitlement.guild_id)
+            .bind(entitlement.ends_at)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn delete_entitlement(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("DELETE FROM entitlements WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Replaces every stored entitlement, after fetching all of them from Discord
+    pub async fn replace_entitlements(&self, entitlements: &[Entitlement]) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+            sqlx::query(pool.sql("DELETE FROM entitlements"))
+                .execute(&mut *tx)
+                .await?;
+            for entitlement in entitlements {
+                sqlx::query(pool.sql(
+                    "INSERT INTO entitlements (id, sku_id, user_id, guild_id, ends_at)
+                    VALUES (?, ?, ?, ?, ?)",
+                ))
+                .bind(entitlement.id)
+                .bind(entitlement.sku_id)
+                .bind(entitlement.user_id)
+                .bind(entitlement.guild_id)
+                .bind(entitlement.ends_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Entitlements to a SKU that haven't ended at `now`, of the guild and of the user
+    pub async fn active_entitlements(
+        &self,
+        sku: i64,
+        guild: Option<serenity::GuildId>,
+        user: Option<serenity::UserId>,
+        now: i64,
+    ) -> Result<Vec<Entitlement>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let entitlements = sqlx::query_as(
+                pool.sql("SELECT id, sku_id, user_id, guild_id, ends_at FROM entitlements
+                WHERE sku_id = ? AND (guild_id = ? OR user_id = ?) AND (ends_at IS NULL OR ends_at > ?)
+                ORDER BY id"),
+            )
+            .bind(sku)
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(user.map(|u| u.0 as i64))
+            .bind(now)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(entitlements)
+        })
+    }
+
+    pub async fn add_vote(&self, user: serenity::UserId, voted_at: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("INSERT INTO votes (user_id, voted_at) VALUES (?, ?)"))
+                .bind(user.0 as i64)
+                .bind(voted_at)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// When the user last voted, if ever
+    pub async fn last_vote(&self, user: serenity::UserId) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (voted_at,): (Option<i64>,) =
+                sqlx::query_as(pool.sql("SELECT MAX(voted_at) FROM votes WHERE user_id = ?"))
+                    .bind(user.0 as i64)
+                    .fetch_one(pool)
+                    .await?;
+
+            Ok(voted_at)
+        })
+    }
+
+    /// Users with the most votes from `since` until before `until`, with their vote counts
+    pub async fn top_voters(
+        &self,
+        since: i64,
+        until: i64,
+        limit: u32,
+    ) -> Result<Vec<(serenity::UserId, i64)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT user_id, COUNT(*) AS votes FROM votes WHERE voted_at >= ? AND voted_at < ?
+                GROUP BY user_id ORDER BY votes DESC, MIN(voted_at) LIMIT ?",
+            ))
+            .bind(since)
+            .bind(until)
+            .bind(limit as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(user, votes)| (serenity::UserId(user as u64), votes))
+                .collect())
+        })
+    }
+
+    /// Marks the vote leaderboard of a month as posted, returns false if it was already
+    pub async fn claim_vote_leaderboard(&self, month: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO vote_leaderboards (month, posted_at)
+                VALUES (?, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT (month) DO NOTHING",
+            ))
+            .bind(month)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Adds a channel to the digest or takes it out
+    pub async fn set_digest_channel(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        included: bool,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let query = if included {
+                "INSERT OR IGNORE INTO digest_channels (guild_id, channel_id) VALUES (?, ?)"
+            } else {
+                "DELETE FROM digest_channels WHERE guild_id = ? AND channel_id = ?"
+            };
+
+            sqlx::query(pool.sql(query))
+                .bind(guild.0 as i64)
+                .bind(channel.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn digest_channels(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<serenity::ChannelId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64,)> = sqlx::query_as(pool.sql(
+                "SELECT channel_id FROM digest_channels WHERE guild_id = ? ORDER BY channel_id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(id,)| serenity::ChannelId(id as u64))
+                .collect())
+        })
+    }
+
+    /// Exempts a channel or category from the archive policy, or stops exempting it
+    pub async fn set_archive_exemption(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        exempt: bool,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let query = if exempt {
+                "INSERT OR IGNORE INTO archive_exemptions (guild_id, channel_id) VALUES (?, ?)"
+            } else {
+                "DELETE FROM archive_exemptions WHERE guild_id = ? AND channel_id = ?"
+            };
+
+            sqlx::query(pool.sql(query))
+                .bind(guild.0 as i64)
+                .bind(channel.0 as i64)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn archive_exemptions(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<HashSet<serenity::ChannelId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT channel_id FROM archive_exemptions WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(id,)| serenity::ChannelId(id as u64))
+                .collect())
+        })
+    }
+
+    /// Channels of a guild the archive action was taken on, with when that happened
+    pub async fn archived_channels(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<HashMap<serenity::ChannelId, i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, i64)> =
+                sqlx::query_as(pool.sql(
+                    "SELECT channel_id, archived_at FROM archived_channels WHERE guild_id = ?",
+                ))
+                .bind(guild.0 as i64)
+                .fetch_all(pool)
+                .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(id, at)| (serenity::ChannelId(id as u64), at))
+                .collect())
+        })
+    }
+
+    /// Records that the archive action was taken on a channel
+    pub async fn set_archived(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("INSERT INTO archived_channels (guild_id, channel_id, archived_at)
+                VALUES (?, ?, ?)
+                ON CONFLICT (guild_id, channel_id) DO UPDATE SET archived_at = excluded.archived_at"),
+            )
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(at)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Forgets that the archive action was taken on a channel, so it can be taken again
+    pub async fn unset_archived(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("DELETE FROM archived_channels WHERE guild_id = ? AND channel_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Sets the anti-spam rule of a kind, replacing the guild's current one
+    pub async fn set_spam_rule(
+        &self,
+        guild: serenity::GuildId,
+        rule: &str,
+        threshold: i64,
+        action: &str,
+        timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO spam_rules (guild_id, rule, threshold, action, timeout_secs)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id, rule) DO UPDATE SET threshold = excluded.threshold,
+                action = excluded.action, timeout_secs = excluded.timeout_secs",
+            ))
+            .bind(guild.0 as i64)
+            .bind(rule)
+            .bind(threshold)
+            .bind(action)
+            .bind(timeout.map(|t| t.as_secs() as i64))
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Removes an anti-spam rule, returns false if the guild didn't have it
+    pub async fn delete_spam_rule(
+        &self,
+        guild: serenity::GuildId,
+        rule: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM spam_rules WHERE guild_id = ? AND rule = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(rule)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn spam_rules(&self, guild: serenity::GuildId) -> Result<Vec<SpamRuleRow>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rules = sqlx::query_as(pool.sql(
+                "SELECT rule, threshold, action, timeout_secs FROM spam_rules WHERE guild_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rules)
+        })
+    }
+
+    /// Stores a new poll and returns its ID, the message is added once it's posted
+    pub async fn create_poll(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        author: serenity::UserId,
+        question: &str,
+        options: &[String],
+        closes_at: Option<i64>,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO polls (guild_id, channel_id, author_id, question, options, closes_at)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(author.0 as i64)
+            .bind(question)
+            .bind(options.join("\n"))
+            .bind(closes_at)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    pub async fn set_poll_message(
+        &self,
+        id: i64,
+        message: serenity::MessageId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE polls SET message_id = ? WHERE id = ?"))
+                .bind(message.0 as i64)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// A poll by ID
+    pub async fn poll(&self, id: i64) -> Result<Option<Poll>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<PollRow> = sqlx::query_as(
+                pool.sql("SELECT id, guild_id, channel_id, message_id, author_id, question, options, closes_at,
+                closed, results_posted FROM polls WHERE id = ?"),
+            )
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(row.map(Poll::from))
+        })
+    }
+
+    /// Records a member's vote, replacing the one they gave before
+    pub async fn vote(
+        &self,
+        poll: i64,
+        user: serenity::UserId,
+        option: usize,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO poll_votes (poll_id, user_id, option) VALUES (?, ?, ?)
+                ON CONFLICT (poll_id, user_id) DO UPDATE SET option = excluded.option",
+            ))
+            .bind(poll)
+            .bind(user.0 as i64)
+            .bind(option as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Votes per option of a poll, options without votes are left out
+    pub async fn poll_votes(&self, poll: i64) -> Result<HashMap<usize, i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, i64)> =
+                sqlx::query_as(pool.sql(
+                    "SELECT option, COUNT(*) FROM poll_votes WHERE poll_id = ? GROUP BY option",
+                ))
+                .bind(poll)
+                .fetch_all(pool)
+                .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(option, votes)| (option as usize, votes))
+                .collect())
+        })
+    }
+
+    /// Closes a poll, nobody can vote on it from then on
+    pub async fn close_poll(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE polls SET closed = TRUE WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Marks the results of a closed poll as posted
+    pub async fn set_poll_results_posted(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE polls SET results_posted = TRUE WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Stores a new giveaway and returns its ID, the message is added once it's posted
+    pub async fn create_giveaway(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        host: serenity::UserId,
+        prize: &str,
+        ends_at: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO giveaways (guild_id, channel_id, host_id, prize, ends_at)
+                VALUES (?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(host.0 as i64)
+            .bind(prize)
+            .bind(ends_at)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    pub async fn set_giveaway_message(
+        &self,
+        id: i64,
+        message: serenity::MessageId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE giveaways SET message_id = ? WHERE id = ?"))
+                .bind(message.0 as i64)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// A giveaway by ID, only if it's from `guild` when given
+    pub async fn giveaway(
+        &self,
+        id: i64,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Option<Giveaway>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let row: Option<GiveawayRow> = sqlx::query_as(
+                pool.sql("SELECT id, guild_id, channel_id, message_id, host_id, prize, ends_at, ended, winner_id,
+                announced FROM giveaways WHERE id = ? AND (? IS NULL OR guild_id = ?)"),
+            )
+            .bind(id)
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(row.map(Giveaway::from))
+        })
+    }
+
+    /// Enters a member into a giveaway, returns false if they had entered already
+    pub async fn enter_giveaway(&self, id: i64, user: serenity::UserId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO giveaway_entries (giveaway_id, user_id) VALUES (?, ?)",
+            ))
+            .bind(id)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn leave_giveaway(&self, id: i64, user: serenity::UserId) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("DELETE FROM giveaway_entries WHERE giveaway_id = ? AND user_id = ?"),
+            )
+            .bind(id)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn giveaway_entrants(&self, id: i64) -> Result<Vec<serenity::UserId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT user_id FROM giveaway_entries WHERE giveaway_id = ?"),
+            )
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(user,)| serenity::UserId(user as u64))
+                .collect())
+        })
+    }
+
+    /// Ends a giveaway, nobody can enter it from then on
+    pub async fn end_giveaway(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE giveaways SET ended = TRUE WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Marks the winner of a giveaway as announced
+    pub async fn set_giveaway_announced(&self, id: i64) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE giveaways SET announced = TRUE WHERE id = ?"))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    pub async fn set_giveaway_winner(
+        &self,
+        id: i64,
+        winner: Option<serenity::UserId>,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE giveaways SET winner_id = ? WHERE id = ?"))
+                .bind(winner.map(|w| w.0 as i64))
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Starts a meeting, returns None if the voice channel has one running already
+    pub async fn start_meeting(
+        &self,
+        guild: serenity::GuildId,
+        voice_channel: serenity::ChannelId,
+        text_channel: serenity::ChannelId,
+        host: serenity::UserId,
+        title: Option<&str>,
+        started_at: i64,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let id: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "INSERT OR IGNORE INTO meetings
+                (guild_id, voice_channel_id, text_channel_id, host_id, title, started_at)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(voice_channel.0 as i64)
+            .bind(text_channel.0 as i64)
+            .bind(host.0 as i64)
+            .bind(title)
+            .bind(started_at)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(id.map(|(id,)| id))
+        })
+    }
+
+    /// The meeting running in a voice channel
+    pub async fn running_meeting(
+        &self,
+        guild: serenity::GuildId,
+        voice_channel: serenity::ChannelId,
+    ) -> Result<Option<Meeting>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let meeting = sqlx::query_as(pool.sql(
+                "SELECT id, voice_channel_id, text_channel_id, host_id, title, started_at, ended_at
+                FROM meetings
+                WHERE guild_id = ? AND voice_channel_id = ? AND ended_at IS NULL",
+            ))
+            .bind(guild.0 as i64)
+            .bind(voice_channel.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(meeting)
+        })
+    }
+
+    /// Records a member joining a meeting, unless they're in it already
+    pub async fn join_meeting(
+        &self,
+        meeting: i64,
+        user: serenity::UserId,
+        at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO meeting_attendance (meeting_id, user_id, joined_at)
+                SELECT ?, ?, ? WHERE NOT EXISTS (
+                    SELECT 1 FROM meeting_attendance
+                    WHERE meeting_id = ? AND user_id = ? AND left_at IS NULL
+                )",
+            ))
+            .bind(meeting)
+            .bind(user.0 as i64)
+            .bind(at)
+            .bind(meeting)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Records a member leaving every running meeting of a guild except the one in `staying`
+    pub async fn leave_meetings(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        staying: Option<serenity::ChannelId>,
+        at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "UPDATE meeting_attendance SET left_at = ?
+                WHERE user_id = ? AND left_at IS NULL AND meeting_id IN (
+                    SELECT id FROM meetings WHERE guild_id = ? AND ended_at IS NULL
+                    AND (? IS NULL OR voice_channel_id != ?)
+                )",
+            ))
+            .bind(at)
+            .bind(user.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(staying.map(|c| c.0 as i64))
+            .bind(staying.map(|c| c.0 as i64))
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Ends a meeting and everyone's attendance, returns false if it had ended already
+    pub async fn end_meeting(&self, meeting: i64, at: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let result = sqlx::query(
+                pool.sql("UPDATE meetings SET ended_at = ? WHERE id = ? AND ended_at IS NULL"),
+            )
+            .bind(at)
+            .bind(meeting)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                pool.sql("UPDATE meeting_attendance SET left_at = ? WHERE meeting_id = ? AND left_at IS NULL"),
+            )
+            .bind(at)
+            .bind(meeting)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Everyone who attended a meeting with when they first joined and how many seconds they
+    /// spent in it, in the order they joined
+    pub async fn meeting_attendance(&self, meeting: i64) -> Result<Vec<Attendance>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let attendance = sqlx::query_as(
+                pool.sql("SELECT user_id, MIN(joined_at) AS first_joined_at,
+                CAST(SUM(COALESCE(left_at, CAST(strftime('%s', 'now') AS INTEGER)) - joined_at) AS INTEGER)
+                    AS seconds
+                FROM meeting_attendance WHERE meeting_id = ?
+                GROUP BY user_id ORDER BY first_joined_at"),
+            )
+            .bind(meeting)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(attendance)
+        })
+    }
+
+    /// Starts a stage, returns None if the channel has one running already
+    pub async fn start_stage(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        text_channel: serenity::ChannelId,
+        host: serenity::UserId,
+        topic: &str,
+        started_at: i64,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let id: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "INSERT OR IGNORE INTO stages
+                (guild_id, channel_id, text_channel_id, host_id, topic, started_at)
+                VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .bind(text_channel.0 as i64)
+            .bind(host.0 as i64)
+            .bind(topic)
+            .bind(started_at)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(id.map(|(id,)| id))
+        })
+    }
+
+    pub async fn set_stage_message(
+        &self,
+        id: i64,
+        message: serenity::MessageId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql("UPDATE stages SET message_id = ? WHERE id = ?"))
+                .bind(message.0 as i64)
+                .bind(id)
+                .execute(pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// A stage by ID
+    pub async fn stage(&self, id: i64) -> Result<Option<Stage>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let stage = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, channel_id, text_channel_id, message_id, host_id, topic,
+                started_at, ended_at FROM stages WHERE id = ?",
+            ))
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(stage)
+        })
+    }
+
+    /// The stage running in a channel
+    pub async fn running_stage(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+    ) -> Result<Option<Stage>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let stage = sqlx::query_as(pool.sql(
+                "SELECT id, guild_id, channel_id, text_channel_id, message_id, host_id, topic,
+                started_at, ended_at FROM stages
+                WHERE guild_id = ? AND channel_id = ? AND ended_at IS NULL",
+            ))
+            .bind(guild.0 as i64)
+            .bind(channel.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(stage)
+        })
+    }
+
+    /// Queues a member to speak, again if they were denied before. Returns false if they're
+    /// waiting or approved already
+    pub async fn raise_hand(
+        &self,
+        stage: i64,
+        user: serenity::UserId,
+        at: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT INTO stage_requests (stage_id, user_id, requested_at) VALUES (?, ?, ?)
+                ON CONFLICT (stage_id, user_id) DO UPDATE
+                SET status = 'waiting', requested_at = excluded.requested_at, decided_at = NULL
+                WHERE stage_requests.status = 'denied'",
+            ))
+            .bind(stage)
+            .bind(user.0 as i64)
+            .bind(at)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Takes a member out of the queue, returns false if they weren't waiting
+    pub async fn lower_hand(&self, stage: i64, user: serenity::UserId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("DELETE FROM stage_requests WHERE stage_id = ? AND user_id = ? AND status = 'waiting'"),
+            )
+            .bind(stage)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Members waiting to speak, longest waiting first
+    pub async fn stage_queue(&self, stage: i64) -> Result<Vec<StageRequest>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let queue = sqlx::query_as(pool.sql(
+                "SELECT user_id, requested_at, decided_at FROM stage_requests
+                WHERE stage_id = ? AND status = 'waiting' ORDER BY requested_at",
+            ))
+            .bind(stage)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(queue)
+        })
+    }
+
+    /// Lets a member speak, whether they raised their hand or not
+    pub async fn approve_speaker(
+        &self,
+        stage: i64,
+        user: serenity::UserId,
+        at: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO stage_requests (stage_id, user_id, requested_at, status, decided_at)
+                VALUES (?, ?, ?, 'approved', ?)
+                ON CONFLICT (stage_id, user_id) DO UPDATE
+                SET status = 'approved', decided_at = excluded.decided_at",
+            ))
+            .bind(stage)
+            .bind(user.0 as i64)
+            .bind(at)
+            .bind(at)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Turns down a raised hand, returns false if they weren't waiting
+    pub async fn deny_speaker(
+        &self,
+        stage: i64,
+        user: serenity::UserId,
+        at: i64,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "UPDATE stage_requests SET status = 'denied', decided_at = ?
+                WHERE stage_id = ? AND user_id = ? AND status = 'waiting'",
+            ))
+            .bind(at)
+            .bind(stage)
+            .bind(user.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn is_approved_speaker(
+        &self,
+        stage: i64,
+        user: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let approved: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "SELECT user_id FROM stage_requests
+                WHERE stage_id = ? AND user_id = ? AND status = 'approved'",
+            ))
+            .bind(stage)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(approved.is_some())
+        })
+    }
+
+    /// Approved speakers in the order they were approved
+    pub async fn stage_speakers(&self, stage: i64) -> Result<Vec<StageRequest>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let speakers = sqlx::query_as(pool.sql(
+                "SELECT user_id, requested_at, decided_at FROM stage_requests
+                WHERE stage_id = ? AND status = 'approved' ORDER BY decided_at",
+            ))
+            .bind(stage)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(speakers)
+        })
+    }
+
+    /// How many members raised their hand during a stage
+    pub async fn stage_request_count(&self, stage: i64) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (count,): (i64,) =
+                sqlx::query_as(pool.sql("SELECT COUNT(*) FROM stage_requests WHERE stage_id = ?"))
+                    .bind(stage)
+                    .fetch_one(pool)
+                    .await?;
+
+            Ok(count)
+        })
+    }
+
+    /// Ends a stage, returns false if it had ended already
+    pub async fn end_stage(&self, id: i64, at: i64) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("UPDATE stages SET ended_at = ? WHERE id = ? AND ended_at IS NULL"),
+            )
+            .bind(at)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    pub async fn create_temp_voice(
+        &self,
+        guild: serenity::GuildId,
+        channel: serenity::ChannelId,
+        owner: serenity::UserId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(pool.sql(
+                "INSERT INTO temp_voice_channels (channel_id, guild_id, owner_id, created_at)
+                VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                ON CONFLICT (channel_id) DO UPDATE SET guild_id = excluded.guild_id,
+                owner_id = excluded.owner_id, created_at = excluded.created_at",
+            ))
+            .bind(channel.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(owner.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Owner of a temporary voice channel, None if it isn't one
+    pub async fn temp_voice_owner(
+        &self,
+        channel: serenity::ChannelId,
+    ) -> Result<Option<serenity::UserId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let owner: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT owner_id FROM temp_voice_channels WHERE channel_id = ?"),
+            )
+            .bind(channel.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(owner.map(|(owner,)| serenity::UserId(owner as u64)))
+        })
+    }
+
+    /// Temporary voice channels of a guild
+    pub async fn temp_voice_channels(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<Vec<serenity::ChannelId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let channels: Vec<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT channel_id FROM temp_voice_channels WHERE guild_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(channels
+                .into_iter()
+                .map(|(channel,)| serenity::ChannelId(channel as u64))
+                .collect())
+        })
+    }
+
+    pub async fn set_temp_voice_owner(
+        &self,
+        channel: serenity::ChannelId,
+        owner: serenity::UserId,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("UPDATE temp_voice_channels SET owner_id = ? WHERE channel_id = ?"),
+            )
+            .bind(owner.0 as i64)
+            .bind(channel.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Forgets a temporary voice channel, returns false if it wasn't one
+    pub async fn delete_temp_voice(&self, channel: serenity::ChannelId) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM temp_voice_channels WHERE channel_id = ?"))
+                    .bind(channel.0 as i64)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Marks a message as the answer of a thread, returns false if it has one already
+    pub async fn mark_answer(
+        &self,
+        guild: serenity::GuildId,
+        thread: serenity::ChannelId,
+        message: serenity::MessageId,
+        answerer: serenity::UserId,
+        marked_by: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO forum_answers
+                    (thread_id, guild_id, message_id, answerer_id, marked_by, marked_at)
+                VALUES (?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+            ))
+            .bind(thread.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(message.0 as i64)
+            .bind(answerer.0 as i64)
+            .bind(marked_by.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// The message marked as the answer of a thread, None if there's none
+    pub async fn forum_answer(
+        &self,
+        thread: serenity::ChannelId,
+    ) -> Result<Option<serenity::MessageId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let answer: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT message_id FROM forum_answers WHERE thread_id = ?"),
+            )
+            .bind(thread.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(answer.map(|(message,)| serenity::MessageId(message as u64)))
+        })
+    }
+
+    /// Creates a tag, returns false if the name is taken
+    pub async fn create_tag(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+        content: &str,
+        author: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO tags (guild_id, name, content, author_id, created_at)
+                VALUES (?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+            ))
+            .bind(guild.0 as i64)
+            .bind(name)
+            .bind(content)
+            .bind(author.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Content of a tag, counting it as used
+    pub async fn use_tag(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<Option<String>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let content: Option<(String,)> = sqlx::query_as(pool.sql(
+                "UPDATE tags SET uses = uses + 1 WHERE guild_id = ? AND name = ? RETURNING content",
+            ))
+            .bind(guild.0 as i64)
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(content.map(|(content,)| content))
+        })
+    }
+
+    /// All tags of a guild, by name
+    pub async fn tags(&self, guild: serenity::GuildId) -> Result<Vec<Tag>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let tags = sqlx::query_as(pool.sql(
+                "SELECT name, author_id, uses, created_at FROM tags
+                WHERE guild_id = ? ORDER BY name",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(tags)
+        })
+    }
+
+    /// Deletes a tag, returns false if it didn't exist
+    pub async fn delete_tag(&self, guild: serenity::GuildId, name: &str) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql("DELETE FROM tags WHERE guild_id = ? AND name = ?"))
+                .bind(guild.0 as i64)
+                .bind(name)
+                .execute(pool)
+                .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// XP of a member, 0 if they never earned any
+    pub async fn xp(&self, guild: serenity::GuildId, user: serenity::UserId) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let xp: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT xp FROM member_xp WHERE guild_id = ? AND user_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(xp.map_or(0, |(xp,)| xp))
+        })
+    }
+
+    /// Adds to counters in one transaction, see `counters`
+    pub async fn add_counts(&self, counts: &HashMap<Counter, i64>) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            for (counter, amount) in counts {
+                match counter {
+                    Counter::Xp { guild, user } => {
+                        sqlx::query(
+                            pool.sql("INSERT INTO member_xp (guild_id, user_id, xp) VALUES (?, ?, ?)
+                            ON CONFLICT (guild_id, user_id) DO UPDATE SET xp = member_xp.xp + excluded.xp"),
+                        )
+                        .bind(guild.0 as i64)
+                        .bind(user.0 as i64)
+                        .bind(amount)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Counter::WallReaction {
+                        guild,
+                        channel,
+                        week,
+                        emoji,
+                    } => {
+                        sqlx::query(
+                            pool.sql("INSERT INTO reaction_wall_stats (guild_id, channel_id, week, emoji, count)
+                            VALUES (?, ?, ?, ?, ?)
+                            ON CONFLICT (channel_id, week, emoji)
+                            DO UPDATE SET count = reaction_wall_stats.count + excluded.count"),
+                        )
+                        .bind(guild.0 as i64)
+                        .bind(channel.0 as i64)
+                        .bind(week)
+                        .bind(emoji)
+                        .bind(amount)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                }
+            }
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Coins of a member, 0 if they never had any
+    pub async fn balance(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let balance: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT balance FROM balances WHERE guild_id = ? AND user_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(balance.map_or(0, |(balance,)| balance))
+        })
+    }
+
+    /// Moves coins from one member to another, creating them if there's no sender and spending
+    /// them if there's no receiver. Applies once per `key`, see `economy`
+    pub async fn transfer(
+        &self,
+        guild: serenity::GuildId,
+        from: Option<serenity::UserId>,
+        to: Option<serenity::UserId>,
+        amount: i64,
+        reason: &str,
+        key: &str,
+    ) -> Result<Transfer, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+            let transfer = transfer_in(&mut tx, guild, from, to, amount, reason, key).await?;
+            if transfer == Transfer::Done {
+                tx.commit().await?;
+            }
+
+            Ok(transfer)
+        })
+    }
+
+    /// Adds an item to a guild's shop, returns false if there's one with that name
+    pub async fn create_shop_item(
+        &self,
+        guild: serenity::GuildId,
+        item: &NewShopItem<'_>,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(pool.sql(
+                "INSERT OR IGNORE INTO shop_items
+                    (guild_id, name, kind, price, description, role_id, rental_secs)
+                VALUES (?, ?, ?, ?, ?, ?, ?)",
+            ))
+            .bind(guild.0 as i64)
+            .bind(item.name)
+            .bind(item.kind)
+            .bind(item.price)
+            .bind(item.description)
+            .bind(item.role.map(|r| r.0 as i64))
+            .bind(item.rental.map(|r| r.as_secs() as i64))
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Removes an item from the shop and every inventory, returns false if there was none
+    pub async fn delete_shop_item(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let deleted: Option<(i64,)> = sqlx::query_as(
+                pool.sql("DELETE FROM shop_items WHERE guild_id = ? AND name = ? RETURNING id"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let id = match deleted {
+                Some((id,)) => id,
+                None => return Ok(false),
+            };
+            sqlx::query(pool.sql("DELETE FROM inventory WHERE item_id = ?"))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(true)
+        })
+    }
+
+    /// Items of a guild's shop, cheapest first
+    pub async fn shop_items(&self, guild: serenity::GuildId) -> Result<Vec<ShopItem>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let items = sqlx::query_as(
+                pool.sql("SELECT * FROM shop_items WHERE guild_id = ? ORDER BY price, name"),
+            )
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(items)
+        })
+    }
+
+    /// An item of a guild's shop by its name, ignoring case
+    pub async fn shop_item(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+    ) -> Result<Option<ShopItem>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let item = sqlx::query_as(
+                pool.sql("SELECT * FROM shop_items WHERE guild_id = ? AND name = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(name.trim())
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(item)
+        })
+    }
+
+    /// Pays for an item and puts it in the member's inventory in one transaction. Rentals are
+    /// extended from when the current one expires, returns when the new one does
+    pub async fn buy_item(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        item: &ShopItem,
+        key: &str,
+    ) -> Result<(Transfer, Option<i64>), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            let reason = format!("shop:{}", item.name);
+            let transfer =
+                transfer_in(&mut tx, guild, Some(user), None, item.price, &reason, key).await?;
+            if transfer != Transfer::Done {
+                return Ok((transfer, None));
+            }
+
+            let expires_at = match item.rental_secs {
+                Some(rental) => {
+                    let current: Option<(Option<i64>,)> = sqlx::query_as(pool.sql(
+                        "SELECT expires_at FROM inventory
+                        WHERE guild_id = ? AND user_id = ? AND item_id = ?",
+                    ))
+                    .bind(guild.0 as i64)
+                    .bind(user.0 as i64)
+                    .bind(item.id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                    let now = serenity::Timestamp::now().unix_timestamp();
+                    let from = current.and_then(|(at,)| at).unwrap_or(now).max(now);
+                    Some(from + rental)
+                }
+                None => None,
+            };
+
+            sqlx::query(pool.sql(
+                "INSERT INTO inventory (guild_id, user_id, item_id, quantity, expires_at)
+                VALUES (?, ?, ?, 1, ?)
+                ON CONFLICT (guild_id, user_id, item_id)
+                DO UPDATE SET quantity = inventory.quantity + 1, expires_at = excluded.expires_at",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(item.id)
+            .bind(expires_at)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok((transfer, expires_at))
+        })
+    }
+
+    /// Undoes `buy_item` when the item couldn't be handed out, pays the price back and takes
+    /// one out of the inventory
+    pub async fn refund_item(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        item: &ShopItem,
+        key: &str,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let reason = format!("refund:{}", item.name);
+            let key = format!("refund:{}", key);
+            if transfer_in(&mut tx, guild, None, Some(user), item.price, &reason, &key).await?
+                != Transfer::Done
+            {
+                return Ok(());
+            }
+            sqlx::query(pool.sql(
+                "UPDATE inventory SET quantity = quantity - 1
+                WHERE guild_id = ? AND user_id = ? AND item_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(pool.sql(
+                "DELETE FROM inventory
+                WHERE guild_id = ? AND user_id = ? AND item_id = ? AND quantity <= 0",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Takes a bet and pays out `payout`, 0 for a lost bet, in one transaction
+    pub async fn gamble(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        bet: i64,
+        payout: i64,
+        game: &str,
+        key: &str,
+    ) -> Result<Transfer, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let reason = format!("gamble:{}", game);
+            let transfer = transfer_in(&mut tx, guild, Some(user), None, bet, &reason, key).await?;
+            if transfer != Transfer::Done {
+                return Ok(transfer);
+            }
+            if payout > 0 {
+                let key = format!("{}:payout", key);
+                transfer_in(&mut tx, guild, None, Some(user), payout, &reason, &key).await?;
+            }
+
+            tx.commit().await?;
+            Ok(Transfer::Done)
+        })
+    }
+
+    /// Coins a member lost gambling since the unix timestamp `since`, less what they won
+    pub async fn gambling_losses(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        since: i64,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (losses,): (i64,) = sqlx::query_as(pool.sql(
+                "SELECT CAST(COALESCE(SUM(CASE WHEN from_id = ? THEN amount ELSE -amount END), 0) AS INTEGER)
+                FROM economy_ledger
+                WHERE guild_id = ? AND (from_id = ? OR to_id = ?) AND reason LIKE 'gamble:%'
+                    AND created_at >= ?",
+            ))
+            .bind(user.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(user.0 as i64)
+            .bind(since)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(losses)
+        })
+    }
+
+    /// Starts the cooldown of an economy action if the last one is over. Returns None if it
+    /// started, or when the running one ends
+    pub async fn start_cooldown(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        action: &str,
+        cooldown: Duration,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let now = serenity::Timestamp::now().unix_timestamp();
+
+            // Only one of two racing commands can move a cooldown that's over
+            let started = sqlx::query(
+                pool.sql("INSERT INTO economy_cooldowns (guild_id, user_id, action, ready_at) VALUES (?, ?, ?, ?)
+                ON CONFLICT (guild_id, user_id, action) DO UPDATE SET ready_at = excluded.ready_at
+                WHERE economy_cooldowns.ready_at <= ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(action)
+            .bind(now + cooldown.as_secs() as i64)
+            .bind(now)
+            .execute(pool)
+            .await?;
+            if started.rows_affected() > 0 {
+                return Ok(None);
+            }
+
+            let (ready_at,): (i64,) = sqlx::query_as(pool.sql(
+                "SELECT ready_at FROM economy_cooldowns
+                WHERE guild_id = ? AND user_id = ? AND action = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(action)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(Some(ready_at))
+        })
+    }
+
+    /// When the cooldown of an economy action ends, if it's running
+    pub async fn cooldown(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        action: &str,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let ready_at: Option<(i64,)> = sqlx::query_as(pool.sql(
+                "SELECT ready_at FROM economy_cooldowns
+                WHERE guild_id = ? AND user_id = ? AND action = ?
+                    AND ready_at > CAST(strftime('%s', 'now') AS INTEGER)",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(action)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(ready_at.map(|(ready_at,)| ready_at))
+        })
+    }
+
+    /// Saves the roles of a member that left, replacing ones saved before. Roles of other
+    /// members saved before `expired` are deleted on the way
+    pub async fn save_roles(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        roles: &[serenity::RoleId],
+        expired: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            sqlx::query(pool.sql(
+                "DELETE FROM saved_roles WHERE guild_id = ? AND (user_id = ? OR saved_at < ?)",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(expired)
+            .execute(&mut *tx)
+            .await?;
+            for role in roles {
+                sqlx::query(pool.sql(
+                    "INSERT INTO saved_roles (guild_id, user_id, role_id, saved_at)
+                    VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+                ))
+                .bind(guild.0 as i64)
+                .bind(user.0 as i64)
+                .bind(role.0 as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            tx.commit().await?;
+            Ok(())
+        })
+    }
+
+    /// Takes the saved roles of a member, ones saved before `expired` left out
+    pub async fn take_saved_roles(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        expired: i64,
+    ) -> Result<Vec<serenity::RoleId>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let roles: Vec<(i64, i64)> = sqlx::query_as(
+                pool.sql("DELETE FROM saved_roles WHERE guild_id = ? AND user_id = ? RETURNING role_id, saved_at"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(roles
+                .into_iter()
+                .filter(|(_, saved_at)| *saved_at >= expired)
+                .map(|(role, _)| serenity::RoleId(role as u64))
+                .collect())
+        })
+    }
+
+    /// UTC offset of a user in minutes, 0 if they never set one
+    pub async fn timezone(&self, user: serenity::UserId) -> Result<i32, Error> {
+        with_pool!(&self.pool, |pool| {
+            let offset: Option<(i64,)> = sqlx::query_as(
+                pool.sql("SELECT offset_minutes FROM user_timezones WHERE user_id = ?"),
+            )
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(offset.map_or(0, |(offset,)| offset as i32))
+        })
+    }
+
+    /// Sets the UTC offset of a user unless they changed it within `cooldown`. Returns None if
+    /// it was set, or when it can be changed again
+    pub async fn set_timezone(
+        &self,
+        user: serenity::UserId,
+        offset: i32,
+        cooldown: Duration,
+    ) -> Result<Option<i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let now = serenity::Timestamp::now().unix_timestamp();
+            let cooldown = cooldown.as_secs() as i64;
+
+            let set = sqlx::query(pool.sql(
+                "INSERT INTO user_timezones (user_id, offset_minutes, changed_at) VALUES (?, ?, ?)
+                ON CONFLICT (user_id) DO UPDATE
+                SET offset_minutes = excluded.offset_minutes, changed_at = excluded.changed_at
+                WHERE user_timezones.changed_at <= ?",
+            ))
+            .bind(user.0 as i64)
+            .bind(offset)
+            .bind(now)
+            .bind(now - cooldown)
+            .execute(pool)
+            .await?;
+            if set.rows_affected() > 0 {
+                return Ok(None);
+            }
+
+            let (changed_at,): (i64,) =
+                sqlx::query_as(pool.sql("SELECT changed_at FROM user_timezones WHERE user_id = ?"))
+                    .bind(user.0 as i64)
+                    .fetch_one(pool)
+                    .await?;
+
+            Ok(Some(changed_at + cooldown))
+        })
+    }
+
+    /// Counts `today` towards a member's streak, using up streak freezes for missed days.
+    /// None if today is counted already
+    pub async fn record_streak(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        activity: Activity,
+        today: i64,
+    ) -> Result<Option<Advanced>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.guild_transaction(guild).await?;
+
+            let streak: Option<Streak> = sqlx::query_as(pool.sql(
+                "SELECT current, best, last_day FROM streaks
+                WHERE guild_id = ? AND user_id = ? AND activity = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(activity.name())
+            .fetch_optional(&mut *tx)
+            .await?;
+            let freezes: Vec<(i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT inventory.item_id, inventory.quantity
+                FROM inventory JOIN shop_items ON shop_items.id = inventory.item_id
+                WHERE inventory.guild_id = ? AND inventory.user_id = ?
+                    AND shop_items.kind = 'streak_freeze'",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let owned = freezes.iter().map(|(_, quantity)| quantity).sum();
+            let advanced = match streaks::advance(streak.as_ref(), today, owned) {
+                Some(advanced) => advanced,
+                None => return Ok(None),
+            };
+
+            let mut to_use = advanced.freezes_used;
+            for (item, quantity) in freezes {
+                if to_use == 0 {
+                    break;
+                }
+                let used = quantity.min(to_use);
+                to_use -= used;
+                sqlx::query(pool.sql(
+                    "UPDATE inventory SET quantity = quantity - ?
+                    WHERE guild_id = ? AND user_id = ? AND item_id = ?",
+                ))
+                .bind(used)
+                .bind(guild.0 as i64)
+                .bind(user.0 as i64)
+                .bind(item)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(pool.sql(
+                    "DELETE FROM inventory
+                    WHERE guild_id = ? AND user_id = ? AND item_id = ? AND quantity <= 0",
+                ))
+                .bind(guild.0 as i64)
+                .bind(user.0 as i64)
+                .bind(item)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(pool.sql(
+                "INSERT INTO streaks (guild_id, user_id, activity, current, best, last_day)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id, user_id, activity) DO UPDATE SET current = excluded.current,
+                best = excluded.best, last_day = excluded.last_day",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(activity.name())
+            .bind(advanced.streak.current)
+            .bind(advanced.streak.best)
+            .bind(advanced.streak.last_day)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(Some(advanced))
+        })
+    }
+
+    /// A member's streak of an activity, if they ever started one
+    pub async fn streak(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        activity: Activity,
+    ) -> Result<Option<Streak>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let streak = sqlx::query_as(pool.sql(
+                "SELECT current, best, last_day FROM streaks
+                WHERE guild_id = ? AND user_id = ? AND activity = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(activity.name())
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(streak)
+        })
+    }
+
+    /// Members with the longest streaks of an activity last counted on `since` or later
+    pub async fn top_streaks(
+        &self,
+        guild: serenity::GuildId,
+        activity: Activity,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, Streak)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rows: Vec<(i64, i64, i64, i64)> = sqlx::query_as(pool.sql(
+                "SELECT user_id, current, best, last_day FROM streaks
+                WHERE guild_id = ? AND activity = ? AND last_day >= ?
+                ORDER BY current DESC, best DESC LIMIT ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(activity.name())
+            .bind(since)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(user, current, best, last_day)| {
+                    (
+                        user,
+                        Streak {
+                            current,
+                            best,
+                            last_day,
+                        },
+                    )
+                })
+                .collect())
+        })
+    }
+
+    /// Items a member owns, rentals that expired left out
+    pub async fn inventory(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Vec<InventoryItem>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let items = sqlx::query_as(pool.sql(
+                "SELECT shop_items.name, shop_items.kind, shop_items.role_id, inventory.quantity,
+                    inventory.expires_at
+                FROM inventory JOIN shop_items ON shop_items.id = inventory.item_id
+                WHERE inventory.guild_id = ? AND inventory.user_id = ?
+                    AND (inventory.expires_at IS NULL
+                        OR inventory.expires_at > CAST(strftime('%s', 'now') AS INTEGER))
+                ORDER BY shop_items.name",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(items)
+        })
+    }
+
+    /// XP of a member and their place on the leaderboard, starting at 1
+    pub async fn member_rank(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Option<(i64, i64)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rank = sqlx::query_as(pool.sql(
+                "SELECT xp, (SELECT COUNT(*) FROM member_xp AS other
+                    WHERE other.guild_id = member_xp.guild_id AND other.xp > member_xp.xp) + 1
+                FROM member_xp WHERE guild_id = ? AND user_id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(rank)
+        })
+    }
+
+    /// Members with the most XP, `limit` of them starting at `offset`
+    pub async fn leaderboard(
+        &self,
+        guild: serenity::GuildId,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MemberXp>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let members = sqlx::query_as(pool.sql(
+                "SELECT user_id, xp FROM member_xp WHERE guild_id = ?
+                ORDER BY xp DESC LIMIT ? OFFSET ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(members)
+        })
+    }
+
+    /// XP of every member of a guild that earned some
+    pub async fn guild_xp(&self, guild: serenity::GuildId) -> Result<HashMap<i64, i64>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let members: Vec<MemberXp> =
+                sqlx::query_as(pool.sql("SELECT user_id, xp FROM member_xp WHERE guild_id = ?"))
+                    .bind(guild.0 as i64)
+                    .fetch_all(pool)
+                    .await?;
+
+            Ok(members.into_iter().map(|m| (m.user_id, m.xp)).collect())
+        })
+    }
+
+    /// Adds or replaces the promotion rule for a role
+    pub async fn set_promotion_rule(
+        &self,
+        guild: serenity::GuildId,
+        role: serenity::RoleId,
+        min_level: i64,
+        min_days: i64,
+    ) -> Result<(), Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                pool.sql("INSERT INTO promotion_rules (guild_id, role_id, min_level, min_days) VALUES (?, ?, ?, ?)
+                ON CONFLICT (guild_id, role_id) DO UPDATE
+                SET min_level = excluded.min_level, min_days = excluded.min_days"),
+            )
+            .bind(guild.0 as i64)
+            .bind(role.0 as i64)
+            .bind(min_level)
+            .bind(min_days)
+            .execute(pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Promotion rules of a guild, or of every guild if none is given, easiest first
+    pub async fn promotion_rules(
+        &self,
+        guild: Option<serenity::GuildId>,
+    ) -> Result<Vec<PromotionRule>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let rules = sqlx::query_as(pool.sql(
+                "SELECT guild_id, role_id, min_level, min_days FROM promotion_rules
+                WHERE ? IS NULL OR guild_id = ? ORDER BY guild_id, min_level, min_days",
+            ))
+            .bind(guild.map(|g| g.0 as i64))
+            .bind(guild.map(|g| g.0 as i64))
+            .fetch_all(pool)
+            .await?;
+
+            Ok(rules)
+        })
+    }
+
+    /// Deletes the promotion rule for a role, returns false if there was none
+    pub async fn delete_promotion_rule(
+        &self,
+        guild: serenity::GuildId,
+        role: serenity::RoleId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result = sqlx::query(
+                pool.sql("DELETE FROM promotion_rules WHERE guild_id = ? AND role_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(role.0 as i64)
+            .execute(pool)
+            .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Records a ban or unban as a case and returns its number
+    pub async fn add_case(
+        &self,
+        guild: serenity::GuildId,
+        action: &str,
+        user: serenity::UserId,
+        moderator: serenity::UserId,
+        reason: &str,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+            let case_id = next_case_id(&mut tx, guild).await?;
+
+            sqlx::query(
+                pool.sql("INSERT INTO mod_cases (guild_id, case_id, action, user_id, moderator_id, reason, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))"),
+            )
+            .bind(guild.0 as i64)
+            .bind(case_id)
+            .bind(action)
+            .bind(user.0 as i64)
+            .bind(moderator.0 as i64)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(case_id)
+        })
+    }
+
+    /// Stores a warning and returns it with its new case ID
+    pub async fn add_warning(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        moderator: serenity::UserId,
+        reason: &str,
+    ) -> Result<Warning, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+            let case_id = next_case_id(&mut tx, guild).await?;
+
+            let warning: Warning = sqlx::query_as(
+                pool.sql("INSERT INTO warnings (guild_id, case_id, user_id, moderator_id, reason, created_at)
+                VALUES (?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                RETURNING case_id, user_id, moderator_id, reason, created_at"),
+            )
+            .bind(guild.0 as i64)
+            .bind(case_id)
+            .bind(user.0 as i64)
+            .bind(moderator.0 as i64)
+            .bind(reason)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok(warning)
+        })
+    }
+
+    /// All warnings of a user, oldest first
+    pub async fn warnings_for(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Vec<Warning>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let warnings = sqlx::query_as(pool.sql(
+                "SELECT case_id, user_id, moderator_id, reason, created_at FROM warnings
+                WHERE guild_id = ? AND user_id = ? ORDER BY case_id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(warnings)
+        })
+    }
+
+    /// Adds a note on a user
+    pub async fn add_note(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        author: serenity::UserId,
+        content: &str,
+    ) -> Result<i64, Error> {
+        with_pool!(&self.pool, |pool| {
+            let (id,): (i64,) = sqlx::query_as(pool.sql(
+                "INSERT INTO mod_notes (guild_id, user_id, author_id, content, created_at)
+                VALUES (?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))
+                RETURNING id",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(author.0 as i64)
+            .bind(content)
+            .fetch_one(pool)
+            .await?;
+
+            Ok(id)
+        })
+    }
+
+    /// Notes on a user, newest first
+    pub async fn notes_for(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Vec<ModNote>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let notes = sqlx::query_as(pool.sql(
+                "SELECT id, user_id, author_id, content, created_at,
+                    (SELECT COUNT(*) FROM mod_note_edits WHERE note_id = mod_notes.id) AS edits
+                FROM mod_notes WHERE guild_id = ? AND user_id = ? ORDER BY id DESC",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(notes)
+        })
+    }
+
+    /// Changes what a note says and keeps what it said before, false if there's no such note
+    pub async fn edit_note(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+        editor: serenity::UserId,
+        content: &str,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.transaction().await?;
+
+            let recorded = sqlx::query(pool.sql(
+                "INSERT INTO mod_note_edits (note_id, editor_id, previous_content, edited_at)
+                SELECT id, ?, content, CAST(strftime('%s', 'now') AS INTEGER)
+                FROM mod_notes WHERE guild_id = ? AND id = ?",
+            ))
+            .bind(editor.0 as i64)
+            .bind(guild.0 as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            if recorded.rows_affected() == 0 {
+                return Ok(false);
+            }
+            sqlx::query(pool.sql("UPDATE mod_notes SET content = ? WHERE guild_id = ? AND id = ?"))
+                .bind(content)
+                .bind(guild.0 as i64)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(true)
+        })
+    }
+
+    /// A note and its earlier versions, oldest first
+    pub async fn note_history(
+        &self,
+        guild: serenity::GuildId,
+        id: i64,
+    ) -> Result<Option<(ModNote, Vec<NoteEdit>)>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let note: Option<ModNote> = sqlx::query_as(pool.sql(
+                "SELECT id, user_id, author_id, content, created_at,
+                    (SELECT COUNT(*) FROM mod_note_edits WHERE note_id = mod_notes.id) AS edits
+                FROM mod_notes WHERE guild_id = ? AND id = ?",
+            ))
+            .bind(guild.0 as i64)
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+            let note = match note {
+                Some(note) => note,
+                None => return Ok(None),
+            };
+
+            let edits = sqlx::query_as(pool.sql(
+                "SELECT editor_id, previous_content, edited_at FROM mod_note_edits
+                WHERE note_id = ? ORDER BY id",
+            ))
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(Some((note, edits)))
+        })
+    }
+
+    /// Puts a user on the watchlist, or updates the reason, returns false if they were already on it
+    pub async fn watch(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        reason: Option<&str>,
+        added_by: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let existed = sqlx::query_scalar::<_, i64>(
+                pool.sql("SELECT user_id FROM watchlist WHERE guild_id = ? AND user_id = ?"),
+            )
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .fetch_optional(pool)
+            .await?
+            .is_some();
+
+            sqlx::query(pool.sql(
+                "INSERT INTO watchlist (guild_id, user_id, reason, added_by, added_at)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (guild_id, user_id) DO UPDATE SET
+                    reason = excluded.reason, added_by = excluded.added_by",
+            ))
+            .bind(guild.0 as i64)
+            .bind(user.0 as i64)
+            .bind(reason)
+            .bind(added_by.0 as i64)
+            .bind(serenity::Timestamp::now().unix_timestamp())
+            .execute(pool)
+            .await?;
+
+            Ok(!existed)
+        })
+    }
+
+    /// Takes a user off the watchlist, returns whether they were on it
+    pub async fn unwatch(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<bool, Error> {
+        with_pool!(&self.pool, |pool| {
+            let result =
+                sqlx::query(pool.sql("DELETE FROM watchlist WHERE guild_id = ? AND user_id = ?"))
+                    .bind(guild.0 as i64)
+                    .bind(user.0 as i64)
+                    .execute(pool)
+                    .await?;
+
+            Ok(result.rows_affected() > 0)
+        })
+    }
+
+    /// Watched users of a guild, longest watched first
+    pub async fn watchlist(&self, guild: serenity::GuildId) -> Result<Vec<Watched>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let watched = sqlx::query_as(pool.sql(
+                "SELECT user_id, reason, added_by, added_at FROM watchlist
+                WHERE guild_id = ? ORDER BY added_at, user_id",
+            ))
+            .bind(guild.0 as i64)
+            .fetch_all(pool)
+            .await?;
+
+            Ok(watched)
+        })
+    }
+
+    /// Deletes a warning by case ID, returns it if it existed
+    pub async fn delete_warning(
+        &self,
+        guild: serenity::GuildId,
+        case_id: i64,
+    ) -> Result<Option<Warning>, Error> {
+        with_pool!(&self.pool, |pool| {
+            let warning = sqlx::query_as(pool.sql(
+                "DELETE FROM warnings WHERE guild_id = ? AND case_id = ?
+                RETURNING case_id, user_id, moderator_id, reason, created_at",
+            ))
+            .bind(guild.0 as i64)
+            .bind(case_id)
+            .fetch_optional(pool)
+            .await?;
+
+            Ok(warning)
+        })
+    }
+}
+
+// Takes the next case number of a guild. The counter is bumped in one statement, which locks its
+// row, so concurrent cases can't get the same number
+async fn next_case_id<S>(
+    tx: &mut sqlx::Transaction<'_, S>,
+    guild: serenity::GuildId,
+) -> Result<i64, Error>
+where
+    S: Storage,
+    for<'c> &'c mut S::Connection: sqlx::Executor<'c, Database = S>,
+    for<'q> S::Arguments<'q>: sqlx::IntoArguments<'q, S>,
+    i64: for<'q> sqlx::Encode<'q, S> + sqlx::Type<S>,
+    (i64,): for<'r> sqlx::FromRow<'r, S::Row>,
+{
+    let (case_id,): (i64,) = sqlx::query_as(S::sql(
+        "INSERT INTO case_counters (guild_id, last_case_id) VALUES (?, 1)
+        ON CONFLICT (guild_id) DO UPDATE SET last_case_id = case_counters.last_case_id + 1
+        RETURNING last_case_id",
+    ))
+    .bind(guild.0 as i64)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(case_id)
+}
+
+/// Questions members answer to get a role, see `applications`
+pub struct ApplicationForm {
+    pub name: String,
+    pub role: serenity::RoleId,
+    pub review_channel: serenity::ChannelId,
+    pub questions: Vec<String>,
+}
+
+/// A submitted application, `status` is pending, accepted or rejected
+pub struct Poll {
+    pub id: i64,
+    pub guild: serenity::GuildId,
+    pub channel: serenity::ChannelId,
+    pub message: Option<serenity::MessageId>,
+    pub author: serenity::UserId,
+    pub question: String,
+    pub options: Vec<String>,
+    pub closes_at: Option<i64>,
+    pub closed: bool,
+    pub results_posted: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Stage {
+    pub id: i64,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub text_channel_id: i64,
+    pub message_id: Option<i64>,
+    pub host_id: i64,
+    pub topic: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct StageRequest {
+    pub user_id: i64,
+    pub requested_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Meeting {
+    pub id: i64,
+    pub voice_channel_id: i64,
+    pub text_channel_id: i64,
+    pub host_id: i64,
+    pub title: Option<String>,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Attendance {
+    pub user_id: i64,
+    pub first_joined_at: i64,
+    pub seconds: i64,
+}
+
+pub struct Giveaway {
+    pub id: i64,
+    pub guild: serenity::GuildId,
+    pub channel: serenity::ChannelId,
+    pub message: Option<serenity::MessageId>,
+    pub host: serenity::UserId,
+    pub prize: String,
+    pub ends_at: i64,
+    pub ended: bool,
+    pub winner: Option<serenity::UserId>,
+    pub announced: bool,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Application {
+    pub guild_id: i64,
+    pub form_name: String,
+    pub user_id: i64,
+    pub status: String,
+}
+
+/// A message members voted to delete
+#[derive(sqlx::FromRow)]
+pub struct VoteDeletion {
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub message_id: i64,
+    pub author_id: i64,
+    /// pending, deleted or dismissed
+    pub status: String,
+}
+
+/// A message automod flagged for staff to review
+#[derive(sqlx::FromRow)]
+pub struct AutomodFlag {
+    pub id: i64,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub message_id: i64,
+    pub author_id: i64,
+    pub source: String,
+    pub rule: String,
+    /// pending, approved, deleted, warned or timed_out
+    pub status: String,
+    pub review_channel_id: Option<i64>,
+    pub review_message_id: Option<i64>,
+    pub created_at: i64,
+}
+
+/// How many flags of a rule ended up with a status
+#[derive(sqlx::FromRow)]
+pub struct FlagCount {
+    pub source: String,
+    pub rule: String,
+    pub status: String,
+    pub count: i64,
+}
+
+/// An entitlement to one of the app's SKUs, see `entitlements`
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+pub struct Entitlement {
+    pub id: i64,
+    pub sku_id: i64,
+    pub user_id: Option<i64>,
+    pub guild_id: Option<i64>,
+    pub ends_at: Option<i64>,
+}
+
+/// A warning given to a user
+#[derive(sqlx::FromRow)]
+pub struct OnboardingStep {
+    pub id: i64,
+    pub kind: String,
+    pub content: String,
+    pub delay_secs: i64,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct OnboardingProgress {
+    pub steps_sent: i64,
+    pub via_channel: bool,
+    pub started_at: i64,
+    pub completed_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct ArchivePolicy {
+    pub guild_id: i64,
+    pub inactive_days: i64,
+    pub action: String,
+    pub category_id: Option<i64>,
+    pub report_channel_id: Option<i64>,
+    pub last_report_at: Option<i64>,
+}
+
+/// A guild's daily digest set up with /digest setup
+#[derive(sqlx::FromRow)]
+pub struct Digest {
+    pub guild_id: i64,
+    pub channel_id: i64,
+    /// Hour of the day in UTC it's posted at
+    pub hour: i64,
+    pub summarize: bool,
+    pub last_posted_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct SpamRuleRow {
+    pub rule: String,
+    pub threshold: i64,
+    pub action: String,
+    pub timeout_secs: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct Warning {
+    pub case_id: i64,
+    pub user_id: i64,
+    pub moderator_id: i64,
+    pub reason: String,
+    pub created_at: i64,
+}
+
+/// A note a moderator keeps on a user
+#[derive(sqlx::FromRow)]
+pub struct ModNote {
+    pub id: i64,
+    pub user_id: i64,
+    pub author_id: i64,
+    pub content: String,
+    pub created_at: i64,
+    /// How often it was edited
+    pub edits: i64,
+}
+
+/// What a note said before an edit
+#[derive(sqlx::FromRow)]
+pub struct NoteEdit {
+    pub editor_id: i64,
+    pub previous_content: String,
+    pub edited_at: i64,
+}
+
+/// A user on the watchlist
+#[derive(sqlx::FromRow)]
+pub struct Watched {
+    pub user_id: i64,
+    pub reason: Option<String>,
+    pub added_by: i64,
+    pub added_at: i64,
+}
+
+/// A weekly window in which a moderator is on call
+#[derive(Clone, sqlx::FromRow)]
+pub struct OncallShift {
+    pub id: i64,
+    pub user_id: i64,
+    /// Minutes since Monday 00:00 UTC
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+/// A moderator taking over the shifts of another one
+#[derive(Clone, sqlx::FromRow)]
+pub struct OncallCover {
+    pub user_id: i64,
+    pub covered_id: i64,
+    /// Unix timestamps
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+/// An incident staff opened with /incident start or /staff
+#[derive(sqlx::FromRow)]
+pub struct Incident {
+    pub id: i64,
+    pub title: String,
+    pub opened_by: i64,
+    pub opened_at: i64,
+    pub closed_at: Option<i64>,
+}
+
+/// Something that happened during an incident
+#[derive(sqlx::FromRow)]
+pub struct IncidentEvent {
+    pub at: i64,
+    pub kind: String,
+    pub summary: String,
+}
+
+/// A reminder set with /remind
+#[derive(sqlx::FromRow)]
+pub struct Reminder {
+    pub id: i64,
+    pub user_id: i64,
+    pub channel_id: i64,
+    pub due_at: i64,
+    pub text: String,
+}
+
+/// An emoji on a message that hands out a role
+#[derive(sqlx::FromRow)]
+pub struct ReactionRole {
+    pub channel_id: i64,
+    pub message_id: i64,
+    pub emoji: String,
+    pub role_id: i64,
+}
+
+/// Keywords that have to be spoilered, in all channels if `channels` is empty
+pub struct SpoilerRule {
+    pub id: i64,
+    pub name: String,
+    pub keywords: Vec<String>,
+    pub channels: Vec<serenity::ChannelId>,
+    pub expires_at: i64,
+}
+
+/// Work scheduled for later, see `jobs`
+#[derive(sqlx::FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub guild_id: i64,
+    pub user_id: i64,
+    pub target_id: i64,
+    pub run_at: i64,
+}
+
+/// A role given to members with another role during a weekly window
+#[derive(sqlx::FromRow)]
+pub struct RoleSchedule {
+    pub id: i64,
+    pub guild_id: i64,
+    pub role_id: i64,
+    pub members_role_id: i64,
+    /// Minutes since Monday 00:00 UTC
+    pub grant_at: i64,
+    pub remove_at: i64,
+    pub active: bool,
+}
+
+/// A weekly window in which a channel is locked or slowed down
+#[derive(sqlx::FromRow)]
+pub struct QuietHours {
+    pub id: i64,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    /// Minutes since Monday 00:00 UTC
+    pub starts_at: i64,
+    pub ends_at: i64,
+    /// Slowmode in seconds, the channel is locked if there is none
+    pub slowmode: Option<i64>,
+    pub active: bool,
+    saved_allow: Option<i64>,
+    saved_deny: Option<i64>,
+    saved_slowmode: Option<i64>,
+}
+
+impl QuietHours {
+    /// What the window changed when it started, if it's active
+    pub fn saved(&self) -> Option<QuietSnapshot> {
+        if !self.active {
+            return None;
+        }
+
+        let bits = |b: i64| serenity::Permissions::from_bits_truncate(b as u64);
+        Some(QuietSnapshot {
+            overwrite: self
+                .saved_allow
+                .zip(self.saved_deny)
+                .map(|(allow, deny)| (bits(allow), bits(deny))),
+            slowmode: self.saved_slowmode.unwrap_or_default() as u64,
+        })
+    }
+}
+
+/// A channel's @everyone overwrite and slowmode from before quiet hours
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietSnapshot {
+    /// Allowed and denied permissions, if the channel had an overwrite
+    pub overwrite: Option<(serenity::Permissions, serenity::Permissions)>,
+    /// Seconds
+    pub slowmode: u64,
+}
+
+/// A yearly theme created with /theme add
+#[derive(sqlx::FromRow, Clone, Default)]
+pub struct Theme {
+    pub id: i64,
+    pub guild_id: i64,
+    pub name: String,
+    /// Month * 100 + day, both days are included
+    pub starts_on: i64,
+    pub ends_on: i64,
+    pub colour: Option<i64>,
+    /// Server icon as a data URI
+    pub icon: Option<String>,
+    /// Unicode emojis separated by spaces
+    pub reactions: Option<String>,
+    /// Bot status, like `watching the snow fall`
+    pub status: Option<String>,
+    pub active: bool,
+    pub saved_icon: Option<String>,
+}
+
+/// A voice channel showing a statistic of its guild in its name
+#[derive(sqlx::FromRow)]
+pub struct StatsChannel {
+    pub channel_id: i64,
+    pub guild_id: i64,
+    pub stat: String,
+    pub template: String,
+}
+
+/// A partner server whose ad is posted in turn with the others
+#[derive(sqlx::FromRow, Clone, Default)]
+pub struct Partner {
+    pub id: i64,
+    pub guild_id: i64,
+    pub name: String,
+    pub blurb: String,
+    pub invite: String,
+    pub added_by: i64,
+    /// Unix timestamp after which the ad isn't posted anymore
+    pub expires_at: Option<i64>,
+    /// How often the ad was posted
+    pub impressions: i64,
+    pub last_posted_at: Option<i64>,
+}
+
+/// An article of a guild's knowledge base
+#[derive(sqlx::FromRow)]
+pub struct KbArticle {
+    pub id: i64,
+    pub title: String,
+    pub body: String,
+    pub author_id: i64,
+    pub updated_at: i64,
+}
+
+/// A message found in the archive by /search
+#[derive(sqlx::FromRow)]
+pub struct ArchivedMessage {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub author_id: i64,
+    pub created_at: i64,
+    /// The matching part of the content, with the matched words in bold
+    pub snippet: String,
+}
+
+/// An answer to a frequently asked question created with /faq add
+#[derive(sqlx::FromRow, Clone, Default)]
+pub struct Faq {
+    pub id: i64,
+    pub guild_id: i64,
+    pub question: String,
+    pub answer: String,
+    /// Separated by spaces
+    pub keywords: Option<String>,
+    pub regex: Option<String>,
+    /// Share of the keywords in percent a message needs to be answered
+    pub min_confidence: i64,
+    pub matches: i64,
+    pub helpful: i64,
+    pub unhelpful: i64,
+}
+
+/// An auto-response created with /trigger add
+#[derive(sqlx::FromRow)]
+pub struct Trigger {
+    pub id: i64,
+    pub kind: String,
+    pub pattern: String,
+    pub response: Option<String>,
+    pub reaction: Option<String>,
+    /// Where matching messages are copied to
+    pub forward_channel_id: Option<i64>,
+}
+
+// Takes an article out of the search index, which needs the indexed text to do it
+async fn delete_kb_index<S>(
+    tx: &mut sqlx::Transaction<'_, S>,
+    guild: serenity::GuildId,
+    title: &str,
+) -> Result<Option<KbArticle>, Error>
+where
+    S: Storage,
+    for<'c> &'c mut S::Connection: sqlx::Executor<'c, Database = S>,
+    for<'q> S::Arguments<'q>: sqlx::IntoArguments<'q, S>,
+    i64: for<'q> sqlx::Encode<'q, S> + sqlx::Type<S>,
+    for<'q> &'q str: sqlx::Encode<'q, S> + sqlx::Type<S>,
+    for<'q> &'q String: sqlx::Encode<'q, S> + sqlx::Type<S>,
+    KbArticle: for<'r> sqlx::FromRow<'r, S::Row>,
+{
+    let old: Option<KbArticle> = sqlx::query_as(S::sql(
+        "SELECT id, title, body, author_id, updated_at FROM kb_articles
+        WHERE guild_id = ? AND title = ?",
+    ))
+    .bind(guild.0 as i64)
+    .bind(title)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if let Some(old) = &old {
+        sqlx::query(S::sql(
+            "INSERT INTO kb_search (kb_search, rowid, title, body) VALUES ('delete', ?, ?, ?)",
+        ))
+        .bind(old.id)
+        .bind(&old.title)
+        .bind(&old.body)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(old)
+}
+
+// Does a transfer inside a transaction, the caller commits it if it's done. Each statement
+// checks and writes at once, the ledger's key and the balance it debits from, so concurrent
+// transfers can't apply twice or overdraw
+async fn transfer_in<S>(
+    tx: &mut sqlx::Transaction<'_, S>,
+    guild: serenity::GuildId,
+    from: Option<serenity::UserId>,
+    to: Option<serenity::UserId>,
+    amount: i64,
+    reason: &str,
+    key: &str,
+) -> Result<Transfer, Error>
+where
+    S: Storage,
+    for<'c> &'c mut S::Connection: sqlx::Executor<'c, Database = S>,
+    for<'q> S::Arguments<'q>: sqlx::IntoArguments<'q, S>,
+    i64: for<'q> sqlx::Encode<'q, S> + sqlx::Type<S>,
+    for<'q> &'q str: sqlx::Encode<'q, S> + sqlx::Type<S>,
+    Option<i64>: for<'q> sqlx::Encode<'q, S> + sqlx::Type<S>,
+{
+    let recorded = sqlx::query(S::sql(
+        "INSERT OR IGNORE INTO economy_ledger
+            (guild_id, from_id, to_id, amount, reason, idempotency_key, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+    ))
+    .bind(guild.0 as i64)
+    .bind(from.map(|u| u.0 as i64))
+    .bind(to.map(|u| u.0 as i64))
+    .bind(amount)
+    .bind(reason)
+    .bind(key)
+    .execute(&mut **tx)
+    .await?;
+    if S::rows_affected(&recorded) == 0 {
+        return Ok(Transfer::Duplicate);
+    }
+
+    if let Some(from) = from {
+        let debited = sqlx::query(S::sql(
+            "UPDATE balances SET balance = balance - ?
+            WHERE guild_id = ? AND user_id = ? AND balance >= ?",
+        ))
+        .bind(amount)
+        .bind(guild.0 as i64)
+        .bind(from.0 as i64)
+        .bind(amount)
+        .execute(&mut **tx)
+        .await?;
+        if S::rows_affected(&debited) == 0 {
+            return Ok(Transfer::Insufficient);
+        }
+    }
+
+    if let Some(to) = to {
+        sqlx::query(
+            S::sql("INSERT INTO balances (guild_id, user_id, balance) VALUES (?, ?, ?)
+            ON CONFLICT (guild_id, user_id) DO UPDATE SET balance = balances.balance + excluded.balance"),
+        )
+        .bind(guild.0 as i64)
+        .bind(to.0 as i64)
+        .bind(amount)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(Transfer::Done)
+}
+
+/// An item members can buy with /shop buy
+#[derive(sqlx::FromRow)]
+pub struct ShopItem {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    pub price: i64,
+    pub description: Option<String>,
+    pub role_id: Option<i64>,
+    pub rental_secs: Option<i64>,
+}
+
+/// An item to add to the shop
+pub struct NewShopItem<'a> {
+    pub name: &'a str,
+    pub kind: &'a str,
+    pub price: i64,
+    pub description: Option<&'a str>,
+    pub role: Option<serenity::RoleId>,
+    pub rental: Option<Duration>,
+}
+
+/// An item in a member's inventory
+#[derive(sqlx::FromRow)]
+pub struct InventoryItem {
+    pub name: String,
+    pub kind: String,
+    pub role_id: Option<i64>,
+    pub quantity: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// Days in a row a member did an activity
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow)]
+pub struct Streak {
+    pub current: i64,
+    pub best: i64,
+    /// Last day counted, in the member's timezone
+    pub last_day: i64,
+}
+
+/// A canned response created with /tag create
+#[derive(sqlx::FromRow)]
+pub struct Tag {
+    pub name: String,
+    pub author_id: i64,
+    pub uses: i64,
+    pub created_at: i64,
+}
+
+/// XP of a member
+#[derive(sqlx::FromRow)]
+pub struct MemberXp {
+    pub user_id: i64,
+    pub xp: i64,
+}
+
+/// A role given automatically once a member reaches a level and membership age
+#[derive(sqlx::FromRow)]
+pub struct PromotionRule {
+    pub guild_id: i64,
+    pub role_id: i64,
+    pub min_level: i64,
+    pub min_days: i64,
+}
+
+/// A user that was banned recently
+#[derive(sqlx::FromRow)]
+pub struct RecentBan {
+    pub name: String,
+    pub invite_code: Option<String>,
+}
+
+/// How often an emoji was used in a reaction wall during one week
+#[derive(sqlx::FromRow)]
+pub struct WallStat {
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub week: i64,
+    pub emoji: String,
+    pub count: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct GuildConfigRow {
+    prefix: Option<String>,
+    log_channel_id: Option<i64>,
+    message_log_channel_id: Option<i64>,
+    warn_threshold: Option<i64>,
+    warn_timeout_secs: Option<i64>,
+    alt_threshold: Option<i64>,
+    alt_action: Option<String>,
+    image_hash_tolerance: Option<i64>,
+    review_channel_id: Option<i64>,
+    nsfw_threshold: Option<i64>,
+    level_channel_id: Option<i64>,
+    starboard_channel_id: Option<i64>,
+    starboard_threshold: Option<i64>,
+    welcome_channel_id: Option<i64>,
+    welcome_message: Option<String>,
+    goodbye_message: Option<String>,
+    onboarding_channel_id: Option<i64>,
+    voice_hub_channel_id: Option<i64>,
+    staff_role_id: Option<i64>,
+    house_edge: Option<i64>,
+    min_bet: Option<i64>,
+    max_bet: Option<i64>,
+    gambling_loss_limit: Option<i64>,
+    rob_chance: Option<i64>,
+    role_retention_secs: Option<i64>,
+    watch_channel_id: Option<i64>,
+    oncall_channel_id: Option<i64>,
+    boost_channel_id: Option<i64>,
+    partner_channel_id: Option<i64>,
+    partner_interval_secs: Option<i64>,
+    vote_delete_threshold: Option<i64>,
+    vote_delete_action: Option<String>,
+    vote_delete_emoji: Option<String>,
+    summary_limit: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PollRow {
+    id: i64,
+    guild_id: i64,
+    channel_id: i64,
+    message_id: Option<i64>,
+    author_id: i64,
+    question: String,
+    options: String,
+    closes_at: Option<i64>,
+    closed: bool,
+    results_posted: bool,
+}
+
+impl From<PollRow> for Poll {
+    fn from(row: PollRow) -> Self {
+        Poll {
+            id: row.id,
+            guild: serenity::GuildId(row.guild_id as u64),
+            channel: serenity::ChannelId(row.channel_id as u64),
+            message: row.message_id.map(|m| serenity::MessageId(m as u64)),
+            author: serenity::UserId(row.author_id as u64),
+            question: row.question,
+            options: row.options.lines().map(str::to_string).collect(),
+            closes_at: row.closes_at,
+            closed: row.closed,
+            results_posted: row.results_posted,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct GiveawayRow {
+    id: i64,
+    guild_id: i64,
+    channel_id: i64,
+    message_id: Option<i64>,
+    host_id: i64,
+    prize: String,
+    ends_at: i64,
+    ended: bool,
+    winner_id: Option<i64>,
+    announced: bool,
+}
+
+impl From<GiveawayRow> for Giveaway {
+    fn from(row: GiveawayRow) -> Self {
+        Giveaway {
+            id: row.id,
+            guild: serenity::GuildId(row.guild_id as u64),
+            channel: serenity::ChannelId(row.channel_id as u64),
+            message: row.message_id.map(|m| serenity::MessageId(m as u64)),
+            host: serenity::UserId(row.host_id as u64),
+            prize: row.prize,
+            ends_at: row.ends_at,
+            ended: row.ended,
+            winner: row.winner_id.map(|w| serenity::UserId(w as u64)),
+            announced: row.announced,
+        }
+    }
+}
+
+impl From<GuildConfigRow> for GuildConfig {
+    fn from(row: GuildConfigRow) -> Self {
+        GuildConfig {
+            prefix: row.prefix,
+            log_channel: row.log_channel_id.map(|id| serenity::ChannelId(id as u64)),
+            message_log_channel: row
+                .message_log_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            warn_threshold: row.warn_threshold.map(|t| t as u32),
+            warn_timeout: row.warn_timeout_secs.map(|t| Duration::from_secs(t as u64)),
+            alt_threshold: row.alt_threshold.map(|t| t as u32),
+            alt_action: row.alt_action.and_then(|a| a.parse().ok()),
+            image_hash_tolerance: row.image_hash_tolerance.map(|t| t as u32),
+            review_channel: row
+                .review_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            nsfw_threshold: row.nsfw_threshold.map(|t| t as u32),
+            level_channel: row
+                .level_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            starboard_channel: row
+                .starboard_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            starboard_threshold: row.starboard_threshold.map(|t| t as u32),
+            welcome_channel: row
+                .welcome_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            welcome_message: row.welcome_message,
+            goodbye_message: row.goodbye_message,
+            onboarding_channel: row
+                .onboarding_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            voice_hub_channel: row
+                .voice_hub_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            staff_role: row.staff_role_id.map(|id| serenity::RoleId(id as u64)),
+            house_edge: row.house_edge.map(|e| e as u32),
+            min_bet: row.min_bet,
+            max_bet: row.max_bet,
+            gambling_loss_limit: row.gambling_loss_limit,
+            rob_chance: row.rob_chance.map(|c| c as u32),
+            role_retention: row
+                .role_retention_secs
+                .map(|r| Duration::from_secs(r as u64)),
+            watch_channel: row
+                .watch_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            oncall_channel: row
+                .oncall_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            boost_channel: row
+                .boost_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            partner_channel: row
+                .partner_channel_id
+                .map(|id| serenity::ChannelId(id as u64)),
+            partner_interval: row
+                .partner_interval_secs
+                .map(|i| Duration::from_secs(i as u64)),
+            vote_delete_threshold: row.vote_delete_threshold.map(|t| t as u32),
+            vote_delete_action: row.vote_delete_action.and_then(|a| a.parse().ok()),
+            vote_delete_emoji: row.vote_delete_emoji,
+            summary_limit: row.summary_limit.map(|l| l as u32),
+            features: HashMap::new(),
+            channel_modes: HashMap::new(),
+            role_lists: HashMap::new(),
+            link_allowlist: HashSet::new(),
+            translation_links: HashMap::new(),
+            alt_text_channels: HashSet::new(),
+            toxicity_thresholds: HashMap::new(),
+        }
+    }
+}
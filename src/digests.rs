@@ -0,0 +1,315 @@
+// Daily digests
+// Guilds pick channels with /digest add, and once a day at the hour set with /digest setup the last
+// day of messages in them is fetched and a digest is posted: how many messages each channel got,
+// the most reacted messages and the most shared links. With `summarize` on and an LLM configured, a
+// summary of what was talked about is added, except in privacy mode. The slot the last digest was
+// for is stored, so restarting doesn't post one twice or skip one.
+use std::{cmp::Reverse, collections::HashMap, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Digest},
+    links,
+    llm::Llm,
+    mentions::{self, Mentions},
+    watchdog, Error,
+};
+
+/// Most channels a digest can cover, each costs requests for its history every day
+pub const MAX_CHANNELS: usize = 10;
+// How often we check whether a digest is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DAY_SECS: i64 = 24 * 60 * 60;
+// Busier channels are counted up to this many messages
+const MAX_MESSAGES: usize = 1000;
+// Messages fetched per request, the most Discord allows
+const PAGE_SIZE: u64 = 100;
+// Entries in the most reacted and top links lists
+const TOP: usize = 5;
+const PREVIEW_LENGTH: usize = 100;
+// Characters of the transcript sent for the summary, the most recent ones are kept
+const MAX_TRANSCRIPT_LENGTH: usize = 12_000;
+const SUMMARY_TOKENS: u32 = 500;
+const SUMMARY_PROMPT: &str = "You summarize a day of messages from a Discord server for its \
+    members. Write a few short bullet points on the main topics and anything decided or \
+    announced. Don't mention or quote individual members by name.";
+// Embed field values can't be longer than this
+const FIELD_LIMIT: usize = 1024;
+
+/// The unix timestamp of the latest time at `hour` UTC that isn't after `now`
+pub fn latest_slot(now: i64, hour: i64) -> i64 {
+    let slot = now - now.rem_euclid(DAY_SECS) + hour * 60 * 60;
+    if slot > now {
+        slot - DAY_SECS
+    } else {
+        slot
+    }
+}
+
+/// Links shared in the messages, the most shared first, with how often each was
+pub fn top_links<'a>(contents: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for content in contents {
+        for link in links::find_links(content) {
+            *counts.entry(link).or_default() += 1;
+        }
+    }
+
+    let mut links: Vec<_> = counts.into_iter().collect();
+    links.sort_unstable_by_key(|(link, count)| (Reverse(*count), *link));
+    links.truncate(TOP);
+    links
+}
+
+/// Starts the task that posts the digests
+pub fn spawn(ctx: serenity::Context, db: Db, llm: Option<Arc<Llm>>) {
+    watchdog::spawn("digests", async move {
+        loop {
+            if let Err(e) = post_digests(&ctx, &db, llm.as_deref()).await {
+                tracing::warn!("Error posting digests: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn post_digests(ctx: &serenity::Context, db: &Db, llm: Option<&Llm>) -> Result<(), Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    for digest in db.digests(None).await? {
+        let guild = serenity::GuildId(digest.guild_id as u64);
+        let slot = latest_slot(now, digest.hour);
+        if digest.last_posted_at.is_some_and(|at| at >= slot)
+            || !db.claim_digest(guild, slot).await?
+        {
+            continue;
+        }
+
+        // Not retried, a missed digest is only replaced by the next one
+        if let Err(e) = post_digest(ctx, db, llm, &digest, slot).await {
+            tracing::warn!(guild = guild.0, "Error posting digest: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn post_digest(
+    ctx: &serenity::Context,
+    db: &Db,
+    llm: Option<&Llm>,
+    digest: &Digest,
+    slot: i64,
+) -> Result<(), Error> {
+    let guild = serenity::GuildId(digest.guild_id as u64);
+    let since = slot - DAY_SECS;
+
+    let mut counts = Vec::new();
+    let mut messages = Vec::new();
+    for channel in db.digest_channels(guild).await? {
+        let (fetched, capped) = match fetch_since(ctx, channel, since).await {
+            Ok(fetched) => fetched,
+            // Deleted channels or ones we can't read anymore just aren't in the digest
+            Err(e) => {
+                tracing::warn!(
+                    channel = channel.0,
+                    "Error fetching messages for digest: {}",
+                    e
+                );
+                continue;
+            }
+        };
+        counts.push((channel, fetched.len(), capped));
+        messages.extend(fetched);
+    }
+    counts.sort_unstable_by_key(|(channel, count, _)| (Reverse(*count), *channel));
+
+    let activity = counts
+        .iter()
+        .map(|(channel, count, capped)| {
+            let plus = if *capped { "+" } else { "" };
+            format!("<#{}>: {}{}", channel.0, count, plus)
+        })
+        .collect::<Vec<_>>();
+
+    let mut reacted: Vec<_> = messages
+        .iter()
+        .map(|m| (m.reactions.iter().map(|r| r.count).sum::<u64>(), m))
+        .filter(|(reactions, _)| *reactions > 0)
+        .collect();
+    reacted.sort_unstable_by_key(|(reactions, m)| (Reverse(*reactions), m.id));
+    let reacted = reacted
+        .iter()
+        .take(TOP)
+        .map(|(reactions, m)| {
+            let preview: String = m.content.chars().take(PREVIEW_LENGTH).collect();
+            format!(
+                "[{} reactions]({}) by <@{}>: {}",
+                reactions,
+                m.link(),
+                m.author.id.0,
+                preview.replace('\n', " ")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let links = top_links(messages.iter().map(|m| m.content.as_str()))
+        .into_iter()
+        .map(|(link, count)| format!("{}x <{}>", count, link))
+        .collect::<Vec<_>>();
+
+    // Privacy mode keeps messages away from the LLM's API too
+    let summarize = digest.summarize && db.stores_content(guild).await?;
+    let summary = match llm {
+        Some(llm) if summarize && !messages.is_empty() => {
+            match llm
+                .complete(SUMMARY_PROMPT, &transcript(&messages), SUMMARY_TOKENS)
+                .await
+            {
+                Ok(summary) => Some(summary),
+                Err(e) => {
+                    tracing::warn!(guild = guild.0, "Error summarizing digest: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let channel = serenity::ChannelId(digest.channel_id as u64);
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title("Daily digest")
+                .field(
+                    format!("Messages: {}", messages.len()),
+                    fit(activity, "Nothing was said"),
+                    false,
+                )
+                .field("Most reacted", fit(reacted, "No reactions"), false)
+                .field("Top links", fit(links, "No links"), false)
+                .footer(|f| f.text("Since"))
+                .timestamp(
+                    serenity::Timestamp::from_unix_timestamp(since)
+                        .unwrap_or_else(|_| serenity::Timestamp::now()),
+                );
+            if let Some(summary) = &summary {
+                e.description(summary.chars().take(4000).collect::<String>());
+            }
+            e
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+// Messages of members sent after `since`, newest first, and whether there were more. Bot
+// messages count towards the pages fetched so bot-heavy channels can't take more requests
+async fn fetch_since(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    since: i64,
+) -> Result<(Vec<serenity::Message>, bool), Error> {
+    let mut messages = Vec::new();
+    let mut before = None;
+
+    for _ in 0..MAX_MESSAGES / PAGE_SIZE as usize {
+        let page = channel
+            .messages(&ctx.http, |r| {
+                if let Some(before) = before {
+                    r.before(before);
+                }
+                r.limit(PAGE_SIZE)
+            })
+            .await?;
+        let full = page.len() == PAGE_SIZE as usize;
+        before = page.last().map(|m| m.id);
+
+        for message in page {
+            if message.timestamp.unix_timestamp() < since {
+                return Ok((messages, false));
+            }
+            if !message.author.bot {
+                messages.push(message);
+            }
+        }
+
+        if !full {
+            return Ok((messages, false));
+        }
+    }
+
+    Ok((messages, true))
+}
+
+// The messages as chat lines oldest first, cut at the start to fit
+fn transcript(messages: &[serenity::Message]) -> String {
+    let mut messages: Vec<_> = messages.iter().filter(|m| !m.content.is_empty()).collect();
+    messages.sort_unstable_by_key(|m| m.id);
+
+    let mut lines = Vec::new();
+    let mut length = 0;
+    for message in messages.iter().rev() {
+        let line = format!("{}: {}", message.author.name, message.content);
+        length += line.len() + 1;
+        if length > MAX_TRANSCRIPT_LENGTH {
+            break;
+        }
+        lines.push(line);
+    }
+    lines.reverse();
+    lines.join("\n")
+}
+
+// Joins lines for an embed field, leaving out those that don't fit
+fn fit(lines: Vec<String>, empty: &str) -> String {
+    if lines.is_empty() {
+        return empty.to_string();
+    }
+
+    let mut value = String::new();
+    for line in lines {
+        if value.len() + line.len() + 1 > FIELD_LIMIT {
+            break;
+        }
+        value.push_str(&line);
+        value.push('\n');
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_the_last_time_at_the_hour() {
+        // 2024-01-02 15:30 UTC
+        let now = 1_704_209_400;
+        let midnight = 1_704_153_600;
+        assert_eq!(latest_slot(now, 9), midnight + 9 * 60 * 60);
+        assert_eq!(latest_slot(now, 15), midnight + 15 * 60 * 60);
+        assert_eq!(latest_slot(now, 16), midnight - DAY_SECS + 16 * 60 * 60);
+        assert_eq!(latest_slot(midnight, 0), midnight);
+    }
+
+    #[test]
+    fn the_most_shared_links_come_first() {
+        let contents = [
+            "see https://a.example and <https://b.example>",
+            "https://b.example",
+            "no links here",
+            "https://c.example.",
+        ];
+        assert_eq!(
+            top_links(contents),
+            [
+                ("https://b.example", 2),
+                ("https://a.example", 1),
+                ("https://c.example", 1)
+            ]
+        );
+    }
+}
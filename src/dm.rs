@@ -0,0 +1,221 @@
+// Sending DMs with a fallback for users who have them closed
+// Each feature that DMs users picks what happens when the DM can't be delivered.
+// The default can be overridden with DM_FALLBACK_<FEATURE> set to "channel", "ping", "retry"
+// or "drop", e.g. DM_FALLBACK_RATE_LIMIT_NOTICE=retry
+use std::{
+    env, fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::{self, Mentions},
+    retry::{retry, RetryPolicy},
+};
+
+// How long a queued DM waits before its one retry
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+// Channel notices are removed after this so they don't clutter the channel
+const NOTICE_LIFETIME: Duration = Duration::from_secs(10);
+
+/// What to do when a DM fails
+#[derive(Clone, Copy)]
+pub enum Fallback {
+    /// Post the message in the channel the user was active in, then delete it
+    ChannelNotice,
+    /// Post the message in the channel and leave it there
+    ChannelMessage,
+    /// Try sending the DM once more later
+    RetryLater,
+    /// Give up, for messages that are only useful right away
+    Drop,
+}
+
+/// A feature that sends DMs, with its default fallback strategy
+pub struct Feature {
+    pub name: &'static str,
+    pub default_fallback: Fallback,
+}
+
+impl Feature {
+    fn fallback(&self) -> Fallback {
+        let key = format!("DM_FALLBACK_{}", self.name.to_uppercase());
+
+        match env::var(key).as_deref() {
+            Ok("channel") => Fallback::ChannelNotice,
+            Ok("ping") => Fallback::ChannelMessage,
+            Ok("retry") => Fallback::RetryLater,
+            Ok("drop") => Fallback::Drop,
+            _ => self.default_fallback,
+        }
+    }
+}
+
+/// Warning sent to users who hit the message rate limit
+pub const RATE_LIMIT_NOTICE: Feature = Feature {
+    name: "rate_limit_notice",
+    default_fallback: Fallback::ChannelNotice,
+};
+
+/// Reason sent to members who get kicked, banned or timed out
+/// Posting it in a channel would make the reason public, so it's dropped instead
+pub const MODERATION_NOTICE: Feature = Feature {
+    name: "moderation_notice",
+    default_fallback: Fallback::Drop,
+};
+
+/// Reminders set with /remind, they would be lost if the channel copy was deleted
+pub const REMINDER: Feature = Feature {
+    name: "reminder",
+    default_fallback: Fallback::ChannelMessage,
+};
+
+/// Outcome of an application from /apply
+/// The fallback channel is the review channel, which applicants usually can't see
+pub const APPLICATION_RESULT: Feature = Feature {
+    name: "application_result",
+    default_fallback: Fallback::Drop,
+};
+
+/// Keyword notifications from /notify
+/// Posting them in the channel would tell everyone which keywords the user follows
+pub const KEYWORD_NOTIFICATION: Feature = Feature {
+    name: "keyword_notification",
+    default_fallback: Fallback::Drop,
+};
+
+/// Deliverability counters, shown in /diagnostics
+#[derive(Default)]
+pub struct DmStats {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    fallbacks: AtomicU64,
+}
+
+impl fmt::Display for DmStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} delivered, {} failed, {} fallbacks",
+            self.delivered.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+            self.fallbacks.load(Ordering::Relaxed)
+        )
+    }
+}
+
+/// DMs `user`, falling back according to `feature` if that fails. Returns false if the message
+/// didn't reach them either way and won't be retried
+pub async fn send(
+    ctx: &serenity::Context,
+    stats: &Arc<DmStats>,
+    feature: &Feature,
+    user: &serenity::User,
+    channel: serenity::ChannelId,
+    content: &str,
+) -> bool {
+    // Closed DMs fail straight away, only Discord having trouble is retried
+    let error = match retry(&RetryPolicy::DEFAULT, || {
+        user.dm(&ctx.http, |m| m.content(content))
+    })
+    .await
+    {
+        Ok(_) => {
+            stats.delivered.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        Err(e) => e,
+    };
+
+    stats.failed.fetch_add(1, Ordering::Relaxed);
+    tracing::warn!(
+        feature = feature.name,
+        user = user.id.0,
+        "Error sending DM: {}",
+        error
+    );
+
+    match feature.fallback() {
+        Fallback::Drop => false,
+        Fallback::ChannelNotice => {
+            stats.fallbacks.fetch_add(1, Ordering::Relaxed);
+            channel_notice(ctx, channel, user.id, content).await
+        }
+        Fallback::ChannelMessage => {
+            stats.fallbacks.fetch_add(1, Ordering::Relaxed);
+
+            // The content can be user written, like a reminder, so only the user is pinged
+            let result = mentions::send_message(&ctx.http, channel, Mentions::User(user.id), |m| {
+                m.content(format!("<@{}> {}", user.id.0, content))
+            })
+            .await;
+            match result {
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::warn!("Error sending channel message: {}", e);
+                    false
+                }
+            }
+        }
+        Fallback::RetryLater => {
+            stats.fallbacks.fetch_add(1, Ordering::Relaxed);
+
+            let ctx = ctx.clone();
+            let stats = Arc::clone(stats);
+            let user = user.clone();
+            let content = content.to_string();
+            let name = feature.name;
+
+            tokio::spawn(async move {
+                tokio::time::sleep(RETRY_DELAY).await;
+
+                match user.dm(&ctx.http, |m| m.content(content)).await {
+                    Ok(_) => stats.delivered.fetch_add(1, Ordering::Relaxed),
+                    Err(e) => {
+                        tracing::warn!(
+                            feature = name,
+                            user = user.id.0,
+                            "Retried DM failed: {}",
+                            e
+                        );
+                        stats.failed.fetch_add(1, Ordering::Relaxed)
+                    }
+                };
+            });
+
+            true
+        }
+    }
+}
+
+async fn channel_notice(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    user: serenity::UserId,
+    content: &str,
+) -> bool {
+    let notice = mentions::send_message(&ctx.http, channel, Mentions::User(user), |m| {
+        m.content(format!("<@{}> {}", user.0, content))
+    })
+    .await;
+
+    match notice {
+        Ok(notice) => {
+            let http = Arc::clone(&ctx.http);
+            tokio::spawn(async move {
+                tokio::time::sleep(NOTICE_LIFETIME).await;
+                let _ = notice.delete(&http).await;
+            });
+            true
+        }
+        Err(e) => {
+            tracing::warn!("Error sending channel notice: {}", e);
+            false
+        }
+    }
+}
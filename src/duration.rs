@@ -0,0 +1,95 @@
+// Parsing of human friendly durations like `10m`, `1h30m` or `2d`
+use std::time::Duration;
+
+/// Discord doesn't allow timeouts longer than 28 days
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// Parses a duration made of `<number><unit>` parts, units being s, m, h, d and w
+pub fn parse(input: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+
+        let value: u64 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    // Trailing numbers without a unit are ambiguous, and zero durations are useless
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(total))
+}
+
+/// Formats a duration the same way `parse` reads it, e.g. `1d2h`
+pub fn format(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    let mut out = String::new();
+
+    for (unit, size) in [
+        ("w", 604800),
+        ("d", 86400),
+        ("h", 3600),
+        ("m", 60),
+        ("s", 1),
+    ] {
+        if secs >= size {
+            out.push_str(&format!("{}{}", secs / size, unit));
+            secs %= size;
+        }
+    }
+
+    if out.is_empty() {
+        out.push_str("0s");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_parsed_from_parts() {
+        assert_eq!(parse("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse(" 1h30m "), Some(Duration::from_secs(5400)));
+        assert_eq!(parse("1W2D"), Some(Duration::from_secs(9 * 86400)));
+        assert_eq!(parse("90s"), Some(Duration::from_secs(90)));
+
+        // No unit, unknown units, nothing at all or nothing to wait
+        assert_eq!(parse("10"), None);
+        assert_eq!(parse("1h30"), None);
+        assert_eq!(parse("5y"), None);
+        assert_eq!(parse("m"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("0m"), None);
+        assert_eq!(parse("99999999999999999999w"), None);
+    }
+
+    #[test]
+    fn formatted_durations_parse_back() {
+        assert_eq!(format(Duration::from_secs(93784)), "1d2h3m4s");
+        assert_eq!(format(Duration::ZERO), "0s");
+        for secs in [1, 59, 3600, 90061, 28 * 86400] {
+            let duration = Duration::from_secs(secs);
+            assert_eq!(parse(&format(duration)), Some(duration));
+        }
+    }
+}
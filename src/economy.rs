@@ -0,0 +1,140 @@
+// Economy
+// Members of each guild have a balance of coins, earned with /daily and sent to each other with
+// /pay. Every change goes through `Db::transfer`, which writes it to the ledger and moves the
+// coins in one transaction. A balance is only debited if it covers the amount, so two commands
+// racing can't overdraw it, and each transfer has a key, usually the ID of the command
+// invocation, so one that's retried can't create or destroy coins a second time.
+use poise::serenity_prelude as serenity;
+
+use crate::Context;
+
+/// Name of the currency, after amounts
+pub const CURRENCY: &str = "coins";
+// Seconds in a day, days start at midnight UTC
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// What became of a transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    Done,
+    /// One with the same key was made already, nothing changed
+    Duplicate,
+    /// The sender doesn't have enough coins, nothing changed
+    Insufficient,
+}
+
+/// An amount of coins for messages
+pub fn format(amount: i64) -> String {
+    format!("**{}** {}", amount, CURRENCY)
+}
+
+/// Key for a transfer done by a command, `purpose` tells apart several transfers of one
+/// invocation
+pub fn invocation_key(ctx: Context<'_>, purpose: &str) -> String {
+    format!("{}:{}", purpose, ctx.id())
+}
+
+/// Key for a transfer that can only happen once per member on `day`
+pub fn daily_key(
+    purpose: &str,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    day: i64,
+) -> String {
+    format!("{}:{}:{}:{}", purpose, guild.0, user.0, day)
+}
+
+/// Days since the unix epoch
+pub fn today() -> i64 {
+    serenity::Timestamp::now().unix_timestamp() / DAY_SECS
+}
+
+/// Unix timestamp of the last midnight UTC
+pub fn day_start() -> i64 {
+    today() * DAY_SECS
+}
+
+/// Unix timestamp of the next midnight UTC
+pub fn tomorrow() -> i64 {
+    (today() + 1) * DAY_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    const GUILD: serenity::GuildId = serenity::GuildId(1);
+    const ALICE: serenity::UserId = serenity::UserId(2);
+    const BOB: serenity::UserId = serenity::UserId(3);
+
+    #[tokio::test]
+    async fn transfers_apply_once_per_key() {
+        let db = Db::memory().await;
+
+        let minted = db
+            .transfer(GUILD, None, Some(ALICE), 100, "test", "a")
+            .await;
+        assert_eq!(minted.unwrap(), Transfer::Done);
+        let again = db
+            .transfer(GUILD, None, Some(ALICE), 100, "test", "a")
+            .await;
+        assert_eq!(again.unwrap(), Transfer::Duplicate);
+
+        let paid = db
+            .transfer(GUILD, Some(ALICE), Some(BOB), 30, "test", "b")
+            .await;
+        assert_eq!(paid.unwrap(), Transfer::Done);
+        assert_eq!(db.balance(GUILD, ALICE).await.unwrap(), 70);
+        assert_eq!(db.balance(GUILD, BOB).await.unwrap(), 30);
+    }
+
+    #[tokio::test]
+    async fn balances_never_go_negative() {
+        let db = Db::memory().await;
+        db.transfer(GUILD, None, Some(ALICE), 50, "test", "mint")
+            .await
+            .unwrap();
+
+        // Every payment would fit on its own, only two fit together
+        let payments = (0..10).map(|i| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let key = format!("pay-{}", i);
+                db.transfer(GUILD, Some(ALICE), Some(BOB), 20, "test", &key)
+                    .await
+                    .unwrap()
+            })
+        });
+        let mut done = 0;
+        for payment in payments.collect::<Vec<_>>() {
+            if payment.await.unwrap() == Transfer::Done {
+                done += 1;
+            }
+        }
+
+        assert_eq!(done, 2);
+        assert_eq!(db.balance(GUILD, ALICE).await.unwrap(), 10);
+        assert_eq!(db.balance(GUILD, BOB).await.unwrap(), 40);
+    }
+
+    #[tokio::test]
+    async fn failed_transfers_leave_no_trace() {
+        let db = Db::memory().await;
+
+        let spent = db
+            .transfer(GUILD, Some(ALICE), None, 5, "test", "spend")
+            .await;
+        assert_eq!(spent.unwrap(), Transfer::Insufficient);
+        db.transfer(GUILD, None, Some(ALICE), 5, "test", "mint")
+            .await
+            .unwrap();
+
+        // Rolled back with the debit, so the same key works once there are coins
+        let spent = db
+            .transfer(GUILD, Some(ALICE), None, 5, "test", "spend")
+            .await;
+        assert_eq!(spent.unwrap(), Transfer::Done);
+        assert_eq!(db.balance(GUILD, ALICE).await.unwrap(), 0);
+    }
+}
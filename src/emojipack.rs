@@ -0,0 +1,315 @@
+// Emoji packs
+// /emojipack export downloads every custom emoji of a guild into zip files, and
+// /emojipack import uploads the images of such a zip to another guild. Downloads from the CDN
+// run a few at a time and are retried when rate limited, uploads go through serenity, which
+// waits for Discord's rate limits itself. Imports check every file before uploading it and
+// report what was skipped and why, so a pack with a few bad files still mostly works.
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use poise::{
+    futures_util::{stream, StreamExt},
+    serenity_prelude as serenity,
+};
+
+use crate::{
+    retry::{retry, RetryPolicy},
+    worker::{self, Worker},
+    zip, Error,
+};
+
+/// Biggest image Discord accepts for an emoji
+pub const MAX_EMOJI_BYTES: usize = 256 * 1024;
+/// Biggest zip the bot downloads for an import
+pub const MAX_IMPORT_BYTES: u64 = 25 * 1024 * 1024;
+// Exports are split into zips no bigger than what a bot can upload without boosts
+const MAX_EXPORT_BYTES: usize = 8 * 1024 * 1024;
+// Discord allows this many files per message
+pub const MAX_EXPORT_FILES: usize = 10;
+const CONCURRENT_DOWNLOADS: usize = 4;
+// A hanging download would keep the command waiting until Discord drops the interaction
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_NAME_LENGTH: usize = 2;
+const MAX_NAME_LENGTH: usize = 32;
+
+/// What to do when an imported emoji has the name of an existing one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Collision {
+    /// Keep the existing emoji and skip the new one
+    #[name = "skip"]
+    Skip,
+    /// Add the new emoji with a number after its name
+    #[name = "rename"]
+    Rename,
+    /// Delete the existing emoji and add the new one
+    #[name = "replace"]
+    Replace,
+}
+
+/// The zips of an export
+pub struct Export {
+    pub archives: Vec<Vec<u8>>,
+    pub exported: usize,
+    /// Emojis that couldn't be downloaded
+    pub failed: Vec<String>,
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .expect("Failed to build emoji pack HTTP client")
+}
+
+/// Downloads a zip uploaded for an import
+pub async fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let response = client().get(url).send().await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Downloads every custom emoji of a guild, named after the emoji with the extension of its
+/// format. The zips are built on the worker if there is one
+pub async fn export(
+    http: &serenity::Http,
+    guild: serenity::GuildId,
+    worker: Option<&Worker>,
+) -> Result<Export, Error> {
+    let emojis = guild.emojis(http).await?;
+    let client = client();
+
+    let downloads: Vec<_> = stream::iter(emojis)
+        .map(|emoji| {
+            let client = &client;
+            async move {
+                let data = retry(&RetryPolicy::DEFAULT, || async {
+                    let response = client.get(emoji.url()).send().await?.error_for_status()?;
+                    response.bytes().await
+                })
+                .await;
+                (emoji, data)
+            }
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .collect()
+        .await;
+
+    let mut names = HashSet::new();
+    let mut entries = Vec::new();
+    let mut failed = Vec::new();
+    for (emoji, data) in downloads {
+        match data {
+            Ok(data) => {
+                // Guilds can have several emojis with the same name
+                let name = free_name(&emoji.name, |n| names.contains(n));
+                names.insert(name.clone());
+                let extension = if emoji.animated { "gif" } else { "png" };
+                entries.push(zip::Entry {
+                    name: format!("{}.{}", name, extension),
+                    data: data.to_vec(),
+                });
+            }
+            Err(e) => {
+                tracing::debug!(emoji = emoji.id.0, "Error downloading emoji: {}", e);
+                failed.push(emoji.name);
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    let exported = entries.len();
+
+    // Splits the emojis into zips that stay under the upload limit
+    let mut archives = Vec::new();
+    let mut chunk: Vec<zip::Entry> = Vec::new();
+    let mut chunk_size = 0;
+    for entry in entries {
+        // Headers take about twice the name and 76 bytes
+        let size = entry.data.len() + 2 * entry.name.len() + 76;
+        if !chunk.is_empty() && chunk_size + size > MAX_EXPORT_BYTES {
+            archives.push(worker::zip(worker, std::mem::take(&mut chunk)).await?);
+            chunk_size = 0;
+        }
+        chunk_size += size;
+        chunk.push(entry);
+    }
+    if !chunk.is_empty() {
+        archives.push(worker::zip(worker, chunk).await?);
+    }
+
+    Ok(Export {
+        archives,
+        exported,
+        failed,
+    })
+}
+
+/// What an import did
+#[derive(Default)]
+pub struct Import {
+    pub imported: Vec<String>,
+    /// File names with why they were skipped
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Emoji slots per boost tier, animated and static emojis have separate slots
+fn emoji_slots(tier: serenity::PremiumTier) -> usize {
+    match tier {
+        serenity::PremiumTier::Tier1 => 100,
+        serenity::PremiumTier::Tier2 => 150,
+        serenity::PremiumTier::Tier3 => 250,
+        _ => 50,
+    }
+}
+
+/// Uploads the images of a zip as emojis, named after their files
+pub async fn import(
+    http: &serenity::Http,
+    guild: serenity::GuildId,
+    archive: &[u8],
+    collision: Collision,
+) -> Result<Import, Error> {
+    let mut archive = zip::Archive::new(archive)?;
+    let files = archive.files()?;
+    let slots = emoji_slots(guild.to_partial_guild(http).await?.premium_tier);
+    let mut existing: HashMap<String, serenity::Emoji> = guild
+        .emojis(http)
+        .await?
+        .into_iter()
+        .map(|e| (e.name.clone(), e))
+        .collect();
+    let mut used_static = existing.values().filter(|e| !e.animated).count();
+    let mut used_animated = existing.len() - used_static;
+
+    let mut report = Import::default();
+    for file in files {
+        let path = file.name.clone();
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+        // Metadata macOS adds to zips it creates
+        if path.starts_with("__MACOSX/") || file_name.starts_with('.') {
+            continue;
+        }
+        let mut skip = |reason: &str| report.skipped.push((path.clone(), reason.to_string()));
+
+        let (stem, extension) = match file_name.rsplit_once('.') {
+            Some((stem, extension)) => (stem, extension.to_lowercase()),
+            None => {
+                skip("not an image");
+                continue;
+            }
+        };
+        let mime = match extension.as_str() {
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "jpg" | "jpeg" => "image/jpeg",
+            "webp" => "image/webp",
+            _ => {
+                skip("not a PNG, GIF, JPEG or WebP image");
+                continue;
+            }
+        };
+        // Static GIFs end up as static emojis, assuming they're animated at worst refuses one
+        // that would have fit
+        let animated = mime == "image/gif";
+
+        if file.size > MAX_EMOJI_BYTES as u64 {
+            skip("bigger than 256 KB");
+            continue;
+        }
+        let data = match archive.extract(&file, MAX_EMOJI_BYTES) {
+            Ok(data) => data,
+            Err(e) => {
+                skip(&e.to_string());
+                continue;
+            }
+        };
+
+        let mut name = match sanitize_name(stem) {
+            Some(name) => name,
+            None => {
+                skip("name needs 2 letters, digits or underscores");
+                continue;
+            }
+        };
+
+        let mut replacing = None;
+        if existing.contains_key(&name) {
+            match collision {
+                Collision::Skip => {
+                    skip("an emoji with that name exists");
+                    continue;
+                }
+                Collision::Rename => name = free_name(&name, |n| existing.contains_key(n)),
+                Collision::Replace => replacing = existing.get(&name),
+            }
+        }
+
+        // Replacing frees a slot, but only one of the same kind
+        let replaced_slot = replacing.is_some_and(|e| e.animated == animated);
+        let used = if animated { used_animated } else { used_static };
+        if used >= slots && !replaced_slot {
+            skip(if animated {
+                "no animated emoji slots left"
+            } else {
+                "no emoji slots left"
+            });
+            continue;
+        }
+
+        if let Some(old) = replacing {
+            if let Err(e) = guild.delete_emoji(http, old.id).await {
+                skip(&format!("couldn't delete the existing emoji: {}", e));
+                continue;
+            }
+            if old.animated {
+                used_animated -= 1;
+            } else {
+                used_static -= 1;
+            }
+            existing.remove(&name);
+        }
+
+        let image = format!("data:{};base64,{}", mime, base64::encode(&data));
+        match guild.create_emoji(http, &name, &image).await {
+            Ok(emoji) => {
+                if emoji.animated {
+                    used_animated += 1;
+                } else {
+                    used_static += 1;
+                }
+                report.imported.push(emoji.name.clone());
+                existing.insert(emoji.name.clone(), emoji);
+            }
+            Err(e) => skip(&e.to_string()),
+        }
+    }
+
+    Ok(report)
+}
+
+// Keeps the characters Discord allows in emoji names
+fn sanitize_name(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .take(MAX_NAME_LENGTH)
+        .collect();
+
+    (name.len() >= MIN_NAME_LENGTH).then_some(name)
+}
+
+// The name with the lowest number after it that isn't taken, or the name itself if it's free
+fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+
+    (2..)
+        .map(|i| {
+            let suffix = format!("_{}", i);
+            let stem: String = name.chars().take(MAX_NAME_LENGTH - suffix.len()).collect();
+            stem + &suffix
+        })
+        .find(|n| !taken(n))
+        .unwrap()
+}
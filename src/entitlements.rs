@@ -0,0 +1,270 @@
+// Premium through Discord app subscriptions
+// With PREMIUM_SKU_ID set to the SKU of the app's subscription, guilds subscribed to it and
+// members subscribed themselves get the premium tier of quotas. Entitlements are mirrored into
+// the database: all of them are fetched once at startup, and the ENTITLEMENT_CREATE, _UPDATE and
+// _DELETE gateway events keep them up to date after that. serenity doesn't know these events,
+// so they arrive as unknown events with their raw payload. It doesn't keep the entitlements
+// interactions come with either, which is why they aren't read from there.
+use std::{env, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+
+use crate::{
+    db::{Db, Entitlement},
+    watchdog, Error,
+};
+
+const API_URL: &str = "https://discord.com/api/v10";
+// Entitlements fetched per request, the most Discord allows
+const PAGE_SIZE: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Premium {
+    /// The SKU that unlocks premium
+    pub sku: i64,
+    application: serenity::UserId,
+    token: String,
+    client: reqwest::Client,
+}
+
+impl Premium {
+    /// Sets up premium if PREMIUM_SKU_ID is set, a bot's application has the bot's user ID
+    pub fn from_env(application: serenity::UserId) -> Option<Arc<Self>> {
+        let sku = match env::var("PREMIUM_SKU_ID").ok()?.parse() {
+            Ok(sku) => sku,
+            Err(e) => {
+                tracing::warn!("Ignoring invalid PREMIUM_SKU_ID: {}", e);
+                return None;
+            }
+        };
+
+        Some(Arc::new(Self {
+            sku,
+            application,
+            token: env::var("DISCORD_TOKEN").ok()?,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build entitlements HTTP client"),
+        }))
+    }
+
+    /// The guild's and the user's entitlements to premium that are active right now
+    pub async fn active(
+        &self,
+        db: &Db,
+        guild: Option<serenity::GuildId>,
+        user: Option<serenity::UserId>,
+    ) -> Result<Vec<Entitlement>, Error> {
+        let now = serenity::Timestamp::now().unix_timestamp();
+        db.active_entitlements(self.sku, guild, user, now).await
+    }
+
+    // Every entitlement of the app that hasn't ended
+    async fn fetch_all(&self) -> Result<Vec<Entitlement>, Error> {
+        let mut entitlements = Vec::new();
+        let mut after = 0;
+
+        loop {
+            let page: Vec<Value> = self
+                .client
+                .get(format!(
+                    "{}/applications/{}/entitlements",
+                    API_URL, self.application.0
+                ))
+                .header("Authorization", format!("Bot {}", self.token))
+                .query(&[
+                    ("exclude_ended", "true".to_string()),
+                    ("limit", PAGE_SIZE.to_string()),
+                    ("after", after.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let full = page.len() == PAGE_SIZE;
+
+            for value in &page {
+                match parse(value) {
+                    Some(entitlement) => entitlements.push(entitlement),
+                    None => tracing::warn!(
+                        id = value["id"].as_str(),
+                        "Ignoring entitlement that couldn't be read"
+                    ),
+                }
+            }
+
+            if !full {
+                return Ok(entitlements);
+            }
+            // Entitlements that couldn't be read still move on to the next page
+            after = match next_page(&page, after) {
+                Some(next) => next,
+                None => return Err("Entitlement page without readable IDs".into()),
+            };
+        }
+    }
+}
+
+// The `after` for the page following `page`, None if it wouldn't move on from `after`
+fn next_page(page: &[Value], after: i64) -> Option<i64> {
+    page.iter()
+        .filter_map(|value| value["id"].as_str()?.parse::<i64>().ok())
+        .max()
+        .filter(|&last| last > after)
+}
+
+/// Reads an entitlement out of an entitlement object from Discord
+pub fn parse(value: &Value) -> Option<Entitlement> {
+    let id = |key: &str| value[key].as_str()?.parse::<i64>().ok();
+    let ends_at = match value["ends_at"].as_str() {
+        Some(ends_at) => Some(serenity::Timestamp::parse(ends_at).ok()?.unix_timestamp()),
+        None => None,
+    };
+
+    Some(Entitlement {
+        id: id("id")?,
+        sku_id: id("sku_id")?,
+        user_id: id("user_id"),
+        guild_id: id("guild_id"),
+        ends_at,
+    })
+}
+
+/// Keeps the stored entitlements up to date, for events serenity passes on as unknown
+pub async fn handle_event(db: &Db, name: &str, raw: &Value) -> Result<(), Error> {
+    if !name.starts_with("ENTITLEMENT_") {
+        return Ok(());
+    }
+    let entitlement = match parse(raw) {
+        Some(entitlement) => entitlement,
+        None => {
+            tracing::warn!(
+                event = name,
+                "Ignoring entitlement event that couldn't be read"
+            );
+            return Ok(());
+        }
+    };
+
+    // Refunded or removed entitlements are deleted, ones that ran out are updated with an end
+    if name == "ENTITLEMENT_DELETE" || raw["deleted"].as_bool() == Some(true) {
+        db.delete_entitlement(entitlement.id).await?;
+    } else {
+        db.save_entitlement(&entitlement).await?;
+    }
+    tracing::info!(
+        event = name,
+        sku = entitlement.sku_id,
+        guild = entitlement.guild_id,
+        user = entitlement.user_id,
+        "Entitlement changed"
+    );
+
+    Ok(())
+}
+
+/// Starts the task that fetches the entitlements, for ones changed while the bot was offline
+pub fn spawn_sync(db: Db, premium: Arc<Premium>) {
+    watchdog::spawn("entitlement sync", async move {
+        let result = match premium.fetch_all().await {
+            Ok(entitlements) => db.replace_entitlements(&entitlements).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Error syncing entitlements: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn entitlements_are_read_from_discord_objects() {
+        let subscription = json!({
+            "id": "1019653849998299136",
+            "sku_id": "1019475255913222144",
+            "application_id": "1019370614521200640",
+            "guild_id": "1015034326372454400",
+            "type": 8,
+            "deleted": false,
+            "starts_at": "2024-01-02T15:30:00.000000+00:00",
+            "ends_at": "2024-02-02T15:30:00.000000+00:00"
+        });
+        assert_eq!(
+            parse(&subscription),
+            Some(Entitlement {
+                id: 1019653849998299136,
+                sku_id: 1019475255913222144,
+                user_id: None,
+                guild_id: Some(1015034326372454400),
+                ends_at: Some(1_706_887_800),
+            })
+        );
+
+        // Test entitlements have no dates
+        let test = json!({ "id": "1", "sku_id": "2", "user_id": "3", "type": 4 });
+        assert_eq!(parse(&test).unwrap().ends_at, None);
+        assert_eq!(parse(&test).unwrap().user_id, Some(3));
+
+        assert_eq!(parse(&json!({ "sku_id": "2" })), None);
+        assert_eq!(
+            parse(&json!({ "id": "1", "sku_id": "2", "ends_at": "soon" })),
+            None
+        );
+    }
+
+    #[test]
+    fn pages_move_on_past_unreadable_entitlements() {
+        let unreadable = |id: &str| json!({ "id": id, "sku_id": "2", "ends_at": "soon" });
+        let page = [unreadable("5"), unreadable("9"), json!({ "sku_id": "2" })];
+        assert_eq!(next_page(&page, 0), Some(9));
+        assert_eq!(next_page(&page, 9), None);
+        assert_eq!(next_page(&[], 0), None);
+    }
+
+    #[tokio::test]
+    async fn only_active_entitlements_to_the_sku_count() {
+        let db = Db::memory().await;
+        let entitlement = |id, sku, guild, user, ends_at| Entitlement {
+            id,
+            sku_id: sku,
+            user_id: user,
+            guild_id: guild,
+            ends_at,
+        };
+        let (guild, user) = (serenity::GuildId(10), serenity::UserId(20));
+
+        db.replace_entitlements(&[
+            entitlement(1, 5, Some(10), None, Some(2000)),
+            // Another SKU, another guild, ended
+            entitlement(2, 6, Some(10), None, None),
+            entitlement(3, 5, Some(11), None, None),
+            entitlement(4, 5, None, Some(20), Some(500)),
+        ])
+        .await
+        .unwrap();
+        let ids =
+            |entitlements: Vec<Entitlement>| entitlements.iter().map(|e| e.id).collect::<Vec<_>>();
+
+        let active = db.active_entitlements(5, Some(guild), Some(user), 1000);
+        assert_eq!(ids(active.await.unwrap()), [1]);
+
+        // Renewed
+        db.save_entitlement(&entitlement(4, 5, None, Some(20), Some(3000)))
+            .await
+            .unwrap();
+        let active = db.active_entitlements(5, None, Some(user), 1000);
+        assert_eq!(ids(active.await.unwrap()), [4]);
+
+        db.delete_entitlement(1).await.unwrap();
+        let active = db.active_entitlements(5, Some(guild), None, 1000);
+        assert!(active.await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,361 @@
+// FAQ answers
+// Staff add answers to frequently asked questions with /faq add, matched by keywords, a regex or
+// both. Messages in help channels and their threads are scored against every FAQ of the guild:
+// a regex match is certain, otherwise the confidence is the share of the FAQ's keywords the
+// message has. The answer of the best FAQ above its minimum confidence is sent with buttons
+// asking whether it helped, and the asker's answers are counted so /faq list shows which FAQs
+// match well. A FAQ isn't answered twice in a channel within COOLDOWN, so a busy help channel
+// doesn't get the same answer to every follow-up.
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::{ChannelMode, GuildCache},
+    db::{Db, Faq},
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Most FAQs a guild can have, every message in help channels is scored against all of them
+pub const MAX_FAQS: usize = 50;
+const BUTTON_PREFIX: &str = "faq";
+const COOLDOWN: Duration = Duration::from_secs(5 * 60);
+// Keeps a single regex from taking a lot of memory, matching is linear time either way
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// The words of a text in lowercase, which is what keywords are matched against
+pub fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Compiles a FAQ's regex the way it's matched, ignoring case
+pub fn compile_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// A FAQ ready to be matched against messages
+pub struct CompiledFaq {
+    pub id: i64,
+    pub question: String,
+    pub answer: String,
+    keywords: Vec<String>,
+    regex: Option<Regex>,
+    min_confidence: f64,
+}
+
+impl CompiledFaq {
+    /// Fails for invalid or too big regexes
+    pub fn new(faq: Faq) -> Result<Self, regex::Error> {
+        let mut keywords: Vec<_> = faq
+            .keywords
+            .as_deref()
+            .map(words)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        keywords.sort_unstable();
+
+        Ok(Self {
+            id: faq.id,
+            question: faq.question,
+            answer: faq.answer,
+            keywords,
+            regex: faq.regex.as_deref().map(compile_regex).transpose()?,
+            min_confidence: faq.min_confidence as f64 / 100.0,
+        })
+    }
+
+    /// How sure we are that a message with these words and content asks this, from 0 to 1
+    pub fn confidence(&self, words: &HashSet<String>, content: &str) -> f64 {
+        if self.regex.as_ref().is_some_and(|r| r.is_match(content)) {
+            return 1.0;
+        }
+        if self.keywords.is_empty() {
+            return 0.0;
+        }
+
+        let found = self.keywords.iter().filter(|k| words.contains(*k)).count();
+        found as f64 / self.keywords.len() as f64
+    }
+}
+
+/// The FAQ a message most likely asks, if any is confident enough
+pub fn best_match<'a>(faqs: &'a [CompiledFaq], content: &str) -> Option<(&'a CompiledFaq, f64)> {
+    let words = words(content);
+    faqs.iter()
+        .map(|f| (f, f.confidence(&words, content)))
+        .filter(|(f, confidence)| *confidence > 0.0 && *confidence >= f.min_confidence)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// Returns whether the answer helped, the FAQ and who asked
+fn parse_button(custom_id: &str) -> Option<(bool, i64, serenity::UserId)> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    let (helpful, rest) = rest.split_once('-')?;
+    let helpful = match helpful {
+        "yes" => true,
+        "no" => false,
+        _ => return None,
+    };
+    let (id, user) = rest.split_once('-')?;
+
+    Some((
+        helpful,
+        id.parse().ok()?,
+        serenity::UserId(user.parse().ok()?),
+    ))
+}
+
+/// FAQs per guild, loaded from the database on first use
+pub struct Faqs {
+    db: Db,
+    cache: GuildCache<Vec<CompiledFaq>>,
+    // When each FAQ was last answered in a channel
+    answered: Mutex<HashMap<(serenity::ChannelId, i64), Instant>>,
+}
+
+impl Faqs {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            cache: GuildCache::default(),
+            answered: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Vec<CompiledFaq>>, Error> {
+        self.cache
+            .get_or_load(guild, async {
+                // FAQs whose regex stopped compiling, e.g. after a regex crate update, are skipped
+                Ok(self
+                    .db
+                    .faqs(guild)
+                    .await?
+                    .into_iter()
+                    .filter_map(|f| CompiledFaq::new(f).ok())
+                    .collect())
+            })
+            .await
+    }
+
+    /// Adds a FAQ and returns its ID, its regex has to compile
+    pub async fn add(&self, faq: &Faq) -> Result<i64, Error> {
+        let id = self.db.add_faq(faq).await?;
+        self.cache
+            .invalidate(serenity::GuildId(faq.guild_id as u64));
+        Ok(id)
+    }
+
+    /// Removes a FAQ, returns false if it didn't exist
+    pub async fn remove(&self, guild: serenity::GuildId, id: i64) -> Result<bool, Error> {
+        let removed = self.db.delete_faq(guild, id).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached FAQs, returns how many guilds had them cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    // Whether a FAQ may be answered in a channel now, remembering that it was if so
+    fn claim(&self, channel: serenity::ChannelId, faq: i64) -> bool {
+        let mut answered = self.answered.lock().unwrap();
+        let now = Instant::now();
+        answered.retain(|_, at| now.duration_since(*at) < COOLDOWN);
+        if answered.contains_key(&(channel, faq)) {
+            return false;
+        }
+        answered.insert((channel, faq), now);
+        true
+    }
+}
+
+/// Answers a message in a help channel if it matches a FAQ, returns whether it did
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(false),
+    };
+    let config = data.guild_configs.get(guild).await?;
+    // Help channels are set with /config channel, their threads count too
+    let parent = ctx
+        .cache
+        .guild_channel(message.channel_id)
+        .and_then(|c| c.parent_id);
+    let help = [Some(message.channel_id), parent]
+        .into_iter()
+        .flatten()
+        .any(|c| config.channel_modes.get(&c) == Some(&ChannelMode::Help));
+    if !help {
+        return Ok(false);
+    }
+
+    let faqs = data.faqs.get(guild).await?;
+    let (faq, confidence) = match best_match(&faqs, &message.content) {
+        Some(best) => best,
+        None => return Ok(false),
+    };
+    if !data.faqs.claim(message.channel_id, faq.id) {
+        return Ok(false);
+    }
+
+    mentions::send_message(&ctx.http, message.channel_id, Mentions::Nothing, |m| {
+        m.reference_message(message)
+            .embed(|e| {
+                e.title(&faq.question).description(&faq.answer).footer(|f| {
+                    f.text(format!(
+                        "FAQ #{}, {:.0}% sure. Did this help?",
+                        faq.id,
+                        confidence * 100.0
+                    ))
+                })
+            })
+            .components(|c| {
+                c.create_action_row(|r| {
+                    r.create_button(|b| {
+                        b.custom_id(format!(
+                            "{}-yes-{}-{}",
+                            BUTTON_PREFIX, faq.id, message.author.id.0
+                        ))
+                        .label("Yes")
+                        .style(serenity::ButtonStyle::Success)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!(
+                            "{}-no-{}-{}",
+                            BUTTON_PREFIX, faq.id, message.author.id.0
+                        ))
+                        .label("No")
+                        .style(serenity::ButtonStyle::Secondary)
+                    })
+                })
+            })
+    })
+    .await?;
+    data.db.add_faq_match(faq.id).await?;
+
+    Ok(true)
+}
+
+/// Counts whether an answer helped when the member that asked clicks one of its buttons
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (helpful, id, asker) = match parse_button(&interaction.data.custom_id) {
+        Some(button) => button,
+        None => return Ok(()),
+    };
+
+    if interaction.user.id != asker {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| {
+                    d.content(":x: Only the member that asked can say whether this helped.")
+                        .ephemeral(true)
+                })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    data.db.add_faq_feedback(id, helpful).await?;
+
+    let mut embed = interaction
+        .message
+        .embeds
+        .first()
+        .cloned()
+        .map(serenity::CreateEmbed::from)
+        .unwrap_or_default();
+    embed.footer(|f| {
+        f.text(if helpful {
+            "Glad it helped!"
+        } else {
+            "Sorry about that, someone will be along to help"
+        })
+    });
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(serenity::InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faq(
+        id: i64,
+        keywords: Option<&str>,
+        regex: Option<&str>,
+        min_confidence: i64,
+    ) -> CompiledFaq {
+        CompiledFaq::new(Faq {
+            id,
+            keywords: keywords.map(str::to_string),
+            regex: regex.map(str::to_string),
+            min_confidence,
+            ..Faq::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn the_most_confident_faq_is_answered() {
+        let faqs = [
+            faq(1, Some("reset password"), None, 100),
+            faq(2, Some("refund order money"), None, 60),
+            faq(
+                3,
+                None,
+                Some(r"how (do|can) i (get|become) (staff|a mod)"),
+                100,
+            ),
+        ];
+        let best = |content| best_match(&faqs, content).map(|(f, _)| f.id);
+
+        assert_eq!(best("How do I RESET my password?"), Some(1));
+        assert_eq!(best("can I get my password back"), None);
+        assert_eq!(best("I want a refund for my order"), Some(2));
+        assert_eq!(best("where's my money"), None);
+        assert_eq!(best("how can i become staff here"), Some(3));
+        assert_eq!(best(""), None);
+    }
+
+    #[test]
+    fn feedback_buttons_are_read_back() {
+        assert_eq!(
+            parse_button("faq-yes-4-123"),
+            Some((true, 4, serenity::UserId(123)))
+        );
+        assert_eq!(
+            parse_button("faq-no-4-123"),
+            Some((false, 4, serenity::UserId(123)))
+        );
+        assert_eq!(parse_button("faq-maybe-4-123"), None);
+        assert_eq!(parse_button("faq-yes-4"), None);
+    }
+}
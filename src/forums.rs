@@ -0,0 +1,146 @@
+// Forum and help threads
+// The Mark as answer message command lets the author of a thread, or anyone who can manage
+// threads, pick the message that solved it. The thread gets the forum's `solved` tag if it has
+// one, the answer is pinned and linked in the thread, its author earns XP when levels are on,
+// and the thread is archived once ARCHIVE_GRACE has passed so late thanks still fit in.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::Feature,
+    db::Job,
+    jobs::{self, JobKind},
+    levels,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+// Name of the forum tag put on solved threads, compared ignoring case
+const SOLVED_TAG: &str = "solved";
+// Discord allows at most this many tags on a thread
+const MAX_APPLIED_TAGS: usize = 5;
+// XP earned by the author of an answer
+const ANSWER_XP: i64 = 50;
+// How long a solved thread stays open before it's archived
+const ARCHIVE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Marks `answer` as the answer of `thread`, returns false if the thread has one already
+pub async fn mark(
+    ctx: &serenity::Context,
+    data: &Data,
+    thread: &serenity::GuildChannel,
+    answer: &serenity::Message,
+    marked_by: serenity::UserId,
+) -> Result<bool, Error> {
+    if data.db.forum_answer(thread.id).await?.is_some() {
+        return Ok(false);
+    }
+
+    tag_solved(ctx, thread).await?;
+    if !answer.pinned {
+        answer.pin(ctx).await?;
+    }
+    if !data
+        .db
+        .mark_answer(
+            thread.guild_id,
+            thread.id,
+            answer.id,
+            answer.author.id,
+            marked_by,
+        )
+        .await?
+    {
+        return Ok(false);
+    }
+
+    mentions::send_message(&ctx.http, thread.id, Mentions::Nothing, |m| {
+        m.content(format!(
+            ":white_check_mark: <@{}> marked the answer by <@{}>: {}\nThis thread will be \
+            archived <t:{}:R>.",
+            marked_by.0,
+            answer.author.id.0,
+            answer.link(),
+            archive_at()
+        ))
+    })
+    .await?;
+
+    // Answering your own question or being a bot earns nothing
+    let config = data.guild_configs.get(thread.guild_id).await?;
+    if config.is_enabled(Feature::Levels)
+        && !answer.author.bot
+        && Some(answer.author.id) != thread.owner_id
+    {
+        let channel = config.level_channel.unwrap_or(thread.id);
+        levels::give_xp(
+            ctx,
+            data,
+            thread.guild_id,
+            answer.author.id,
+            ANSWER_XP,
+            channel,
+        )
+        .await?;
+    }
+
+    jobs::schedule(
+        &data.db,
+        JobKind::ArchiveThread,
+        thread.guild_id,
+        marked_by,
+        thread.id.0,
+        archive_at(),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// Archives the thread `target_id` once its grace period is over
+pub async fn archive_job(ctx: &serenity::Context, job: &Job) -> Result<(), Error> {
+    serenity::ChannelId(job.target_id as u64)
+        .edit_thread(&ctx.http, |t| t.archived(true))
+        .await?;
+
+    Ok(())
+}
+
+// Puts the parent forum's solved tag on the thread, if there's one and room for it
+async fn tag_solved(ctx: &serenity::Context, thread: &serenity::GuildChannel) -> Result<(), Error> {
+    let parent = match thread.parent_id {
+        Some(parent) => parent.to_channel(ctx).await?.guild(),
+        None => None,
+    };
+    let tag = parent.and_then(|p| {
+        p.available_tags
+            .into_iter()
+            .find(|t| t.name.eq_ignore_ascii_case(SOLVED_TAG))
+    });
+    let tag = match tag {
+        Some(tag) => tag.id,
+        None => return Ok(()),
+    };
+    if thread.applied_tags.contains(&tag) || thread.applied_tags.len() >= MAX_APPLIED_TAGS {
+        return Ok(());
+    }
+
+    let mut tags = thread.applied_tags.clone();
+    tags.push(tag);
+    // serenity has no builder method for the tags of a thread yet
+    let tags: Vec<_> = tags.iter().map(|t| t.0.to_string()).collect();
+    thread
+        .id
+        .edit_thread(&ctx.http, |t| {
+            t.0.insert("applied_tags", serde_json::json!(tags));
+            t
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn archive_at() -> i64 {
+    serenity::Timestamp::now().unix_timestamp() + ARCHIVE_GRACE.as_secs() as i64
+}
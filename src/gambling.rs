@@ -0,0 +1,298 @@
+// Gambling
+// /slots, /roulette and /coinbet bet economy coins. Every game pays out so that on average it
+// returns what was bet less the guild's house edge, so the odds stay the same whatever the edge
+// is and only the payouts change. Guilds also set the smallest and biggest bets and how many
+// coins a member may lose in a day, and servers that don't want gambling turn off the
+// `gambling` feature. A bet and its payout are written to the ledger in one transaction.
+use rand::Rng;
+
+use crate::{
+    config::{Feature, GuildConfig},
+    economy::{self, Transfer},
+    Context, Error,
+};
+
+/// Highest house edge a guild can set, in percent
+pub const MAX_HOUSE_EDGE: u32 = 50;
+
+// Symbols on each slot machine reel and how often they come up
+const SYMBOLS: &[(&str, u32)] = &[("🍒", 8), ("🍋", 6), ("🔔", 4), ("⭐", 2), ("💎", 1)];
+// What three of a kind pays, in bets, before the house edge is taken into account
+const THREE_OF_A_KIND: &[f64] = &[5.0, 10.0, 25.0, 100.0, 500.0];
+// Two cherries pay this many bets, still before the house edge
+const TWO_CHERRIES: f64 = 1.0;
+
+// Red numbers of a European roulette wheel, 0 is green and the rest are black
+const RED: &[u8] = &[
+    1, 3, 5, 7, 9, 12, 14, 16, 18, 19, 21, 23, 25, 27, 30, 32, 34, 36,
+];
+const POCKETS: u8 = 37;
+
+/// What a roulette bet is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Space {
+    Number(u8),
+    Red,
+    Black,
+    Even,
+    Odd,
+}
+
+impl Space {
+    /// Parses `red`, `black`, `even`, `odd` or a number from 0 to 36
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "red" => Some(Space::Red),
+            "black" => Some(Space::Black),
+            "even" => Some(Space::Even),
+            "odd" => Some(Space::Odd),
+            number => number
+                .parse()
+                .ok()
+                .filter(|n| *n < POCKETS)
+                .map(Space::Number),
+        }
+    }
+
+    /// Whether the ball landing on `pocket` wins, 0 only wins bets on 0
+    pub fn wins(self, pocket: u8) -> bool {
+        match self {
+            Space::Number(number) => pocket == number,
+            _ if pocket == 0 => false,
+            Space::Red => RED.contains(&pocket),
+            Space::Black => !RED.contains(&pocket),
+            Space::Even => pocket.is_multiple_of(2),
+            Space::Odd => pocket % 2 == 1,
+        }
+    }
+
+    fn probability(self) -> f64 {
+        match self {
+            Space::Number(_) => 1.0 / POCKETS as f64,
+            _ => 18.0 / POCKETS as f64,
+        }
+    }
+
+    /// What a winning bet pays with the house edge `edge` in percent
+    pub fn payout(self, bet: i64, edge: u32) -> i64 {
+        payout(bet, 1.0 / self.probability(), edge)
+    }
+}
+
+/// Color of a roulette pocket, for messages
+pub fn color(pocket: u8) -> &'static str {
+    match pocket {
+        0 => "green",
+        _ if RED.contains(&pocket) => "red",
+        _ => "black",
+    }
+}
+
+/// Spins the roulette wheel
+pub fn spin_wheel() -> u8 {
+    rand::thread_rng().gen_range(0..POCKETS)
+}
+
+/// Spins the three reels of the slot machine, returns the symbol indices
+pub fn spin_reels() -> [usize; 3] {
+    let total: u32 = SYMBOLS.iter().map(|(_, weight)| weight).sum();
+    let mut rng = rand::thread_rng();
+    [(); 3].map(|_| {
+        let mut roll = rng.gen_range(0..total);
+        SYMBOLS
+            .iter()
+            .position(|(_, weight)| {
+                let hit = roll < *weight;
+                roll = roll.saturating_sub(*weight);
+                hit
+            })
+            .unwrap_or(0)
+    })
+}
+
+/// The symbols of a spin, for messages
+pub fn show_reels(reels: [usize; 3]) -> String {
+    reels.map(|r| SYMBOLS[r].0).join(" ")
+}
+
+/// What a slot machine spin pays with the house edge `edge` in percent, 0 if it lost
+pub fn slots_payout(reels: [usize; 3], bet: i64, edge: u32) -> i64 {
+    match base_multiplier(reels) {
+        0.0 => 0,
+        multiplier => payout(bet, multiplier / expected_base_return(), edge),
+    }
+}
+
+/// What a winning coin flip pays with the house edge `edge` in percent
+pub fn coin_payout(bet: i64, edge: u32) -> i64 {
+    payout(bet, 2.0, edge)
+}
+
+// Pays `multiplier` bets, less the house edge. Multipliers are those of a fair game, where the
+// average return is exactly the bet
+fn payout(bet: i64, multiplier: f64, edge: u32) -> i64 {
+    let edge = edge.min(MAX_HOUSE_EDGE) as f64 / 100.0;
+    (bet as f64 * multiplier * (1.0 - edge)).floor() as i64
+}
+
+fn base_multiplier(reels: [usize; 3]) -> f64 {
+    let [a, b, c] = reels;
+    if a == b && b == c {
+        THREE_OF_A_KIND[a]
+    } else if reels.iter().filter(|r| **r == 0).count() == 2 {
+        TWO_CHERRIES
+    } else {
+        0.0
+    }
+}
+
+// Average return of the pay table per bet, over every way the reels can land
+fn expected_base_return() -> f64 {
+    let total: u32 = SYMBOLS.iter().map(|(_, weight)| weight).sum();
+    let chance = |symbol: usize| SYMBOLS[symbol].1 as f64 / total as f64;
+
+    let mut expected = 0.0;
+    for a in 0..SYMBOLS.len() {
+        for b in 0..SYMBOLS.len() {
+            for c in 0..SYMBOLS.len() {
+                expected += chance(a) * chance(b) * chance(c) * base_multiplier([a, b, c]);
+            }
+        }
+    }
+    expected
+}
+
+/// Returns why the author can't bet `bet` coins, if they can't
+pub async fn check_bet(
+    ctx: Context<'_>,
+    config: &GuildConfig,
+    bet: i64,
+) -> Result<Option<String>, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+
+    if !config.is_enabled(Feature::Gambling) {
+        return Ok(Some(
+            ":x: Gambling is turned off on this server.".to_string(),
+        ));
+    }
+    if bet < config.min_bet() || bet > config.max_bet() {
+        return Ok(Some(format!(
+            ":x: Bets on this server are from {} to {}.",
+            economy::format(config.min_bet()),
+            economy::format(config.max_bet())
+        )));
+    }
+
+    if let Some(limit) = config.gambling_loss_limit {
+        let lost = ctx
+            .data()
+            .db
+            .gambling_losses(guild, ctx.author().id, economy::day_start())
+            .await?;
+        if lost + bet > limit {
+            return Ok(Some(format!(
+                ":x: You can lose up to {} a day gambling here, you lost {} today. Come back \
+                <t:{}:R>.",
+                economy::format(limit),
+                economy::format(lost.max(0)),
+                economy::tomorrow()
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Takes the bet and pays out `payout`, returns None if the author can't cover the bet
+pub async fn settle(
+    ctx: Context<'_>,
+    game: &str,
+    bet: i64,
+    payout: i64,
+) -> Result<Option<i64>, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+
+    let key = economy::invocation_key(ctx, game);
+    match db.gamble(guild, author, bet, payout, game, &key).await? {
+        Transfer::Insufficient => Ok(None),
+        _ => Ok(Some(db.balance(guild, author).await?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Average return of a game per coin bet, `payouts` are (chance, payout of a 1000 coin bet)
+    fn average_return(payouts: impl Iterator<Item = (f64, i64)>) -> f64 {
+        payouts
+            .map(|(chance, payout)| chance * payout as f64)
+            .sum::<f64>()
+            / 1000.0
+    }
+
+    #[test]
+    fn games_return_all_but_the_house_edge() {
+        for edge in [0, 5, 20] {
+            let expected = 1.0 - edge as f64 / 100.0;
+
+            let total: u32 = SYMBOLS.iter().map(|(_, weight)| weight).sum();
+            let chance = |symbol: usize| SYMBOLS[symbol].1 as f64 / total as f64;
+            let mut spins = Vec::new();
+            for a in 0..SYMBOLS.len() {
+                for b in 0..SYMBOLS.len() {
+                    for c in 0..SYMBOLS.len() {
+                        spins.push((
+                            chance(a) * chance(b) * chance(c),
+                            slots_payout([a, b, c], 1000, edge),
+                        ));
+                    }
+                }
+            }
+            let slots = average_return(spins.into_iter());
+
+            let red = average_return((0..POCKETS).map(|pocket| {
+                let won = Space::Red.wins(pocket);
+                (
+                    1.0 / 37.0,
+                    if won {
+                        Space::Red.payout(1000, edge)
+                    } else {
+                        0
+                    },
+                )
+            }));
+            let seven = average_return((0..POCKETS).map(|pocket| {
+                let won = Space::Number(7).wins(pocket);
+                let payout = Space::Number(7).payout(1000, edge);
+                (1.0 / 37.0, if won { payout } else { 0 })
+            }));
+            let coin = average_return([(0.5, coin_payout(1000, edge)), (0.5, 0)].into_iter());
+
+            // Payouts are rounded down to whole coins
+            for game in [slots, red, seven, coin] {
+                assert!((game - expected).abs() < 0.005, "{} at {}%", game, edge);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_only_wins_bets_on_zero() {
+        for space in [Space::Red, Space::Black, Space::Even, Space::Odd] {
+            assert!(!space.wins(0));
+        }
+        assert!(Space::Number(0).wins(0));
+        assert_eq!(color(0), "green");
+    }
+
+    #[test]
+    fn parses_roulette_spaces() {
+        assert_eq!(Space::parse(" Red "), Some(Space::Red));
+        assert_eq!(Space::parse("36"), Some(Space::Number(36)));
+        assert_eq!(Space::parse("37"), None);
+        assert_eq!(Space::parse("green"), None);
+        assert_eq!((1..POCKETS).filter(|p| Space::Black.wins(*p)).count(), 18);
+    }
+}
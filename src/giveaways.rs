@@ -0,0 +1,260 @@
+// Giveaways
+// /giveaway start posts a message with an Enter button, pressing it again leaves the giveaway.
+// Entries are stored, so the button keeps working after a restart, and the job scheduler ends
+// the giveaway when its time is up: a random entrant wins and is announced in a reply to the
+// giveaway. /giveaway reroll draws someone else, for winners that don't claim their prize.
+use poise::serenity_prelude as serenity;
+use rand::seq::SliceRandom;
+
+use crate::{
+    db::{Db, Giveaway, Job},
+    jobs::{self, JobKind},
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+const BUTTON_PREFIX: &str = "giveaway";
+
+/// Posts a stored giveaway in its channel and schedules ending it
+pub async fn post(ctx: &serenity::Context, db: &Db, giveaway: &Giveaway) -> Result<(), Error> {
+    let message = mentions::send_message(&ctx.http, giveaway.channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(format!(":tada: {}", giveaway.prize))
+                .description(format!(
+                    "Press Enter to take part!\nEnds <t:{}:R>, hosted by <@{}>",
+                    giveaway.ends_at, giveaway.host.0
+                ))
+                .footer(|f| f.text(format!("Giveaway #{}", giveaway.id)))
+        })
+        .components(|c| {
+            c.create_action_row(|r| {
+                r.create_button(|b| {
+                    b.custom_id(format!("{}-enter-{}", BUTTON_PREFIX, giveaway.id))
+                        .label("Enter")
+                        .emoji('🎉')
+                        .style(serenity::ButtonStyle::Primary)
+                })
+            })
+        })
+    })
+    .await?;
+    db.set_giveaway_message(giveaway.id, message.id).await?;
+
+    jobs::schedule(
+        db,
+        JobKind::EndGiveaway,
+        giveaway.guild,
+        giveaway.host,
+        giveaway.id as u64,
+        giveaway.ends_at,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Handles the Enter buttons of giveaways
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let id: i64 = match interaction
+        .data
+        .custom_id
+        .strip_prefix(BUTTON_PREFIX)
+        .and_then(|rest| rest.strip_prefix("-enter-"))
+        .and_then(|id| id.parse().ok())
+    {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let user = interaction.user.id;
+    let reply = match data.db.giveaway(id, None).await? {
+        Some(giveaway) if giveaway.ended => ":x: This giveaway has ended.".to_string(),
+        Some(giveaway) => {
+            if data.db.enter_giveaway(giveaway.id, user).await? {
+                format!(
+                    ":white_check_mark: You entered the giveaway for **{}**, good luck!",
+                    giveaway.prize
+                )
+            } else {
+                data.db.leave_giveaway(giveaway.id, user).await?;
+                ":white_check_mark: You left the giveaway, press Enter again to rejoin".to_string()
+            }
+        }
+        None => ":x: That giveaway doesn't exist anymore.".to_string(),
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Ends the giveaway `target_id`, run by the job scheduler. Retries after a failed
+/// announcement announce the winner drawn the first time
+pub async fn end_job(ctx: &serenity::Context, db: &Db, job: &Job) -> Result<(), Error> {
+    let giveaway = match db.giveaway(job.target_id, None).await? {
+        Some(giveaway) if !giveaway.announced => giveaway,
+        _ => return Ok(()),
+    };
+    db.end_giveaway(giveaway.id).await?;
+
+    let winner = match giveaway.winner {
+        Some(winner) => Some(winner),
+        None => draw(db, &giveaway, None).await?,
+    };
+    announce(ctx, db, &giveaway, winner, false).await
+}
+
+/// Draws a new winner of an ended giveaway, anyone but the current one. Returns None if there
+/// is nobody else to pick
+pub async fn reroll(
+    ctx: &serenity::Context,
+    db: &Db,
+    giveaway: &Giveaway,
+) -> Result<Option<serenity::UserId>, Error> {
+    let winner = draw(db, giveaway, giveaway.winner).await?;
+    if winner.is_some() {
+        announce(ctx, db, giveaway, winner, true).await?;
+    }
+    Ok(winner)
+}
+
+// Picks a random entrant other than `exclude` and stores them
+async fn draw(
+    db: &Db,
+    giveaway: &Giveaway,
+    exclude: Option<serenity::UserId>,
+) -> Result<Option<serenity::UserId>, Error> {
+    let entrants = db.giveaway_entrants(giveaway.id).await?;
+    let eligible: Vec<_> = entrants
+        .into_iter()
+        .filter(|u| Some(*u) != exclude)
+        .collect();
+    let winner = eligible.choose(&mut rand::thread_rng()).copied();
+
+    // A reroll without anyone else to pick keeps the current winner
+    if winner.is_none() && exclude.is_some() {
+        return Ok(None);
+    }
+    db.set_giveaway_winner(giveaway.id, winner).await?;
+    Ok(winner)
+}
+
+// Shows the winner on the giveaway message and announces them
+async fn announce(
+    ctx: &serenity::Context,
+    db: &Db,
+    giveaway: &Giveaway,
+    winner: Option<serenity::UserId>,
+    reroll: bool,
+) -> Result<(), Error> {
+    let entrants = db.giveaway_entrants(giveaway.id).await?;
+    let result = match winner {
+        Some(winner) => format!("Winner: <@{}>", winner.0),
+        None => "Nobody entered.".to_string(),
+    };
+    // The announcement still goes out if the giveaway message was deleted, just not as a reply
+    let mut message = giveaway.message;
+    if let Some(id) = message {
+        let edited = giveaway
+            .channel
+            .edit_message(&ctx.http, id, |m| {
+                m.embed(|e| {
+                    e.title(format!(":tada: {}", giveaway.prize))
+                        .description(format!(
+                            "{}\n{} entries, hosted by <@{}>",
+                            result,
+                            entrants.len(),
+                            giveaway.host.0
+                        ))
+                        .footer(|f| f.text(format!("Giveaway #{}, ended", giveaway.id)))
+                        .timestamp(serenity::Timestamp::now())
+                })
+                .components(|c| c)
+            })
+            .await;
+        if let Err(e) = edited {
+            tracing::warn!(
+                giveaway = giveaway.id,
+                "Error showing the winner on the giveaway message: {}",
+                e
+            );
+            message = None;
+        }
+    }
+
+    let announcement = match (winner, reroll) {
+        (Some(winner), false) => format!(
+            ":tada: Congratulations <@{}>, you won **{}**!",
+            winner.0, giveaway.prize
+        ),
+        (Some(winner), true) => format!(
+            ":tada: Rerolled, congratulations <@{}>, you won **{}**!",
+            winner.0, giveaway.prize
+        ),
+        (None, _) => format!(
+            "The giveaway for **{}** ended without entries.",
+            giveaway.prize
+        ),
+    };
+    mentions::send_message(&ctx.http, giveaway.channel, Mentions::Users, |m| {
+        if let Some(message) = message {
+            m.reference_message((giveaway.channel, message));
+        }
+        m.content(announcement)
+    })
+    .await?;
+    db.set_giveaway_announced(giveaway.id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rerolls_pick_someone_else() {
+        let db = Db::memory().await;
+        let (a, b) = (serenity::UserId(1), serenity::UserId(2));
+        let id = db
+            .create_giveaway(serenity::GuildId(1), serenity::ChannelId(2), a, "Prize", 0)
+            .await
+            .unwrap();
+        let giveaway = db.giveaway(id, None).await.unwrap().unwrap();
+
+        // Nobody entered
+        assert_eq!(draw(&db, &giveaway, None).await.unwrap(), None);
+
+        assert!(db.enter_giveaway(id, a).await.unwrap());
+        assert!(!db.enter_giveaway(id, a).await.unwrap());
+        assert_eq!(draw(&db, &giveaway, None).await.unwrap(), Some(a));
+        assert_eq!(
+            db.giveaway(id, None).await.unwrap().unwrap().winner,
+            Some(a)
+        );
+
+        // Without anyone else the winner stays
+        assert_eq!(draw(&db, &giveaway, Some(a)).await.unwrap(), None);
+        assert_eq!(
+            db.giveaway(id, None).await.unwrap().unwrap().winner,
+            Some(a)
+        );
+
+        db.enter_giveaway(id, b).await.unwrap();
+        for _ in 0..10 {
+            assert_eq!(draw(&db, &giveaway, Some(a)).await.unwrap(), Some(b));
+        }
+        assert_eq!(
+            db.giveaway(id, None).await.unwrap().unwrap().winner,
+            Some(b)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// Second confirmation step for dangerous owner commands
+// The owner gets a one-time code by DM and has to send it back in the DM within a
+// minute, so a hijacked session or a misclick in a busy channel can't trigger the action.
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{Context, Error};
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Asks the invoking owner to confirm `action` with a one-time code, returns whether they did
+pub async fn dangerous_action(ctx: Context<'_>, action: &str) -> Result<bool, Error> {
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+    let author = ctx.author();
+
+    let dm = author
+        .dm(ctx.discord(), |m| {
+            m.content(format!(
+                ":warning: Someone (hopefully you) ran `{}`.\nReply with `{}` within {} seconds to confirm.",
+                action,
+                code,
+                CONFIRM_TIMEOUT.as_secs()
+            ))
+        })
+        .await;
+
+    let dm = match dm {
+        Ok(dm) => dm,
+        Err(_) => {
+            ctx.send(|m| {
+                m.content(":x: I couldn't DM you the confirmation code, please open your DMs.")
+                    .ephemeral(true)
+            })
+            .await?;
+            return Ok(false);
+        }
+    };
+
+    ctx.send(|m| {
+        m.content(":lock: Check your DMs to confirm this action.")
+            .ephemeral(true)
+    })
+    .await?;
+
+    let reply = author
+        .await_reply(ctx.discord())
+        .channel_id(dm.channel_id)
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+
+    let confirmed = matches!(&reply, Some(reply) if reply.content.trim() == code);
+    let response = match (&reply, confirmed) {
+        (_, true) => format!(":white_check_mark: Confirmed, running `{}`.", action),
+        (Some(_), false) => ":x: Wrong code, action cancelled.".to_string(),
+        (None, false) => ":x: Timed out, action cancelled.".to_string(),
+    };
+
+    if let Err(e) = dm.channel_id.say(ctx.discord(), response).await {
+        tracing::warn!("Error sending confirmation result: {}", e);
+    }
+
+    Ok(confirmed)
+}
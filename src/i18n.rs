@@ -0,0 +1,196 @@
+// Localized slash command names and descriptions
+// Translations live in locales/commands.json, keyed by the qualified command name (like
+// `config set`) and then by parameter name, with every name and description mapping Discord
+// locales to text. They are filled into the commands once they're built, so adding a language
+// only means adding it to the file. Prefix commands keep their English names.
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{Data, Error};
+
+const COMMANDS: &str = include_str!("../locales/commands.json");
+
+/// Fills in the localized names and descriptions of the commands and their subcommands
+pub fn localize(commands: &mut [poise::Command<Data, Error>]) {
+    let table: Value = serde_json::from_str(COMMANDS).expect("locales/commands.json is invalid");
+    for command in commands {
+        localize_command(&table, command, None);
+    }
+}
+
+fn localize_command(
+    table: &Value,
+    command: &mut poise::Command<Data, Error>,
+    parent: Option<&str>,
+) {
+    let name = match parent {
+        Some(parent) => format!("{} {}", parent, command.name),
+        None => command.name.clone(),
+    };
+
+    if let Some(entry) = table.get(&name) {
+        command.name_localizations = texts(entry.get("name"));
+        command.description_localizations = texts(entry.get("description"));
+
+        for parameter in &mut command.parameters {
+            if let Some(entry) = entry.get("parameters").and_then(|p| p.get(&parameter.name)) {
+                parameter.name_localizations = texts(entry.get("name"));
+                parameter.description_localizations = texts(entry.get("description"));
+            }
+        }
+    }
+
+    for subcommand in &mut command.subcommands {
+        localize_command(table, subcommand, Some(&name));
+    }
+}
+
+// Locale to text, anything that isn't a string is skipped
+fn texts(value: Option<&Value>) -> HashMap<String, String> {
+    value
+        .and_then(Value::as_object)
+        .map(|texts| {
+            texts
+                .iter()
+                .filter_map(|(locale, text)| Some((locale.clone(), text.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    // Discord rejects the whole registration if a single name or description is invalid
+    fn check_name(name: &str) -> bool {
+        (1..=32).contains(&name.chars().count())
+            && name.to_lowercase() == name
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    }
+
+    fn check(
+        what: &str,
+        names: &HashMap<String, String>,
+        descriptions: &HashMap<String, String>,
+        locales: &HashSet<String>,
+    ) {
+        for locale in locales {
+            let name = names
+                .get(locale)
+                .unwrap_or_else(|| panic!("{} has no {} name", what, locale));
+            assert!(
+                check_name(name),
+                "Invalid {} name of {}: {}",
+                locale,
+                what,
+                name
+            );
+            let description = descriptions
+                .get(locale)
+                .unwrap_or_else(|| panic!("{} has no {} description", what, locale));
+            assert!(
+                (1..=100).contains(&description.chars().count()),
+                "{} description of {} is too long",
+                locale,
+                what
+            );
+        }
+    }
+
+    fn walk(
+        command: &poise::Command<Data, Error>,
+        parent: Option<&str>,
+        locales: &HashSet<String>,
+        seen: &mut HashSet<String>,
+    ) {
+        if command.slash_action.is_none() && command.subcommands.is_empty() {
+            return;
+        }
+        let name = match parent {
+            Some(parent) => format!("{} {}", parent, command.name),
+            None => command.name.clone(),
+        };
+
+        check(
+            &format!("/{}", name),
+            &command.name_localizations,
+            &command.description_localizations,
+            locales,
+        );
+        for parameter in &command.parameters {
+            check(
+                &format!("/{} {}", name, parameter.name),
+                &parameter.name_localizations,
+                &parameter.description_localizations,
+                locales,
+            );
+        }
+        for locale in locales {
+            let mut names = HashSet::new();
+            for parameter in &command.parameters {
+                assert!(
+                    names.insert(&parameter.name_localizations[locale]),
+                    "/{} has two {} parameters with the same name",
+                    name,
+                    locale
+                );
+            }
+            let mut names = HashSet::new();
+            for subcommand in &command.subcommands {
+                assert!(
+                    names.insert(&subcommand.name_localizations[locale]),
+                    "/{} has two {} subcommands with the same name",
+                    name,
+                    locale
+                );
+            }
+        }
+
+        for subcommand in &command.subcommands {
+            walk(subcommand, Some(&name), locales, seen);
+        }
+        seen.insert(name);
+    }
+
+    #[test]
+    fn every_command_is_localized() {
+        let table: Value = serde_json::from_str(COMMANDS).unwrap();
+        let table = table.as_object().unwrap();
+        let locales: HashSet<String> = table
+            .values()
+            .filter_map(|entry| entry["name"].as_object())
+            .flat_map(|names| names.keys().cloned())
+            .collect();
+        assert!(!locales.is_empty());
+
+        let commands = crate::commands::all();
+        let mut seen = HashSet::new();
+        let mut top_level = HashMap::new();
+        for command in &commands {
+            walk(command, None, &locales, &mut seen);
+            for (locale, name) in &command.name_localizations {
+                if let Some(other) = top_level.insert((locale.clone(), name.clone()), &command.name)
+                {
+                    panic!(
+                        "/{} and /{} are both called {} in {}",
+                        other, command.name, name, locale
+                    );
+                }
+            }
+        }
+
+        for name in table.keys() {
+            assert!(
+                seen.contains(name),
+                "Translations for unknown command /{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Blocking known bad images by perceptual hash
+// Moderators block an image with the "Block image" message command, which stores a pHash of it.
+// Unlike a file hash a pHash barely changes when an image is resized, recompressed or slightly
+// edited, so reposts are caught too. How many bits may differ is set per guild with
+// `/config set image_hash_tolerance`.
+use std::{f64::consts::PI, sync::Arc, time::Duration};
+
+use image::imageops::FilterType;
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::GuildCache,
+    db::Db,
+    worker::{self, Worker},
+    Error,
+};
+
+// Images are scaled down to this before the DCT
+const SAMPLE_SIZE: usize = 32;
+// Only the lowest frequencies end up in the hash
+const HASH_SIZE: usize = 8;
+// Bigger attachments aren't downloaded
+const MAX_IMAGE_BYTES: u64 = 8 * 1024 * 1024;
+// Every image in a message is hashed before the pipeline continues, so downloads can't hang
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Blocked hashes per guild, loaded from the database on first use
+pub struct ImageBlocklist {
+    db: Db,
+    client: reqwest::Client,
+    worker: Option<Arc<Worker>>,
+    cache: GuildCache<Vec<u64>>,
+}
+
+impl ImageBlocklist {
+    pub fn new(db: Db, worker: Option<Arc<Worker>>) -> Self {
+        Self {
+            db,
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .expect("Failed to build image blocklist HTTP client"),
+            worker,
+            cache: GuildCache::default(),
+        }
+    }
+
+    pub async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Vec<u64>>, Error> {
+        self.cache
+            .get_or_load(guild, self.db.blocked_images(guild))
+            .await
+    }
+
+    /// Blocks a hash, returns false if it already was blocked
+    pub async fn block(
+        &self,
+        guild: serenity::GuildId,
+        hash: u64,
+        moderator: serenity::UserId,
+    ) -> Result<bool, Error> {
+        let added = self.db.block_image(guild, hash, moderator).await?;
+        self.cache.invalidate(guild);
+        Ok(added)
+    }
+
+    /// Unblocks a hash, returns false if it wasn't blocked
+    pub async fn unblock(&self, guild: serenity::GuildId, hash: u64) -> Result<bool, Error> {
+        let removed = self.db.unblock_image(guild, hash).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached hashes, returns how many guilds had them cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    /// Downloads and hashes every image attached to a message
+    pub async fn hash_attachments(&self, message: &serenity::Message) -> Vec<u64> {
+        let mut hashes = Vec::new();
+
+        for attachment in message.attachments.iter().filter(|a| is_image(a)) {
+            match self.hash_attachment(attachment).await {
+                Ok(hash) => hashes.push(hash),
+                Err(e) => tracing::debug!(
+                    attachment = %attachment.url,
+                    "Error hashing attachment: {}",
+                    e
+                ),
+            }
+        }
+
+        hashes
+    }
+
+    async fn hash_attachment(&self, attachment: &serenity::Attachment) -> Result<u64, Error> {
+        let bytes = self
+            .client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        // Decoding and the DCT are CPU heavy, keep them off the event loop
+        worker::phash(self.worker.as_deref(), bytes.to_vec()).await
+    }
+}
+
+/// Whether a hash is within `tolerance` bits of any blocked hash
+pub fn matches(blocked: &[u64], hash: u64, tolerance: u32) -> bool {
+    blocked.iter().any(|b| (b ^ hash).count_ones() <= tolerance)
+}
+
+pub fn is_image(attachment: &serenity::Attachment) -> bool {
+    let image = match &attachment.content_type {
+        Some(content_type) => content_type.starts_with("image/"),
+        None => {
+            let name = attachment.filename.to_lowercase();
+            [".png", ".jpg", ".jpeg", ".gif", ".webp"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+        }
+    };
+
+    image && attachment.size <= MAX_IMAGE_BYTES
+}
+
+// DCT based perceptual hash: the low frequencies of the image compared to their median
+pub fn phash(bytes: &[u8]) -> Result<u64, image::ImageError> {
+    let image = image::load_from_memory(bytes)?
+        .resize_exact(SAMPLE_SIZE as u32, SAMPLE_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = image.pixels().map(|p| p.0[0] as f64).collect();
+
+    // cos[u][x] for the first HASH_SIZE frequencies
+    let mut cos = [[0.0; SAMPLE_SIZE]; HASH_SIZE];
+    for (u, row) in cos.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = ((2 * x + 1) as f64 * u as f64 * PI / (2 * SAMPLE_SIZE) as f64).cos();
+        }
+    }
+
+    let mut dct = [0.0; HASH_SIZE * HASH_SIZE];
+    for u in 0..HASH_SIZE {
+        for v in 0..HASH_SIZE {
+            let mut sum = 0.0;
+            for y in 0..SAMPLE_SIZE {
+                for x in 0..SAMPLE_SIZE {
+                    sum += pixels[y * SAMPLE_SIZE + x] * cos[u][x] * cos[v][y];
+                }
+            }
+            dct[v * HASH_SIZE + u] = sum;
+        }
+    }
+
+    // The first coefficient is the average brightness, it would dominate the median
+    let mut sorted = dct[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    let hash = dct
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value > median)
+        .fold(0u64, |hash, (i, _)| hash | 1 << i);
+
+    Ok(hash)
+}
@@ -0,0 +1,170 @@
+// Incidents
+// Staff open an incident with /incident start, or by calling staff with /staff when none is
+// open. Until it's closed, moderation actions, flagged messages, staff calls and notes added
+// with /incident note end up on its timeline, and /incident close turns the timeline into a
+// markdown postmortem that's posted to the log channel for the staff archive. A guild has at
+// most one open incident, everything that happens while it's open is assumed to belong to it.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Incident, IncidentEvent},
+    duration, Data,
+};
+
+/// Longest incident title
+pub const MAX_TITLE_LENGTH: usize = 100;
+
+/// What an event on the timeline is
+#[derive(Clone, Copy)]
+pub enum EventKind {
+    /// A moderation action, by a moderator or automod
+    Action,
+    /// Content flagged for review
+    Flag,
+    /// Someone called staff with /staff
+    StaffCall,
+    /// Added by staff with /incident note
+    Note,
+}
+
+impl EventKind {
+    fn name(self) -> &'static str {
+        match self {
+            EventKind::Action => "action",
+            EventKind::Flag => "flag",
+            EventKind::StaffCall => "staff call",
+            EventKind::Note => "note",
+        }
+    }
+}
+
+/// Adds an event to the guild's open incident, if there is one
+/// Failures are only logged, the timeline shouldn't get in the way of what it records
+pub async fn record(data: &Data, guild: serenity::GuildId, kind: EventKind, summary: &str) {
+    if let Err(e) = data
+        .db
+        .record_incident_event(guild, kind.name(), summary)
+        .await
+    {
+        tracing::warn!(guild = guild.0, "Error recording incident event: {}", e);
+    }
+}
+
+// Timestamps in the postmortem, which is read outside of Discord
+fn format_time(unix: i64) -> String {
+    match serenity::Timestamp::from_unix_timestamp(unix) {
+        Ok(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        Err(_) => unix.to_string(),
+    }
+}
+
+/// The postmortem of a closed incident as a markdown document
+pub fn postmortem(
+    incident: &Incident,
+    events: &[IncidentEvent],
+    opened_by: &str,
+    closed_by: &str,
+    summary: Option<&str>,
+) -> String {
+    let closed_at = incident.closed_at.unwrap_or(incident.opened_at);
+    let lasted = Duration::from_secs((closed_at - incident.opened_at).max(0) as u64);
+
+    let mut doc = format!("# Incident #{}: {}\n\n", incident.id, incident.title);
+    doc.push_str(&format!(
+        "- **Opened:** {} by {}\n",
+        format_time(incident.opened_at),
+        opened_by
+    ));
+    doc.push_str(&format!(
+        "- **Closed:** {} by {}\n",
+        format_time(closed_at),
+        closed_by
+    ));
+    doc.push_str(&format!("- **Duration:** {}\n", duration::format(lasted)));
+    doc.push_str(&format!("- **Events:** {}\n", events.len()));
+
+    doc.push_str("\n## Summary\n\n");
+    doc.push_str(summary.unwrap_or("*No summary given.*"));
+    doc.push('\n');
+
+    doc.push_str("\n## Timeline\n\n");
+    if events.is_empty() {
+        doc.push_str("*Nothing was recorded.*\n");
+    }
+    for event in events {
+        doc.push_str(&format!(
+            "- `{}` **{}**: {}\n",
+            format_time(event.at),
+            event.kind,
+            event.summary.replace('\n', " ")
+        ));
+    }
+
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[test]
+    fn postmortems_list_the_timeline() {
+        let incident = Incident {
+            id: 4,
+            title: "Raid".to_string(),
+            opened_by: 1,
+            opened_at: 0,
+            closed_at: Some(90 * 60),
+        };
+        let events = [IncidentEvent {
+            at: 60,
+            kind: "action".to_string(),
+            summary: "mod banned raider\nfor spam".to_string(),
+        }];
+
+        let doc = postmortem(&incident, &events, "alice", "bob", None);
+        assert!(doc.starts_with(
+            "# Incident #4: Raid\n\n- **Opened:** 1970-01-01 00:00:00 UTC by alice\n"
+        ));
+        assert!(doc.contains("- **Closed:** 1970-01-01 01:30:00 UTC by bob\n"));
+        assert!(doc.contains("*No summary given.*"));
+        assert!(
+            doc.ends_with("- `1970-01-01 00:01:00 UTC` **action**: mod banned raider for spam\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn events_only_go_to_the_open_incident() {
+        let db = Db::memory().await;
+        let (guild, user) = (serenity::GuildId(1), serenity::UserId(2));
+
+        // Nothing open yet, so this is dropped
+        db.record_incident_event(guild, "note", "before")
+            .await
+            .unwrap();
+        let id = db.open_incident(guild, "Raid", user).await.unwrap();
+        assert!(id.is_some());
+        assert!(db
+            .open_incident(guild, "Other", user)
+            .await
+            .unwrap()
+            .is_none());
+        db.record_incident_event(guild, "note", "during")
+            .await
+            .unwrap();
+
+        let (incident, events) = db.close_incident(guild, user).await.unwrap().unwrap();
+        assert_eq!(Some(incident.id), id);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "during");
+        assert!(db.close_incident(guild, user).await.unwrap().is_none());
+        assert!(db
+            .open_incident(guild, "Next", user)
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,167 @@
+// Job scheduler
+// Work that has to happen later, like taking away a temporary role, is stored in the `jobs`
+// table and run by a background task once it's due. Jobs that became due while the bot was
+// offline run right after startup. A job failing with a transient error (rate limits, Discord
+// having trouble) is tried again later, one failing for good (a deleted role, missing
+// permissions) is dropped. New kinds of jobs get a `JobKind` variant and a branch in `run`.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    bans,
+    db::{Db, Job},
+    forums, giveaways, onboarding, passes, permissions, polls,
+    retry::{retry, RetryPolicy, Transient},
+    temproles, watchdog, Error,
+};
+
+// How often due jobs are looked up, also the most a job can be late by
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobKind {
+    /// Takes the role `target_id` away from `user_id`
+    RemoveRole,
+    /// Takes away the access of `user_id` to the channel `target_id`
+    RevokePass,
+    /// Sends the onboarding step `target_id` to `user_id`
+    OnboardingStep,
+    /// Closes the poll `target_id`, `user_id` is its author
+    ClosePoll,
+    /// Ends the giveaway `target_id`, `user_id` is its host
+    EndGiveaway,
+    /// Archives the solved thread `target_id`, `user_id` marked its answer
+    ArchiveThread,
+    /// Lifts the tempban of `user_id`, `target_id` is who banned them
+    Unban,
+}
+
+impl JobKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            JobKind::RemoveRole => "remove_role",
+            JobKind::RevokePass => "revoke_pass",
+            JobKind::OnboardingStep => "onboarding_step",
+            JobKind::ClosePoll => "close_poll",
+            JobKind::EndGiveaway => "end_giveaway",
+            JobKind::ArchiveThread => "archive_thread",
+            JobKind::Unban => "unban",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "remove_role" => Some(JobKind::RemoveRole),
+            "revoke_pass" => Some(JobKind::RevokePass),
+            "onboarding_step" => Some(JobKind::OnboardingStep),
+            "close_poll" => Some(JobKind::ClosePoll),
+            "end_giveaway" => Some(JobKind::EndGiveaway),
+            "archive_thread" => Some(JobKind::ArchiveThread),
+            "unban" => Some(JobKind::Unban),
+            _ => None,
+        }
+    }
+}
+
+/// Schedules a job at the unix timestamp `run_at`, replacing the same pending job
+pub async fn schedule(
+    db: &Db,
+    kind: JobKind,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    target: u64,
+    run_at: i64,
+) -> Result<i64, Error> {
+    db.schedule_job(kind.name(), guild, user, target, run_at)
+        .await
+}
+
+/// Pending jobs of one kind in a guild, soonest first
+pub async fn pending(db: &Db, kind: JobKind, guild: serenity::GuildId) -> Result<Vec<Job>, Error> {
+    db.pending_jobs(kind.name(), guild).await
+}
+
+/// Cancels the pending jobs of one kind for a user and returns them so they can be run early,
+/// for any target if `target` is None
+pub async fn take(
+    db: &Db,
+    kind: JobKind,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    target: Option<u64>,
+) -> Result<Vec<Job>, Error> {
+    db.take_jobs(kind.name(), guild, user, target).await
+}
+
+/// Starts the task that runs due jobs
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("jobs", async move {
+        loop {
+            if let Err(e) = run_due(&ctx, &db).await {
+                tracing::warn!("Error running jobs: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn run_due(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    for job in db.due_jobs(now).await? {
+        let kind = match JobKind::parse(&job.kind) {
+            Some(kind) => kind,
+            None => {
+                tracing::warn!(job = job.id, kind = %job.kind, "Dropping job of unknown kind");
+                db.delete_job(job.id).await?;
+                continue;
+            }
+        };
+
+        // Pushed back before running, so it's tried again later unless it's done with
+        if !db.claim_job(job.id, now).await? {
+            continue;
+        }
+
+        match retry(&RetryPolicy::DEFAULT, || run(ctx, db, kind, &job)).await {
+            Ok(()) => {}
+            Err(e) if e.is_transient() => {
+                tracing::warn!(
+                    job = job.id,
+                    kind = kind.name(),
+                    "Error running job, trying again later: {}",
+                    e
+                );
+                continue;
+            }
+            // Granting the permission later won't bring back the job, but the log says where
+            Err(e) if permissions::log_missing(ctx, kind.name(), &e) => {}
+            Err(e) => {
+                tracing::warn!(
+                    job = job.id,
+                    kind = kind.name(),
+                    "Dropping failed job: {}",
+                    e
+                );
+            }
+        }
+
+        db.delete_job(job.id).await?;
+    }
+
+    Ok(())
+}
+
+async fn run(ctx: &serenity::Context, db: &Db, kind: JobKind, job: &Job) -> Result<(), Error> {
+    match kind {
+        JobKind::RemoveRole => temproles::expire(ctx, job).await,
+        JobKind::RevokePass => passes::revoke(ctx, job).await,
+        JobKind::OnboardingStep => onboarding::run_step(ctx, db, job).await,
+        JobKind::ClosePoll => polls::close_job(ctx, db, job).await,
+        JobKind::EndGiveaway => giveaways::end_job(ctx, db, job).await,
+        JobKind::ArchiveThread => forums::archive_job(ctx, job).await,
+        JobKind::Unban => bans::expire(ctx, db, job).await,
+    }
+}
@@ -0,0 +1,107 @@
+// Knowledge base
+// Staff write articles with /kb add, and anyone can look them up with /kb search, which
+// autocompletes titles and falls back to a full-text search of titles and text for anything
+// else. The search index is SQLite's FTS5, ranked with bm25 so articles sharing more and rarer
+// words with the query come first, and titles count more than the text. On PostgreSQL it's a
+// tsvector ranked the same way, apart from rarer words counting more.
+
+/// Longest article title, titles are autocomplete choices which Discord limits to 100
+pub const MAX_TITLE_LENGTH: usize = 100;
+/// Most results a search shows
+pub const MAX_RESULTS: u32 = 5;
+
+/// Turns what a member typed into an FTS5 query matching any of its words, or word prefixes
+/// Words are quoted so FTS5 syntax like `AND` or `title:` in a query is searched for as text
+pub fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<_> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"*", w.to_lowercase()))
+        .collect();
+
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude as serenity;
+
+    use super::*;
+    use crate::db::Db;
+
+    #[test]
+    fn queries_are_quoted_words() {
+        assert_eq!(
+            fts_query("How do I reset?").as_deref(),
+            Some("\"how\"* OR \"do\"* OR \"i\"* OR \"reset\"*")
+        );
+        assert_eq!(
+            fts_query("title:x AND \"y").as_deref(),
+            Some("\"title\"* OR \"x\"* OR \"and\"* OR \"y\"*")
+        );
+        assert_eq!(fts_query(" ?! "), None);
+    }
+
+    #[tokio::test]
+    async fn search_follows_edits_and_removals() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+        let other = serenity::GuildId(2);
+        let author = serenity::UserId(3);
+        let search = |query: &str| {
+            let db = db.clone();
+            let query = fts_query(query).unwrap();
+            async move {
+                db.search_kb(guild, &query, MAX_RESULTS)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|(title, _)| title)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert!(db
+            .add_kb_article(
+                guild,
+                "Passwords",
+                "Reset yours from the login page",
+                author
+            )
+            .await
+            .unwrap());
+        assert!(db
+            .add_kb_article(
+                guild,
+                "Refunds",
+                "Ask in #billing for a password reset",
+                author
+            )
+            .await
+            .unwrap());
+        assert!(db
+            .add_kb_article(other, "Passwords", "Other servers aren't searched", author)
+            .await
+            .unwrap());
+        assert!(!db
+            .add_kb_article(guild, "passwords", "Titles ignore case", author)
+            .await
+            .unwrap());
+
+        // The title counts more
+        assert_eq!(search("password").await, ["Passwords", "Refunds"]);
+        assert_eq!(search("login").await, ["Passwords"]);
+
+        assert!(db
+            .edit_kb_article(guild, "PASSWORDS", "Use the forgot link", author)
+            .await
+            .unwrap());
+        assert!(search("login").await.is_empty());
+        assert_eq!(search("forgot").await, ["Passwords"]);
+
+        assert!(db.delete_kb_article(guild, "refunds").await.unwrap());
+        assert!(!db.delete_kb_article(guild, "refunds").await.unwrap());
+        assert!(search("billing").await.is_empty());
+        assert_eq!(db.kb_titles(guild).await.unwrap(), ["Passwords"]);
+    }
+}
@@ -0,0 +1,170 @@
+// Leveling
+// Members earn a bit of random XP for chatting, at most once per XP_COOLDOWN so spamming
+// doesn't pay off. Servers opt in with the `levels` feature, level ups are announced in the
+// level channel or where the message was sent.
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+
+use crate::{
+    config::Feature,
+    mentions::{self, Mentions},
+    promotions,
+    ratelimit::RateLimiter,
+    Data, Error,
+};
+
+// XP given per message
+const XP_MIN: i64 = 15;
+const XP_MAX: i64 = 25;
+// Members only earn XP for one message within this time
+const XP_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Limiter that allows one XP gain per member every XP_COOLDOWN, each guild separately
+pub fn spawn_limiter() -> Arc<RateLimiter<(serenity::GuildId, serenity::UserId)>> {
+    RateLimiter::spawn(1, XP_COOLDOWN)
+}
+
+/// A level and the progress towards the next one
+pub struct Level {
+    pub level: i64,
+    /// XP earned since reaching `level`
+    pub progress: i64,
+    /// XP needed to go from `level` to the next one
+    pub needed: i64,
+}
+
+impl Level {
+    pub fn from_xp(xp: i64) -> Self {
+        let mut level = 0;
+        let mut progress = xp.max(0);
+
+        // Every level needs a bit more XP than the one before, so each takes longer
+        while progress >= xp_for_next(level) {
+            progress -= xp_for_next(level);
+            level += 1;
+        }
+
+        Self {
+            level,
+            progress,
+            needed: xp_for_next(level),
+        }
+    }
+}
+
+/// Gives the author XP for a message and announces it if they leveled up
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let config = data.guild_configs.get(guild).await?;
+    if !config.is_enabled(Feature::Levels)
+        || !data.xp_limiter.try_acquire((guild, message.author.id))
+    {
+        return Ok(());
+    }
+
+    let gained = rand::thread_rng().gen_range(XP_MIN..=XP_MAX);
+    give_xp(
+        ctx,
+        data,
+        guild,
+        message.author.id,
+        gained,
+        config.level_channel.unwrap_or(message.channel_id),
+    )
+    .await
+}
+
+/// Gives a member XP, and if they leveled up applies promotions and announces it in `channel`
+pub async fn give_xp(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    gained: i64,
+    channel: serenity::ChannelId,
+) -> Result<(), Error> {
+    let xp = data.counters.add_xp(guild, user, gained).await?;
+
+    let level = Level::from_xp(xp).level;
+    if level == Level::from_xp(xp - gained).level {
+        return Ok(());
+    }
+
+    let promoted = match promotions::handle_level_up(ctx, &data.db, guild, user, level).await {
+        Ok(roles) => roles,
+        Err(e) => {
+            tracing::warn!(guild = guild.0, "Error applying promotion rules: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut announcement = format!(":tada: <@{}> reached level **{}**", user.0, level);
+    if !promoted.is_empty() {
+        let roles = promoted
+            .iter()
+            .map(|r| format!("<@&{}>", r.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        announcement.push_str(&format!(" and was promoted to {}", roles));
+    }
+    announcement.push('!');
+
+    // Role mentions in the announcement don't ping, only the member does
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Users, |m| {
+        m.content(announcement)
+    })
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!(channel = channel.0, "Error announcing level up: {}", e);
+    }
+
+    Ok(())
+}
+
+// XP needed to go from `level` to the next one
+fn xp_for_next(level: i64) -> i64 {
+    5 * level * level + 50 * level + 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_need_more_xp_each_time() {
+        let level = Level::from_xp(0);
+        assert_eq!((level.level, level.progress, level.needed), (0, 0, 100));
+        assert_eq!(Level::from_xp(99).level, 0);
+
+        let level = Level::from_xp(100);
+        assert_eq!((level.level, level.progress, level.needed), (1, 0, 155));
+        let level = Level::from_xp(300);
+        assert_eq!((level.level, level.progress, level.needed), (2, 45, 220));
+        // Negative XP, e.g. after an admin took some away, is level 0
+        assert_eq!(Level::from_xp(-50).progress, 0);
+
+        assert!((0..50).all(|level| xp_for_next(level + 1) > xp_for_next(level)));
+    }
+
+    #[test]
+    fn xp_is_given_once_per_cooldown() {
+        let limiter = RateLimiter::new(1, XP_COOLDOWN);
+        let member = (serenity::GuildId(1), serenity::UserId(2));
+
+        assert!(limiter.try_acquire(member));
+        assert!(!limiter.try_acquire(member));
+        // Each guild has a cooldown of its own
+        assert!(limiter.try_acquire((serenity::GuildId(3), serenity::UserId(2))));
+    }
+}
@@ -0,0 +1,862 @@
+mod a2s;
+mod alts;
+mod alttext;
+mod applications;
+mod archive;
+mod autorole;
+mod bans;
+mod boosts;
+mod botlists;
+mod circuit;
+mod commands;
+mod config;
+mod counters;
+mod db;
+mod digests;
+mod dm;
+mod duration;
+mod economy;
+mod emojipack;
+mod entitlements;
+mod faq;
+mod forums;
+mod gambling;
+mod giveaways;
+mod guard;
+mod i18n;
+mod imagehash;
+mod incidents;
+mod jobs;
+mod kb;
+mod levels;
+mod links;
+mod llm;
+mod logs;
+mod meetings;
+mod mentions;
+mod messagearchive;
+mod metrics;
+mod modlog;
+mod notes;
+mod notify;
+mod nsfw;
+mod onboarding;
+mod oncall;
+mod partners;
+mod passes;
+mod permissions;
+mod pipeline;
+mod polls;
+mod privacy;
+mod promotions;
+mod quiet;
+mod quotas;
+mod ratelimit;
+mod reactionroles;
+mod reminders;
+mod restart;
+mod retry;
+mod reviewqueue;
+mod rolepersist;
+mod serversync;
+mod setup;
+mod shop;
+mod shutdown;
+mod spam;
+mod spoilers;
+mod stages;
+mod starboard;
+mod statschannels;
+mod storage;
+mod streaks;
+mod summaries;
+mod tags;
+mod temproles;
+mod tempvoice;
+mod themes;
+mod toxicity;
+mod translate;
+mod triggers;
+mod votedelete;
+mod votes;
+mod walls;
+mod watchdog;
+mod watchlist;
+mod webhooks;
+mod welcome;
+mod wordgame;
+mod work;
+mod worker;
+mod zip;
+
+/// Hot paths of the message pipeline, used by the benchmarks in `benches/`
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::{
+        levels::Level,
+        ratelimit::RateLimiter,
+        spam::caps_percent,
+        triggers::{match_triggers, CompiledTrigger, TriggerKind},
+    };
+}
+
+// Load rust dependencies
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
+
+use alts::InviteTracker;
+use circuit::CircuitBreaker;
+use config::GuildConfigs;
+use counters::Counters;
+use db::Db;
+use dm::DmStats;
+use entitlements::Premium;
+use faq::Faqs;
+use imagehash::ImageBlocklist;
+use links::LinkCleaner;
+use llm::Llm;
+use logs::LogTail;
+use metrics::Metrics;
+use notify::Notifications;
+use nsfw::Classifier;
+use ratelimit::RateLimiter;
+use shutdown::Shutdown;
+use spam::SpamFilter;
+use spoilers::SpoilerRules;
+use starboard::Starboard;
+use themes::Themes;
+use toxicity::Toxicity;
+use translate::Translator;
+use triggers::Triggers;
+use votes::VoteWebhook;
+use watchdog::LoopWatchdog;
+use watchlist::Watchlist;
+use worker::Worker;
+
+// S L A S H  C O M M A N D S
+use poise::{
+    serenity_prelude::{self as serenity},
+    FrameworkOptions,
+};
+use tracing::Instrument;
+use tracing_subscriber::{prelude::*, EnvFilter};
+
+// Our own logs at info, dependencies only when something goes wrong
+const DEFAULT_LOG_FILTER: &str = "warn,discordbot_but_rust=info";
+// Messages kept in memory per channel
+const MESSAGE_CACHE_SIZE: usize = 200;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[allow(dead_code)]
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+// User data, which is stored and accessible in all command invocations
+struct Data {
+    rate_limiter: Arc<RateLimiter>,
+    xp_limiter: Arc<RateLimiter<(serenity::GuildId, serenity::UserId)>>,
+    watchdog: Arc<LoopWatchdog>,
+    integrations: Vec<Arc<CircuitBreaker>>,
+    dm_stats: Arc<DmStats>,
+    db: Db,
+    counters: Counters,
+    guild_configs: GuildConfigs,
+    translator: Option<Arc<Translator>>,
+    llm: Option<Arc<Llm>>,
+    worker: Option<Arc<Worker>>,
+    invites: InviteTracker,
+    image_blocklist: ImageBlocklist,
+    classifier: Option<Arc<Classifier>>,
+    toxicity: Option<Arc<Toxicity>>,
+    premium: Option<Arc<Premium>>,
+    spoiler_rules: SpoilerRules,
+    link_cleaner: LinkCleaner,
+    starboard: Starboard,
+    triggers: Triggers,
+    faqs: Faqs,
+    notifications: Notifications,
+    spam_filter: SpamFilter,
+    watchlist: Watchlist,
+    themes: Themes,
+    metrics: Arc<Metrics>,
+    shutdown: Arc<Shutdown>,
+    log_tail: LogTail,
+}
+
+async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    // This is our custom error handler
+    // They are many errors that can occur, so we only handle the ones we want to customize
+    // and forward the rest to the default handler
+    // Only failures of our own code count, not users passing bad arguments
+    match &error {
+        poise::FrameworkError::Command { ctx, .. } => ctx.data().metrics.error("command"),
+        poise::FrameworkError::Listener { framework, .. } => {
+            framework.user_data.metrics.error("event")
+        }
+        _ => {}
+    }
+
+    match error {
+        poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {:?}", error),
+        poise::FrameworkError::Command { error, ctx } => {
+            tracing::error!(
+                command = %ctx.command().qualified_name,
+                invocation = ctx.id(),
+                "Error in command: {:?}",
+                error
+            );
+
+            // Tell the user exactly what to fix instead of failing silently
+            if permissions::is_missing_permissions(&error) {
+                // The request may have been for another channel than the one the command is in
+                let channel = permissions::target_channel(&error).unwrap_or(ctx.channel_id());
+                let message =
+                    permissions::explain(channel, permissions::missing_in_channel(ctx, channel));
+                if let Err(e) = ctx.send(|m| m.content(message).ephemeral(true)).await {
+                    tracing::warn!("Error sending missing permissions notice: {}", e);
+                }
+            }
+        }
+        poise::FrameworkError::CooldownHit {
+            remaining_cooldown,
+            ctx,
+        } => {
+            // Rounded up, a command that can be used again in 0 seconds would look broken
+            let ready_at = serenity::Timestamp::now().unix_timestamp()
+                + remaining_cooldown.as_secs() as i64
+                + 1;
+            let message = format!(
+                ":x: `{}{}` can be used again <t:{}:R>.",
+                ctx.prefix(),
+                ctx.command().qualified_name,
+                ready_at
+            );
+            if let Err(e) = ctx.send(|m| m.content(message).ephemeral(true)).await {
+                tracing::warn!("Error sending cooldown notice: {}", e);
+            }
+        }
+        poise::FrameworkError::MissingBotPermissions {
+            missing_permissions,
+            ctx,
+        } => {
+            let message = permissions::explain(ctx.channel_id(), Some(missing_permissions));
+            if let Err(e) = ctx.send(|m| m.content(message).ephemeral(true)).await {
+                tracing::warn!("Error sending missing permissions notice: {}", e);
+            }
+        }
+        poise::FrameworkError::Listener {
+            error, ctx, event, ..
+        } if permissions::log_missing(ctx, event.name(), &error) => {}
+        // Prefix messages that aren't a command may be a tag, like `~rules`
+        poise::FrameworkError::UnknownCommand {
+            ctx,
+            msg,
+            msg_content,
+            framework,
+            trigger: poise::MessageDispatchTrigger::MessageCreate,
+            ..
+        } => {
+            if let Err(e) =
+                tags::handle_unknown_command(ctx, framework.user_data, msg, msg_content).await
+            {
+                tracing::warn!("Error sending tag: {}", e);
+            }
+        }
+        error => {
+            if let Err(e) = poise::builtins::on_error(error).await {
+                tracing::error!("Error while handling error: {}", e) // lol
+            }
+        }
+    }
+}
+
+// Handlers of the same event don't depend on each other, so one failing (a deleted channel,
+// missing permissions) is logged and doesn't stop the others from running
+fn log_handler_errors<const N: usize>(
+    ctx: &serenity::Context,
+    data: &Data,
+    results: [(&str, Result<(), Error>); N],
+) {
+    for (handler, result) in results {
+        if let Err(e) = result {
+            data.metrics.error("event");
+            if !permissions::log_missing(ctx, handler, &e) {
+                tracing::error!(handler, "Error in event handler: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Runs the worker process heavy jobs are sent to, see `worker`
+pub async fn run_worker() {
+    tracing_subscriber::registry()
+        .with(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER)),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let addr = env::var("WORKER_ADDR").unwrap_or_else(|_| worker::DEFAULT_ADDR.to_string());
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .expect("Failed to bind WORKER_ADDR");
+    tracing::info!(%addr, "Worker listening");
+    if let Err(e) = worker::serve(listener, Llm::from_env(None), worker::token_from_env()).await {
+        tracing::error!("Worker stopped: {}", e);
+    }
+}
+
+/// Starts the bot and runs it until it's shut down
+pub async fn run() {
+    // Log level is set with RUST_LOG, e.g. RUST_LOG=debug or RUST_LOG=discordbot_but_rust=trace
+    let log_tail = LogTail::default();
+    tracing_subscriber::registry()
+        .with(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER)),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        // Owners can watch the logs from Discord with /logs tail
+        .with(log_tail.clone())
+        .init();
+
+    // `copy-storage <from> <to>` moves the bot's data to another database instead of starting it
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let [command, from, to] = args.as_slice() {
+        if command == "copy-storage" {
+            match storage::copy(from, to).await {
+                Ok(rows) => tracing::info!(rows, "Copied storage"),
+                Err(e) => {
+                    tracing::error!("Error copying storage: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    }
+
+    // With --ephemeral nothing is written anywhere, for CI, demos and deployments that mustn't
+    // keep data
+    let database_url = if args.iter().any(|a| a == "--ephemeral") {
+        tracing::warn!("Running ephemeral, everything is forgotten when the bot stops");
+        Some(storage::MEMORY_URL.to_string())
+    } else {
+        env::var("DATABASE_URL").ok()
+    };
+    let ephemeral = database_url.as_deref() == Some(storage::MEMORY_URL);
+
+    // Configure the client with your Discord bot token in the environment
+    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
+
+    // The application owner is picked up automatically, BOT_OWNER_ID allows adding another one
+    let mut owners = HashSet::new();
+    if let Ok(owner_id) = env::var("BOT_OWNER_ID") {
+        match owner_id.parse::<u64>() {
+            Ok(id) => {
+                owners.insert(serenity::UserId(id));
+            }
+            Err(e) => tracing::warn!("Ignoring invalid BOT_OWNER_ID: {}", e),
+        }
+    }
+
+    let options = FrameworkOptions {
+        prefix_options: poise::PrefixFrameworkOptions {
+            // Resolved per guild, defaults to ~
+            prefix: None,
+            dynamic_prefix: Some(config::dynamic_prefix),
+            edit_tracker: Some(poise::EditTracker::for_timespan(Duration::from_secs(3600))),
+            ..Default::default()
+        },
+        on_error: |error| Box::pin(on_error(error)),
+        // Refuses expensive commands once the guild's daily quota is used up
+        command_check: Some(|ctx| Box::pin(quotas::check(ctx))),
+        pre_command: |ctx| {
+            Box::pin(async move {
+                ctx.data().metrics.command(&ctx.command().qualified_name);
+                tracing::info!(
+                    command = %ctx.command().qualified_name,
+                    invocation = ctx.id(),
+                    user = ctx.author().id.0,
+                    guild = ctx.guild_id().map(|g| g.0),
+                    "Command invoked"
+                );
+            })
+        },
+        post_command: |ctx| {
+            Box::pin(async move {
+                tracing::debug!(
+                    command = %ctx.command().qualified_name,
+                    invocation = ctx.id(),
+                    "Command finished"
+                );
+            })
+        },
+        // Never ping @everyone, @here or roles unless a command explicitly allows it
+        allowed_mentions: Some(mentions::framework_default()),
+        listener: |_ctx, event, _framework, _data| {
+            // This is a custom event handler
+            // It is called for every event that the framework receives
+            // We can use it to log events, or do other things
+
+            // Names the event in the log if a handler blocks the runtime
+            Box::pin(watchdog::watch(
+                event.name(),
+                async move {
+                    // We can also return a future to be run after the event is handled
+                    // This is useful for things like logging
+                    // We can also return an error to stop the event from being handled
+
+                    _data.metrics.event(event.name());
+
+                    // Half handled events are what a graceful shutdown is trying to avoid
+                    if _data.shutdown.is_shutting_down() {
+                        return Ok(());
+                    }
+
+                    match event {
+                        poise::Event::Ready { data_about_bot } => {
+                            tracing::info!(
+                                session_id = %data_about_bot.session_id,
+                                "Ready! Logged in as {}",
+                                data_about_bot.user.name
+                            );
+
+                            _ctx.set_activity(_data.themes.activity()).await;
+                        }
+                        poise::Event::Message { new_message } => {
+                            pipeline::handle_message(_ctx, _data, new_message).await;
+                        }
+                        poise::Event::GuildCreate { guild, .. } => {
+                            // Remember the current invite uses so the next join can be matched
+                            _data.invites.refresh(_ctx, guild.id).await;
+                            // Voice states come with the guild, so this is the first time we
+                            // can tell which temporary channels emptied while we were offline
+                            tempvoice::sweep(_ctx, _data, guild).await?;
+                        }
+                        poise::Event::GuildMemberAddition { new_member } => {
+                            let results = [
+                                (
+                                    "welcome",
+                                    welcome::handle_join(_ctx, _data, new_member).await,
+                                ),
+                                ("alts", alts::handle_join(_ctx, _data, new_member).await),
+                                (
+                                    "autorole",
+                                    autorole::handle_join(_ctx, _data, new_member).await,
+                                ),
+                                (
+                                    "onboarding",
+                                    onboarding::handle_join(_ctx, _data, new_member).await,
+                                ),
+                                (
+                                    "rolepersist",
+                                    rolepersist::handle_join(_ctx, _data, new_member).await,
+                                ),
+                                ("notes", notes::handle_join(_ctx, _data, new_member).await),
+                                ("watchlist", _data.watchlist.handle_join(new_member).await),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::GuildMemberUpdate {
+                            old_if_available,
+                            new,
+                        } => {
+                            let old = old_if_available.as_ref();
+                            let results = [
+                                (
+                                    "autorole",
+                                    autorole::handle_update(_ctx, _data, old, new).await,
+                                ),
+                                (
+                                    "rolepersist",
+                                    rolepersist::handle_update(_ctx, _data, old, new).await,
+                                ),
+                                ("boosts", boosts::handle_update(_ctx, _data, old, new).await),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::GuildMemberRemoval {
+                            guild_id,
+                            user,
+                            member_data_if_available,
+                        } => {
+                            let results = [
+                                (
+                                    "welcome",
+                                    welcome::handle_leave(_ctx, _data, *guild_id, user).await,
+                                ),
+                                (
+                                    "passes",
+                                    passes::handle_leave(_ctx, _data, *guild_id, user).await,
+                                ),
+                                (
+                                    "onboarding",
+                                    onboarding::handle_leave(_ctx, _data, *guild_id, user).await,
+                                ),
+                                (
+                                    "rolepersist",
+                                    rolepersist::handle_leave(
+                                        _data,
+                                        *guild_id,
+                                        member_data_if_available.as_ref(),
+                                    )
+                                    .await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::GuildBanAddition {
+                            guild_id,
+                            banned_user,
+                        } => {
+                            _data.db.record_ban(*guild_id, banned_user).await?;
+                        }
+                        poise::Event::Unknown { name, raw } => {
+                            entitlements::handle_event(&_data.db, name, raw).await?;
+                        }
+                        poise::Event::GuildBanRemoval {
+                            guild_id,
+                            unbanned_user,
+                        } => {
+                            bans::handle_unban(_data, *guild_id, unbanned_user).await?;
+                        }
+                        poise::Event::MessageDelete {
+                            channel_id,
+                            deleted_message_id,
+                            guild_id: Some(guild_id),
+                        } => {
+                            let results = [
+                                (
+                                    "modlog",
+                                    modlog::log_delete(
+                                        _ctx,
+                                        _data,
+                                        *guild_id,
+                                        *channel_id,
+                                        *deleted_message_id,
+                                    )
+                                    .await,
+                                ),
+                                (
+                                    "messagearchive",
+                                    messagearchive::handle_delete(_data, &[*deleted_message_id])
+                                        .await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::MessageDeleteBulk {
+                            channel_id,
+                            multiple_deleted_messages_ids,
+                            guild_id: Some(guild_id),
+                        } => {
+                            let results = [
+                                (
+                                    "modlog",
+                                    modlog::log_bulk_delete(
+                                        _ctx,
+                                        _data,
+                                        *guild_id,
+                                        *channel_id,
+                                        multiple_deleted_messages_ids,
+                                    )
+                                    .await,
+                                ),
+                                (
+                                    "messagearchive",
+                                    messagearchive::handle_delete(
+                                        _data,
+                                        multiple_deleted_messages_ids,
+                                    )
+                                    .await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::MessageUpdate {
+                            old_if_available,
+                            event,
+                            ..
+                        } => {
+                            let results = [
+                                (
+                                    "modlog",
+                                    modlog::log_edit(_ctx, _data, old_if_available.as_ref(), event)
+                                        .await,
+                                ),
+                                (
+                                    "messagearchive",
+                                    messagearchive::handle_edit(_data, event).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::InteractionCreate {
+                            interaction: serenity::Interaction::MessageComponent(component),
+                        } => {
+                            let results = [
+                                (
+                                    "alttext",
+                                    alttext::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "applications",
+                                    applications::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "onboarding",
+                                    onboarding::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "polls",
+                                    polls::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "giveaways",
+                                    giveaways::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "stages",
+                                    stages::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "votedelete",
+                                    votedelete::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                (
+                                    "reviewqueue",
+                                    reviewqueue::handle_interaction(_ctx, _data, component).await,
+                                ),
+                                ("faq", faq::handle_interaction(_ctx, _data, component).await),
+                                (
+                                    "setup",
+                                    setup::handle_interaction(_ctx, _data, component).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::InteractionCreate {
+                            interaction: serenity::Interaction::ModalSubmit(modal),
+                        } => {
+                            let results = [
+                                ("alttext", alttext::handle_modal(_ctx, _data, modal).await),
+                                (
+                                    "onboarding",
+                                    onboarding::handle_modal(_ctx, _data, modal).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::VoiceStateUpdate { old, new } => {
+                            let results = [
+                                (
+                                    "tempvoice",
+                                    tempvoice::handle_voice_state(_ctx, _data, old.as_ref(), new)
+                                        .await,
+                                ),
+                                ("meetings", meetings::handle_voice_state(_data, new).await),
+                                ("stages", stages::handle_voice_state(_ctx, _data, new).await),
+                                (
+                                    "watchlist",
+                                    _data.watchlist.handle_voice_state(old.as_ref(), new).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::StageInstanceDelete { stage_instance } => {
+                            stages::handle_stage_delete(_ctx, _data, stage_instance).await?;
+                        }
+                        poise::Event::ReactionAdd { add_reaction } => {
+                            let results = [
+                                (
+                                    "walls",
+                                    walls::record_reaction(_ctx, _data, add_reaction).await,
+                                ),
+                                (
+                                    "translate",
+                                    translate::handle_reaction(_ctx, _data, add_reaction).await,
+                                ),
+                                (
+                                    "reactionroles",
+                                    reactionroles::handle_add(_ctx, _data, add_reaction).await,
+                                ),
+                                (
+                                    "starboard",
+                                    starboard::handle_reaction(_ctx, _data, add_reaction).await,
+                                ),
+                                (
+                                    "votedelete",
+                                    votedelete::handle_reaction(_ctx, _data, add_reaction).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        poise::Event::ReactionRemove { removed_reaction } => {
+                            let results = [
+                                (
+                                    "reactionroles",
+                                    reactionroles::handle_remove(_ctx, _data, removed_reaction)
+                                        .await,
+                                ),
+                                (
+                                    "starboard",
+                                    starboard::handle_reaction(_ctx, _data, removed_reaction).await,
+                                ),
+                            ];
+                            log_handler_errors(_ctx, _data, results);
+                        }
+                        _ => {}
+                    };
+
+                    Ok(())
+                }
+                .instrument(tracing::info_span!("event", name = event.name())),
+            ))
+        },
+        commands: commands::all(),
+        owners,
+        ..Default::default()
+    };
+
+    // Shared with main so it can wait for a shutdown to finish before returning
+    let shutdown = Arc::new(Shutdown::default());
+    let data_shutdown = Arc::clone(&shutdown);
+
+    let framework = poise::Framework::builder()
+        .options(options)
+        .token(token)
+        .intents(
+            serenity::GatewayIntents::non_privileged()
+                | serenity::GatewayIntents::MESSAGE_CONTENT
+                | serenity::GatewayIntents::GUILD_MEMBERS,
+        )
+        // Deleted and edited messages can only be logged with their content if we still have it
+        .client_settings(|c| c.cache_settings(|s| s.max_messages(MESSAGE_CACHE_SIZE)))
+        .user_data_setup(move |_ctx, _ready, _framework| {
+            Box::pin(async move {
+                // After a rolling restart, wait for the old process to let go of everything
+                restart::take_over().await;
+
+                // Keep bot list sites up to date with our server count
+                let mut integrations = botlists::spawn(_ctx.clone(), _ready.user.id);
+
+                let translator = Translator::from_env();
+                if let Some(translator) = &translator {
+                    integrations.push(Arc::clone(&translator.breaker));
+                }
+
+                // Heavy jobs go to the worker process when there is one
+                let worker = Worker::from_env();
+                let llm = Llm::from_env(worker.clone());
+                if let Some(llm) = &llm {
+                    integrations.push(Arc::clone(&llm.breaker));
+                }
+
+                let classifier = Classifier::from_env();
+                if let Some(classifier) = &classifier {
+                    integrations.push(Arc::clone(&classifier.breaker));
+                }
+
+                let toxicity = Toxicity::from_env();
+                if let Some(toxicity) = &toxicity {
+                    integrations.push(Arc::clone(&toxicity.breaker));
+                }
+
+                let db = Db::connect(database_url.as_deref()).await?;
+                let counters = Counters::new(db.clone());
+                counters::spawn(counters.clone());
+                walls::spawn(_ctx.clone(), db.clone(), counters.clone());
+                // Also runs jobs and schedule changes missed while we were offline
+                jobs::spawn(_ctx.clone(), db.clone());
+                bans::spawn_reconcile(_ctx.clone(), db.clone());
+                let premium = Premium::from_env(_ready.user.id);
+                if let Some(premium) = &premium {
+                    entitlements::spawn_sync(db.clone(), Arc::clone(premium));
+                }
+                temproles::spawn(_ctx.clone(), db.clone());
+                promotions::spawn(_ctx.clone(), db.clone());
+                archive::spawn(_ctx.clone(), db.clone());
+                quiet::spawn(_ctx.clone(), db.clone());
+                statschannels::spawn(_ctx.clone(), db.clone());
+                oncall::spawn(_ctx.clone(), db.clone());
+                partners::spawn(_ctx.clone(), db.clone());
+                digests::spawn(_ctx.clone(), db.clone(), llm.clone());
+                votes::spawn_leaderboard(_ctx.clone(), db.clone());
+                let watchlist = Watchlist::new(db.clone());
+                watchlist::spawn(_ctx.clone(), watchlist.clone());
+                let themes = Themes::new(db.clone());
+                themes::spawn(
+                    _ctx.clone(),
+                    themes.clone(),
+                    Arc::clone(_framework.shard_manager()),
+                );
+
+                let shutdown = data_shutdown;
+                shutdown::spawn_signal_handler(
+                    Arc::clone(&shutdown),
+                    Arc::clone(_framework.shard_manager()),
+                    db.clone(),
+                    counters.clone(),
+                );
+                restart::spawn_signal_handler(
+                    Arc::clone(&shutdown),
+                    Arc::clone(_framework.shard_manager()),
+                    db.clone(),
+                    counters.clone(),
+                    ephemeral,
+                );
+
+                // Start sampling event loop lag
+                let watchdog = LoopWatchdog::spawn();
+                let metrics = Arc::new(Metrics::default());
+                // On VOTE_ADDR if that's set, otherwise next to /metrics
+                let votes = VoteWebhook::from_env(db.clone())
+                    .filter(|webhook| !votes::spawn_server(webhook));
+                metrics::spawn_server(
+                    Arc::clone(&metrics),
+                    Arc::clone(_framework.shard_manager()),
+                    Arc::clone(&watchdog),
+                    votes,
+                );
+
+                let dm_stats = Arc::new(DmStats::default());
+                reminders::spawn(_ctx.clone(), db.clone(), Arc::clone(&dm_stats));
+
+                Ok(Data {
+                    // Shared by every handler that responds to messages
+                    rate_limiter: RateLimiter::spawn_from_env(),
+                    xp_limiter: levels::spawn_limiter(),
+                    watchdog,
+                    integrations,
+                    dm_stats,
+                    counters,
+                    guild_configs: GuildConfigs::new(db.clone()),
+                    image_blocklist: ImageBlocklist::new(db.clone(), worker.clone()),
+                    spoiler_rules: SpoilerRules::new(db.clone()),
+                    link_cleaner: LinkCleaner::new(),
+                    starboard: Starboard::default(),
+                    triggers: Triggers::new(db.clone()),
+                    faqs: Faqs::new(db.clone()),
+                    notifications: Notifications::new(db.clone()),
+                    spam_filter: SpamFilter::new(db.clone()),
+                    watchlist,
+                    themes,
+                    metrics,
+                    shutdown,
+                    log_tail,
+                    classifier,
+                    toxicity,
+                    premium,
+                    db,
+                    translator,
+                    llm,
+                    worker,
+                    invites: InviteTracker::default(),
+                })
+            })
+        });
+
+    framework.run().await.unwrap();
+    shutdown.wait().await;
+    tracing::info!("Client stopped");
+}
@@ -0,0 +1,219 @@
+// Link hygiene
+// With the `clean_links` feature, links from known URL shorteners are expanded and tracking
+// parameters like utm_source or fbclid are removed. The cleaned links are posted as a reply,
+// or with `repost_links` the message is replaced by a webhook repost with the cleaned links.
+// Domains on the guild's link allowlist are never touched.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+use reqwest::{redirect, Url};
+
+use crate::{
+    config::Feature,
+    mentions::{self, Mentions},
+    webhooks, Data, Error,
+};
+
+// Hosts that only redirect to another URL
+const SHORTENERS: &[&str] = &[
+    "amzn.to",
+    "bit.ly",
+    "buff.ly",
+    "cutt.ly",
+    "goo.gl",
+    "is.gd",
+    "lnkd.in",
+    "ow.ly",
+    "rebrand.ly",
+    "shorturl.at",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+];
+// Query parameters that only exist to track who clicked, `utm_` ones are matched by prefix
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "mc_cid",
+    "mc_eid", "_hsenc", "_hsmi", "ref_src",
+];
+// Shorteners pointing at other shorteners are followed this many times
+const MAX_REDIRECTS: usize = 5;
+const EXPAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Expands shortened links, they are requested without following the redirect automatically
+pub struct LinkCleaner {
+    client: reqwest::Client,
+}
+
+impl LinkCleaner {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .redirect(redirect::Policy::none())
+                .timeout(EXPAND_TIMEOUT)
+                .build()
+                .expect("Failed to build link cleaner HTTP client"),
+        }
+    }
+
+    /// Expands and strips a link, returns None if nothing had to change
+    async fn clean(&self, link: &str) -> Option<String> {
+        let mut url = Url::parse(link).ok()?;
+        let mut changed = false;
+
+        for _ in 0..MAX_REDIRECTS {
+            if !url.host_str().is_some_and(is_shortener) {
+                break;
+            }
+
+            match self.expand(&url).await {
+                Ok(Some(target)) => {
+                    url = target;
+                    changed = true;
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::debug!(link, "Error expanding link: {}", e);
+                    break;
+                }
+            }
+        }
+
+        changed |= strip_tracking(&mut url);
+        changed.then(|| url.to_string())
+    }
+
+    // Where a shortened link redirects to, None if it doesn't
+    async fn expand(&self, url: &Url) -> Result<Option<Url>, Error> {
+        let response = self.client.head(url.clone()).send().await?;
+        if !response.status().is_redirection() {
+            return Ok(None);
+        }
+
+        let location = match response.headers().get(reqwest::header::LOCATION) {
+            Some(location) => location.to_str()?,
+            None => return Ok(None),
+        };
+
+        // Locations can be relative to the shortened link
+        Ok(Some(url.join(location)?))
+    }
+}
+
+/// Replies with or reposts cleaned versions of the links in a message
+/// Returns whether the message was replaced
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(false),
+    };
+
+    if !message.content.contains("http") {
+        return Ok(false);
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    if !config.is_enabled(Feature::CleanLinks) {
+        return Ok(false);
+    }
+
+    let mut cleaned = Vec::new();
+    for link in find_links(&message.content) {
+        let allowed = Url::parse(link)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| config.is_link_allowed(host)))
+            .unwrap_or(true);
+        if allowed {
+            continue;
+        }
+
+        if let Some(clean) = data.link_cleaner.clean(link).await {
+            cleaned.push((link, clean));
+        }
+    }
+
+    if cleaned.is_empty() {
+        return Ok(false);
+    }
+
+    // Attachments can't be reposted without downloading them, so those messages get a reply
+    if config.is_enabled(Feature::RepostLinks) && message.attachments.is_empty() {
+        let mut content = message.content.clone();
+        for (link, clean) in &cleaned {
+            content = content.replace(link, clean);
+        }
+
+        message.delete(ctx).await?;
+        let parts = webhooks::split(&content, webhooks::MAX_PART_LENGTH);
+        if let Err(e) = webhooks::repost(ctx, message, parts, Vec::new()).await {
+            tracing::warn!(
+                channel = message.channel_id.0,
+                "Error reposting cleaned links: {}",
+                e
+            );
+        }
+
+        return Ok(true);
+    }
+
+    let mut reply = "Cleaned link(s):".to_string();
+    for (_, clean) in &cleaned {
+        if reply.len() + clean.len() + 1 > 2000 {
+            break;
+        }
+        reply.push('\n');
+        reply.push_str(clean);
+    }
+
+    mentions::send_message(&ctx.http, message.channel_id, Mentions::Nothing, |m| {
+        m.content(reply).reference_message(message)
+    })
+    .await?;
+
+    Ok(false)
+}
+
+// Links in a message, without the <> that suppress embeds or trailing punctuation
+pub fn find_links(content: &str) -> Vec<&str> {
+    content
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('<').trim_end_matches('>'))
+        .map(|word| word.trim_end_matches(['.', ',', '!', '?', ')', ']']))
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .collect()
+}
+
+fn is_shortener(host: &str) -> bool {
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    SHORTENERS.contains(&host)
+}
+
+// Removes tracking parameters, returns whether there were any
+fn strip_tracking(url: &mut Url) -> bool {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let kept: Vec<&(String, String)> = pairs.iter().filter(|(k, _)| !is_tracking(k)).collect();
+
+    if kept.len() == pairs.len() {
+        return false;
+    }
+
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+
+    true
+}
+
+fn is_tracking(param: &str) -> bool {
+    let param = param.to_ascii_lowercase();
+    param.starts_with("utm_") || TRACKING_PARAMS.contains(&param.as_str())
+}
@@ -0,0 +1,116 @@
+// LLM summaries
+// Uses an OpenAI compatible chat completions API, enabled by setting LLM_URL
+// (e.g. https://api.openai.com/v1), LLM_MODEL and LLM_API_KEY if the API needs one. Daily
+// digests and thread summaries send chat messages to it, so servers opt in to each of them.
+// With a worker the requests are made by the worker process.
+use std::{env, sync::Arc, time::Duration};
+
+use serde_json::{json, Value};
+
+use crate::{
+    circuit::CircuitBreaker,
+    worker::{Job, Output, Worker},
+    Error,
+};
+
+// Completions of long transcripts can take a while, but not this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Llm {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    worker: Option<Arc<Worker>>,
+    pub breaker: Arc<CircuitBreaker>,
+}
+
+impl Llm {
+    /// Creates the client if LLM_URL and LLM_MODEL are set
+    pub fn from_env(worker: Option<Arc<Worker>>) -> Option<Arc<Self>> {
+        let url = env::var("LLM_URL").ok()?;
+
+        Some(Arc::new(Self {
+            url: format!("{}/chat/completions", url.trim_end_matches('/')),
+            model: env::var("LLM_MODEL").ok()?,
+            api_key: env::var("LLM_API_KEY").ok(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build LLM HTTP client"),
+            worker,
+            breaker: Arc::new(CircuitBreaker::new("llm")),
+        }))
+    }
+
+    /// Answers `text` following the instructions in `prompt`, in at most `max_tokens` tokens
+    /// Fails right away while the API is failing
+    pub async fn complete(
+        &self,
+        prompt: &str,
+        text: &str,
+        max_tokens: u32,
+    ) -> Result<String, Error> {
+        if !self.breaker.allow() {
+            return Err("The LLM API is unavailable".into());
+        }
+
+        let result = match self.on_worker(prompt, text, max_tokens).await {
+            Some(result) => result,
+            None => match self.request(prompt, text, max_tokens).await {
+                Ok(answer) => answer.ok_or_else(|| "The LLM API sent no answer".into()),
+                Err(e) => Err(e.into()),
+            },
+        };
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(_) => self.breaker.record_failure(),
+        }
+        result
+    }
+
+    // None without a worker or when it can't be reached
+    async fn on_worker(
+        &self,
+        prompt: &str,
+        text: &str,
+        max_tokens: u32,
+    ) -> Option<Result<String, Error>> {
+        let job = Job::Complete {
+            prompt: prompt.to_string(),
+            text: text.to_string(),
+            max_tokens,
+        };
+        Some(match self.worker.as_ref()?.send(&job).await? {
+            Ok(Output::Text(answer)) => Ok(answer),
+            Ok(_) => Err("The worker sent an invalid answer".into()),
+            Err(e) => Err(e),
+        })
+    }
+
+    async fn request(
+        &self,
+        prompt: &str,
+        text: &str,
+        max_tokens: u32,
+    ) -> Result<Option<String>, reqwest::Error> {
+        let mut request = self.client.post(&self.url).json(&json!({
+            "model": self.model,
+            "max_tokens": max_tokens,
+            "messages": [
+                { "role": "system", "content": prompt },
+                { "role": "user", "content": text },
+            ],
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response: Value = request.send().await?.error_for_status()?.json().await?;
+
+        Ok(response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|answer| answer.trim().to_string())
+            .filter(|answer| !answer.is_empty()))
+    }
+}
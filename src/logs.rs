@@ -0,0 +1,316 @@
+// Live logs
+// /logs tail streams the bot's logs into a private thread for TAIL_DURATION, so owners can watch
+// it in production without a shell on the server. `LogTail` is a layer of the tracing subscriber
+// that hands every line RUST_LOG lets through to the running tails, and drops them while none
+// runs. Tails post a batch of lines every FLUSH_INTERVAL, lines that don't fit are skipped and
+// counted. What the tail itself logs while posting is left out, it would feed itself otherwise.
+use std::{fmt, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{
+    field::{Field, Visit},
+    Event, Instrument, Level, Subscriber,
+};
+use tracing_subscriber::{layer, registry::LookupSpan, Layer};
+
+use crate::{
+    mentions::{self, Mentions},
+    Error,
+};
+
+/// How long a tail runs
+pub const TAIL_DURATION: Duration = Duration::from_secs(10 * 60);
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+// Lines a tail can fall behind by before skipping some
+const CAPACITY: usize = 1024;
+// Longest batch, so it fits in a message with its code block
+const MAX_BATCH: usize = 1900;
+const TAIL_SPAN: &str = "log_tail";
+const CRATE: &str = "discordbot_but_rust";
+
+/// A log line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub level: Level,
+    pub target: String,
+    pub text: String,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>5} {}: {}", self.level, self.target, self.text)
+    }
+}
+
+/// Which lines a tail shows, those at `level` or above and, with a module, only the module's
+pub struct Filter {
+    pub level: Level,
+    pub module: Option<String>,
+}
+
+impl Filter {
+    /// Modules can be given without the crate name, like `spam` for `discordbot_but_rust::spam`
+    pub fn matches(&self, line: &Line) -> bool {
+        if line.level > self.level {
+            return false;
+        }
+
+        match &self.module {
+            None => true,
+            Some(module) => [module.clone(), format!("{}::{}", CRATE, module)]
+                .iter()
+                .any(|module| {
+                    line.target == *module
+                        || line
+                            .target
+                            .strip_prefix(module.as_str())
+                            .is_some_and(|rest| rest.starts_with("::"))
+                }),
+        }
+    }
+}
+
+/// Sends the log lines to the running tails
+#[derive(Clone)]
+pub struct LogTail {
+    sender: broadcast::Sender<Line>,
+}
+
+impl Default for LogTail {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+}
+
+impl<S> Layer<S> for LogTail
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: layer::Context<'_, S>) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        if ctx
+            .event_scope(event)
+            .into_iter()
+            .flatten()
+            .any(|span| span.name() == TAIL_SPAN)
+        {
+            return;
+        }
+
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        // Only fails when the last tail stopped meanwhile
+        let _ = self.sender.send(Line {
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            text: fields.text(),
+        });
+    }
+}
+
+// The message and then the other fields as `name=value`, like the fmt layer writes them
+#[derive(Default)]
+struct Fields {
+    message: String,
+    others: Vec<String>,
+}
+
+impl Fields {
+    fn text(self) -> String {
+        let mut parts = vec![self.message];
+        parts.extend(self.others);
+        parts.retain(|part| !part.is_empty());
+        parts.join(" ")
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.others.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Fills a batch of at most MAX_BATCH bytes with the lines, returns how many didn't fit
+pub fn batch(lines: &[Line]) -> (String, usize) {
+    let mut batch = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        // Code blocks can't be closed from inside a line
+        let line = line.to_string().replace("```", "`\u{200b}``");
+        if batch.len() + line.len() + 1 > MAX_BATCH {
+            if batch.is_empty() {
+                let mut end = MAX_BATCH;
+                while !line.is_char_boundary(end) {
+                    end -= 1;
+                }
+                batch.push_str(&line[..end]);
+                return (batch, lines.len() - i - 1);
+            }
+            return (batch, lines.len() - i);
+        }
+        batch.push_str(&line);
+        batch.push('\n');
+    }
+
+    (batch, 0)
+}
+
+/// Streams the logs matching `filter` into `channel` for TAIL_DURATION
+pub fn spawn(
+    http: std::sync::Arc<serenity::Http>,
+    tail: &LogTail,
+    channel: serenity::ChannelId,
+    filter: Filter,
+) {
+    let mut receiver = tail.sender.subscribe();
+    tokio::spawn(
+        async move {
+            let deadline = tokio::time::Instant::now() + TAIL_DURATION;
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            let mut lines = Vec::new();
+            let mut skipped = 0;
+
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => break,
+                    line = receiver.recv() => match line {
+                        Ok(line) if filter.matches(&line) => lines.push(line),
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(n)) => skipped += n as usize,
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = interval.tick() => {
+                        if let Err(e) = flush(&http, channel, &mut lines, &mut skipped).await {
+                            tracing::warn!("Error posting logs: {}", e);
+                            return;
+                        }
+                    }
+                }
+            }
+
+            if let Err(e) = flush(&http, channel, &mut lines, &mut skipped).await {
+                tracing::warn!("Error posting logs: {}", e);
+            }
+            let _ = mentions::send_message(&http, channel, Mentions::Nothing, |m| {
+                m.content("Stopped tailing the logs.")
+            })
+            .await;
+        }
+        .instrument(tracing::info_span!("log_tail", channel = channel.0)),
+    );
+}
+
+// Posts the lines waiting, and how many were skipped since the last post
+async fn flush(
+    http: &serenity::Http,
+    channel: serenity::ChannelId,
+    lines: &mut Vec<Line>,
+    skipped: &mut usize,
+) -> Result<(), Error> {
+    if lines.is_empty() && *skipped == 0 {
+        return Ok(());
+    }
+
+    let (batch, left_out) = batch(lines);
+    lines.clear();
+    let mut content = String::new();
+    if !batch.is_empty() {
+        content = format!("```\n{}```", batch);
+    }
+    let skipped_now = std::mem::take(skipped) + left_out;
+    if skipped_now > 0 {
+        content.push_str(&format!("*{} lines skipped*", skipped_now));
+    }
+
+    mentions::send_message(http, channel, Mentions::Nothing, |m| m.content(content)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(level: Level, target: &str, text: &str) -> Line {
+        Line {
+            level,
+            target: target.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn filters_match_the_level_and_module() {
+        let spam = line(Level::INFO, "discordbot_but_rust::spam", "Deleted spam");
+        let filter = |level, module: Option<&str>| Filter {
+            level,
+            module: module.map(str::to_string),
+        };
+
+        assert!(filter(Level::INFO, None).matches(&spam));
+        assert!(filter(Level::DEBUG, None).matches(&spam));
+        assert!(!filter(Level::WARN, None).matches(&spam));
+        assert!(filter(Level::INFO, Some("spam")).matches(&spam));
+        assert!(filter(Level::INFO, Some("discordbot_but_rust")).matches(&spam));
+        assert!(!filter(Level::INFO, Some("spa")).matches(&spam));
+        assert!(!filter(Level::INFO, Some("spoilers")).matches(&spam));
+        assert!(filter(Level::INFO, Some("serenity")).matches(&line(
+            Level::WARN,
+            "serenity::gateway::shard",
+            "Reconnecting"
+        )));
+    }
+
+    #[test]
+    fn events_reach_tails_but_not_from_tails() {
+        use tracing_subscriber::prelude::*;
+
+        let tail = LogTail::default();
+        let mut receiver = tail.sender.subscribe();
+        let subscriber = tracing_subscriber::registry().with(tail.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(guild = 1, "Deleted {} messages", 3);
+            tracing::info_span!("log_tail").in_scope(|| tracing::warn!("Error posting logs"));
+        });
+
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            line(
+                Level::WARN,
+                "discordbot_but_rust::logs::tests",
+                "Deleted 3 messages guild=1"
+            )
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn batches_fit_in_a_message() {
+        let lines = vec![
+            line(Level::WARN, "a", "first"),
+            line(Level::INFO, "b", "```"),
+        ];
+        assert_eq!(
+            batch(&lines),
+            (" WARN a: first\n INFO b: `\u{200b}``\n".to_string(), 0)
+        );
+
+        let long = vec![line(Level::INFO, "a", &"x".repeat(1000)); 3];
+        let (batch_text, left_out) = batch(&long);
+        assert!(batch_text.len() <= MAX_BATCH);
+        assert_eq!(left_out, 2);
+
+        // A line longer than a message is cut off
+        let (batch_text, left_out) = batch(&[line(Level::INFO, "a", &"é".repeat(2000))]);
+        assert!(batch_text.len() <= MAX_BATCH);
+        assert_eq!(left_out, 0);
+    }
+}
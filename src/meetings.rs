@@ -0,0 +1,120 @@
+// Meeting attendance
+// /meeting start in a voice channel posts a notice that attendance is being recorded, both
+// where the command was used and in the voice channel's chat, and stores who is there. Voice
+// state updates then record every join and leave, so members can drop out and rejoin and
+// still get their total time. /meeting end closes the meeting and posts who attended and for
+// how long. Time the bot spends offline counts as attended for anyone who was in the channel.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Attendance, Meeting},
+    duration,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Records members joining and leaving voice channels with a meeting running
+pub async fn handle_voice_state(data: &Data, state: &serenity::VoiceState) -> Result<(), Error> {
+    let guild = match state.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+    if state.member.as_ref().is_some_and(|m| m.user.bot) {
+        return Ok(());
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    // Old voice states aren't always cached, so leaving is recorded for every other meeting
+    data.db
+        .leave_meetings(guild, state.user_id, state.channel_id, now)
+        .await?;
+
+    if let Some(channel) = state.channel_id {
+        if let Some(meeting) = data.db.running_meeting(guild, channel).await? {
+            data.db.join_meeting(meeting.id, state.user_id, now).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts that a meeting started and attendance is recorded, in the voice channel's chat too
+pub async fn announce(ctx: &serenity::Context, meeting: &Meeting) -> Result<(), Error> {
+    let voice = serenity::ChannelId(meeting.voice_channel_id as u64);
+    let content = format!(
+        ":red_circle: A meeting{} started in <#{}>, who joins and for how long is recorded \
+        until it ends.",
+        meeting
+            .title
+            .as_ref()
+            .map(|t| format!(" about **{}**", t))
+            .unwrap_or_default(),
+        voice.0
+    );
+
+    let text = serenity::ChannelId(meeting.text_channel_id as u64);
+    mentions::send_message(&ctx.http, text, Mentions::Nothing, |m| m.content(&content)).await?;
+
+    // Voice channels without a chat can't take the notice
+    if text != voice {
+        if let Err(e) =
+            mentions::send_message(&ctx.http, voice, Mentions::Nothing, |m| m.content(&content))
+                .await
+        {
+            tracing::debug!(channel = voice.0, "Error posting meeting notice: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Attendance summary of an ended meeting
+pub fn summary<'a>(
+    e: &'a mut serenity::CreateEmbed,
+    meeting: &Meeting,
+    attendance: &[Attendance],
+) -> &'a mut serenity::CreateEmbed {
+    let ended_at = meeting
+        .ended_at
+        .unwrap_or_else(|| serenity::Timestamp::now().unix_timestamp());
+
+    let mut list = String::new();
+    for (i, attendee) in attendance.iter().enumerate() {
+        let line = format!(
+            "<@{}> {}, joined <t:{}:t>\n",
+            attendee.user_id,
+            format_seconds(attendee.seconds),
+            attendee.first_joined_at
+        );
+        if list.len() + line.len() > 4000 {
+            list.push_str(&format!("...and {} more", attendance.len() - i));
+            break;
+        }
+        list.push_str(&line);
+    }
+    if list.is_empty() {
+        list = "Nobody attended.".to_string();
+    }
+
+    e.title(meeting.title.as_deref().unwrap_or("Meeting attendance"))
+        .description(list)
+        .field("Channel", format!("<#{}>", meeting.voice_channel_id), true)
+        .field("Host", format!("<@{}>", meeting.host_id), true)
+        .field(
+            "Length",
+            format_seconds(ended_at - meeting.started_at),
+            true,
+        )
+        .footer(|f| {
+            f.text(format!(
+                "Meeting #{}, {} attended",
+                meeting.id,
+                attendance.len()
+            ))
+        })
+        .timestamp(serenity::Timestamp::now())
+}
+
+fn format_seconds(seconds: i64) -> String {
+    duration::format(std::time::Duration::from_secs(seconds.max(0) as u64))
+}
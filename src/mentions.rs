@@ -0,0 +1,76 @@
+// Global allowed mentions policy
+// Every outgoing message goes through here so user provided text (server names,
+// reasons, copied messages) can never ping @everyone, @here or arbitrary roles.
+use poise::serenity_prelude as serenity;
+
+/// Which mentions a message is allowed to ping
+pub enum Mentions {
+    /// Users (and the replied-to user) only, the default for everything
+    Users,
+    /// Nothing at all, for messages that repeat other people's content
+    Nothing,
+    /// Users plus specific roles, for features that are meant to ping a role
+    Roles(Vec<serenity::RoleId>),
+    /// Only this user, for pinging someone about text other people may have written
+    User(serenity::UserId),
+}
+
+impl Mentions {
+    pub fn apply<'a>(
+        &self,
+        m: &'a mut serenity::CreateAllowedMentions,
+    ) -> &'a mut serenity::CreateAllowedMentions {
+        match self {
+            Mentions::Users => m
+                .empty_parse()
+                .parse(serenity::ParseValue::Users)
+                .replied_user(true),
+            Mentions::Nothing => m.empty_parse().empty_users().empty_roles(),
+            Mentions::Roles(roles) => m
+                .empty_parse()
+                .parse(serenity::ParseValue::Users)
+                .roles(roles.iter().copied())
+                .replied_user(true),
+            Mentions::User(user) => m.empty_parse().empty_roles().users([*user]),
+        }
+    }
+}
+
+/// Policy used for all poise replies unless a command picks another one
+pub fn framework_default() -> serenity::CreateAllowedMentions {
+    let mut m = serenity::CreateAllowedMentions::default();
+    Mentions::Users.apply(&mut m);
+    m
+}
+
+/// Sends a message with `mentions` enforced, after the builder ran so it can't be overridden
+pub async fn send_message<'a, F>(
+    http: impl AsRef<serenity::Http>,
+    channel: serenity::ChannelId,
+    mentions: Mentions,
+    f: F,
+) -> serenity::Result<serenity::Message>
+where
+    for<'b> F: FnOnce(&'b mut serenity::CreateMessage<'a>) -> &'b mut serenity::CreateMessage<'a>,
+{
+    channel
+        .send_message(http, |m| f(m).allowed_mentions(|a| mentions.apply(a)))
+        .await
+}
+
+/// Executes a webhook with `mentions` enforced, like `send_message`
+pub async fn execute_webhook<'a, F>(
+    http: impl AsRef<serenity::Http>,
+    webhook: &serenity::Webhook,
+    mentions: Mentions,
+    f: F,
+) -> serenity::Result<Option<serenity::Message>>
+where
+    for<'b> F: FnOnce(&'b mut serenity::ExecuteWebhook<'a>) -> &'b mut serenity::ExecuteWebhook<'a>,
+{
+    webhook
+        .execute(http, false, |w| {
+            f(w).allowed_mentions(|a| mentions.apply(a))
+        })
+        .await
+}
@@ -0,0 +1,267 @@
+// Message archive
+// Guilds that turn on the message_archive feature get their members' messages stored in an FTS5
+// index that /search searches. Edited messages are updated and deleted ones removed, and turning
+// the feature off deletes the whole archive. Private threads aren't archived, and searches only
+// cover channels the searching member can read the history of, with threads going by their
+// parent channel like they do on Discord. In privacy mode only hashes of the words are stored,
+// so searches have to match whole words.
+use poise::serenity_prelude as serenity;
+
+use crate::{config::Feature, notify, privacy, Data, Error};
+
+/// Results shown on one page of /search
+pub const PAGE_SIZE: i64 = 5;
+
+/// Turns what a member typed into an FTS5 query matching messages with all of its words, or
+/// words they start with. Words are quoted so FTS5 syntax in a query is searched for as text.
+/// Archives of guilds in privacy mode, `hashed_in`, are searched by the hashes of whole words
+pub fn fts_query(input: &str, hashed_in: Option<serenity::GuildId>) -> Option<String> {
+    let terms: Vec<_> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| match hashed_in {
+            Some(guild) => Some(format!("\"{}\"", privacy::hash_word(guild, w)?)),
+            None => Some(format!("\"{}\"*", w.to_lowercase())),
+        })
+        .collect::<Option<_>>()?;
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Channels of a guild a member can read the history of, the ones whose archived messages they
+/// can search
+pub fn readable_channels(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    member: &serenity::Member,
+) -> Vec<serenity::ChannelId> {
+    ctx.cache
+        .guild_field(guild, |g| {
+            g.channels
+                .values()
+                .filter_map(|c| match c {
+                    serenity::Channel::Guild(channel) => Some(channel),
+                    _ => None,
+                })
+                .filter(|channel| {
+                    g.user_permissions_in(channel, member)
+                        .is_ok_and(|p| p.view_channel() && p.read_message_history())
+                })
+                .map(|channel| channel.id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Archives a message if its guild has the archive on
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+    if message.content.trim().is_empty()
+        || !data
+            .guild_configs
+            .get(guild)
+            .await?
+            .is_enabled(Feature::MessageArchive)
+    {
+        return Ok(());
+    }
+
+    let parent = match notify::readable_channel(ctx, guild, message.channel_id) {
+        Some(parent) => parent,
+        None => return Ok(()),
+    };
+    data.db.archive_message(guild, parent.id, message).await
+}
+
+/// Keeps the archived content of edited messages up to date
+pub async fn handle_edit(data: &Data, event: &serenity::MessageUpdateEvent) -> Result<(), Error> {
+    // Updates without content are embeds being resolved, not edits
+    match (&event.content, event.guild_id) {
+        (Some(content), Some(guild)) => {
+            data.db
+                .edit_archived_message(guild, event.id, content)
+                .await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Removes deleted messages from the archive
+pub async fn handle_delete(data: &Data, messages: &[serenity::MessageId]) -> Result<(), Error> {
+    data.db.delete_archived_messages(messages).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GuildConfig, db::Db};
+
+    fn message(id: u64, channel: u64, author: u64, content: &str) -> serenity::Message {
+        serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "channel_id": channel.to_string(),
+            "author": {
+                "id": author.to_string(),
+                "username": "member",
+                "discriminator": "0001",
+                "avatar": null
+            },
+            "content": content,
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn queries_need_every_word() {
+        assert_eq!(
+            fts_query("release date?", None).as_deref(),
+            Some("\"release\"* \"date\"*")
+        );
+        assert_eq!(
+            fts_query("NOT content:x", None).as_deref(),
+            Some("\"not\"* \"content\"* \"x\"*")
+        );
+        assert_eq!(fts_query("...", None), None);
+    }
+
+    #[tokio::test]
+    async fn searches_only_cover_readable_channels() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+        let (general, staff, thread) = (
+            serenity::ChannelId(10),
+            serenity::ChannelId(11),
+            serenity::ChannelId(12),
+        );
+        let search = |query: &str,
+                      readable: Vec<serenity::ChannelId>,
+                      channel: Option<serenity::ChannelId>,
+                      author: Option<serenity::UserId>| {
+            let db = db.clone();
+            let query = fts_query(query, None).unwrap();
+            async move {
+                db.search_archive(guild, &query, &readable, channel, author, PAGE_SIZE, 0)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| m.message_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        for (id, parent, channel, author, content) in [
+            (100, general, general, 1000, "the release date is friday"),
+            (101, staff, staff, 1000, "the release date slipped"),
+            (102, general, thread, 1001, "release notes are up"),
+        ] {
+            db.archive_message(guild, parent, &message(id, channel.0, author, content))
+                .await
+                .unwrap();
+        }
+        db.archive_message(
+            serenity::GuildId(2),
+            general,
+            &message(103, general.0, 1000, "release elsewhere"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            search("release date", vec![general], None, None).await,
+            [100]
+        );
+        let mut both = search("release", vec![general, staff], None, None).await;
+        both.sort_unstable();
+        assert_eq!(both, [100, 101, 102]);
+        // Threads are found through their parent
+        let mut in_general = search("release", vec![general], Some(general), None).await;
+        in_general.sort_unstable();
+        assert_eq!(in_general, [100, 102]);
+        assert_eq!(
+            search("release", vec![general], Some(thread), None).await,
+            [102]
+        );
+        assert_eq!(
+            search("release", vec![general], None, Some(serenity::UserId(1001))).await,
+            [102]
+        );
+        assert!(search("release", vec![], None, None).await.is_empty());
+
+        db.edit_archived_message(guild, serenity::MessageId(100), "moved to monday")
+            .await
+            .unwrap();
+        assert_eq!(search("monday", vec![general], None, None).await, [100]);
+        db.delete_archived_messages(&[serenity::MessageId(100), serenity::MessageId(102)])
+            .await
+            .unwrap();
+        assert!(search("release monday", vec![general], None, None)
+            .await
+            .is_empty());
+        assert_eq!(db.clear_archive(guild).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn privacy_mode_only_stores_hashed_words() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+        let general = serenity::ChannelId(10);
+        let search = |query: &str, hashed: bool| {
+            let db = db.clone();
+            let query = fts_query(query, hashed.then_some(guild)).unwrap();
+            async move {
+                db.search_archive(guild, &query, &[general], None, None, PAGE_SIZE, 0)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|m| (m.message_id, m.snippet))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        db.archive_message(guild, general, &message(100, 10, 1000, "Release on friday"))
+            .await
+            .unwrap();
+        assert!(db.stores_content(guild).await.unwrap());
+
+        let mut config = GuildConfig::default();
+        config.features.insert(Feature::PrivacyMode, true);
+        db.save_guild_config(guild, &config).await.unwrap();
+        assert!(!db.stores_content(guild).await.unwrap());
+        assert_eq!(db.redact_content(guild).await.unwrap(), 1);
+        db.archive_message(guild, general, &message(101, 10, 1000, "release notes"))
+            .await
+            .unwrap();
+
+        // Words are only found whole, and nothing readable is left
+        let found = search("RELEASE", true).await;
+        assert_eq!(
+            found.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [101, 100]
+        );
+        assert!(found.iter().all(|(_, snippet)| !snippet.contains("elease")));
+        assert!(search("releas", true).await.is_empty());
+        assert!(search("release", false).await.is_empty());
+
+        db.edit_archived_message(guild, serenity::MessageId(101), "moved to monday")
+            .await
+            .unwrap();
+        assert_eq!(search("monday", true).await.len(), 1);
+    }
+}
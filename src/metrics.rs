@@ -0,0 +1,270 @@
+// Prometheus metrics
+// Counters are kept in memory and served in the Prometheus text format on `/metrics` of
+// METRICS_ADDR, e.g. METRICS_ADDR=127.0.0.1:9100. Without it no server is started.
+// Gateway latency is read from the shard runners and event loop lag from the watchdog on
+// every scrape. Unless VOTE_ADDR is set the same server receives top.gg vote webhooks, see
+// `votes`.
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    env,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    votes::VoteWebhook,
+    watchdog::{self, LoopWatchdog},
+};
+
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4";
+
+#[derive(Default)]
+pub struct Metrics {
+    commands: Mutex<BTreeMap<String, u64>>,
+    events: Mutex<BTreeMap<&'static str, u64>>,
+    errors: Mutex<BTreeMap<&'static str, u64>>,
+    rate_limit_hits: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts an invocation of a command by its qualified name, like `config set`
+    pub fn command(&self, name: &str) {
+        let mut commands = self.commands.lock().unwrap();
+        match commands.get_mut(name) {
+            Some(count) => *count += 1,
+            None => {
+                commands.insert(name.to_string(), 1);
+            }
+        }
+    }
+
+    /// Counts a gateway event by its type
+    pub fn event(&self, name: &'static str) {
+        *self.events.lock().unwrap().entry(name).or_default() += 1;
+    }
+
+    /// Counts an error, `source` is what failed, like `command` or `event`
+    pub fn error(&self, source: &'static str) {
+        *self.errors.lock().unwrap().entry(source).or_default() += 1;
+    }
+
+    /// Counts a message that was ignored because its author was rate limited
+    pub fn rate_limit_hit(&self) {
+        self.rate_limit_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, latencies: &[(u64, Option<f64>)], watchdog: &LoopWatchdog) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "bot_commands_total",
+            "counter",
+            "Commands invoked, by name",
+        );
+        for (name, count) in self.commands.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "bot_commands_total{{command=\"{}\"}} {}",
+                escape(name),
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "bot_events_total",
+            "counter",
+            "Gateway events received, by type",
+        );
+        for (name, count) in self.events.lock().unwrap().iter() {
+            let _ = writeln!(out, "bot_events_total{{event=\"{}\"}} {}", name, count);
+        }
+
+        header(
+            &mut out,
+            "bot_errors_total",
+            "counter",
+            "Errors in command and event handlers",
+        );
+        for (source, count) in self.errors.lock().unwrap().iter() {
+            let _ = writeln!(out, "bot_errors_total{{source=\"{}\"}} {}", source, count);
+        }
+
+        header(
+            &mut out,
+            "bot_rate_limit_hits_total",
+            "counter",
+            "Messages ignored because their author was rate limited",
+        );
+        let _ = writeln!(
+            out,
+            "bot_rate_limit_hits_total {}",
+            self.rate_limit_hits.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "bot_gateway_latency_seconds",
+            "gauge",
+            "Heartbeat latency per shard",
+        );
+        // Shards that haven't had a heartbeat acknowledged yet have no latency to report
+        for (shard, latency) in latencies {
+            if let Some(latency) = latency {
+                let _ = writeln!(
+                    out,
+                    "bot_gateway_latency_seconds{{shard=\"{}\"}} {}",
+                    shard, latency
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "bot_event_loop_lag_ms",
+            "gauge",
+            "How late the runtime woke up the watchdog in its latest sample",
+        );
+        let _ = writeln!(
+            out,
+            "bot_event_loop_lag_ms {}",
+            watchdog.last_lag().as_secs_f64() * 1000.0
+        );
+
+        header(
+            &mut out,
+            "bot_event_loop_stalls_total",
+            "counter",
+            "Watchdog samples where the runtime was blocked",
+        );
+        let _ = writeln!(out, "bot_event_loop_stalls_total {}", watchdog.stalls());
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Starts the metrics server if METRICS_ADDR is set
+pub fn spawn_server(
+    metrics: Arc<Metrics>,
+    shard_manager: Arc<tokio::sync::Mutex<serenity::ShardManager>>,
+    watchdog: Arc<LoopWatchdog>,
+    votes: Option<Arc<VoteWebhook>>,
+) {
+    let addr: SocketAddr = match env::var("METRICS_ADDR").map(|addr| addr.parse()) {
+        Ok(Ok(addr)) => addr,
+        Ok(Err(e)) => {
+            tracing::warn!("Ignoring invalid METRICS_ADDR: {}", e);
+            return;
+        }
+        Err(_) => return,
+    };
+
+    let server = match Server::try_bind(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            tracing::error!(%addr, "Error starting metrics server: {}", e);
+            return;
+        }
+    };
+
+    let make_service = make_service_fn(move |_| {
+        let metrics = Arc::clone(&metrics);
+        let shard_manager = Arc::clone(&shard_manager);
+        let watchdog = Arc::clone(&watchdog);
+        let votes = votes.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = Arc::clone(&metrics);
+                let shard_manager = Arc::clone(&shard_manager);
+                let watchdog = Arc::clone(&watchdog);
+                let votes = votes.clone();
+
+                async move {
+                    if let Some(votes) = &votes {
+                        if request.method() == Method::POST && request.uri().path() == "/topgg/vote"
+                        {
+                            return Ok::<_, Infallible>(votes.handle(request).await);
+                        }
+                    }
+
+                    Ok(respond(request, &metrics, &shard_manager, &watchdog).await)
+                }
+            }))
+        }
+    });
+
+    tracing::info!(%addr, "Serving metrics");
+    watchdog::spawn("metrics server", async move {
+        if let Err(e) = server.serve(make_service).await {
+            tracing::error!("Metrics server stopped: {}", e);
+        }
+    });
+}
+
+async fn respond(
+    request: Request<Body>,
+    metrics: &Metrics,
+    shard_manager: &tokio::sync::Mutex<serenity::ShardManager>,
+    watchdog: &LoopWatchdog,
+) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+
+    let latencies: Vec<_> = {
+        let manager = shard_manager.lock().await;
+        let runners = manager.runners.lock().await;
+        runners
+            .iter()
+            .map(|(id, runner)| (id.0, runner.latency.map(|l| l.as_secs_f64())))
+            .collect()
+    };
+
+    let mut response = Response::new(Body::from(metrics.render(&latencies, watchdog)));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, CONTENT_TYPE_TEXT.parse().unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_event_loop_metrics() {
+        let metrics = Metrics::default();
+        let out = metrics.render(&[], &LoopWatchdog::default());
+
+        assert!(out.contains("\nbot_event_loop_lag_ms 0\n"));
+        assert!(out.contains("\nbot_event_loop_stalls_total 0\n"));
+    }
+}
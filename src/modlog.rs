@@ -0,0 +1,234 @@
+// Logging of moderation actions and message changes
+// Every action is printed and, if the guild has a log channel configured, posted there.
+// Edited and deleted messages go to the separate message log channel, deleted content is
+// recovered from serenity's message cache so only recent messages can be shown.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    incidents::{self, EventKind},
+    mentions::{self, Mentions},
+    Context, Data, Error,
+};
+
+// Embed field values can't be longer than this
+const FIELD_LIMIT: usize = 1024;
+
+/// A moderation action that was taken
+pub struct Action<'a> {
+    pub name: &'a str,
+    pub target: &'a serenity::User,
+    pub reason: &'a str,
+    /// Extra details shown with the action, e.g. the timeout length
+    pub details: Option<String>,
+}
+
+pub async fn log_action(ctx: Context<'_>, action: Action<'_>) -> Result<(), Error> {
+    let guild = ctx.guild_id().ok_or("Moderation actions need a guild")?;
+    post_action(ctx.discord(), ctx.data(), guild, ctx.author(), action).await
+}
+
+/// Logs an action that wasn't taken through a command, e.g. by automod
+pub async fn post_action(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    moderator: &serenity::User,
+    action: Action<'_>,
+) -> Result<(), Error> {
+    tracing::info!(
+        moderator = %moderator.tag(),
+        member = %action.target.tag(),
+        guild = guild.0,
+        reason = action.reason,
+        "Member {}",
+        action.name
+    );
+    incidents::record(
+        data,
+        guild,
+        EventKind::Action,
+        &format!(
+            "{} {} {}: {}",
+            moderator.tag(),
+            action.name,
+            action.target.tag(),
+            action.reason
+        ),
+    )
+    .await;
+
+    let channel = match data.guild_configs.get(guild).await?.log_channel {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(format!("Member {}", action.name))
+                .field(
+                    "User",
+                    format!("{} (<@{}>)", action.target.tag(), action.target.id.0),
+                    true,
+                )
+                .field(
+                    "Moderator",
+                    format!("{} (<@{}>)", moderator.tag(), moderator.id.0),
+                    true,
+                )
+                .field("Reason", action.reason, false);
+
+            if let Some(details) = &action.details {
+                e.field("Details", details, false);
+            }
+
+            e.timestamp(serenity::Timestamp::now())
+        })
+    })
+    .await;
+
+    // Failing to log shouldn't fail the action itself
+    if let Err(e) = result {
+        tracing::warn!("Error posting to log channel: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Logs a deleted message, with its content if it was still cached
+pub async fn log_delete(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    channel: serenity::ChannelId,
+    message: serenity::MessageId,
+) -> Result<(), Error> {
+    let log_channel = match data.guild_configs.get(guild).await?.message_log_channel {
+        Some(log_channel) => log_channel,
+        None => return Ok(()),
+    };
+
+    let cached = ctx.cache.message(channel, message);
+    if cached.as_ref().is_some_and(|m| m.author.bot) {
+        return Ok(());
+    }
+
+    post_message_log(ctx, log_channel, |e| {
+        e.title("Message deleted")
+            .field("Channel", format!("<#{}>", channel.0), true);
+
+        match &cached {
+            Some(message) => e
+                .field("Author", author_field(&message.author), true)
+                .field("Content", content_field(&message.content), false),
+            None => e.field("Content", "*Not cached*", false),
+        }
+    })
+    .await;
+
+    Ok(())
+}
+
+/// Logs several messages that were deleted at once, e.g. by a purge
+pub async fn log_bulk_delete(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    channel: serenity::ChannelId,
+    messages: &[serenity::MessageId],
+) -> Result<(), Error> {
+    let log_channel = match data.guild_configs.get(guild).await?.message_log_channel {
+        Some(log_channel) => log_channel,
+        None => return Ok(()),
+    };
+
+    post_message_log(ctx, log_channel, |e| {
+        e.title("Messages bulk deleted")
+            .field("Channel", format!("<#{}>", channel.0), true)
+            .field("Count", messages.len(), true)
+    })
+    .await;
+
+    Ok(())
+}
+
+/// Logs an edited message, with the old content if it was still cached
+pub async fn log_edit(
+    ctx: &serenity::Context,
+    data: &Data,
+    old: Option<&serenity::Message>,
+    event: &serenity::MessageUpdateEvent,
+) -> Result<(), Error> {
+    let guild = match event.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    // Updates without content are embeds being resolved, not edits
+    let new_content = match &event.content {
+        Some(content) => content,
+        None => return Ok(()),
+    };
+    if old.is_some_and(|m| &m.content == new_content) {
+        return Ok(());
+    }
+
+    let author = match &event.author {
+        Some(author) if !author.bot => author,
+        _ => return Ok(()),
+    };
+
+    let log_channel = match data.guild_configs.get(guild).await?.message_log_channel {
+        Some(log_channel) => log_channel,
+        None => return Ok(()),
+    };
+
+    let before = match old {
+        Some(old) => content_field(&old.content),
+        None => "*Not cached*".to_string(),
+    };
+
+    post_message_log(ctx, log_channel, |e| {
+        e.title("Message edited")
+            .url(event.id.link(event.channel_id, Some(guild)))
+            .field("Channel", format!("<#{}>", event.channel_id.0), true)
+            .field("Author", author_field(author), true)
+            .field("Before", before, false)
+            .field("After", content_field(new_content), false)
+    })
+    .await;
+
+    Ok(())
+}
+
+async fn post_message_log(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    f: impl FnOnce(&mut serenity::CreateEmbed) -> &mut serenity::CreateEmbed,
+) {
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| f(e).timestamp(serenity::Timestamp::now()))
+    })
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("Error posting to message log channel: {}", e);
+    }
+}
+
+fn author_field(user: &serenity::User) -> String {
+    format!("{} (<@{}>)", user.tag(), user.id.0)
+}
+
+fn content_field(content: &str) -> String {
+    if content.is_empty() {
+        return "*No text*".to_string();
+    }
+
+    if content.chars().count() > FIELD_LIMIT {
+        let mut content: String = content.chars().take(FIELD_LIMIT - 3).collect();
+        content.push_str("...");
+        return content;
+    }
+
+    content.to_string()
+}
@@ -0,0 +1,125 @@
+// Moderator notes
+// Moderators keep free-form notes on users with /note, for things that don't deserve a warning
+// but that the next moderator should know. Notes are shown above the warnings in /warnings,
+// and posted to the log channel when a user with notes joins again. Edits keep what the note
+// said before and who changed it, /note history shows them.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::ModNote,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Longest note, so several still fit in an embed
+pub const MAX_NOTE_LENGTH: usize = 900;
+
+/// Notes as lines of an embed, newest first and only as many as fit in `limit` characters
+pub fn summary(notes: &[ModNote], limit: usize) -> String {
+    let mut list = String::new();
+    for note in notes {
+        let mut line = format!(
+            "**#{}** <t:{}:d> by <@{}>: {}",
+            note.id, note.created_at, note.author_id, note.content
+        );
+        if note.edits > 0 {
+            line.push_str(" *(edited)*");
+        }
+        line.push('\n');
+
+        if list.len() + line.len() + "...".len() > limit {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+    list
+}
+
+/// Tells the moderators when a user they keep notes on joins again
+pub async fn handle_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let guild = member.guild_id;
+    let notes = data.db.notes_for(guild, member.user.id).await?;
+    if notes.is_empty() {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let channel = match config.log_channel {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title("Member with notes joined")
+                .field(
+                    "User",
+                    format!("{} (<@{}>)", member.user.tag(), member.user.id.0),
+                    false,
+                )
+                .field("Notes", summary(&notes, 1000), false)
+                .timestamp(serenity::Timestamp::now())
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    fn note(id: i64, content: &str, edits: i64) -> ModNote {
+        ModNote {
+            id,
+            user_id: 2,
+            author_id: 3,
+            content: content.to_string(),
+            created_at: 0,
+            edits,
+        }
+    }
+
+    #[test]
+    fn summaries_fit_their_limit() {
+        let notes = [
+            note(2, "Argues with staff", 1),
+            note(1, &"x".repeat(200), 0),
+        ];
+        let full = summary(&notes, 1000);
+        assert!(full.starts_with("**#2** <t:0:d> by <@3>: Argues with staff *(edited)*\n"));
+        assert!(full.contains("**#1**"));
+
+        let short = summary(&notes, 100);
+        assert!(short.ends_with("..."));
+        assert!(!short.contains("**#1**"));
+    }
+
+    #[tokio::test]
+    async fn edits_keep_the_previous_content() {
+        let db = Db::memory().await;
+        let (guild, other) = (serenity::GuildId(1), serenity::GuildId(9));
+        let (user, author, editor) = (
+            serenity::UserId(2),
+            serenity::UserId(3),
+            serenity::UserId(4),
+        );
+
+        let id = db.add_note(guild, user, author, "First").await.unwrap();
+        assert!(db.edit_note(guild, id, editor, "Second").await.unwrap());
+        assert!(!db.edit_note(other, id, editor, "Elsewhere").await.unwrap());
+
+        let (current, edits) = db.note_history(guild, id).await.unwrap().unwrap();
+        assert_eq!((current.content.as_str(), current.edits), ("Second", 1));
+        assert_eq!(edits[0].previous_content, "First");
+        assert_eq!(edits[0].editor_id, 4);
+        assert!(db.note_history(other, id).await.unwrap().is_none());
+    }
+}
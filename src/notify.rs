@@ -0,0 +1,278 @@
+// Keyword notifications
+// Members subscribe to keywords with /notify and get a DM when someone uses one in a channel
+// they can read. Keywords are single words, so each guild gets an index from word to
+// subscribers and a message costs one lookup per word however many subscriptions there are.
+// Users can mute channels, and get at most one notification per cooldown.
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{config::GuildCache, db::Db, dm, ratelimit::RateLimiter, Data, Error};
+
+/// Most keywords a user can have per guild
+pub const MAX_KEYWORDS: usize = 10;
+pub const MAX_KEYWORD_LENGTH: usize = 32;
+// A busy channel can use the same keyword over and over, one DM a minute is plenty
+const NOTIFY_COOLDOWN: Duration = Duration::from_secs(60);
+// Characters of the message quoted in the DM
+const PREVIEW_LENGTH: usize = 300;
+
+#[derive(Default)]
+struct KeywordIndex {
+    subscribers: HashMap<String, Vec<serenity::UserId>>,
+    mutes: HashMap<serenity::UserId, HashSet<serenity::ChannelId>>,
+}
+
+/// Keyword subscriptions per guild, loaded from the database on first use
+pub struct Notifications {
+    db: Db,
+    limiter: Arc<RateLimiter>,
+    cache: GuildCache<KeywordIndex>,
+}
+
+impl Notifications {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            limiter: RateLimiter::spawn(1, NOTIFY_COOLDOWN),
+            cache: GuildCache::default(),
+        }
+    }
+
+    async fn get(&self, guild: serenity::GuildId) -> Result<Arc<KeywordIndex>, Error> {
+        self.cache
+            .get_or_load(guild, async {
+                let mut index = KeywordIndex::default();
+                for (user, keyword) in self.db.notify_keywords(guild).await? {
+                    index.subscribers.entry(keyword).or_default().push(user);
+                }
+                for (user, channel) in self.db.notify_mutes(guild).await? {
+                    index.mutes.entry(user).or_default().insert(channel);
+                }
+                Ok(index)
+            })
+            .await
+    }
+
+    /// Keywords a user is subscribed to, sorted
+    pub async fn keywords(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Vec<String>, Error> {
+        let mut keywords: Vec<_> = self
+            .get(guild)
+            .await?
+            .subscribers
+            .iter()
+            .filter(|(_, users)| users.contains(&user))
+            .map(|(keyword, _)| keyword.clone())
+            .collect();
+        keywords.sort();
+
+        Ok(keywords)
+    }
+
+    /// Channels a user muted
+    pub async fn muted(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<Vec<serenity::ChannelId>, Error> {
+        let index = self.get(guild).await?;
+        Ok(index
+            .mutes
+            .get(&user)
+            .map(|channels| channels.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    /// Subscribes a user to a keyword from `normalize_keyword`, returns false if they already were
+    pub async fn add(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        keyword: &str,
+    ) -> Result<bool, Error> {
+        let added = self.db.add_notify_keyword(guild, user, keyword).await?;
+        self.cache.invalidate(guild);
+        Ok(added)
+    }
+
+    /// Unsubscribes a user from a keyword, returns false if they weren't subscribed
+    pub async fn remove(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        keyword: &str,
+    ) -> Result<bool, Error> {
+        let removed = self.db.delete_notify_keyword(guild, user, keyword).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Mutes or unmutes a channel for a user
+    pub async fn set_muted(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        channel: serenity::ChannelId,
+        muted: bool,
+    ) -> Result<(), Error> {
+        self.db.set_notify_mute(guild, user, channel, muted).await?;
+        self.cache.invalidate(guild);
+        Ok(())
+    }
+
+    /// Drops every guild's cached keyword index, returns how many guilds had one
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+}
+
+/// Lowercases a keyword, returns None if it isn't a single word
+pub fn normalize_keyword(keyword: &str) -> Option<String> {
+    let keyword = keyword.trim().to_lowercase();
+    let valid = !keyword.is_empty()
+        && keyword.chars().count() <= MAX_KEYWORD_LENGTH
+        && keyword.chars().all(char::is_alphanumeric);
+
+    valid.then_some(keyword)
+}
+
+// Splits a message the same way keywords are validated, so every keyword can match
+fn words(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// DMs the users whose keywords a message uses
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let index = data.notifications.get(guild).await?;
+    if index.subscribers.is_empty() {
+        return Ok(());
+    }
+
+    // One notification per user even if the message uses several of their keywords
+    let mut matched: HashMap<serenity::UserId, &str> = HashMap::new();
+    for word in words(&message.content) {
+        if let Some((keyword, users)) = index.subscribers.get_key_value(&word) {
+            for user in users {
+                matched.entry(*user).or_insert(keyword);
+            }
+        }
+    }
+    matched.remove(&message.author.id);
+    matched.retain(|user, _| {
+        !index
+            .mutes
+            .get(user)
+            .is_some_and(|channels| channels.contains(&message.channel_id))
+    });
+    if matched.is_empty() {
+        return Ok(());
+    }
+
+    let channel = match readable_channel(ctx, guild, message.channel_id) {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    let server = ctx
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_default();
+    let preview: String = message.content.chars().take(PREVIEW_LENGTH).collect();
+
+    for (user, keyword) in matched {
+        if !can_read(ctx, guild, &channel, user).await
+            || !data.notifications.limiter.try_acquire(user)
+        {
+            continue;
+        }
+
+        let user = match user.to_user(ctx).await {
+            Ok(user) => user,
+            Err(e) => {
+                tracing::warn!(user = user.0, "Error fetching user to notify: {}", e);
+                continue;
+            }
+        };
+        let content = format!(
+            "**{}** mentioned `{}` in <#{}> ({}):\n>>> {}\n{}",
+            message.author.tag(),
+            keyword,
+            message.channel_id.0,
+            server,
+            preview,
+            message.link()
+        );
+        dm::send(
+            ctx,
+            &data.dm_stats,
+            &dm::KEYWORD_NOTIFICATION,
+            &user,
+            message.channel_id,
+            &content,
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// The channel whose permissions decide who can read a message, threads go by their parent
+pub fn readable_channel(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    channel: serenity::ChannelId,
+) -> Option<serenity::GuildChannel> {
+    let channel = ctx.cache.guild_channel(channel).or_else(|| {
+        ctx.cache
+            .guild_field(guild, |g| {
+                g.threads.iter().find(|t| t.id == channel).cloned()
+            })
+            .flatten()
+    })?;
+
+    match channel.kind {
+        // Only its members can see a private thread, whatever their permissions are
+        serenity::ChannelType::PrivateThread => None,
+        serenity::ChannelType::PublicThread | serenity::ChannelType::NewsThread => {
+            ctx.cache.guild_channel(channel.parent_id?)
+        }
+        _ => Some(channel),
+    }
+}
+
+async fn can_read(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    channel: &serenity::GuildChannel,
+    user: serenity::UserId,
+) -> bool {
+    // Fails for users that left, so they are skipped too
+    let member = match guild.member(ctx, user).await {
+        Ok(member) => member,
+        Err(_) => return false,
+    };
+
+    ctx.cache
+        .guild_field(guild, |g| g.user_permissions_in(channel, &member).ok())
+        .flatten()
+        .is_some_and(|p| p.view_channel())
+}
@@ -0,0 +1,157 @@
+// NSFW image scanning for channels that aren't marked NSFW
+// Images are classified by the Sightengine nudity model, enabled by setting SIGHTENGINE_USER
+// and SIGHTENGINE_SECRET. Servers opt in with the `nsfw_scan` feature; flagged images go to the
+// review queue for a moderator to decide on, nothing is deleted automatically.
+use std::{env, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+
+use crate::{
+    circuit::CircuitBreaker,
+    config::{Feature, RoleList},
+    imagehash,
+    reviewqueue::{self, Flag},
+    Data, Error,
+};
+
+const API_URL: &str = "https://api.sightengine.com/1.0/check.json";
+// Scans run in the message pipeline, a hanging request would hold up every later stage
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Classifier {
+    user: String,
+    secret: String,
+    client: reqwest::Client,
+    pub breaker: Arc<CircuitBreaker>,
+}
+
+impl Classifier {
+    /// Creates the classifier if the Sightengine credentials are set
+    pub fn from_env() -> Option<Arc<Self>> {
+        Some(Arc::new(Self {
+            user: env::var("SIGHTENGINE_USER").ok()?,
+            secret: env::var("SIGHTENGINE_SECRET").ok()?,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build NSFW scan HTTP client"),
+            breaker: Arc::new(CircuitBreaker::new("nsfw scan")),
+        }))
+    }
+
+    // Likelihood from 0 to 1 that the image at `url` is NSFW
+    async fn classify(&self, url: &str) -> Result<f64, reqwest::Error> {
+        let response: Value = self
+            .client
+            .get(API_URL)
+            .query(&[
+                ("models", "nudity-2.0"),
+                ("url", url),
+                ("api_user", &self.user),
+                ("api_secret", &self.secret),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // `none` is the probability that there is no nudity at all
+        let safe = response["nudity"]["none"].as_f64().unwrap_or(1.0);
+        Ok(1.0 - safe)
+    }
+}
+
+/// Classifies the images in a message and flags it for review if one scores too high
+/// Returns whether the message was flagged
+pub async fn scan(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let (classifier, guild) = match (&data.classifier, message.guild_id) {
+        (Some(classifier), Some(guild)) => (classifier, guild),
+        _ => return Ok(false),
+    };
+
+    if !message.attachments.iter().any(imagehash::is_image) {
+        return Ok(false);
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    if !config.is_enabled(Feature::NsfwScan) {
+        return Ok(false);
+    }
+
+    let roles = message
+        .member
+        .as_ref()
+        .map(|m| m.roles.as_slice())
+        .unwrap_or_default();
+    if config.has_role_in(RoleList::NsfwExempt, roles) || is_nsfw_channel(ctx, message).await {
+        return Ok(false);
+    }
+
+    let threshold = config.nsfw_threshold() as f64 / 100.0;
+    let mut flagged = None;
+
+    for attachment in message
+        .attachments
+        .iter()
+        .filter(|a| imagehash::is_image(a))
+    {
+        if !classifier.breaker.allow() {
+            break;
+        }
+
+        match classifier.classify(&attachment.url).await {
+            Ok(score) => {
+                classifier.breaker.record_success();
+                if score >= threshold {
+                    flagged = Some((attachment, score));
+                    break;
+                }
+            }
+            Err(e) => {
+                classifier.breaker.record_failure();
+                tracing::warn!("Error classifying image: {}", e);
+            }
+        }
+    }
+
+    let (attachment, score) = match flagged {
+        Some(flagged) => flagged,
+        None => return Ok(false),
+    };
+    reviewqueue::flag(
+        ctx,
+        data,
+        &config,
+        message,
+        Flag {
+            source: "nsfw_scan",
+            rule: "nudity",
+            title: "Possible NSFW image",
+            details: format!("{:.0}% NSFW", score * 100.0),
+            image: Some(&attachment.url),
+        },
+    )
+    .await?;
+
+    Ok(true)
+}
+
+// Threads count as NSFW when their parent channel is
+async fn is_nsfw_channel(ctx: &serenity::Context, message: &serenity::Message) -> bool {
+    let channel = match message.channel_id.to_channel(ctx).await {
+        Ok(serenity::Channel::Guild(channel)) => channel,
+        _ => return false,
+    };
+
+    channel.nsfw
+        || channel
+            .parent_id
+            .and_then(|parent| ctx.cache.guild_channel(parent))
+            .is_some_and(|parent| parent.nsfw)
+}
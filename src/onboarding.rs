@@ -0,0 +1,357 @@
+// Onboarding sequences for new members
+// Admins set up a list of steps with /onboarding, each one a DM sent some time after the
+// previous one: a plain message like a rules summary, a select menu with the guild's
+// `onboarding` role list, or a button that opens a modal for introducing yourself. Every step
+// is a job, so a sequence spanning days survives restarts. Members with closed DMs get the
+// steps in the onboarding channel instead, with a ping, which is also where introductions are
+// posted. The components are handled as events so they keep working after a restart.
+use std::{collections::HashSet, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{GuildConfig, RoleList},
+    db::{Db, Job},
+    jobs::{self, JobKind},
+    mentions::{self, Mentions},
+    welcome, Data, Error,
+};
+
+/// Most steps a guild can have
+pub const MAX_STEPS: usize = 10;
+/// Longest wait before a step
+pub const MAX_DELAY: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Most characters of an introduction
+const MAX_INTRO_LENGTH: u64 = 1000;
+// Discord limits select menus to 25 options
+const MAX_ROLE_OPTIONS: usize = 25;
+const ROLES_PREFIX: &str = "onboarding-roles-";
+const INTRO_PREFIX: &str = "onboarding-intro-";
+const MODAL_PREFIX: &str = "onboarding-modal-";
+
+/// What a step shows besides its message
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum StepKind {
+    /// Only the message
+    #[name = "message"]
+    Message,
+    /// A select menu with the roles of the `onboarding` role list
+    #[name = "roles"]
+    Roles,
+    /// A button to post an introduction
+    #[name = "intro"]
+    Intro,
+}
+
+/// Schedules the onboarding steps of a member that just joined
+pub async fn handle_join(
+    _ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let steps = data.db.onboarding_steps(member.guild_id).await?;
+    if steps.is_empty() {
+        return Ok(());
+    }
+
+    data.db
+        .start_onboarding(member.guild_id, member.user.id)
+        .await?;
+
+    let mut run_at = serenity::Timestamp::now().unix_timestamp();
+    for step in steps {
+        run_at += step.delay_secs;
+        jobs::schedule(
+            &data.db,
+            JobKind::OnboardingStep,
+            member.guild_id,
+            member.user.id,
+            step.id as u64,
+            run_at,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Cancels the steps a member that left didn't get yet
+pub async fn handle_leave(
+    _ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    user: &serenity::User,
+) -> Result<(), Error> {
+    jobs::take(&data.db, JobKind::OnboardingStep, guild, user.id, None).await?;
+    Ok(())
+}
+
+/// Sends the step `target_id` to `user_id`, run by the job scheduler
+pub async fn run_step(ctx: &serenity::Context, db: &Db, job: &Job) -> Result<(), Error> {
+    let guild = serenity::GuildId(job.guild_id as u64);
+    let steps = db.onboarding_steps(guild).await?;
+
+    // The step was removed after the member joined
+    let position = match steps.iter().position(|s| s.id == job.target_id) {
+        Some(position) => position,
+        None => return Ok(()),
+    };
+    let step = &steps[position];
+    let kind = step.kind.parse().unwrap_or(StepKind::Message);
+
+    let config = db.load_guild_config(guild).await?;
+    let user = serenity::UserId(job.user_id as u64).to_user(ctx).await?;
+    let content = welcome::render(&step.content, ctx, guild, &user);
+    let roles = offered_roles(ctx, &config, guild);
+
+    let dm = user
+        .dm(&ctx.http, |m| {
+            m.content(&content);
+            add_components(m, kind, guild, &roles)
+        })
+        .await;
+
+    let via_channel = match dm {
+        Ok(_) => false,
+        Err(e) => {
+            let channel = match config.onboarding_channel {
+                Some(channel) => channel,
+                None => {
+                    tracing::debug!(user = user.id.0, "Can't DM onboarding step: {}", e);
+                    return Ok(());
+                }
+            };
+
+            mentions::send_message(&ctx.http, channel, Mentions::Users, |m| {
+                m.content(format!("<@{}> {}", user.id.0, content));
+                add_components(m, kind, guild, &roles)
+            })
+            .await?;
+            true
+        }
+    };
+
+    db.record_onboarding_step(guild, user.id, via_channel, position + 1 == steps.len())
+        .await?;
+
+    Ok(())
+}
+
+// The roles of the `onboarding` list that still exist, with their names, highest first
+fn offered_roles(
+    ctx: &serenity::Context,
+    config: &GuildConfig,
+    guild: serenity::GuildId,
+) -> Vec<(serenity::RoleId, String)> {
+    let list = match config.role_lists.get(&RoleList::Onboarding) {
+        Some(list) => list,
+        None => return Vec::new(),
+    };
+
+    let mut roles: Vec<_> = ctx
+        .cache
+        .guild_field(guild, |g| {
+            g.roles
+                .values()
+                .filter(|r| list.contains(&r.id))
+                .map(|r| (r.position, r.id, r.name.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    roles.sort_by_key(|r| std::cmp::Reverse(r.0));
+
+    roles
+        .into_iter()
+        .take(MAX_ROLE_OPTIONS)
+        .map(|(_, id, name)| (id, name))
+        .collect()
+}
+
+fn add_components<'a, 'b>(
+    m: &'b mut serenity::CreateMessage<'a>,
+    kind: StepKind,
+    guild: serenity::GuildId,
+    roles: &[(serenity::RoleId, String)],
+) -> &'b mut serenity::CreateMessage<'a> {
+    match kind {
+        StepKind::Message => m,
+        // Without roles to pick there's nothing to show, the message still goes out
+        StepKind::Roles if roles.is_empty() => m,
+        StepKind::Roles => m.components(|c| {
+            c.create_action_row(|r| {
+                r.create_select_menu(|s| {
+                    s.custom_id(format!("{}{}", ROLES_PREFIX, guild.0))
+                        .placeholder("Pick your roles")
+                        .min_values(0)
+                        .max_values(roles.len() as u64)
+                        .options(|o| {
+                            for (id, name) in roles {
+                                o.create_option(|opt| opt.label(name).value(id.0));
+                            }
+                            o
+                        })
+                })
+            })
+        }),
+        StepKind::Intro => m.components(|c| {
+            c.create_action_row(|r| {
+                r.create_button(|b| {
+                    b.custom_id(format!("{}{}", INTRO_PREFIX, guild.0))
+                        .label("Introduce yourself")
+                        .style(serenity::ButtonStyle::Primary)
+                })
+            })
+        }),
+    }
+}
+
+// The guild a component or modal belongs to, from its custom ID
+fn parse_guild(custom_id: &str, prefix: &str) -> Option<serenity::GuildId> {
+    Some(serenity::GuildId(
+        custom_id.strip_prefix(prefix)?.parse().ok()?,
+    ))
+}
+
+/// Handles the role menu and intro button of onboarding steps
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let custom_id = &interaction.data.custom_id;
+
+    if let Some(guild) = parse_guild(custom_id, ROLES_PREFIX) {
+        let reply = pick_roles(ctx, data, interaction, guild).await?;
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+            })
+            .await?;
+    } else if let Some(guild) = parse_guild(custom_id, INTRO_PREFIX) {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.kind(serenity::InteractionResponseType::Modal)
+                    .interaction_response_data(|d| {
+                        d.custom_id(format!("{}{}", MODAL_PREFIX, guild.0))
+                            .title("Introduce yourself")
+                            .components(|c| {
+                                c.create_action_row(|r| {
+                                    r.create_input_text(|t| {
+                                        t.custom_id("intro")
+                                            .label("Tell everyone a bit about yourself")
+                                            .style(serenity::InputTextStyle::Paragraph)
+                                            .max_length(MAX_INTRO_LENGTH)
+                                            .required(true)
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Gives the picked roles of the list and takes away the others, returns the reply
+async fn pick_roles(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+    guild: serenity::GuildId,
+) -> Result<String, Error> {
+    let config = data.guild_configs.get(guild).await?;
+    let offered = match config.role_lists.get(&RoleList::Onboarding) {
+        Some(offered) => offered,
+        None => return Ok(":x: This server doesn't offer any roles anymore.".to_string()),
+    };
+
+    // Values are only trusted for roles that are still on the list
+    let picked: HashSet<_> = interaction
+        .data
+        .values
+        .iter()
+        .filter_map(|v| v.parse().ok().map(serenity::RoleId))
+        .filter(|r| offered.contains(r))
+        .collect();
+
+    let mut member = match guild.member(ctx, interaction.user.id).await {
+        Ok(member) => member,
+        Err(_) => return Ok(":x: You're not in that server anymore.".to_string()),
+    };
+
+    let add: Vec<_> = picked
+        .iter()
+        .filter(|r| !member.roles.contains(r))
+        .copied()
+        .collect();
+    let remove: Vec<_> = offered
+        .iter()
+        .filter(|r| !picked.contains(r) && member.roles.contains(r))
+        .copied()
+        .collect();
+
+    if !add.is_empty() {
+        member.add_roles(ctx, &add).await?;
+    }
+    if !remove.is_empty() {
+        member.remove_roles(ctx, &remove).await?;
+    }
+
+    Ok(":white_check_mark: Updated your roles".to_string())
+}
+
+/// Posts introductions submitted with the intro modal
+pub async fn handle_modal(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ModalSubmitInteraction,
+) -> Result<(), Error> {
+    let guild = match parse_guild(&interaction.data.custom_id, MODAL_PREFIX) {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let intro = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|c| match c {
+            serenity::ActionRowComponent::InputText(input) => Some(input.value.trim().to_string()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    let config = data.guild_configs.get(guild).await?;
+    let reply = match config.onboarding_channel {
+        _ if intro.is_empty() => ":x: Your introduction is empty.",
+        None => ":x: This server has no channel for introductions.",
+        Some(channel) => {
+            let user = &interaction.user;
+            mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+                m.embed(|e| {
+                    e.author(|a| a.name(user.tag()).icon_url(user.face()))
+                        .title("Introduction")
+                        .description(format!("<@{}>\n\n{}", user.id.0, intro))
+                        .timestamp(serenity::Timestamp::now())
+                })
+            })
+            .await?;
+
+            ":white_check_mark: Posted your introduction"
+        }
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
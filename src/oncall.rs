@@ -0,0 +1,216 @@
+// On-call rotation
+// Moderators register weekly shifts with /oncall add, and /staff pings whoever is on call
+// instead of the whole staff role, falling back to the role when nobody is. A moderator can
+// cover the shifts of another one for a while with /oncall cover, for swaps and holidays. The
+// rotation of the week is posted to the on-call channel on Monday, or as soon as we're back
+// online if we weren't then. Like role schedules, shifts are weekly windows in UTC.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, OncallCover, OncallShift},
+    mentions::{self, Mentions},
+    temproles, watchdog, Error,
+};
+
+// Embed descriptions can't be longer than this
+const DESCRIPTION_LIMIT: usize = 4096;
+// How often we check whether a new week started
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Moderators on call at `minute` of the week and unix timestamp `now`, covers applied
+pub fn on_call(
+    shifts: &[OncallShift],
+    covers: &[OncallCover],
+    minute: i64,
+    now: i64,
+) -> Vec<serenity::UserId> {
+    let mut users: Vec<i64> = shifts
+        .iter()
+        .filter(|s| temproles::in_window(s.starts_at, s.ends_at, minute))
+        .map(|s| s.user_id)
+        .collect();
+
+    for cover in covers {
+        if cover.starts_at > now || now >= cover.ends_at {
+            continue;
+        }
+        if let Some(user) = users.iter_mut().find(|u| **u == cover.covered_id) {
+            *user = cover.user_id;
+        }
+    }
+
+    users.sort_unstable();
+    users.dedup();
+    users
+        .into_iter()
+        .map(|u| serenity::UserId(u as u64))
+        .collect()
+}
+
+/// The shifts of a week and the covers that aren't over, one per line
+pub fn rotation(shifts: &[OncallShift], covers: &[OncallCover]) -> String {
+    let mut lines: Vec<_> = shifts
+        .iter()
+        .map(|s| {
+            format!(
+                "**#{}** `{}` to `{}` <@{}>",
+                s.id,
+                temproles::format_weekly(s.starts_at),
+                temproles::format_weekly(s.ends_at),
+                s.user_id
+            )
+        })
+        .collect();
+
+    lines.extend(covers.iter().map(|c| {
+        format!(
+            "<@{}> covers for <@{}> from <t:{}:f> until <t:{}:f>",
+            c.user_id, c.covered_id, c.starts_at, c.ends_at
+        )
+    }));
+
+    let mut rotation = String::new();
+    for line in lines {
+        if rotation.len() + line.len() + "\n...".len() > DESCRIPTION_LIMIT {
+            rotation.push_str("...");
+            break;
+        }
+        rotation.push_str(&line);
+        rotation.push('\n');
+    }
+    rotation.truncate(rotation.trim_end().len());
+    rotation
+}
+
+/// Moderators on call in a guild right now
+pub async fn on_call_now(
+    db: &Db,
+    guild: serenity::GuildId,
+) -> Result<Vec<serenity::UserId>, Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let shifts = db.oncall_shifts(guild).await?;
+    if shifts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let covers = db.oncall_covers(guild, now).await?;
+
+    Ok(on_call(
+        &shifts,
+        &covers,
+        temproles::current_minute_of_week(),
+        now,
+    ))
+}
+
+// Weeks since the one the unix epoch fell in, starting on Mondays
+fn current_week() -> i64 {
+    let minutes = serenity::Timestamp::now().unix_timestamp() / 60;
+    // The unix epoch was a Thursday
+    (minutes + 3 * 24 * 60).div_euclid(temproles::MINUTES_PER_WEEK)
+}
+
+/// Starts the task that posts the rotation of every week
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("on-call rotation", async move {
+        loop {
+            if let Err(e) = post_rotations(&ctx, &db).await {
+                tracing::warn!("Error posting on-call rotations: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn post_rotations(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let week = current_week();
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    for guild in db.oncall_guilds().await? {
+        let channel = match db.load_guild_config(guild).await?.oncall_channel() {
+            Some(channel) => channel,
+            None => continue,
+        };
+        if !db.claim_oncall_post(guild, week).await? {
+            continue;
+        }
+
+        let shifts = db.oncall_shifts(guild).await?;
+        let covers = db.oncall_covers(guild, now).await?;
+        let sent = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+            m.embed(|e| {
+                e.title("On-call rotation this week")
+                    .description(rotation(&shifts, &covers))
+                    .footer(|f| f.text("Times are in UTC, swap shifts with /oncall cover"))
+            })
+        })
+        .await;
+
+        // Not retried, the rotation can still be looked up with /oncall schedule
+        if let Err(e) = sent {
+            tracing::warn!(
+                guild = guild.0,
+                channel = channel.0,
+                "Error posting on-call rotation: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(id: i64, user_id: i64, starts_at: &str, ends_at: &str) -> OncallShift {
+        OncallShift {
+            id,
+            user_id,
+            starts_at: temproles::parse_weekly(starts_at).unwrap(),
+            ends_at: temproles::parse_weekly(ends_at).unwrap(),
+        }
+    }
+
+    #[test]
+    fn covers_take_over_shifts_while_they_last() {
+        let shifts = [
+            shift(1, 10, "mon 08:00", "mon 16:00"),
+            shift(2, 11, "mon 12:00", "mon 20:00"),
+            // Over the end of the week
+            shift(3, 12, "sun 22:00", "mon 09:00"),
+        ];
+        let covers = [OncallCover {
+            user_id: 13,
+            covered_id: 11,
+            starts_at: 100,
+            ends_at: 200,
+        }];
+        let at = |time| temproles::parse_weekly(time).unwrap();
+        let users = |ids: &[u64]| {
+            ids.iter()
+                .map(|id| serenity::UserId(*id))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(on_call(&shifts, &[], at("mon 08:30"), 0), users(&[10, 12]));
+        assert_eq!(on_call(&shifts, &[], at("mon 13:00"), 0), users(&[10, 11]));
+        assert_eq!(
+            on_call(&shifts, &covers, at("mon 13:00"), 150),
+            users(&[10, 13])
+        );
+        assert_eq!(
+            on_call(&shifts, &covers, at("mon 13:00"), 200),
+            users(&[10, 11])
+        );
+        assert!(on_call(&shifts, &covers, at("wed 12:00"), 150).is_empty());
+
+        assert_eq!(
+            rotation(&shifts[..1], &[]),
+            "**#1** `mon 08:00` to `mon 16:00` <@10>"
+        );
+    }
+}
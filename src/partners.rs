@@ -0,0 +1,177 @@
+// Partner ads
+// Staff register partner servers with /partner add, and every partner interval the ad of the
+// partner that went the longest without one is posted in the partner channel, so they take
+// turns. Each post counts as an impression, which /partner list shows so both sides can see
+// what the partnership is worth. Partnerships can end on a date, after which their ad isn't
+// posted anymore but stays listed until it's removed. When the last ad was posted is stored,
+// so restarting doesn't post one early.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Partner},
+    mentions::{self, Mentions},
+    watchdog, Error,
+};
+
+// How often we check whether an ad is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const MAX_INVITE_CODE_LENGTH: usize = 32;
+
+/// Whether a partnership ended at unix timestamp `now`
+pub fn expired(partner: &Partner, now: i64) -> bool {
+    partner.expires_at.is_some_and(|e| e <= now)
+}
+
+/// The partner whose ad is next, the one that went the longest without one
+pub fn next_partner(partners: &[Partner], now: i64) -> Option<&Partner> {
+    partners
+        .iter()
+        .filter(|p| !expired(p, now))
+        .min_by_key(|p| (p.last_posted_at, p.id))
+}
+
+/// Parses an invite like `discord.gg/abc` or just its code into its link
+pub fn parse_invite(input: &str) -> Option<String> {
+    let input = input.trim();
+    let input = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input);
+    let code = [
+        "discord.gg/",
+        "discord.com/invite/",
+        "discordapp.com/invite/",
+    ]
+    .iter()
+    .find_map(|prefix| input.strip_prefix(prefix))
+    .unwrap_or(input);
+
+    if code.is_empty()
+        || code.len() > MAX_INVITE_CODE_LENGTH
+        || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return None;
+    }
+    Some(format!("https://discord.gg/{}", code))
+}
+
+/// Starts the task that posts partner ads
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("partner ads", async move {
+        loop {
+            if let Err(e) = post_ads(&ctx, &db).await {
+                tracing::warn!("Error posting partner ads: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn post_ads(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    for guild in db.partner_guilds().await? {
+        let config = db.load_guild_config(guild).await?;
+        let channel = match config.partner_channel {
+            Some(channel) => channel,
+            None => continue,
+        };
+        let partners = db.partners(guild).await?;
+        let partner = match next_partner(&partners, now) {
+            Some(partner) => partner,
+            None => continue,
+        };
+        if !db
+            .claim_partner_post(guild, now, config.partner_interval().as_secs() as i64)
+            .await?
+        {
+            continue;
+        }
+
+        let sent = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+            m.content(&partner.invite).embed(|e| {
+                e.title(&partner.name)
+                    .description(&partner.blurb)
+                    .url(&partner.invite)
+                    .footer(|f| f.text("Partner"))
+            })
+        })
+        .await;
+
+        match sent {
+            Ok(_) => db.add_partner_impression(partner.id, now).await?,
+            // Not retried, the next partner gets the next slot
+            Err(e) => {
+                tracing::warn!(
+                    guild = guild.0,
+                    channel = channel.0,
+                    partner = partner.id,
+                    "Error posting partner ad: {}",
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partner(id: i64, last_posted_at: Option<i64>, expires_at: Option<i64>) -> Partner {
+        Partner {
+            id,
+            last_posted_at,
+            expires_at,
+            ..Partner::default()
+        }
+    }
+
+    #[test]
+    fn partners_take_turns_until_they_expire() {
+        let partners = [
+            partner(1, Some(300), None),
+            partner(2, Some(100), None),
+            partner(3, Some(200), None),
+        ];
+        assert_eq!(next_partner(&partners, 1000).map(|p| p.id), Some(2));
+
+        // New partners go first, then the oldest of them
+        let partners = [
+            partner(1, Some(100), None),
+            partner(3, None, None),
+            partner(2, None, None),
+        ];
+        assert_eq!(next_partner(&partners, 1000).map(|p| p.id), Some(2));
+
+        let partners = [partner(1, None, Some(500)), partner(2, Some(900), None)];
+        assert_eq!(next_partner(&partners, 400).map(|p| p.id), Some(1));
+        assert_eq!(next_partner(&partners, 500).map(|p| p.id), Some(2));
+        assert!(next_partner(&partners[..1], 500).is_none());
+    }
+
+    #[test]
+    fn invites_are_links_or_codes() {
+        let link = Some("https://discord.gg/rust-lang".to_string());
+        assert_eq!(parse_invite("https://discord.gg/rust-lang"), link);
+        assert_eq!(parse_invite("discord.com/invite/rust-lang"), link);
+        assert_eq!(parse_invite(" rust-lang "), link);
+        assert_eq!(parse_invite("https://example.com/rust"), None);
+        assert_eq!(parse_invite(""), None);
+    }
+
+    #[tokio::test]
+    async fn ads_are_claimed_once_per_interval() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+
+        assert!(db.claim_partner_post(guild, 1000, 600).await.unwrap());
+        assert!(!db.claim_partner_post(guild, 1599, 600).await.unwrap());
+        assert!(db.claim_partner_post(guild, 1600, 600).await.unwrap());
+    }
+}
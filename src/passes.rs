@@ -0,0 +1,67 @@
+// Channel passes
+// /pass grant gives a member access to a private channel with a member overwrite and schedules
+// a job that deletes the overwrite again. Passes of members who leave are revoked right away,
+// so they don't get the access back if they rejoin before it would have expired.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::Job,
+    jobs::{self, JobKind},
+    Data, Error,
+};
+
+/// What a pass allows in a channel
+pub fn permissions(kind: serenity::ChannelType) -> serenity::Permissions {
+    let text = serenity::Permissions::VIEW_CHANNEL
+        | serenity::Permissions::SEND_MESSAGES
+        | serenity::Permissions::READ_MESSAGE_HISTORY;
+
+    match kind {
+        serenity::ChannelType::Voice | serenity::ChannelType::Stage => {
+            text | serenity::Permissions::CONNECT | serenity::Permissions::SPEAK
+        }
+        _ => text,
+    }
+}
+
+/// Deletes the overwrite of an expired or revoked pass, for `JobKind::RevokePass`
+pub async fn revoke(ctx: &serenity::Context, job: &Job) -> Result<(), Error> {
+    let result = serenity::ChannelId(job.target_id as u64)
+        .delete_permission(
+            &ctx.http,
+            serenity::PermissionOverwriteType::Member(serenity::UserId(job.user_id as u64)),
+        )
+        .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        // The channel or the overwrite were deleted by hand already
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Revokes the passes of a member that left
+pub async fn handle_leave(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    user: &serenity::User,
+) -> Result<(), Error> {
+    for job in jobs::take(&data.db, JobKind::RevokePass, guild, user.id, None).await? {
+        if let Err(e) = revoke(ctx, &job).await {
+            tracing::warn!(
+                channel = job.target_id,
+                user = user.id.0,
+                "Error revoking pass: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
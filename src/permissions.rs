@@ -0,0 +1,129 @@
+// Helpers for turning Discord's "Missing Permissions" errors into something actionable
+use poise::serenity_prelude as serenity;
+
+use crate::{Context, Error};
+
+// JSON error code Discord returns when the bot lacks a permission
+const MISSING_PERMISSIONS: isize = 50013;
+
+// Permissions practically every command needs to be able to answer
+const BASELINE: serenity::Permissions = serenity::Permissions::VIEW_CHANNEL
+    .union(serenity::Permissions::SEND_MESSAGES)
+    .union(serenity::Permissions::EMBED_LINKS)
+    .union(serenity::Permissions::READ_MESSAGE_HISTORY);
+
+/// Whether the error is Discord rejecting a request because of missing permissions
+pub fn is_missing_permissions(error: &Error) -> bool {
+    match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(http_error)) => matches!(
+            http_error.as_ref(),
+            serenity::HttpError::UnsuccessfulRequest(response)
+                if response.error.code == MISSING_PERMISSIONS
+        ),
+        _ => false,
+    }
+}
+
+/// The channel a failed request was for, read from its URL
+pub fn target_channel(error: &Error) -> Option<serenity::ChannelId> {
+    let response = match error.downcast_ref::<serenity::Error>() {
+        Some(serenity::Error::Http(http_error)) => match http_error.as_ref() {
+            serenity::HttpError::UnsuccessfulRequest(response) => response,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    channel_in_path(response.url.path())
+}
+
+// Routes look like /api/v10/channels/<id>/messages
+fn channel_in_path(path: &str) -> Option<serenity::ChannelId> {
+    let mut segments = path.split('/');
+    segments.find(|s| *s == "channels")?;
+    segments.next()?.parse().ok().map(serenity::ChannelId)
+}
+
+/// Figures out which of the permissions the command needs the bot lacks in a channel
+pub fn missing_in_channel(
+    ctx: Context<'_>,
+    channel: serenity::ChannelId,
+) -> Option<serenity::Permissions> {
+    let needed = BASELINE | ctx.command().required_bot_permissions;
+    missing_in(
+        &ctx.discord().cache,
+        ctx.framework().bot_id,
+        channel,
+        needed,
+    )
+}
+
+fn missing_in(
+    cache: &serenity::Cache,
+    bot: serenity::UserId,
+    channel: serenity::ChannelId,
+    needed: serenity::Permissions,
+) -> Option<serenity::Permissions> {
+    let channel = cache.guild_channel(channel)?;
+    let actual = channel.permissions_for_user(cache, bot).ok()?;
+
+    Some(needed - actual)
+}
+
+/// Logs a missing permissions error of an event handler or background job as a warning
+/// with the channel it happened in, since there is nobody to answer. Returns false for other
+/// errors, which are left to the caller
+pub fn log_missing(ctx: &serenity::Context, source: &str, error: &Error) -> bool {
+    if !is_missing_permissions(error) {
+        return false;
+    }
+
+    let channel = target_channel(error);
+    let missing = channel
+        .and_then(|c| missing_in(&ctx.cache, ctx.cache.current_user_id(), c, BASELINE))
+        .filter(|m| !m.is_empty())
+        .map(|m| m.get_permission_names().join(", "));
+    tracing::warn!(
+        source,
+        channel = channel.map(|c| c.0),
+        missing = missing.as_deref().unwrap_or("unknown"),
+        "Missing permissions: {}",
+        error
+    );
+
+    true
+}
+
+/// Builds the message shown to the user when the bot lacks permissions in a channel
+pub fn explain(channel: serenity::ChannelId, missing: Option<serenity::Permissions>) -> String {
+    match missing {
+        Some(missing) if !missing.is_empty() => format!(
+            ":x: I'm missing the **{}** permission(s) in <#{}>.\n\
+            To fix this, ask an admin to grant my role these permissions in the channel's \
+            settings (Edit Channel → Permissions) or in the server's role settings.",
+            missing.get_permission_names().join(", "),
+            channel.0
+        ),
+        // Discord rejected the request but we can't tell why, e.g. the target is above our role
+        _ => format!(
+            ":x: Discord says I'm missing permissions for this in <#{}>, \
+            but I couldn't work out which ones. \
+            Make sure my role is above any roles or members this command acts on.",
+            channel.0
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_channel_of_a_route() {
+        assert_eq!(
+            channel_in_path("/api/v10/channels/123/messages/456"),
+            Some(serenity::ChannelId(123))
+        );
+        assert_eq!(channel_in_path("/api/v10/guilds/123/members/456"), None);
+    }
+}
@@ -0,0 +1,501 @@
+// Message handling pipeline
+// Every message goes through the stages below in order, cheapest first.
+// A stage can stop the pipeline early so expensive stages only run when needed. A stage that
+// fails (missing permissions, a deleted message) is logged and the next one runs anyway.
+use std::time::{Duration, Instant};
+
+use poise::{serenity_prelude as serenity, BoxFuture};
+
+use crate::{
+    alttext,
+    config::ChannelMode,
+    dm, faq, imagehash, levels, links,
+    mentions::{self, Mentions},
+    messagearchive,
+    modlog::{self, Action},
+    notify, nsfw, permissions, spam, spoilers, toxicity, translate, triggers, walls, Data, Error,
+};
+
+// Stages slower than this get logged so we can see what slows down message handling
+const SLOW_STAGE: Duration = Duration::from_millis(250);
+
+/// What the pipeline should do after a stage ran
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+type StageFn = for<'a> fn(
+    &'a serenity::Context,
+    &'a Data,
+    &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>>;
+
+struct Stage {
+    name: &'static str,
+    run: StageFn,
+}
+
+// Ordered from cheap to expensive, new stages should be inserted based on their cost
+const STAGES: &[Stage] = &[
+    Stage {
+        name: "bot_check",
+        run: bot_check,
+    },
+    // A cached config lookup, and nothing else should run in ignored channels
+    Stage {
+        name: "ignored_channel",
+        run: ignored_channel,
+    },
+    // Cheap with the watchlist cached, and before spam so removed messages are summarized too
+    Stage {
+        name: "watchlist",
+        run: watchlist,
+    },
+    // Cheap with the rules cached, and spam shouldn't reach any other stage
+    Stage {
+        name: "spam",
+        run: spam,
+    },
+    Stage {
+        name: "channel_mode",
+        run: channel_mode,
+    },
+    // Expensive, but has to run before stages that stop the pipeline for harmless messages
+    Stage {
+        name: "image_hash",
+        run: image_hash,
+    },
+    // After the image blocklist so blocked images are never reposted
+    Stage {
+        name: "spoilers",
+        run: spoilers,
+    },
+    Stage {
+        name: "links",
+        run: links,
+    },
+    Stage {
+        name: "nsfw_scan",
+        run: nsfw_scan,
+    },
+    Stage {
+        name: "toxicity",
+        run: toxicity,
+    },
+    // After the moderation stages so removed images don't get a reminder
+    Stage {
+        name: "alt_text",
+        run: alt_text,
+    },
+    // After the moderation stages so removed messages aren't mirrored
+    Stage {
+        name: "translation_links",
+        run: translation_links,
+    },
+    // After the moderation stages so removed messages aren't archived
+    Stage {
+        name: "message_archive",
+        run: message_archive,
+    },
+    // Runs after the moderation stages so removed messages don't earn XP
+    Stage {
+        name: "xp",
+        run: xp,
+    },
+    Stage {
+        name: "theme_reactions",
+        run: theme_reactions,
+    },
+    Stage {
+        name: "notify",
+        run: notify,
+    },
+    // Answered questions don't also get a trigger's reply, and the cooldown stands in for
+    // rate limiting
+    Stage {
+        name: "faq",
+        run: faq,
+    },
+    Stage {
+        name: "triggers",
+        run: triggers,
+    },
+];
+
+/// Runs a message through all stages until one of them stops it
+pub async fn handle_message(ctx: &serenity::Context, data: &Data, message: &serenity::Message) {
+    for stage in STAGES {
+        let start = Instant::now();
+        let result = (stage.run)(ctx, data, message).await;
+        let elapsed = start.elapsed();
+
+        if elapsed >= SLOW_STAGE {
+            tracing::warn!(
+                stage = stage.name,
+                "Message stage took {}ms",
+                elapsed.as_millis()
+            );
+        }
+
+        match result {
+            Ok(Flow::Stop) => break,
+            Ok(Flow::Continue) => {}
+            Err(e) => {
+                data.metrics.error("message_stage");
+                if !permissions::log_missing(ctx, stage.name, &e) {
+                    tracing::error!(stage = stage.name, "Error in message stage: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+fn bot_check<'a>(
+    _ctx: &'a serenity::Context,
+    _data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        if message.author.bot {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+// Stops messages in channels set to be ignored
+fn ignored_channel<'a>(
+    _ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        let guild = match message.guild_id {
+            Some(guild) => guild,
+            None => return Ok(Flow::Continue),
+        };
+
+        let config = data.guild_configs.get(guild).await?;
+        if config.channel_modes.get(&message.channel_id) == Some(&ChannelMode::Ignored) {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+// Collects messages of watched members for their next summary
+fn watchlist<'a>(
+    _ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        data.watchlist.handle_message(message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+// Removes messages that break the guild's anti-spam rules
+fn spam<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        if spam::handle_message(ctx, data, message).await? {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+// Removes messages that break the rules of emoji-only channels
+fn channel_mode<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        let guild = match message.guild_id {
+            Some(guild) => guild,
+            None => return Ok(Flow::Continue),
+        };
+
+        let config = data.guild_configs.get(guild).await?;
+        let emoji_only = matches!(
+            config.channel_modes.get(&message.channel_id),
+            Some(ChannelMode::EmojiOnly | ChannelMode::ReactionWall)
+        );
+        if !emoji_only || walls::is_emoji_only(message) {
+            return Ok(Flow::Continue);
+        }
+
+        if let Err(e) = message.delete(ctx).await {
+            tracing::warn!(
+                channel = message.channel_id.0,
+                "Error deleting message in emoji-only channel: {}",
+                e
+            );
+        }
+
+        Ok(Flow::Stop)
+    })
+}
+
+// Removes images that match the guild's blocklist
+fn image_hash<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        let guild = match message.guild_id {
+            Some(guild) => guild,
+            None => return Ok(Flow::Continue),
+        };
+
+        if !message.attachments.iter().any(imagehash::is_image) {
+            return Ok(Flow::Continue);
+        }
+
+        let blocked = data.image_blocklist.get(guild).await?;
+        if blocked.is_empty() {
+            return Ok(Flow::Continue);
+        }
+
+        let tolerance = data.guild_configs.get(guild).await?.image_hash_tolerance();
+        let hashes = data.image_blocklist.hash_attachments(message).await;
+        let hash = match hashes
+            .into_iter()
+            .find(|hash| imagehash::matches(&blocked, *hash, tolerance))
+        {
+            Some(hash) => hash,
+            None => return Ok(Flow::Continue),
+        };
+
+        message.delete(ctx).await?;
+
+        // The message is gone, so the rest of the pipeline shouldn't see it even if this fails
+        let bot = ctx.cache.current_user();
+        let logged = modlog::post_action(
+            ctx,
+            data,
+            guild,
+            &bot.into(),
+            Action {
+                name: "posted a blocked image",
+                target: &message.author,
+                reason: "Matched the image blocklist",
+                details: Some(format!(
+                    "Hash `{:016x}` in <#{}>",
+                    hash, message.channel_id.0
+                )),
+            },
+        )
+        .await;
+        if let Err(e) = logged {
+            if !permissions::log_missing(ctx, "image_hash", &e) {
+                tracing::error!(guild = guild.0, "Error logging blocked image: {:?}", e);
+            }
+        }
+
+        Ok(Flow::Stop)
+    })
+}
+
+// Reposts messages that break a spoiler rule with spoiler tags
+fn spoilers<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        if spoilers::enforce(ctx, data, message).await? {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+// Expands shortened links and strips tracking parameters
+fn links<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        if links::handle_message(ctx, data, message).await? {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+// Flags likely NSFW images for review, the message itself is left alone
+fn nsfw_scan<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        nsfw::scan(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn toxicity<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        toxicity::scan(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn alt_text<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        alttext::check(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn translation_links<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        translate::mirror(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn message_archive<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        messagearchive::handle_message(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn xp<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        levels::handle_message(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn theme_reactions<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        data.themes.handle_message(ctx, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn notify<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        notify::handle_message(ctx, data, message).await?;
+        Ok(Flow::Continue)
+    })
+}
+
+fn faq<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        if faq::handle_message(ctx, data, message).await? {
+            return Ok(Flow::Stop);
+        }
+
+        Ok(Flow::Continue)
+    })
+}
+
+/// Takes a rate limit token for the author, DMing them if they ran out
+/// Handlers that respond to a message should call this before responding
+async fn rate_limited(ctx: &serenity::Context, data: &Data, message: &serenity::Message) -> bool {
+    if data.rate_limiter.try_acquire(message.author.id) {
+        return false;
+    }
+    data.metrics.rate_limit_hit();
+
+    // Attempt to DM the user and tell them to stop spamming
+    dm::send(
+        ctx,
+        &data.dm_stats,
+        &dm::RATE_LIMIT_NOTICE,
+        &message.author,
+        message.channel_id,
+        ":x: You are being rate limited. Please wait a few seconds before sending another message.",
+    )
+    .await;
+
+    true
+}
+
+fn triggers<'a>(
+    ctx: &'a serenity::Context,
+    data: &'a Data,
+    message: &'a serenity::Message,
+) -> BoxFuture<'a, Result<Flow, Error>> {
+    Box::pin(async move {
+        let matches = triggers::evaluate(data, message).await?;
+
+        // Forwarding doesn't answer the author, so it isn't rate limited
+        for channel in &matches.forwards {
+            if let Err(e) = triggers::forward(ctx, *channel, message).await {
+                tracing::warn!(channel = channel.0, "Error forwarding message: {}", e);
+            }
+        }
+
+        let trigger = match matches.reply {
+            Some(trigger) => trigger,
+            None => return Ok(Flow::Continue),
+        };
+
+        if rate_limited(ctx, data, message).await {
+            return Ok(Flow::Stop);
+        }
+
+        if let Some(response) = &trigger.response {
+            mentions::send_message(ctx, message.channel_id, Mentions::Users, |m| {
+                m.content(response).reference_message(message)
+            })
+            .await?;
+        }
+        if let Some(reaction) = trigger.reaction {
+            message.react(ctx, reaction).await?;
+        }
+
+        Ok(Flow::Stop)
+    })
+}
@@ -0,0 +1,263 @@
+// Polls
+// /poll posts a question with one button per option. Every member has one vote and can change
+// it until the poll closes, either at the time given when it was created or when its author or
+// a moderator presses Close. Closing edits the message to show the results and removes the
+// buttons. Votes are stored, so the buttons are handled as events and keep working after a
+// restart, and timed polls are closed by the job scheduler.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Job, Poll},
+    jobs::{self, JobKind},
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Most buttons Discord allows in one row
+pub const MAX_OPTIONS: usize = 5;
+/// Options are button labels, which Discord limits to 80 characters
+pub const MAX_OPTION_LENGTH: usize = 80;
+const BUTTON_PREFIX: &str = "poll";
+// Width of the result bars in characters
+const BAR_WIDTH: usize = 10;
+
+/// Posts a stored poll in its channel, and schedules closing it if it has a time
+pub async fn post(ctx: &serenity::Context, db: &Db, poll: &Poll) -> Result<(), Error> {
+    let message = mentions::send_message(&ctx.http, poll.channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            let mut description = format!("Poll by <@{}>", poll.author.0);
+            if let Some(closes_at) = poll.closes_at {
+                description.push_str(&format!(", closes <t:{}:R>", closes_at));
+            }
+
+            e.title(&poll.question)
+                .description(description)
+                .footer(|f| f.text(format!("Poll #{}, one vote per member", poll.id)))
+        })
+        .components(|c| {
+            c.create_action_row(|r| {
+                for (i, option) in poll.options.iter().enumerate() {
+                    r.create_button(|b| {
+                        b.custom_id(format!("{}-vote-{}-{}", BUTTON_PREFIX, poll.id, i))
+                            .label(option)
+                            .style(serenity::ButtonStyle::Primary)
+                    });
+                }
+                r
+            })
+            .create_action_row(|r| {
+                r.create_button(|b| {
+                    b.custom_id(format!("{}-close-{}", BUTTON_PREFIX, poll.id))
+                        .label("Close")
+                        .style(serenity::ButtonStyle::Secondary)
+                })
+            })
+        })
+    })
+    .await?;
+    db.set_poll_message(poll.id, message.id).await?;
+
+    if let Some(closes_at) = poll.closes_at {
+        jobs::schedule(
+            db,
+            JobKind::ClosePoll,
+            poll.guild,
+            poll.author,
+            poll.id as u64,
+            closes_at,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+enum Button {
+    Vote(i64, usize),
+    Close(i64),
+}
+
+fn parse_button(custom_id: &str) -> Option<Button> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    if let Some(id) = rest.strip_prefix("close-") {
+        return Some(Button::Close(id.parse().ok()?));
+    }
+
+    let (id, option) = rest.strip_prefix("vote-")?.split_once('-')?;
+    Some(Button::Vote(id.parse().ok()?, option.parse().ok()?))
+}
+
+/// Handles the vote and close buttons of polls
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let button = match parse_button(&interaction.data.custom_id) {
+        Some(button) => button,
+        None => return Ok(()),
+    };
+
+    let reply = match button {
+        Button::Vote(id, option) => vote(data, interaction, id, option).await?,
+        Button::Close(id) => match data.db.poll(id).await? {
+            Some(poll) if poll.results_posted => ":x: This poll is closed already.".to_string(),
+            Some(poll) => {
+                let moderator = interaction
+                    .member
+                    .as_ref()
+                    .and_then(|m| m.permissions)
+                    .is_some_and(|p| p.manage_messages());
+                if interaction.user.id != poll.author && !moderator {
+                    ":x: Only whoever made the poll and moderators can close it.".to_string()
+                } else {
+                    close(ctx, &data.db, poll.id).await?;
+                    ":white_check_mark: Closed the poll".to_string()
+                }
+            }
+            None => ":x: That poll doesn't exist anymore.".to_string(),
+        },
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await?;
+
+    Ok(())
+}
+
+// Records a vote and returns the reply for the voter
+async fn vote(
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+    id: i64,
+    option: usize,
+) -> Result<String, Error> {
+    let poll = match data.db.poll(id).await? {
+        Some(poll) if !poll.closed => poll,
+        Some(_) => return Ok(":x: This poll is closed.".to_string()),
+        None => return Ok(":x: That poll doesn't exist anymore.".to_string()),
+    };
+    let label = match poll.options.get(option) {
+        Some(label) => label,
+        None => return Ok(":x: That option doesn't exist.".to_string()),
+    };
+
+    data.db.vote(poll.id, interaction.user.id, option).await?;
+    Ok(format!(
+        ":white_check_mark: You voted for **{}**, press another option to change your vote",
+        label
+    ))
+}
+
+/// Closes the poll `target_id`, run by the job scheduler
+pub async fn close_job(ctx: &serenity::Context, db: &Db, job: &Job) -> Result<(), Error> {
+    match db.poll(job.target_id).await? {
+        Some(poll) => post_results(ctx, db, &poll).await,
+        None => Ok(()),
+    }
+}
+
+/// Closes a poll by hand and shows the results on its message, does nothing if they were
+/// shown already
+pub async fn close(ctx: &serenity::Context, db: &Db, id: i64) -> Result<(), Error> {
+    let poll = match db.poll(id).await? {
+        Some(poll) => poll,
+        None => return Ok(()),
+    };
+
+    // Closed before its time, the pending job would only find it closed
+    if poll.closes_at.is_some() {
+        jobs::take(
+            db,
+            JobKind::ClosePoll,
+            poll.guild,
+            poll.author,
+            Some(id as u64),
+        )
+        .await?;
+    }
+
+    post_results(ctx, db, &poll).await
+}
+
+// Closes the poll and edits the results into its message. Until that worked the poll only
+// counts as closed, so retries post them
+async fn post_results(ctx: &serenity::Context, db: &Db, poll: &Poll) -> Result<(), Error> {
+    if poll.results_posted {
+        return Ok(());
+    }
+    db.close_poll(poll.id).await?;
+
+    let message = match poll.message {
+        Some(message) => message,
+        None => return db.set_poll_results_posted(poll.id).await,
+    };
+    let results = results(db, poll).await?;
+    poll.channel
+        .edit_message(&ctx.http, message, |m| {
+            m.embed(|e| {
+                e.title(&poll.question)
+                    .description(results)
+                    .footer(|f| f.text(format!("Poll #{}, closed", poll.id)))
+                    .timestamp(serenity::Timestamp::now())
+            })
+            .components(|c| c)
+        })
+        .await?;
+    db.set_poll_results_posted(poll.id).await?;
+
+    Ok(())
+}
+
+// Every option with its votes and a bar, the winners in bold
+async fn results(db: &Db, poll: &Poll) -> Result<String, Error> {
+    let votes = db.poll_votes(poll.id).await?;
+    let total: i64 = votes.values().sum();
+    let most = votes.values().copied().max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (i, option) in poll.options.iter().enumerate() {
+        let count = votes.get(&i).copied().unwrap_or(0);
+        let percent = if total > 0 { count * 100 / total } else { 0 };
+        let filled = (percent as usize * BAR_WIDTH + 50) / 100;
+        let name = if count > 0 && count == most {
+            format!("**{}**", option)
+        } else {
+            option.clone()
+        };
+
+        lines.push(format!(
+            "{}\n`{}{}` {} votes ({}%)",
+            name,
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled),
+            count,
+            percent
+        ));
+    }
+    lines.push(format!("{} votes in total, by <@{}>", total, poll.author.0));
+
+    Ok(lines.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons_name_their_poll() {
+        assert!(matches!(
+            parse_button("poll-vote-12-3"),
+            Some(Button::Vote(12, 3))
+        ));
+        assert!(matches!(
+            parse_button("poll-close-12"),
+            Some(Button::Close(12))
+        ));
+        assert!(parse_button("poll-vote-12").is_none());
+        assert!(parse_button("giveaway-12").is_none());
+    }
+}
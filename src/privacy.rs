@@ -0,0 +1,103 @@
+// Privacy mode
+// Guilds with the privacy_mode feature on don't get message content stored. The database layer
+// enforces it for everything that would store what members wrote: the message archive and
+// messages voted to be deleted. Their words are stored as hashes instead, so /search still
+// finds whole words without the database holding readable messages, and turning privacy mode on
+// hashes what was stored before. Digests of such guilds don't get an LLM summary, which would
+// send their messages to an external API. Edited and deleted message logs only come from the
+// in-memory message cache and never touch the database.
+//
+// Words are hashed with HMAC-SHA256 keyed with PRIVACY_KEY and the guild, so whoever holds the
+// database can't find the words by hashing a dictionary. Without PRIVACY_KEY no words are
+// stored at all, and changing it makes what was stored before unsearchable.
+use std::{env, sync::LazyLock};
+
+use hmac::{Hmac, Mac};
+use poise::serenity_prelude as serenity;
+use sha2::Sha256;
+
+// Hashes are cut to 128 bits, plenty to tell words apart at half the size
+const HASH_BYTES: usize = 16;
+
+static KEY: LazyLock<Option<Vec<u8>>> = LazyLock::new(|| {
+    // Tests don't depend on the environment
+    if cfg!(test) {
+        return Some(b"test key".to_vec());
+    }
+    env::var("PRIVACY_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(String::into_bytes)
+});
+
+/// Whether PRIVACY_KEY is set, without it nothing is stored of messages in privacy mode
+pub fn has_key() -> bool {
+    KEY.is_some()
+}
+
+/// The hash a word of a guild is stored as, words are compared case insensitively. None
+/// without PRIVACY_KEY
+pub fn hash_word(guild: serenity::GuildId, word: &str) -> Option<String> {
+    KEY.as_deref().map(|key| keyed_hash(key, guild, word))
+}
+
+fn keyed_hash(key: &[u8], guild: serenity::GuildId, word: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(&guild.0.to_le_bytes());
+    mac.update(word.to_lowercase().as_bytes());
+    mac.finalize().into_bytes()[..HASH_BYTES]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Message content as stored in privacy mode, its words hashed one by one
+pub fn hash_words(guild: serenity::GuildId, content: &str) -> String {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .filter_map(|w| hash_word(guild, w))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::Digest;
+
+    use super::*;
+
+    #[test]
+    fn words_are_hashed_one_by_one() {
+        let guild = serenity::GuildId(1);
+        let hash = |word| hash_word(guild, word).unwrap();
+        assert_eq!(hash("Release"), hash("release"));
+
+        let hashed = hash_words(guild, "The release, date!");
+        assert_eq!(
+            hashed,
+            [hash("the"), hash("release"), hash("date")].join(" ")
+        );
+        assert!(!hashed.contains("release"));
+        assert_eq!(hash_words(guild, " ?! "), "");
+    }
+
+    #[test]
+    fn hashes_are_stable_per_key_and_guild() {
+        let guild = serenity::GuildId(1);
+        let stored = hash_word(guild, "release").unwrap();
+
+        // The same key always gives the same hash, so /search finds what was stored before
+        assert_eq!(stored, keyed_hash(b"test key", guild, "release"));
+        assert_eq!(stored.len(), HASH_BYTES * 2);
+        // Without the key, a hash of the word doesn't find it
+        let sha: String = Sha256::digest(b"release")
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert!(!sha.starts_with(&stored));
+
+        assert_ne!(stored, keyed_hash(b"other key", guild, "release"));
+        assert_ne!(stored, hash_word(serenity::GuildId(2), "release").unwrap());
+    }
+}
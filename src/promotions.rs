@@ -0,0 +1,155 @@
+// Auto-promotion
+// Promotion rules give a role once a member reached a level and has been in the server for a
+// number of days, e.g. Member at level 5 after 7 days and Regular at level 20. Rules are
+// checked when someone levels up and once a day for everyone, the daily sweep catches members
+// that only needed more time. Roles are only ever given, never taken away.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, PromotionRule},
+    levels::Level,
+    watchdog, Error,
+};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+// Members fetched per request while sweeping a guild
+const MEMBER_PAGE_SIZE: u64 = 1000;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Full days since a member joined, 0 if we don't know when they did
+pub fn days_since(joined_at: Option<serenity::Timestamp>, now: i64) -> i64 {
+    joined_at
+        .map(|j| (now - j.unix_timestamp()) / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// Whether a member with `level` who has been in the server for `days` meets a rule
+pub fn qualifies(rule: &PromotionRule, level: i64, days: i64) -> bool {
+    level >= rule.min_level && days >= rule.min_days
+}
+
+/// What a rule asks for, like `level 5 and 7 days`
+pub fn describe(rule: &PromotionRule) -> String {
+    match (rule.min_level, rule.min_days) {
+        (level, 0) => format!("level {}", level),
+        (0, days) => format!("{} days", days),
+        (level, days) => format!("level {} and {} days", level, days),
+    }
+}
+
+/// Gives a member that just reached `level` the roles they earned, returns the given roles
+pub async fn handle_level_up(
+    ctx: &serenity::Context,
+    db: &Db,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    level: i64,
+) -> Result<Vec<serenity::RoleId>, Error> {
+    let rules = db.promotion_rules(Some(guild)).await?;
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let member = guild.member(ctx, user).await?;
+    Ok(promote(ctx, &rules, &member, level).await)
+}
+
+/// Starts the daily check of every member against the promotion rules
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("promotions", async move {
+        loop {
+            if let Err(e) = sweep(&ctx, &db).await {
+                tracing::warn!("Error checking promotion rules: {}", e);
+            }
+
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
+
+async fn sweep(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let rules = db.promotion_rules(None).await?;
+
+    // Rules come sorted by guild
+    for rules in rules.chunk_by(|a, b| a.guild_id == b.guild_id) {
+        let guild = serenity::GuildId(rules[0].guild_id as u64);
+        if let Err(e) = sweep_guild(ctx, db, guild, rules).await {
+            tracing::warn!(guild = guild.0, "Error checking promotion rules: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every member of a guild against its promotion rules
+pub async fn sweep_guild(
+    ctx: &serenity::Context,
+    db: &Db,
+    guild: serenity::GuildId,
+    rules: &[PromotionRule],
+) -> Result<(), Error> {
+    let xp = db.guild_xp(guild).await?;
+
+    let mut after = None;
+    loop {
+        let members = guild
+            .members(&ctx.http, Some(MEMBER_PAGE_SIZE), after)
+            .await?;
+        after = members.last().map(|m| m.user.id);
+
+        for member in members.iter().filter(|m| !m.user.bot) {
+            let member_xp = xp.get(&(member.user.id.0 as i64)).copied().unwrap_or(0);
+            promote(ctx, rules, member, Level::from_xp(member_xp).level).await;
+        }
+
+        if (members.len() as u64) < MEMBER_PAGE_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+// Gives the member every role they qualify for and don't have yet
+async fn promote(
+    ctx: &serenity::Context,
+    rules: &[PromotionRule],
+    member: &serenity::Member,
+    level: i64,
+) -> Vec<serenity::RoleId> {
+    let days = days_since(
+        member.joined_at,
+        serenity::Timestamp::now().unix_timestamp(),
+    );
+    let mut granted = Vec::new();
+
+    for rule in rules {
+        let role = serenity::RoleId(rule.role_id as u64);
+        if member.roles.contains(&role) || !qualifies(rule, level, days) {
+            continue;
+        }
+
+        // One broken rule, e.g. for a deleted role, shouldn't keep the others from applying
+        let result = ctx
+            .http
+            .add_member_role(
+                member.guild_id.0,
+                member.user.id.0,
+                role.0,
+                Some("Auto-promotion"),
+            )
+            .await;
+
+        match result {
+            Ok(()) => granted.push(role),
+            Err(e) => tracing::debug!(
+                guild = member.guild_id.0,
+                role = role.0,
+                "Error giving promotion role: {}",
+                e
+            ),
+        }
+    }
+
+    granted
+}
@@ -0,0 +1,236 @@
+// Quiet hours
+// Staff set weekly windows with /quiethours add in which a channel is locked for everyone, or
+// gets a slowmode. When a window starts, the channel's @everyone overwrite and slowmode are
+// saved before they're changed, and put back as they were when it ends, so whatever else the
+// overwrite allows or denies survives. Like role schedules, windows are compared against the
+// current time on every check, windows that started or ended while the bot was offline are
+// caught up on. A channel can't have overlapping windows, they'd save each other's changes.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    archive,
+    db::{Db, QuietHours, QuietSnapshot},
+    temproles, watchdog, Error,
+};
+
+// How often quiet hours are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether two weekly windows share any minute
+pub fn overlaps(a: (i64, i64), b: (i64, i64)) -> bool {
+    temproles::in_window(a.0, a.1, b.0) || temproles::in_window(b.0, b.1, a.0)
+}
+
+/// The @everyone overwrite of a locked channel, keeping everything else the old one did
+pub fn locked(
+    overwrite: Option<(serenity::Permissions, serenity::Permissions)>,
+) -> (serenity::Permissions, serenity::Permissions) {
+    let (allow, deny) = overwrite.unwrap_or_default();
+    (allow - archive::LOCKED, deny | archive::LOCKED)
+}
+
+fn everyone(channel: &serenity::GuildChannel) -> serenity::PermissionOverwriteType {
+    serenity::PermissionOverwriteType::Role(serenity::RoleId(channel.guild_id.0))
+}
+
+// What quiet hours are about to change in a channel
+fn snapshot(channel: &serenity::GuildChannel) -> QuietSnapshot {
+    let everyone = everyone(channel);
+    QuietSnapshot {
+        overwrite: channel
+            .permission_overwrites
+            .iter()
+            .find(|o| o.kind == everyone)
+            .map(|o| (o.allow, o.deny)),
+        slowmode: channel.rate_limit_per_user.unwrap_or_default(),
+    }
+}
+
+/// Starts the task that starts and ends quiet hours
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("quiet hours", async move {
+        loop {
+            if let Err(e) = reconcile(&ctx, &db).await {
+                tracing::warn!("Error applying quiet hours: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn reconcile(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let now = temproles::current_minute_of_week();
+
+    for window in db.quiet_hours(None).await? {
+        let active = temproles::in_window(window.starts_at, window.ends_at, now);
+        if active == window.active {
+            continue;
+        }
+
+        // Starting marks the window itself once its snapshot is taken. Ends are marked even
+        // if restoring failed, the snapshot would be stale by the next window anyway.
+        let result = if active {
+            start(ctx, db, &window).await
+        } else {
+            db.set_quiet_hours_active(window.id, None).await?;
+            end(ctx, &window).await
+        };
+
+        if let Err(e) = result {
+            tracing::warn!(
+                window = window.id,
+                guild = window.guild_id,
+                "Error applying quiet hours: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn start(ctx: &serenity::Context, db: &Db, window: &QuietHours) -> Result<(), Error> {
+    let channel = serenity::ChannelId(window.channel_id as u64)
+        .to_channel(ctx)
+        .await?
+        .guild()
+        .ok_or("Quiet hours channel isn't in a guild")?;
+
+    // Saved before anything changes, if we stop halfway the end restores it anyway
+    let saved = snapshot(&channel);
+    db.set_quiet_hours_active(window.id, Some(&saved)).await?;
+
+    match window.slowmode {
+        Some(slowmode) => {
+            channel
+                .id
+                .edit(&ctx.http, |c| c.rate_limit_per_user(slowmode as u64))
+                .await?;
+        }
+        None => {
+            let (allow, deny) = locked(saved.overwrite);
+            channel
+                .create_permission(
+                    &ctx.http,
+                    &serenity::PermissionOverwrite {
+                        allow,
+                        deny,
+                        kind: everyone(&channel),
+                    },
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Puts back what quiet hours changed in their channel, if they're active
+pub async fn end(ctx: &serenity::Context, window: &QuietHours) -> Result<(), Error> {
+    let saved = match window.saved() {
+        Some(saved) => saved,
+        None => return Ok(()),
+    };
+    let channel = serenity::ChannelId(window.channel_id as u64);
+
+    if window.slowmode.is_some() {
+        channel
+            .edit(&ctx.http, |c| c.rate_limit_per_user(saved.slowmode))
+            .await?;
+        return Ok(());
+    }
+
+    let everyone =
+        serenity::PermissionOverwriteType::Role(serenity::RoleId(window.guild_id as u64));
+    match saved.overwrite {
+        Some((allow, deny)) => {
+            channel
+                .create_permission(
+                    &ctx.http,
+                    &serenity::PermissionOverwrite {
+                        allow,
+                        deny,
+                        kind: everyone,
+                    },
+                )
+                .await?
+        }
+        None => channel.delete_permission(&ctx.http, everyone).await?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_overlap_across_the_end_of_the_week() {
+        let at = |time| temproles::parse_weekly(time).unwrap();
+
+        assert!(overlaps(
+            (at("mon 22:00"), at("tue 06:00")),
+            (at("tue 05:00"), at("tue 08:00"))
+        ));
+        assert!(!overlaps(
+            (at("mon 22:00"), at("tue 06:00")),
+            (at("tue 06:00"), at("tue 08:00"))
+        ));
+        assert!(overlaps(
+            (at("sun 23:00"), at("mon 07:00")),
+            (at("mon 01:00"), at("mon 02:00"))
+        ));
+        assert!(!overlaps(
+            (at("sun 23:00"), at("mon 07:00")),
+            (at("wed 01:00"), at("wed 02:00"))
+        ));
+    }
+
+    #[test]
+    fn locking_keeps_the_rest_of_the_overwrite() {
+        let (allow, deny) = locked(Some((
+            serenity::Permissions::SEND_MESSAGES | serenity::Permissions::ATTACH_FILES,
+            serenity::Permissions::MENTION_EVERYONE,
+        )));
+        assert_eq!(allow, serenity::Permissions::ATTACH_FILES);
+        assert_eq!(
+            deny,
+            serenity::Permissions::MENTION_EVERYONE | archive::LOCKED
+        );
+
+        assert_eq!(
+            locked(None),
+            (serenity::Permissions::empty(), archive::LOCKED)
+        );
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_saved_until_the_window_ends() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+
+        let id = db
+            .add_quiet_hours(guild, serenity::ChannelId(2), 0, 60, None)
+            .await
+            .unwrap();
+        let saved = QuietSnapshot {
+            overwrite: Some((
+                serenity::Permissions::ATTACH_FILES,
+                serenity::Permissions::MENTION_EVERYONE,
+            )),
+            slowmode: 5,
+        };
+        db.set_quiet_hours_active(id, Some(&saved)).await.unwrap();
+        let window = db.quiet_hours(Some(guild)).await.unwrap().pop().unwrap();
+        assert!(window.active);
+        assert_eq!(window.saved(), Some(saved));
+
+        db.set_quiet_hours_active(id, None).await.unwrap();
+        let window = db.delete_quiet_hours(guild, id).await.unwrap().unwrap();
+        assert_eq!(window.saved(), None);
+    }
+}
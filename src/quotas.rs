@@ -0,0 +1,188 @@
+// Daily quotas for expensive commands
+// Commands that call the LLM or build big exports count towards a per-guild daily quota, checked
+// before the command runs by the framework's command check. How big a guild's quotas are depends
+// on its tier, which the bot owner sets with /admin tier. Guilds subscribed to premium, and
+// members subscribed themselves or who voted for the bot recently, get the premium tier. A use
+// is counted when the command starts, so one that fails later still counts. Quotas reset at
+// midnight UTC, /stats shows how much of them a guild used today.
+use poise::serenity_prelude as serenity;
+
+use crate::{votes, Context, Data, Error};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// What a command costs from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quota {
+    Ai,
+    Export,
+}
+
+impl Quota {
+    pub const ALL: &'static [Quota] = &[Quota::Ai, Quota::Export];
+
+    /// The quota a command counts towards, by its qualified name
+    pub fn for_command(name: &str) -> Option<Quota> {
+        match name {
+            "Summarize thread" => Some(Quota::Ai),
+            "emojipack export" | "incident close" => Some(Quota::Export),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quota::Ai => "ai",
+            Quota::Export => "export",
+        }
+    }
+
+    /// What one use is called, for quota messages
+    pub fn uses(self) -> &'static str {
+        match self {
+            Quota::Ai => "AI requests",
+            Quota::Export => "exports",
+        }
+    }
+}
+
+/// How big a guild's quotas are
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum Tier {
+    #[default]
+    #[name = "default"]
+    Default,
+    #[name = "premium"]
+    Premium,
+}
+
+impl Tier {
+    /// Uses of a quota a guild of this tier gets a day
+    pub fn limit(self, quota: Quota) -> u32 {
+        match (self, quota) {
+            (Tier::Default, Quota::Ai) => 20,
+            (Tier::Default, Quota::Export) => 5,
+            (Tier::Premium, Quota::Ai) => 200,
+            (Tier::Premium, Quota::Export) => 50,
+        }
+    }
+}
+
+/// The day `now` is in, counted in days since the unix epoch
+pub fn day(now: i64) -> i64 {
+    now.div_euclid(DAY_SECS)
+}
+
+/// The unix timestamp the quotas of `day` reset at
+pub fn resets_at(day: i64) -> i64 {
+    (day + 1) * DAY_SECS
+}
+
+/// The tier of a guild's quotas for a member, premium if either of them subscribed or the member
+/// voted recently
+pub async fn tier(
+    data: &Data,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+) -> Result<Tier, Error> {
+    if let Some(premium) = &data.premium {
+        if !premium
+            .active(&data.db, Some(guild), Some(user))
+            .await?
+            .is_empty()
+        {
+            return Ok(Tier::Premium);
+        }
+    }
+    if votes::perks_until(&data.db, user).await?.is_some() {
+        return Ok(Tier::Premium);
+    }
+
+    data.db.guild_tier(guild).await
+}
+
+/// Counts a use of the command's quota, telling the user and refusing it when the guild has
+/// none left. For `FrameworkOptions::command_check`
+pub async fn check(ctx: Context<'_>) -> Result<bool, Error> {
+    let (quota, guild) = match (
+        Quota::for_command(&ctx.command().qualified_name),
+        ctx.guild_id(),
+    ) {
+        (Some(quota), Some(guild)) => (quota, guild),
+        _ => return Ok(true),
+    };
+
+    let tier = tier(ctx.data(), guild, ctx.author().id).await?;
+    let day = day(serenity::Timestamp::now().unix_timestamp());
+    if ctx
+        .data()
+        .db
+        .claim_quota(guild, quota.name(), day, tier.limit(quota))
+        .await?
+    {
+        return Ok(true);
+    }
+
+    tracing::info!(guild = guild.0, quota = quota.name(), "Quota exceeded");
+    ctx.send(|m| {
+        m.content(format!(
+            ":x: Quota exceeded, this server used all {} of its {} for today. It resets <t:{}:R>.",
+            tier.limit(quota),
+            quota.uses(),
+            resets_at(day)
+        ))
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[test]
+    fn quotas_reset_at_midnight_utc() {
+        // 2024-01-02 15:30 UTC
+        let now = 1_704_209_400;
+        let midnight = 1_704_153_600;
+        assert_eq!(resets_at(day(now)), midnight + DAY_SECS);
+        assert_eq!(resets_at(day(midnight)), midnight + DAY_SECS);
+        assert_eq!(resets_at(day(midnight - 1)), midnight);
+    }
+
+    #[test]
+    fn only_expensive_commands_have_quotas() {
+        assert_eq!(Quota::for_command("Summarize thread"), Some(Quota::Ai));
+        assert_eq!(Quota::for_command("emojipack export"), Some(Quota::Export));
+        assert_eq!(Quota::for_command("emojipack import"), None);
+        assert_eq!(Quota::for_command("ping"), None);
+        for &quota in Quota::ALL {
+            assert!(Tier::Premium.limit(quota) > Tier::Default.limit(quota));
+        }
+    }
+
+    #[tokio::test]
+    async fn quotas_run_out_per_guild_and_day() {
+        let db = Db::memory().await;
+        let (a, b) = (serenity::GuildId(1), serenity::GuildId(2));
+
+        assert_eq!(db.guild_tier(a).await.unwrap(), Tier::Default);
+        db.set_guild_tier(a, Tier::Premium).await.unwrap();
+        assert_eq!(db.guild_tier(a).await.unwrap(), Tier::Premium);
+
+        assert!(db.claim_quota(a, "ai", 10, 2).await.unwrap());
+        assert!(db.claim_quota(a, "ai", 10, 2).await.unwrap());
+        assert!(!db.claim_quota(a, "ai", 10, 2).await.unwrap());
+        // Other quotas, guilds and days are counted apart
+        assert!(db.claim_quota(a, "export", 10, 2).await.unwrap());
+        assert!(db.claim_quota(b, "ai", 10, 2).await.unwrap());
+        assert!(db.claim_quota(a, "ai", 11, 2).await.unwrap());
+
+        let mut usage = db.quota_usage(a, 10).await.unwrap();
+        usage.sort();
+        assert_eq!(usage, [("ai".to_string(), 2), ("export".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,170 @@
+// Per-user token bucket rate limiter
+// Every user, or whatever else the limiter is keyed by, like a member of one guild, gets a
+// bucket of `capacity` tokens which refills by one token every
+// `refill` interval. Handlers take a token before responding, so a user can burst a
+// few messages but can't keep spamming, and a user who only sent one message is never
+// punished just because a timer happened to fire.
+use std::{
+    collections::HashMap,
+    env,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+
+const DEFAULT_CAPACITY: u32 = 3;
+const DEFAULT_REFILL: Duration = Duration::from_secs(2);
+// How often buckets that are full again get dropped to keep memory bounded
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter<K = serenity::UserId> {
+    capacity: u32,
+    refill: Duration,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Copy + Eq + Hash + Send + 'static> RateLimiter<K> {
+    pub fn new(capacity: u32, refill: Duration) -> Self {
+        Self {
+            capacity,
+            refill,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a limiter configured by RATE_LIMIT_CAPACITY and RATE_LIMIT_REFILL_MS
+    /// and starts the task that cleans up idle buckets
+    pub fn spawn_from_env() -> Arc<Self> {
+        let capacity = env::var("RATE_LIMIT_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let refill = env::var("RATE_LIMIT_REFILL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_REFILL);
+
+        Self::spawn(capacity, refill)
+    }
+
+    /// Creates a limiter and starts the task that cleans up idle buckets
+    pub fn spawn(capacity: u32, refill: Duration) -> Arc<Self> {
+        let limiter = Arc::new(Self::new(capacity, refill));
+        let limiter_clone = Arc::clone(&limiter);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CLEANUP_INTERVAL).await;
+                limiter_clone.cleanup();
+            }
+        });
+
+        limiter
+    }
+
+    /// Takes a token for `key`, returns false if it's rate limited
+    pub fn try_acquire(&self, key: K) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity as f64,
+            updated: now,
+        });
+
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Number of users currently being tracked
+    pub fn tracked_users(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+
+    /// Tokens every tracked user has right now, for /admin state
+    pub fn snapshot(&self) -> Vec<(K, f64)> {
+        let buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+
+        buckets
+            .iter()
+            .map(|(key, bucket)| (*key, self.refilled(bucket, now)))
+            .collect()
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        let refilled = bucket.tokens + elapsed / self.refill.as_secs_f64();
+        refilled.min(self.capacity as f64)
+    }
+
+    fn cleanup(&self) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        let capacity = self.capacity as f64;
+
+        buckets.retain(|_, bucket| self.refilled(bucket, now) < capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Moves the last update of a bucket back, as if `elapsed` passed since
+    fn wait(limiter: &RateLimiter<u64>, key: u64, elapsed: Duration) {
+        let mut buckets = limiter.buckets.lock().unwrap();
+        let bucket = buckets.get_mut(&key).unwrap();
+        bucket.updated -= elapsed;
+    }
+
+    #[test]
+    fn buckets_allow_a_burst_then_refill_over_time() {
+        let refill = Duration::from_secs(2);
+        let limiter = RateLimiter::<u64>::new(3, refill);
+
+        assert!((0..3).all(|_| limiter.try_acquire(1)));
+        assert!(!limiter.try_acquire(1));
+        // Other keys have buckets of their own
+        assert!(limiter.try_acquire(2));
+
+        wait(&limiter, 1, refill / 2);
+        assert!(!limiter.try_acquire(1));
+        wait(&limiter, 1, refill);
+        assert!(limiter.try_acquire(1));
+        assert!(!limiter.try_acquire(1));
+
+        // Refills stop at the capacity
+        wait(&limiter, 1, refill * 100);
+        assert!((0..3).all(|_| limiter.try_acquire(1)));
+        assert!(!limiter.try_acquire(1));
+    }
+
+    #[test]
+    fn full_buckets_are_cleaned_up() {
+        let refill = Duration::from_secs(2);
+        let limiter = RateLimiter::<u64>::new(3, refill);
+        limiter.try_acquire(1);
+        limiter.try_acquire(2);
+
+        wait(&limiter, 1, refill);
+        limiter.cleanup();
+        assert_eq!(limiter.tracked_users(), 1);
+        assert_eq!(limiter.snapshot()[0].0, 2);
+    }
+}
@@ -0,0 +1,107 @@
+// Reaction roles
+// Moderators map emoji on a message to roles with /reactionrole, members then get the role by
+// reacting and lose it again by removing their reaction. Needs the Manage Roles permission and
+// a top role above the roles that are handed out.
+use poise::serenity_prelude as serenity;
+
+use crate::{Data, Error};
+
+/// The key an emoji is stored under: the ID of custom emoji, the emoji itself otherwise
+/// Names of custom emoji can change, and unicode emoji are sent with and without U+FE0F
+pub fn emoji_key(emoji: &serenity::ReactionType) -> String {
+    match emoji {
+        serenity::ReactionType::Custom { id, .. } => id.0.to_string(),
+        serenity::ReactionType::Unicode(emoji) => emoji.replace('\u{FE0F}', ""),
+        _ => emoji.to_string(),
+    }
+}
+
+/// Gives the member the role belonging to the emoji they reacted with
+pub async fn handle_add(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let (guild, role, user) = match lookup(ctx, data, reaction).await? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    if reaction
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .is_some_and(|u| u.bot)
+    {
+        return Ok(());
+    }
+
+    let result = ctx
+        .http
+        .add_member_role(guild.0, user.0, role.0, Some("Reaction role"))
+        .await;
+
+    // Usually the role was moved above ours or we lost Manage Roles, nothing the member can fix
+    if let Err(e) = result {
+        tracing::warn!(
+            guild = guild.0,
+            role = role.0,
+            "Error adding reaction role: {}",
+            e
+        );
+    }
+
+    Ok(())
+}
+
+/// Takes the role of the emoji away again when the reaction is removed
+pub async fn handle_remove(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let (guild, role, user) = match lookup(ctx, data, reaction).await? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    let result = ctx
+        .http
+        .remove_member_role(guild.0, user.0, role.0, Some("Reaction role"))
+        .await;
+
+    if let Err(e) = result {
+        tracing::warn!(
+            guild = guild.0,
+            role = role.0,
+            "Error removing reaction role: {}",
+            e
+        );
+    }
+
+    Ok(())
+}
+
+// The guild, role and reacting user, if the reaction is on a reaction role message
+async fn lookup(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<Option<(serenity::GuildId, serenity::RoleId, serenity::UserId)>, Error> {
+    let (guild, user) = match (reaction.guild_id, reaction.user_id) {
+        (Some(guild), Some(user)) => (guild, user),
+        _ => return Ok(None),
+    };
+
+    // Our own reactions are the ones members click on
+    if user == ctx.cache.current_user_id() {
+        return Ok(None);
+    }
+
+    let role = data
+        .db
+        .reaction_role(reaction.message_id, &emoji_key(&reaction.emoji))
+        .await?;
+
+    Ok(role.map(|role| (guild, role, user)))
+}
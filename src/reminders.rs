@@ -0,0 +1,75 @@
+// Delivering reminders set with /remind
+// Reminders live in the database so they survive restarts, a background task checks for due
+// ones every few seconds and DMs them, falling back to a ping in the channel they were set in.
+// A reminder is only deleted once it was delivered, until then it's tried again with a growing
+// delay.
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Reminder},
+    dm,
+    dm::DmStats,
+    retry::{retry, RetryPolicy},
+    watchdog, Error,
+};
+
+// How often due reminders are looked up, also the most a reminder can be late by
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// Reminders that can't be delivered are tried this often, over about an hour, before giving up
+const MAX_ATTEMPTS: u32 = 6;
+
+/// Starts the task that delivers due reminders
+pub fn spawn(ctx: serenity::Context, db: Db, stats: Arc<DmStats>) {
+    watchdog::spawn("reminders", async move {
+        loop {
+            if let Err(e) = deliver_due(&ctx, &db, &stats).await {
+                tracing::warn!("Error delivering reminders: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn deliver_due(ctx: &serenity::Context, db: &Db, stats: &Arc<DmStats>) -> Result<(), Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    for reminder in db.due_reminders(now).await? {
+        if deliver(ctx, stats, &reminder).await {
+            db.delete_reminder(reminder.id, None).await?;
+        } else if !db.postpone_reminder(reminder.id, now, MAX_ATTEMPTS).await? {
+            tracing::warn!(reminder = reminder.id, "Giving up on delivering reminder");
+            db.delete_reminder(reminder.id, None).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns false if the reminder should be tried again later
+async fn deliver(ctx: &serenity::Context, stats: &Arc<DmStats>, reminder: &Reminder) -> bool {
+    let user_id = serenity::UserId(reminder.user_id as u64);
+    let user = match retry(&RetryPolicy::DEFAULT, || user_id.to_user(&ctx.http)).await {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::warn!(
+                reminder = reminder.id,
+                "Error fetching reminder user: {}",
+                e
+            );
+            return false;
+        }
+    };
+
+    dm::send(
+        ctx,
+        stats,
+        &dm::REMINDER,
+        &user,
+        serenity::ChannelId(reminder.channel_id as u64),
+        &format!(":alarm_clock: Reminder: {}", reminder.text),
+    )
+    .await
+}
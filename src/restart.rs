@@ -0,0 +1,156 @@
+// Rolling restarts
+// On SIGUSR2 the bot starts its binary again with the same arguments, e.g. after a deploy
+// replaced it, and hands over to the new process. serenity can't resume a gateway session in
+// another process, so the new one identifies from scratch while the old one keeps handling
+// events. Once it's ready it connects to RESTART_HANDOFF, which the old process set for it, and
+// the old process shuts down like on SIGTERM. Events the new process receives meanwhile wait in
+// poise until its setup is done, so none are missed, but events the old process was still
+// handling can be handled twice. Buttons and collectors waiting in the old process stop
+// responding, everything else lives in the database.
+// Supervisors that stop the whole service when the main process exits (systemd's default
+// KillMode) also stop the new process, restart those through the supervisor instead.
+use std::{env, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+
+use crate::{counters::Counters, db::Db, shutdown::Shutdown, Error};
+
+/// Set for the new process to the address the old one waits on
+const HANDOFF_VAR: &str = "RESTART_HANDOFF";
+// Identifying and the first READY can take a while with many guilds
+const READY_TIMEOUT: Duration = Duration::from_secs(120);
+const READY: &[u8] = b"ready\n";
+
+/// Waits for the process that started us to shut down if it did so for a rolling restart.
+/// Called before setup, which opens the database and binds the ports the old process holds
+pub async fn take_over() {
+    let addr = match env::var(HANDOFF_VAR) {
+        Ok(addr) => addr,
+        Err(_) => return,
+    };
+    // Our own restarts set their own address
+    env::remove_var(HANDOFF_VAR);
+
+    tracing::info!("Taking over from the previous process");
+    if let Err(e) = wait_for_exit(&addr).await {
+        tracing::warn!(%addr, "Error taking over from the previous process: {}", e);
+    }
+}
+
+// The old process keeps the connection open until it exits
+async fn wait_for_exit(addr: &str) -> Result<(), Error> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(READY).await?;
+    stream.read_to_end(&mut Vec::new()).await?;
+    Ok(())
+}
+
+/// Starts a task that hands over to a new process on SIGUSR2
+pub fn spawn_signal_handler(
+    shutdown: Arc<Shutdown>,
+    shard_manager: Arc<Mutex<serenity::ShardManager>>,
+    db: Db,
+    counters: Counters,
+    ephemeral: bool,
+) {
+    // SIGUSR2 only exists on unix
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut restart = match signal(SignalKind::user_defined2()) {
+            Ok(restart) => restart,
+            Err(e) => {
+                tracing::warn!("Error listening for SIGUSR2: {}", e);
+                return;
+            }
+        };
+
+        while restart.recv().await.is_some() {
+            if shutdown.is_shutting_down() {
+                return;
+            }
+            // The new process would start with an empty database
+            if ephemeral {
+                tracing::warn!("Ignoring SIGUSR2, ephemeral data can't be handed over");
+                continue;
+            }
+
+            match start_successor().await {
+                Ok(handoff) => {
+                    tracing::info!("Handing over to the new process");
+                    shutdown.run(&shard_manager, &db, &counters).await;
+                    // Closed when this process exits, which tells the new one that the
+                    // database and ports are free
+                    std::mem::forget(handoff);
+                    return;
+                }
+                Err(e) => tracing::error!("Error starting a new process, still running: {}", e),
+            }
+        }
+    });
+
+    #[cfg(not(unix))]
+    let _ = (shutdown, shard_manager, db, counters, ephemeral);
+}
+
+// Starts the binary again and waits until it's ready to take over
+async fn start_successor() -> Result<TcpStream, Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let mut child = tokio::process::Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(HANDOFF_VAR, listener.local_addr()?.to_string())
+        .spawn()?;
+    tracing::info!(pid = child.id(), "Started a new process");
+
+    let ready = tokio::select! {
+        ready = tokio::time::timeout(READY_TIMEOUT, wait_for_ready(&listener)) => ready,
+        status = child.wait() => return Err(format!("The new process exited: {}", status?).into()),
+    };
+    match ready {
+        Ok(Ok(handoff)) => Ok(handoff),
+        Ok(Err(e)) => {
+            let _ = child.kill().await;
+            Err(e)
+        }
+        Err(_) => {
+            let _ = child.kill().await;
+            Err("The new process didn't get ready in time".into())
+        }
+    }
+}
+
+async fn wait_for_ready(listener: &TcpListener) -> Result<TcpStream, Error> {
+    let (mut stream, _) = listener.accept().await?;
+    let mut message = [0; READY.len()];
+    stream.read_exact(&mut message).await?;
+    if message != READY {
+        return Err("The new process sent an invalid handoff".into());
+    }
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn new_process_waits_until_the_old_one_exits() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let new_process = tokio::spawn(async move { wait_for_exit(&addr).await });
+
+        let handoff = wait_for_ready(&listener).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!new_process.is_finished());
+
+        // The old process exiting closes the connection
+        drop(handoff);
+        new_process.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+// Retries for REST calls made from background jobs
+// Transient failures (5xx, rate limits, connection problems) are retried with
+// exponential backoff and jitter, anything else fails straight away.
+use std::{env, fmt::Display, future::Future, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+
+use crate::{
+    mentions::{self, Mentions},
+    Error,
+};
+
+/// How often and how long to retry
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 4 attempts, waiting roughly 1s, 2s and 4s in between
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+    };
+
+    // Exponential backoff with "full jitter" so many failing jobs don't retry in lockstep
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+
+        rand::thread_rng().gen_range(exponential / 2..=exponential)
+    }
+}
+
+/// Errors that know whether retrying could help
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+fn is_transient_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+impl Transient for serenity::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            serenity::Error::Http(http_error) => match http_error.as_ref() {
+                serenity::HttpError::UnsuccessfulRequest(response) => {
+                    is_transient_status(response.status_code.as_u16())
+                }
+                serenity::HttpError::Request(e) => e.is_timeout() || e.is_connect(),
+                _ => false,
+            },
+            serenity::Error::Gateway(_) | serenity::Error::Io(_) => true,
+            _ => false,
+        }
+    }
+}
+
+// Errors passed around as `Error` are transient if what's inside is
+impl Transient for Error {
+    fn is_transient(&self) -> bool {
+        if let Some(e) = self.downcast_ref::<serenity::Error>() {
+            e.is_transient()
+        } else if let Some(e) = self.downcast_ref::<reqwest::Error>() {
+            e.is_transient()
+        } else {
+            false
+        }
+    }
+}
+
+impl Transient for reqwest::Error {
+    fn is_transient(&self) -> bool {
+        match self.status() {
+            Some(status) => is_transient_status(status.as_u16()),
+            None => self.is_timeout() || self.is_connect() || self.is_request(),
+        }
+    }
+}
+
+/// Runs `op` until it succeeds, fails permanently or runs out of attempts
+pub async fn retry<T, E, F, Fut>(policy: &RetryPolicy, mut op: F) -> Result<T, E>
+where
+    E: Transient,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= policy.max_attempts || !e.is_transient() => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Logs a job that gave up and posts it to the channel in ERROR_CHANNEL_ID if set
+pub async fn report_failure(http: &serenity::Http, job: &str, error: impl Display) {
+    tracing::error!(job, "Failed after retrying: {}", error);
+
+    let channel = match env::var("ERROR_CHANNEL_ID").map(|id| id.parse::<u64>()) {
+        Ok(Ok(id)) => serenity::ChannelId(id),
+        _ => return,
+    };
+
+    let content = format!(":warning: `{}` failed after retrying: {}", job, error);
+    if let Err(e) =
+        mentions::send_message(http, channel, Mentions::Nothing, |m| m.content(content)).await
+    {
+        tracing::warn!("Error reporting failure to error channel: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether it's transient
+    struct Failure(bool);
+
+    impl Transient for Failure {
+        fn is_transient(&self) -> bool {
+            self.0
+        }
+    }
+
+    #[test]
+    fn delays_double_with_jitter_up_to_the_max() {
+        let policy = RetryPolicy::DEFAULT;
+        for attempt in 1..=8 {
+            let exponential = (policy.base_delay * 2u32.pow(attempt - 1)).min(policy.max_delay);
+            for _ in 0..50 {
+                let delay = policy.delay(attempt);
+                assert!(delay >= exponential / 2 && delay <= exponential);
+            }
+        }
+        // Huge attempts don't overflow
+        assert!(policy.delay(u32::MAX) <= policy.max_delay);
+    }
+
+    #[test]
+    fn rate_limits_and_server_errors_are_transient() {
+        assert!(is_transient_status(429));
+        assert!(is_transient_status(500));
+        assert!(is_transient_status(503));
+        assert!(!is_transient_status(400));
+        assert!(!is_transient_status(403));
+        assert!(!is_transient_status(404));
+
+        let error: Error = "not a request error".into();
+        assert!(!error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn only_transient_errors_are_retried() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+        let run = |transient| {
+            let mut attempts = 0;
+            let policy = &policy;
+            async move {
+                let result: Result<(), Failure> = retry(policy, || {
+                    attempts += 1;
+                    async move { Err(Failure(transient)) }
+                })
+                .await;
+                assert!(result.is_err());
+                attempts
+            }
+        };
+        assert_eq!(run(true).await, 3);
+        assert_eq!(run(false).await, 1);
+
+        let mut attempts = 0;
+        let result = retry(&policy, || {
+            attempts += 1;
+            let done = attempts == 2;
+            async move {
+                if done {
+                    Ok(attempts)
+                } else {
+                    Err(Failure(true))
+                }
+            }
+        })
+        .await;
+        assert!(matches!(result, Ok(2)));
+    }
+}
@@ -0,0 +1,545 @@
+// Review queue for automod flags
+// Toxicity scoring, NSFW scans and anti-spam rules with the `review` action flag messages
+// instead of acting on them. Flags are posted to the review channel with buttons to approve the
+// message, delete it, or delete it and warn or time out its author, and stored with who reviewed
+// them. Approved flags are false positives, so /review stats shows per rule how often that
+// happens, to see which thresholds are set too low.
+use std::collections::BTreeMap;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::GuildConfig,
+    db::{AutomodFlag, FlagCount},
+    dm, duration,
+    incidents::{self, EventKind},
+    mentions::{self, Mentions},
+    modlog::{self, Action},
+    spam, Data, Error,
+};
+
+const BUTTON_PREFIX: &str = "reviewqueue";
+const PREVIEW_LENGTH: usize = 1000;
+
+/// Something automod flagged in a message
+pub struct Flag<'a> {
+    /// What flagged it, like `toxicity`
+    pub source: &'static str,
+    /// The rule or category that flagged it, stats are kept per rule
+    pub rule: &'a str,
+    pub title: &'a str,
+    /// Why it was flagged, like the scores
+    pub details: String,
+    pub image: Option<&'a str>,
+}
+
+/// What a reviewer decided on a flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Review {
+    /// The message is fine, the flag was a false positive
+    Approve,
+    Delete,
+    /// Delete the message and warn its author
+    Warn,
+    /// Delete the message and time its author out
+    Timeout,
+}
+
+impl Review {
+    const ALL: &'static [Review] = &[
+        Review::Approve,
+        Review::Delete,
+        Review::Warn,
+        Review::Timeout,
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            Review::Approve => "approve",
+            Review::Delete => "delete",
+            Review::Warn => "warn",
+            Review::Timeout => "timeout",
+        }
+    }
+
+    /// The status of flags reviewed like this
+    pub fn status(self) -> &'static str {
+        match self {
+            Review::Approve => "approved",
+            Review::Delete => "deleted",
+            Review::Warn => "warned",
+            Review::Timeout => "timed_out",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Review::Approve => "Approve",
+            Review::Delete => "Delete",
+            Review::Warn => "Warn",
+            Review::Timeout => "Timeout",
+        }
+    }
+
+    fn style(self) -> serenity::ButtonStyle {
+        match self {
+            Review::Approve => serenity::ButtonStyle::Success,
+            Review::Delete | Review::Warn | Review::Timeout => serenity::ButtonStyle::Danger,
+        }
+    }
+}
+
+// Returns what the button does and the flag ID
+fn parse_button(custom_id: &str) -> Option<(Review, i64)> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    let (action, id) = rest.split_once('-')?;
+    let review = *Review::ALL.iter().find(|r| r.id() == action)?;
+
+    Some((review, id.parse().ok()?))
+}
+
+/// How the flags of one rule were reviewed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    pub flagged: i64,
+    pub pending: i64,
+    /// False positives
+    pub approved: i64,
+}
+
+impl RuleStats {
+    /// Share of reviewed flags that were false positives, in percent
+    pub fn false_positive_rate(&self) -> Option<i64> {
+        let reviewed = self.flagged - self.pending;
+        (reviewed > 0).then(|| self.approved * 100 / reviewed)
+    }
+}
+
+/// Adds up flag counts per source and rule
+pub fn rule_stats(counts: &[FlagCount]) -> BTreeMap<(&str, &str), RuleStats> {
+    let mut stats: BTreeMap<_, RuleStats> = BTreeMap::new();
+    for count in counts {
+        let rule = stats
+            .entry((count.source.as_str(), count.rule.as_str()))
+            .or_default();
+        rule.flagged += count.count;
+        match count.status.as_str() {
+            "pending" => rule.pending += count.count,
+            "approved" => rule.approved += count.count,
+            _ => {}
+        }
+    }
+    stats
+}
+
+/// Records a flag and posts it to the review channel with buttons to act on it
+/// Flags of guilds without a review channel are only recorded as incident events
+pub async fn flag(
+    ctx: &serenity::Context,
+    data: &Data,
+    config: &GuildConfig,
+    message: &serenity::Message,
+    flag: Flag<'_>,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    incidents::record(
+        data,
+        guild,
+        EventKind::Flag,
+        &format!(
+            "{} ({}) by {} in <#{}>: {}",
+            flag.title,
+            flag.details,
+            message.author.tag(),
+            message.channel_id.0,
+            message.link()
+        ),
+    )
+    .await;
+
+    let channel = match config.review_channel() {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    let id = data
+        .db
+        .add_automod_flag(guild, message, flag.source, flag.rule)
+        .await?;
+
+    let preview: String = message.content.chars().take(PREVIEW_LENGTH).collect();
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(flag.title)
+                .url(message.link())
+                .field(
+                    "User",
+                    format!("{} (<@{}>)", message.author.tag(), message.author.id.0),
+                    true,
+                )
+                .field("Channel", format!("<#{}>", message.channel_id.0), true)
+                .field("Flagged for", &flag.details, true)
+                .footer(|f| f.text(format!("Flag #{} by {}", id, flag.source)))
+                .timestamp(serenity::Timestamp::now());
+            if !preview.is_empty() {
+                e.description(&preview);
+            }
+            if let Some(image) = flag.image {
+                e.thumbnail(image);
+            }
+            e
+        })
+        .components(|c| {
+            c.create_action_row(|r| {
+                for review in Review::ALL {
+                    r.create_button(|b| {
+                        b.custom_id(format!("{}-{}-{}", BUTTON_PREFIX, review.id(), id))
+                            .label(review.label())
+                            .style(review.style())
+                    });
+                }
+                r
+            })
+        })
+    })
+    .await;
+
+    match result {
+        Ok(posted) => data.db.set_automod_flag_message(id, &posted).await?,
+        Err(e) => tracing::warn!(guild = guild.0, "Error posting to review channel: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Acts on a flag when staff click one of its buttons
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (review, id) = match parse_button(&interaction.data.custom_id) {
+        Some(button) => button,
+        None => return Ok(()),
+    };
+
+    match handle_review(ctx, data, interaction, review, id).await? {
+        Ok(outcome) => {
+            let mut embed = interaction
+                .message
+                .embeds
+                .first()
+                .cloned()
+                .map(serenity::CreateEmbed::from)
+                .unwrap_or_default();
+            embed.field("Outcome", outcome, false);
+
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+                })
+                .await?;
+        }
+        Err(refusal) => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.interaction_response_data(|d| d.content(refusal).ephemeral(true))
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the outcome to show on the flag, or why the reviewer can't do this
+async fn handle_review(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+    review: Review,
+    id: i64,
+) -> Result<Result<String, String>, Error> {
+    let (guild, reviewer) = match (interaction.guild_id, &interaction.member) {
+        (Some(guild), Some(member)) => (guild, member),
+        _ => {
+            return Ok(Err(
+                ":x: Flags can only be reviewed in a server.".to_string()
+            ))
+        }
+    };
+
+    let permissions = reviewer.permissions.unwrap_or_default();
+    let refusal = match review {
+        Review::Approve | Review::Delete if !permissions.manage_messages() => {
+            Some(":x: You need the Manage Messages permission to review flags.")
+        }
+        Review::Warn | Review::Timeout if !permissions.moderate_members() => {
+            Some(":x: You need the Moderate Members permission to warn or time out members.")
+        }
+        _ => None,
+    };
+    if let Some(refusal) = refusal {
+        return Ok(Err(refusal.to_string()));
+    }
+
+    let flag = match data.db.automod_flag(id).await? {
+        Some(flag) if flag.guild_id as u64 == guild.0 => flag,
+        _ => return Ok(Err(":x: That flag doesn't exist anymore.".to_string())),
+    };
+    if flag.status != "pending" {
+        return Ok(Err(format!(
+            ":x: That flag was already reviewed ({}).",
+            flag.status.replace('_', " ")
+        )));
+    }
+    // Claimed before acting so two reviewers can't both warn someone for the same message
+    if !data
+        .db
+        .review_automod_flag(id, review.status(), reviewer.user.id)
+        .await?
+    {
+        return Ok(Err(
+            ":x: Someone else reviewed that flag just now.".to_string()
+        ));
+    }
+
+    let details = act(ctx, data, guild, &reviewer.user, &flag, review).await?;
+
+    tracing::info!(
+        moderator = %reviewer.user.tag(),
+        guild = guild.0,
+        author = flag.author_id,
+        source = %flag.source,
+        rule = %flag.rule,
+        "Automod flag {}",
+        review.status()
+    );
+
+    let mut outcome = format!(
+        "{} by <@{}>",
+        match review {
+            Review::Approve => "Approved",
+            Review::Delete => "Deleted",
+            Review::Warn => "Deleted and warned",
+            Review::Timeout => "Deleted and timed out",
+        },
+        reviewer.user.id.0
+    );
+    if let Some(details) = details {
+        outcome.push_str(&format!(" ({})", details));
+    }
+    Ok(Ok(outcome))
+}
+
+// Deletes the message and warns or times out its author, returns details for the outcome
+async fn act(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    reviewer: &serenity::User,
+    flag: &AutomodFlag,
+    review: Review,
+) -> Result<Option<String>, Error> {
+    if review == Review::Approve {
+        return Ok(None);
+    }
+
+    let channel = serenity::ChannelId(flag.channel_id as u64);
+    match channel
+        .delete_message(&ctx.http, flag.message_id as u64)
+        .await
+    {
+        Ok(()) => {}
+        // Deleted by its author or another moderator, which is what we wanted anyway
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) => {}
+        Err(e) => return Err(e.into()),
+    }
+    if review == Review::Delete {
+        return Ok(None);
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let author = serenity::UserId(flag.author_id as u64).to_user(ctx).await?;
+    let reason = format!("Flagged by {} ({}) and reviewed", flag.source, flag.rule);
+    let guild_name = ctx
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_default();
+
+    let (name, notice, details) = match review {
+        Review::Warn => (
+            "warned",
+            format!(
+                "You have been warned in **{}**.\nReason: {}",
+                guild_name, reason
+            ),
+            spam::warn(ctx, data, &config, guild, author.id, reviewer.id, &reason).await?,
+        ),
+        _ => {
+            spam::timeout(ctx, guild, author.id, config.warn_timeout()).await?;
+            let length = duration::format(config.warn_timeout());
+            (
+                "timed out",
+                format!(
+                    "You have been timed out in **{}** for {}.\nReason: {}",
+                    guild_name, length, reason
+                ),
+                format!("For {}", length),
+            )
+        }
+    };
+
+    dm::send(
+        ctx,
+        &data.dm_stats,
+        &dm::MODERATION_NOTICE,
+        &author,
+        channel,
+        &notice,
+    )
+    .await;
+
+    modlog::post_action(
+        ctx,
+        data,
+        guild,
+        reviewer,
+        Action {
+            name,
+            target: &author,
+            reason: &reason,
+            details: Some(format!("{}, in <#{}>", details, channel.0)),
+        },
+    )
+    .await?;
+
+    Ok(Some(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[test]
+    fn buttons_are_read_back() {
+        assert_eq!(
+            parse_button("reviewqueue-approve-4"),
+            Some((Review::Approve, 4))
+        );
+        assert_eq!(
+            parse_button("reviewqueue-timeout-12"),
+            Some((Review::Timeout, 12))
+        );
+        assert_eq!(parse_button("reviewqueue-ban-4"), None);
+        assert_eq!(parse_button("votedelete-delete-4"), None);
+    }
+
+    #[test]
+    fn false_positives_are_counted_per_rule() {
+        let count = |source: &str, rule: &str, status: &str, count| FlagCount {
+            source: source.to_string(),
+            rule: rule.to_string(),
+            status: status.to_string(),
+            count,
+        };
+        let counts = [
+            count("toxicity", "insult", "approved", 3),
+            count("toxicity", "insult", "deleted", 1),
+            count("toxicity", "insult", "pending", 2),
+            count("spam", "repeat", "warned", 4),
+            count("spam", "links", "pending", 1),
+        ];
+
+        let stats = rule_stats(&counts);
+        let insult = &stats[&("toxicity", "insult")];
+        assert_eq!(
+            insult,
+            &RuleStats {
+                flagged: 6,
+                pending: 2,
+                approved: 3
+            }
+        );
+        assert_eq!(insult.false_positive_rate(), Some(75));
+        assert_eq!(stats[&("spam", "repeat")].false_positive_rate(), Some(0));
+        assert_eq!(stats[&("spam", "links")].false_positive_rate(), None);
+        assert_eq!(
+            stats.keys().collect::<Vec<_>>(),
+            [
+                &("spam", "links"),
+                &("spam", "repeat"),
+                &("toxicity", "insult")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn flags_are_reviewed_once() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+        let message: serenity::Message = serde_json::from_value(serde_json::json!({
+            "id": "10",
+            "channel_id": "2",
+            "author": {
+                "id": "3",
+                "username": "member",
+                "discriminator": "0001",
+                "avatar": null
+            },
+            "content": "spam spam spam",
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0
+        }))
+        .unwrap();
+
+        let first = db
+            .add_automod_flag(guild, &message, "spam", "repeat")
+            .await
+            .unwrap();
+        let second = db
+            .add_automod_flag(guild, &message, "toxicity", "insult")
+            .await
+            .unwrap();
+        assert_eq!(db.pending_automod_flags(guild, 10).await.unwrap().len(), 2);
+
+        let reviewer = serenity::UserId(4);
+        assert!(db
+            .review_automod_flag(first, Review::Approve.status(), reviewer)
+            .await
+            .unwrap());
+        assert!(!db
+            .review_automod_flag(first, Review::Warn.status(), reviewer)
+            .await
+            .unwrap());
+        assert_eq!(
+            db.automod_flag(first).await.unwrap().unwrap().status,
+            "approved"
+        );
+
+        let pending = db.pending_automod_flags(guild, 10).await.unwrap();
+        assert_eq!(pending.iter().map(|f| f.id).collect::<Vec<_>>(), [second]);
+        assert_eq!(
+            db.automod_flag_reviewers(guild, 0).await.unwrap(),
+            [(reviewer, 1)]
+        );
+        let counts = db.automod_flag_counts(guild, 0).await.unwrap();
+        let stats = rule_stats(&counts);
+        assert_eq!(stats[&("spam", "repeat")].false_positive_rate(), Some(100));
+        assert_eq!(stats[&("toxicity", "insult")].pending, 1);
+    }
+}
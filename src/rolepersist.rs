@@ -0,0 +1,178 @@
+// Role persistence
+// The roles of members that leave are saved, and with the `restore_roles` feature given back
+// when they rejoin within the guild's role retention, so leaving doesn't shed a muted role or
+// lose earned ones. Roles of the `not_restored` list, managed roles and roles above ours are
+// never given back, moderators can restore the others by hand with /restoreroles. Like
+// autoroles, members only get them back once they passed membership screening. Roles are only
+// known if the member was cached when they left.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{Feature, RoleList},
+    Data, Error,
+};
+
+/// Saves the roles of a member that left
+pub async fn handle_leave(
+    data: &Data,
+    guild: serenity::GuildId,
+    member: Option<&serenity::Member>,
+) -> Result<(), Error> {
+    let member = match member {
+        Some(member) if !member.user.bot => member,
+        _ => return Ok(()),
+    };
+
+    let config = data.guild_configs.get(guild).await?;
+    let expired = expired(config.role_retention());
+    data.db
+        .save_roles(guild, member.user.id, &member.roles, expired)
+        .await
+}
+
+/// Gives a member that rejoined their roles back, unless they still have to pass screening
+pub async fn handle_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    if member.pending {
+        return Ok(());
+    }
+
+    restore_if_enabled(ctx, data, member).await
+}
+
+/// Gives members that just passed membership screening their roles back
+pub async fn handle_update(
+    ctx: &serenity::Context,
+    data: &Data,
+    old: Option<&serenity::Member>,
+    new: &serenity::Member,
+) -> Result<(), Error> {
+    // Without the old member we can't tell whether screening was passed just now
+    if !old.is_some_and(|old| old.pending) || new.pending {
+        return Ok(());
+    }
+
+    restore_if_enabled(ctx, data, new).await
+}
+
+async fn restore_if_enabled(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let config = data.guild_configs.get(member.guild_id).await?;
+    if member.user.bot || !config.is_enabled(Feature::RestoreRoles) {
+        return Ok(());
+    }
+
+    restore(ctx, data, member, "Restoring roles from before they left").await?;
+    Ok(())
+}
+
+/// Gives a member the roles they had when they left, returns the ones given
+pub async fn restore(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+    reason: &str,
+) -> Result<Vec<serenity::RoleId>, Error> {
+    let guild = member.guild_id;
+    let config = data.guild_configs.get(guild).await?;
+
+    let saved = data
+        .db
+        .take_saved_roles(guild, member.user.id, expired(config.role_retention()))
+        .await?;
+    let bot = ctx.cache.current_user_id();
+    let restorable: Vec<_> = ctx
+        .cache
+        .guild_field(guild, |g| {
+            let top = g
+                .members
+                .get(&bot)
+                .and_then(|m| {
+                    m.roles
+                        .iter()
+                        .filter_map(|r| g.roles.get(r))
+                        .map(|r| r.position)
+                        .max()
+                })
+                .unwrap_or(0);
+            saved
+                .iter()
+                .filter(|r| !member.roles.contains(r))
+                .filter(|r| !config.has_role_in(RoleList::NotRestored, &[**r]))
+                .filter(|r| {
+                    g.roles.get(r).is_some_and(|role| {
+                        role.id.0 != guild.0 && !role.managed && role.position < top
+                    })
+                })
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut restored = Vec::new();
+    for role in restorable {
+        let result = ctx
+            .http
+            .add_member_role(guild.0, member.user.id.0, role.0, Some(reason))
+            .await;
+
+        // The other roles may still work
+        match result {
+            Ok(()) => restored.push(role),
+            Err(e) => tracing::warn!(
+                guild = guild.0,
+                role = role.0,
+                member = member.user.id.0,
+                "Error restoring role: {}",
+                e
+            ),
+        }
+    }
+
+    Ok(restored)
+}
+
+// Roles saved before this unix timestamp aren't restored anymore
+fn expired(retention: std::time::Duration) -> i64 {
+    serenity::Timestamp::now().unix_timestamp() - retention.as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[tokio::test]
+    async fn saved_roles_are_taken_once_and_expire() {
+        let db = Db::memory().await;
+        let guild = serenity::GuildId(1);
+        let (alice, bob) = (serenity::UserId(2), serenity::UserId(3));
+        let roles = [serenity::RoleId(10), serenity::RoleId(11)];
+        let now = serenity::Timestamp::now().unix_timestamp();
+
+        db.save_roles(guild, alice, &roles, 0).await.unwrap();
+        db.save_roles(guild, bob, &roles[..1], 0).await.unwrap();
+        let mut taken = db.take_saved_roles(guild, alice, now - 60).await.unwrap();
+        taken.sort();
+        assert_eq!(taken, roles);
+        assert!(db
+            .take_saved_roles(guild, alice, 0)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Too old to restore, and gone for good
+        assert!(db
+            .take_saved_roles(guild, bob, now + 60)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db.take_saved_roles(guild, bob, 0).await.unwrap().is_empty());
+    }
+}
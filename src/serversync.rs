@@ -0,0 +1,336 @@
+// Syncing the structure of a template guild to other guilds
+// The roles, categories and channels of the source guild are compared with each target guild
+// and whatever is missing gets created, roles first so channel permissions can refer to them.
+// Renames and position changes are optional. Every source object is linked to its copy in the
+// `sync_links` table, so a renamed source object is still recognized on the next sync, objects
+// that were never linked are matched by name. Nothing is ever deleted.
+use std::{collections::HashMap, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Db, Error};
+
+// Pause between changes. serenity already waits out rate limits, but creating dozens of
+// channels back to back still runs into Discord's hidden limits on guild structure changes
+const STEP_DELAY: Duration = Duration::from_millis(750);
+
+/// What a sync may change besides creating missing objects
+pub struct SyncOptions {
+    pub rename: bool,
+    pub reorder: bool,
+    /// Names to skip, case-insensitive, a trailing `*` matches by prefix
+    pub exclude: Vec<String>,
+}
+
+impl SyncOptions {
+    fn is_excluded(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.exclude
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == *pattern,
+            })
+    }
+}
+
+/// One change to a target guild
+pub enum Step {
+    CreateRole(Box<serenity::Role>),
+    RenameRole {
+        target: serenity::RoleId,
+        name: String,
+    },
+    MoveRole {
+        target: serenity::RoleId,
+        position: i64,
+    },
+    /// Categories are created like channels
+    CreateChannel(Box<serenity::GuildChannel>),
+    RenameChannel {
+        target: serenity::ChannelId,
+        name: String,
+    },
+    MoveChannel {
+        target: serenity::ChannelId,
+        position: i64,
+    },
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::CreateRole(role) => write!(f, "+ role `{}`", role.name),
+            Step::RenameRole { target, name } => write!(f, "~ role <@&{}> → `{}`", target.0, name),
+            Step::MoveRole { target, position } => {
+                write!(f, "↕ role <@&{}> to position {}", target.0, position)
+            }
+            Step::CreateChannel(channel) if channel.kind == serenity::ChannelType::Category => {
+                write!(f, "+ category `{}`", channel.name)
+            }
+            Step::CreateChannel(channel) => write!(f, "+ channel `{}`", channel.name),
+            Step::RenameChannel { target, name } => write!(f, "~ <#{}> → `{}`", target.0, name),
+            Step::MoveChannel { target, position } => {
+                write!(f, "↕ <#{}> to position {}", target.0, position)
+            }
+        }
+    }
+}
+
+/// The changes a sync would make to one target guild
+pub struct Plan {
+    pub target: serenity::GuildId,
+    /// Source objects that already have a copy, to be linked once the plan runs
+    matches: Vec<(u64, u64)>,
+    pub steps: Vec<Step>,
+}
+
+/// Compares a target guild with the source and lists what has to change
+pub async fn plan(
+    http: &serenity::Http,
+    db: &Db,
+    source: serenity::GuildId,
+    target: serenity::GuildId,
+    options: &SyncOptions,
+) -> Result<Plan, Error> {
+    let links = db.sync_links(target).await?;
+    let mut plan = Plan {
+        target,
+        matches: vec![(source.0, target.0)],
+        steps: Vec::new(),
+    };
+
+    let mut source_roles: Vec<_> = source.roles(http).await?.into_values().collect();
+    let target_roles = target.roles(http).await?;
+    source_roles.sort_by_key(|r| r.position);
+
+    for role in source_roles {
+        // @everyone has the guild's ID and always exists, integrations manage their own roles
+        if role.id.0 == source.0 || role.managed || options.is_excluded(&role.name) {
+            continue;
+        }
+
+        let existing = links
+            .get(&role.id.0)
+            .and_then(|id| target_roles.get(&serenity::RoleId(*id)))
+            .or_else(|| {
+                target_roles
+                    .values()
+                    .find(|r| r.name.eq_ignore_ascii_case(&role.name) && !plan.is_matched(r.id.0))
+            });
+
+        let existing = match existing {
+            Some(existing) => existing,
+            None => {
+                plan.steps.push(Step::CreateRole(Box::new(role)));
+                continue;
+            }
+        };
+
+        plan.matches.push((role.id.0, existing.id.0));
+        if options.rename && existing.name != role.name {
+            plan.steps.push(Step::RenameRole {
+                target: existing.id,
+                name: role.name.clone(),
+            });
+        }
+        if options.reorder && existing.position != role.position {
+            plan.steps.push(Step::MoveRole {
+                target: existing.id,
+                position: role.position,
+            });
+        }
+    }
+
+    let mut source_channels: Vec<_> = source.channels(http).await?.into_values().collect();
+    let target_channels = target.channels(http).await?;
+    // Categories first, so channels can be matched and created inside them
+    source_channels.sort_by_key(|c| (c.kind != serenity::ChannelType::Category, c.position));
+
+    let mut excluded_categories = Vec::new();
+    for channel in source_channels {
+        if options.is_excluded(&channel.name)
+            || channel
+                .parent_id
+                .is_some_and(|p| excluded_categories.contains(&p))
+        {
+            if channel.kind == serenity::ChannelType::Category {
+                excluded_categories.push(channel.id);
+            }
+            continue;
+        }
+
+        // A channel whose category is still missing can't have a copy yet
+        let parent = match channel.parent_id {
+            Some(parent) => plan.matched(parent.0).map(Some),
+            None => Some(None),
+        };
+        let existing = links
+            .get(&channel.id.0)
+            .and_then(|id| target_channels.get(&serenity::ChannelId(*id)))
+            .or_else(|| {
+                let parent = parent?;
+                target_channels.values().find(|c| {
+                    c.kind == channel.kind
+                        && c.name.eq_ignore_ascii_case(&channel.name)
+                        && c.parent_id.map(|p| p.0) == parent
+                        && !plan.is_matched(c.id.0)
+                })
+            });
+
+        let existing = match existing {
+            Some(existing) => existing,
+            None => {
+                plan.steps.push(Step::CreateChannel(Box::new(channel)));
+                continue;
+            }
+        };
+
+        plan.matches.push((channel.id.0, existing.id.0));
+        if options.rename && existing.name != channel.name {
+            plan.steps.push(Step::RenameChannel {
+                target: existing.id,
+                name: channel.name.clone(),
+            });
+        }
+        if options.reorder && existing.position != channel.position {
+            plan.steps.push(Step::MoveChannel {
+                target: existing.id,
+                position: channel.position,
+            });
+        }
+    }
+
+    Ok(plan)
+}
+
+impl Plan {
+    fn matched(&self, source: u64) -> Option<u64> {
+        self.matches
+            .iter()
+            .find(|(s, _)| *s == source)
+            .map(|(_, t)| *t)
+    }
+
+    fn is_matched(&self, target: u64) -> bool {
+        self.matches.iter().any(|(_, t)| *t == target)
+    }
+
+    /// Makes the changes one by one, returns the errors of the steps that failed
+    pub async fn run(self, http: &serenity::Http, db: &Db) -> Result<Vec<String>, Error> {
+        let mut links: HashMap<u64, u64> = self.matches.iter().copied().collect();
+        for (source, target) in &self.matches {
+            db.set_sync_link(*source, self.target, *target).await?;
+        }
+
+        let mut errors = Vec::new();
+        for step in &self.steps {
+            match self.run_step(http, step, &links).await {
+                Ok(Some((source, target))) => {
+                    links.insert(source, target);
+                    db.set_sync_link(source, self.target, target).await?;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(guild = self.target.0, "Error syncing {}: {}", step, e);
+                    errors.push(format!("{}: {}", step, e));
+                }
+            }
+
+            tokio::time::sleep(STEP_DELAY).await;
+        }
+
+        Ok(errors)
+    }
+
+    // Returns the link between the source object and the one that was created, if any
+    async fn run_step(
+        &self,
+        http: &serenity::Http,
+        step: &Step,
+        links: &HashMap<u64, u64>,
+    ) -> Result<Option<(u64, u64)>, Error> {
+        match step {
+            Step::CreateRole(role) => {
+                let created = self
+                    .target
+                    .create_role(http, |r| {
+                        r.name(&role.name)
+                            .colour(role.colour.0 as u64)
+                            .hoist(role.hoist)
+                            .mentionable(role.mentionable)
+                            .permissions(role.permissions)
+                    })
+                    .await?;
+                Ok(Some((role.id.0, created.id.0)))
+            }
+            Step::RenameRole { target, name } => {
+                self.target
+                    .edit_role(http, *target, |r| r.name(name))
+                    .await?;
+                Ok(None)
+            }
+            Step::MoveRole { target, position } => {
+                self.target
+                    .edit_role_position(http, *target, (*position).max(1) as u64)
+                    .await?;
+                Ok(None)
+            }
+            Step::CreateChannel(channel) => {
+                // Overwrites are only copied for roles that exist in the target, members differ
+                let overwrites: Vec<_> = channel
+                    .permission_overwrites
+                    .iter()
+                    .filter_map(|o| match o.kind {
+                        serenity::PermissionOverwriteType::Role(role) => {
+                            Some(serenity::PermissionOverwrite {
+                                allow: o.allow,
+                                deny: o.deny,
+                                kind: serenity::PermissionOverwriteType::Role(serenity::RoleId(
+                                    *links.get(&role.0)?,
+                                )),
+                            })
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let parent = channel.parent_id.and_then(|p| links.get(&p.0)).copied();
+
+                let created = self
+                    .target
+                    .create_channel(http, |c| {
+                        c.name(&channel.name)
+                            .kind(channel.kind)
+                            .nsfw(channel.nsfw)
+                            .position(channel.position.max(0) as u32)
+                            .permissions(overwrites);
+                        if let Some(topic) = &channel.topic {
+                            c.topic(topic);
+                        }
+                        if let Some(parent) = parent {
+                            c.category(parent);
+                        }
+                        if let Some(slowmode) = channel.rate_limit_per_user {
+                            c.rate_limit_per_user(slowmode);
+                        }
+                        if let Some(limit) = channel.user_limit {
+                            c.user_limit(limit as u32);
+                        }
+                        c
+                    })
+                    .await?;
+                Ok(Some((channel.id.0, created.id.0)))
+            }
+            Step::RenameChannel { target, name } => {
+                target.edit(http, |c| c.name(name)).await?;
+                Ok(None)
+            }
+            Step::MoveChannel { target, position } => {
+                target
+                    .edit(http, |c| c.position((*position).max(0) as u64))
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+// Setup checklist
+// /setup status audits the settings that are easy to miss when adding the bot: where it logs
+// and sends automod flags, whether automod does anything, whether it has the permissions to
+// moderate and whether its role is above the roles it hands out. The bot times members out
+// rather than giving them a muted role, so there's no such role to check. Items with a one
+// click fix get a button, the others say what to do, and the checklist is audited again after
+// every fix.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::RoleList,
+    spam::{Rule, SpamAction, SpamRule},
+    Data, Error,
+};
+
+const BUTTON_PREFIX: &str = "setup";
+/// Permissions the bot's moderation and automod features need
+pub const REQUIRED_PERMISSIONS: serenity::Permissions = serenity::Permissions::MANAGE_ROLES
+    .union(serenity::Permissions::MANAGE_MESSAGES)
+    .union(serenity::Permissions::MODERATE_MEMBERS)
+    .union(serenity::Permissions::KICK_MEMBERS)
+    .union(serenity::Permissions::BAN_MEMBERS)
+    .union(serenity::Permissions::VIEW_AUDIT_LOG);
+// Added by the anti-spam fix, rules the guild has already are left alone
+const RECOMMENDED_RULES: &[(SpamRule, u32, SpamAction)] = &[
+    (SpamRule::Flood, 6, SpamAction::Timeout),
+    (SpamRule::Repeat, 4, SpamAction::Delete),
+    (SpamRule::Mentions, 6, SpamAction::Warn),
+];
+
+/// What a checklist button fixes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fix {
+    LogChannel,
+    ReviewChannel,
+    Antispam,
+}
+
+impl Fix {
+    const ALL: &'static [Fix] = &[Fix::LogChannel, Fix::ReviewChannel, Fix::Antispam];
+
+    fn id(self) -> &'static str {
+        match self {
+            Fix::LogChannel => "logchannel",
+            Fix::ReviewChannel => "reviewchannel",
+            Fix::Antispam => "antispam",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Fix::LogChannel => "Log to this channel",
+            Fix::ReviewChannel => "Review flags in this channel",
+            Fix::Antispam => "Add recommended anti-spam rules",
+        }
+    }
+}
+
+fn parse_button(custom_id: &str) -> Option<Fix> {
+    let action = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    Fix::ALL.iter().copied().find(|fix| fix.id() == action)
+}
+
+/// One line of the checklist
+pub struct Item {
+    pub name: &'static str,
+    pub done: bool,
+    pub detail: String,
+    /// How to fix the item with a button, if it can be
+    pub fix: Option<Fix>,
+}
+
+/// Roles at or above the bot's highest role, which it can't give or take, highest first
+pub fn unreachable_roles(
+    bot_position: i64,
+    roles: &[(serenity::RoleId, i64)],
+) -> Vec<serenity::RoleId> {
+    let mut unreachable: Vec<_> = roles
+        .iter()
+        .filter(|(_, position)| *position >= bot_position)
+        .collect();
+    unreachable.sort_unstable_by_key(|(role, position)| (-position, *role));
+    unreachable.dedup();
+    unreachable.into_iter().map(|(role, _)| *role).collect()
+}
+
+/// Lines of the checklist and how many items are done
+pub fn render(items: &[Item]) -> (String, usize) {
+    let lines = items
+        .iter()
+        .map(|item| {
+            let mark = if item.done {
+                ":white_check_mark:"
+            } else {
+                ":x:"
+            };
+            format!("{} **{}**: {}", mark, item.name, item.detail)
+        })
+        .collect::<Vec<_>>();
+
+    (lines.join("\n"), items.iter().filter(|i| i.done).count())
+}
+
+/// Audits the guild's setup
+pub async fn audit(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+) -> Result<Vec<Item>, Error> {
+    let config = data.guild_configs.get(guild).await?;
+    let rules = data.spam_filter.get(guild).await?;
+    let bot = guild.member(ctx, ctx.cache.current_user_id()).await?;
+    let mut items = Vec::new();
+
+    items.push(Item {
+        name: "Log channel",
+        done: config.log_channel.is_some(),
+        detail: match config.log_channel {
+            Some(channel) => format!("<#{}>", channel.0),
+            None => "Not set, moderation actions aren't logged anywhere".to_string(),
+        },
+        fix: Some(Fix::LogChannel),
+    });
+    items.push(Item {
+        name: "Review channel",
+        done: config.review_channel.is_some(),
+        detail: match config.review_channel {
+            Some(channel) => format!("<#{}>", channel.0),
+            None => "Not set, automod flags have nowhere to go".to_string(),
+        },
+        fix: Some(Fix::ReviewChannel),
+    });
+    items.push(Item {
+        name: "Automod",
+        done: !rules.is_empty(),
+        detail: match rules.len() {
+            0 => "No anti-spam rules, spam is let through".to_string(),
+            n => format!("{} anti-spam rules, see `/antispam list`", n),
+        },
+        fix: Some(Fix::Antispam),
+    });
+    items.push(Item {
+        name: "Staff role",
+        done: config.staff_role.is_some(),
+        detail: match config.staff_role {
+            Some(role) => format!("<@&{}>", role.0),
+            None => {
+                "Not set, `/staff` pings nobody. Set it with `/config set staff_role`".to_string()
+            }
+        },
+        fix: None,
+    });
+
+    let missing = REQUIRED_PERMISSIONS - bot.permissions(ctx)?;
+    items.push(Item {
+        name: "Bot permissions",
+        done: missing.is_empty(),
+        detail: if missing.is_empty() {
+            "Everything moderation needs".to_string()
+        } else {
+            format!(
+                "Missing {}, grant them to the bot's role in Server Settings > Roles",
+                missing.get_permission_names().join(", ")
+            )
+        },
+        fix: None,
+    });
+
+    // Roles the bot gives out by itself
+    let mut handed_out: Vec<serenity::RoleId> = data
+        .db
+        .reaction_roles(guild)
+        .await?
+        .iter()
+        .map(|r| serenity::RoleId(r.role_id as u64))
+        .collect();
+    for list in [RoleList::AutoRole, RoleList::Onboarding] {
+        handed_out.extend(config.role_lists.get(&list).into_iter().flatten().copied());
+    }
+    let positions: Vec<_> = handed_out
+        .iter()
+        .filter_map(|&role| Some((role, role.to_role_cached(ctx)?.position)))
+        .collect();
+    let bot_position = bot
+        .highest_role_info(ctx)
+        .map_or(0, |(_, position)| position);
+    let unreachable = unreachable_roles(bot_position, &positions);
+    items.push(Item {
+        name: "Bot role",
+        done: unreachable.is_empty(),
+        detail: if unreachable.is_empty() {
+            "Above every role the bot hands out".to_string()
+        } else {
+            format!(
+                "Below {}, drag the bot's role above them in Server Settings > Roles",
+                unreachable
+                    .iter()
+                    .map(|role| format!("<@&{}>", role.0))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        },
+        fix: None,
+    });
+
+    Ok(items)
+}
+
+/// Fills an embed with the checklist
+pub fn embed<'a>(
+    e: &'a mut serenity::CreateEmbed,
+    guild_name: &str,
+    items: &[Item],
+) -> &'a mut serenity::CreateEmbed {
+    let (lines, done) = render(items);
+    e.title(format!("Setup of {}", guild_name))
+        .description(lines)
+        .footer(|f| f.text(format!("{} of {} done", done, items.len())))
+}
+
+/// Adds buttons for the items that can be fixed, none once everything is done
+pub fn buttons<'a>(
+    c: &'a mut serenity::CreateComponents,
+    items: &[Item],
+) -> &'a mut serenity::CreateComponents {
+    let fixes: Vec<_> = items
+        .iter()
+        .filter(|item| !item.done)
+        .filter_map(|item| item.fix)
+        .collect();
+    if fixes.is_empty() {
+        return c;
+    }
+
+    c.create_action_row(|r| {
+        for fix in &fixes {
+            r.create_button(|b| {
+                b.custom_id(format!("{}-{}", BUTTON_PREFIX, fix.id()))
+                    .label(fix.label())
+                    .style(serenity::ButtonStyle::Primary)
+            });
+        }
+        r
+    })
+}
+
+/// Applies a fix when an admin clicks one of the checklist's buttons
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let fix = match parse_button(&interaction.data.custom_id) {
+        Some(fix) => fix,
+        None => return Ok(()),
+    };
+
+    let guild = match interaction.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+    let permissions = interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .unwrap_or_default();
+    if !permissions.administrator() {
+        interaction
+            .create_interaction_response(ctx, |r| {
+                r.interaction_response_data(|d| {
+                    d.content(":x: Only admins can change the setup.")
+                        .ephemeral(true)
+                })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let channel = interaction.channel_id;
+    match fix {
+        Fix::LogChannel => {
+            data.guild_configs
+                .update(guild, |c| c.log_channel = Some(channel))
+                .await?;
+        }
+        Fix::ReviewChannel => {
+            data.guild_configs
+                .update(guild, |c| c.review_channel = Some(channel))
+                .await?;
+        }
+        Fix::Antispam => {
+            let rules = data.spam_filter.get(guild).await?;
+            for &(kind, threshold, action) in RECOMMENDED_RULES {
+                if rules.iter().any(|r| r.kind == kind) {
+                    continue;
+                }
+                let rule = Rule {
+                    kind,
+                    threshold,
+                    action,
+                    timeout: None,
+                };
+                data.spam_filter.set(guild, &rule).await?;
+            }
+        }
+    }
+    tracing::info!(
+        guild = guild.0,
+        user = interaction.user.id.0,
+        fix = fix.id(),
+        "Applied setup fix"
+    );
+
+    let items = audit(ctx, data, guild).await?;
+    let name = guild.name(ctx).unwrap_or_else(|| "this server".to_string());
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.kind(serenity::InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    d.embed(|e| embed(e, &name, &items))
+                        .components(|c| buttons(c, &items))
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &'static str, done: bool) -> Item {
+        Item {
+            name,
+            done,
+            detail: "details".to_string(),
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn buttons_are_parsed() {
+        for &fix in Fix::ALL {
+            let custom_id = format!("{}-{}", BUTTON_PREFIX, fix.id());
+            assert_eq!(parse_button(&custom_id), Some(fix));
+        }
+        assert_eq!(parse_button("setup-muterole"), None);
+        assert_eq!(parse_button("reviewqueue-approve-1"), None);
+    }
+
+    #[test]
+    fn roles_at_or_above_the_bot_are_unreachable() {
+        let role = serenity::RoleId;
+        let roles = [(role(1), 3), (role(2), 5), (role(3), 8), (role(2), 5)];
+
+        assert_eq!(unreachable_roles(5, &roles), [role(3), role(2)]);
+        assert!(unreachable_roles(9, &roles).is_empty());
+        // A bot without roles can't hand out any
+        assert_eq!(unreachable_roles(0, &roles).len(), 3);
+    }
+
+    #[test]
+    fn the_checklist_counts_done_items() {
+        let (lines, done) = render(&[item("Log channel", true), item("Automod", false)]);
+        assert_eq!(
+            lines,
+            ":white_check_mark: **Log channel**: details\n:x: **Automod**: details"
+        );
+        assert_eq!(done, 1);
+    }
+}
@@ -0,0 +1,199 @@
+// Shop
+// Admins stock each guild's shop with /shop admin. Role items give a role, for a while if they're
+// rentals, titles are cosmetic, perks are whatever the server promises, handed out by its staff,
+// and streak freezes save streaks over a missed day. Buying pays with economy coins and fills the
+// inventory in one transaction, a role that can't be given is refunded right away. Rented roles are
+// taken away by a job when the rental ends, buying one again extends it.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::ShopItem,
+    economy::{self, Transfer},
+    jobs::{self, JobKind},
+    Context, Error,
+};
+
+/// Most items a shop can have
+pub const MAX_ITEMS: usize = 25;
+/// Longest item names
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// What an item is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ItemKind {
+    /// Gives a role, for a while if it's a rental
+    #[name = "role"]
+    Role,
+    /// Cosmetic, shown in the inventory
+    #[name = "title"]
+    Title,
+    /// Anything else, the server's staff hands it out
+    #[name = "perk"]
+    Perk,
+    /// Keeps a streak going over a missed day, used up automatically
+    #[name = "streak_freeze"]
+    StreakFreeze,
+}
+
+impl ItemKind {
+    /// Whether members can own more than one
+    pub fn stacks(self) -> bool {
+        matches!(self, ItemKind::Perk | ItemKind::StreakFreeze)
+    }
+}
+
+/// Buys an item for the author and returns the reply
+pub async fn buy(ctx: Context<'_>, item: &ShopItem) -> Result<String, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+    let kind: ItemKind = item.kind.parse()?;
+
+    let owned = db
+        .inventory(guild, author)
+        .await?
+        .into_iter()
+        .any(|i| i.name == item.name);
+    if owned && !kind.stacks() && item.rental_secs.is_none() {
+        return Ok(format!(":x: You own **{}** already.", item.name));
+    }
+
+    let key = economy::invocation_key(ctx, "shop");
+    let expires_at = match db.buy_item(guild, author, item, &key).await? {
+        (Transfer::Insufficient, _) => {
+            return Ok(format!(
+                ":x: **{}** costs {}, you have {}.",
+                item.name,
+                economy::format(item.price),
+                economy::format(db.balance(guild, author).await?)
+            ))
+        }
+        (_, expires_at) => expires_at,
+    };
+
+    if let Some(role) = item.role_id {
+        if let Err(e) = grant(ctx, guild, role as u64, expires_at).await {
+            db.refund_item(guild, author, item, &key).await?;
+            return Err(e);
+        }
+    }
+
+    let mut reply = format!(
+        ":white_check_mark: You bought **{}** for {}",
+        item.name,
+        economy::format(item.price)
+    );
+    if let Some(expires_at) = expires_at {
+        reply.push_str(&format!(", it's yours until <t:{}:f>", expires_at));
+    }
+    match kind {
+        ItemKind::Perk => reply.push_str(", the staff of this server will hand it out"),
+        ItemKind::StreakFreeze => reply.push_str(", it saves your streaks when you miss a day"),
+        _ => {}
+    }
+
+    Ok(reply)
+}
+
+// Gives the role of an item, and schedules taking it away again if it's rented
+async fn grant(
+    ctx: Context<'_>,
+    guild: serenity::GuildId,
+    role: u64,
+    expires_at: Option<i64>,
+) -> Result<(), Error> {
+    let author = ctx.author().id;
+    ctx.discord()
+        .http
+        .add_member_role(guild.0, author.0, role, Some("Bought in the shop"))
+        .await?;
+
+    // Replaces the job of the current rental, so buying again extends it
+    if let Some(expires_at) = expires_at {
+        jobs::schedule(
+            &ctx.data().db,
+            JobKind::RemoveRole,
+            guild,
+            author,
+            role,
+            expires_at,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::db::{Db, NewShopItem};
+
+    const GUILD: serenity::GuildId = serenity::GuildId(1);
+    const USER: serenity::UserId = serenity::UserId(2);
+    const DAY: i64 = 24 * 60 * 60;
+
+    async fn db_with(item: NewShopItem<'_>, coins: i64) -> (Db, ShopItem) {
+        let db = Db::memory().await;
+        db.create_shop_item(GUILD, &item).await.unwrap();
+        db.transfer(GUILD, None, Some(USER), coins, "test", "mint")
+            .await
+            .unwrap();
+        let item = db.shop_item(GUILD, item.name).await.unwrap().unwrap();
+        (db, item)
+    }
+
+    #[tokio::test]
+    async fn rentals_extend_from_when_they_expire() {
+        let (db, item) = db_with(
+            NewShopItem {
+                name: "VIP",
+                kind: "role",
+                price: 40,
+                description: None,
+                role: Some(serenity::RoleId(3)),
+                rental: Some(Duration::from_secs(DAY as u64)),
+            },
+            100,
+        )
+        .await;
+
+        let (first, until) = db.buy_item(GUILD, USER, &item, "a").await.unwrap();
+        assert_eq!(first, Transfer::Done);
+        let (second, extended) = db.buy_item(GUILD, USER, &item, "b").await.unwrap();
+        assert_eq!(second, Transfer::Done);
+        assert_eq!(extended.unwrap(), until.unwrap() + DAY);
+
+        let (third, _) = db.buy_item(GUILD, USER, &item, "c").await.unwrap();
+        assert_eq!(third, Transfer::Insufficient);
+        assert_eq!(db.balance(GUILD, USER).await.unwrap(), 20);
+        assert_eq!(db.inventory(GUILD, USER).await.unwrap()[0].quantity, 2);
+    }
+
+    #[tokio::test]
+    async fn refunds_undo_the_purchase() {
+        let (db, item) = db_with(
+            NewShopItem {
+                name: "Champion",
+                kind: "title",
+                price: 30,
+                description: Some("Bragging rights"),
+                role: None,
+                rental: None,
+            },
+            30,
+        )
+        .await;
+
+        db.buy_item(GUILD, USER, &item, "a").await.unwrap();
+        assert_eq!(db.balance(GUILD, USER).await.unwrap(), 0);
+        db.refund_item(GUILD, USER, &item, "a").await.unwrap();
+        // Refunding twice pays back only once
+        db.refund_item(GUILD, USER, &item, "a").await.unwrap();
+
+        assert_eq!(db.balance(GUILD, USER).await.unwrap(), 30);
+        assert!(db.inventory(GUILD, USER).await.unwrap().is_empty());
+    }
+}
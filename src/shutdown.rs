@@ -0,0 +1,127 @@
+// Graceful shutdown
+// SIGINT and SIGTERM (Ctrl+C, systemd, `docker stop`) and /shutdown all end up in
+// `Shutdown::run`. New events are ignored from then on, the bot goes invisible so nobody talks
+// to it while it leaves, and handlers that are still running get a moment to finish before
+// every shard disconnects. Pending counters are written, then the database is closed last so
+// SQLite can finish pending writes, main waits for that before returning since the runtime
+// would drop the task otherwise.
+// Rate limiters only live in memory and start over on the next start anyway.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+use tokio::sync::{watch, Mutex};
+
+use crate::{counters::Counters, db::Db};
+
+// Time handlers that are already running get, also lets the presence update go out
+const GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+pub struct Shutdown {
+    started: AtomicBool,
+    finished: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            finished: watch::Sender::new(false),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Whether the bot is shutting down, events arriving now should be ignored
+    pub fn is_shutting_down(&self) -> bool {
+        self.started.load(Ordering::Relaxed)
+    }
+
+    /// Shuts the bot down, does nothing if a shutdown is already in progress
+    pub async fn run(
+        &self,
+        shard_manager: &Arc<Mutex<serenity::ShardManager>>,
+        db: &Db,
+        counters: &Counters,
+    ) {
+        if self.started.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        tracing::info!("Shutting down");
+
+        {
+            let manager = shard_manager.lock().await;
+            for runner in manager.runners.lock().await.values() {
+                runner
+                    .runner_tx
+                    .set_presence(None, serenity::OnlineStatus::Invisible);
+            }
+        }
+
+        tokio::time::sleep(GRACE_PERIOD).await;
+        shard_manager.lock().await.shutdown_all().await;
+        if let Err(e) = counters.flush().await {
+            tracing::error!(
+                "Error writing counters, their latest counts are lost: {}",
+                e
+            );
+        }
+        db.close().await;
+        self.finished.send_replace(true);
+    }
+
+    /// Waits until a shutdown that was started has closed everything, returns right away if
+    /// none was started
+    pub async fn wait(&self) {
+        if !self.is_shutting_down() {
+            return;
+        }
+
+        let mut finished = self.finished.subscribe();
+        // The sender lives in self, so this can't fail
+        let _ = finished.wait_for(|finished| *finished).await;
+    }
+}
+
+/// Starts a task that shuts the bot down on SIGINT or SIGTERM
+pub fn spawn_signal_handler(
+    shutdown: Arc<Shutdown>,
+    shard_manager: Arc<Mutex<serenity::ShardManager>>,
+    db: Db,
+    counters: Counters,
+) {
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        shutdown.run(&shard_manager, &db, &counters).await;
+    });
+}
+
+async fn wait_for_signal() {
+    // Docker and systemd stop services with SIGTERM, which only exists on unix
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Error listening for SIGTERM: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        // Without a handler the default behavior of dying on the signal stays in place
+        tracing::warn!("Error listening for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,651 @@
+// Anti-spam automod
+// Guilds set up rules with /antispam for flooding (too many messages in a few seconds), repeated
+// messages, mass mentions, link spam and messages in all caps. A message that breaks a rule is
+// deleted, and depending on the rule its author is also warned or timed out, or it's left alone
+// and sent to the review queue. Messages that keep breaking the rule within its window are
+// deleted too, without warning or timing out again. Warnings count towards the guild's warning
+// threshold like ones given with /warn. Members with a role of the `automod_exempt` list are
+// never checked. Recent messages are only kept in memory, so counting starts over after a
+// restart.
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{GuildCache, GuildConfig, RoleList},
+    db::Db,
+    dm, duration, links,
+    modlog::{self, Action},
+    permissions,
+    reviewqueue::{self, Flag},
+    Data, Error,
+};
+
+// Window flood rules count messages in
+const FLOOD_WINDOW: Duration = Duration::from_secs(10);
+// Window repeat rules count identical messages in, also how long messages are remembered
+const REPEAT_WINDOW: Duration = Duration::from_secs(60);
+// Short messages like "OK" or "LOL" aren't shouting
+const CAPS_MIN_LETTERS: usize = 10;
+/// Timeout length for rules that don't set their own
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+// Histories are swept for inactive members once this many are kept
+const HISTORY_SWEEP_SIZE: usize = 10_000;
+
+/// What a rule looks for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum SpamRule {
+    /// Too many messages within a few seconds
+    #[name = "flood"]
+    Flood,
+    /// The same message over and over
+    #[name = "repeat"]
+    Repeat,
+    /// Too many mentions in one message
+    #[name = "mentions"]
+    Mentions,
+    /// Mostly capital letters
+    #[name = "caps"]
+    Caps,
+    /// Too many links in one message
+    #[name = "links"]
+    Links,
+}
+
+impl SpamRule {
+    /// Checks are done in this order, the first broken rule is acted on
+    pub const ALL: &'static [SpamRule] = &[
+        SpamRule::Mentions,
+        SpamRule::Links,
+        SpamRule::Repeat,
+        SpamRule::Flood,
+        SpamRule::Caps,
+    ];
+
+    /// Thresholds that make sense for the rule
+    pub fn limits(self) -> (u32, u32) {
+        match self {
+            SpamRule::Flood => (2, 50),
+            SpamRule::Repeat => (2, 20),
+            SpamRule::Mentions => (1, 50),
+            SpamRule::Caps => (50, 100),
+            SpamRule::Links => (1, 20),
+        }
+    }
+
+    // How long after acting on a member their next messages breaking the rule are only deleted
+    fn window(self) -> Duration {
+        match self {
+            SpamRule::Repeat => REPEAT_WINDOW,
+            SpamRule::Flood | SpamRule::Mentions | SpamRule::Caps | SpamRule::Links => FLOOD_WINDOW,
+        }
+    }
+
+    /// What a threshold means for the rule, like `5 messages in 10s`
+    pub fn describe(self, threshold: u32) -> String {
+        match self {
+            SpamRule::Flood => format!(
+                "{} messages in {}",
+                threshold,
+                duration::format(FLOOD_WINDOW)
+            ),
+            SpamRule::Repeat => format!(
+                "the same message {} times in {}",
+                threshold,
+                duration::format(REPEAT_WINDOW)
+            ),
+            SpamRule::Mentions => format!("{} mentions in one message", threshold),
+            SpamRule::Caps => format!("{}% capital letters", threshold),
+            SpamRule::Links => format!("{} links in one message", threshold),
+        }
+    }
+}
+
+/// What happens to a message that breaks a rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum SpamAction {
+    /// Only delete the message
+    #[name = "delete"]
+    Delete,
+    /// Delete it and warn the author
+    #[name = "warn"]
+    Warn,
+    /// Delete it and time the author out
+    #[name = "timeout"]
+    Timeout,
+    /// Leave it and flag it for staff to review
+    #[name = "review"]
+    Review,
+}
+
+#[derive(Clone)]
+pub struct Rule {
+    pub kind: SpamRule,
+    pub threshold: u32,
+    pub action: SpamAction,
+    pub timeout: Option<Duration>,
+}
+
+impl Rule {
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+}
+
+struct Recent {
+    at: Instant,
+    content: u64,
+}
+
+#[derive(Default)]
+struct History {
+    messages: VecDeque<Recent>,
+    // When each rule last acted on the member
+    acted: HashMap<SpamRule, Instant>,
+}
+
+/// Anti-spam rules per guild, loaded from the database on first use, and recent messages
+pub struct SpamFilter {
+    db: Db,
+    cache: GuildCache<Vec<Rule>>,
+    history: Mutex<HashMap<(serenity::GuildId, serenity::UserId), History>>,
+}
+
+impl SpamFilter {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            cache: GuildCache::default(),
+            history: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rules of a guild in the order they're checked
+    pub async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Vec<Rule>>, Error> {
+        self.cache
+            .get_or_load(guild, async {
+                let mut rules: Vec<_> = self
+                    .db
+                    .spam_rules(guild)
+                    .await?
+                    .into_iter()
+                    .filter_map(|r| {
+                        Some(Rule {
+                            kind: r.rule.parse().ok()?,
+                            threshold: r.threshold as u32,
+                            action: r.action.parse().ok()?,
+                            timeout: r.timeout_secs.map(|t| Duration::from_secs(t as u64)),
+                        })
+                    })
+                    .collect();
+                rules.sort_by_key(|r| SpamRule::ALL.iter().position(|k| *k == r.kind));
+                Ok(rules)
+            })
+            .await
+    }
+
+    /// Adds a rule, replacing the guild's current rule of the same kind
+    pub async fn set(&self, guild: serenity::GuildId, rule: &Rule) -> Result<(), Error> {
+        self.db
+            .set_spam_rule(
+                guild,
+                rule.kind.name(),
+                rule.threshold as i64,
+                rule.action.name(),
+                rule.timeout,
+            )
+            .await?;
+        self.cache.invalidate(guild);
+        Ok(())
+    }
+
+    /// Removes a rule, returns false if the guild didn't have it
+    pub async fn remove(&self, guild: serenity::GuildId, kind: SpamRule) -> Result<bool, Error> {
+        let removed = self.db.delete_spam_rule(guild, kind.name()).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached rules, returns how many guilds had them cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    // Remembers a message, returns how many the author sent within the flood window and how
+    // many of them, this one included, were the same within the repeat window
+    fn record(&self, message: &serenity::Message, guild: serenity::GuildId) -> (u32, u32) {
+        let now = Instant::now();
+        let mut hasher = DefaultHasher::new();
+        message.content.trim().to_lowercase().hash(&mut hasher);
+        let content = hasher.finish();
+
+        let mut history = self.history.lock().unwrap();
+        if history.len() >= HISTORY_SWEEP_SIZE {
+            history.retain(|_, history| {
+                history
+                    .messages
+                    .back()
+                    .is_some_and(|r| now.duration_since(r.at) < REPEAT_WINDOW)
+            });
+        }
+
+        let recent = &mut history
+            .entry((guild, message.author.id))
+            .or_default()
+            .messages;
+        while recent
+            .front()
+            .is_some_and(|r| now.duration_since(r.at) >= REPEAT_WINDOW)
+        {
+            recent.pop_front();
+        }
+        recent.push_back(Recent { at: now, content });
+
+        let flood = recent
+            .iter()
+            .filter(|r| now.duration_since(r.at) < FLOOD_WINDOW)
+            .count();
+        // Messages with only attachments all look the same
+        let repeats = if message.content.trim().is_empty() {
+            1
+        } else {
+            recent.iter().filter(|r| r.content == content).count()
+        };
+
+        (flood as u32, repeats as u32)
+    }
+
+    // Remembers that a rule acted on a member, returns false if it did already within the
+    // rule's window, so the member was warned or timed out for this burst already
+    fn first_offence(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        rule: SpamRule,
+    ) -> bool {
+        let now = Instant::now();
+        let mut history = self.history.lock().unwrap();
+        let acted = &mut history.entry((guild, user)).or_default().acted;
+
+        let first = acted
+            .get(&rule)
+            .is_none_or(|at| now.duration_since(*at) >= rule.window());
+        acted.insert(rule, now);
+        first
+    }
+}
+
+fn mentions(message: &serenity::Message) -> u32 {
+    let everyone = if message.mention_everyone { 1 } else { 0 };
+    (message.mentions.len() + message.mention_roles.len()) as u32 + everyone
+}
+
+/// Percentage of the letters that are capital, None for messages with too few letters
+pub fn caps_percent(content: &str) -> Option<u32> {
+    let letters: Vec<_> = content.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < CAPS_MIN_LETTERS {
+        return None;
+    }
+
+    let upper = letters.iter().filter(|c| c.is_uppercase()).count();
+    Some((upper * 100 / letters.len()) as u32)
+}
+
+// The first rule a message breaks, given how many messages its author sent within the flood
+// window and how many times they repeated it, see `SpamFilter::record`
+fn broken<'a>(
+    rules: &'a [Rule],
+    message: &serenity::Message,
+    flood: u32,
+    repeats: u32,
+) -> Option<&'a Rule> {
+    rules.iter().find(|rule| match rule.kind {
+        SpamRule::Flood => flood >= rule.threshold,
+        SpamRule::Repeat => repeats >= rule.threshold,
+        SpamRule::Mentions => mentions(message) >= rule.threshold,
+        SpamRule::Caps => caps_percent(&message.content).is_some_and(|p| p >= rule.threshold),
+        SpamRule::Links => links::find_links(&message.content).len() as u32 >= rule.threshold,
+    })
+}
+
+/// Checks a message against the guild's rules and acts on the first one it breaks, returns
+/// whether the message was deleted
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(false),
+    };
+
+    let rules = data.spam_filter.get(guild).await?;
+    if rules.is_empty() {
+        return Ok(false);
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let roles = message
+        .member
+        .as_ref()
+        .map(|m| m.roles.as_slice())
+        .unwrap_or_default();
+    if config.has_role_in(RoleList::AutomodExempt, roles) {
+        return Ok(false);
+    }
+
+    let (flood, repeats) = data.spam_filter.record(message, guild);
+    let rule = match broken(&rules, message, flood, repeats) {
+        Some(rule) => rule,
+        None => return Ok(false),
+    };
+
+    // Only the first message of a burst is flagged, the rest would fill the review queue
+    if rule.action == SpamAction::Review {
+        if data
+            .spam_filter
+            .first_offence(guild, message.author.id, rule.kind)
+        {
+            let flag = Flag {
+                source: "spam",
+                rule: rule.kind.name(),
+                title: "Possible spam",
+                details: rule.kind.describe(rule.threshold),
+                image: None,
+            };
+            reviewqueue::flag(ctx, data, &config, message, flag).await?;
+        }
+        return Ok(false);
+    }
+
+    // Missing permissions shouldn't stop the rest of the rule, or the rest of the pipeline
+    let deleted = match message.delete(ctx).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                guild = guild.0,
+                channel = message.channel_id.0,
+                "Error deleting spam message: {}",
+                e
+            );
+            false
+        }
+    };
+    if !data
+        .spam_filter
+        .first_offence(guild, message.author.id, rule.kind)
+    {
+        return Ok(deleted);
+    }
+
+    // The message is gone either way, so the rest of the pipeline shouldn't see it
+    if let Err(e) = punish(ctx, data, &config, guild, message, rule).await {
+        if !permissions::log_missing(ctx, "spam", &e) {
+            tracing::error!(guild = guild.0, "Error acting on spam: {:?}", e);
+        }
+    }
+
+    Ok(deleted)
+}
+
+// Warns or times out the author of a message that broke a rule and logs it
+async fn punish(
+    ctx: &serenity::Context,
+    data: &Data,
+    config: &GuildConfig,
+    guild: serenity::GuildId,
+    message: &serenity::Message,
+    rule: &Rule,
+) -> Result<(), Error> {
+    let reason = format!("Automod: {}", rule.kind.describe(rule.threshold));
+    let guild_name = ctx
+        .cache
+        .guild_field(guild, |g| g.name.clone())
+        .unwrap_or_default();
+    let bot: serenity::User = ctx.cache.current_user().into();
+
+    let (name, notice, details) = match rule.action {
+        SpamAction::Delete => (
+            "had a message removed",
+            format!(
+                "Your message in **{}** was removed.\nReason: {}",
+                guild_name, reason
+            ),
+            format!("In <#{}>", message.channel_id.0),
+        ),
+        // Flagged before deleting anything
+        SpamAction::Review => return Ok(()),
+        SpamAction::Warn => {
+            let details = format!(
+                "{}, in <#{}>",
+                warn(ctx, data, config, guild, message.author.id, bot.id, &reason).await?,
+                message.channel_id.0
+            );
+
+            (
+                "warned",
+                format!(
+                    "You have been warned in **{}**.\nReason: {}",
+                    guild_name, reason
+                ),
+                details,
+            )
+        }
+        SpamAction::Timeout => {
+            timeout(ctx, guild, message.author.id, rule.timeout()).await?;
+            let length = duration::format(rule.timeout());
+            (
+                "timed out",
+                format!(
+                    "You have been timed out in **{}** for {}.\nReason: {}",
+                    guild_name, length, reason
+                ),
+                format!("For {} in <#{}>", length, message.channel_id.0),
+            )
+        }
+    };
+
+    dm::send(
+        ctx,
+        &data.dm_stats,
+        &dm::MODERATION_NOTICE,
+        &message.author,
+        message.channel_id,
+        &notice,
+    )
+    .await;
+
+    modlog::post_action(
+        ctx,
+        data,
+        guild,
+        &bot,
+        Action {
+            name,
+            target: &message.author,
+            reason: &reason,
+            details: Some(details),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Warns a member, timing them out when they reach another multiple of the warning threshold
+/// like /warn does. Returns the case and warning count for the mod log
+pub async fn warn(
+    ctx: &serenity::Context,
+    data: &Data,
+    config: &GuildConfig,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    moderator: serenity::UserId,
+    reason: &str,
+) -> Result<String, Error> {
+    let warning = data.db.add_warning(guild, user, moderator, reason).await?;
+    let count = data.db.warnings_for(guild, user).await?.len();
+    let mut details = format!("Case #{}, warning {} in total", warning.case_id, count);
+
+    if let Some(threshold) = config.warn_threshold.filter(|t| *t > 0) {
+        if count % threshold as usize == 0 {
+            match timeout(ctx, guild, user, config.warn_timeout()).await {
+                Ok(()) => details.push_str(&format!(
+                    ", timed out for {} for reaching {} warnings",
+                    duration::format(config.warn_timeout()),
+                    count
+                )),
+                Err(e) => tracing::warn!("Error applying warning timeout: {}", e),
+            }
+        }
+    }
+
+    Ok(details)
+}
+
+/// Times a member out for `length` from now
+pub async fn timeout(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+    length: Duration,
+) -> Result<(), Error> {
+    let until = serenity::Timestamp::from_unix_timestamp(
+        serenity::Timestamp::now().unix_timestamp() + length.as_secs() as i64,
+    )?;
+    guild
+        .edit_member(ctx, user, |m| m.disable_communication_until_datetime(until))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: &str, mentions: usize) -> serenity::Message {
+        let users: Vec<_> = (0..mentions)
+            .map(|i| {
+                serde_json::json!({
+                    "id": (100 + i).to_string(),
+                    "username": "mentioned",
+                    "discriminator": "0001",
+                    "avatar": null
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": "10",
+            "channel_id": "2",
+            "author": {
+                "id": "3",
+                "username": "member",
+                "discriminator": "0001",
+                "avatar": null
+            },
+            "content": content,
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": users,
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": 0
+        }))
+        .unwrap()
+    }
+
+    fn rule(kind: SpamRule, threshold: u32) -> Rule {
+        Rule {
+            kind,
+            threshold,
+            action: SpamAction::Delete,
+            timeout: None,
+        }
+    }
+
+    #[test]
+    fn rules_break_at_their_threshold() {
+        fn kind(
+            rules: &[Rule],
+            message: &serenity::Message,
+            flood: u32,
+            repeats: u32,
+        ) -> Option<SpamRule> {
+            broken(rules, message, flood, repeats).map(|r| r.kind)
+        }
+        let hello = message("hello there", 0);
+
+        let flood = [rule(SpamRule::Flood, 5)];
+        assert_eq!(kind(&flood, &hello, 4, 1), None);
+        assert_eq!(kind(&flood, &hello, 5, 1), Some(SpamRule::Flood));
+
+        let repeat = [rule(SpamRule::Repeat, 3)];
+        assert_eq!(kind(&repeat, &hello, 3, 2), None);
+        assert_eq!(kind(&repeat, &hello, 3, 3), Some(SpamRule::Repeat));
+
+        let mentions = [rule(SpamRule::Mentions, 3)];
+        assert_eq!(kind(&mentions, &message("hi all", 2), 1, 1), None);
+        assert_eq!(
+            kind(&mentions, &message("hi all", 3), 1, 1),
+            Some(SpamRule::Mentions)
+        );
+
+        let caps = [rule(SpamRule::Caps, 70)];
+        assert_eq!(kind(&caps, &message("WHY IS THIS so slow", 0), 1, 1), None);
+        assert_eq!(
+            kind(&caps, &message("WHY IS THIS SO SLOW", 0), 1, 1),
+            Some(SpamRule::Caps)
+        );
+        // Too short to count as shouting
+        assert_eq!(kind(&caps, &message("OK LOL", 0), 1, 1), None);
+
+        // The first broken rule in checking order wins
+        let mut rules = vec![rule(SpamRule::Flood, 2), rule(SpamRule::Mentions, 1)];
+        rules.sort_by_key(|r| SpamRule::ALL.iter().position(|k| *k == r.kind));
+        assert_eq!(
+            kind(&rules, &message("hi", 1), 2, 1),
+            Some(SpamRule::Mentions)
+        );
+    }
+
+    #[test]
+    fn capital_letters_are_counted_as_a_percentage() {
+        assert_eq!(caps_percent("HELLO WORLD"), Some(100));
+        assert_eq!(caps_percent("Hello World, how are you"), Some(10));
+        assert_eq!(caps_percent("1234567890 !!"), None);
+    }
+
+    #[tokio::test]
+    async fn messages_are_counted_per_member() {
+        let filter = SpamFilter::new(Db::memory().await);
+        let guild = serenity::GuildId(1);
+
+        assert_eq!(filter.record(&message("hello", 0), guild), (1, 1));
+        assert_eq!(filter.record(&message("Hello ", 0), guild), (2, 2));
+        assert_eq!(filter.record(&message("bye", 0), guild), (3, 1));
+        // Attachment-only messages aren't repeats of each other
+        assert_eq!(filter.record(&message("", 0), guild), (4, 1));
+        assert_eq!(filter.record(&message("", 0), guild), (5, 1));
+        // Other guilds count apart
+        assert_eq!(
+            filter.record(&message("hello", 0), serenity::GuildId(2)),
+            (1, 1)
+        );
+
+        let user = serenity::UserId(3);
+        assert!(filter.first_offence(guild, user, SpamRule::Flood));
+        assert!(!filter.first_offence(guild, user, SpamRule::Flood));
+        assert!(filter.first_offence(guild, user, SpamRule::Caps));
+    }
+}
@@ -0,0 +1,185 @@
+// Spoiler enforcement
+// Moderators add rules with keywords for something that shouldn't be spoiled, like a show that
+// is currently airing, and how long the rule lasts. Messages mentioning a keyword outside of
+// spoiler tags are deleted and reposted through a webhook with everything spoilered.
+// Needs the Manage Messages and Manage Webhooks permissions.
+use std::{borrow::Cow, sync::Arc, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::GuildCache,
+    db::{Db, SpoilerRule},
+    webhooks, Data, Error,
+};
+
+// Bigger attachments can't be uploaded again without Nitro boosts, so the message is kept
+const MAX_ATTACHMENT_BYTES: u64 = 8 * 1024 * 1024;
+// Attachments are downloaded again for the repost, which shouldn't hang on a slow CDN
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Spoiler rules per guild, loaded from the database on first use
+pub struct SpoilerRules {
+    db: Db,
+    client: reqwest::Client,
+    cache: GuildCache<Vec<SpoilerRule>>,
+}
+
+impl SpoilerRules {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .expect("Failed to build spoiler HTTP client"),
+            cache: GuildCache::default(),
+        }
+    }
+
+    /// Rules that hadn't expired when they were loaded, check `expires_at` before using one
+    pub async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Vec<SpoilerRule>>, Error> {
+        let now = serenity::Timestamp::now().unix_timestamp();
+        self.cache
+            .get_or_load(guild, self.db.spoiler_rules(guild, now))
+            .await
+    }
+
+    /// Adds a rule and returns its ID
+    pub async fn add(
+        &self,
+        guild: serenity::GuildId,
+        name: &str,
+        keywords: &[String],
+        channels: &[serenity::ChannelId],
+        expires_at: i64,
+    ) -> Result<i64, Error> {
+        let id = self
+            .db
+            .add_spoiler_rule(guild, name, keywords, channels, expires_at)
+            .await?;
+        self.cache.invalidate(guild);
+        Ok(id)
+    }
+
+    /// Removes a rule, returns false if it didn't exist
+    pub async fn remove(&self, guild: serenity::GuildId, id: i64) -> Result<bool, Error> {
+        let removed = self.db.delete_spoiler_rule(guild, id).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached rules, returns how many guilds had them cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    async fn download(&self, attachment: &serenity::Attachment) -> Result<Vec<u8>, Error> {
+        if attachment.size > MAX_ATTACHMENT_BYTES {
+            return Err("Attachment is too big to upload again".into());
+        }
+
+        let bytes = self
+            .client
+            .get(&attachment.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Reposts a message with spoilers if it breaks a spoiler rule
+/// Returns whether the message was replaced
+pub async fn enforce(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(false),
+    };
+
+    let rules = data.spoiler_rules.get(guild).await?;
+    if rules.is_empty() {
+        return Ok(false);
+    }
+
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let visible = outside_spoilers(&message.content).to_lowercase();
+    let broken = rules.iter().any(|rule| {
+        rule.expires_at > now
+            && (rule.channels.is_empty() || rule.channels.contains(&message.channel_id))
+            && rule.keywords.iter().any(|k| visible.contains(k.as_str()))
+    });
+    if !broken {
+        return Ok(false);
+    }
+
+    // The attachments are gone once the message is deleted, so they are downloaded first
+    let mut files = Vec::new();
+    for attachment in &message.attachments {
+        match data.spoiler_rules.download(attachment).await {
+            Ok(bytes) => files.push(serenity::AttachmentType::Bytes {
+                data: Cow::Owned(bytes),
+                filename: format!("SPOILER_{}", attachment.filename),
+            }),
+            Err(e) => {
+                tracing::debug!(
+                    attachment = %attachment.url,
+                    "Not reposting spoiler, error downloading attachment: {}",
+                    e
+                );
+                return Ok(false);
+            }
+        }
+    }
+
+    message.delete(ctx).await?;
+
+    let parts = spoilered_parts(&message.content);
+    if let Err(e) = webhooks::repost(ctx, message, parts, files).await {
+        tracing::warn!(
+            channel = message.channel_id.0,
+            "Error reposting spoilered message: {}",
+            e
+        );
+    }
+
+    Ok(true)
+}
+
+// The text that isn't hidden between a pair of spoiler tags
+fn outside_spoilers(content: &str) -> String {
+    let parts: Vec<&str> = content.split("||").collect();
+
+    // With an even number of parts the last `||` isn't closed, so what follows it is visible
+    let closed = if parts.len().is_multiple_of(2) {
+        parts.len() - 1
+    } else {
+        parts.len()
+    };
+
+    parts
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % 2 == 0 || *i >= closed)
+        .map(|(_, part)| *part)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The whole content as spoilers, split so every part fits in a message
+fn spoilered_parts(content: &str) -> Vec<String> {
+    // Existing tags would end our spoiler early
+    let content = content.replace("||", "");
+
+    webhooks::split(&content, webhooks::MAX_PART_LENGTH - 4)
+        .into_iter()
+        .map(|part| format!("||{}||", part))
+        .collect()
+}
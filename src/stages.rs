@@ -0,0 +1,289 @@
+// Stage channels
+// /stage start opens a stage with a topic and posts a control message where members press Raise
+// hand to ask to speak. Moderators go through the queue with /stage approve and /stage deny,
+// approved members are moved to the speakers right away, or as soon as they join the stage if
+// they aren't there yet. Ending the stage, with /stage end or from Discord itself, posts who
+// spoke and how long it ran.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Stage},
+    duration,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+const BUTTON_PREFIX: &str = "stage";
+// Queue entries shown on the control message, the rest are counted
+const MAX_LISTED_QUEUE: usize = 20;
+
+/// Posts the control message of a stage in its text channel
+pub async fn post(ctx: &serenity::Context, db: &Db, stage: &Stage) -> Result<(), Error> {
+    let text = serenity::ChannelId(stage.text_channel_id as u64);
+    let description = control_description(db, stage).await?;
+    let message = mentions::send_message(&ctx.http, text, Mentions::Nothing, |m| {
+        m.embed(|e| control_embed(e, stage, description))
+            .components(|c| {
+                c.create_action_row(|r| {
+                    r.create_button(|b| {
+                        b.custom_id(format!("{}-raise-{}", BUTTON_PREFIX, stage.id))
+                            .label("Raise hand")
+                            .emoji('✋')
+                            .style(serenity::ButtonStyle::Primary)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!("{}-lower-{}", BUTTON_PREFIX, stage.id))
+                            .label("Lower hand")
+                            .style(serenity::ButtonStyle::Secondary)
+                    })
+                })
+            })
+    })
+    .await?;
+    db.set_stage_message(stage.id, message.id).await?;
+
+    Ok(())
+}
+
+/// Shows the current queue on the control message
+pub async fn refresh(ctx: &serenity::Context, db: &Db, stage: &Stage) -> Result<(), Error> {
+    let message = match stage.message_id {
+        Some(message) => serenity::MessageId(message as u64),
+        None => return Ok(()),
+    };
+
+    let description = control_description(db, stage).await?;
+    serenity::ChannelId(stage.text_channel_id as u64)
+        .edit_message(&ctx.http, message, |m| {
+            m.embed(|e| control_embed(e, stage, description))
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn control_embed<'a>(
+    e: &'a mut serenity::CreateEmbed,
+    stage: &Stage,
+    description: String,
+) -> &'a mut serenity::CreateEmbed {
+    e.title(format!("Stage: {}", stage.topic))
+        .description(description)
+        .footer(|f| f.text(format!("Stage #{}", stage.id)))
+}
+
+async fn control_description(db: &Db, stage: &Stage) -> Result<String, Error> {
+    let queue = db.stage_queue(stage.id).await?;
+
+    let mut description = format!(
+        "Live in <#{}> since <t:{}:R>, hosted by <@{}>\nPress Raise hand to ask to speak.\n\n\
+        **Queue**\n",
+        stage.channel_id, stage.started_at, stage.host_id
+    );
+    for (i, request) in queue.iter().take(MAX_LISTED_QUEUE).enumerate() {
+        description.push_str(&format!(
+            "{}. <@{}> <t:{}:R>\n",
+            i + 1,
+            request.user_id,
+            request.requested_at
+        ));
+    }
+    if queue.len() > MAX_LISTED_QUEUE {
+        description.push_str(&format!("...and {} more", queue.len() - MAX_LISTED_QUEUE));
+    }
+    if queue.is_empty() {
+        description.push_str("Nobody is waiting.");
+    }
+
+    Ok(description)
+}
+
+/// Moves a member to the speakers, returns false if they aren't in the stage channel
+pub async fn invite(
+    ctx: &serenity::Context,
+    stage: &Stage,
+    user: serenity::UserId,
+) -> Result<bool, Error> {
+    let channel = serenity::ChannelId(stage.channel_id as u64);
+    let present = ctx
+        .cache
+        .guild(stage.guild_id as u64)
+        .and_then(|g| g.voice_states.get(&user).and_then(|v| v.channel_id))
+        == Some(channel);
+    if !present {
+        return Ok(false);
+    }
+
+    let channel = match ctx.cache.guild_channel(channel) {
+        Some(channel) => channel,
+        None => channel
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .ok_or("Stage channel isn't in a guild")?,
+    };
+    channel
+        .edit_voice_state(&ctx.http, user, |v| v.suppress(false))
+        .await?;
+
+    Ok(true)
+}
+
+/// Handles the raise and lower hand buttons of stage control messages
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (raise, id) = match interaction
+        .data
+        .custom_id
+        .strip_prefix(BUTTON_PREFIX)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.split_once('-'))
+    {
+        Some(("raise", id)) => (true, id),
+        Some(("lower", id)) => (false, id),
+        _ => return Ok(()),
+    };
+    let id: i64 = match id.parse() {
+        Ok(id) => id,
+        Err(_) => return Ok(()),
+    };
+
+    let user = interaction.user.id;
+    let now = serenity::Timestamp::now().unix_timestamp();
+    let mut changed = false;
+    let reply = match data.db.stage(id).await? {
+        Some(stage) if stage.ended_at.is_some() => ":x: This stage has ended.",
+        Some(_) if raise => {
+            changed = data.db.raise_hand(id, user, now).await?;
+            if changed {
+                ":white_check_mark: You raised your hand, a moderator will get to you"
+            } else {
+                ":x: Your hand is raised already, or you were approved to speak."
+            }
+        }
+        Some(_) => {
+            changed = data.db.lower_hand(id, user).await?;
+            if changed {
+                ":white_check_mark: You lowered your hand"
+            } else {
+                ":x: Your hand isn't raised."
+            }
+        }
+        None => ":x: That stage doesn't exist anymore.",
+    };
+
+    interaction
+        .create_interaction_response(ctx, |r| {
+            r.interaction_response_data(|d| d.content(reply).ephemeral(true))
+        })
+        .await?;
+
+    if changed {
+        if let Some(stage) = data.db.stage(id).await? {
+            refresh(ctx, &data.db, &stage).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves approved speakers to the speakers when they join a stage
+pub async fn handle_voice_state(
+    ctx: &serenity::Context,
+    data: &Data,
+    state: &serenity::VoiceState,
+) -> Result<(), Error> {
+    let (guild, channel) = match (state.guild_id, state.channel_id) {
+        (Some(guild), Some(channel)) if state.suppress => (guild, channel),
+        _ => return Ok(()),
+    };
+    let stage = match data.db.running_stage(guild, channel).await? {
+        Some(stage) => stage,
+        None => return Ok(()),
+    };
+
+    if data.db.is_approved_speaker(stage.id, state.user_id).await? {
+        invite(ctx, &stage, state.user_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Ends the stage when it's ended from Discord itself
+pub async fn handle_stage_delete(
+    ctx: &serenity::Context,
+    data: &Data,
+    instance: &serenity::StageInstance,
+) -> Result<(), Error> {
+    if let Some(stage) = data
+        .db
+        .running_stage(instance.guild_id, instance.channel_id)
+        .await?
+    {
+        end(ctx, &data.db, stage, false).await?;
+    }
+
+    Ok(())
+}
+
+/// Ends a stage, closing it on Discord if `close` is set, and posts the summary in its text
+/// channel. Does nothing if it had ended already
+pub async fn end(ctx: &serenity::Context, db: &Db, stage: Stage, close: bool) -> Result<(), Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+    if !db.end_stage(stage.id, now).await? {
+        return Ok(());
+    }
+
+    let channel = serenity::ChannelId(stage.channel_id as u64);
+    if close {
+        if let Err(e) = channel.delete_stage_instance(&ctx.http).await {
+            tracing::debug!(channel = channel.0, "Error closing stage: {}", e);
+        }
+    }
+
+    let text = serenity::ChannelId(stage.text_channel_id as u64);
+    if let Some(message) = stage.message_id {
+        // The buttons would only answer that the stage has ended
+        text.edit_message(&ctx.http, serenity::MessageId(message as u64), |m| {
+            m.components(|c| c)
+        })
+        .await?;
+    }
+
+    let speakers = db.stage_speakers(stage.id).await?;
+    let requests = db.stage_request_count(stage.id).await?;
+    let mut list = String::new();
+    for speaker in &speakers {
+        let line = match speaker.decided_at {
+            Some(at) => format!("<@{}> from <t:{}:t>\n", speaker.user_id, at),
+            None => format!("<@{}>\n", speaker.user_id),
+        };
+        if list.len() + line.len() > 4000 {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+    if list.is_empty() {
+        list = "Nobody was invited to speak.".to_string();
+    }
+
+    let length = std::time::Duration::from_secs((now - stage.started_at).max(0) as u64);
+    mentions::send_message(&ctx.http, text, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(format!("Stage ended: {}", stage.topic))
+                .description(list)
+                .field("Host", format!("<@{}>", stage.host_id), true)
+                .field("Length", duration::format(length), true)
+                .field("Raised hands", requests, true)
+                .footer(|f| f.text(format!("Stage #{}, {} speakers", stage.id, speakers.len())))
+                .timestamp(serenity::Timestamp::now())
+        })
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Starboard
+// Messages that get enough ⭐ reactions are reposted as an embed in the guild's starboard
+// channel. Posted messages are remembered, so further stars (or removed ones) edit the star
+// count of the existing post instead of posting the message again.
+use poise::serenity_prelude as serenity;
+use tokio::sync::Mutex;
+
+use crate::{
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+const STAR: &str = "⭐";
+
+/// Serializes starboard updates, two stars arriving together would otherwise both post
+#[derive(Default)]
+pub struct Starboard {
+    lock: Mutex<()>,
+}
+
+/// Posts or updates the starboard entry of the message a star was added to or removed from
+pub async fn handle_reaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let guild = match reaction.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    if !matches!(&reaction.emoji, serenity::ReactionType::Unicode(e) if e == STAR) {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let starboard = match config.starboard_channel {
+        // Starring the starboard itself would repost the posts
+        Some(channel) if channel != reaction.channel_id => channel,
+        _ => return Ok(()),
+    };
+
+    let _guard = data.starboard.lock.lock().await;
+
+    let message = reaction.message(&ctx.http).await?;
+    let stars = message
+        .reactions
+        .iter()
+        .find(|r| matches!(&r.reaction_type, serenity::ReactionType::Unicode(e) if e == STAR))
+        .map(|r| r.count)
+        .unwrap_or(0);
+    let content = format!("{} **{}** <#{}>", STAR, stars, message.channel_id.0);
+
+    if let Some(post) = data.db.starboard_post(message.id).await? {
+        let result = starboard
+            .edit_message(&ctx.http, post, |m| m.content(&content))
+            .await;
+
+        match result {
+            Ok(_) => return Ok(()),
+            // The post was deleted by a moderator, don't bring it back
+            Err(serenity::Error::Http(e))
+                if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                return Ok(())
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if stars < config.starboard_threshold() as u64 {
+        return Ok(());
+    }
+
+    let post = mentions::send_message(&ctx.http, starboard, Mentions::Nothing, |m| {
+        m.content(&content).embed(|e| {
+            e.author(|a| a.name(message.author.tag()).icon_url(message.author.face()))
+                .field(
+                    "Source",
+                    format!("[Jump to message]({})", message.link()),
+                    false,
+                )
+                .footer(|f| f.text(message.id.0))
+                .timestamp(message.timestamp);
+
+            if let Some(colour) = data.themes.colour(guild) {
+                e.colour(colour);
+            }
+
+            // Discord rejects empty descriptions, e.g. for messages that are only an image
+            if !message.content.is_empty() {
+                e.description(&message.content);
+            }
+
+            if let Some(image) = message.attachments.iter().find(|a| {
+                a.content_type
+                    .as_deref()
+                    .is_some_and(|t| t.starts_with("image/"))
+            }) {
+                e.image(&image.url);
+            }
+
+            e
+        })
+    })
+    .await?;
+
+    data.db.set_starboard_post(guild, message.id, post.id).await
+}
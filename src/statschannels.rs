@@ -0,0 +1,164 @@
+// Stats channels
+// /statschannels setup creates voice channels nobody can join whose names show how many
+// members the server has, how many of them are online and how many boosts it has. Discord
+// only allows renaming a channel twice every ten minutes, so the names are refreshed every
+// RENAME_INTERVAL and only renamed when the number changed. The counts come from Discord's
+// approximate counts, we don't ask for presences just to count who's online.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{db::Db, watchdog, Error};
+
+// Stays under the rename limit of two every ten minutes, even with a rename from setup
+const RENAME_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// What a stats channel counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stat {
+    Members,
+    Online,
+    Boosts,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 3] = [Stat::Members, Stat::Online, Stat::Boosts];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stat::Members => "members",
+            Stat::Online => "online",
+            Stat::Boosts => "boosts",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// Channel name a stat gets when nothing else is given
+    pub fn default_template(self) -> &'static str {
+        match self {
+            Stat::Members => "Members: {count}",
+            Stat::Online => "Online: {count}",
+            Stat::Boosts => "Boosts: {count}",
+        }
+    }
+
+    fn count(self, guild: &serenity::PartialGuild) -> u64 {
+        match self {
+            Stat::Members => guild.approximate_member_count.unwrap_or_default(),
+            Stat::Online => guild.approximate_presence_count.unwrap_or_default(),
+            Stat::Boosts => guild.premium_subscription_count,
+        }
+    }
+}
+
+/// Formats a count with thousands separators, like 12,345
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// A stats channel's name for a count
+pub fn render(template: &str, count: u64) -> String {
+    template.replace("{count}", &format_count(count))
+}
+
+/// Starts the task that keeps stats channel names up to date
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("stats channels", async move {
+        loop {
+            if let Err(e) = refresh(&ctx, &db, None).await {
+                tracing::warn!("Error refreshing stats channels: {}", e);
+            }
+
+            tokio::time::sleep(RENAME_INTERVAL).await;
+        }
+    });
+}
+
+/// Renames the stats channels of a guild, or of every guild, whose number changed
+pub async fn refresh(
+    ctx: &serenity::Context,
+    db: &Db,
+    guild: Option<serenity::GuildId>,
+) -> Result<(), Error> {
+    let mut counted: Option<serenity::PartialGuild> = None;
+
+    for channel in db.stats_channels(guild).await? {
+        let stat = match Stat::from_name(&channel.stat) {
+            Some(stat) => stat,
+            None => continue,
+        };
+        // Ordered by guild, so each guild is fetched once
+        if counted.as_ref().map(|g| g.id.0) != Some(channel.guild_id as u64) {
+            counted = match ctx
+                .http
+                .get_guild_with_counts(channel.guild_id as u64)
+                .await
+            {
+                Ok(guild) => Some(guild),
+                Err(e) => {
+                    tracing::warn!(guild = channel.guild_id, "Error counting members: {}", e);
+                    None
+                }
+            };
+        }
+        let guild = match &counted {
+            Some(guild) => guild,
+            None => continue,
+        };
+
+        let id = serenity::ChannelId(channel.channel_id as u64);
+        let name = render(&channel.template, stat.count(guild));
+        if ctx.cache.guild_channel_field(id, |c| c.name == name) == Some(true) {
+            continue;
+        }
+
+        match id.edit(&ctx.http, |c| c.name(&name)).await {
+            Ok(_) => {}
+            // Deleted by hand, which is how they're removed too
+            Err(serenity::Error::Http(e))
+                if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+            {
+                db.delete_stats_channel(id).await?;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    guild = channel.guild_id,
+                    channel = channel.channel_id,
+                    "Error renaming stats channel: {}",
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_rendered_with_separators() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1000), "1,000");
+        assert_eq!(format_count(1234567), "1,234,567");
+        assert_eq!(render("Members: {count}", 12345), "Members: 12,345");
+
+        for stat in Stat::ALL {
+            assert_eq!(Stat::from_name(stat.name()), Some(stat));
+        }
+    }
+}
@@ -0,0 +1,837 @@
+// Storage backends
+// The scheme of DATABASE_URL picks where the bot stores its data: `sqlite:` for a single file,
+// which is all most bots need, or `postgres:` for a PostgreSQL server. Queries are written once
+// in SQLite's dialect, and each backend rewrites them into its own. Where the dialects differ too
+// much, like full-text search, `Db` has a query per backend. Each backend has its own migrations,
+// `migrations/` for SQLite and `migrations/postgres/` for PostgreSQL.
+//
+// `memory:` keeps everything in an in-memory SQLite database instead, which is gone once the bot
+// stops. The bot uses it when started with `--ephemeral`.
+//
+// Transactions that read something and then write it back lock the guild they're for first, see
+// `PoolExt::guild_transaction`. SQLite has one writer at a time anyway, on PostgreSQL this takes
+// an advisory lock on the guild, so transactions of different guilds still run side by side.
+//
+// PostgreSQL databases must use the UTF8 encoding, which the case-insensitive ICU collation of
+// the migrations needs. `connect` checks it and refuses others, create the database with
+// `CREATE DATABASE bot ENCODING 'UTF8' TEMPLATE template0` if the server defaults to another.
+//
+// Moving a bot between backends is `discordbot-but-rust copy-storage <from> <to>`, which copies
+// every table from one database URL into a new database at the other.
+use std::{
+    collections::HashMap,
+    future::Future,
+    str::FromStr,
+    sync::{LazyLock, Mutex},
+};
+
+use sqlx::{
+    migrate::Migrator,
+    postgres::{PgPool, PgPoolOptions},
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
+    Postgres, Sqlite,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::Error;
+
+/// The URL of a new in-memory database
+pub const MEMORY_URL: &str = "memory:";
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// A database the bot can store its data in
+pub trait Storage: sqlx::Database {
+    /// Connects to the database at `url`, creating it if the backend can, and applies pending
+    /// migrations
+    fn connect(url: &str) -> impl Future<Output = Result<sqlx::Pool<Self>, Error>> + Send;
+
+    /// A query written in SQLite's dialect in this backend's
+    fn sql(query: &'static str) -> &'static str;
+
+    /// Starts a transaction
+    fn begin(
+        pool: &sqlx::Pool<Self>,
+    ) -> impl Future<Output = Result<sqlx::Transaction<'static, Self>, Error>> + Send;
+
+    /// Waits until no other transaction holds the lock of `guild`, and holds it until `tx` ends
+    fn lock(
+        tx: &mut sqlx::Transaction<'static, Self>,
+        guild: serenity::GuildId,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// How many rows a statement changed
+    fn rows_affected(result: &Self::QueryResult) -> u64;
+}
+
+impl Storage for Sqlite {
+    async fn connect(url: &str) -> Result<SqlitePool, Error> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+
+        SQLITE_MIGRATIONS.run(&pool).await?;
+        Ok(pool)
+    }
+
+    fn sql(query: &'static str) -> &'static str {
+        query
+    }
+
+    async fn begin(pool: &SqlitePool) -> Result<sqlx::Transaction<'static, Sqlite>, Error> {
+        Ok(pool.begin().await?)
+    }
+
+    async fn lock(
+        _tx: &mut sqlx::Transaction<'static, Sqlite>,
+        _guild: serenity::GuildId,
+    ) -> Result<(), Error> {
+        // Writing takes the database's lock, which is already held until the transaction ends
+        Ok(())
+    }
+
+    fn rows_affected(result: &sqlx::sqlite::SqliteQueryResult) -> u64 {
+        result.rows_affected()
+    }
+}
+
+impl Storage for Postgres {
+    async fn connect(url: &str) -> Result<PgPool, Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect(url)
+            .await?;
+
+        let encoding: String = sqlx::query_scalar("SHOW server_encoding")
+            .fetch_one(&pool)
+            .await?;
+        if encoding != "UTF8" {
+            return Err(format!(
+                "The database uses the {} encoding, the bot needs UTF8. Create it with \
+                CREATE DATABASE <name> ENCODING 'UTF8' TEMPLATE template0",
+                encoding
+            )
+            .into());
+        }
+
+        POSTGRES_MIGRATIONS.run(&pool).await?;
+        Ok(pool)
+    }
+
+    fn sql(query: &'static str) -> &'static str {
+        // Queries are literals, so rewriting each once and keeping it doesn't grow forever
+        static QUERIES: LazyLock<Mutex<HashMap<&'static str, &'static str>>> =
+            LazyLock::new(Default::default);
+
+        let mut queries = QUERIES.lock().unwrap();
+        queries
+            .entry(query)
+            .or_insert_with(|| Box::leak(to_postgres(query).into_boxed_str()))
+    }
+
+    async fn begin(pool: &PgPool) -> Result<sqlx::Transaction<'static, Postgres>, Error> {
+        Ok(pool.begin().await?)
+    }
+
+    async fn lock(
+        tx: &mut sqlx::Transaction<'static, Postgres>,
+        guild: serenity::GuildId,
+    ) -> Result<(), Error> {
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(guild.0 as i64)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    fn rows_affected(result: &sqlx::postgres::PgQueryResult) -> u64 {
+        result.rows_affected()
+    }
+}
+
+/// The pool of whichever backend DATABASE_URL picked
+#[derive(Clone)]
+pub enum Pool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl Pool {
+    /// Connects to the backend the URL's scheme names
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+            Ok(Pool::Postgres(Postgres::connect(url).await?))
+        } else if url == MEMORY_URL {
+            Ok(Pool::Sqlite(memory().await?))
+        } else {
+            Ok(Pool::Sqlite(Sqlite::connect(url).await?))
+        }
+    }
+}
+
+// A SQLite database that only lives in memory, under a name of its own so every one is new. It
+// goes away with its last connection, so the pool never closes them
+async fn memory() -> Result<SqlitePool, Error> {
+    let url = format!(
+        "sqlite:file:memory-{}?mode=memory&cache=shared",
+        rand::random::<u64>()
+    );
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::from_str(&url)?)
+        .await?;
+
+    SQLITE_MIGRATIONS.run(&pool).await?;
+    Ok(pool)
+}
+
+/// Runs the same code with the pool of either backend, with `$pool` bound to it. The code is
+/// compiled once per backend, so queries in it get the right types for each
+macro_rules! with_pool {
+    ($pool:expr, |$name:ident| $body:expr) => {
+        match $pool {
+            $crate::storage::Pool::Sqlite($name) => $body,
+            $crate::storage::Pool::Postgres($name) => $body,
+        }
+    };
+}
+pub(crate) use with_pool;
+
+/// Lets queries get at their backend through the pool they run on
+pub trait PoolExt {
+    type Storage: Storage;
+
+    /// See `Storage::sql`
+    fn sql(&self, query: &'static str) -> &'static str;
+
+    /// See `Storage::begin`
+    fn transaction(
+        &self,
+    ) -> impl Future<Output = Result<sqlx::Transaction<'static, Self::Storage>, Error>> + Send;
+
+    /// Starts a transaction holding the lock of `guild`, for ones that read something and then
+    /// write. See `Storage::lock`
+    fn guild_transaction(
+        &self,
+        guild: serenity::GuildId,
+    ) -> impl Future<Output = Result<sqlx::Transaction<'static, Self::Storage>, Error>> + Send;
+}
+
+impl<S: Storage> PoolExt for sqlx::Pool<S> {
+    type Storage = S;
+
+    fn sql(&self, query: &'static str) -> &'static str {
+        S::sql(query)
+    }
+
+    fn transaction(
+        &self,
+    ) -> impl Future<Output = Result<sqlx::Transaction<'static, S>, Error>> + Send {
+        S::begin(self)
+    }
+
+    async fn guild_transaction(
+        &self,
+        guild: serenity::GuildId,
+    ) -> Result<sqlx::Transaction<'static, S>, Error> {
+        let mut tx = S::begin(self).await?;
+        S::lock(&mut tx, guild).await?;
+        Ok(tx)
+    }
+}
+
+/// Rewrites a query from SQLite's dialect into PostgreSQL's
+pub fn to_postgres(query: &str) -> String {
+    let mut sql = query
+        .replace(
+            "CAST(strftime('%s', 'now') AS INTEGER)",
+            "CAST(EXTRACT(EPOCH FROM now()) AS BIGINT)",
+        )
+        .replace(" AS INTEGER)", " AS BIGINT)")
+        .replace(
+            "SELECT value FROM json_each(?)",
+            "SELECT CAST(value AS BIGINT) FROM json_array_elements_text(CAST(? AS JSON))",
+        );
+
+    sql = rewrite_calls(&sql, &|name, args| match (name, args) {
+        ("GROUP_CONCAT", [arg]) => Some(format!("string_agg(CAST({} AS TEXT), ',')", arg)),
+        // Scalar MIN and MAX, the aggregates take one argument
+        ("MIN", [_, _]) => Some(format!("LEAST({})", args.join(", "))),
+        ("MAX", [_, _]) => Some(format!("GREATEST({})", args.join(", "))),
+        _ => None,
+    });
+
+    if sql.contains("INSERT OR IGNORE INTO") {
+        sql = sql.replace("INSERT OR IGNORE INTO", "INSERT INTO");
+        match sql.find(" RETURNING") {
+            Some(at) => sql.insert_str(at, " ON CONFLICT DO NOTHING"),
+            None => {
+                sql.truncate(sql.trim_end().len());
+                sql.push_str(" ON CONFLICT DO NOTHING");
+            }
+        }
+    }
+
+    number_placeholders(&sql)
+}
+
+// Rewrites calls of the functions `rewrite` returns something for, innermost first
+fn rewrite_calls(sql: &str, rewrite: &dyn Fn(&str, &[String]) -> Option<String>) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+
+    while let Some(open) = rest.find('(') {
+        let name_start = rest[..open]
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |i| i + 1);
+        let close = match closing_paren(&rest[open..]) {
+            Some(close) => open + close,
+            None => break,
+        };
+
+        let args: Vec<String> = split_args(&rest[open + 1..close])
+            .into_iter()
+            .map(|arg| rewrite_calls(arg.trim(), rewrite))
+            .collect();
+        out.push_str(&rest[..name_start]);
+        match rewrite(&rest[name_start..open], &args) {
+            Some(call) => out.push_str(&call),
+            None => {
+                out.push_str(&rest[name_start..=open]);
+                out.push_str(&rewrite_calls(&rest[open + 1..close], rewrite));
+                out.push(')');
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+// Where the parenthesis `sql` starts with is closed, skipping ones in string literals
+fn closing_paren(sql: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in sql.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Splits the arguments of a call at the commas outside of nested calls and string literals
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts
+}
+
+// Numbers the `?` placeholders as `$1`, `$2` and so on
+fn number_placeholders(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len() + 8);
+    let (mut n, mut quoted) = (0, false);
+    for c in sql.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                out.push(c);
+            }
+            '?' if !quoted => {
+                n += 1;
+                out.push_str(&format!("${}", n));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Turns an FTS5 query as `kb::fts_query` and `messagearchive::fts_query` build them, quoted
+/// words or prefixes joined by spaces or `OR`, into a PostgreSQL tsquery
+pub fn to_tsquery(fts: &str) -> String {
+    let mut out = String::new();
+    let mut chars = fts.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let word: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                if !out.is_empty() && !out.ends_with("| ") {
+                    out.push_str(" & ");
+                }
+                out.push('\'');
+                out.push_str(&word.replace('\'', "''"));
+                out.push('\'');
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    out.push_str(":*");
+                }
+            }
+            'O' if chars.peek() == Some(&'R') => {
+                chars.next();
+                out.push_str(" | ");
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// A value of any column, for copying tables between backends
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Copies every table of the database at `from` into a new database at `to`, returns how many
+/// rows were copied. Both are migrated first, and `to` must have no data yet
+pub async fn copy(from: &str, to: &str) -> Result<u64, Error> {
+    let source = Pool::connect(from).await?;
+    let target = Pool::connect(to).await?;
+
+    let tables = target.tables().await?;
+    for (table, _) in &tables {
+        // The meta table gets its row from the migrations, and is overwritten below
+        if table != "meta" && target.count(table).await? > 0 {
+            return Err(format!("The target database already has data in {}", table).into());
+        }
+    }
+
+    let mut copied = 0;
+    let source_tables: HashMap<_, _> = source.tables().await?.into_iter().collect();
+    for (table, columns) in &tables {
+        let columns: Vec<&str> = match source_tables.get(table) {
+            Some(source_columns) => columns
+                .iter()
+                .filter(|c| source_columns.contains(c))
+                .map(String::as_str)
+                .collect(),
+            None => continue,
+        };
+        let rows = source.rows(table, &columns).await?;
+        target.insert_rows(table, &columns, &rows).await?;
+        copied += rows.len() as u64;
+        tracing::info!(table, rows = rows.len(), "Copied table");
+    }
+
+    target.finish_copy(&tables).await?;
+    Ok(copied)
+}
+
+impl Pool {
+    // Tables with data and their columns, FTS5's own tables and generated columns left out
+    async fn tables(&self) -> Result<Vec<(String, Vec<String>)>, Error> {
+        match self {
+            Pool::Sqlite(pool) => {
+                // FTS5 names its tables in quotes, and search tables' rowid is the row's key
+                let tables: Vec<(String, bool)> = sqlx::query_as(
+                    "SELECT name, sql LIKE 'CREATE VIRTUAL TABLE%' FROM sqlite_master
+                    WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name NOT LIKE '_sqlx%'
+                    AND sql NOT LIKE 'CREATE TABLE ''%'
+                    ORDER BY name",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut out = Vec::new();
+                for (table, fts) in tables {
+                    let mut columns: Vec<String> =
+                        sqlx::query_scalar("SELECT name FROM pragma_table_info(?) ORDER BY cid")
+                            .bind(&table)
+                            .fetch_all(pool)
+                            .await?;
+                    if fts {
+                        columns.insert(0, "rowid".to_string());
+                    }
+                    out.push((table, columns));
+                }
+                Ok(out)
+            }
+            Pool::Postgres(pool) => {
+                let columns: Vec<(String, String)> = sqlx::query_as(
+                    "SELECT c.table_name, c.column_name FROM information_schema.columns c
+                    JOIN information_schema.tables t
+                        ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+                    WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+                    AND c.table_name NOT LIKE '\\_sqlx%' AND c.is_generated = 'NEVER'
+                    ORDER BY c.table_name, c.ordinal_position",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut out: Vec<(String, Vec<String>)> = Vec::new();
+                for (table, column) in columns {
+                    match out.last_mut() {
+                        Some((last, columns)) if *last == table => columns.push(column),
+                        _ => out.push((table, vec![column])),
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    async fn count(&self, table: &str) -> Result<i64, Error> {
+        let query = format!("SELECT COUNT(*) FROM {}", table);
+        Ok(with_pool!(self, |pool| {
+            sqlx::query_scalar(&query).fetch_one(pool).await?
+        }))
+    }
+
+    async fn rows(&self, table: &str, columns: &[&str]) -> Result<Vec<Vec<Value>>, Error> {
+        use sqlx::{Row, TypeInfo, ValueRef};
+
+        let query = format!("SELECT {} FROM {}", columns.join(", "), table);
+        with_pool!(self, |pool| {
+            let rows = sqlx::query(&query).fetch_all(pool).await?;
+            let mut out = Vec::with_capacity(rows.len());
+            for row in &rows {
+                let mut values = Vec::with_capacity(columns.len());
+                for i in 0..columns.len() {
+                    let raw = row.try_get_raw(i)?;
+                    let kind = raw.type_info().name().to_uppercase();
+                    let value = if raw.is_null() {
+                        Value::Null
+                    } else if kind == "BOOLEAN" || kind == "BOOL" {
+                        Value::Integer(row.try_get::<bool, _>(i)? as i64)
+                    } else if kind.contains("INT") {
+                        Value::Integer(row.try_get(i)?)
+                    } else if kind == "REAL" || kind.starts_with("FLOAT") || kind == "NUMERIC" {
+                        Value::Real(row.try_get(i)?)
+                    } else if kind == "BLOB" || kind == "BYTEA" {
+                        Value::Blob(row.try_get(i)?)
+                    } else {
+                        Value::Text(row.try_get(i)?)
+                    };
+                    values.push(value);
+                }
+                out.push(values);
+            }
+            Ok(out)
+        })
+    }
+
+    async fn insert_rows(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: &[Vec<Value>],
+    ) -> Result<(), Error> {
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders
+        );
+        match self {
+            Pool::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                if table == "meta" {
+                    sqlx::query("DELETE FROM meta").execute(&mut *tx).await?;
+                }
+                for row in rows {
+                    let mut insert = sqlx::query(&query);
+                    for value in row {
+                        insert = match value {
+                            Value::Null => insert.bind(None::<i64>),
+                            Value::Integer(v) => insert.bind(*v),
+                            Value::Real(v) => insert.bind(*v),
+                            Value::Text(v) => insert.bind(v.clone()),
+                            Value::Blob(v) => insert.bind(v.clone()),
+                        };
+                    }
+                    insert.execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+            Pool::Postgres(pool) => {
+                // Integer columns that SQLite uses as booleans are booleans here
+                let booleans: Vec<String> = sqlx::query_scalar(
+                    "SELECT column_name FROM information_schema.columns
+                    WHERE table_schema = current_schema() AND table_name = $1
+                    AND data_type = 'boolean'",
+                )
+                .bind(table)
+                .fetch_all(pool)
+                .await?;
+
+                let query = to_postgres(&query);
+                let mut tx = pool.begin().await?;
+                if table == "meta" {
+                    sqlx::query("DELETE FROM meta").execute(&mut *tx).await?;
+                }
+                for row in rows {
+                    let mut insert = sqlx::query(&query);
+                    for (column, value) in columns.iter().zip(row) {
+                        let boolean = booleans.iter().any(|b| b == column);
+                        insert = match value {
+                            Value::Null if boolean => insert.bind(None::<bool>),
+                            Value::Null => insert.bind(None::<i64>),
+                            Value::Integer(v) if boolean => insert.bind(*v != 0),
+                            Value::Integer(v) => insert.bind(*v),
+                            Value::Real(v) => insert.bind(*v),
+                            Value::Text(v) => insert.bind(v.clone()),
+                            Value::Blob(v) => insert.bind(v.clone()),
+                        };
+                    }
+                    insert.execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Catches generated IDs up on the copied ones
+    async fn finish_copy(&self, tables: &[(String, Vec<String>)]) -> Result<(), Error> {
+        let pool = match self {
+            // Inserting an ID already updates sqlite_sequence
+            Pool::Sqlite(_) => return Ok(()),
+            Pool::Postgres(pool) => pool,
+        };
+
+        for (table, columns) in tables {
+            if !columns.iter().any(|c| c == "id") {
+                continue;
+            }
+            let query = format!(
+                "SELECT setval(pg_get_serial_sequence('{0}', 'id'), MAX(id)) FROM {0}
+                HAVING MAX(id) IS NOT NULL AND pg_get_serial_sequence('{0}', 'id') IS NOT NULL",
+                table
+            );
+            sqlx::query(&query).execute(pool).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_rewritten_for_postgres() {
+        assert_eq!(
+            to_postgres("SELECT a FROM t WHERE b = ? AND c = '?' AND d = ?"),
+            "SELECT a FROM t WHERE b = $1 AND c = '?' AND d = $2"
+        );
+        assert_eq!(
+            to_postgres(
+                "INSERT OR IGNORE INTO t (a, created_at)
+                VALUES (?, CAST(strftime('%s', 'now') AS INTEGER))"
+            ),
+            "INSERT INTO t (a, created_at)
+                VALUES ($1, CAST(EXTRACT(EPOCH FROM now()) AS BIGINT)) ON CONFLICT DO NOTHING"
+        );
+        assert_eq!(
+            to_postgres("INSERT OR IGNORE INTO t (a) VALUES (?) RETURNING id"),
+            "INSERT INTO t (a) VALUES ($1) ON CONFLICT DO NOTHING RETURNING id"
+        );
+        assert_eq!(
+            to_postgres("UPDATE jobs SET run_at = ? + MIN(60 << MIN(attempts, 6), 3600)"),
+            "UPDATE jobs SET run_at = $1 + LEAST(60 << LEAST(attempts, 6), 3600)"
+        );
+        // Aggregates are left alone
+        assert_eq!(
+            to_postgres("SELECT MAX(voted_at), MIN(x) FROM votes GROUP BY user_id"),
+            "SELECT MAX(voted_at), MIN(x) FROM votes GROUP BY user_id"
+        );
+        assert_eq!(
+            to_postgres("SELECT r.id, GROUP_CONCAT(c.channel_id) FROM r"),
+            "SELECT r.id, string_agg(CAST(c.channel_id AS TEXT), ',') FROM r"
+        );
+        assert_eq!(
+            to_postgres("DELETE FROM m WHERE rowid IN (SELECT value FROM json_each(?))"),
+            "DELETE FROM m WHERE rowid IN \
+            (SELECT CAST(value AS BIGINT) FROM json_array_elements_text(CAST($1 AS JSON)))"
+        );
+    }
+
+    #[test]
+    fn search_queries_become_tsqueries() {
+        assert_eq!(
+            to_tsquery("\"release\"* \"date\"*"),
+            "'release':* & 'date':*"
+        );
+        assert_eq!(to_tsquery("\"how\"* OR \"reset\"*"), "'how':* | 'reset':*");
+        assert_eq!(to_tsquery("\"0a1b\" \"it's\""), "'0a1b' & 'it''s'");
+    }
+
+    #[tokio::test]
+    async fn memory_databases_are_new_and_kept_while_connected() {
+        use poise::serenity_prelude::UserId;
+
+        let (a, b) = (
+            crate::db::Db::connect(Some(MEMORY_URL)).await.unwrap(),
+            crate::db::Db::connect(Some(MEMORY_URL)).await.unwrap(),
+        );
+        a.add_vote(UserId(1), 100).await.unwrap();
+
+        // Every connection of the pool sees the same database
+        let mut reads = tokio::task::JoinSet::new();
+        for _ in 0..8 {
+            let a = a.clone();
+            reads.spawn(async move { a.last_vote(UserId(1)).await.unwrap() });
+        }
+        while let Some(vote) = reads.join_next().await {
+            assert_eq!(vote.unwrap(), Some(100));
+        }
+        assert_eq!(b.last_vote(UserId(1)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn tables_are_copied_between_databases() {
+        let url = |name: &str| {
+            format!(
+                "sqlite:file:copy-{}-{}?mode=memory&cache=shared",
+                name,
+                rand::random::<u32>()
+            )
+        };
+        let (from, to) = (url("from"), url("to"));
+        let source = crate::db::Db::connect(Some(&from)).await.unwrap();
+        source
+            .add_vote(poise::serenity_prelude::UserId(1), 100)
+            .await
+            .unwrap();
+        let created_at = source.created_at().await.unwrap();
+
+        assert_eq!(copy(&from, &to).await.unwrap(), 2);
+        let target = crate::db::Db::connect(Some(&to)).await.unwrap();
+        assert_eq!(
+            target
+                .last_vote(poise::serenity_prelude::UserId(1))
+                .await
+                .unwrap(),
+            Some(100)
+        );
+        assert_eq!(target.created_at().await.unwrap(), created_at);
+        // Copying again would mix two bots' data
+        assert!(copy(&from, &to).await.is_err());
+    }
+
+    // Runs against a real server, e.g.
+    // DATABASE_URL=postgres://bot@localhost/bot_test cargo test -- --ignored postgres
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL server at DATABASE_URL"]
+    async fn postgres_runs_the_rewritten_queries() {
+        use poise::serenity_prelude::UserId;
+
+        use crate::{config::GuildConfig, db::Db, economy::Transfer};
+
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL is a postgres:// URL");
+        assert!(
+            url.starts_with("postgres"),
+            "DATABASE_URL is a postgres:// URL"
+        );
+        let db = Db::connect(Some(&url)).await.unwrap();
+        // A guild of its own, so runs against the same database don't see each other
+        let guild = serenity::GuildId(rand::random::<u32>() as u64 + 1);
+
+        let mut config = db.load_guild_config(guild).await.unwrap();
+        config.prefix = Some("?".to_string());
+        db.save_guild_config(guild, &config).await.unwrap();
+        assert_eq!(
+            db.load_guild_config(guild).await.unwrap().prefix.as_deref(),
+            Some("?")
+        );
+
+        // Concurrent transactions of a guild neither fail nor lose writes
+        let mut cases = tokio::task::JoinSet::new();
+        for i in 0..8 {
+            let db = db.clone();
+            cases.spawn(async move {
+                db.save_guild_config(guild, &GuildConfig::default())
+                    .await
+                    .unwrap();
+                db.add_case(guild, "ban", UserId(i), UserId(1), "test")
+                    .await
+                    .unwrap()
+            });
+        }
+        let mut numbers = Vec::new();
+        while let Some(case) = cases.join_next().await {
+            numbers.push(case.unwrap());
+        }
+        numbers.sort();
+        assert_eq!(numbers, (1..=8).collect::<Vec<_>>());
+
+        let (a, b) = (UserId(1), UserId(2));
+        // Keys are unique across guilds
+        let transfer = |from, to, amount, key: &'static str| {
+            let (db, key) = (db.clone(), format!("{}:{}", guild.0, key));
+            async move {
+                db.transfer(guild, from, to, amount, "test", &key)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(transfer(None, Some(a), 100, "mint").await, Transfer::Done);
+        assert_eq!(
+            transfer(None, Some(a), 100, "mint").await,
+            Transfer::Duplicate
+        );
+        let (first, second) = tokio::join!(
+            transfer(Some(a), Some(b), 60, "first"),
+            transfer(Some(a), Some(b), 60, "second")
+        );
+        assert_eq!(
+            [first, second]
+                .iter()
+                .filter(|&&t| t == Transfer::Done)
+                .count(),
+            1
+        );
+        assert_eq!(db.balance(guild, a).await.unwrap(), 40);
+
+        // Titles ignore case through the ICU collation
+        assert!(db
+            .add_kb_article(guild, "Rules", "Be nice", a)
+            .await
+            .unwrap());
+        assert!(!db
+            .add_kb_article(guild, "rules", "Be nice", a)
+            .await
+            .unwrap());
+        assert!(db.kb_article(guild, "RULES").await.unwrap().is_some());
+        let found = db
+            .search_kb(guild, &crate::kb::fts_query("nice").unwrap(), 5)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(db.delete_kb_article(guild, "rules").await.unwrap());
+    }
+}
@@ -0,0 +1,230 @@
+// Streaks
+// /daily and the daily word count how many days in a row each member did them. Days follow the
+// member's timezone, set with /timezone, so a streak doesn't break because the day changed in
+// the middle of their evening. Timezones are fixed UTC offsets that members change themselves
+// for daylight saving time, at most once every TIMEZONE_COOLDOWN so moving the day boundary
+// around can't claim many extra days. A missed day is covered by a streak freeze bought in the
+// shop, one per missed day, used up automatically when the streak continues.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Streak},
+    Error,
+};
+
+/// How long members wait between timezone changes
+pub const TIMEZONE_COOLDOWN: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+// Seconds in a day
+const DAY_SECS: i64 = 24 * 60 * 60;
+// Offsets go from UTC-12:00 to UTC+14:00
+const MIN_OFFSET: i32 = -12 * 60;
+const MAX_OFFSET: i32 = 14 * 60;
+// Each day of a streak adds this much to /daily in percent, up to MAX_BONUS_DAYS days
+const BONUS_PER_DAY: i64 = 10;
+const MAX_BONUS_DAYS: i64 = 10;
+
+/// Something members do once a day
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Activity {
+    #[name = "daily"]
+    Daily,
+    #[name = "word"]
+    Word,
+}
+
+/// A streak after counting today
+#[derive(Debug, PartialEq, Eq)]
+pub struct Advanced {
+    pub streak: Streak,
+    /// Freezes used up for the days that were missed
+    pub freezes_used: i64,
+}
+
+/// Counts `today` towards a streak, None if it's counted already
+pub fn advance(streak: Option<&Streak>, today: i64, freezes: i64) -> Option<Advanced> {
+    let (current, freezes_used) = match streak {
+        None => (1, 0),
+        // Setting an earlier timezone can make today one of the days counted already
+        Some(streak) if streak.last_day >= today => return None,
+        Some(streak) => match today - streak.last_day - 1 {
+            missed if missed <= freezes => (streak.current + 1, missed),
+            _ => (1, 0),
+        },
+    };
+
+    Some(Advanced {
+        streak: Streak {
+            current,
+            best: streak.map_or(current, |s| s.best.max(current)),
+            last_day: today,
+        },
+        freezes_used,
+    })
+}
+
+/// Whether a streak last counted on `last_day` still goes on for someone whose day is `today`
+pub fn is_alive(last_day: i64, today: i64) -> bool {
+    last_day >= today - 1
+}
+
+/// Extra coins /daily gives on day `current` of a streak
+pub fn daily_bonus(amount: i64, current: i64) -> i64 {
+    amount * (current - 1).clamp(0, MAX_BONUS_DAYS) * BONUS_PER_DAY / 100
+}
+
+/// Day number at the unix timestamp `now` for someone `offset` minutes ahead of UTC
+pub fn local_day(now: i64, offset: i32) -> i64 {
+    (now + offset as i64 * 60).div_euclid(DAY_SECS)
+}
+
+/// Unix timestamp at which `day` ends for someone `offset` minutes ahead of UTC
+pub fn day_end(day: i64, offset: i32) -> i64 {
+    (day + 1) * DAY_SECS - offset as i64 * 60
+}
+
+/// Today of a member, and their offset from UTC in minutes
+pub async fn today(db: &Db, user: serenity::UserId) -> Result<(i64, i32), Error> {
+    let offset = db.timezone(user).await?;
+    let now = serenity::Timestamp::now().unix_timestamp();
+
+    Ok((local_day(now, offset), offset))
+}
+
+/// Parses a UTC offset like `+2`, `-05:30` or `UTC+9` into minutes
+pub fn parse_offset(input: &str) -> Option<i32> {
+    let input = input.trim().to_uppercase();
+    let input = input
+        .strip_prefix("UTC")
+        .or_else(|| input.strip_prefix("GMT"))
+        .unwrap_or(&input)
+        .trim();
+    if input.is_empty() || input == "0" {
+        return Some(0);
+    }
+
+    let (sign, rest) = match input.as_bytes()[0] {
+        b'+' => (1, &input[1..]),
+        b'-' => (-1, &input[1..]),
+        _ => (1, input),
+    };
+    if !rest.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return None;
+    }
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        // Written without a colon, like 0530
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours < 0 || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    Some(sign * (hours * 60 + minutes)).filter(|o| (MIN_OFFSET..=MAX_OFFSET).contains(o))
+}
+
+/// An offset from UTC in minutes for messages, like `UTC+05:30`
+pub fn format_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!(
+        "UTC{}{:02}:{:02}",
+        sign,
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::NewShopItem;
+
+    fn streak(current: i64, best: i64, last_day: i64) -> Streak {
+        Streak {
+            current,
+            best,
+            last_day,
+        }
+    }
+
+    #[test]
+    fn streaks_count_each_day_once() {
+        let first = advance(None, 10, 0).unwrap();
+        assert_eq!(first.streak, streak(1, 1, 10));
+        assert_eq!(advance(Some(&first.streak), 10, 0), None);
+
+        let next = advance(Some(&streak(4, 6, 10)), 11, 0).unwrap();
+        assert_eq!(next.streak, streak(5, 6, 11));
+        let broken = advance(Some(&streak(4, 6, 10)), 13, 1).unwrap();
+        assert_eq!(broken.streak, streak(1, 6, 13));
+        assert_eq!(broken.freezes_used, 0);
+    }
+
+    #[test]
+    fn freezes_cover_missed_days() {
+        let frozen = advance(Some(&streak(7, 7, 10)), 13, 3).unwrap();
+        assert_eq!(frozen.streak, streak(8, 8, 13));
+        assert_eq!(frozen.freezes_used, 2);
+    }
+
+    #[test]
+    fn days_follow_the_timezone() {
+        // 2024-01-01 23:30 UTC
+        let now = 1_704_151_800;
+        let utc = local_day(now, 0);
+        assert_eq!(local_day(now, 60), utc + 1);
+        assert_eq!(local_day(now, -5 * 60), utc);
+        assert_eq!(day_end(utc, 0), now + 30 * 60);
+        assert_eq!(day_end(utc + 1, 60), now + 30 * 60 + DAY_SECS - 60 * 60);
+
+        assert_eq!(daily_bonus(100, 1), 0);
+        assert_eq!(daily_bonus(100, 4), 30);
+        assert_eq!(daily_bonus(100, 50), 100);
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse_offset("UTC"), Some(0));
+        assert_eq!(parse_offset("+2"), Some(120));
+        assert_eq!(parse_offset("utc-05:30"), Some(-330));
+        assert_eq!(parse_offset("+0545"), Some(345));
+        assert_eq!(parse_offset("+15"), None);
+        assert_eq!(parse_offset("+2:75"), None);
+        assert_eq!(parse_offset("Europe/Paris"), None);
+        assert_eq!(format_offset(-330), "UTC-05:30");
+        assert_eq!(format_offset(0), "UTC+00:00");
+    }
+
+    #[tokio::test]
+    async fn recording_uses_up_freezes() {
+        let db = Db::memory().await;
+        let (guild, user) = (serenity::GuildId(1), serenity::UserId(2));
+
+        let freeze = NewShopItem {
+            name: "Freeze",
+            kind: "streak_freeze",
+            price: 10,
+            description: None,
+            role: None,
+            rental: None,
+        };
+        db.create_shop_item(guild, &freeze).await.unwrap();
+        db.transfer(guild, None, Some(user), 10, "test", "mint")
+            .await
+            .unwrap();
+        let freeze = db.shop_item(guild, "Freeze").await.unwrap().unwrap();
+        db.buy_item(guild, user, &freeze, "buy").await.unwrap();
+
+        let record = |day| db.record_streak(guild, user, Activity::Daily, day);
+        assert_eq!(record(1).await.unwrap().unwrap().streak.current, 1);
+        assert_eq!(record(1).await.unwrap(), None);
+        let frozen = record(3).await.unwrap().unwrap();
+        assert_eq!((frozen.streak.current, frozen.freezes_used), (2, 1));
+        assert!(db.inventory(guild, user).await.unwrap().is_empty());
+        assert_eq!(record(5).await.unwrap().unwrap().streak, streak(1, 2, 5));
+    }
+}
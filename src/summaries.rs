@@ -0,0 +1,129 @@
+// Thread summaries
+// With the `summaries` feature on, members can use "Summarize thread" on any message of a thread
+// to get a bulleted summary of it from the LLM. The thread is sent as a transcript, split into
+// chunks that are summarized one by one and then merged into one summary when it doesn't fit
+// in a single request. Only the latest messages are summarized and only a few chunks are sent,
+// so a summary costs a bounded number of tokens, and guilds get `summary_limit` of them a day.
+use poise::serenity_prelude as serenity;
+
+use crate::{llm::Llm, Error};
+
+// Messages fetched from a thread, one request fetches 100
+const MAX_MESSAGES: usize = 500;
+// Characters of transcript sent in one request
+const CHUNK_LENGTH: usize = 6000;
+// Chunks summarized for one thread, older messages are left out beyond that
+const MAX_CHUNKS: usize = 4;
+const SUMMARY_TOKENS: u32 = 400;
+const CHUNK_PROMPT: &str = "You summarize part of a Discord thread. Write short bullet points \
+    on what was asked, answered and decided, starting each with \"- \". Leave out greetings \
+    and small talk.";
+const MERGE_PROMPT: &str = "You are given summaries of consecutive parts of a Discord thread. \
+    Merge them into one list of short bullet points on what was asked, answered and decided, \
+    starting each with \"- \". Leave out repetition.";
+
+/// Splits transcript lines into chunks of at most `length` characters, keeping the latest lines
+/// in up to `max` chunks. Lines that are too long on their own are cut
+pub fn chunks(lines: &[String], length: usize, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in lines.iter().rev() {
+        let line: String = line.chars().take(length).collect();
+        if !current.is_empty() && current.len() + 1 + line.len() > length {
+            chunks.push(std::mem::take(&mut current));
+            if chunks.len() == max {
+                break;
+            }
+        }
+        current = if current.is_empty() {
+            line
+        } else {
+            format!("{}\n{}", line, current)
+        };
+    }
+    if !current.is_empty() && chunks.len() < max {
+        chunks.push(current);
+    }
+
+    chunks.reverse();
+    chunks
+}
+
+/// The messages of a thread as transcript lines, oldest first
+pub fn transcript(messages: &[serenity::Message]) -> Vec<String> {
+    let mut messages: Vec<_> = messages
+        .iter()
+        .filter(|m| !m.author.bot && !m.content.trim().is_empty())
+        .collect();
+    messages.sort_unstable_by_key(|m| m.id);
+
+    messages
+        .iter()
+        .map(|m| format!("{}: {}", m.author.name, m.content.replace('\n', " ")))
+        .collect()
+}
+
+/// Summarizes transcript lines as bullet points
+pub async fn summarize(llm: &Llm, lines: &[String]) -> Result<String, Error> {
+    let chunks = chunks(lines, CHUNK_LENGTH, MAX_CHUNKS);
+
+    let mut summaries = Vec::new();
+    for chunk in &chunks {
+        summaries.push(llm.complete(CHUNK_PROMPT, chunk, SUMMARY_TOKENS).await?);
+    }
+    if summaries.len() == 1 {
+        return Ok(summaries.remove(0));
+    }
+
+    llm.complete(MERGE_PROMPT, &summaries.join("\n\n"), SUMMARY_TOKENS)
+        .await
+}
+
+/// The latest messages of a thread, newest first
+pub async fn fetch(
+    http: &serenity::Http,
+    thread: serenity::ChannelId,
+) -> Result<Vec<serenity::Message>, Error> {
+    let mut messages: Vec<serenity::Message> = Vec::new();
+
+    while messages.len() < MAX_MESSAGES {
+        let before = messages.last().map(|m| m.id);
+        let page = thread
+            .messages(http, |r| {
+                if let Some(before) = before {
+                    r.before(before);
+                }
+                r.limit(100)
+            })
+            .await?;
+        let full = page.len() == 100;
+        messages.extend(page);
+
+        if !full {
+            break;
+        }
+    }
+
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_keep_the_latest_lines() {
+        let lines: Vec<String> = ["aaaa", "bbbb", "cccc", "dddd", "eeee"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+
+        assert_eq!(chunks(&lines, 100, 3), ["aaaa\nbbbb\ncccc\ndddd\neeee"]);
+        assert_eq!(chunks(&lines, 10, 3), ["aaaa", "bbbb\ncccc", "dddd\neeee"]);
+        // The oldest lines are left out
+        assert_eq!(chunks(&lines, 10, 2), ["bbbb\ncccc", "dddd\neeee"]);
+        assert_eq!(chunks(&["x".repeat(20)], 10, 2), ["x".repeat(10)]);
+        assert!(chunks(&[], 10, 2).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+// Tags, canned responses created by staff
+// Tags are shown with /tag show, or by sending the prefix and the tag name like `~rules`.
+// Prefix messages that aren't a command end up in on_error as UnknownCommand and get
+// resolved to a tag here.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Lowercases a tag name, returns None if it has characters that can't be typed after a prefix
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+    valid.then_some(name)
+}
+
+/// Sends the tag named by an unknown prefix command, does nothing if there is none
+pub async fn handle_unknown_command(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+    msg_content: &str,
+) -> Result<(), Error> {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let name = match msg_content
+        .split_whitespace()
+        .next()
+        .and_then(normalize_name)
+    {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    // Rate limited users are ignored quietly, a DM for every typo would be more annoying
+    if !data.rate_limiter.try_acquire(message.author.id) {
+        return Ok(());
+    }
+
+    let content = match data.db.use_tag(guild, &name).await? {
+        Some(content) => content,
+        None => return Ok(()),
+    };
+
+    // Tags are written by staff but may still contain @everyone copied from somewhere
+    mentions::send_message(&ctx.http, message.channel_id, Mentions::Nothing, |m| {
+        m.content(content)
+    })
+    .await?;
+
+    Ok(())
+}
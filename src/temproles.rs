@@ -0,0 +1,273 @@
+// Temporary and scheduled roles
+// /temprole gives a role and schedules a job that takes it away again. Role schedules give a
+// role to everyone with another role during a weekly window, e.g. from Friday 18:00 until
+// Monday 00:00, and take it back only from the members they gave it to. Schedules are compared
+// against the current time on every check instead of firing once, so windows that started or
+// ended while the bot was offline are caught up on. All times are UTC.
+use std::time::Duration;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    db::{Db, Job, RoleSchedule},
+    watchdog, Error,
+};
+
+// How often role schedules are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+pub const MINUTES_PER_WEEK: i64 = 7 * 24 * 60;
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+// Members fetched per request while applying a schedule
+const MEMBER_PAGE_SIZE: u64 = 1000;
+
+/// Takes away a temporary role, for `JobKind::RemoveRole`
+pub async fn expire(ctx: &serenity::Context, job: &Job) -> Result<(), Error> {
+    let result = ctx
+        .http
+        .remove_member_role(
+            job.guild_id as u64,
+            job.user_id as u64,
+            job.target_id as u64,
+            Some("Temporary role expired"),
+        )
+        .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        // The member left or the role was deleted, so there is nothing to take away
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Parses a weekly time like `fri 18:00` into minutes since Monday 00:00
+pub fn parse_weekly(input: &str) -> Option<i64> {
+    let mut parts = input.split_whitespace();
+    let day = parts.next()?.to_lowercase();
+    let time = parts.next()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let day = WEEKDAYS.iter().position(|d| day.starts_with(d))? as i64;
+    let (hours, minutes) = time.split_once(':')?;
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    if !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    Some(day * 24 * 60 + hours * 60 + minutes)
+}
+
+/// Formats minutes since Monday 00:00 the way `parse_weekly` reads them
+pub fn format_weekly(minutes: i64) -> String {
+    let day = WEEKDAYS[(minutes / (24 * 60)).rem_euclid(7) as usize];
+    format!("{} {:02}:{:02}", day, minutes / 60 % 24, minutes % 60)
+}
+
+/// Starts the task that grants and removes scheduled roles
+pub fn spawn(ctx: serenity::Context, db: Db) {
+    watchdog::spawn("role schedules", async move {
+        loop {
+            if let Err(e) = reconcile(&ctx, &db).await {
+                tracing::warn!("Error applying role schedules: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn reconcile(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let now = current_minute_of_week();
+
+    for schedule in db.role_schedules(None).await? {
+        let active = in_window(schedule.grant_at, schedule.remove_at, now);
+        if active == schedule.active {
+            continue;
+        }
+
+        // Marked first so a failing guild isn't retried every minute, the next window retries
+        db.set_role_schedule_active(schedule.id, active).await?;
+
+        if let Err(e) = apply(ctx, db, &schedule, active).await {
+            tracing::warn!(
+                schedule = schedule.id,
+                guild = schedule.guild_id,
+                "Error applying role schedule: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Gives the role to every member with the schedule's member role, or takes it from the ones
+/// the schedule gave it to. Members who had the role already keep it
+pub async fn apply(
+    ctx: &serenity::Context,
+    db: &Db,
+    schedule: &RoleSchedule,
+    grant: bool,
+) -> Result<(), Error> {
+    let guild = serenity::GuildId(schedule.guild_id as u64);
+    let role = serenity::RoleId(schedule.role_id as u64);
+    if !grant {
+        return remove(ctx, db, schedule, guild, role).await;
+    }
+
+    let members_role = serenity::RoleId(schedule.members_role_id as u64);
+    let mut after = None;
+    loop {
+        let members = guild
+            .members(&ctx.http, Some(MEMBER_PAGE_SIZE), after)
+            .await?;
+        after = members.last().map(|m| m.user.id);
+
+        for member in &members {
+            if member.roles.contains(&role) || !member.roles.contains(&members_role) {
+                continue;
+            }
+
+            // Recorded first, so a role given just before a crash is still taken away
+            db.add_role_schedule_grant(schedule.id, member.user.id)
+                .await?;
+            if let Err(e) = ctx
+                .http
+                .add_member_role(
+                    guild.0,
+                    member.user.id.0,
+                    role.0,
+                    Some("Role schedule started"),
+                )
+                .await
+            {
+                tracing::debug!(
+                    guild = guild.0,
+                    member = member.user.id.0,
+                    "Error applying scheduled role: {}",
+                    e
+                );
+            }
+        }
+
+        if (members.len() as u64) < MEMBER_PAGE_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+// Takes the role from the members the schedule gave it to
+async fn remove(
+    ctx: &serenity::Context,
+    db: &Db,
+    schedule: &RoleSchedule,
+    guild: serenity::GuildId,
+    role: serenity::RoleId,
+) -> Result<(), Error> {
+    for user in db.role_schedule_grants(schedule.id).await? {
+        let result = ctx
+            .http
+            .remove_member_role(guild.0, user.0, role.0, Some("Role schedule ended"))
+            .await;
+
+        match result {
+            Ok(()) => {}
+            // The member left or the role was deleted, so there is nothing to take away
+            Err(serenity::Error::Http(e))
+                if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) => {}
+            // Kept, so the next window's end tries again
+            Err(e) => {
+                tracing::debug!(
+                    guild = guild.0,
+                    member = user.0,
+                    "Error removing scheduled role: {}",
+                    e
+                );
+                continue;
+            }
+        }
+        db.delete_role_schedule_grant(schedule.id, user).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether `now` is inside a window, windows can wrap around the end of the week
+pub fn in_window(start: i64, end: i64, now: i64) -> bool {
+    if start <= end {
+        start <= now && now < end
+    } else {
+        now >= start || now < end
+    }
+}
+
+pub fn current_minute_of_week() -> i64 {
+    let minutes = serenity::Timestamp::now().unix_timestamp() / 60;
+    // The unix epoch was a Thursday
+    (minutes + 3 * 24 * 60).rem_euclid(MINUTES_PER_WEEK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weekly_times_are_minutes_since_monday() {
+        assert_eq!(parse_weekly("mon 00:00"), Some(0));
+        assert_eq!(
+            parse_weekly("Friday 18:30"),
+            Some(4 * 24 * 60 + 18 * 60 + 30)
+        );
+        assert_eq!(parse_weekly("sun 23:59"), Some(MINUTES_PER_WEEK - 1));
+        assert_eq!(parse_weekly("fri 24:00"), None);
+        assert_eq!(parse_weekly("fri 18:60"), None);
+        assert_eq!(parse_weekly("fri"), None);
+        assert_eq!(parse_weekly("fri 18:00 utc"), None);
+        assert_eq!(parse_weekly("someday 18:00"), None);
+
+        for minutes in [0, 59, 4 * 24 * 60 + 18 * 60, MINUTES_PER_WEEK - 1] {
+            assert_eq!(parse_weekly(&format_weekly(minutes)), Some(minutes));
+        }
+    }
+
+    #[test]
+    fn windows_can_wrap_around_the_week() {
+        let friday = parse_weekly("fri 18:00").unwrap();
+        let monday = parse_weekly("mon 00:00").unwrap();
+        let wednesday = parse_weekly("wed 12:00").unwrap();
+
+        assert!(in_window(friday, MINUTES_PER_WEEK - 1, friday));
+        assert!(!in_window(friday, MINUTES_PER_WEEK - 1, friday - 1));
+        // Friday evening until Monday morning wraps over Sunday night
+        let tuesday = parse_weekly("tue 08:00").unwrap();
+        assert!(in_window(friday, tuesday, MINUTES_PER_WEEK - 1));
+        assert!(in_window(friday, tuesday, monday));
+        assert!(!in_window(friday, tuesday, tuesday));
+        assert!(!in_window(friday, tuesday, wednesday));
+        // The end is outside the window
+        assert!(!in_window(monday, wednesday, wednesday));
+        assert!((0..MINUTES_PER_WEEK).contains(&current_minute_of_week()));
+    }
+
+    #[tokio::test]
+    async fn grants_are_remembered_per_schedule() {
+        let db = Db::memory().await;
+        let (a, b) = (serenity::UserId(1), serenity::UserId(2));
+
+        db.add_role_schedule_grant(1, a).await.unwrap();
+        db.add_role_schedule_grant(1, a).await.unwrap();
+        db.add_role_schedule_grant(2, b).await.unwrap();
+        assert_eq!(db.role_schedule_grants(1).await.unwrap(), [a]);
+
+        db.delete_role_schedule_grant(1, a).await.unwrap();
+        assert!(db.role_schedule_grants(1).await.unwrap().is_empty());
+        assert_eq!(db.role_schedule_grants(2).await.unwrap(), [b]);
+    }
+}
@@ -0,0 +1,189 @@
+// Join to create voice channels
+// Joining the hub set with `/config set voice_hub_channel` creates a voice channel next to it
+// for the member and moves them in. They own it and can change it with /voice, and when they
+// leave anyone still in it can claim it. The channel is deleted once the last member leaves.
+use poise::serenity_prelude as serenity;
+
+use crate::{Data, Error};
+
+// Discord limits channel names to this many characters
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Voice server regions Discord lets channels pick
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum VoiceRegion {
+    #[name = "automatic"]
+    Automatic,
+    #[name = "brazil"]
+    Brazil,
+    #[name = "hongkong"]
+    HongKong,
+    #[name = "india"]
+    India,
+    #[name = "japan"]
+    Japan,
+    #[name = "rotterdam"]
+    Rotterdam,
+    #[name = "russia"]
+    Russia,
+    #[name = "singapore"]
+    Singapore,
+    #[name = "southafrica"]
+    SouthAfrica,
+    #[name = "sydney"]
+    Sydney,
+    #[name = "us-central"]
+    UsCentral,
+    #[name = "us-east"]
+    UsEast,
+    #[name = "us-south"]
+    UsSouth,
+    #[name = "us-west"]
+    UsWest,
+}
+
+impl VoiceRegion {
+    /// ID of the region for the API, None lets Discord pick
+    pub fn id(self) -> Option<String> {
+        match self {
+            VoiceRegion::Automatic => None,
+            region => Some(region.name().to_string()),
+        }
+    }
+}
+
+/// Creates channels for members joining the hub, and deletes temporary channels left empty
+pub async fn handle_voice_state(
+    ctx: &serenity::Context,
+    data: &Data,
+    old: Option<&serenity::VoiceState>,
+    new: &serenity::VoiceState,
+) -> Result<(), Error> {
+    let guild = match new.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    let left = old
+        .and_then(|o| o.channel_id)
+        .filter(|c| Some(*c) != new.channel_id);
+    if let Some(left) = left {
+        if is_empty(ctx, guild, left) && data.db.delete_temp_voice(left).await? {
+            left.delete(&ctx.http).await?;
+        }
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    let joined_hub = new.channel_id.is_some() && new.channel_id == config.voice_hub_channel;
+    let bot = new.member.as_ref().is_some_and(|m| m.user.bot);
+    if joined_hub && !bot {
+        if let Some(member) = &new.member {
+            create(ctx, data, guild, config.voice_hub_channel, member).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn create(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    hub: Option<serenity::ChannelId>,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let category = hub
+        .and_then(|hub| ctx.cache.guild_channel(hub))
+        .and_then(|hub| hub.parent_id);
+    let name: String = format!("{}'s channel", member.display_name())
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect();
+
+    let channel = guild
+        .create_channel(&ctx.http, |c| {
+            c.name(name).kind(serenity::ChannelType::Voice);
+            if let Some(category) = category {
+                c.category(category);
+            }
+            c
+        })
+        .await?;
+    data.db
+        .create_temp_voice(guild, channel.id, member.user.id)
+        .await?;
+
+    // They may have left the hub again already, which is normal and not an error
+    if let Err(e) = guild
+        .move_member(&ctx.http, member.user.id, channel.id)
+        .await
+    {
+        tracing::debug!(
+            guild = guild.0,
+            user = member.user.id.0,
+            "Couldn't move member into their voice channel: {}",
+            e
+        );
+        data.db.delete_temp_voice(channel.id).await?;
+        channel.delete(&ctx.http).await?;
+    }
+
+    Ok(())
+}
+
+/// Deletes temporary channels that were left empty, or deleted, while we were offline
+pub async fn sweep(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: &serenity::Guild,
+) -> Result<(), Error> {
+    for channel in data.db.temp_voice_channels(guild.id).await? {
+        if guild
+            .voice_states
+            .values()
+            .any(|v| v.channel_id == Some(channel))
+        {
+            continue;
+        }
+
+        data.db.delete_temp_voice(channel).await?;
+        if guild.channels.contains_key(&channel) {
+            if let Err(e) = channel.delete(&ctx.http).await {
+                tracing::warn!(
+                    channel = channel.0,
+                    "Error deleting empty temporary voice channel: {}",
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether nobody is in a voice channel, from the cache
+pub fn is_empty(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    channel: serenity::ChannelId,
+) -> bool {
+    ctx.cache.guild(guild).is_some_and(|g| {
+        !g.voice_states
+            .values()
+            .any(|v| v.channel_id == Some(channel))
+    })
+}
+
+/// Whether a member is in a voice channel, from the cache
+pub fn is_in(
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    channel: serenity::ChannelId,
+    user: serenity::UserId,
+) -> bool {
+    ctx.cache.guild(guild).is_some_and(|g| {
+        g.voice_states
+            .get(&user)
+            .is_some_and(|v| v.channel_id == Some(channel))
+    })
+}
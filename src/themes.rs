@@ -0,0 +1,362 @@
+// Seasonal themes
+// /theme add sets up a yearly theme like a holiday, from one day to another. While it's going,
+// the server icon is swapped for the theme's, embeds members see like starboard posts use its
+// colour, and the bot sometimes reacts to messages with one of its emojis. Themes added by the
+// bot owner can also change the bot's status, which every server sees. The server icon from
+// before is saved when a theme starts and put back when it ends. Like role schedules, themes
+// are compared against the date on every check so starts and ends missed while offline are
+// caught up on. A server can't have overlapping themes, they'd save each other's icons.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+use rand::seq::SliceRandom;
+use tokio::sync::Mutex;
+
+use crate::{
+    db::{Db, Theme},
+    watchdog, Error,
+};
+
+// How often themes are checked, they change by the day
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// A hanging icon download would hold up every later theme check
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+// What the bot is doing without a themed status
+const DEFAULT_STATUS: &str = "watching sticks & sham cry";
+/// One in this many messages gets a reaction while a theme with emojis is going
+const REACTION_CHANCE: u32 = 50;
+const DAYS_PER_MONTH: [i64; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// Parses a date like `12-24` into month * 100 + day
+pub fn parse_date(input: &str) -> Option<i64> {
+    let (month, day) = input.trim().split_once(['-', '/'])?;
+    let month: i64 = month.parse().ok()?;
+    let day: i64 = day.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 || day > DAYS_PER_MONTH[month as usize - 1] {
+        return None;
+    }
+
+    Some(month * 100 + day)
+}
+
+/// Formats a date the way `parse_date` reads it
+pub fn format_date(date: i64) -> String {
+    format!("{:02}-{:02}", date / 100, date % 100)
+}
+
+/// Whether `today` is between two dates, both included, themes can go over new year
+pub fn in_season(starts_on: i64, ends_on: i64, today: i64) -> bool {
+    if starts_on <= ends_on {
+        starts_on <= today && today <= ends_on
+    } else {
+        today >= starts_on || today <= ends_on
+    }
+}
+
+/// Whether two themes share any day
+pub fn overlaps(a: (i64, i64), b: (i64, i64)) -> bool {
+    in_season(a.0, a.1, b.0) || in_season(b.0, b.1, a.0)
+}
+
+/// The activity for a status like `watching the snow fall`, playing if it doesn't say
+pub fn activity(status: &str) -> serenity::Activity {
+    let (kind, text) = status.split_once(' ').unwrap_or((status, ""));
+    match kind.to_lowercase().as_str() {
+        "playing" => serenity::Activity::playing(text),
+        "listening" => serenity::Activity::listening(text.trim_start_matches("to ")),
+        "watching" => serenity::Activity::watching(text),
+        "competing" => serenity::Activity::competing(text.trim_start_matches("in ")),
+        _ => serenity::Activity::playing(status),
+    }
+}
+
+/// Parses a colour in hex like `#7fb2f0`
+pub fn parse_colour(input: &str) -> Option<u32> {
+    let hex = input.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Unicode emojis of a theme's reaction pool, separated by spaces
+pub fn parse_reactions(reactions: &str) -> Vec<serenity::ReactionType> {
+    reactions
+        .split_whitespace()
+        .map(|e| serenity::ReactionType::Unicode(e.to_string()))
+        .collect()
+}
+
+fn today() -> i64 {
+    serenity::Timestamp::now()
+        .format("%m%d")
+        .to_string()
+        .parse()
+        .unwrap_or_default()
+}
+
+/// What a guild's current theme changes in messages
+pub struct ActiveTheme {
+    pub colour: Option<serenity::Colour>,
+    pub reactions: Vec<serenity::ReactionType>,
+}
+
+/// The current theme of every guild that has one, and the themed status
+#[derive(Clone)]
+pub struct Themes {
+    db: Db,
+    client: reqwest::Client,
+    active: Arc<RwLock<HashMap<serenity::GuildId, Arc<ActiveTheme>>>>,
+    status: Arc<RwLock<Option<String>>>,
+}
+
+impl Themes {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .expect("Failed to build theme HTTP client"),
+            active: Arc::default(),
+            status: Arc::default(),
+        }
+    }
+
+    /// Downloads an icon as the data URI Discord takes for server icons
+    pub async fn download_icon(&self, url: &str) -> Result<String, Error> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        let mime = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|t| t.to_str().ok())
+            .unwrap_or("image/png")
+            .to_string();
+        let data = response.bytes().await?;
+        Ok(format!("data:{};base64,{}", mime, base64::encode(&data)))
+    }
+
+    /// Colour for embeds members of a guild see, if its theme has one
+    pub fn colour(&self, guild: serenity::GuildId) -> Option<serenity::Colour> {
+        self.active.read().unwrap().get(&guild)?.colour
+    }
+
+    /// What the bot should be doing, a theme's status or the usual one
+    pub fn activity(&self) -> serenity::Activity {
+        activity(
+            self.status
+                .read()
+                .unwrap()
+                .as_deref()
+                .unwrap_or(DEFAULT_STATUS),
+        )
+    }
+
+    /// Sometimes reacts to a message with an emoji of its guild's theme
+    pub async fn handle_message(
+        &self,
+        ctx: &serenity::Context,
+        message: &serenity::Message,
+    ) -> Result<(), Error> {
+        let guild = match message.guild_id {
+            Some(guild) if !message.author.bot => guild,
+            _ => return Ok(()),
+        };
+
+        let reaction = {
+            let active = self.active.read().unwrap();
+            let reactions = match active.get(&guild) {
+                Some(theme) => &theme.reactions,
+                None => return Ok(()),
+            };
+            let mut rng = rand::thread_rng();
+            if !rand::Rng::gen_ratio(&mut rng, 1, REACTION_CHANCE) {
+                return Ok(());
+            }
+            match reactions.choose(&mut rng) {
+                Some(reaction) => reaction.clone(),
+                None => return Ok(()),
+            }
+        };
+
+        message.react(ctx, reaction).await?;
+        Ok(())
+    }
+
+    /// Starts and ends themes that are due, then updates the cached themes and the status
+    pub async fn reconcile(
+        &self,
+        ctx: &serenity::Context,
+        shard_manager: &Mutex<serenity::ShardManager>,
+    ) -> Result<(), Error> {
+        let today = today();
+        let mut active = HashMap::new();
+        let mut status = None;
+
+        for theme in self.db.themes(None).await? {
+            let season = in_season(theme.starts_on, theme.ends_on, today);
+            if season != theme.active {
+                let result = if season {
+                    self.start(ctx, &theme).await
+                } else {
+                    self.end(ctx, &theme).await
+                };
+                if let Err(e) = result {
+                    tracing::warn!(
+                        theme = theme.id,
+                        guild = theme.guild_id,
+                        "Error applying theme: {}",
+                        e
+                    );
+                }
+            }
+            if !season {
+                continue;
+            }
+
+            if status.is_none() {
+                status = theme.status.clone();
+            }
+            active.insert(
+                serenity::GuildId(theme.guild_id as u64),
+                Arc::new(ActiveTheme {
+                    colour: theme.colour.map(|c| serenity::Colour(c as u32)),
+                    reactions: theme
+                        .reactions
+                        .as_deref()
+                        .map(parse_reactions)
+                        .unwrap_or_default(),
+                }),
+            );
+        }
+
+        *self.active.write().unwrap() = active;
+        let changed = {
+            let mut current = self.status.write().unwrap();
+            let changed = *current != status;
+            *current = status;
+            changed
+        };
+        // Only on changes so a status set with /admin status stays until the next theme
+        if changed {
+            let activity = self.activity();
+            let manager = shard_manager.lock().await;
+            for runner in manager.runners.lock().await.values() {
+                runner.runner_tx.set_activity(Some(activity.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn start(&self, ctx: &serenity::Context, theme: &Theme) -> Result<(), Error> {
+        let icon = match &theme.icon {
+            Some(icon) => icon,
+            None => {
+                return self.db.set_theme_active(theme.id, true, None).await;
+            }
+        };
+        let mut guild = serenity::GuildId(theme.guild_id as u64);
+
+        // Saved before it's replaced, a failed download is retried on the next check
+        let saved = match guild.to_partial_guild(&ctx.http).await?.icon_url() {
+            Some(url) => Some(self.download_icon(&url).await?),
+            None => None,
+        };
+        self.db
+            .set_theme_active(theme.id, true, saved.as_deref())
+            .await?;
+
+        guild
+            .edit(&ctx.http, |g| g.icon(Some(icon.as_str())))
+            .await?;
+        Ok(())
+    }
+
+    /// Puts back the server icon a theme replaced, if it's applied
+    pub async fn end(&self, ctx: &serenity::Context, theme: &Theme) -> Result<(), Error> {
+        self.db.set_theme_active(theme.id, false, None).await?;
+        if !theme.active || theme.icon.is_none() {
+            return Ok(());
+        }
+
+        serenity::GuildId(theme.guild_id as u64)
+            .edit(&ctx.http, |g| g.icon(theme.saved_icon.as_deref()))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Starts the task that starts and ends themes
+pub fn spawn(
+    ctx: serenity::Context,
+    themes: Themes,
+    shard_manager: Arc<Mutex<serenity::ShardManager>>,
+) {
+    watchdog::spawn("themes", async move {
+        loop {
+            if let Err(e) = themes.reconcile(&ctx, &shard_manager).await {
+                tracing::warn!("Error applying themes: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_wrap_around_new_year() {
+        let date = |d| parse_date(d).unwrap();
+
+        assert_eq!(date("12-24"), 1224);
+        assert_eq!(date("2/29"), 229);
+        assert_eq!(parse_date("02-30"), None);
+        assert_eq!(parse_date("13-01"), None);
+        assert_eq!(format_date(date("1-5")), "01-05");
+
+        assert!(in_season(date("12-20"), date("01-02"), date("12-31")));
+        assert!(in_season(date("12-20"), date("01-02"), date("01-02")));
+        assert!(!in_season(date("12-20"), date("01-02"), date("01-03")));
+        assert!(in_season(date("10-31"), date("10-31"), date("10-31")));
+
+        assert!(overlaps(
+            (date("12-20"), date("01-02")),
+            (date("01-01"), date("01-10"))
+        ));
+        assert!(!overlaps(
+            (date("12-20"), date("01-02")),
+            (date("10-01"), date("10-31"))
+        ));
+    }
+
+    #[test]
+    fn colours_are_six_hex_digits() {
+        assert_eq!(parse_colour("#7fb2f0"), Some(0x7fb2f0));
+        assert_eq!(parse_colour("FF7518"), Some(0xff7518));
+        assert_eq!(parse_colour("#fff"), None);
+        assert_eq!(parse_colour("#12345g"), None);
+    }
+
+    #[test]
+    fn statuses_say_what_the_bot_does() {
+        let watching = activity("watching the snow fall");
+        assert_eq!(watching.kind, serenity::ActivityType::Watching);
+        assert_eq!(watching.name, "the snow fall");
+
+        let listening = activity("listening to carols");
+        assert_eq!(listening.kind, serenity::ActivityType::Listening);
+        assert_eq!(listening.name, "carols");
+
+        let playing = activity("Spooky season");
+        assert_eq!(playing.kind, serenity::ActivityType::Playing);
+        assert_eq!(playing.name, "Spooky season");
+    }
+}
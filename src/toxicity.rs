@@ -0,0 +1,351 @@
+// Toxicity scoring for automod
+// Messages are scored by Perspective API when PERSPECTIVE_API_KEY is set, or else by a local
+// model served at TOXICITY_URL, which gets `{"text": ..., "categories": [...]}` posted and
+// answers with a score from 0 to 1 per category name. Servers opt in per category with
+// /toxicity set, and messages scoring at or above a threshold go to the review queue for a
+// moderator to decide on, nothing is deleted automatically.
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use poise::{serenity_prelude as serenity, BoxFuture};
+use serde_json::{json, Value};
+
+use crate::{
+    circuit::CircuitBreaker,
+    config::RoleList,
+    reviewqueue::{self, Flag},
+    Data, Error,
+};
+
+const PERSPECTIVE_URL: &str = "https://commentanalyzer.googleapis.com/v1alpha1/comments:analyze";
+// Scoring runs in the message pipeline, a hanging request would hold up every later stage
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Messages shorter than this rarely say anything a score would be right about
+const MIN_LENGTH: usize = 3;
+
+/// What a message can be scored for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Category {
+    /// Rude or disrespectful
+    #[name = "toxicity"]
+    Toxicity,
+    /// Very hateful or aggressive
+    #[name = "severe_toxicity"]
+    SevereToxicity,
+    /// Hateful towards someone for their identity
+    #[name = "identity_attack"]
+    IdentityAttack,
+    #[name = "insult"]
+    Insult,
+    /// Swearing and other obscene language
+    #[name = "profanity"]
+    Profanity,
+    /// Wishing or threatening harm
+    #[name = "threat"]
+    Threat,
+}
+
+impl Category {
+    pub const ALL: &'static [Category] = &[
+        Category::Toxicity,
+        Category::SevereToxicity,
+        Category::IdentityAttack,
+        Category::Insult,
+        Category::Profanity,
+        Category::Threat,
+    ];
+
+    // The name of the category's attribute in Perspective API
+    fn attribute(self) -> &'static str {
+        match self {
+            Category::Toxicity => "TOXICITY",
+            Category::SevereToxicity => "SEVERE_TOXICITY",
+            Category::IdentityAttack => "IDENTITY_ATTACK",
+            Category::Insult => "INSULT",
+            Category::Profanity => "PROFANITY",
+            Category::Threat => "THREAT",
+        }
+    }
+}
+
+/// Scores from 0 to 1 of how likely a text falls into each category
+pub type Scores = HashMap<Category, f64>;
+
+/// Something that can score texts, a hosted API or a local model
+pub trait Scorer: Send + Sync {
+    /// Scores `text` for `categories`, categories the scorer has no score for are left out
+    fn score<'a>(
+        &'a self,
+        text: &'a str,
+        categories: &'a [Category],
+    ) -> BoxFuture<'a, Result<Scores, reqwest::Error>>;
+}
+
+struct Perspective {
+    key: String,
+    client: reqwest::Client,
+}
+
+impl Scorer for Perspective {
+    fn score<'a>(
+        &'a self,
+        text: &'a str,
+        categories: &'a [Category],
+    ) -> BoxFuture<'a, Result<Scores, reqwest::Error>> {
+        Box::pin(async move {
+            let attributes: serde_json::Map<String, Value> = categories
+                .iter()
+                .map(|c| (c.attribute().to_string(), json!({})))
+                .collect();
+
+            let response: Value = self
+                .client
+                .post(PERSPECTIVE_URL)
+                .query(&[("key", &self.key)])
+                .json(&json!({
+                    "comment": { "text": text },
+                    "requestedAttributes": attributes,
+                    "doNotStore": true,
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            Ok(perspective_scores(&response))
+        })
+    }
+}
+
+struct LocalModel {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Scorer for LocalModel {
+    fn score<'a>(
+        &'a self,
+        text: &'a str,
+        categories: &'a [Category],
+    ) -> BoxFuture<'a, Result<Scores, reqwest::Error>> {
+        Box::pin(async move {
+            let names: Vec<_> = categories.iter().map(|c| c.name()).collect();
+
+            let response: Value = self
+                .client
+                .post(&self.url)
+                .json(&json!({ "text": text, "categories": names }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            Ok(local_scores(&response))
+        })
+    }
+}
+
+/// Reads the scores out of a Perspective API response
+pub fn perspective_scores(response: &Value) -> Scores {
+    Category::ALL
+        .iter()
+        .filter_map(|&category| {
+            let score = response["attributeScores"][category.attribute()]["summaryScore"]["value"]
+                .as_f64()?;
+            Some((category, score))
+        })
+        .collect()
+}
+
+/// Reads the scores out of a local model's response, an object of scores by category name
+pub fn local_scores(response: &Value) -> Scores {
+    Category::ALL
+        .iter()
+        .filter_map(|&category| Some((category, response[category.name()].as_f64()?)))
+        .collect()
+}
+
+/// The categories scoring at or above their threshold in percent, the highest score first
+pub fn flagged(scores: &Scores, thresholds: &HashMap<Category, u32>) -> Vec<(Category, f64)> {
+    let mut flagged: Vec<_> = thresholds
+        .iter()
+        .filter_map(|(category, &threshold)| {
+            let score = *scores.get(category)?;
+            (score * 100.0 >= threshold as f64).then_some((*category, score))
+        })
+        .collect();
+    flagged.sort_unstable_by(|a, b| b.1.total_cmp(&a.1));
+    flagged
+}
+
+pub struct Toxicity {
+    scorer: Box<dyn Scorer>,
+    pub breaker: Arc<CircuitBreaker>,
+}
+
+impl Toxicity {
+    /// Creates the scorer if PERSPECTIVE_API_KEY or TOXICITY_URL is set, Perspective first
+    pub fn from_env() -> Option<Arc<Self>> {
+        let client = || {
+            reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build toxicity HTTP client")
+        };
+
+        let scorer: Box<dyn Scorer> = match env::var("PERSPECTIVE_API_KEY") {
+            Ok(key) => Box::new(Perspective {
+                key,
+                client: client(),
+            }),
+            Err(_) => Box::new(LocalModel {
+                url: env::var("TOXICITY_URL").ok()?,
+                client: client(),
+            }),
+        };
+
+        Some(Arc::new(Self {
+            scorer,
+            breaker: Arc::new(CircuitBreaker::new("toxicity")),
+        }))
+    }
+
+    async fn score(&self, text: &str, categories: &[Category]) -> Option<Scores> {
+        if !self.breaker.allow() {
+            return None;
+        }
+
+        match self.scorer.score(text, categories).await {
+            Ok(scores) => {
+                self.breaker.record_success();
+                Some(scores)
+            }
+            // Perspective refuses categories it can't score in the message's language, it still
+            // answered so the API is up
+            Err(e)
+                if e.status()
+                    .is_some_and(|s| s == reqwest::StatusCode::BAD_REQUEST) =>
+            {
+                self.breaker.record_success();
+                tracing::debug!("Message toxicity couldn't be scored: {}", e);
+                None
+            }
+            Err(e) => {
+                self.breaker.record_failure();
+                tracing::warn!("Error scoring message toxicity: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Scores a message for the categories the guild turned on and flags it for review if it
+/// scores too high. Returns whether the message was flagged
+pub async fn scan(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<bool, Error> {
+    let (toxicity, guild) = match (&data.toxicity, message.guild_id) {
+        (Some(toxicity), Some(guild)) => (toxicity, guild),
+        _ => return Ok(false),
+    };
+
+    if message.content.trim().chars().count() < MIN_LENGTH {
+        return Ok(false);
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    if config.toxicity_thresholds.is_empty() {
+        return Ok(false);
+    }
+
+    let roles = message
+        .member
+        .as_ref()
+        .map(|m| m.roles.as_slice())
+        .unwrap_or_default();
+    if config.has_role_in(RoleList::AutomodExempt, roles) {
+        return Ok(false);
+    }
+
+    let categories: Vec<_> = config.toxicity_thresholds.keys().copied().collect();
+    let scores = match toxicity.score(&message.content, &categories).await {
+        Some(scores) => scores,
+        None => return Ok(false),
+    };
+    let flagged = flagged(&scores, &config.toxicity_thresholds);
+    if flagged.is_empty() {
+        return Ok(false);
+    }
+
+    let summary = flagged
+        .iter()
+        .map(|(category, score)| format!("{} {:.0}%", category.name(), score * 100.0))
+        .collect::<Vec<_>>()
+        .join(", ");
+    reviewqueue::flag(
+        ctx,
+        data,
+        &config,
+        message,
+        Flag {
+            source: "toxicity",
+            rule: flagged[0].0.name(),
+            title: "Possibly toxic message",
+            details: summary,
+            image: None,
+        },
+    )
+    .await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_read_from_responses() {
+        let perspective = json!({
+            "attributeScores": {
+                "TOXICITY": { "summaryScore": { "value": 0.9, "type": "PROBABILITY" } },
+                "THREAT": { "summaryScore": { "value": 0.1, "type": "PROBABILITY" } },
+            }
+        });
+        assert_eq!(
+            perspective_scores(&perspective),
+            Scores::from([(Category::Toxicity, 0.9), (Category::Threat, 0.1)])
+        );
+
+        let local = json!({ "insult": 0.5, "profanity": "high", "unknown": 1.0 });
+        assert_eq!(
+            local_scores(&local),
+            Scores::from([(Category::Insult, 0.5)])
+        );
+    }
+
+    #[test]
+    fn only_categories_over_their_threshold_are_flagged() {
+        let scores = Scores::from([
+            (Category::Toxicity, 0.7),
+            (Category::Insult, 0.95),
+            (Category::Threat, 0.2),
+        ]);
+        let thresholds = HashMap::from([
+            (Category::Toxicity, 70),
+            (Category::Insult, 90),
+            (Category::Threat, 50),
+            // Not scored, e.g. because the language isn't supported for it
+            (Category::Profanity, 10),
+        ]);
+
+        assert_eq!(
+            flagged(&scores, &thresholds),
+            [(Category::Insult, 0.95), (Category::Toxicity, 0.7)]
+        );
+        assert!(flagged(&scores, &HashMap::new()).is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+// Translating messages when someone reacts with a country flag
+// Uses a LibreTranslate compatible API, enabled by setting TRANSLATE_URL
+// (e.g. https://libretranslate.com) and TRANSLATE_API_KEY if the instance needs one.
+// Servers have to opt in with the `translate` feature.
+// Channels can also be linked with /config translation, then every message posted in one is
+// translated and reposted in the other in the name of its author. Our reposts come from a
+// webhook, so they are bot messages and never mirrored back.
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+use serde_json::{json, Value};
+
+use crate::{
+    circuit::CircuitBreaker,
+    config::Feature,
+    mentions::{self, Mentions},
+    webhooks, Data, Error,
+};
+
+// The same message isn't translated into the same language again within this time
+const DEBOUNCE: Duration = Duration::from_secs(10 * 60);
+// Long messages are cut so the translation fits in a single reply
+const MAX_LENGTH: usize = 1800;
+// A slow instance shouldn't hold up the reaction handler
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Translator {
+    url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    pub breaker: Arc<CircuitBreaker>,
+    recent: Mutex<HashMap<(serenity::MessageId, &'static str), Instant>>,
+}
+
+impl Translator {
+    /// Creates the translator if TRANSLATE_URL is set
+    pub fn from_env() -> Option<Arc<Self>> {
+        let url = env::var("TRANSLATE_URL").ok()?;
+
+        Some(Arc::new(Self {
+            url: format!("{}/translate", url.trim_end_matches('/')),
+            api_key: env::var("TRANSLATE_API_KEY").ok(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build translation HTTP client"),
+            breaker: Arc::new(CircuitBreaker::new("translation")),
+            recent: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    // Returns false if this message was translated into `language` recently
+    fn claim(&self, message: serenity::MessageId, language: &'static str) -> bool {
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|_, at| at.elapsed() < DEBOUNCE);
+
+        if recent.contains_key(&(message, language)) {
+            return false;
+        }
+        recent.insert((message, language), Instant::now());
+        true
+    }
+
+    // Returns None if the text already is in `target`
+    async fn translate(&self, text: &str, target: &str) -> Result<Option<String>, reqwest::Error> {
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "q": text,
+                "source": "auto",
+                "target": target,
+                "format": "text",
+                "api_key": self.api_key,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if response["detectedLanguage"]["language"].as_str() == Some(target) {
+            return Ok(None);
+        }
+
+        Ok(response["translatedText"].as_str().map(str::to_string))
+    }
+}
+
+/// Replies with a translation when a message gets a flag reaction
+pub async fn handle_reaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let translator = match &data.translator {
+        Some(translator) => translator,
+        None => return Ok(()),
+    };
+
+    let (guild, user) = match (reaction.guild_id, reaction.user_id) {
+        (Some(guild), Some(user)) => (guild, user),
+        _ => return Ok(()),
+    };
+
+    let language = match &reaction.emoji {
+        serenity::ReactionType::Unicode(emoji) => match flag_language(emoji) {
+            Some(language) => language,
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    if !data
+        .guild_configs
+        .get(guild)
+        .await?
+        .is_enabled(Feature::Translate)
+    {
+        return Ok(());
+    }
+
+    // Checked before the rate limit so repeated flags don't cost the user tokens
+    if !translator.claim(reaction.message_id, language) {
+        return Ok(());
+    }
+
+    if !data.rate_limiter.try_acquire(user) {
+        return Ok(());
+    }
+
+    let message = reaction.message(&ctx.http).await?;
+    if message.content.trim().is_empty() {
+        return Ok(());
+    }
+
+    let text: String = message.content.chars().take(MAX_LENGTH).collect();
+    // Right before the request, so every call the breaker allows reports back
+    if !translator.breaker.allow() {
+        return Ok(());
+    }
+    let translated = match translator.translate(&text, language).await {
+        Ok(translated) => {
+            translator.breaker.record_success();
+            translated
+        }
+        Err(e) => {
+            translator.breaker.record_failure();
+            tracing::warn!(
+                message_id = message.id.0,
+                "Error translating message: {}",
+                e
+            );
+            return Ok(());
+        }
+    };
+
+    let translated = match translated {
+        Some(translated) => translated,
+        None => return Ok(()),
+    };
+
+    // Reactions aren't interactions so this can't be ephemeral, reply to the message instead
+    mentions::send_message(&ctx.http, message.channel_id, Mentions::Nothing, |m| {
+        m.reference_message(&message).content(format!(
+            "{} Translated for <@{}>:\n{}",
+            reaction.emoji, user.0, translated
+        ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Language codes messages can be translated into, the ones flag reactions map to
+pub const LANGUAGES: &[&str] = &[
+    "ar", "cs", "da", "de", "el", "en", "es", "fi", "fr", "he", "hi", "id", "it", "ja", "ko", "nl",
+    "pl", "pt", "ru", "sv", "tr", "uk", "vi", "zh",
+];
+
+/// Mirrors a message into the channels linked to its channel, translated into their language
+pub async fn mirror(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    let (translator, guild) = match (&data.translator, message.guild_id) {
+        (Some(translator), Some(guild)) => (translator, guild),
+        _ => return Ok(()),
+    };
+
+    let config = data.guild_configs.get(guild).await?;
+    let links = match config.translation_links.get(&message.channel_id) {
+        Some(links) => links,
+        None => return Ok(()),
+    };
+
+    let text: String = message.content.chars().take(MAX_LENGTH).collect();
+    for (target, language) in links {
+        let mut content = if text.trim().is_empty() {
+            String::new()
+        } else if !translator.breaker.allow() {
+            return Ok(());
+        } else {
+            match translator.translate(&text, language).await {
+                Ok(translated) => {
+                    translator.breaker.record_success();
+                    translated.unwrap_or_else(|| text.clone())
+                }
+                Err(e) => {
+                    translator.breaker.record_failure();
+                    tracing::warn!(
+                        message_id = message.id.0,
+                        "Error translating message: {}",
+                        e
+                    );
+                    continue;
+                }
+            }
+        };
+
+        // Linked rather than copied, they stay where they were posted
+        for attachment in &message.attachments {
+            content.push('\n');
+            content.push_str(&attachment.url);
+        }
+        if content.trim().is_empty() {
+            continue;
+        }
+
+        let parts = webhooks::split(&content, webhooks::MAX_PART_LENGTH);
+        if let Err(e) = webhooks::repost_to(ctx, *target, message, parts, Vec::new()).await {
+            tracing::warn!(
+                channel = target.0,
+                "Error mirroring message to linked channel: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Maps a flag emoji to the main language of that country
+fn flag_language(emoji: &str) -> Option<&'static str> {
+    // Flags are two regional indicator symbols, one for each letter of the country code
+    let code = emoji
+        .chars()
+        .map(|c| match c as u32 {
+            0x1F1E6..=0x1F1FF => Some((b'a' + (c as u32 - 0x1F1E6) as u8) as char),
+            _ => None,
+        })
+        .collect::<Option<String>>()?;
+
+    let language = match code.as_str() {
+        "us" | "gb" | "au" | "ca" | "nz" | "ie" => "en",
+        "es" | "mx" | "ar" | "co" | "cl" | "pe" => "es",
+        "fr" | "be" => "fr",
+        "de" | "at" | "ch" => "de",
+        "it" => "it",
+        "pt" | "br" => "pt",
+        "nl" => "nl",
+        "pl" => "pl",
+        "ru" => "ru",
+        "ua" => "uk",
+        "tr" => "tr",
+        "se" => "sv",
+        "dk" => "da",
+        "fi" => "fi",
+        "cz" => "cs",
+        "gr" => "el",
+        "jp" => "ja",
+        "kr" => "ko",
+        "cn" | "tw" => "zh",
+        "in" => "hi",
+        "id" => "id",
+        "vn" => "vi",
+        "sa" | "eg" | "ae" => "ar",
+        "il" => "he",
+        _ => return None,
+    };
+
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_map_to_languages_links_accept() {
+        assert_eq!(flag_language("\u{1F1EB}\u{1F1F7}"), Some("fr"));
+        assert_eq!(flag_language("\u{1F1E7}\u{1F1F7}"), Some("pt"));
+        assert_eq!(flag_language("\u{1F1FF}\u{1F1FF}"), None);
+        assert_eq!(flag_language("fr"), None);
+
+        // Every country letter pair, so no mapped language is missing from LANGUAGES
+        for a in 0x1F1E6..=0x1F1FF {
+            for b in 0x1F1E6..=0x1F1FF {
+                let flag: String = [a, b].iter().filter_map(|c| char::from_u32(*c)).collect();
+                if let Some(language) = flag_language(&flag) {
+                    assert!(LANGUAGES.contains(&language), "{}", language);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+// Auto-responses and message forwarding
+// Triggers answer messages matching a pattern with a reply, a reaction or both, and can copy
+// them to another channel. Admins add them per guild with /trigger, patterns match the whole
+// message, a part of it or a regex, always ignoring case. Only the first matching reply is
+// sent, but every matching forward is. The `h` feature is a built-in trigger that works
+// everywhere unless a guild turns it off.
+use std::sync::Arc;
+
+use poise::serenity_prelude as serenity;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::{Feature, GuildCache},
+    db::Db,
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Most triggers a guild can have, every message is checked against all of them
+pub const MAX_TRIGGERS: usize = 50;
+// Keeps a single regex from taking a lot of memory, matching is linear time either way
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// How a trigger's pattern is matched
+#[derive(Clone, Copy, Debug, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum TriggerKind {
+    /// The whole message
+    #[name = "exact"]
+    Exact,
+    /// Anywhere in the message
+    #[name = "contains"]
+    Contains,
+    #[name = "regex"]
+    Regex,
+}
+
+#[derive(Clone)]
+enum Matcher {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Fails for invalid or too big regexes
+    fn new(kind: TriggerKind, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(match kind {
+            TriggerKind::Exact => Matcher::Exact(pattern.trim().to_lowercase()),
+            TriggerKind::Contains => Matcher::Contains(pattern.to_lowercase()),
+            TriggerKind::Regex => Matcher::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .build()?,
+            ),
+        })
+    }
+
+    fn matches(&self, content: &str) -> bool {
+        match self {
+            Matcher::Exact(pattern) => content.trim().to_lowercase() == *pattern,
+            Matcher::Contains(pattern) => content.to_lowercase().contains(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(content),
+        }
+    }
+}
+
+/// A trigger ready to be matched against messages
+#[derive(Clone)]
+pub struct CompiledTrigger {
+    matcher: Matcher,
+    pub response: Option<String>,
+    pub reaction: Option<serenity::ReactionType>,
+    pub forward: Option<serenity::ChannelId>,
+}
+
+impl CompiledTrigger {
+    /// Fails for invalid or too big regexes
+    pub fn new(
+        kind: TriggerKind,
+        pattern: &str,
+        response: Option<String>,
+        reaction: Option<serenity::ReactionType>,
+        forward: Option<serenity::ChannelId>,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            matcher: Matcher::new(kind, pattern)?,
+            response,
+            reaction,
+            forward,
+        })
+    }
+
+    // The reply the bot has always had
+    fn h() -> Self {
+        Self {
+            matcher: Matcher::Exact("h".to_string()),
+            response: Some("h".to_string()),
+            reaction: Some(serenity::ReactionType::Unicode("🇭".to_string())),
+            forward: None,
+        }
+    }
+
+    fn replies(&self) -> bool {
+        self.response.is_some() || self.reaction.is_some()
+    }
+}
+
+/// What to do with a message
+#[derive(Default)]
+pub struct Matches {
+    /// The trigger to answer with
+    pub reply: Option<CompiledTrigger>,
+    /// Channels to copy the message to
+    pub forwards: Vec<serenity::ChannelId>,
+}
+
+/// Checks a pattern before it's stored, returns why it can't be used
+pub fn validate(kind: TriggerKind, pattern: &str) -> Result<(), String> {
+    Matcher::new(kind, pattern)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Triggers per guild, loaded from the database on first use
+pub struct Triggers {
+    db: Db,
+    cache: GuildCache<Vec<CompiledTrigger>>,
+}
+
+impl Triggers {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            cache: GuildCache::default(),
+        }
+    }
+
+    async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Vec<CompiledTrigger>>, Error> {
+        self.cache
+            .get_or_load(guild, async {
+                // Triggers that stopped compiling, e.g. after a regex crate update, are skipped
+                Ok(self
+                    .db
+                    .triggers(guild)
+                    .await?
+                    .into_iter()
+                    .filter_map(|t| {
+                        CompiledTrigger::new(
+                            t.kind.parse().ok()?,
+                            &t.pattern,
+                            t.response,
+                            t.reaction
+                                .and_then(|r| serenity::ReactionType::try_from(r.as_str()).ok()),
+                            t.forward_channel_id.map(|c| serenity::ChannelId(c as u64)),
+                        )
+                        .ok()
+                    })
+                    .collect())
+            })
+            .await
+    }
+
+    /// Adds a trigger and returns its ID, the pattern has to pass `validate`
+    pub async fn add(
+        &self,
+        guild: serenity::GuildId,
+        kind: TriggerKind,
+        pattern: &str,
+        response: Option<&str>,
+        reaction: Option<&str>,
+        forward: Option<serenity::ChannelId>,
+    ) -> Result<i64, Error> {
+        let id = self
+            .db
+            .add_trigger(guild, kind.name(), pattern, response, reaction, forward)
+            .await?;
+        self.cache.invalidate(guild);
+        Ok(id)
+    }
+
+    /// Removes a trigger, returns false if it didn't exist
+    pub async fn remove(&self, guild: serenity::GuildId, id: i64) -> Result<bool, Error> {
+        let removed = self.db.delete_trigger(guild, id).await?;
+        self.cache.invalidate(guild);
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached triggers, returns how many guilds had them cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+}
+
+/// Finds the triggers a message matches, a guild's own triggers go before the built-in one
+pub async fn evaluate(data: &Data, message: &serenity::Message) -> Result<Matches, Error> {
+    let h = CompiledTrigger::h();
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        // Only the built-in trigger works in DMs
+        None => {
+            return Ok(Matches {
+                reply: h.matcher.matches(&message.content).then_some(h),
+                forwards: Vec::new(),
+            })
+        }
+    };
+
+    let triggers = data.triggers.get(guild).await?;
+    let mut matches = match_triggers(&triggers, message.channel_id, &message.content);
+    if matches.reply.is_none()
+        && h.matcher.matches(&message.content)
+        && data
+            .guild_configs
+            .get(guild)
+            .await?
+            .is_enabled(Feature::HReply)
+    {
+        matches.reply = Some(h);
+    }
+
+    Ok(matches)
+}
+
+/// Matches a message sent in `channel` against a guild's triggers, the first one that replies
+/// is the reply
+pub fn match_triggers(
+    triggers: &[CompiledTrigger],
+    channel: serenity::ChannelId,
+    content: &str,
+) -> Matches {
+    let mut matches = Matches::default();
+    for trigger in triggers {
+        if !trigger.matcher.matches(content) {
+            continue;
+        }
+
+        // Forwarding a message into the channel it came from would only duplicate it
+        if let Some(forward) = trigger.forward.filter(|c| *c != channel) {
+            if !matches.forwards.contains(&forward) {
+                matches.forwards.push(forward);
+            }
+        }
+        if matches.reply.is_none() && trigger.replies() {
+            matches.reply = Some(trigger.clone());
+        }
+    }
+
+    matches
+}
+
+/// Copies a message to a forwarding channel, with a link back to it
+pub async fn forward(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    message: &serenity::Message,
+) -> Result<(), Error> {
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.author(|a| a.name(message.author.tag()).icon_url(message.author.face()))
+                .field(
+                    "Source",
+                    format!(
+                        "<#{}> [Jump to message]({})",
+                        message.channel_id.0,
+                        message.link()
+                    ),
+                    false,
+                )
+                .footer(|f| f.text(message.id.0))
+                .timestamp(message.timestamp);
+
+            // Discord rejects empty descriptions, e.g. for messages that are only an image
+            if !message.content.is_empty() {
+                e.description(&message.content);
+            }
+
+            e
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(kind: TriggerKind, pattern: &str, response: &str, forward: u64) -> CompiledTrigger {
+        let response = Some(response.to_string()).filter(|r| !r.is_empty());
+        let forward = Some(serenity::ChannelId(forward)).filter(|c| c.0 != 0);
+        CompiledTrigger::new(kind, pattern, response, None, forward).unwrap()
+    }
+
+    #[test]
+    fn first_reply_wins_and_every_forward_counts() {
+        let triggers = [
+            trigger(TriggerKind::Contains, "deploy", "", 10),
+            trigger(TriggerKind::Regex, r"dep\w+", "first", 0),
+            trigger(TriggerKind::Contains, "DEPLOY", "second", 10),
+            trigger(TriggerKind::Exact, "deploy", "", 11),
+        ];
+
+        let matches = match_triggers(&triggers, serenity::ChannelId(1), "Deploy is done");
+        assert_eq!(matches.reply.unwrap().response.as_deref(), Some("first"));
+        assert_eq!(matches.forwards, [serenity::ChannelId(10)]);
+    }
+
+    #[test]
+    fn never_forwards_into_the_same_channel() {
+        let triggers = [trigger(TriggerKind::Exact, " hello ", "", 5)];
+
+        let matches = match_triggers(&triggers, serenity::ChannelId(5), "HELLO");
+        assert!(matches.reply.is_none());
+        assert!(matches.forwards.is_empty());
+        assert_eq!(
+            match_triggers(&triggers, serenity::ChannelId(6), "hello").forwards,
+            [serenity::ChannelId(5)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_regexes() {
+        assert!(validate(TriggerKind::Regex, "(unclosed").is_err());
+        assert!(validate(TriggerKind::Contains, "(unclosed").is_ok());
+    }
+}
@@ -0,0 +1,413 @@
+// Vote to delete
+// Servers can let members moderate each other: once enough members react to a message with the
+// vote emoji (🗑️ unless set otherwise), it's deleted right away or sent to the review channel
+// where staff delete or dismiss it. Only votes of members with a vote_delete role count if that
+// list has any, and messages of staff and automod-exempt members can't be voted on. Who voted
+// is stored and shown with every vote, along with how many of their votes staff dismissed
+// before, so members brigading someone stand out.
+use std::collections::HashMap;
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::{ChannelMode, GuildConfig, RoleList, VoteAction},
+    incidents::{self, EventKind},
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Highest threshold, reactions are fetched in one page of this many users
+pub const MAX_THRESHOLD: u32 = 100;
+const BUTTON_PREFIX: &str = "votedelete";
+// Embed field values can't be longer than this
+const FIELD_LIMIT: usize = 1024;
+
+/// Parses the vote emoji setting, a unicode emoji or a custom one like `<:name:id>`
+pub fn parse_emoji(input: &str) -> Option<serenity::ReactionType> {
+    let input = input.trim();
+    if input.starts_with('<') {
+        return serenity::ReactionType::try_from(input).ok();
+    }
+    if input.is_empty()
+        || input.contains(char::is_whitespace)
+        || input.chars().any(|c| c.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+    Some(serenity::ReactionType::Unicode(input.to_string()))
+}
+
+/// Whether a reaction is the vote emoji, with or without a variation selector
+pub fn is_vote(setting: &str, emoji: &serenity::ReactionType) -> bool {
+    match (parse_emoji(setting), emoji) {
+        (Some(serenity::ReactionType::Unicode(a)), serenity::ReactionType::Unicode(b)) => {
+            a.trim_end_matches('\u{fe0f}') == b.trim_end_matches('\u{fe0f}')
+        }
+        (
+            Some(serenity::ReactionType::Custom { id: a, .. }),
+            serenity::ReactionType::Custom { id: b, .. },
+        ) => a == *b,
+        _ => false,
+    }
+}
+
+/// Mentions of the voters, with how many of their votes staff dismissed before
+pub fn format_voters(
+    voters: &[serenity::UserId],
+    dismissed: &HashMap<serenity::UserId, i64>,
+) -> String {
+    let mut list = String::new();
+    for voter in voters {
+        let line = match dismissed.get(voter) {
+            Some(count) => format!("<@{}> ({} dismissed before)\n", voter.0, count),
+            None => format!("<@{}>\n", voter.0),
+        };
+        if list.len() + line.len() + "...".len() > FIELD_LIMIT {
+            list.push_str("...");
+            break;
+        }
+        list.push_str(&line);
+    }
+    list.truncate(list.trim_end().len());
+    list
+}
+
+// Returns whether the button deletes and the vote deletion ID
+fn parse_button(custom_id: &str) -> Option<(bool, i64)> {
+    let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix('-')?;
+    let (action, id) = rest.split_once('-')?;
+    let delete = match action {
+        "delete" => true,
+        "dismiss" => false,
+        _ => return None,
+    };
+
+    Some((delete, id.parse().ok()?))
+}
+
+// Staff and automod-exempt members can't be voted on, so votes can't be used against moderators
+async fn is_protected(
+    ctx: &serenity::Context,
+    config: &GuildConfig,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+) -> bool {
+    let roles = match guild.member(ctx, user).await {
+        Ok(member) => member.roles,
+        Err(_) => return false,
+    };
+    config.staff_role.is_some_and(|r| roles.contains(&r))
+        || config.has_role_in(RoleList::AutomodExempt, &roles)
+}
+
+/// Counts the votes on a message that got the vote emoji, deleting it or sending it to review
+/// once there are enough
+pub async fn handle_reaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let guild = match reaction.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+    let config = data.guild_configs.get(guild).await?;
+    let threshold = match config.vote_delete_threshold {
+        Some(threshold) => threshold as usize,
+        None => return Ok(()),
+    };
+    if !is_vote(config.vote_delete_emoji(), &reaction.emoji)
+        || config.channel_modes.get(&reaction.channel_id) == Some(&ChannelMode::Ignored)
+    {
+        return Ok(());
+    }
+
+    let message = reaction.message(&ctx.http).await?;
+    let count = message
+        .reactions
+        .iter()
+        .find(|r| r.reaction_type == reaction.emoji)
+        .map(|r| r.count)
+        .unwrap_or(0);
+    if message.author.bot || (count as usize) < threshold {
+        return Ok(());
+    }
+    if is_protected(ctx, &config, guild, message.author.id).await {
+        return Ok(());
+    }
+
+    let voters = message
+        .reaction_users(
+            &ctx.http,
+            reaction.emoji.clone(),
+            Some(MAX_THRESHOLD as u8),
+            None,
+        )
+        .await?;
+    let eligible = config
+        .role_lists
+        .get(&RoleList::VoteDelete)
+        .filter(|l| !l.is_empty());
+    let mut counted = Vec::new();
+    for voter in voters {
+        if voter.bot || voter.id == message.author.id {
+            continue;
+        }
+        if let Some(eligible) = eligible {
+            let roles = match guild.member(ctx, voter.id).await {
+                Ok(member) => member.roles,
+                Err(_) => continue,
+            };
+            if !roles.iter().any(|r| eligible.contains(r)) {
+                continue;
+            }
+        }
+        counted.push(voter.id);
+    }
+    if counted.len() < threshold {
+        return Ok(());
+    }
+
+    let action = config.vote_delete_action();
+    let status = match action {
+        VoteAction::Review => "pending",
+        VoteAction::Delete => "deleted",
+    };
+    let id = match data
+        .db
+        .add_vote_deletion(&message, guild, &counted, status)
+        .await?
+    {
+        Some(id) => id,
+        // Another vote got there first
+        None => return Ok(()),
+    };
+    if action == VoteAction::Delete {
+        message.delete(ctx).await?;
+    }
+
+    incidents::record(
+        data,
+        guild,
+        EventKind::Flag,
+        &format!(
+            "Message by {} in <#{}> voted to be deleted by {} members: {}",
+            message.author.tag(),
+            message.channel_id.0,
+            counted.len(),
+            message.link()
+        ),
+    )
+    .await;
+
+    let channel = match action {
+        VoteAction::Review => config.review_channel(),
+        VoteAction::Delete => config.log_channel,
+    };
+    let channel = match channel {
+        Some(channel) => channel,
+        None => return Ok(()),
+    };
+    let dismissed = data.db.dismissed_votes(guild).await?;
+    let mut content = message.content.clone();
+    if content.chars().count() > FIELD_LIMIT {
+        content = content.chars().take(FIELD_LIMIT - 3).collect::<String>() + "...";
+    }
+
+    let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(match action {
+                VoteAction::Review => "Message voted to be deleted",
+                VoteAction::Delete => "Message deleted by vote",
+            })
+            .author(|a| a.name(message.author.tag()).icon_url(message.author.face()))
+            .field(
+                "Content",
+                if content.is_empty() {
+                    "*No text*"
+                } else {
+                    &content
+                },
+                false,
+            )
+            .field("Channel", format!("<#{}>", message.channel_id.0), true)
+            .field("Votes", counted.len(), true)
+            .field("Voters", format_voters(&counted, &dismissed), false)
+            .footer(|f| f.text(format!("User ID: {}", message.author.id.0)))
+            .timestamp(serenity::Timestamp::now());
+            if action == VoteAction::Review {
+                e.url(message.link());
+            }
+            e
+        });
+        if action == VoteAction::Review {
+            m.components(|c| {
+                c.create_action_row(|r| {
+                    r.create_button(|b| {
+                        b.custom_id(format!("{}-delete-{}", BUTTON_PREFIX, id))
+                            .label("Delete")
+                            .style(serenity::ButtonStyle::Danger)
+                    })
+                    .create_button(|b| {
+                        b.custom_id(format!("{}-dismiss-{}", BUTTON_PREFIX, id))
+                            .label("Dismiss")
+                            .style(serenity::ButtonStyle::Secondary)
+                    })
+                })
+            });
+        }
+        m
+    })
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!(guild = guild.0, "Error posting vote to delete: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Deletes or dismisses a message voted to be deleted when staff click one of its buttons
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+) -> Result<(), Error> {
+    let (delete, id) = match parse_button(&interaction.data.custom_id) {
+        Some(button) => button,
+        None => return Ok(()),
+    };
+
+    match review(ctx, data, interaction, delete, id).await? {
+        Ok(outcome) => {
+            let mut embed = interaction
+                .message
+                .embeds
+                .first()
+                .cloned()
+                .map(serenity::CreateEmbed::from)
+                .unwrap_or_default();
+            embed.field("Outcome", outcome, false);
+
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.kind(serenity::InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+                })
+                .await?;
+        }
+        Err(refusal) => {
+            interaction
+                .create_interaction_response(ctx, |r| {
+                    r.interaction_response_data(|d| d.content(refusal).ephemeral(true))
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the outcome to show on the review message, or why the reviewer can't do this
+async fn review(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::MessageComponentInteraction,
+    delete: bool,
+    id: i64,
+) -> Result<Result<String, String>, Error> {
+    let (guild, reviewer) = match (interaction.guild_id, &interaction.member) {
+        (Some(guild), Some(member)) => (guild, member),
+        _ => {
+            return Ok(Err(
+                ":x: Votes can only be reviewed in a server.".to_string()
+            ))
+        }
+    };
+    if !reviewer.permissions.is_some_and(|p| p.manage_messages()) {
+        return Ok(Err(
+            ":x: You need the Manage Messages permission to review votes.".to_string(),
+        ));
+    }
+
+    let deletion = match data.db.vote_deletion(id).await? {
+        Some(deletion) if deletion.guild_id as u64 == guild.0 => deletion,
+        _ => return Ok(Err(":x: That vote doesn't exist anymore.".to_string())),
+    };
+    if deletion.status != "pending" {
+        return Ok(Err(format!(
+            ":x: That message was already {}.",
+            deletion.status
+        )));
+    }
+
+    if delete {
+        let result = serenity::ChannelId(deletion.channel_id as u64)
+            .delete_message(&ctx.http, deletion.message_id as u64)
+            .await;
+        match result {
+            Ok(()) => {}
+            // Deleted by its author or another moderator, which is what we wanted anyway
+            Err(serenity::Error::Http(e))
+                if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if !data
+        .db
+        .review_vote_deletion(id, delete, reviewer.user.id)
+        .await?
+    {
+        return Ok(Err(
+            ":x: Someone else reviewed that vote just now.".to_string()
+        ));
+    }
+
+    tracing::info!(
+        moderator = %reviewer.user.tag(),
+        guild = guild.0,
+        author = deletion.author_id,
+        "Vote to delete {}",
+        if delete { "accepted" } else { "dismissed" }
+    );
+
+    Ok(Ok(format!(
+        "{} by <@{}>",
+        if delete { "Deleted" } else { "Dismissed" },
+        reviewer.user.id.0
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn votes_match_with_or_without_variation_selectors() {
+        let unicode = |e: &str| serenity::ReactionType::Unicode(e.to_string());
+
+        assert!(is_vote("🗑️", &unicode("🗑️")));
+        assert!(is_vote("🗑️", &unicode("🗑")));
+        assert!(!is_vote("🗑️", &unicode("⭐")));
+        assert!(is_vote("<:trash:123>", &parse_emoji("<:bin:123>").unwrap()));
+        assert!(!is_vote("<:trash:123>", &unicode("🗑️")));
+
+        assert!(parse_emoji("trash").is_none());
+        assert!(parse_emoji("🗑️ ⭐").is_none());
+    }
+
+    #[test]
+    fn buttons_and_voters_are_read_back() {
+        assert_eq!(parse_button("votedelete-delete-12"), Some((true, 12)));
+        assert_eq!(parse_button("votedelete-dismiss-3"), Some((false, 3)));
+        assert_eq!(parse_button("votedelete-ban-3"), None);
+        assert_eq!(parse_button("application-accept-3"), None);
+
+        let voters = [serenity::UserId(1), serenity::UserId(2)];
+        let dismissed = HashMap::from([(serenity::UserId(2), 4)]);
+        assert_eq!(
+            format_voters(&voters, &dismissed),
+            "<@1>\n<@2> (4 dismissed before)"
+        );
+    }
+}
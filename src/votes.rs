@@ -0,0 +1,426 @@
+// top.gg votes
+// With TOPGG_WEBHOOK_SECRET set, top.gg can post votes for the bot to `/topgg/vote`, with the
+// secret as the webhook's authorization. The endpoint has to be public, so it's served on
+// VOTE_ADDR when that's set, and only falls back to the METRICS_ADDR server otherwise, which
+// would make `/metrics` public too. Every vote is recorded, and for PERK_DURATION after voting a
+// member gets DAILY_BONUS more coins from /daily and the premium tier of quotas. With
+// VOTE_CHANNEL_ID set, the month's top voters are posted there when the next month starts.
+use std::{convert::Infallible, env, net::SocketAddr, sync::Arc, time::Duration};
+
+use hyper::{
+    body::HttpBody,
+    header::{AUTHORIZATION, CONTENT_LENGTH},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    db::Db,
+    mentions::{self, Mentions},
+    watchdog, Error,
+};
+
+/// How long the perks of a vote last, top.gg lets users vote again after this
+pub const PERK_DURATION: Duration = Duration::from_secs(12 * 60 * 60);
+/// Extra coins voters get from /daily
+pub const DAILY_BONUS: i64 = 50;
+// How often we check whether a month ended
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const LEADERBOARD_SIZE: u32 = 10;
+const DAY_SECS: i64 = 24 * 60 * 60;
+// A vote is a few hundred bytes, anything much bigger isn't from top.gg
+const MAX_BODY: usize = 4 * 1024;
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A vote as top.gg posts it
+#[derive(Debug, PartialEq)]
+pub struct Vote {
+    pub user: serenity::UserId,
+    /// Sent with "Send test" on top.gg, not a real vote
+    pub test: bool,
+}
+
+/// Reads a vote out of a webhook body
+pub fn parse(body: &Value) -> Option<Vote> {
+    let user = serenity::UserId(body["user"].as_str()?.parse().ok()?);
+    let test = match body["type"].as_str()? {
+        "upvote" => false,
+        "test" => true,
+        _ => return None,
+    };
+
+    Some(Vote { user, test })
+}
+
+// Year, month and day of a day counted since the unix epoch, from Howard Hinnant's algorithms
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The unix timestamp the month `now` is in started at, in UTC
+pub fn month_start(now: i64) -> i64 {
+    let (year, month, _) = civil_from_days(now.div_euclid(DAY_SECS));
+    days_from_civil(year, month, 1) * DAY_SECS
+}
+
+/// The month starting at `start`, like "January 2024"
+pub fn month_name(start: i64) -> String {
+    let (year, month, _) = civil_from_days(start.div_euclid(DAY_SECS));
+    format!("{} {}", MONTHS[month as usize - 1], year)
+}
+
+/// Until when the user's vote perks last, None if they didn't vote recently
+pub async fn perks_until(db: &Db, user: serenity::UserId) -> Result<Option<i64>, Error> {
+    let now = serenity::Timestamp::now().unix_timestamp();
+    Ok(db
+        .last_vote(user)
+        .await?
+        .map(|voted_at| voted_at + PERK_DURATION.as_secs() as i64)
+        .filter(|until| *until > now))
+}
+
+pub struct VoteWebhook {
+    secret: String,
+    db: Db,
+}
+
+impl VoteWebhook {
+    /// Accepts votes if TOPGG_WEBHOOK_SECRET is set to something, an empty secret would let
+    /// anyone in
+    pub fn from_env(db: Db) -> Option<Arc<Self>> {
+        Some(Arc::new(Self {
+            secret: env::var("TOPGG_WEBHOOK_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())?,
+            db,
+        }))
+    }
+
+    /// Records the vote of a webhook request
+    pub async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        // Compared in constant time so the secret can't be guessed from response times
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .is_some_and(|value| bool::from(value.as_bytes().ct_eq(self.secret.as_bytes())));
+        if !authorized {
+            return status(StatusCode::UNAUTHORIZED);
+        }
+
+        let length = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+        if length.is_some_and(|length| length > MAX_BODY) {
+            return status(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        let vote = match read_body(request.into_body()).await {
+            Ok(Some(body)) => serde_json::from_slice(&body).ok().and_then(|v| parse(&v)),
+            Ok(None) => return status(StatusCode::PAYLOAD_TOO_LARGE),
+            Err(e) => {
+                tracing::warn!("Error reading vote webhook: {}", e);
+                return status(StatusCode::BAD_REQUEST);
+            }
+        };
+
+        match vote {
+            None => status(StatusCode::BAD_REQUEST),
+            Some(Vote { user, test: true }) => {
+                tracing::info!(user = user.0, "Received test vote");
+                status(StatusCode::NO_CONTENT)
+            }
+            Some(Vote { user, .. }) => {
+                let now = serenity::Timestamp::now().unix_timestamp();
+                match self.db.add_vote(user, now).await {
+                    Ok(()) => {
+                        tracing::info!(user = user.0, "Received vote");
+                        status(StatusCode::NO_CONTENT)
+                    }
+                    // top.gg sends the vote again when it isn't accepted
+                    Err(e) => {
+                        tracing::warn!(user = user.0, "Error recording vote: {}", e);
+                        status(StatusCode::INTERNAL_SERVER_ERROR)
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Reads a body of at most MAX_BODY bytes, None if it's longer. Chunked bodies have no length
+// to check up front
+async fn read_body(mut body: Body) -> Result<Option<Vec<u8>>, hyper::Error> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > MAX_BODY {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(bytes))
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// Serves the webhook on VOTE_ADDR, returns false if that isn't set so the metrics server
+/// should serve it instead
+pub fn spawn_server(webhook: &Arc<VoteWebhook>) -> bool {
+    let addr: SocketAddr = match env::var("VOTE_ADDR").map(|addr| addr.parse()) {
+        Ok(Ok(addr)) => addr,
+        Ok(Err(e)) => {
+            // Not served at all rather than on the metrics server the owner wanted to avoid
+            tracing::warn!("Ignoring invalid VOTE_ADDR, not receiving votes: {}", e);
+            return true;
+        }
+        Err(_) => return false,
+    };
+
+    let server = match Server::try_bind(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            tracing::error!(%addr, "Error starting vote webhook server: {}", e);
+            return true;
+        }
+    };
+
+    let webhook = Arc::clone(webhook);
+    let make_service = make_service_fn(move |_| {
+        let webhook = Arc::clone(&webhook);
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let webhook = Arc::clone(&webhook);
+
+                async move {
+                    if request.method() != Method::POST || request.uri().path() != "/topgg/vote" {
+                        return Ok::<_, Infallible>(status(StatusCode::NOT_FOUND));
+                    }
+                    Ok(webhook.handle(request).await)
+                }
+            }))
+        }
+    });
+
+    tracing::info!(%addr, "Receiving top.gg votes");
+    watchdog::spawn("vote webhook server", async move {
+        if let Err(e) = server.serve(make_service).await {
+            tracing::error!("Vote webhook server stopped: {}", e);
+        }
+    });
+
+    true
+}
+
+/// Starts the task that posts the monthly leaderboards if VOTE_CHANNEL_ID is set
+pub fn spawn_leaderboard(ctx: serenity::Context, db: Db) {
+    let channel = match env::var("VOTE_CHANNEL_ID").map(|id| id.parse::<u64>()) {
+        Ok(Ok(id)) => serenity::ChannelId(id),
+        Ok(Err(e)) => {
+            tracing::warn!("Ignoring invalid VOTE_CHANNEL_ID: {}", e);
+            return;
+        }
+        Err(_) => return,
+    };
+
+    watchdog::spawn("vote leaderboard", async move {
+        loop {
+            if let Err(e) = post_leaderboard(&ctx, &db, channel).await {
+                tracing::warn!("Error posting vote leaderboard: {}", e);
+            }
+
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// Posts the leaderboard of the month that ended last, unless it was already
+async fn post_leaderboard(
+    ctx: &serenity::Context,
+    db: &Db,
+    channel: serenity::ChannelId,
+) -> Result<(), Error> {
+    let until = month_start(serenity::Timestamp::now().unix_timestamp());
+    let since = month_start(until - 1);
+    // Not retried, like digests a missed leaderboard isn't worth posting late
+    if !db.claim_vote_leaderboard(since).await? {
+        return Ok(());
+    }
+
+    let voters = db.top_voters(since, until, LEADERBOARD_SIZE).await?;
+    if voters.is_empty() {
+        return Ok(());
+    }
+    let lines = voters
+        .iter()
+        .enumerate()
+        .map(|(i, (user, votes))| format!("{}. <@{}>: {} votes", i + 1, user.0, votes))
+        .collect::<Vec<_>>();
+
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.embed(|e| {
+            e.title(format!("Top voters of {}", month_name(since)))
+                .description(lines.join("\n"))
+                .footer(|f| f.text("Thank you for voting!"))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn votes_are_read_from_webhooks() {
+        let vote = json!({
+            "bot": "1",
+            "user": "123456789012345678",
+            "type": "upvote",
+            "isWeekend": false,
+            "query": ""
+        });
+        assert_eq!(
+            parse(&vote),
+            Some(Vote {
+                user: serenity::UserId(123456789012345678),
+                test: false
+            })
+        );
+        assert!(parse(&json!({ "user": "1", "type": "test" })).unwrap().test);
+        assert_eq!(parse(&json!({ "user": "me", "type": "upvote" })), None);
+        assert_eq!(parse(&json!({ "user": "1", "type": "downvote" })), None);
+    }
+
+    #[tokio::test]
+    async fn webhooks_need_the_secret_and_a_small_body() {
+        let webhook = VoteWebhook {
+            secret: "secret".to_string(),
+            db: Db::memory().await,
+        };
+        let request = |authorization: Option<&str>, body: Vec<u8>| {
+            let mut request = Request::post("/topgg/vote");
+            if let Some(authorization) = authorization {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+            request.body(Body::from(body)).unwrap()
+        };
+        let test_vote = br#"{"user": "1", "type": "test"}"#.to_vec();
+
+        for authorization in [None, Some(""), Some("secre"), Some("secret2")] {
+            let response = webhook
+                .handle(request(authorization, test_vote.clone()))
+                .await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let response = webhook.handle(request(Some("secret"), test_vote)).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = webhook
+            .handle(request(Some("secret"), vec![b' '; MAX_BODY + 1]))
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Without a length to check up front
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for _ in 0..=MAX_BODY / 1024 {
+                let _ = sender.send_data(vec![b' '; 1024].into()).await;
+            }
+        });
+        let mut chunked = request(Some("secret"), Vec::new());
+        *chunked.body_mut() = body;
+        assert_eq!(
+            webhook.handle(chunked).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn months_start_on_the_first_at_midnight_utc() {
+        // 2024-01-02 15:30 UTC
+        let now = 1_704_209_400;
+        let january = 1_704_067_200;
+        assert_eq!(month_start(now), january);
+        assert_eq!(month_start(january), january);
+        // 2023-12-01
+        assert_eq!(month_start(january - 1), 1_701_388_800);
+        // 2024-03-15, after a leap day
+        assert_eq!(month_start(1_710_460_800), 1_709_251_200);
+        assert_eq!(month_start(0), 0);
+
+        assert_eq!(month_name(january), "January 2024");
+        assert_eq!(month_name(1_701_388_800), "December 2023");
+    }
+
+    #[tokio::test]
+    async fn voters_are_ranked_by_votes_in_the_month() {
+        let db = Db::memory().await;
+        let (a, b, c) = (
+            serenity::UserId(1),
+            serenity::UserId(2),
+            serenity::UserId(3),
+        );
+
+        for (user, at) in [(a, 100), (b, 110), (b, 150), (c, 120), (a, 300)] {
+            db.add_vote(user, at).await.unwrap();
+        }
+
+        assert_eq!(
+            db.top_voters(100, 200, 10).await.unwrap(),
+            [(b, 2), (a, 1), (c, 1)]
+        );
+        assert_eq!(db.top_voters(100, 200, 1).await.unwrap(), [(b, 2)]);
+        assert_eq!(db.last_vote(a).await.unwrap(), Some(300));
+        assert_eq!(db.last_vote(serenity::UserId(4)).await.unwrap(), None);
+
+        assert!(db.claim_vote_leaderboard(100).await.unwrap());
+        assert!(!db.claim_vote_leaderboard(100).await.unwrap());
+    }
+}
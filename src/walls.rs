@@ -0,0 +1,205 @@
+// Emoji-only channels and reaction walls
+// Channels in either mode only allow emoji, stickers and GIFs, anything else is deleted by
+// the message pipeline. Reaction walls also count the reactions their messages get and post
+// the most used ones at the start of every week.
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::ChannelMode,
+    counters::Counters,
+    db::{Db, WallStat},
+    mentions::{self, Mentions},
+    watchdog, Data, Error,
+};
+
+// How often we check whether a week is over
+const REPORT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const WEEK_SECS: i64 = 7 * 24 * 60 * 60;
+// Number of emoji shown in a weekly report
+const REPORT_SIZE: usize = 10;
+
+/// Whether a message only contains emoji, stickers and GIFs
+pub fn is_emoji_only(message: &serenity::Message) -> bool {
+    let gifs_only = message.attachments.iter().all(|a| {
+        a.content_type.as_deref() == Some("image/gif")
+            || a.filename.to_lowercase().ends_with(".gif")
+    });
+    if !gifs_only {
+        return false;
+    }
+
+    message
+        .content
+        .split_whitespace()
+        .all(|word| is_gif_link(word) || is_emoji_text(word))
+}
+
+/// Counts a reaction if it was added in a reaction wall
+pub async fn record_reaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    reaction: &serenity::Reaction,
+) -> Result<(), Error> {
+    let guild = match reaction.guild_id {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+
+    if reaction.user_id == Some(ctx.cache.current_user_id()) {
+        return Ok(());
+    }
+
+    let config = data.guild_configs.get(guild).await?;
+    if config.channel_modes.get(&reaction.channel_id) != Some(&ChannelMode::ReactionWall) {
+        return Ok(());
+    }
+
+    data.counters
+        .record_wall_reaction(
+            guild,
+            reaction.channel_id,
+            current_week(),
+            &reaction.emoji.to_string(),
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Starts the task that posts the weekly reaction wall reports
+pub fn spawn(ctx: serenity::Context, db: Db, counters: Counters) {
+    watchdog::spawn("reaction walls", async move {
+        loop {
+            // Reactions from the end of last week may still be pending
+            if let Err(e) = counters.flush().await {
+                tracing::warn!("Error writing counters before reaction wall reports: {}", e);
+            } else if let Err(e) = post_reports(&ctx, &db).await {
+                tracing::warn!("Error posting reaction wall reports: {}", e);
+            }
+
+            tokio::time::sleep(REPORT_INTERVAL).await;
+        }
+    });
+}
+
+async fn post_reports(ctx: &serenity::Context, db: &Db) -> Result<(), Error> {
+    let mut walls: HashMap<(i64, i64), Vec<WallStat>> = HashMap::new();
+    for stat in db.take_wall_stats(current_week()).await? {
+        walls
+            .entry((stat.channel_id, stat.week))
+            .or_default()
+            .push(stat);
+    }
+
+    for mut stats in walls.into_values() {
+        let guild = serenity::GuildId(stats[0].guild_id as u64);
+        let channel = serenity::ChannelId(stats[0].channel_id as u64);
+        let week = stats[0].week;
+
+        // The channel may have been switched to another mode during the week
+        let config = db.load_guild_config(guild).await?;
+        if config.channel_modes.get(&channel) != Some(&ChannelMode::ReactionWall) {
+            continue;
+        }
+
+        stats.sort_by_key(|s| Reverse(s.count));
+        let total: i64 = stats.iter().map(|s| s.count).sum();
+        let top = stats
+            .iter()
+            .take(REPORT_SIZE)
+            .enumerate()
+            .map(|(i, s)| format!("**{}.** {} × {}", i + 1, s.emoji, s.count))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let result = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+            m.embed(|e| {
+                e.title("Reactions of the week")
+                    .description(top)
+                    .footer(|f| f.text(format!("{} reactions in total", total)))
+                    .timestamp(
+                        serenity::Timestamp::from_unix_timestamp(week * WEEK_SECS)
+                            .unwrap_or_else(|_| serenity::Timestamp::now()),
+                    )
+            })
+        })
+        .await;
+
+        // The counts are already gone, so a failed report is only logged
+        if let Err(e) = result {
+            tracing::warn!(
+                channel = channel.0,
+                "Error posting reaction wall report: {}",
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Weeks since the unix epoch
+fn current_week() -> i64 {
+    serenity::Timestamp::now().unix_timestamp() / WEEK_SECS
+}
+
+fn is_gif_link(word: &str) -> bool {
+    let word = word.trim_matches(|c| c == '<' || c == '>');
+    if !word.starts_with("https://") && !word.starts_with("http://") {
+        return false;
+    }
+
+    let host = word.split('/').nth(2).unwrap_or_default();
+    host.ends_with("tenor.com")
+        || host.ends_with("giphy.com")
+        || word.split('?').next().unwrap_or_default().ends_with(".gif")
+}
+
+// Custom emoji (<:name:id> and <a:name:id>) and unicode emoji, with no spaces in between
+fn is_emoji_text(word: &str) -> bool {
+    let mut rest = word;
+
+    while !rest.is_empty() {
+        if rest.starts_with("<:") || rest.starts_with("<a:") {
+            match rest.find('>') {
+                Some(end) => {
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+                None => return false,
+            }
+        }
+
+        let mut chars = rest.chars();
+        let c = chars.next().unwrap_or_default();
+        let next = chars.clone().next();
+
+        // Keycaps like 1️⃣ start with a plain digit, # or *
+        let keycap = (c.is_ascii_digit() || c == '#' || c == '*')
+            && matches!(next, Some('\u{FE0F}') | Some('\u{20E3}'));
+        if !keycap && !is_emoji_char(c) {
+            return false;
+        }
+
+        rest = chars.as_str();
+    }
+
+    true
+}
+
+fn is_emoji_char(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // pictographs, flags, emoticons, symbols
+        | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+        | 0x2300..=0x23FF // technical symbols like ⌚ and ⏰
+        | 0x2B00..=0x2BFF // arrows and shapes like ⭐
+        | 0x2190..=0x21FF // arrows
+        | 0x3030 | 0x303D | 0x3297 | 0x3299 | 0x00A9 | 0x00AE | 0x2122 | 0x2934 | 0x2935
+        | 0x200D // zero width joiner
+        | 0xFE0F // variation selector
+        | 0x20E3 // keycap
+        | 0xE0020..=0xE007F // tag sequences used by some flags
+    )
+}
@@ -0,0 +1,144 @@
+// Watchdog that measures how late the tokio runtime wakes up a sleeping task.
+// If something blocks a worker thread (heavy CPU work, sync IO) timers fire late,
+// so a large gap between the requested and actual sleep means the runtime is starved.
+// The sampler can't tell what blocked it, so background tasks and event handlers also run
+// through `spawn` and `watch`, which time every poll and name the task that held the thread.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tracing::Instrument;
+
+// How often the watchdog wakes up to take a sample
+const TICK: Duration = Duration::from_millis(500);
+// Lag above this is considered a stall and gets logged
+const STALL_THRESHOLD: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+pub struct LoopWatchdog {
+    last_lag_us: AtomicU64,
+    max_lag_us: AtomicU64,
+    stalls: AtomicU64,
+}
+
+impl LoopWatchdog {
+    /// Creates the watchdog and starts its sampling task
+    pub fn spawn() -> Arc<Self> {
+        let watchdog = Arc::new(Self::default());
+        let watchdog_clone = Arc::clone(&watchdog);
+
+        tokio::spawn(async move {
+            loop {
+                let start = Instant::now();
+                tokio::time::sleep(TICK).await;
+                let lag = start.elapsed().saturating_sub(TICK);
+
+                watchdog_clone.record(lag);
+            }
+        });
+
+        watchdog
+    }
+
+    fn record(&self, lag: Duration) {
+        let lag_us = lag.as_micros() as u64;
+        self.last_lag_us.store(lag_us, Ordering::Relaxed);
+        self.max_lag_us.fetch_max(lag_us, Ordering::Relaxed);
+
+        if lag >= STALL_THRESHOLD {
+            let stalls = self.stalls.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::warn!(
+                "Event loop stalled for {}ms, something is blocking the runtime (stalls so far: {})",
+                lag.as_millis(),
+                stalls
+            );
+        }
+    }
+
+    /// Scheduling lag of the most recent sample
+    pub fn last_lag(&self) -> Duration {
+        Duration::from_micros(self.last_lag_us.load(Ordering::Relaxed))
+    }
+
+    /// Highest scheduling lag seen since startup
+    pub fn max_lag(&self) -> Duration {
+        Duration::from_micros(self.max_lag_us.load(Ordering::Relaxed))
+    }
+
+    /// Number of samples that exceeded the stall threshold
+    pub fn stalls(&self) -> u64 {
+        self.stalls.load(Ordering::Relaxed)
+    }
+}
+
+/// Spawns a background task in a span with its name, logging polls that block the runtime
+pub fn spawn<F>(name: &'static str, task: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(watch(name, task).instrument(tracing::info_span!("task", name)))
+}
+
+/// Logs polls of the future that take long enough to stall the runtime, naming `task`
+pub fn watch<F: Future>(task: &'static str, future: F) -> impl Future<Output = F::Output> {
+    Watched {
+        task,
+        future: Box::pin(future),
+    }
+}
+
+struct Watched<F> {
+    task: &'static str,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Watched<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let start = Instant::now();
+        let poll = self.future.as_mut().poll(cx);
+        let elapsed = start.elapsed();
+
+        // Nothing else could run on this worker thread in the meantime
+        if elapsed >= STALL_THRESHOLD {
+            tracing::warn!(
+                task = self.task,
+                "Task blocked the runtime for {}ms in a single poll",
+                elapsed.as_millis()
+            );
+        }
+
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_stalls() {
+        let watchdog = LoopWatchdog::default();
+        watchdog.record(Duration::from_millis(5));
+        watchdog.record(STALL_THRESHOLD * 2);
+        watchdog.record(Duration::from_millis(1));
+
+        assert_eq!(watchdog.stalls(), 1);
+        assert_eq!(watchdog.max_lag(), STALL_THRESHOLD * 2);
+        assert_eq!(watchdog.last_lag(), Duration::from_millis(1));
+    }
+
+    #[tokio::test]
+    async fn watched_futures_keep_their_output() {
+        assert_eq!(watch("test", async { 42 }).await, 42);
+    }
+}
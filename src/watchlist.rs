@@ -0,0 +1,345 @@
+// Watchlist
+// Moderators put borderline members on the watchlist with /watchlist add. Their messages, joins
+// and voice channel changes are collected in memory and summarized to the watch channel, one
+// embed per member every WATCH_INTERVAL at most, so a chatty member can't flood it. Only the
+// first few messages and voice changes of an interval are quoted, the rest are counted.
+// Activity that wasn't posted yet is lost if the bot stops.
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    config::GuildCache,
+    db::Db,
+    mentions::{self, Mentions},
+    watchdog, Error,
+};
+
+/// Longest reason a member can be watched for
+pub const MAX_REASON_LENGTH: usize = 200;
+// How often summaries are posted, also the most one member gets
+const WATCH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Messages and voice changes quoted per summary
+const MAX_QUOTES: usize = 5;
+// Characters quoted of each message
+const QUOTE_LENGTH: usize = 100;
+
+/// What a watched member did since their last summary
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Activity {
+    /// Messages sent per channel
+    pub messages: BTreeMap<serenity::ChannelId, u32>,
+    pub quotes: Vec<String>,
+    pub joins: u32,
+    pub voice: Vec<String>,
+}
+
+impl Activity {
+    fn quote(&mut self, channel: serenity::ChannelId, content: &str) {
+        *self.messages.entry(channel).or_default() += 1;
+        if self.quotes.len() >= MAX_QUOTES {
+            return;
+        }
+
+        let mut quote: String = content.chars().take(QUOTE_LENGTH).collect();
+        if quote.len() < content.len() {
+            quote.push_str("...");
+        }
+        let quote = quote.replace('\n', " ").replace('`', "'");
+        self.quotes.push(format!("<#{}>: `{}`", channel.0, quote));
+    }
+
+    fn voice(&mut self, change: String) {
+        if self.voice.len() < MAX_QUOTES {
+            self.voice.push(change);
+        }
+    }
+}
+
+/// Summary of an interval's activity, for an embed description
+pub fn summary(activity: &Activity) -> String {
+    let mut lines = Vec::new();
+
+    match activity.joins {
+        0 => {}
+        1 => lines.push("Joined the server".to_string()),
+        joins => lines.push(format!("Joined the server {} times", joins)),
+    }
+
+    let total: u32 = activity.messages.values().sum();
+    if total > 0 {
+        let channels = activity
+            .messages
+            .iter()
+            .map(|(channel, count)| format!("<#{}> ({})", channel.0, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!("**{} messages** in {}", total, channels));
+        lines.extend(activity.quotes.iter().cloned());
+        if total as usize > activity.quotes.len() {
+            lines.push(format!(
+                "*and {} more*",
+                total as usize - activity.quotes.len()
+            ));
+        }
+    }
+
+    if !activity.voice.is_empty() {
+        lines.push("**Voice**".to_string());
+        lines.extend(activity.voice.iter().cloned());
+    }
+
+    lines.join("\n")
+}
+
+// Watched members of a guild with the reason they're watched
+type Watched = HashMap<serenity::UserId, Option<String>>;
+
+/// Watched members per guild with the reason they're watched, and their pending activity
+#[derive(Clone)]
+pub struct Watchlist {
+    db: Db,
+    cache: Arc<GuildCache<Watched>>,
+    pending: Arc<Mutex<HashMap<(serenity::GuildId, serenity::UserId), Activity>>>,
+}
+
+impl Watchlist {
+    pub fn new(db: Db) -> Self {
+        Self {
+            db,
+            cache: Arc::default(),
+            pending: Arc::default(),
+        }
+    }
+
+    async fn get(&self, guild: serenity::GuildId) -> Result<Arc<Watched>, Error> {
+        self.cache
+            .get_or_load(guild, async {
+                Ok(self
+                    .db
+                    .watchlist(guild)
+                    .await?
+                    .into_iter()
+                    .map(|w| (serenity::UserId(w.user_id as u64), w.reason))
+                    .collect())
+            })
+            .await
+    }
+
+    /// Puts a user on the watchlist, returns false if they already were
+    pub async fn add(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        reason: Option<&str>,
+        moderator: serenity::UserId,
+    ) -> Result<bool, Error> {
+        let added = self.db.watch(guild, user, reason, moderator).await?;
+        self.cache.invalidate(guild);
+        Ok(added)
+    }
+
+    /// Takes a user off the watchlist and drops their pending activity
+    pub async fn remove(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+    ) -> Result<bool, Error> {
+        let removed = self.db.unwatch(guild, user).await?;
+        self.cache.invalidate(guild);
+        self.pending.lock().unwrap().remove(&(guild, user));
+        Ok(removed)
+    }
+
+    /// Drops every guild's cached watchlist, returns how many guilds had one cached
+    pub fn clear_cache(&self) -> usize {
+        self.cache.clear()
+    }
+
+    // Adds to a member's activity if they're watched
+    async fn record(
+        &self,
+        guild: serenity::GuildId,
+        user: serenity::UserId,
+        f: impl FnOnce(&mut Activity),
+    ) -> Result<(), Error> {
+        if !self.get(guild).await?.contains_key(&user) {
+            return Ok(());
+        }
+
+        f(self
+            .pending
+            .lock()
+            .unwrap()
+            .entry((guild, user))
+            .or_default());
+        Ok(())
+    }
+
+    /// Counts a message of a watched member
+    pub async fn handle_message(&self, message: &serenity::Message) -> Result<(), Error> {
+        let guild = match message.guild_id {
+            Some(guild) if !message.author.bot => guild,
+            _ => return Ok(()),
+        };
+
+        self.record(guild, message.author.id, |a| {
+            a.quote(message.channel_id, &message.content)
+        })
+        .await
+    }
+
+    /// Counts a watched member joining
+    pub async fn handle_join(&self, member: &serenity::Member) -> Result<(), Error> {
+        self.record(member.guild_id, member.user.id, |a| a.joins += 1)
+            .await
+    }
+
+    /// Notes a watched member joining, leaving or moving between voice channels
+    pub async fn handle_voice_state(
+        &self,
+        old: Option<&serenity::VoiceState>,
+        new: &serenity::VoiceState,
+    ) -> Result<(), Error> {
+        let guild = match new.guild_id {
+            Some(guild) => guild,
+            None => return Ok(()),
+        };
+
+        let now = serenity::Timestamp::now().unix_timestamp();
+        let change = match (old.and_then(|o| o.channel_id), new.channel_id) {
+            (None, Some(joined)) => format!("<t:{}:t> joined <#{}>", now, joined.0),
+            (Some(left), None) => format!("<t:{}:t> left <#{}>", now, left.0),
+            (Some(from), Some(to)) if from != to => {
+                format!("<t:{}:t> moved from <#{}> to <#{}>", now, from.0, to.0)
+            }
+            // Mutes and the like
+            _ => return Ok(()),
+        };
+
+        self.record(guild, new.user_id, |a| a.voice(change)).await
+    }
+
+    // Posts the summaries of everything collected since the last flush
+    async fn flush(&self, ctx: &serenity::Context) -> Result<(), Error> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        for ((guild, user), activity) in pending {
+            // Could have been taken off the list since
+            let reason = match self.get(guild).await?.get(&user) {
+                Some(reason) => reason.clone(),
+                None => continue,
+            };
+            let config = self.db.load_guild_config(guild).await?;
+            let channel = match config.watch_channel() {
+                Some(channel) => channel,
+                None => continue,
+            };
+
+            let sent = mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+                m.embed(|e| {
+                    e.title("Watchlist activity")
+                        .description(summary(&activity))
+                        .field("User", format!("<@{}>", user.0), true)
+                        .field(
+                            "Watched for",
+                            reason.as_deref().unwrap_or("No reason"),
+                            true,
+                        )
+                        .timestamp(serenity::Timestamp::now())
+                })
+            })
+            .await;
+
+            // One channel we can't post in shouldn't stop the other guilds
+            if let Err(e) = sent {
+                tracing::warn!(
+                    guild = guild.0,
+                    channel = channel.0,
+                    "Error posting watchlist summary: {}",
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn spawn(ctx: serenity::Context, watchlist: Watchlist) {
+    watchdog::spawn("watchlist", async move {
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            if let Err(e) = watchlist.flush(&ctx).await {
+                tracing::warn!("Error posting watchlist summaries: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_count_what_isnt_quoted() {
+        let (general, memes) = (serenity::ChannelId(1), serenity::ChannelId(2));
+        let mut activity = Activity::default();
+        for _ in 0..6 {
+            activity.quote(general, "hello");
+        }
+        activity.quote(memes, &"x".repeat(150));
+        activity.joins = 1;
+        activity.voice("joined <#3>".to_string());
+
+        assert_eq!(activity.quotes.len(), MAX_QUOTES);
+        assert_eq!(
+            summary(&activity),
+            "Joined the server\n\
+            **7 messages** in <#1> (6), <#2> (1)\n\
+            <#1>: `hello`\n<#1>: `hello`\n<#1>: `hello`\n<#1>: `hello`\n<#1>: `hello`\n\
+            *and 2 more*\n\
+            **Voice**\njoined <#3>"
+        );
+
+        let mut long = Activity::default();
+        long.quote(memes, &"x".repeat(150));
+        assert_eq!(long.quotes[0], format!("<#2>: `{}...`", "x".repeat(100)));
+    }
+
+    #[tokio::test]
+    async fn only_watched_members_are_recorded() {
+        let watchlist = Watchlist::new(Db::memory().await);
+        let guild = serenity::GuildId(1);
+        let (watched, other, moderator) = (
+            serenity::UserId(2),
+            serenity::UserId(3),
+            serenity::UserId(4),
+        );
+
+        assert!(watchlist
+            .add(guild, watched, Some("Spam"), moderator)
+            .await
+            .unwrap());
+        assert!(!watchlist
+            .add(guild, watched, None, moderator)
+            .await
+            .unwrap());
+        for user in [watched, other] {
+            watchlist
+                .record(guild, user, |a| a.joins += 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(watchlist.pending.lock().unwrap().len(), 1);
+
+        assert!(watchlist.remove(guild, watched).await.unwrap());
+        assert!(watchlist.pending.lock().unwrap().is_empty());
+        assert!(!watchlist.remove(guild, watched).await.unwrap());
+    }
+}
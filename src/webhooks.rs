@@ -0,0 +1,124 @@
+// Reposting messages in the name of their author
+// Features that replace a message (spoilers, cleaned links) delete it and post the new version
+// through a webhook with the author's name and avatar, so the conversation still reads the same.
+// Needs the Manage Webhooks permission, without it or in threads the bot posts the message.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::{self, Mentions},
+    Error,
+};
+
+const WEBHOOK_NAME: &str = "Reposts";
+/// Longest part `split` returns, leaves room for the author name in the bot fallback
+pub const MAX_PART_LENGTH: usize = 1950;
+
+/// Posts `parts` as separate messages in the name of the author of `message`
+/// Files go with the last part, or on their own if there are no parts
+pub async fn repost(
+    ctx: &serenity::Context,
+    message: &serenity::Message,
+    parts: Vec<String>,
+    files: Vec<serenity::AttachmentType<'static>>,
+) -> Result<(), Error> {
+    repost_to(ctx, message.channel_id, message, parts, files).await
+}
+
+/// Like `repost`, but into another channel
+pub async fn repost_to(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+    message: &serenity::Message,
+    parts: Vec<String>,
+    files: Vec<serenity::AttachmentType<'static>>,
+) -> Result<(), Error> {
+    let name = message
+        .member
+        .as_ref()
+        .and_then(|m| m.nick.clone())
+        .unwrap_or_else(|| message.author.name.clone());
+
+    let webhook = match webhook(ctx, channel).await {
+        Ok(webhook) => Some(webhook),
+        Err(e) => {
+            tracing::debug!(channel = channel.0, "No repost webhook: {}", e);
+            None
+        }
+    };
+
+    let last = parts.len().saturating_sub(1);
+    let mut files = Some(files);
+    for i in 0..parts.len().max(1) {
+        let content = parts.get(i).cloned().unwrap_or_default();
+        let files = if i == last {
+            files.take().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        match &webhook {
+            Some(webhook) => {
+                mentions::execute_webhook(&ctx.http, webhook, Mentions::Nothing, |w| {
+                    w.username(&name)
+                        .avatar_url(message.author.face())
+                        .content(content)
+                        .add_files(files)
+                })
+                .await?;
+            }
+            None => {
+                mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+                    m.content(format!("**{}**: {}", name, content))
+                        .add_files(files)
+                })
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits text into parts of at most `max` bytes, preferring to split at whitespace
+pub fn split(content: &str, max: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = content.trim();
+
+    while rest.len() > max {
+        let mut end = max;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = rest[..end]
+            .rfind(char::is_whitespace)
+            .filter(|&i| i > 0)
+            .unwrap_or(end);
+
+        parts.push(rest[..end].trim_end().to_string());
+        rest = rest[end..].trim_start();
+    }
+
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+
+    parts
+}
+
+// Our own webhook in the channel, created on first use
+async fn webhook(
+    ctx: &serenity::Context,
+    channel: serenity::ChannelId,
+) -> Result<serenity::Webhook, Error> {
+    let bot = ctx.cache.current_user_id();
+    let existing = channel
+        .webhooks(&ctx.http)
+        .await?
+        .into_iter()
+        .find(|w| w.token.is_some() && w.user.as_ref().map(|u| u.id) == Some(bot));
+
+    match existing {
+        Some(webhook) => Ok(webhook),
+        None => Ok(channel.create_webhook(&ctx.http, WEBHOOK_NAME).await?),
+    }
+}
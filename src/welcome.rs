@@ -0,0 +1,74 @@
+// Welcome and goodbye messages
+// Joining and leaving members are announced in the guild's welcome channel with a template
+// the guild can change with /welcome. Templates can use {user}, {mention}, {server} and
+// {count}, the member count after the join or leave.
+use poise::serenity_prelude as serenity;
+
+use crate::{
+    mentions::{self, Mentions},
+    Data, Error,
+};
+
+/// Placeholders templates can use, for the help text of /welcome
+pub const PLACEHOLDERS: &str = "`{user}`, `{mention}`, `{server}` and `{count}`";
+
+/// Fills in the placeholders of a template
+pub fn render(
+    template: &str,
+    ctx: &serenity::Context,
+    guild: serenity::GuildId,
+    user: &serenity::User,
+) -> String {
+    let (server, count) = ctx
+        .cache
+        .guild_field(guild, |g| (g.name.clone(), g.member_count))
+        .unwrap_or_default();
+
+    template
+        .replace("{user}", &user.name)
+        .replace("{mention}", &format!("<@{}>", user.id.0))
+        .replace("{server}", &server)
+        .replace("{count}", &count.to_string())
+}
+
+/// Welcomes a member that just joined
+pub async fn handle_join(
+    ctx: &serenity::Context,
+    data: &Data,
+    member: &serenity::Member,
+) -> Result<(), Error> {
+    let config = data.guild_configs.get(member.guild_id).await?;
+    let channel = match config.welcome_channel {
+        Some(channel) if !member.user.bot => channel,
+        _ => return Ok(()),
+    };
+
+    let content = render(config.welcome_message(), ctx, member.guild_id, &member.user);
+    // Templates are written by staff, but still shouldn't be able to ping everyone on every join
+    mentions::send_message(&ctx.http, channel, Mentions::Users, |m| m.content(content)).await?;
+
+    Ok(())
+}
+
+/// Says goodbye to a member that left, was kicked or was banned
+pub async fn handle_leave(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild: serenity::GuildId,
+    user: &serenity::User,
+) -> Result<(), Error> {
+    let config = data.guild_configs.get(guild).await?;
+    let channel = match config.welcome_channel {
+        Some(channel) if !user.bot => channel,
+        _ => return Ok(()),
+    };
+
+    // They can't see the channel anymore, a ping would only show up as a notification elsewhere
+    let content = render(config.goodbye_message(), ctx, guild, user);
+    mentions::send_message(&ctx.http, channel, Mentions::Nothing, |m| {
+        m.content(content)
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// Daily word
+// Every day has a word, shown scrambled by /word today. The first right guess of the day with
+// /word guess pays WORD_REWARD and counts towards the member's `word` streak. Days are those of
+// the member's timezone like for /daily, the word of a day is picked and scrambled with the day as
+// seed, so it's the same for everyone and doesn't need to be stored.
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Coins a right guess pays
+pub const WORD_REWARD: i64 = 50;
+
+const WORDS: &[&str] = &[
+    "anchor", "badger", "banana", "basket", "beacon", "blanket", "bridge", "button", "candle",
+    "canyon", "carpet", "castle", "cellar", "cherry", "circus", "cobweb", "compass", "cookie",
+    "copper", "dragon", "engine", "falcon", "forest", "garden", "ginger", "glacier", "goblin",
+    "guitar", "hammer", "harbor", "helmet", "island", "jacket", "jungle", "kettle", "ladder",
+    "lantern", "lizard", "magnet", "marble", "meadow", "mirror", "monkey", "needle", "orange",
+    "oyster", "parrot", "pepper", "pirate", "planet", "pocket", "puzzle", "rabbit", "rocket",
+    "saddle", "silver", "spider", "teapot", "thunder", "tunnel", "turtle", "velvet", "violin",
+    "walrus", "window", "wizard",
+];
+
+/// The word of a day and how it's shown scrambled
+pub fn word_of(day: i64) -> (&'static str, String) {
+    let mut rng = StdRng::seed_from_u64(day as u64);
+    let word = WORDS.choose(&mut rng).copied().unwrap_or("puzzle");
+
+    // Every word has two different letters, so a scramble that changed something turns up
+    let mut letters: Vec<char> = word.chars().collect();
+    loop {
+        letters.shuffle(&mut rng);
+        let scrambled: String = letters.iter().collect();
+        if scrambled != word {
+            return (word, scrambled.to_uppercase());
+        }
+    }
+}
+
+/// Whether `guess` is the word of `day`
+pub fn is_right(day: i64, guess: &str) -> bool {
+    guess.trim().eq_ignore_ascii_case(word_of(day).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrambles_use_the_letters_of_the_word() {
+        for day in 19_000..19_100 {
+            let (word, scrambled) = word_of(day);
+            let mut letters: Vec<_> = word.to_uppercase().chars().collect();
+            let mut shown: Vec<_> = scrambled.chars().collect();
+            letters.sort_unstable();
+            shown.sort_unstable();
+
+            assert_eq!(letters, shown);
+            assert_ne!(word.to_uppercase(), scrambled);
+            assert_eq!(word_of(day), (word, scrambled));
+        }
+        let (word, _) = word_of(7);
+        assert!(is_right(7, &format!(" {} ", word.to_uppercase())));
+    }
+}
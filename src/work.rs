@@ -0,0 +1,287 @@
+// Work, crime and robbing
+// Members earn coins with /work, which always pays, and /crime, which pays more but can end in
+// a fine. Both pay more the higher a member's level is. /rob takes a share of another member's
+// coins with the guild's `rob_chance`, a failed robbery costs a share of the robber's own coins
+// instead, paid to whoever they tried to rob. Members with fewer than MIN_ROB_BALANCE coins
+// can't rob or be robbed, and someone who was just robbed is safe for ROB_PROTECTION.
+// Cooldowns are stored, so restarting the bot doesn't reset them, and every payout goes through
+// the ledger keyed by the invocation.
+use std::{ops::RangeInclusive, time::Duration};
+
+use poise::serenity_prelude as serenity;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    duration,
+    economy::{self, Transfer},
+    levels::Level,
+    Context, Error,
+};
+
+const WORK_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+const CRIME_COOLDOWN: Duration = Duration::from_secs(2 * 60 * 60);
+const ROB_COOLDOWN: Duration = Duration::from_secs(4 * 60 * 60);
+// How long members who were robbed can't be robbed again
+const ROB_PROTECTION: Duration = Duration::from_secs(6 * 60 * 60);
+// Coins both the robber and the member they rob need
+const MIN_ROB_BALANCE: i64 = 50;
+// Chance in percent that a crime pays off
+const CRIME_CHANCE: u32 = 50;
+// Share of the robbed member's coins that's taken, in percent
+const ROB_SHARE: RangeInclusive<i64> = 10..=30;
+// Share of the robber's coins a failed robbery costs, in percent
+const ROB_FINE: i64 = 20;
+// Levels above this don't raise payouts any further
+const MAX_PAID_LEVEL: i64 = 40;
+
+// What members do for /work and what it pays at level 0
+const JOBS: &[(&str, i64, i64)] = &[
+    ("You delivered pizzas across town", 60, 120),
+    ("You walked the neighbours' dogs", 40, 90),
+    ("You fixed a printer nobody else could", 80, 140),
+    (
+        "You streamed for three viewers, one of them generous",
+        30,
+        160,
+    ),
+    ("You tutored a student for their exam", 70, 130),
+    ("You washed cars in the rain", 50, 100),
+];
+
+// Crimes for /crime, how they end if they work out and if they don't
+const CRIMES: &[(&str, &str)] = &[
+    (
+        "You sold fake concert tickets",
+        "The buyers recognised you at the concert",
+    ),
+    (
+        "You picked the pocket of a tourist",
+        "The tourist was an off-duty police officer",
+    ),
+    (
+        "You ran an underground card game",
+        "Someone at the table was counting cards better than you",
+    ),
+    (
+        "You smuggled snacks into the cinema and resold them",
+        "An usher caught you with a backpack full of candy",
+    ),
+];
+// Payout of a crime and its fine, at level 0
+const CRIME_PAYOUT: RangeInclusive<i64> = 150..=400;
+const CRIME_FINE: RangeInclusive<i64> = 50..=150;
+
+/// Scales a payout with the level of the member earning it, each level adds 5%
+pub fn scaled(amount: i64, level: i64) -> i64 {
+    let level = level.clamp(0, MAX_PAID_LEVEL);
+    amount + amount * level * 5 / 100
+}
+
+/// Coins taken from a balance when `percent` of it is stolen, at least 1 coin
+pub fn share(balance: i64, percent: i64) -> i64 {
+    (balance * percent / 100).max(1)
+}
+
+/// Works a random job for the author and returns the reply
+pub async fn work(ctx: Context<'_>) -> Result<String, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+
+    if let Some(ready_at) = db
+        .start_cooldown(guild, author, "work", WORK_COOLDOWN)
+        .await?
+    {
+        return Ok(format!(":x: You can work again <t:{}:R>.", ready_at));
+    }
+
+    let (job, earned) = {
+        let mut rng = rand::thread_rng();
+        let (job, min, max) = JOBS.choose(&mut rng).ok_or("No jobs")?;
+        (*job, rng.gen_range(*min..=*max))
+    };
+    let earned = scaled(earned, level(ctx, guild, author).await?);
+
+    let key = economy::invocation_key(ctx, "work");
+    db.transfer(guild, None, Some(author), earned, "work", &key)
+        .await?;
+
+    Ok(format!(
+        ":white_check_mark: {} and earned {}",
+        job,
+        economy::format(earned)
+    ))
+}
+
+/// Commits a random crime for the author and returns the reply
+pub async fn crime(ctx: Context<'_>) -> Result<String, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+
+    if let Some(ready_at) = db
+        .start_cooldown(guild, author, "crime", CRIME_COOLDOWN)
+        .await?
+    {
+        return Ok(format!(
+            ":x: Lay low for a while, you can try again <t:{}:R>.",
+            ready_at
+        ));
+    }
+
+    let ((success, failure), paid_off, payout, fine) = {
+        let mut rng = rand::thread_rng();
+        (
+            *CRIMES.choose(&mut rng).ok_or("No crimes")?,
+            rng.gen_range(0..100) < CRIME_CHANCE,
+            rng.gen_range(CRIME_PAYOUT),
+            rng.gen_range(CRIME_FINE),
+        )
+    };
+    let level = level(ctx, guild, author).await?;
+    let key = economy::invocation_key(ctx, "crime");
+
+    if paid_off {
+        let payout = scaled(payout, level);
+        db.transfer(guild, None, Some(author), payout, "crime", &key)
+            .await?;
+        return Ok(format!(
+            ":white_check_mark: {} and made {}",
+            success,
+            economy::format(payout)
+        ));
+    }
+
+    // Fines never take more than a member has
+    let fine = scaled(fine, level).min(db.balance(guild, author).await?);
+    if fine > 0 {
+        db.transfer(guild, Some(author), None, fine, "crime", &key)
+            .await?;
+    }
+    Ok(format!(
+        ":x: {} and you were fined {}.",
+        failure,
+        economy::format(fine)
+    ))
+}
+
+/// Tries to rob `target` for the author and returns the reply
+pub async fn rob(ctx: Context<'_>, target: &serenity::User, chance: u32) -> Result<String, Error> {
+    let guild = ctx.guild_id().ok_or("Must be used in a guild")?;
+    let author = ctx.author().id;
+    let db = &ctx.data().db;
+
+    if chance == 0 {
+        return Ok(":x: Robbing is turned off on this server.".to_string());
+    }
+    if target.id == author || target.bot {
+        return Ok(":x: You can only rob other members.".to_string());
+    }
+    if db.balance(guild, author).await? < MIN_ROB_BALANCE {
+        return Ok(format!(
+            ":x: You need at least {} to rob someone, in case you get caught.",
+            economy::format(MIN_ROB_BALANCE)
+        ));
+    }
+    if db.balance(guild, target.id).await? < MIN_ROB_BALANCE {
+        return Ok(format!(":x: {} isn't worth robbing.", target.name));
+    }
+    if let Some(safe_until) = db.cooldown(guild, target.id, "robbed").await? {
+        return Ok(format!(
+            ":x: {} was robbed recently and is on guard until <t:{}:R>.",
+            target.name, safe_until
+        ));
+    }
+    if let Some(ready_at) = db
+        .start_cooldown(guild, author, "rob", ROB_COOLDOWN)
+        .await?
+    {
+        return Ok(format!(":x: You can rob again <t:{}:R>.", ready_at));
+    }
+
+    let key = economy::invocation_key(ctx, "rob");
+    let succeeded = rand::thread_rng().gen_range(0..100) < chance;
+    if !succeeded {
+        let fine = share(db.balance(guild, author).await?, ROB_FINE);
+        db.transfer(guild, Some(author), Some(target.id), fine, "rob", &key)
+            .await?;
+        return Ok(format!(
+            ":x: {} caught you, you paid them {} to keep quiet.",
+            target.name,
+            economy::format(fine)
+        ));
+    }
+
+    // Taken before the coins, so two robbers at once can't both get away with it
+    if let Some(safe_until) = db
+        .start_cooldown(guild, target.id, "robbed", ROB_PROTECTION)
+        .await?
+    {
+        return Ok(format!(
+            ":x: {} was robbed recently and is on guard until <t:{}:R>.",
+            target.name, safe_until
+        ));
+    }
+    let percent = rand::thread_rng().gen_range(ROB_SHARE);
+    let stolen = share(db.balance(guild, target.id).await?, percent);
+    let transfer = db
+        .transfer(guild, Some(target.id), Some(author), stolen, "rob", &key)
+        .await?;
+
+    Ok(match transfer {
+        Transfer::Insufficient => format!(":x: {} spent their coins just in time.", target.name),
+        _ => format!(
+            ":white_check_mark: You got away with {} from {}, they're safe from robbers for {}",
+            economy::format(stolen),
+            target.name,
+            duration::format(ROB_PROTECTION)
+        ),
+    })
+}
+
+async fn level(
+    ctx: Context<'_>,
+    guild: serenity::GuildId,
+    user: serenity::UserId,
+) -> Result<i64, Error> {
+    Ok(Level::from_xp(ctx.data().db.xp(guild, user).await?).level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    #[test]
+    fn payouts_grow_with_level_up_to_a_cap() {
+        assert_eq!(scaled(100, 0), 100);
+        assert_eq!(scaled(100, 10), 150);
+        assert_eq!(scaled(100, MAX_PAID_LEVEL), 300);
+        assert_eq!(scaled(100, 500), 300);
+        assert_eq!(share(1000, 20), 200);
+        assert_eq!(share(3, 10), 1);
+    }
+
+    #[tokio::test]
+    async fn cooldowns_only_start_once_they_are_over() {
+        let db = Db::memory().await;
+        let (guild, user) = (serenity::GuildId(1), serenity::UserId(2));
+
+        let first = db.start_cooldown(guild, user, "work", WORK_COOLDOWN).await;
+        assert_eq!(first.unwrap(), None);
+        let ready_at = db.cooldown(guild, user, "work").await.unwrap().unwrap();
+        let again = db.start_cooldown(guild, user, "work", WORK_COOLDOWN).await;
+        assert_eq!(again.unwrap(), Some(ready_at));
+
+        // Other actions have cooldowns of their own, and an ended one starts again
+        let crime = db
+            .start_cooldown(guild, user, "crime", Duration::ZERO)
+            .await;
+        assert_eq!(crime.unwrap(), None);
+        assert_eq!(db.cooldown(guild, user, "crime").await.unwrap(), None);
+        let crime = db
+            .start_cooldown(guild, user, "crime", CRIME_COOLDOWN)
+            .await;
+        assert_eq!(crime.unwrap(), None);
+    }
+}
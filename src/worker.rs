@@ -0,0 +1,404 @@
+// Companion worker
+// Hashing images, building export zips and LLM completions are the heaviest work the bot does.
+// Built with the `distributed` feature and with WORKER_ADDR set, the bot sends them to a worker
+// process listening there instead, started with `cargo run --features distributed --bin worker`
+// and the same environment, so the gateway process only waits for the answers. Every job is a
+// line with WORKER_TOKEN and a line of JSON over its own TCP connection, answered with another
+// line. Jobs spend the bot's LLM key, so the worker refuses to listen on anything but a loopback
+// address without WORKER_TOKEN, and only runs MAX_JOBS at once. Without a worker, or when it
+// can't be reached, jobs run in process.
+use std::{env, sync::Arc, time::Duration};
+
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedReadHalf, TcpListener, TcpStream},
+    sync::Semaphore,
+};
+
+use crate::{imagehash, llm::Llm, zip, Error};
+
+/// Where the worker listens without WORKER_ADDR
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7070";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+// Longer than LLM requests take
+const JOB_TIMEOUT: Duration = Duration::from_secs(90);
+// Emoji packs are the biggest jobs, and stay below 25 MiB before base64
+const MAX_LINE: u64 = 64 * 1024 * 1024;
+// Read before the job, so connections without the token can't make the worker buffer much
+const MAX_TOKEN_LINE: u64 = 1024;
+// Jobs answered at once, each can hold a MAX_LINE job and its answer in memory
+const MAX_JOBS: usize = 4;
+
+/// Work that can run on the worker
+#[derive(Debug, PartialEq)]
+pub enum Job {
+    Phash(Vec<u8>),
+    Zip(Vec<zip::Entry>),
+    Complete {
+        prompt: String,
+        text: String,
+        max_tokens: u32,
+    },
+}
+
+/// What a job produced
+#[derive(Debug, PartialEq)]
+pub enum Output {
+    Hash(u64),
+    Archive(Vec<u8>),
+    Text(String),
+}
+
+impl Job {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Job::Phash(image) => json!({ "phash": base64::encode(image) }),
+            Job::Zip(entries) => json!({
+                "zip": entries
+                    .iter()
+                    .map(|e| json!({ "name": e.name, "data": base64::encode(&e.data) }))
+                    .collect::<Vec<_>>()
+            }),
+            Job::Complete {
+                prompt,
+                text,
+                max_tokens,
+            } => {
+                json!({ "complete": { "prompt": prompt, "text": text, "max_tokens": max_tokens } })
+            }
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Option<Job> {
+        if let Some(image) = value["phash"].as_str() {
+            return Some(Job::Phash(base64::decode(image).ok()?));
+        }
+        if let Some(entries) = value["zip"].as_array() {
+            let entries = entries
+                .iter()
+                .map(|e| {
+                    Some(zip::Entry {
+                        name: e["name"].as_str()?.to_string(),
+                        data: base64::decode(e["data"].as_str()?).ok()?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            return Some(Job::Zip(entries));
+        }
+        let complete = &value["complete"];
+        Some(Job::Complete {
+            prompt: complete["prompt"].as_str()?.to_string(),
+            text: complete["text"].as_str()?.to_string(),
+            max_tokens: complete["max_tokens"].as_u64()?.try_into().ok()?,
+        })
+    }
+
+    /// Runs the job in this process, off the event loop where it's CPU heavy
+    pub async fn run(self, llm: Option<&Llm>) -> Result<Output, Error> {
+        match self {
+            Job::Phash(image) => Ok(Output::Hash(
+                tokio::task::spawn_blocking(move || imagehash::phash(&image)).await??,
+            )),
+            Job::Zip(entries) => Ok(Output::Archive(
+                tokio::task::spawn_blocking(move || zip::write(&entries)).await??,
+            )),
+            Job::Complete {
+                prompt,
+                text,
+                max_tokens,
+            } => {
+                let llm = llm.ok_or("No LLM is configured")?;
+                Ok(Output::Text(
+                    llm.complete(&prompt, &text, max_tokens).await?,
+                ))
+            }
+        }
+    }
+}
+
+impl Output {
+    pub fn to_json(&self) -> Value {
+        match self {
+            // As a string, JSON numbers lose precision past 2^53
+            Output::Hash(hash) => json!({ "hash": hash.to_string() }),
+            Output::Archive(archive) => json!({ "archive": base64::encode(archive) }),
+            Output::Text(text) => json!({ "text": text }),
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Option<Output> {
+        if let Some(hash) = value["hash"].as_str() {
+            return Some(Output::Hash(hash.parse().ok()?));
+        }
+        if let Some(archive) = value["archive"].as_str() {
+            return Some(Output::Archive(base64::decode(archive).ok()?));
+        }
+        Some(Output::Text(value["text"].as_str()?.to_string()))
+    }
+}
+
+/// WORKER_TOKEN if it's set to something, the bot and the worker need the same one
+pub fn token_from_env() -> Option<String> {
+    env::var("WORKER_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// The worker process jobs are sent to
+pub struct Worker {
+    addr: String,
+    token: Option<String>,
+}
+
+impl Worker {
+    /// Sends jobs to WORKER_ADDR if it's set and the bot was built with `distributed`
+    pub fn from_env() -> Option<Arc<Self>> {
+        if !cfg!(feature = "distributed") {
+            return None;
+        }
+
+        Some(Arc::new(Self {
+            addr: env::var("WORKER_ADDR").ok()?,
+            token: token_from_env(),
+        }))
+    }
+
+    /// Runs the job on the worker, None if the worker can't be reached
+    pub async fn send(&self, job: &Job) -> Option<Result<Output, Error>> {
+        let stream =
+            match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.addr)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    tracing::warn!(addr = %self.addr, "Error connecting to the worker: {}", e);
+                    return None;
+                }
+                Err(_) => {
+                    tracing::warn!(addr = %self.addr, "Timed out connecting to the worker");
+                    return None;
+                }
+            };
+
+        let token = self.token.as_deref().unwrap_or_default();
+        let response = match tokio::time::timeout(
+            JOB_TIMEOUT,
+            exchange(stream, token, &job.to_json()),
+        )
+        .await
+        {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return Some(Err(e)),
+            Err(_) => return Some(Err("The worker took too long".into())),
+        };
+        Some(match (response.get("ok"), response["error"].as_str()) {
+            (Some(output), _) => {
+                Output::from_json(output).ok_or_else(|| "The worker sent an invalid answer".into())
+            }
+            (None, Some(error)) => Err(error.into()),
+            (None, None) => Err("The worker sent an invalid answer".into()),
+        })
+    }
+}
+
+/// Runs the job on the worker if there is one, and in process otherwise or when the worker
+/// can't be reached
+pub async fn run(worker: Option<&Worker>, job: Job, llm: Option<&Llm>) -> Result<Output, Error> {
+    if let Some(worker) = worker {
+        if let Some(result) = worker.send(&job).await {
+            return result;
+        }
+    }
+
+    job.run(llm).await
+}
+
+/// pHash of an image, see `imagehash::phash`
+pub async fn phash(worker: Option<&Worker>, image: Vec<u8>) -> Result<u64, Error> {
+    match run(worker, Job::Phash(image), None).await? {
+        Output::Hash(hash) => Ok(hash),
+        _ => Err("The worker sent an invalid answer".into()),
+    }
+}
+
+/// Uncompressed zip of the entries, see `zip::write`
+pub async fn zip(worker: Option<&Worker>, entries: Vec<zip::Entry>) -> Result<Vec<u8>, Error> {
+    match run(worker, Job::Zip(entries), None).await? {
+        Output::Archive(archive) => Ok(archive),
+        _ => Err("The worker sent an invalid answer".into()),
+    }
+}
+
+// Sends the token and a line of JSON and reads the line answering it
+async fn exchange(stream: TcpStream, token: &str, message: &Value) -> Result<Value, Error> {
+    let (read, mut write) = stream.into_split();
+    write
+        .write_all(format!("{}\n{}\n", token, message).as_bytes())
+        .await?;
+
+    let mut line = String::new();
+    BufReader::new(read.take(MAX_LINE))
+        .read_line(&mut line)
+        .await?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Answers the jobs sent to `listener` with the token, for the worker process. Errors if
+/// there's no token and the listener can be reached from other machines
+pub async fn serve(
+    listener: TcpListener,
+    llm: Option<Arc<Llm>>,
+    token: Option<String>,
+) -> Result<(), Error> {
+    if token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+        return Err(
+            "WORKER_TOKEN has to be set for a WORKER_ADDR that isn't a loopback address".into(),
+        );
+    }
+
+    let token = Arc::new(token.unwrap_or_default());
+    let jobs = Arc::new(Semaphore::new(MAX_JOBS));
+    loop {
+        // Waits here when busy, further connections queue up in the listener's backlog
+        let permit = Arc::clone(&jobs).acquire_owned().await?;
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("Error accepting a job: {}", e);
+                continue;
+            }
+        };
+
+        let llm = llm.clone();
+        let token = Arc::clone(&token);
+        tokio::spawn(async move {
+            match tokio::time::timeout(JOB_TIMEOUT, answer(stream, &token, llm.as_deref())).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("Error answering a job: {}", e),
+                Err(_) => tracing::warn!("Timed out answering a job"),
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn answer(stream: TcpStream, token: &str, llm: Option<&Llm>) -> Result<(), Error> {
+    let (read, mut write) = stream.into_split();
+    let mut read = BufReader::new(read);
+
+    let response = match read_job(&mut read, token).await? {
+        Some(Some(job)) => match job.run(llm).await {
+            Ok(output) => json!({ "ok": output.to_json() }),
+            Err(e) => json!({ "error": e.to_string() }),
+        },
+        Some(None) => json!({ "error": "Invalid job" }),
+        None => json!({ "error": "Invalid token" }),
+    };
+    write
+        .write_all(format!("{}\n", response).as_bytes())
+        .await?;
+    Ok(())
+}
+
+// None if the token is wrong, in which case the job isn't read at all
+async fn read_job(
+    read: &mut BufReader<OwnedReadHalf>,
+    token: &str,
+) -> Result<Option<Option<Job>>, Error> {
+    let mut line = String::new();
+    (&mut *read)
+        .take(MAX_TOKEN_LINE)
+        .read_line(&mut line)
+        .await?;
+    // Compared in constant time so the token can't be guessed from response times
+    if !bool::from(line.trim_end().as_bytes().ct_eq(token.as_bytes())) {
+        return Ok(None);
+    }
+
+    line.clear();
+    read.take(MAX_LINE).read_line(&mut line).await?;
+    Ok(Some(Job::from_json(&serde_json::from_str(&line)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<zip::Entry> {
+        vec![zip::Entry {
+            name: "a.png".to_string(),
+            data: vec![0, 1, 2, 255],
+        }]
+    }
+
+    #[test]
+    fn jobs_and_outputs_survive_json() {
+        let jobs = [
+            Job::Phash(vec![1, 2, 3]),
+            Job::Zip(entries()),
+            Job::Complete {
+                prompt: "Summarize".to_string(),
+                text: "Hello".to_string(),
+                max_tokens: 100,
+            },
+        ];
+        for job in jobs {
+            assert_eq!(Job::from_json(&job.to_json()), Some(job));
+        }
+        for output in [
+            Output::Hash(u64::MAX),
+            Output::Archive(vec![0, 255]),
+            Output::Text("Hi".to_string()),
+        ] {
+            assert_eq!(Output::from_json(&output.to_json()), Some(output));
+        }
+        assert_eq!(Job::from_json(&json!({ "render": "x" })), None);
+    }
+
+    #[tokio::test]
+    async fn jobs_run_on_the_worker_or_in_process() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let worker = Worker {
+            addr: listener.local_addr().unwrap().to_string(),
+            token: None,
+        };
+        tokio::spawn(serve(listener, None, None));
+
+        let archive = zip::write(&entries()).unwrap();
+        assert_eq!(zip(Some(&worker), entries()).await.unwrap(), archive);
+        // Errors of the job come back from the worker
+        let error = phash(Some(&worker), vec![1, 2, 3]).await.unwrap_err();
+        let error_in_process = phash(None, vec![1, 2, 3]).await.unwrap_err();
+        assert_eq!(error.to_string(), error_in_process.to_string());
+
+        // Without a worker listening, jobs run in process
+        let gone = Worker {
+            addr: "127.0.0.1:1".to_string(),
+            token: None,
+        };
+        assert_eq!(zip(Some(&gone), entries()).await.unwrap(), archive);
+    }
+
+    #[tokio::test]
+    async fn jobs_need_the_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(serve(listener, None, Some("token".to_string())));
+
+        let worker = |token: Option<&str>| Worker {
+            addr: addr.clone(),
+            token: token.map(str::to_string),
+        };
+        assert!(zip(Some(&worker(Some("token"))), entries()).await.is_ok());
+        for token in [None, Some("toke"), Some("tokens")] {
+            let error = worker(token).send(&Job::Zip(entries())).await;
+            assert_eq!(error.unwrap().unwrap_err().to_string(), "Invalid token");
+        }
+    }
+
+    #[tokio::test]
+    async fn public_addresses_need_a_token() {
+        let listener = TcpListener::bind("0.0.0.0:0").await.unwrap();
+        assert!(serve(listener, None, None).await.is_err());
+    }
+}
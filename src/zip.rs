@@ -0,0 +1,113 @@
+// Zip archives for emoji packs
+// Reading and writing is left to the zip crate. Exports are stored uncompressed, since images
+// are compressed already, and imports read files that are stored or deflated, which covers what
+// common zip tools create. Archives come from uploads, so extracted files are cut off at a limit
+// instead of trusting the sizes the archive claims.
+use std::io::{Cursor, Read, Write};
+
+use ::zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::Error;
+
+/// A file in an archive
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Builds an uncompressed archive
+pub fn write(entries: &[Entry]) -> Result<Vec<u8>, Error> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    for entry in entries {
+        zip.start_file(entry.name.as_str(), options)?;
+        zip.write_all(&entry.data)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// A file of an archive that hasn't been extracted yet
+pub struct File {
+    pub name: String,
+    /// Size once extracted, as claimed by the archive
+    pub size: u64,
+    index: usize,
+}
+
+/// An archive being read
+pub struct Archive<'a> {
+    zip: ZipArchive<Cursor<&'a [u8]>>,
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            zip: ZipArchive::new(Cursor::new(data))?,
+        })
+    }
+
+    /// Lists the files of the archive, folders are left out
+    pub fn files(&mut self) -> Result<Vec<File>, Error> {
+        let mut files = Vec::new();
+        for index in 0..self.zip.len() {
+            let file = self.zip.by_index_raw(index)?;
+            if file.is_dir() {
+                continue;
+            }
+
+            files.push(File {
+                name: file.name().to_string(),
+                size: file.size(),
+                index,
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Extracts a file, failing if it turns out bigger than `max_size`
+    pub fn extract(&mut self, file: &File, max_size: usize) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.zip
+            .by_index(file.index)?
+            .take(max_size as u64 + 1)
+            .read_to_end(&mut data)?;
+        if data.len() > max_size {
+            return Err("File is bigger than it claims".into());
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_read_back_within_the_size_limit() {
+        let entries = [
+            Entry {
+                name: "a.png".to_string(),
+                data: vec![1, 2, 3],
+            },
+            Entry {
+                name: "folder/b.gif".to_string(),
+                data: vec![4; 100],
+            },
+        ];
+        let data = write(&entries).unwrap();
+
+        let mut archive = Archive::new(&data).unwrap();
+        let files = archive.files().unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["a.png", "folder/b.gif"]);
+        assert_eq!(archive.extract(&files[0], 3).unwrap(), [1, 2, 3]);
+        assert!(archive.extract(&files[1], 99).is_err());
+
+        assert!(Archive::new(b"not a zip").is_err());
+    }
+}